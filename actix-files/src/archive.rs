@@ -0,0 +1,805 @@
+use std::{
+    fmt,
+    io::{self, Write as _},
+    pin::Pin,
+    task::{Context, Poll},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use actix_web::{
+    body::BoxBody, http::header::ContentDisposition, HttpRequest, HttpResponse, Responder,
+};
+use bytes::{BufMut as _, Bytes, BytesMut};
+use flate2::{write::DeflateEncoder, Compression};
+use futures_core::{
+    ready,
+    stream::{LocalBoxStream, Stream},
+};
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// Size of the buffer entries are read into.
+const READ_BUF_SIZE: usize = 64 * 1024;
+
+const TAR_BLOCK: usize = 512;
+
+/// Zero bytes used for tar padding and the end-of-archive marker.
+static ZEROS: [u8; 2 * TAR_BLOCK] = [0; 2 * TAR_BLOCK];
+
+const ZIP_LOCAL_HEADER: u32 = 0x0403_4b50;
+const ZIP_DATA_DESCRIPTOR: u32 = 0x0807_4b50;
+const ZIP_CENTRAL_HEADER: u32 = 0x0201_4b50;
+const ZIP_END_OF_CENTRAL_DIR: u32 = 0x0605_4b50;
+
+/// Zip version 2.0, which supports deflate and data descriptors.
+const ZIP_VERSION: u16 = 20;
+
+/// Sizes and CRC are written in a data descriptor after the data; names are UTF-8.
+const ZIP_FLAGS: u16 = 0x0008 | 0x0800;
+
+/// Named entry of an [`Archive`], read from an [`AsyncRead`].
+pub struct ArchiveEntry {
+    name: String,
+    reader: Pin<Box<dyn AsyncRead>>,
+    size: Option<u64>,
+    modified: Option<SystemTime>,
+}
+
+impl ArchiveEntry {
+    /// Constructs an entry named `name` whose content is read from `reader`.
+    ///
+    /// The name is a relative path within the archive, using `/` as separator.
+    pub fn new(name: impl Into<String>, reader: impl AsyncRead + 'static) -> Self {
+        Self {
+            name: name.into(),
+            reader: Box::pin(reader),
+            size: None,
+            modified: None,
+        }
+    }
+
+    /// Sets the length of the entry's content in bytes.
+    ///
+    /// Tar headers precede the content of their entry, so the content of tar entries without a
+    /// size is buffered in memory until it has been read completely. The archive fails if the
+    /// reader yields a different number of bytes.
+    pub fn size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Sets the modification time recorded for the entry.
+    ///
+    /// By default, the time the entry is written is recorded.
+    pub fn modified(mut self, modified: SystemTime) -> Self {
+        self.modified = Some(modified);
+        self
+    }
+}
+
+impl fmt::Debug for ArchiveEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArchiveEntry")
+            .field("name", &self.name)
+            .field("size", &self.size)
+            .field("modified", &self.modified)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Zip,
+    ZipDeflate,
+    Tar,
+}
+
+/// Zip or tar archive built on the fly from a stream of entries.
+///
+/// Entries are read one after another and written to the response as they are read, so the
+/// archive is never held in memory or on disk as a whole. The response has no known length and is
+/// sent with chunked transfer encoding over HTTP/1.1.
+///
+/// If the client disconnects, the response body is dropped along with the entry stream and the
+/// reader of the current entry, closing any files or connections they hold. If an entry can not be
+/// read, the response is aborted and the client receives a truncated archive.
+///
+/// Zip archives do not use Zip64 extensions, so entries and archives are limited to 4 GiB and
+/// archives to 65,535 entries.
+///
+/// # Examples
+/// ```
+/// use actix_files::{Archive, ArchiveEntry};
+/// use actix_web::get;
+///
+/// #[get("/documents.zip")]
+/// async fn documents() -> Archive {
+///     let entries = [
+///         ArchiveEntry::new("notes/visit.txt", &b"follow-up in 2 weeks"[..]),
+///         ArchiveEntry::new("summary.txt", &b"all clear"[..]),
+///     ];
+///
+///     Archive::zip_deflate(futures_util::stream::iter(entries.map(Ok)))
+///         .filename("documents.zip")
+/// }
+/// ```
+pub struct Archive {
+    format: Format,
+    entries: LocalBoxStream<'static, io::Result<ArchiveEntry>>,
+    filename: Option<String>,
+}
+
+impl Archive {
+    /// Constructs a zip archive whose entries are stored without compression.
+    pub fn zip<S>(entries: S) -> Self
+    where
+        S: Stream<Item = io::Result<ArchiveEntry>> + 'static,
+    {
+        Self::new(Format::Zip, entries)
+    }
+
+    /// Constructs a zip archive whose entries are compressed with deflate.
+    pub fn zip_deflate<S>(entries: S) -> Self
+    where
+        S: Stream<Item = io::Result<ArchiveEntry>> + 'static,
+    {
+        Self::new(Format::ZipDeflate, entries)
+    }
+
+    /// Constructs a tar archive.
+    pub fn tar<S>(entries: S) -> Self
+    where
+        S: Stream<Item = io::Result<ArchiveEntry>> + 'static,
+    {
+        Self::new(Format::Tar, entries)
+    }
+
+    fn new<S>(format: Format, entries: S) -> Self
+    where
+        S: Stream<Item = io::Result<ArchiveEntry>> + 'static,
+    {
+        Self {
+            format,
+            entries: Box::pin(entries),
+            filename: None,
+        }
+    }
+
+    /// Sets the file name suggested to the client in an `attachment` `Content-Disposition` header.
+    pub fn filename(mut self, filename: impl Into<String>) -> Self {
+        self.filename = Some(filename.into());
+        self
+    }
+
+    /// Converts the archive into a stream of its bytes.
+    pub fn into_stream(self) -> impl Stream<Item = io::Result<Bytes>> + 'static {
+        ArchiveStream {
+            format: self.format,
+            entries: self.entries,
+            state: State::Entries,
+            offset: 0,
+            central_directory: BytesMut::new(),
+            zip_entries: 0,
+        }
+    }
+}
+
+impl fmt::Debug for Archive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Archive")
+            .field("format", &self.format)
+            .field("filename", &self.filename)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Responder for Archive {
+    type Body = BoxBody;
+
+    fn respond_to(mut self, _: &HttpRequest) -> HttpResponse<Self::Body> {
+        let mut res = HttpResponse::Ok();
+
+        res.content_type(match self.format {
+            Format::Zip | Format::ZipDeflate => "application/zip",
+            Format::Tar => "application/x-tar",
+        });
+
+        if let Some(filename) = self.filename.take() {
+            res.insert_header(ContentDisposition::attachment(filename));
+        }
+
+        res.streaming(self.into_stream())
+    }
+}
+
+enum State {
+    Entries,
+    Entry(Box<EntryWriter>),
+    Done,
+}
+
+struct ArchiveStream {
+    format: Format,
+    entries: LocalBoxStream<'static, io::Result<ArchiveEntry>>,
+    state: State,
+
+    /// Number of bytes of the archive produced so far.
+    offset: u64,
+
+    /// Central directory records of the zip entries written so far.
+    central_directory: BytesMut,
+    zip_entries: u64,
+}
+
+struct EntryWriter {
+    name: String,
+    reader: Pin<Box<dyn AsyncRead>>,
+    buf: Vec<u8>,
+    modified: SystemTime,
+
+    /// Offset of the entry's zip local header.
+    header_offset: u64,
+    crc: crc32fast::Hasher,
+    encoder: Option<DeflateEncoder<Vec<u8>>>,
+
+    /// Number of content bytes read so far.
+    read: u64,
+
+    /// Number of bytes of (possibly compressed) content written so far.
+    written: u64,
+
+    /// Size of a tar entry given up front.
+    size: Option<u64>,
+
+    /// Content of a tar entry without a given size.
+    buffered: BytesMut,
+}
+
+impl Stream for ArchiveStream {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        let res = loop {
+            let chunk = match this.state {
+                State::Entries => match ready!(this.entries.as_mut().poll_next(cx)) {
+                    Some(Ok(entry)) => this.start_entry(entry),
+                    Some(Err(err)) => Err(err),
+                    None => this.finish(),
+                },
+
+                State::Entry(ref mut entry) => match ready!(entry.poll_content(cx, this.format)) {
+                    Ok(Some(chunk)) => Ok(chunk),
+                    Ok(None) => this.finish_entry(),
+                    Err(err) => Err(err),
+                },
+
+                State::Done => return Poll::Ready(None),
+            };
+
+            match chunk {
+                Ok(chunk) if chunk.is_empty() => continue,
+                res => break res,
+            }
+        };
+
+        match res {
+            Ok(chunk) => {
+                this.offset += chunk.len() as u64;
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Err(err) => {
+                log::debug!("aborting archive response: {err}");
+                this.state = State::Done;
+                Poll::Ready(Some(Err(err)))
+            }
+        }
+    }
+}
+
+impl ArchiveStream {
+    /// Starts writing `entry`, returning its header.
+    fn start_entry(&mut self, entry: ArchiveEntry) -> io::Result<Bytes> {
+        let name = entry_name(&entry.name)?;
+        let modified = entry.modified.unwrap_or_else(SystemTime::now);
+
+        let header = match self.format {
+            Format::Zip | Format::ZipDeflate => {
+                let name_len = u16::try_from(name.len())
+                    .map_err(|_| invalid_input("archive entry name is too long"))?;
+                let (time, date) = dos_date_time(modified);
+
+                let mut header = BytesMut::with_capacity(30 + name.len());
+                header.put_u32_le(ZIP_LOCAL_HEADER);
+                header.put_u16_le(ZIP_VERSION);
+                header.put_u16_le(ZIP_FLAGS);
+                header.put_u16_le(self.zip_method());
+                header.put_u16_le(time);
+                header.put_u16_le(date);
+                header.put_u32_le(0); // crc
+                header.put_u32_le(0); // compressed size
+                header.put_u32_le(0); // uncompressed size
+                header.put_u16_le(name_len);
+                header.put_u16_le(0); // extra field length
+                header.put_slice(name.as_bytes());
+                header.freeze()
+            }
+
+            Format::Tar => match entry.size {
+                Some(size) => tar_header(&name, size, modified),
+                None => Bytes::new(),
+            },
+        };
+
+        self.state = State::Entry(Box::new(EntryWriter {
+            name,
+            reader: entry.reader,
+            buf: Vec::new(),
+            modified,
+            header_offset: self.offset,
+            crc: crc32fast::Hasher::new(),
+            encoder: (self.format == Format::ZipDeflate)
+                .then(|| DeflateEncoder::new(Vec::new(), Compression::default())),
+            read: 0,
+            written: 0,
+            size: entry.size,
+            buffered: BytesMut::new(),
+        }));
+
+        Ok(header)
+    }
+
+    /// Finishes writing the current entry, returning what follows its content.
+    fn finish_entry(&mut self) -> io::Result<Bytes> {
+        let State::Entry(entry) = std::mem::replace(&mut self.state, State::Entries) else {
+            unreachable!("no archive entry is being written");
+        };
+        let mut entry = *entry;
+
+        match self.format {
+            Format::Zip | Format::ZipDeflate => {
+                let mut out = BytesMut::new();
+
+                if let Some(encoder) = entry.encoder.take() {
+                    let rest = encoder.finish()?;
+                    entry.written += rest.len() as u64;
+                    out.put_slice(&rest);
+                }
+
+                let too_large = || invalid_input("archive is too large for the zip format");
+                let crc = entry.crc.finalize();
+                let compressed = u32::try_from(entry.written).map_err(|_| too_large())?;
+                let uncompressed = u32::try_from(entry.read).map_err(|_| too_large())?;
+                let offset = u32::try_from(entry.header_offset).map_err(|_| too_large())?;
+
+                out.put_u32_le(ZIP_DATA_DESCRIPTOR);
+                out.put_u32_le(crc);
+                out.put_u32_le(compressed);
+                out.put_u32_le(uncompressed);
+
+                let (time, date) = dos_date_time(entry.modified);
+                let method = self.zip_method();
+                let cd = &mut self.central_directory;
+                cd.put_u32_le(ZIP_CENTRAL_HEADER);
+                cd.put_u16_le((3 << 8) | ZIP_VERSION); // made by Unix, so that modes are kept
+                cd.put_u16_le(ZIP_VERSION);
+                cd.put_u16_le(ZIP_FLAGS);
+                cd.put_u16_le(method);
+                cd.put_u16_le(time);
+                cd.put_u16_le(date);
+                cd.put_u32_le(crc);
+                cd.put_u32_le(compressed);
+                cd.put_u32_le(uncompressed);
+                cd.put_u16_le(entry.name.len() as u16);
+                cd.put_u16_le(0); // extra field length
+                cd.put_u16_le(0); // comment length
+                cd.put_u16_le(0); // disk number
+                cd.put_u16_le(0); // internal attributes
+                cd.put_u32_le(0o100644 << 16); // external attributes: regular file, rw-r--r--
+                cd.put_u32_le(offset);
+                cd.put_slice(entry.name.as_bytes());
+                self.zip_entries += 1;
+
+                Ok(out.freeze())
+            }
+
+            Format::Tar => match entry.size {
+                Some(size) if size != entry.read => Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("archive entry `{}` is shorter than its size", entry.name),
+                )),
+
+                Some(size) => Ok(Bytes::from_static(&ZEROS[..tar_padding(size)])),
+
+                None => {
+                    let size = entry.buffered.len() as u64;
+                    let mut out =
+                        BytesMut::from(&tar_header(&entry.name, size, entry.modified)[..]);
+                    out.put_slice(&entry.buffered);
+                    out.put_slice(&ZEROS[..tar_padding(size)]);
+                    Ok(out.freeze())
+                }
+            },
+        }
+    }
+
+    /// Finishes the archive, returning its trailer.
+    fn finish(&mut self) -> io::Result<Bytes> {
+        self.state = State::Done;
+
+        match self.format {
+            Format::Zip | Format::ZipDeflate => {
+                let too_large = || invalid_input("archive is too large for the zip format");
+                let entries = u16::try_from(self.zip_entries).map_err(|_| too_large())?;
+                let cd_size =
+                    u32::try_from(self.central_directory.len()).map_err(|_| too_large())?;
+                let cd_offset = u32::try_from(self.offset).map_err(|_| too_large())?;
+
+                let mut out = std::mem::take(&mut self.central_directory);
+                out.put_u32_le(ZIP_END_OF_CENTRAL_DIR);
+                out.put_u16_le(0); // disk number
+                out.put_u16_le(0); // disk with central directory
+                out.put_u16_le(entries);
+                out.put_u16_le(entries);
+                out.put_u32_le(cd_size);
+                out.put_u32_le(cd_offset);
+                out.put_u16_le(0); // comment length
+                Ok(out.freeze())
+            }
+
+            // end of archive is marked by two zero blocks
+            Format::Tar => Ok(Bytes::from_static(&ZEROS)),
+        }
+    }
+
+    fn zip_method(&self) -> u16 {
+        match self.format {
+            Format::ZipDeflate => 8,
+            _ => 0,
+        }
+    }
+}
+
+impl Drop for ArchiveStream {
+    fn drop(&mut self) {
+        if !matches!(self.state, State::Done) {
+            log::debug!("archive response dropped before it was complete");
+        }
+    }
+}
+
+impl EntryWriter {
+    /// Reads the next chunk of content, returning what is written for it, or `None` at its end.
+    fn poll_content(
+        &mut self,
+        cx: &mut Context<'_>,
+        format: Format,
+    ) -> Poll<io::Result<Option<Bytes>>> {
+        if self.buf.is_empty() {
+            self.buf.resize(READ_BUF_SIZE, 0);
+        }
+
+        let mut read_buf = ReadBuf::new(&mut self.buf);
+        ready!(self.reader.as_mut().poll_read(cx, &mut read_buf))?;
+
+        if read_buf.filled().is_empty() {
+            return Poll::Ready(Ok(None));
+        }
+
+        let chunk = Bytes::copy_from_slice(read_buf.filled());
+        self.read += chunk.len() as u64;
+
+        let out = match format {
+            Format::Zip => {
+                self.crc.update(&chunk);
+                chunk
+            }
+
+            Format::ZipDeflate => {
+                self.crc.update(&chunk);
+
+                let encoder = self.encoder.as_mut().expect("deflate encoder is set");
+                encoder.write_all(&chunk)?;
+                Bytes::from(std::mem::take(encoder.get_mut()))
+            }
+
+            Format::Tar => match self.size {
+                Some(size) if self.read > size => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("archive entry `{}` is longer than its size", self.name),
+                    )));
+                }
+
+                Some(_) => chunk,
+
+                None => {
+                    self.buffered.put_slice(&chunk);
+                    Bytes::new()
+                }
+            },
+        };
+
+        self.written += out.len() as u64;
+        Poll::Ready(Ok(Some(out)))
+    }
+}
+
+fn invalid_input(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// Normalizes an entry name, rejecting names that would escape the extraction directory.
+fn entry_name(name: &str) -> io::Result<String> {
+    let name = name.replace('\\', "/");
+    let name = name.trim_start_matches('/');
+
+    if name.is_empty() || name.split('/').any(|segment| segment == "..") {
+        return Err(invalid_input("invalid archive entry name"));
+    }
+
+    Ok(name.to_owned())
+}
+
+/// Returns the number of zero bytes that pad tar content of `size` bytes to a whole block.
+fn tar_padding(size: u64) -> usize {
+    (TAR_BLOCK - (size % TAR_BLOCK as u64) as usize) % TAR_BLOCK
+}
+
+/// Returns the ustar header of an entry, preceded by a GNU long name entry if its name does not
+/// fit in the header.
+fn tar_header(name: &str, size: u64, modified: SystemTime) -> Bytes {
+    let mtime = modified
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |dur| dur.as_secs());
+    let name = name.as_bytes();
+
+    let mut out = BytesMut::new();
+
+    let (prefix, name) = match split_ustar_name(name) {
+        Some(split) => split,
+        None => {
+            let len = name.len() as u64 + 1;
+            out.put_slice(&tar_header_block(b"././@LongLink", b"", len, 0, b'L'));
+            out.put_slice(name);
+            out.put_u8(0);
+            out.put_slice(&ZEROS[..tar_padding(len)]);
+
+            (&[][..], &name[..100])
+        }
+    };
+
+    out.put_slice(&tar_header_block(name, prefix, size, mtime, b'0'));
+    out.freeze()
+}
+
+/// Splits a name into the prefix and name fields of a ustar header, if it fits.
+fn split_ustar_name(name: &[u8]) -> Option<(&[u8], &[u8])> {
+    if name.len() <= 100 {
+        return Some((&[], name));
+    }
+
+    name.iter()
+        .enumerate()
+        .filter(|&(idx, &byte)| byte == b'/' && idx <= 155 && name.len() - idx - 1 <= 100)
+        .map(|(idx, _)| (&name[..idx], &name[idx + 1..]))
+        .next()
+}
+
+fn tar_header_block(
+    name: &[u8],
+    prefix: &[u8],
+    size: u64,
+    mtime: u64,
+    typeflag: u8,
+) -> [u8; TAR_BLOCK] {
+    let mut block = [0; TAR_BLOCK];
+
+    block[..name.len()].copy_from_slice(name);
+    tar_number(&mut block[100..108], 0o644); // mode
+    tar_number(&mut block[108..116], 0); // uid
+    tar_number(&mut block[116..124], 0); // gid
+    tar_number(&mut block[124..136], size);
+    tar_number(&mut block[136..148], mtime);
+    block[148..156].fill(b' '); // checksum is computed with its field set to spaces
+    block[156] = typeflag;
+    block[257..263].copy_from_slice(b"ustar\0");
+    block[263..265].copy_from_slice(b"00");
+    block[345..345 + prefix.len()].copy_from_slice(prefix);
+
+    let checksum = block.iter().map(|&byte| u64::from(byte)).sum();
+    tar_number(&mut block[148..155], checksum);
+
+    block
+}
+
+/// Writes `value` to a numeric tar header field as NUL-terminated octal digits, or in GNU base-256
+/// encoding if it does not fit.
+fn tar_number(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+
+    if value < 1 << (3 * digits) {
+        let octal = format!("{value:0digits$o}");
+        field[..digits].copy_from_slice(octal.as_bytes());
+        field[digits] = 0;
+    } else {
+        let len = field.len();
+        field.fill(0);
+        field[len - 8..].copy_from_slice(&value.to_be_bytes());
+        field[0] = 0x80;
+    }
+}
+
+/// Converts a time to the MS-DOS time and date fields of zip headers, in UTC.
+fn dos_date_time(time: SystemTime) -> (u16, u16) {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |dur| dur.as_secs());
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+
+    // DOS dates start in 1980
+    if year < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+
+    let year = year.min(2107) as u16;
+    let secs_of_day = secs % 86_400;
+    let (hour, minute, second) = (
+        (secs_of_day / 3600) as u16,
+        (secs_of_day / 60 % 60) as u16,
+        (secs_of_day % 60) as u16,
+    );
+
+    let time = (hour << 11) | (minute << 5) | (second / 2);
+    let date = ((year - 1980) << 9) | ((month as u16) << 5) | day as u16;
+    (time, date)
+}
+
+/// Converts days since the Unix epoch to a proleptic Gregorian calendar date.
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Read as _, time::Duration};
+
+    use actix_web::body::{self, BodyStream};
+
+    use super::*;
+
+    async fn collect(archive: Archive) -> io::Result<Bytes> {
+        body::to_bytes(BodyStream::new(archive.into_stream())).await
+    }
+
+    fn entries(entries: Vec<ArchiveEntry>) -> impl Stream<Item = io::Result<ArchiveEntry>> {
+        futures_util::stream::iter(entries.into_iter().map(Ok))
+    }
+
+    fn u16_at(buf: &[u8], idx: usize) -> u16 {
+        u16::from_le_bytes(buf[idx..idx + 2].try_into().unwrap())
+    }
+
+    fn u32_at(buf: &[u8], idx: usize) -> u32 {
+        u32::from_le_bytes(buf[idx..idx + 4].try_into().unwrap())
+    }
+
+    #[actix_rt::test]
+    async fn zip() {
+        let content = b"hello world, hello world, hello world";
+
+        for archive in [
+            Archive::zip(entries(vec![ArchiveEntry::new("a.txt", &content[..])])),
+            Archive::zip_deflate(entries(vec![ArchiveEntry::new("a.txt", &content[..])])),
+        ] {
+            let deflate = archive.format == Format::ZipDeflate;
+            let zip = collect(archive).await.unwrap();
+
+            assert_eq!(u32_at(&zip, 0), ZIP_LOCAL_HEADER);
+            assert_eq!(u16_at(&zip, 8), if deflate { 8 } else { 0 });
+            assert_eq!(&zip[30..35], b"a.txt");
+
+            // end of central directory record
+            let eocd = zip.len() - 22;
+            assert_eq!(u32_at(&zip, eocd), ZIP_END_OF_CENTRAL_DIR);
+            assert_eq!(u16_at(&zip, eocd + 10), 1);
+            let cd_offset = u32_at(&zip, eocd + 16) as usize;
+            assert_eq!(u32_at(&zip, cd_offset), ZIP_CENTRAL_HEADER);
+
+            // data descriptor precedes the central directory
+            let descriptor = cd_offset - 16;
+            assert_eq!(u32_at(&zip, descriptor), ZIP_DATA_DESCRIPTOR);
+            assert_eq!(u32_at(&zip, descriptor + 4), crc32fast::hash(content));
+            assert_eq!(u32_at(&zip, descriptor + 12), content.len() as u32);
+
+            let data = &zip[35..descriptor];
+            assert_eq!(data.len() as u32, u32_at(&zip, descriptor + 8));
+
+            if deflate {
+                let mut inflated = Vec::new();
+                flate2::read::DeflateDecoder::new(data)
+                    .read_to_end(&mut inflated)
+                    .unwrap();
+                assert_eq!(inflated, content);
+            } else {
+                assert_eq!(data, content);
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn tar() {
+        let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let long_name = format!("{}/{}", "d".repeat(120), "f".repeat(90));
+
+        let archive = Archive::tar(entries(vec![
+            ArchiveEntry::new("a.txt", &b"hello"[..])
+                .size(5)
+                .modified(modified),
+            ArchiveEntry::new(long_name.clone(), &b"unsized"[..]),
+        ]));
+        let tar = collect(archive).await.unwrap();
+
+        assert_eq!(tar.len(), 6 * TAR_BLOCK);
+        assert_eq!(&tar[..6], b"a.txt\0");
+        assert_eq!(&tar[124..136], b"00000000005\0");
+        assert_eq!(&tar[136..148], b"14524770400\0");
+        assert_eq!(&tar[257..263], b"ustar\0");
+        assert_eq!(&tar[TAR_BLOCK..TAR_BLOCK + 5], b"hello");
+
+        let header = &tar[2 * TAR_BLOCK..3 * TAR_BLOCK];
+        assert_eq!(&header[..90], "f".repeat(90).as_bytes());
+        assert_eq!(&header[345..465], "d".repeat(120).as_bytes());
+        assert_eq!(&header[124..136], b"00000000007\0");
+        assert_eq!(&tar[3 * TAR_BLOCK..3 * TAR_BLOCK + 7], b"unsized");
+        assert!(tar[4 * TAR_BLOCK..].iter().all(|&byte| byte == 0));
+
+        let checksum = header
+            .iter()
+            .enumerate()
+            .map(|(idx, &byte)| {
+                if (148..156).contains(&idx) {
+                    32
+                } else {
+                    byte as u64
+                }
+            })
+            .sum::<u64>();
+        assert_eq!(&header[148..155], format!("{checksum:06o}\0").as_bytes());
+    }
+
+    #[actix_rt::test]
+    async fn invalid_entries() {
+        let archive = Archive::zip(entries(vec![ArchiveEntry::new("../etc/passwd", &b""[..])]));
+        assert!(collect(archive).await.is_err());
+
+        let archive = Archive::tar(entries(vec![ArchiveEntry::new("a", &b"abc"[..]).size(2)]));
+        assert!(collect(archive).await.is_err());
+
+        let archive = Archive::tar(entries(vec![ArchiveEntry::new("a", &b"abc"[..]).size(4)]));
+        assert!(collect(archive).await.is_err());
+    }
+
+    #[test]
+    fn dos_date_time_conversion() {
+        // 2023-11-14T22:13:20Z
+        let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let (time, date) = dos_date_time(time);
+        assert_eq!(time, (22 << 11) | (13 << 5) | 10);
+        assert_eq!(date, (43 << 9) | (11 << 5) | 14);
+
+        assert_eq!(dos_date_time(UNIX_EPOCH), (0, (1 << 5) | 1));
+    }
+}
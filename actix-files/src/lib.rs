@@ -26,19 +26,31 @@ use actix_web::{
 };
 use mime_guess::from_ext;
 
+#[cfg(feature = "archive")]
+mod archive;
 mod chunked;
 mod directory;
 mod encoding;
 mod error;
 mod files;
 mod named;
+#[cfg(feature = "object-storage")]
+pub mod object;
 mod path_buf;
 mod range;
 mod service;
+mod source;
 
+#[cfg(feature = "archive")]
+pub use self::archive::{Archive, ArchiveEntry};
 pub use self::{
-    chunked::ChunkedReadFile, directory::Directory, files::Files, named::NamedFile,
-    range::HttpRange, service::FilesService,
+    chunked::ChunkedReadFile,
+    directory::Directory,
+    files::Files,
+    named::NamedFile,
+    range::HttpRange,
+    service::FilesService,
+    source::{SeekableFile, SeekableSource, SourceMetadata, SourceStream},
 };
 use self::{
     directory::{directory_listing, DirectoryRenderer},
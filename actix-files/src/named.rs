@@ -18,7 +18,7 @@ use actix_web::{
         },
         StatusCode,
     },
-    Error, HttpMessage, HttpRequest, HttpResponse, Responder,
+    Error, HttpMessage, HttpRequest, HttpResponse, HttpResponseBuilder, Responder,
 };
 use bitflags::bitflags;
 use derive_more::derive::{Deref, DerefMut};
@@ -457,42 +457,6 @@ impl NamedFile {
             None
         };
 
-        // check preconditions
-        let precondition_failed = if !any_match(etag.as_ref(), req) {
-            true
-        } else if let (Some(ref m), Some(header::IfUnmodifiedSince(ref since))) =
-            (last_modified, req.get_header())
-        {
-            let t1: SystemTime = (*m).into();
-            let t2: SystemTime = (*since).into();
-
-            match (t1.duration_since(UNIX_EPOCH), t2.duration_since(UNIX_EPOCH)) {
-                (Ok(t1), Ok(t2)) => t1.as_secs() > t2.as_secs(),
-                _ => false,
-            }
-        } else {
-            false
-        };
-
-        // check last modified
-        let not_modified = if !none_match(etag.as_ref(), req) {
-            true
-        } else if req.headers().contains_key(header::IF_NONE_MATCH) {
-            false
-        } else if let (Some(ref m), Some(header::IfModifiedSince(ref since))) =
-            (last_modified, req.get_header())
-        {
-            let t1: SystemTime = (*m).into();
-            let t2: SystemTime = (*since).into();
-
-            match (t1.duration_since(UNIX_EPOCH), t2.duration_since(UNIX_EPOCH)) {
-                (Ok(t1), Ok(t2)) => t1.as_secs() <= t2.as_secs(),
-                _ => false,
-            }
-        } else {
-            false
-        };
-
         let mut res = HttpResponse::build(self.status_code);
 
         let ct = if self.flags.contains(Flags::PREFER_UTF8) {
@@ -514,77 +478,134 @@ impl NamedFile {
             res.insert_header((header::CONTENT_ENCODING, current_encoding.as_str()));
         }
 
-        if let Some(lm) = last_modified {
-            res.insert_header((header::LAST_MODIFIED, lm.to_string()));
-        }
+        let (offset, length) =
+            match conditional_range(req, &mut res, etag, last_modified, self.md.len()) {
+                Ok(range) => range,
+                Err(res) => return res,
+            };
+
+        let reader = chunked::new_chunked_read(length, offset, self.file);
+
+        res.body(SizedStream::new(length, reader))
+    }
+}
 
-        if let Some(etag) = etag {
-            res.insert_header((header::ETAG, etag.to_string()));
+/// Evaluates conditional request headers and the `Range` header against a resource's validators.
+///
+/// Validator and range headers are added to `res`. Returns the offset and length of the content
+/// to send, having set the status to `206 Partial Content` if it is not the complete resource, or
+/// a finished response if no content should be sent.
+pub(crate) fn conditional_range(
+    req: &HttpRequest,
+    res: &mut HttpResponseBuilder,
+    etag: Option<header::EntityTag>,
+    last_modified: Option<header::HttpDate>,
+    total_len: u64,
+) -> Result<(u64, u64), HttpResponse<BoxBody>> {
+    // check preconditions
+    let precondition_failed = if !any_match(etag.as_ref(), req) {
+        true
+    } else if let (Some(ref m), Some(header::IfUnmodifiedSince(ref since))) =
+        (last_modified, req.get_header())
+    {
+        let t1: SystemTime = (*m).into();
+        let t2: SystemTime = (*since).into();
+
+        match (t1.duration_since(UNIX_EPOCH), t2.duration_since(UNIX_EPOCH)) {
+            (Ok(t1), Ok(t2)) => t1.as_secs() > t2.as_secs(),
+            _ => false,
+        }
+    } else {
+        false
+    };
+
+    // check last modified
+    let not_modified = if !none_match(etag.as_ref(), req) {
+        true
+    } else if req.headers().contains_key(header::IF_NONE_MATCH) {
+        false
+    } else if let (Some(ref m), Some(header::IfModifiedSince(ref since))) =
+        (last_modified, req.get_header())
+    {
+        let t1: SystemTime = (*m).into();
+        let t2: SystemTime = (*since).into();
+
+        match (t1.duration_since(UNIX_EPOCH), t2.duration_since(UNIX_EPOCH)) {
+            (Ok(t1), Ok(t2)) => t1.as_secs() <= t2.as_secs(),
+            _ => false,
         }
+    } else {
+        false
+    };
 
-        res.insert_header((header::ACCEPT_RANGES, "bytes"));
-
-        let mut length = self.md.len();
-        let mut offset = 0;
-
-        // check for range header
-        if let Some(ranges) = req.headers().get(header::RANGE) {
-            if let Ok(ranges_header) = ranges.to_str() {
-                if let Ok(ranges) = HttpRange::parse(ranges_header, length) {
-                    length = ranges[0].length;
-                    offset = ranges[0].start;
-
-                    // When a Content-Encoding header is present in a 206 partial content response
-                    // for video content, it prevents browser video players from starting playback
-                    // before loading the whole video and also prevents seeking.
-                    //
-                    // See: https://github.com/actix/actix-web/issues/2815
-                    //
-                    // The assumption of this fix is that the video player knows to not send an
-                    // Accept-Encoding header for this request and that downstream middleware will
-                    // not attempt compression for requests without it.
-                    //
-                    // TODO: Solve question around what to do if self.encoding is set and partial
-                    // range is requested. Reject request? Ignoring self.encoding seems wrong, too.
-                    // In practice, it should not come up.
-                    if req.headers().contains_key(&header::ACCEPT_ENCODING) {
-                        // don't allow compression middleware to modify partial content
-                        res.insert_header((
-                            header::CONTENT_ENCODING,
-                            HeaderValue::from_static("identity"),
-                        ));
-                    }
+    if let Some(lm) = last_modified {
+        res.insert_header((header::LAST_MODIFIED, lm.to_string()));
+    }
+
+    if let Some(etag) = etag {
+        res.insert_header((header::ETAG, etag.to_string()));
+    }
 
+    res.insert_header((header::ACCEPT_RANGES, "bytes"));
+
+    let mut length = total_len;
+    let mut offset = 0;
+
+    // check for range header
+    if let Some(ranges) = req.headers().get(header::RANGE) {
+        if let Ok(ranges_header) = ranges.to_str() {
+            if let Ok(ranges) = HttpRange::parse(ranges_header, length) {
+                length = ranges[0].length;
+                offset = ranges[0].start;
+
+                // When a Content-Encoding header is present in a 206 partial content response
+                // for video content, it prevents browser video players from starting playback
+                // before loading the whole video and also prevents seeking.
+                //
+                // See: https://github.com/actix/actix-web/issues/2815
+                //
+                // The assumption of this fix is that the video player knows to not send an
+                // Accept-Encoding header for this request and that downstream middleware will
+                // not attempt compression for requests without it.
+                //
+                // TODO: Solve question around what to do if self.encoding is set and partial
+                // range is requested. Reject request? Ignoring self.encoding seems wrong, too.
+                // In practice, it should not come up.
+                if req.headers().contains_key(&header::ACCEPT_ENCODING) {
+                    // don't allow compression middleware to modify partial content
                     res.insert_header((
-                        header::CONTENT_RANGE,
-                        format!("bytes {}-{}/{}", offset, offset + length - 1, self.md.len()),
+                        header::CONTENT_ENCODING,
+                        HeaderValue::from_static("identity"),
                     ));
-                } else {
-                    res.insert_header((header::CONTENT_RANGE, format!("bytes */{}", length)));
-                    return res.status(StatusCode::RANGE_NOT_SATISFIABLE).finish();
-                };
+                }
+
+                res.insert_header((
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", offset, offset + length - 1, total_len),
+                ));
             } else {
-                return res.status(StatusCode::BAD_REQUEST).finish();
+                res.insert_header((header::CONTENT_RANGE, format!("bytes */{}", length)));
+                return Err(res.status(StatusCode::RANGE_NOT_SATISFIABLE).finish());
             };
+        } else {
+            return Err(res.status(StatusCode::BAD_REQUEST).finish());
         };
+    };
+
+    if precondition_failed {
+        return Err(res.status(StatusCode::PRECONDITION_FAILED).finish());
+    } else if not_modified {
+        return Err(res
+            .status(StatusCode::NOT_MODIFIED)
+            .body(body::None::new())
+            .map_into_boxed_body());
+    }
 
-        if precondition_failed {
-            return res.status(StatusCode::PRECONDITION_FAILED).finish();
-        } else if not_modified {
-            return res
-                .status(StatusCode::NOT_MODIFIED)
-                .body(body::None::new())
-                .map_into_boxed_body();
-        }
-
-        let reader = chunked::new_chunked_read(length, offset, self.file);
-
-        if offset != 0 || length != self.md.len() {
-            res.status(StatusCode::PARTIAL_CONTENT);
-        }
-
-        res.body(SizedStream::new(length, reader))
+    if offset != 0 || length != total_len {
+        res.status(StatusCode::PARTIAL_CONTENT);
     }
+
+    Ok((offset, length))
 }
 
 /// Returns true if `req` has no `If-Match` header or one which matches `etag`.
@@ -0,0 +1,526 @@
+//! Object storage sources for [`SeekableFile`](crate::SeekableFile).
+//!
+//! [`HttpObject`] reads objects over HTTP from any store that supports `HEAD` requests and `Range`
+//! requests, such as S3 and GCS. Requests are sent through an [`HttpBackend`], which is
+//! implemented for `awc::Client` when the `awc` crate feature is enabled.
+//!
+//! Requests are not signed. Use presigned URLs or [`HttpObject::header`] to authenticate.
+
+use std::{
+    cell::RefCell,
+    fmt, io,
+    pin::Pin,
+    rc::Rc,
+    str::FromStr as _,
+    task::{Context, Poll},
+    time::SystemTime,
+};
+
+use actix_web::http::{
+    header::{self, EntityTag, HeaderMap, HeaderName, HeaderValue, HttpDate},
+    Method, StatusCode,
+};
+use bytes::Bytes;
+use futures_core::{future::LocalBoxFuture, Stream};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use crate::source::{SeekableSource, SourceMetadata, SourceStream};
+
+/// Characters escaped in object keys. Path separators are kept as-is.
+const OBJECT_KEY: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b'/');
+
+/// HTTP client used by [`HttpObject`] to talk to object storage.
+pub trait HttpBackend: 'static {
+    /// Sends `req`, resolving once the response head has been received.
+    fn send(&self, req: ObjectRequest) -> LocalBoxFuture<'static, io::Result<ObjectResponse>>;
+}
+
+/// Request sent to an [`HttpBackend`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ObjectRequest {
+    /// Request method; either `HEAD` or `GET`.
+    pub method: Method,
+
+    /// Absolute URL of the object.
+    pub url: String,
+
+    /// Request headers.
+    pub headers: HeaderMap,
+}
+
+/// Response received from an [`HttpBackend`].
+#[non_exhaustive]
+pub struct ObjectResponse {
+    /// Response status code.
+    pub status: StatusCode,
+
+    /// Response headers.
+    pub headers: HeaderMap,
+
+    /// Response body.
+    pub body: SourceStream,
+}
+
+impl ObjectResponse {
+    /// Constructs a response from its parts.
+    pub fn new(status: StatusCode, headers: HeaderMap, body: SourceStream) -> Self {
+        Self {
+            status,
+            headers,
+            body,
+        }
+    }
+}
+
+impl fmt::Debug for ObjectResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObjectResponse")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An object in remote storage, read over HTTP.
+///
+/// Metadata is read from the `Content-Length`, `Content-Type`, `ETag`, and `Last-Modified` headers
+/// of a `HEAD` request. Ranges are read with `GET` requests that carry an `If-Match` header when
+/// the object has an entity tag, so that content changed since the metadata was fetched is not
+/// mixed into a response.
+///
+/// # Examples
+/// ```
+/// # #[cfg(feature = "awc")] {
+/// use actix_files::{object::HttpObject, SeekableFile};
+/// use actix_web::{get, web, Responder};
+///
+/// #[get("/docs/{key:.*}")]
+/// async fn document(key: web::Path<String>) -> actix_web::Result<impl Responder> {
+///     let object = HttpObject::s3(awc::Client::default(), "eu-west-1", "documents", &key);
+///     Ok(SeekableFile::open(object).await?)
+/// }
+/// # }
+/// ```
+pub struct HttpObject<B> {
+    backend: B,
+    url: String,
+    headers: HeaderMap,
+    // entity tag observed by the last metadata request
+    etag: Rc<RefCell<Option<HeaderValue>>>,
+}
+
+impl<B: HttpBackend> HttpObject<B> {
+    /// Constructs a source for the object at `url`.
+    pub fn new(backend: B, url: impl Into<String>) -> Self {
+        Self {
+            backend,
+            url: url.into(),
+            headers: HeaderMap::new(),
+            etag: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Constructs a source for an object in an S3 bucket, using a virtual-hosted-style URL.
+    pub fn s3(backend: B, region: &str, bucket: &str, key: &str) -> Self {
+        let key = utf8_percent_encode(key.trim_start_matches('/'), OBJECT_KEY);
+        Self::new(
+            backend,
+            format!("https://{bucket}.s3.{region}.amazonaws.com/{key}"),
+        )
+    }
+
+    /// Constructs a source for an object in a GCS bucket, using the XML API.
+    pub fn gcs(backend: B, bucket: &str, object: &str) -> Self {
+        let object = utf8_percent_encode(object.trim_start_matches('/'), OBJECT_KEY);
+        Self::new(
+            backend,
+            format!("https://storage.googleapis.com/{bucket}/{object}"),
+        )
+    }
+
+    /// Adds a header to every request sent for this object, such as `Authorization`.
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Returns the URL of the object.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn request(&self, method: Method) -> ObjectRequest {
+        ObjectRequest {
+            method,
+            url: self.url.clone(),
+            headers: self.headers.clone(),
+        }
+    }
+}
+
+impl<B: HttpBackend> SeekableSource for HttpObject<B> {
+    fn metadata(&self) -> LocalBoxFuture<'static, io::Result<SourceMetadata>> {
+        let res = self.backend.send(self.request(Method::HEAD));
+        let etag_slot = Rc::clone(&self.etag);
+
+        Box::pin(async move {
+            let res = res.await?;
+            check_status(res.status, &[StatusCode::OK])?;
+
+            let len = res
+                .headers
+                .get(header::CONTENT_LENGTH)
+                .and_then(|len| len.to_str().ok())
+                .and_then(|len| len.parse::<u64>().ok())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "object storage response has no valid Content-Length",
+                    )
+                })?;
+
+            let mut md = SourceMetadata::new(len);
+
+            md.content_type = header_str(&res.headers, &header::CONTENT_TYPE)
+                .and_then(|ct| ct.parse::<mime::Mime>().ok());
+
+            md.etag = header_str(&res.headers, &header::ETAG)
+                .and_then(|etag| EntityTag::from_str(etag).ok());
+
+            md.last_modified = header_str(&res.headers, &header::LAST_MODIFIED)
+                .and_then(|lm| lm.parse::<HttpDate>().ok())
+                .map(SystemTime::from);
+
+            *etag_slot.borrow_mut() = res.headers.get(header::ETAG).cloned();
+
+            Ok(md)
+        })
+    }
+
+    fn read_range(
+        &self,
+        offset: u64,
+        len: u64,
+    ) -> LocalBoxFuture<'static, io::Result<SourceStream>> {
+        if len == 0 {
+            return Box::pin(async { Ok(Box::pin(Empty) as SourceStream) });
+        }
+
+        let mut req = self.request(Method::GET);
+
+        let range = format!("bytes={}-{}", offset, offset + len - 1);
+        req.headers.insert(
+            header::RANGE,
+            HeaderValue::try_from(range).expect("range header should be valid"),
+        );
+
+        if let Some(etag) = self.etag.borrow().clone() {
+            req.headers.insert(header::IF_MATCH, etag);
+        }
+
+        let res = self.backend.send(req);
+
+        Box::pin(async move {
+            let res = res.await?;
+
+            // a server that ignores the range sends the whole object, which is fine from the start
+            if offset == 0 {
+                check_status(res.status, &[StatusCode::PARTIAL_CONTENT, StatusCode::OK])?;
+            } else {
+                check_status(res.status, &[StatusCode::PARTIAL_CONTENT])?;
+            }
+
+            Ok(res.body)
+        })
+    }
+}
+
+impl<B: fmt::Debug> fmt::Debug for HttpObject<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpObject")
+            .field("backend", &self.backend)
+            .field("url", &self.url)
+            .finish_non_exhaustive()
+    }
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &HeaderName) -> Option<&'a str> {
+    headers.get(name).and_then(|val| val.to_str().ok())
+}
+
+fn check_status(status: StatusCode, expected: &[StatusCode]) -> io::Result<()> {
+    if expected.contains(&status) {
+        return Ok(());
+    }
+
+    let kind = match status {
+        StatusCode::NOT_FOUND => io::ErrorKind::NotFound,
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => io::ErrorKind::PermissionDenied,
+        _ => io::ErrorKind::Other,
+    };
+
+    Err(io::Error::new(
+        kind,
+        format!("object storage responded with unexpected status {status}"),
+    ))
+}
+
+/// Body of a zero-length range.
+struct Empty;
+
+impl Stream for Empty {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(None)
+    }
+}
+
+#[cfg(feature = "awc")]
+mod awc_backend {
+    use std::{
+        io,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    use actix_web::error::PayloadError;
+    use awc::{error::SendRequestError, ClientResponse};
+    use bytes::Bytes;
+    use futures_core::{future::LocalBoxFuture, Stream};
+
+    use super::{HttpBackend, ObjectRequest, ObjectResponse};
+
+    impl HttpBackend for awc::Client {
+        fn send(&self, req: ObjectRequest) -> LocalBoxFuture<'static, io::Result<ObjectResponse>> {
+            let mut client_req = self.request(req.method, req.url).no_decompress();
+
+            for (name, value) in req.headers.iter() {
+                client_req = client_req.append_header((name.clone(), value.clone()));
+            }
+
+            Box::pin(async move {
+                let res = client_req.send().await.map_err(send_error)?;
+                let status = res.status();
+                let headers = res.headers().clone();
+
+                Ok(ObjectResponse::new(status, headers, Box::pin(AwcBody(res))))
+            })
+        }
+    }
+
+    fn send_error(err: SendRequestError) -> io::Error {
+        match err {
+            SendRequestError::Timeout => io::Error::new(io::ErrorKind::TimedOut, err.to_string()),
+            err => io::Error::other(err.to_string()),
+        }
+    }
+
+    /// Response body with errors converted to I/O errors.
+    ///
+    /// Generic since the payload type depends on `awc`'s compression features.
+    struct AwcBody<S>(ClientResponse<S>);
+
+    impl<S> Stream for AwcBody<S>
+    where
+        S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+    {
+        type Item = io::Result<Bytes>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Pin::new(&mut self.0)
+                .poll_next(cx)
+                .map(|item| item.map(|res| res.map_err(payload_error)))
+        }
+    }
+
+    fn payload_error(err: PayloadError) -> io::Error {
+        match err {
+            PayloadError::Io(err) => err,
+            err => io::Error::other(err.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use actix_web::{body::to_bytes, test::TestRequest};
+
+    use super::*;
+    use crate::SeekableFile;
+
+    /// Backend that records requests and serves a fixed object.
+    #[derive(Clone, Default)]
+    struct MockBackend {
+        requests: Rc<RefCell<Vec<ObjectRequest>>>,
+    }
+
+    impl HttpBackend for MockBackend {
+        fn send(&self, req: ObjectRequest) -> LocalBoxFuture<'static, io::Result<ObjectResponse>> {
+            const CONTENT: &[u8] = b"object contents";
+
+            let mut headers = HeaderMap::new();
+            headers.insert(header::ETAG, HeaderValue::from_static("\"abc\""));
+            headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+            headers.insert(
+                header::LAST_MODIFIED,
+                HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+            );
+
+            let res = if req.method == Method::HEAD {
+                headers.insert(header::CONTENT_LENGTH, CONTENT.len().into());
+                ObjectResponse::new(StatusCode::OK, headers, Box::pin(Empty))
+            } else {
+                // only single "bytes=a-b" ranges are sent by `HttpObject`
+                let range = req.headers.get(header::RANGE).unwrap().to_str().unwrap();
+                let (start, end) = range["bytes=".len()..].split_once('-').unwrap();
+                let (start, end) = (
+                    start.parse::<usize>().unwrap(),
+                    end.parse::<usize>().unwrap(),
+                );
+                let chunk = Bytes::from_static(&CONTENT[start..=end]);
+
+                ObjectResponse::new(
+                    StatusCode::PARTIAL_CONTENT,
+                    headers,
+                    Box::pin(Once(Some(chunk))),
+                )
+            };
+
+            self.requests.borrow_mut().push(req);
+            Box::pin(async move { Ok(res) })
+        }
+    }
+
+    struct Once(Option<Bytes>);
+
+    impl Stream for Once {
+        type Item = io::Result<Bytes>;
+
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Poll::Ready(self.0.take().map(Ok))
+        }
+    }
+
+    #[test]
+    fn object_urls() {
+        let object = HttpObject::s3(MockBackend::default(), "eu-west-1", "docs", "/a b/c+d.pdf");
+        assert_eq!(
+            object.url(),
+            "https://docs.s3.eu-west-1.amazonaws.com/a%20b/c%2Bd.pdf"
+        );
+
+        let object = HttpObject::gcs(MockBackend::default(), "docs", "reports/2024.pdf");
+        assert_eq!(
+            object.url(),
+            "https://storage.googleapis.com/docs/reports/2024.pdf"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn ranged_object() {
+        let backend = MockBackend::default();
+        let object = HttpObject::new(backend.clone(), "https://example.com/object").header(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer token"),
+        );
+
+        let file = SeekableFile::open(object).await.unwrap();
+        assert_eq!(file.metadata().len, 15);
+        assert_eq!(file.content_type(), &mime::TEXT_PLAIN);
+        assert!(file.metadata().last_modified.is_some());
+
+        let req = TestRequest::default()
+            .insert_header((header::RANGE, "bytes=7-"))
+            .to_http_request();
+        let res = file.into_response(&req);
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(res.headers().get(header::ETAG).unwrap(), "\"abc\"");
+
+        let body = to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, "contents");
+
+        let requests = backend.requests.borrow();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].method, Method::HEAD);
+        assert_eq!(requests[1].method, Method::GET);
+        assert_eq!(
+            requests[1].headers.get(header::RANGE).unwrap(),
+            "bytes=7-14"
+        );
+        assert_eq!(
+            requests[1].headers.get(header::IF_MATCH).unwrap(),
+            "\"abc\""
+        );
+        assert!(requests
+            .iter()
+            .all(|req| req.headers.get(header::AUTHORIZATION).unwrap() == "Bearer token"));
+    }
+
+    #[actix_rt::test]
+    async fn missing_object() {
+        #[derive(Debug)]
+        struct NotFound;
+
+        impl HttpBackend for NotFound {
+            fn send(
+                &self,
+                _: ObjectRequest,
+            ) -> LocalBoxFuture<'static, io::Result<ObjectResponse>> {
+                let res =
+                    ObjectResponse::new(StatusCode::NOT_FOUND, HeaderMap::new(), Box::pin(Empty));
+                Box::pin(async move { Ok(res) })
+            }
+        }
+
+        let err = SeekableFile::open(HttpObject::new(NotFound, "https://example.com/missing"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[cfg(feature = "awc")]
+    #[actix_rt::test]
+    async fn awc_backend() {
+        use actix_web::{web, App};
+
+        use crate::Files;
+
+        // serve this crate's files as the object store
+        let store = actix_test::start(|| App::new().service(Files::new("/bucket", ".")));
+        let url = store.url("/bucket/Cargo.toml");
+
+        let srv = actix_test::start(move || {
+            let url = url.clone();
+
+            App::new().default_service(web::to(move || {
+                let object = HttpObject::new(awc::Client::default(), url.clone());
+                async move { SeekableFile::open(object).await }
+            }))
+        });
+
+        let expected = std::fs::read("Cargo.toml").unwrap();
+
+        let mut res = srv.get("/").send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().contains_key(header::ETAG));
+        assert_eq!(res.body().await.unwrap(), expected);
+
+        let mut res = srv
+            .get("/")
+            .insert_header((header::RANGE, "bytes=10-19"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(res.body().await.unwrap(), expected[10..20]);
+    }
+}
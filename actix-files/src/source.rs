@@ -0,0 +1,428 @@
+use std::{
+    fmt, io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::SystemTime,
+};
+
+use actix_web::{
+    body::{BoxBody, SizedStream},
+    http::header::{self, ContentDisposition, EntityTag},
+    HttpRequest, HttpResponse, Responder,
+};
+use bytes::Bytes;
+use futures_core::{
+    future::LocalBoxFuture,
+    ready,
+    stream::{LocalBoxStream, Stream},
+};
+use mime::Mime;
+
+use crate::named::{conditional_range, Flags};
+
+/// Stream of bytes read from a [`SeekableSource`].
+pub type SourceStream = LocalBoxStream<'static, io::Result<Bytes>>;
+
+/// Random access content source, such as an object in remote object storage.
+///
+/// Sources are served with [`SeekableFile`], which handles conditional and range requests in the
+/// same way as [`NamedFile`](crate::NamedFile), so sources only need to describe their content
+/// and stream byte ranges of it.
+pub trait SeekableSource: 'static {
+    /// Fetches the length and validators of the content.
+    fn metadata(&self) -> LocalBoxFuture<'static, io::Result<SourceMetadata>>;
+
+    /// Streams `len` bytes of the content starting at `offset`.
+    ///
+    /// The range is always within the length reported by [`metadata`](Self::metadata). The
+    /// returned stream should yield exactly `len` bytes; any further bytes are ignored.
+    fn read_range(
+        &self,
+        offset: u64,
+        len: u64,
+    ) -> LocalBoxFuture<'static, io::Result<SourceStream>>;
+}
+
+/// Length and validators of a [`SeekableSource`]'s content.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SourceMetadata {
+    /// Length of the content in bytes.
+    pub len: u64,
+
+    /// Media type of the content, if known.
+    pub content_type: Option<Mime>,
+
+    /// Entity tag identifying the current version of the content.
+    pub etag: Option<EntityTag>,
+
+    /// Time that the content was last modified.
+    pub last_modified: Option<SystemTime>,
+}
+
+impl SourceMetadata {
+    /// Constructs metadata for content of `len` bytes, without validators.
+    pub fn new(len: u64) -> Self {
+        Self {
+            len,
+            content_type: None,
+            etag: None,
+            last_modified: None,
+        }
+    }
+}
+
+/// A [`SeekableSource`] responder with range and caching header support.
+///
+/// # Examples
+/// ```
+/// use std::io;
+///
+/// use actix_files::{SeekableFile, SeekableSource};
+/// use actix_web::Responder;
+///
+/// async fn document(source: impl SeekableSource) -> io::Result<impl Responder> {
+///     Ok(SeekableFile::open(source).await?.use_last_modified(false))
+/// }
+/// ```
+pub struct SeekableFile<S> {
+    source: S,
+    md: SourceMetadata,
+    content_type: Mime,
+    content_disposition: Option<ContentDisposition>,
+    flags: Flags,
+}
+
+impl<S: SeekableSource> SeekableFile<S> {
+    /// Fetches the source's metadata and constructs a responder for it.
+    pub async fn open(source: S) -> io::Result<Self> {
+        let md = source.metadata().await?;
+        Ok(Self::from_parts(source, md))
+    }
+
+    /// Constructs a responder from a source and previously fetched metadata.
+    ///
+    /// The content type defaults to the one in `md`, or `application/octet-stream`.
+    pub fn from_parts(source: S, md: SourceMetadata) -> Self {
+        let content_type = md
+            .content_type
+            .clone()
+            .unwrap_or(mime::APPLICATION_OCTET_STREAM);
+
+        Self {
+            source,
+            md,
+            content_type,
+            content_disposition: None,
+            flags: Flags::ETAG | Flags::LAST_MD,
+        }
+    }
+
+    /// Returns reference to the source.
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Returns the source's metadata.
+    pub fn metadata(&self) -> &SourceMetadata {
+        &self.md
+    }
+
+    /// Returns the `Content-Type` header that will be used when serving this source.
+    pub fn content_type(&self) -> &Mime {
+        &self.content_type
+    }
+
+    /// Sets the `Content-Type` header that will be used when serving this source.
+    pub fn set_content_type(mut self, mime_type: Mime) -> Self {
+        self.content_type = mime_type;
+        self
+    }
+
+    /// Sets the `Content-Disposition` header that will be used when serving this source.
+    ///
+    /// By default, no `Content-Disposition` header is sent.
+    pub fn set_content_disposition(mut self, cd: ContentDisposition) -> Self {
+        self.content_disposition = Some(cd);
+        self
+    }
+
+    /// Specifies whether to return `ETag` header in response.
+    ///
+    /// Default is true.
+    pub fn use_etag(mut self, value: bool) -> Self {
+        self.flags.set(Flags::ETAG, value);
+        self
+    }
+
+    /// Specifies whether to return `Last-Modified` header in response.
+    ///
+    /// Default is true.
+    pub fn use_last_modified(mut self, value: bool) -> Self {
+        self.flags.set(Flags::LAST_MD, value);
+        self
+    }
+
+    /// Creates an `HttpResponse` with the source as a streaming body.
+    pub fn into_response(self, req: &HttpRequest) -> HttpResponse<BoxBody> {
+        let etag = if self.flags.contains(Flags::ETAG) {
+            self.md.etag.clone()
+        } else {
+            None
+        };
+
+        let last_modified = if self.flags.contains(Flags::LAST_MD) {
+            self.md.last_modified.map(Into::into)
+        } else {
+            None
+        };
+
+        let mut res = HttpResponse::Ok();
+
+        res.insert_header((header::CONTENT_TYPE, self.content_type.to_string()));
+
+        if let Some(ref cd) = self.content_disposition {
+            res.insert_header((header::CONTENT_DISPOSITION, cd.to_string()));
+        }
+
+        let (offset, length) =
+            match conditional_range(req, &mut res, etag, last_modified, self.md.len) {
+                Ok(range) => range,
+                Err(res) => return res,
+            };
+
+        let stream = RangeStream::Opening {
+            fut: self.source.read_range(offset, length),
+            remaining: length,
+        };
+
+        res.body(SizedStream::new(length, stream))
+    }
+}
+
+impl<S: SeekableSource> Responder for SeekableFile<S> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse<Self::Body> {
+        self.into_response(req)
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for SeekableFile<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SeekableFile")
+            .field("source", &self.source)
+            .field("md", &self.md)
+            .field("content_type", &self.content_type)
+            .field("content_disposition", &self.content_disposition)
+            .field("flags", &self.flags)
+            .finish()
+    }
+}
+
+/// Body stream that opens the source range when first polled and yields exactly the requested
+/// number of bytes.
+enum RangeStream {
+    Opening {
+        fut: LocalBoxFuture<'static, io::Result<SourceStream>>,
+        remaining: u64,
+    },
+    Streaming {
+        stream: SourceStream,
+        remaining: u64,
+    },
+    Done,
+}
+
+impl Stream for RangeStream {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            match this {
+                RangeStream::Opening { fut, remaining } => {
+                    let remaining = *remaining;
+
+                    match ready!(fut.as_mut().poll(cx)) {
+                        Ok(stream) => *this = RangeStream::Streaming { stream, remaining },
+                        Err(err) => {
+                            *this = RangeStream::Done;
+                            return Poll::Ready(Some(Err(err)));
+                        }
+                    }
+                }
+
+                RangeStream::Streaming { remaining: 0, .. } => {
+                    *this = RangeStream::Done;
+                }
+
+                RangeStream::Streaming { stream, remaining } => {
+                    match ready!(stream.as_mut().poll_next(cx)) {
+                        Some(Ok(mut chunk)) => {
+                            if chunk.len() as u64 > *remaining {
+                                chunk.truncate(*remaining as usize);
+                            }
+
+                            *remaining -= chunk.len() as u64;
+                            return Poll::Ready(Some(Ok(chunk)));
+                        }
+
+                        Some(Err(err)) => {
+                            *this = RangeStream::Done;
+                            return Poll::Ready(Some(Err(err)));
+                        }
+
+                        None => {
+                            *this = RangeStream::Done;
+                            return Poll::Ready(Some(Err(io::Error::new(
+                                io::ErrorKind::UnexpectedEof,
+                                "source ended before the requested range was read",
+                            ))));
+                        }
+                    }
+                }
+
+                RangeStream::Done => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use actix_web::{
+        body::to_bytes,
+        http::{header, StatusCode},
+        test::TestRequest,
+    };
+
+    use super::*;
+
+    /// In-memory source that streams ranges as two chunks.
+    #[derive(Debug)]
+    struct MemorySource(Bytes);
+
+    impl SeekableSource for MemorySource {
+        fn metadata(&self) -> LocalBoxFuture<'static, io::Result<SourceMetadata>> {
+            let mut md = SourceMetadata::new(self.0.len() as u64);
+            md.etag = Some(EntityTag::new_strong("v1".to_owned()));
+            md.last_modified = Some(UNIX_EPOCH + Duration::from_secs(1_000_000_000));
+            md.content_type = Some(mime::TEXT_PLAIN);
+
+            Box::pin(async move { Ok(md) })
+        }
+
+        fn read_range(
+            &self,
+            offset: u64,
+            len: u64,
+        ) -> LocalBoxFuture<'static, io::Result<SourceStream>> {
+            let end = (offset + len).min(self.0.len() as u64);
+            let range = self.0.slice(offset as usize..end as usize);
+
+            Box::pin(async move {
+                let mid = range.len() / 2;
+                let chunks = vec![Ok(range.slice(..mid)), Ok(range.slice(mid..))];
+                Ok(Box::pin(Iter(chunks.into_iter())) as SourceStream)
+            })
+        }
+    }
+
+    struct Iter<I>(I);
+
+    impl<I: Iterator + Unpin> Stream for Iter<I> {
+        type Item = I::Item;
+
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Poll::Ready(self.0.next())
+        }
+    }
+
+    async fn file() -> SeekableFile<MemorySource> {
+        SeekableFile::open(MemorySource(Bytes::from_static(b"hello, world")))
+            .await
+            .unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn full_content() {
+        let req = TestRequest::default().to_http_request();
+        let res = file().await.into_response(&req);
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/plain"
+        );
+        assert_eq!(res.headers().get(header::ETAG).unwrap(), "\"v1\"");
+        assert_eq!(res.headers().get(header::ACCEPT_RANGES).unwrap(), "bytes");
+        assert!(res.headers().contains_key(header::LAST_MODIFIED));
+
+        let body = to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, "hello, world");
+    }
+
+    #[actix_rt::test]
+    async fn range_request() {
+        let req = TestRequest::default()
+            .insert_header((header::RANGE, "bytes=7-11"))
+            .to_http_request();
+        let res = file().await.into_response(&req);
+
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            res.headers().get(header::CONTENT_RANGE).unwrap(),
+            "bytes 7-11/12"
+        );
+
+        let body = to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, "world");
+
+        let req = TestRequest::default()
+            .insert_header((header::RANGE, "bytes=20-"))
+            .to_http_request();
+        let res = file().await.into_response(&req);
+        assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    }
+
+    #[actix_rt::test]
+    async fn conditional_requests() {
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, "\"v1\""))
+            .to_http_request();
+        let res = file().await.into_response(&req);
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_MATCH, "\"v2\""))
+            .to_http_request();
+        let res = file().await.into_response(&req);
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, "\"v1\""))
+            .to_http_request();
+        let res = file().await.use_etag(false).into_response(&req);
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn short_source() {
+        let source = MemorySource(Bytes::from_static(b"hello, world"));
+        let file = SeekableFile::from_parts(source, SourceMetadata::new(20));
+
+        let req = TestRequest::default().to_http_request();
+        let res = file.into_response(&req);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/octet-stream"
+        );
+
+        // source has fewer bytes than its metadata claimed
+        assert!(to_bytes(res.into_body()).await.is_err());
+    }
+}
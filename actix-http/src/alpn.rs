@@ -0,0 +1,201 @@
+//! Routing of TLS connections to custom protocol services by their negotiated ALPN protocol.
+
+use std::{
+    fmt,
+    future::Future,
+    net,
+    pin::Pin,
+    rc::Rc,
+    task::{ready, Context, Poll},
+};
+
+use actix_service::{
+    boxed::{self, BoxService, BoxServiceFactory},
+    IntoServiceFactory, Service, ServiceFactory, ServiceFactoryExt as _,
+};
+use actix_utils::future::Either;
+use futures_core::future::LocalBoxFuture;
+use pin_project_lite::pin_project;
+
+use crate::{error::DispatchError, Protocol};
+
+type AlpnServiceFactory<T> =
+    BoxServiceFactory<(), (T, Option<net::SocketAddr>), (), DispatchError, ()>;
+
+type AlpnService<T> = BoxService<(T, Option<net::SocketAddr>), (), DispatchError>;
+
+/// Custom protocol services, keyed by ALPN protocol ID.
+pub(crate) struct AlpnProtocols<T> {
+    protocols: Vec<(Vec<u8>, AlpnServiceFactory<T>)>,
+}
+
+impl<T> Default for AlpnProtocols<T> {
+    fn default() -> Self {
+        Self {
+            protocols: Vec::new(),
+        }
+    }
+}
+
+impl<T: 'static> AlpnProtocols<T> {
+    /// Registers `service` for connections that negotiate the `id` protocol.
+    ///
+    /// Replaces any service previously registered for the same ID.
+    pub(crate) fn insert<F, P>(&mut self, id: Vec<u8>, service: F)
+    where
+        F: IntoServiceFactory<P, (T, Option<net::SocketAddr>)>,
+        P: ServiceFactory<(T, Option<net::SocketAddr>), Config = (), Response = ()> + 'static,
+        P::Future: 'static,
+        P::Service: 'static,
+        <P::Service as Service<(T, Option<net::SocketAddr>)>>::Future: 'static,
+        P::Error: Into<DispatchError>,
+        P::InitError: fmt::Debug,
+    {
+        let factory = boxed::factory(service.into_factory().map_err(Into::into).map_init_err(
+            |err| {
+                tracing::error!("Initialization of ALPN protocol service error: {err:?}");
+            },
+        ));
+
+        self.protocols.retain(|(existing, _)| *existing != id);
+        self.protocols.push((id, factory));
+    }
+}
+
+impl<T> AlpnProtocols<T> {
+    /// Returns the registered protocol IDs, in registration order.
+    pub(crate) fn ids(&self) -> impl Iterator<Item = &[u8]> {
+        self.protocols.iter().map(|(id, _)| id.as_slice())
+    }
+}
+
+/// Selects the HTTP version of a connection from its negotiated ALPN protocol.
+pub(crate) fn http_protocol(alpn: Option<&[u8]>) -> Protocol {
+    match alpn {
+        Some(protos) if protos.windows(2).any(|window| window == b"h2") => Protocol::Http2,
+        _ => Protocol::Http1,
+    }
+}
+
+/// Service factory that passes connections which negotiated a custom protocol to its service and
+/// all others to the HTTP service.
+pub(crate) struct AlpnDispatch<T, H> {
+    protocols: Rc<AlpnProtocols<T>>,
+    http: H,
+}
+
+impl<T, H> AlpnDispatch<T, H> {
+    pub(crate) fn new(protocols: AlpnProtocols<T>, http: H) -> Self {
+        Self {
+            protocols: Rc::new(protocols),
+            http,
+        }
+    }
+}
+
+impl<T, H> ServiceFactory<(T, Option<Vec<u8>>, Option<net::SocketAddr>)> for AlpnDispatch<T, H>
+where
+    T: 'static,
+    H: ServiceFactory<
+        (T, Protocol, Option<net::SocketAddr>),
+        Config = (),
+        Response = (),
+        Error = DispatchError,
+        InitError = (),
+    >,
+{
+    type Response = ();
+    type Error = DispatchError;
+    type Config = ();
+    type Service = AlpnDispatchService<T, H::Service>;
+    type InitError = ();
+    type Future = AlpnDispatchFuture<T, H::Future>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        let protocols = Rc::clone(&self.protocols);
+
+        AlpnDispatchFuture {
+            protocols: Box::pin(async move {
+                let mut services = Vec::with_capacity(protocols.protocols.len());
+
+                for (id, factory) in &protocols.protocols {
+                    services.push((id.clone(), factory.new_service(()).await?));
+                }
+
+                Ok(services)
+            }),
+            services: None,
+            http: self.http.new_service(()),
+        }
+    }
+}
+
+pin_project! {
+    pub(crate) struct AlpnDispatchFuture<T, F> {
+        protocols: LocalBoxFuture<'static, Result<Vec<(Vec<u8>, AlpnService<T>)>, ()>>,
+        services: Option<Vec<(Vec<u8>, AlpnService<T>)>>,
+        #[pin]
+        http: F,
+    }
+}
+
+impl<T, F, S> Future for AlpnDispatchFuture<T, F>
+where
+    F: Future<Output = Result<S, ()>>,
+{
+    type Output = Result<AlpnDispatchService<T, S>, ()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if this.services.is_none() {
+            *this.services = Some(ready!(this.protocols.as_mut().poll(cx))?);
+        }
+
+        let http = ready!(this.http.poll(cx))?;
+
+        Poll::Ready(Ok(AlpnDispatchService {
+            protocols: this.services.take().unwrap(),
+            http,
+        }))
+    }
+}
+
+pub(crate) struct AlpnDispatchService<T, S> {
+    protocols: Vec<(Vec<u8>, AlpnService<T>)>,
+    http: S,
+}
+
+impl<T, S> Service<(T, Option<Vec<u8>>, Option<net::SocketAddr>)> for AlpnDispatchService<T, S>
+where
+    S: Service<(T, Protocol, Option<net::SocketAddr>), Response = (), Error = DispatchError>,
+{
+    type Response = ();
+    type Error = DispatchError;
+    type Future = Either<LocalBoxFuture<'static, Result<(), DispatchError>>, S::Future>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        for (_, service) in &self.protocols {
+            ready!(service.poll_ready(cx))?;
+        }
+
+        self.http.poll_ready(cx)
+    }
+
+    fn call(
+        &self,
+        (io, alpn, peer_addr): (T, Option<Vec<u8>>, Option<net::SocketAddr>),
+    ) -> Self::Future {
+        let custom = alpn
+            .as_deref()
+            .and_then(|alpn| self.protocols.iter().find(|(id, _)| id.as_slice() == alpn));
+
+        match custom {
+            Some((_, service)) => Either::left(service.call((io, peer_addr))),
+            None => Either::right(
+                self.http
+                    .call((io, http_protocol(alpn.as_deref()), peer_addr)),
+            ),
+        }
+    }
+}
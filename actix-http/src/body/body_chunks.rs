@@ -0,0 +1,59 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures_core::Stream;
+use pin_project_lite::pin_project;
+
+use super::{BodySize, MessageBody};
+
+pin_project! {
+    /// Stream of the chunks of a [`MessageBody`].
+    ///
+    /// This is the reverse of [`BodyStream`](super::BodyStream), for passing a body to APIs that
+    /// expect a `Stream` of `Bytes`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use actix_http::body::{BodyChunks, ReaderBody};
+    /// use futures_util::TryStreamExt as _;
+    ///
+    /// # actix_rt::System::new().block_on(async {
+    /// let chunks = BodyChunks::new(ReaderBody::with_capacity(&b"hello"[..], 2));
+    /// assert_eq!(chunks.try_collect::<Vec<_>>().await.unwrap(), ["he", "ll", "o"]);
+    /// # });
+    /// ```
+    pub struct BodyChunks<B> {
+        #[pin]
+        body: B,
+    }
+}
+
+impl<B: MessageBody> BodyChunks<B> {
+    /// Constructs a stream of the chunks of `body`.
+    pub fn new(body: B) -> Self {
+        Self { body }
+    }
+
+    /// Returns the body.
+    pub fn into_inner(self) -> B {
+        self.body
+    }
+}
+
+impl<B: MessageBody> Stream for BodyChunks<B> {
+    type Item = Result<Bytes, B::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.project().body.poll_next(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.body.size() {
+            BodySize::None | BodySize::Sized(0) => (0, Some(0)),
+            _ => (0, None),
+        }
+    }
+}
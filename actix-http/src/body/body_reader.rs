@@ -0,0 +1,159 @@
+use std::{
+    error::Error as StdError,
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use actix_codec::{AsyncRead, ReadBuf};
+use bytes::{Buf as _, Bytes};
+use pin_project_lite::pin_project;
+use tokio::io::AsyncBufRead;
+
+use super::MessageBody;
+
+pin_project! {
+    /// Adapter that reads the bytes of a [`MessageBody`] through [`AsyncRead`] and
+    /// [`AsyncBufRead`].
+    ///
+    /// Useful for piping a body into a subprocess, a file, or a decoder that expects a reader.
+    /// Errors from the body are returned from reads as [`io::Error`]s of kind
+    /// [`Other`](io::ErrorKind::Other), or unchanged if the body's error type is `io::Error`.
+    ///
+    /// To read from a `Stream` of `Bytes`, wrap it in a [`BodyStream`](super::BodyStream) first.
+    ///
+    /// # Examples
+    /// ```
+    /// # use actix_http::body::BodyReader;
+    /// use tokio::io::AsyncReadExt as _;
+    ///
+    /// # actix_rt::System::new().block_on(async {
+    /// let mut reader = BodyReader::new("hello world");
+    ///
+    /// let mut buf = String::new();
+    /// reader.read_to_string(&mut buf).await.unwrap();
+    /// assert_eq!(buf, "hello world");
+    /// # });
+    /// ```
+    pub struct BodyReader<B> {
+        #[pin]
+        body: B,
+        chunk: Bytes,
+        done: bool,
+    }
+}
+
+impl<B: MessageBody> BodyReader<B> {
+    /// Constructs a reader of `body`.
+    pub fn new(body: B) -> Self {
+        Self {
+            body,
+            chunk: Bytes::new(),
+            done: false,
+        }
+    }
+
+    /// Returns the body, discarding any bytes of the current chunk that have not been read.
+    pub fn into_inner(self) -> B {
+        self.body
+    }
+}
+
+impl<B: MessageBody> AsyncRead for BodyReader<B> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let chunk = ready!(self.as_mut().poll_fill_buf(cx))?;
+        let len = chunk.len().min(buf.remaining());
+        buf.put_slice(&chunk[..len]);
+        self.consume(len);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<B: MessageBody> AsyncBufRead for BodyReader<B> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let mut this = self.project();
+
+        while this.chunk.is_empty() && !*this.done {
+            match ready!(this.body.as_mut().poll_next(cx)) {
+                Some(Ok(chunk)) => *this.chunk = chunk,
+                Some(Err(err)) => {
+                    *this.done = true;
+                    return Poll::Ready(Err(into_io_error(err.into())));
+                }
+                None => *this.done = true,
+            }
+        }
+
+        Poll::Ready(Ok(&this.chunk[..]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.project().chunk.advance(amt);
+    }
+}
+
+fn into_io_error(err: Box<dyn StdError>) -> io::Error {
+    match err.downcast::<io::Error>() {
+        Ok(err) => *err,
+        Err(err) => io::Error::other(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::stream;
+    use static_assertions::assert_impl_all;
+    use tokio::io::{AsyncBufReadExt as _, AsyncReadExt as _};
+
+    use super::*;
+    use crate::body::{BodyStream, BoxBody};
+
+    assert_impl_all!(BodyReader<BoxBody>: AsyncRead, AsyncBufRead);
+
+    #[actix_rt::test]
+    async fn reads_across_chunks() {
+        let body = BodyStream::new(stream::iter(
+            ["hello\nwo", "", "rld\n"].map(|chunk| Ok::<_, io::Error>(Bytes::from(chunk))),
+        ));
+        let mut reader = BodyReader::new(body);
+
+        let mut buf = [0; 3];
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hel");
+
+        let mut lines = Vec::new();
+        let mut line = String::new();
+        while reader.read_line(&mut line).await.unwrap() > 0 {
+            lines.push(std::mem::take(&mut line));
+        }
+
+        assert_eq!(lines, ["lo\n", "world\n"]);
+    }
+
+    #[actix_rt::test]
+    async fn body_errors() {
+        let body = BodyStream::new(stream::iter([
+            Ok(Bytes::from_static(b"a")),
+            Err(io::Error::from(io::ErrorKind::ConnectionReset)),
+        ]));
+        let mut buf = Vec::new();
+        let err = BodyReader::new(body)
+            .read_to_end(&mut buf)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+        assert_eq!(buf, b"a");
+
+        let body = BodyStream::new(stream::iter([Err::<Bytes, _>("oops")]));
+        let err = BodyReader::new(body)
+            .read_to_end(&mut Vec::new())
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        assert_eq!(err.to_string(), "oops");
+    }
+}
@@ -7,8 +7,8 @@ use std::{
 
 use bytes::Bytes;
 
-use super::{BodySize, MessageBody, MessageBodyMapErr};
-use crate::body;
+use super::{BodySize, MessageBody, MessageBodyMapErr, SendFile};
+use crate::{body, header::HeaderMap};
 
 /// A boxed message body with boxed errors.
 #[derive(Debug)]
@@ -92,6 +92,22 @@ impl MessageBody for BoxBody {
         }
     }
 
+    #[inline]
+    fn take_trailers(mut self: Pin<&mut Self>) -> Option<HeaderMap> {
+        match &mut self.0 {
+            BoxBodyInner::Stream(body) => body.as_mut().take_trailers(),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn take_send_file(mut self: Pin<&mut Self>) -> Option<SendFile> {
+        match &mut self.0 {
+            BoxBodyInner::Stream(body) => body.as_mut().take_send_file(),
+            _ => None,
+        }
+    }
+
     #[inline]
     fn boxed(self) -> BoxBody {
         self
@@ -6,8 +6,8 @@ use std::{
 use bytes::Bytes;
 use pin_project_lite::pin_project;
 
-use super::{BodySize, BoxBody, MessageBody};
-use crate::Error;
+use super::{BodySize, BoxBody, MessageBody, SendFile};
+use crate::{header::HeaderMap, Error};
 
 pin_project! {
     /// An "either" type specialized for body types.
@@ -99,6 +99,22 @@ where
         }
     }
 
+    #[inline]
+    fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+        match self.project() {
+            EitherBodyProj::Left { body } => body.take_trailers(),
+            EitherBodyProj::Right { body } => body.take_trailers(),
+        }
+    }
+
+    #[inline]
+    fn take_send_file(self: Pin<&mut Self>) -> Option<SendFile> {
+        match self.project() {
+            EitherBodyProj::Left { body } => body.take_send_file(),
+            EitherBodyProj::Right { body } => body.take_send_file(),
+        }
+    }
+
     #[inline]
     fn boxed(self) -> BoxBody {
         match self {
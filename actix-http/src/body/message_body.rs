@@ -12,7 +12,8 @@ use bytes::{Bytes, BytesMut};
 use futures_core::ready;
 use pin_project_lite::pin_project;
 
-use super::{BodySize, BoxBody};
+use super::{BodySize, BoxBody, SendFile};
+use crate::header::HeaderMap;
 
 /// An interface for types that can be used as a response body.
 ///
@@ -105,6 +106,33 @@ pub trait MessageBody {
         Err(self)
     }
 
+    /// Takes the trailer fields that should be sent after this body.
+    ///
+    /// Called by the dispatchers once `poll_next` has returned `Ready(None)`. Trailers are only
+    /// sent for HTTP/2 responses and for HTTP/1.1 responses using chunked transfer encoding; they
+    /// are silently dropped otherwise. See [`TrailerBody`](super::TrailerBody) for an easy way to
+    /// attach trailers to any body.
+    ///
+    /// The default implementation returns `None`. Wrapper types should forward this call to the
+    /// body they wrap.
+    #[inline]
+    fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+        None
+    }
+
+    /// Takes the file region this body sends, if it is a [`SendFile`] body.
+    ///
+    /// Called by the HTTP/1 dispatcher before polling a response body so it can copy the file
+    /// straight to the socket instead. After returning `Some`, the body must behave as if it had
+    /// been fully sent.
+    ///
+    /// The default implementation returns `None`. Wrapper types that send their inner body
+    /// unchanged should forward this call to it.
+    #[inline]
+    fn take_send_file(self: Pin<&mut Self>) -> Option<SendFile> {
+        None
+    }
+
     /// Wraps this body into a `BoxBody`.
     ///
     /// No-op when called on a `BoxBody`, meaning there is no risk of double boxing when calling
@@ -140,6 +168,14 @@ mod foreign_impls {
         ) -> Poll<Option<Result<Bytes, Self::Error>>> {
             Pin::new(&mut **self).poll_next(cx)
         }
+
+        fn take_trailers(mut self: Pin<&mut Self>) -> Option<HeaderMap> {
+            Pin::new(&mut **self).take_trailers()
+        }
+
+        fn take_send_file(mut self: Pin<&mut Self>) -> Option<SendFile> {
+            Pin::new(&mut **self).take_send_file()
+        }
     }
 
     impl MessageBody for Infallible {
@@ -197,6 +233,16 @@ mod foreign_impls {
         ) -> Poll<Option<Result<Bytes, Self::Error>>> {
             Pin::new(self.get_mut().as_mut()).poll_next(cx)
         }
+
+        #[inline]
+        fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+            Pin::new(self.get_mut().as_mut()).take_trailers()
+        }
+
+        #[inline]
+        fn take_send_file(self: Pin<&mut Self>) -> Option<SendFile> {
+            Pin::new(self.get_mut().as_mut()).take_send_file()
+        }
     }
 
     impl<T, B> MessageBody for Pin<T>
@@ -218,6 +264,16 @@ mod foreign_impls {
         ) -> Poll<Option<Result<Bytes, Self::Error>>> {
             self.get_mut().as_mut().poll_next(cx)
         }
+
+        #[inline]
+        fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+            self.get_mut().as_mut().take_trailers()
+        }
+
+        #[inline]
+        fn take_send_file(self: Pin<&mut Self>) -> Option<SendFile> {
+            self.get_mut().as_mut().take_send_file()
+        }
     }
 
     impl MessageBody for &'static [u8] {
@@ -525,6 +581,16 @@ where
         let Self { body, mapper } = self;
         body.try_into_bytes().map_err(|body| Self { body, mapper })
     }
+
+    #[inline]
+    fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+        self.project().body.take_trailers()
+    }
+
+    #[inline]
+    fn take_send_file(self: Pin<&mut Self>) -> Option<SendFile> {
+        self.project().body.take_send_file()
+    }
 }
 
 #[cfg(test)]
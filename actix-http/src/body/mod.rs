@@ -5,23 +5,33 @@
 // Though the spec kinda reads like "payload" is the possibly-transfer-encoded part of the message
 // and the "body" is the intended possibly-decoded version of that.
 
+mod body_chunks;
+mod body_reader;
 mod body_stream;
 mod boxed;
 mod either;
 mod message_body;
 mod none;
+mod reader_body;
+mod sendfile;
 mod size;
 mod sized_stream;
+mod trailers;
 mod utils;
 
 pub(crate) use self::message_body::MessageBodyMapErr;
 pub use self::{
+    body_chunks::BodyChunks,
+    body_reader::BodyReader,
     body_stream::BodyStream,
     boxed::BoxBody,
     either::EitherBody,
     message_body::MessageBody,
     none::None,
+    reader_body::ReaderBody,
+    sendfile::SendFile,
     size::BodySize,
     sized_stream::SizedStream,
+    trailers::TrailerBody,
     utils::{to_bytes, to_bytes_limited, BodyLimitExceeded},
 };
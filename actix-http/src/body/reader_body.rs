@@ -0,0 +1,114 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_codec::AsyncRead;
+use bytes::Bytes;
+use futures_core::Stream as _;
+use pin_project_lite::pin_project;
+use tokio_util::io::ReaderStream;
+
+use super::{BodySize, MessageBody};
+
+/// Default capacity of the read buffer.
+const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+pin_project! {
+    /// Body that streams the bytes read from an [`AsyncRead`].
+    ///
+    /// Bytes are read into a buffer of a fixed capacity, and each read is sent as a chunk. The size
+    /// of the body is not known up front, so responses are sent using chunked transfer encoding. To
+    /// send a reader of known length, wrap a [`ReaderStream`] in a
+    /// [`SizedStream`](super::SizedStream) instead.
+    ///
+    /// # Examples
+    /// ```
+    /// # use actix_http::body::{self, ReaderBody};
+    /// # actix_rt::System::new().block_on(async {
+    /// let body = ReaderBody::with_capacity(&b"hello world"[..], 4);
+    /// assert_eq!(body::to_bytes(body).await.unwrap(), "hello world");
+    /// # });
+    /// ```
+    pub struct ReaderBody<R> {
+        #[pin]
+        stream: ReaderStream<R>,
+    }
+}
+
+impl<R: AsyncRead> ReaderBody<R> {
+    /// Constructs a body that reads from `reader` using an 8 KiB buffer.
+    pub fn new(reader: R) -> Self {
+        Self::with_capacity(reader, DEFAULT_CAPACITY)
+    }
+
+    /// Constructs a body that reads from `reader` into a buffer of `capacity` bytes.
+    ///
+    /// Chunks are at most `capacity` bytes long.
+    pub fn with_capacity(reader: R, capacity: usize) -> Self {
+        Self {
+            stream: ReaderStream::with_capacity(reader, capacity),
+        }
+    }
+}
+
+impl<R: AsyncRead> MessageBody for ReaderBody<R> {
+    type Error = io::Error;
+
+    #[inline]
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        self.project().stream.poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_rt::pin;
+    use actix_utils::future::poll_fn;
+    use static_assertions::assert_impl_all;
+
+    use super::*;
+    use crate::body::to_bytes;
+
+    assert_impl_all!(ReaderBody<&'static [u8]>: MessageBody);
+
+    #[actix_rt::test]
+    async fn chunks_are_capped_at_capacity() {
+        let body = ReaderBody::with_capacity(&b"hello world"[..], 4);
+        assert_eq!(body.size(), BodySize::Stream);
+        pin!(body);
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = poll_fn(|cx| body.as_mut().poll_next(cx)).await {
+            chunks.push(chunk.unwrap());
+        }
+
+        assert_eq!(chunks, ["hell", "o wo", "rld"]);
+    }
+
+    #[actix_rt::test]
+    async fn read_error() {
+        struct Failing;
+
+        impl AsyncRead for Failing {
+            fn poll_read(
+                self: Pin<&mut Self>,
+                _cx: &mut Context<'_>,
+                _buf: &mut actix_codec::ReadBuf<'_>,
+            ) -> Poll<io::Result<()>> {
+                Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()))
+            }
+        }
+
+        let err = to_bytes(ReaderBody::new(Failing)).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+}
@@ -0,0 +1,256 @@
+use std::{
+    fs::File,
+    future::Future as _,
+    io,
+    path::Path,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+
+use actix_rt::task::{spawn_blocking, JoinHandle};
+use bytes::Bytes;
+
+use super::{BodySize, MessageBody};
+
+const CHUNK_SIZE: u64 = 65_536;
+
+/// Body that sends a region of a file.
+///
+/// On Linux, the HTTP/1 dispatcher copies the file straight to plaintext TCP connections using
+/// `sendfile(2)`, so the contents never pass through userspace. Everywhere else, including TLS and
+/// HTTP/2 connections and bodies wrapped by a compressing encoder, the file is read in chunks on
+/// the blocking thread pool and sent like any other body.
+///
+/// The file must be at least `offset + len` bytes long; a shorter file fails the body with an
+/// [`UnexpectedEof`](io::ErrorKind::UnexpectedEof) error once its end is reached.
+///
+/// # Examples
+/// ```no_run
+/// use actix_http::{body::SendFile, Response};
+///
+/// let body = SendFile::open("static/index.html")?;
+/// let res = Response::ok().set_body(body);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct SendFile {
+    file: Arc<File>,
+    offset: u64,
+    len: u64,
+    read: Option<JoinHandle<io::Result<Bytes>>>,
+}
+
+impl SendFile {
+    /// Constructs a body that sends `len` bytes of `file`, starting at `offset`.
+    pub fn new(file: File, offset: u64, len: u64) -> Self {
+        Self {
+            file: Arc::new(file),
+            offset,
+            len,
+            read: None,
+        }
+    }
+
+    /// Opens the file at `path` and constructs a body that sends all of it.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        Ok(Self::new(file, 0, len))
+    }
+
+    /// Returns the offset in the file of the next byte to send.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the number of bytes left to send.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if there are no bytes left to send.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Copies as much of the file to `io` as it accepts without blocking.
+    ///
+    /// Returns the number of bytes written.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn poll_send_to(
+        &mut self,
+        cx: &mut Context<'_>,
+        io: &actix_rt::net::TcpStream,
+    ) -> Poll<io::Result<usize>> {
+        use std::os::fd::AsRawFd as _;
+
+        loop {
+            ready!(io.poll_write_ready(cx))?;
+
+            let res = io.try_io(tokio::io::Interest::WRITABLE, || {
+                let mut offset = self.offset as libc::off_t;
+                let count = self.len.min(isize::MAX as u64) as usize;
+
+                // SAFETY: both descriptors are open for the duration of the call and `offset`
+                // points to a valid `off_t`
+                let sent = unsafe {
+                    libc::sendfile(io.as_raw_fd(), self.file.as_raw_fd(), &mut offset, count)
+                };
+
+                if sent < 0 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(sent as usize)
+                }
+            });
+
+            match res {
+                Ok(0) => return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into())),
+                Ok(sent) => {
+                    self.offset += sent as u64;
+                    self.len -= sent as u64;
+                    return Poll::Ready(Ok(sent));
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                Err(err) => return Poll::Ready(Err(err)),
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn poll_send_to(
+        &mut self,
+        _cx: &mut Context<'_>,
+        _io: &actix_rt::net::TcpStream,
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(Err(io::ErrorKind::Unsupported.into()))
+    }
+}
+
+impl MessageBody for SendFile {
+    type Error = io::Error;
+
+    fn size(&self) -> BodySize {
+        BodySize::Sized(self.len)
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.get_mut();
+
+        if this.read.is_none() {
+            if this.len == 0 {
+                return Poll::Ready(None);
+            }
+
+            let file = Arc::clone(&this.file);
+            let offset = this.offset;
+            let len = this.len.min(CHUNK_SIZE) as usize;
+
+            this.read = Some(spawn_blocking(move || read_chunk(&file, offset, len)));
+        }
+
+        let res = ready!(Pin::new(this.read.as_mut().unwrap()).poll(cx));
+        this.read = None;
+
+        let chunk = res.map_err(io::Error::other)??;
+        this.offset += chunk.len() as u64;
+        this.len -= chunk.len() as u64;
+
+        Poll::Ready(Some(Ok(chunk)))
+    }
+
+    fn take_send_file(self: Pin<&mut Self>) -> Option<SendFile> {
+        let this = self.get_mut();
+
+        if this.read.is_some() || this.len == 0 {
+            return None;
+        }
+
+        let file = SendFile {
+            file: Arc::clone(&this.file),
+            offset: this.offset,
+            len: this.len,
+            read: None,
+        };
+
+        this.len = 0;
+        Some(file)
+    }
+}
+
+/// Reads up to `len` bytes at `offset`, failing if the file ends first.
+fn read_chunk(file: &File, offset: u64, len: usize) -> io::Result<Bytes> {
+    let mut buf = vec![0; len];
+
+    #[cfg(unix)]
+    let n = std::os::unix::fs::FileExt::read_at(file, &mut buf, offset)?;
+    #[cfg(windows)]
+    let n = std::os::windows::fs::FileExt::seek_read(file, &mut buf, offset)?;
+
+    if n == 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    buf.truncate(n);
+    Ok(Bytes::from(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write as _,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+    use crate::body::to_bytes;
+
+    fn temp_file(contents: &[u8]) -> File {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!(
+            "actix-http-sendfile-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let mut file = File::create(&path).unwrap();
+        file.write_all(contents).unwrap();
+        let file = File::open(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        file
+    }
+
+    #[actix_rt::test]
+    async fn buffered_region() {
+        let contents = (0..200_000).map(|n| n as u8).collect::<Vec<_>>();
+        let body = SendFile::new(temp_file(&contents), 10, 150_000);
+        assert_eq!(body.size(), BodySize::Sized(150_000));
+
+        let bytes = to_bytes(body).await.unwrap();
+        assert_eq!(bytes, contents[10..150_010]);
+    }
+
+    #[actix_rt::test]
+    async fn truncated_file() {
+        let body = SendFile::new(temp_file(b"short"), 0, 10);
+        let err = to_bytes(body).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[actix_rt::test]
+    async fn take_send_file() {
+        let mut body = SendFile::new(temp_file(b"hello world"), 6, 5);
+
+        let file = Pin::new(&mut body).take_send_file().unwrap();
+        assert_eq!((file.offset(), file.len()), (6, 5));
+
+        assert!(body.is_empty());
+        assert!(Pin::new(&mut body).take_send_file().is_none());
+        assert_eq!(to_bytes(body).await.unwrap(), "");
+    }
+}
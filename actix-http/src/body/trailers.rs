@@ -0,0 +1,201 @@
+use std::{
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use pin_project_lite::pin_project;
+
+use super::{BodySize, MessageBody};
+use crate::header::HeaderMap;
+
+pin_project! {
+    /// Body wrapper that sends trailer fields after the wrapped body is complete.
+    ///
+    /// Trailers are sent as HTTP/2 trailing `HEADERS` frames or, for HTTP/1.1, as the trailer
+    /// section of a chunked body. To make sure chunked encoding is used, this wrapper always reports
+    /// a [`BodySize::Stream`] size unless the wrapped body has no content.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_http::{
+    ///     body::TrailerBody,
+    ///     header::{HeaderMap, HeaderName, HeaderValue},
+    /// };
+    ///
+    /// let mut trailers = HeaderMap::new();
+    /// trailers.insert(
+    ///     HeaderName::from_static("grpc-status"),
+    ///     HeaderValue::from_static("0"),
+    /// );
+    ///
+    /// let body = TrailerBody::new("hello world", trailers);
+    /// ```
+    pub struct TrailerBody<B> {
+        #[pin]
+        body: B,
+        trailers: Option<Box<dyn FnOnce() -> HeaderMap>>,
+    }
+}
+
+impl<B> TrailerBody<B>
+where
+    B: MessageBody,
+{
+    /// Wraps `body` so that `trailers` are sent after it.
+    pub fn new(body: B, trailers: HeaderMap) -> Self {
+        Self::from_fn(body, move || trailers)
+    }
+
+    /// Wraps `body` so that the trailers returned from `trailers_fn` are sent after it.
+    ///
+    /// The function is called once the wrapped body is complete, which makes it suitable for
+    /// trailers that depend on the body content, like checksums.
+    pub fn from_fn<F>(body: B, trailers_fn: F) -> Self
+    where
+        F: FnOnce() -> HeaderMap + 'static,
+    {
+        Self {
+            body,
+            trailers: Some(Box::new(trailers_fn)),
+        }
+    }
+
+    /// Returns a reference to the wrapped body.
+    pub fn get_ref(&self) -> &B {
+        &self.body
+    }
+}
+
+impl<B> fmt::Debug for TrailerBody<B>
+where
+    B: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrailerBody")
+            .field("body", &self.body)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<B> MessageBody for TrailerBody<B>
+where
+    B: MessageBody,
+{
+    type Error = B::Error;
+
+    #[inline]
+    fn size(&self) -> BodySize {
+        match self.body.size() {
+            BodySize::None => BodySize::None,
+            _ => BodySize::Stream,
+        }
+    }
+
+    #[inline]
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        self.project().body.poll_next(cx)
+    }
+
+    fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+        let this = self.project();
+
+        // trailers of the wrapped body (if any) are sent together with our own
+        let mut trailers = this.body.take_trailers().unwrap_or_default();
+
+        if let Some(trailers_fn) = this.trailers.take() {
+            for (name, value) in trailers_fn() {
+                trailers.append(name, value);
+            }
+        }
+
+        (!trailers.is_empty()).then_some(trailers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use actix_rt::pin;
+    use static_assertions::assert_impl_all;
+
+    use super::*;
+    use crate::{
+        body::{self, BoxBody, EitherBody},
+        header::{HeaderName, HeaderValue},
+    };
+
+    assert_impl_all!(TrailerBody<()>: MessageBody, fmt::Debug);
+
+    fn trailers(name: &'static str, value: &'static str) -> HeaderMap {
+        let mut trailers = HeaderMap::new();
+        trailers.insert(
+            HeaderName::from_static(name),
+            HeaderValue::from_static(value),
+        );
+        trailers
+    }
+
+    #[actix_rt::test]
+    async fn forces_streaming_size() {
+        assert_eq!(
+            TrailerBody::new("test", HeaderMap::new()).size(),
+            BodySize::Stream
+        );
+        assert_eq!(
+            TrailerBody::new(body::None::new(), HeaderMap::new()).size(),
+            BodySize::None
+        );
+    }
+
+    #[actix_rt::test]
+    async fn trailers_after_body() {
+        let seen = Rc::new(Cell::new(false));
+
+        let body = TrailerBody::from_fn("test", {
+            let seen = Rc::clone(&seen);
+            move || {
+                seen.set(true);
+                trailers("x-checksum", "abc")
+            }
+        });
+        pin!(body);
+
+        assert_eq!(
+            body::to_bytes(body.as_mut()).await.unwrap(),
+            Bytes::from_static(b"test")
+        );
+        assert!(!seen.get());
+
+        let trailers = body.as_mut().take_trailers().unwrap();
+        assert!(seen.get());
+        assert_eq!(trailers.get("x-checksum").unwrap(), "abc");
+
+        // trailers are only yielded once
+        assert!(body.as_mut().take_trailers().is_none());
+    }
+
+    #[actix_rt::test]
+    async fn trailers_through_combinators() {
+        let body = TrailerBody::new(Bytes::from_static(b"test"), trailers("grpc-status", "0"));
+        let body = TrailerBody::new(BoxBody::new(body), trailers("x-outer", "1"));
+        let mut body = EitherBody::<_, ()>::left(body).boxed();
+
+        body::to_bytes(&mut body).await.unwrap();
+
+        let trailers = Pin::new(&mut body).take_trailers().unwrap();
+        assert_eq!(trailers.get("grpc-status").unwrap(), "0");
+        assert_eq!(trailers.get("x-outer").unwrap(), "1");
+    }
+
+    #[actix_rt::test]
+    async fn empty_trailers_skipped() {
+        let mut body = TrailerBody::new("test", HeaderMap::new());
+        assert!(Pin::new(&mut body).take_trailers().is_none());
+    }
+}
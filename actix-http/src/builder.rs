@@ -1,13 +1,24 @@
-use std::{fmt, marker::PhantomData, net, rc::Rc, time::Duration};
+use std::{
+    error::Error as StdError, fmt, future::Future, marker::PhantomData, net, rc::Rc, time::Duration,
+};
 
 use actix_codec::Framed;
 use actix_service::{IntoServiceFactory, Service, ServiceFactory};
+use bytes::Bytes;
 
 use crate::{
     body::{BoxBody, MessageBody},
-    h1::{self, ExpectHandler, H1Service, UpgradeHandler},
+    config::{H2Settings, Shutdown},
+    date::DEFAULT_DATE_UPDATE_INTERVAL,
+    h1::{
+        self, ExpectHandler, H1Service, UpgradeHandler, MAX_BUFFER_SIZE, MAX_HEADERS,
+        PAYLOAD_BUFFER_SIZE, WRITE_HIGH_WATER_MARK, WRITE_LOW_WATER_MARK,
+    },
+    message::DEFAULT_POOL_SIZE,
     service::HttpService,
-    ConnectCallback, Extensions, KeepAlive, Request, Response, ServiceConfig,
+    CannedResponses, Clock, ConnectCallback, ConnectionObserver, DisconnectCallback,
+    DisconnectReason, EtagCache, Extensions, KeepAlive, Request, RequestHeadCallback, Response,
+    ServiceConfig, ServiceConfigBuilder,
 };
 
 /// An HTTP service builder.
@@ -17,11 +28,35 @@ pub struct HttpServiceBuilder<T, S, X = ExpectHandler, U = UpgradeHandler> {
     keep_alive: KeepAlive,
     client_request_timeout: Duration,
     client_disconnect_timeout: Duration,
+    read_header_timeout: Duration,
+    read_body_timeout: Duration,
+    min_body_rate: (u64, Duration),
+    write_timeout: Duration,
+    max_request_line_len: usize,
+    max_headers: usize,
+    max_header_bytes: usize,
+    max_chunk_size: u64,
+    payload_buffer_size: usize,
+    write_high_water_mark: usize,
+    write_low_water_mark: usize,
+    pipelined_requests: usize,
+    requests_per_poll: usize,
+    message_pool_size: usize,
+    h2: H2Settings,
+    observer: Option<Rc<dyn ConnectionObserver>>,
+    canned_responses: Option<Rc<CannedResponses>>,
+    on_request_head: Option<Rc<RequestHeadCallback>>,
+    etag_cache: Option<EtagCache>,
+    on_disconnect: Option<Rc<DisconnectCallback>>,
+    shutdown: Shutdown,
+    clock: Option<Rc<dyn Clock>>,
+    date_update_interval: Duration,
     secure: bool,
     local_addr: Option<net::SocketAddr>,
     expect: X,
     upgrade: Option<U>,
     on_connect_ext: Option<Rc<ConnectCallback<T>>>,
+    diversions: h1::Diversions<T>,
     _phantom: PhantomData<S>,
 }
 
@@ -38,6 +73,29 @@ where
             keep_alive: KeepAlive::default(),
             client_request_timeout: Duration::from_secs(5),
             client_disconnect_timeout: Duration::ZERO,
+            read_header_timeout: Duration::ZERO,
+            read_body_timeout: Duration::ZERO,
+            min_body_rate: (0, Duration::ZERO),
+            write_timeout: Duration::ZERO,
+            max_request_line_len: MAX_BUFFER_SIZE,
+            max_headers: MAX_HEADERS,
+            max_header_bytes: MAX_BUFFER_SIZE,
+            max_chunk_size: u64::MAX,
+            payload_buffer_size: PAYLOAD_BUFFER_SIZE,
+            write_high_water_mark: WRITE_HIGH_WATER_MARK,
+            write_low_water_mark: WRITE_LOW_WATER_MARK,
+            pipelined_requests: 0,
+            requests_per_poll: usize::MAX,
+            message_pool_size: DEFAULT_POOL_SIZE,
+            h2: H2Settings::default(),
+            observer: None,
+            canned_responses: None,
+            on_request_head: None,
+            etag_cache: None,
+            on_disconnect: None,
+            shutdown: Shutdown::default(),
+            clock: None,
+            date_update_interval: DEFAULT_DATE_UPDATE_INTERVAL,
             secure: false,
             local_addr: None,
 
@@ -45,6 +103,7 @@ where
             expect: ExpectHandler,
             upgrade: None,
             on_connect_ext: None,
+            diversions: h1::Diversions::default(),
             _phantom: PhantomData,
         }
     }
@@ -124,11 +183,460 @@ where
         self.client_disconnect_timeout(dur)
     }
 
+    /// Set read-header timeout (for every request).
+    ///
+    /// Defines a timeout for receiving the rest of a request head once the client has started
+    /// sending it. Unlike [`client_request_timeout`](Self::client_request_timeout), this applies to
+    /// every request on a keep-alive connection. The timer does not restart as more of the head
+    /// arrives, so clients that trickle headers are disconnected. If it expires while no response
+    /// is in progress, the connection is terminated with a `408 Request Timeout` response error;
+    /// otherwise, the connection is closed once in-flight responses finish.
+    ///
+    /// Only applies to HTTP/1 connections. A duration of zero disables the timeout.
+    ///
+    /// By default, the read-header timeout is disabled.
+    pub fn read_header_timeout(mut self, dur: Duration) -> Self {
+        self.read_header_timeout = dur;
+        self
+    }
+
+    /// Set read-body timeout.
+    ///
+    /// Defines the maximum time to wait for more data while a request body is being read. The
+    /// timer restarts whenever body data arrives. If it expires, the request payload yields a
+    /// timed-out I/O error and the connection is closed after the response.
+    ///
+    /// Only applies to HTTP/1 connections. A duration of zero disables the timeout.
+    ///
+    /// By default, the read-body timeout is disabled.
+    pub fn read_body_timeout(mut self, dur: Duration) -> Self {
+        self.read_body_timeout = dur;
+        self
+    }
+
+    /// Set minimum request body rate.
+    ///
+    /// Defines the minimum rate, in bytes per second, at which a request body must arrive while it
+    /// is being read, protecting against clients that trickle a body to hold a connection open.
+    /// Throughput is measured over consecutive windows of length `window`. If fewer than
+    /// `bytes_per_sec * window` bytes arrive in a window, the request payload yields a timed-out
+    /// I/O error and the connection is closed after the response. Windows in which the service
+    /// is not reading the body, and so applies backpressure, are not counted.
+    ///
+    /// Only applies to HTTP/1 connections. A rate or window of zero disables enforcement.
+    ///
+    /// By default, the minimum request body rate is disabled.
+    pub fn min_body_rate(mut self, bytes_per_sec: u64, window: Duration) -> Self {
+        self.min_body_rate = (bytes_per_sec, window);
+        self
+    }
+
+    /// Set response write timeout.
+    ///
+    /// Defines the maximum time for the client to accept buffered response data. The timer starts
+    /// when a write would block and restarts each time the write buffer is fully drained, so a
+    /// client that reads a streaming response too slowly is disconnected instead of holding the
+    /// connection open.
+    ///
+    /// Only applies to HTTP/1 connections. A duration of zero disables the timeout.
+    ///
+    /// By default, the write timeout is disabled.
+    pub fn write_timeout(mut self, dur: Duration) -> Self {
+        self.write_timeout = dur;
+        self
+    }
+
+    /// Set maximum request line length.
+    ///
+    /// Defines the maximum length, in bytes, of the request line (e.g., `GET /path HTTP/1.1`).
+    /// Requests with longer request lines are rejected with `431 Request Header Fields Too Large`.
+    ///
+    /// Only applies to HTTP/1 connections.
+    ///
+    /// By default, the request line is only limited by the maximum header size.
+    pub fn max_request_line_len(mut self, len: usize) -> Self {
+        self.max_request_line_len = len;
+        self
+    }
+
+    /// Set maximum header count.
+    ///
+    /// Defines the maximum number of headers in a request head. Requests with more headers are
+    /// rejected with `431 Request Header Fields Too Large`.
+    ///
+    /// Only applies to HTTP/1 connections.
+    ///
+    /// By default, 96 headers are allowed.
+    pub fn max_headers(mut self, count: usize) -> Self {
+        self.max_headers = count;
+        self
+    }
+
+    /// Set maximum header size.
+    ///
+    /// Defines the maximum size, in bytes, of a request head, including the request line. Requests
+    /// with larger heads are rejected with `431 Request Header Fields Too Large`.
+    ///
+    /// Only applies to HTTP/1 connections.
+    ///
+    /// By default, this is 128KiB.
+    pub fn max_header_bytes(mut self, size: usize) -> Self {
+        self.max_header_bytes = size;
+        self
+    }
+
+    /// Set maximum chunk size.
+    ///
+    /// Defines the maximum size, in bytes, of a single chunk of a chunked request body. Requests
+    /// with a larger chunk are failed with a payload error and the connection is closed.
+    ///
+    /// Only applies to HTTP/1 connections.
+    ///
+    /// By default, chunks are not limited.
+    pub fn max_chunk_size(mut self, size: u64) -> Self {
+        self.max_chunk_size = size;
+        self
+    }
+
+    /// Set request payload buffer size.
+    ///
+    /// Defines how many request body bytes are buffered for the service before reading from the
+    /// connection is paused until the service consumes them.
+    ///
+    /// Only applies to HTTP/1 connections.
+    ///
+    /// By default, this is 32KiB.
+    pub fn payload_buffer_size(mut self, size: usize) -> Self {
+        self.payload_buffer_size = size;
+        self
+    }
+
+    /// Set response write buffer high-water mark.
+    ///
+    /// Defines how many response bytes are buffered for writing before the dispatcher stops
+    /// polling the response body. Polling resumes once the client has received enough of the
+    /// buffer to bring it down to the [low-water mark](Self::write_low_water_mark), so a body
+    /// streamed to a slow client is paused instead of being buffered without bound.
+    ///
+    /// Only applies to HTTP/1 connections.
+    ///
+    /// By default, this is 32KiB.
+    pub fn write_high_water_mark(mut self, size: usize) -> Self {
+        self.write_high_water_mark = size;
+        self
+    }
+
+    /// Set response write buffer low-water mark.
+    ///
+    /// Defines the number of buffered response bytes at or below which polling of a paused
+    /// response body is resumed. Values at or above the high-water mark are treated as one byte
+    /// below it.
+    ///
+    /// Only applies to HTTP/1 connections.
+    ///
+    /// By default, this is 8KiB.
+    pub fn write_low_water_mark(mut self, size: usize) -> Self {
+        self.write_low_water_mark = size;
+        self
+    }
+
+    /// Set number of concurrently handled pipelined requests.
+    ///
+    /// Defines how many pipelined requests are passed to the service while an earlier response on
+    /// the same connection is still being produced or written. Responses are always written in the
+    /// order their requests were received.
+    ///
+    /// Only applies to HTTP/1 connections.
+    ///
+    /// By default, this is zero and pipelined requests are handled one at a time.
+    pub fn pipelined_requests(mut self, count: usize) -> Self {
+        self.pipelined_requests = count;
+        self
+    }
+
+    /// Set number of requests a connection handles before yielding to other connections.
+    ///
+    /// Bounds how many requests a single HTTP/1.1 connection passes to the service each time it is
+    /// polled, so that a client pipelining many requests can not hold up the other connections on
+    /// its worker. Remaining requests are handled when the connection is polled again.
+    ///
+    /// Only applies to HTTP/1 connections.
+    ///
+    /// By default, this is not limited.
+    pub fn requests_per_poll(mut self, count: usize) -> Self {
+        self.requests_per_poll = count;
+        self
+    }
+
+    /// Set number of pooled request and response objects.
+    ///
+    /// Defines how many released request and response heads, with their header maps, and request
+    /// and response extensions each worker thread keeps for reuse by later messages. Zero disables
+    /// pooling.
+    ///
+    /// By default, this is 128.
+    pub fn message_pool_size(mut self, size: usize) -> Self {
+        self.message_pool_size = size;
+        self
+    }
+
+    /// Set HTTP/2 initial stream window size.
+    ///
+    /// Defines the initial flow control window, in bytes, of each stream; i.e., how much request
+    /// body data a client may send on a stream before the service reads it. Larger windows
+    /// improve throughput on high-latency links at the cost of buffered memory.
+    ///
+    /// Only applies to HTTP/2 connections. By default, the `h2` crate default of 65,535 bytes
+    /// is used.
+    ///
+    /// # Panics
+    /// Panics if `size` is larger than 2<sup>31</sup> - 1.
+    pub fn h2_initial_window_size(mut self, size: u32) -> Self {
+        self.h2 = self.h2.initial_window_size(size);
+        self
+    }
+
+    /// Set HTTP/2 initial connection window size.
+    ///
+    /// Defines the initial flow control window, in bytes, shared by all streams of a connection.
+    ///
+    /// Only applies to HTTP/2 connections. By default, the `h2` crate default of 65,535 bytes
+    /// is used.
+    ///
+    /// # Panics
+    /// Panics if `size` is larger than 2<sup>31</sup> - 1.
+    pub fn h2_initial_connection_window_size(mut self, size: u32) -> Self {
+        self.h2 = self.h2.initial_connection_window_size(size);
+        self
+    }
+
+    /// Set HTTP/2 maximum concurrent streams.
+    ///
+    /// Defines the maximum number of streams a client may have open at once on a connection.
+    ///
+    /// Only applies to HTTP/2 connections. By default, the number of streams is not limited.
+    pub fn h2_max_concurrent_streams(mut self, max: u32) -> Self {
+        self.h2 = self.h2.max_concurrent_streams(max);
+        self
+    }
+
+    /// Set HTTP/2 maximum frame size.
+    ///
+    /// Defines the largest frame payload, in bytes, the server is willing to receive.
+    ///
+    /// Only applies to HTTP/2 connections. By default, the `h2` crate default of 16,384 bytes is
+    /// used.
+    ///
+    /// # Panics
+    /// Panics if `max` is not between 16,384 and 16,777,215.
+    pub fn h2_max_frame_size(mut self, max: u32) -> Self {
+        self.h2 = self.h2.max_frame_size(max);
+        self
+    }
+
+    /// Set HTTP/2 maximum header list size.
+    ///
+    /// Defines the maximum size, in bytes, of the decoded header list of a request. Requests with
+    /// larger header lists are rejected.
+    ///
+    /// Only applies to HTTP/2 connections. By default, the `h2` crate default of 16MiB is used.
+    pub fn h2_max_header_list_size(mut self, max: u32) -> Self {
+        self.h2 = self.h2.max_header_list_size(max);
+        self
+    }
+
+    /// Set HTTP/2 keep-alive PING interval.
+    ///
+    /// Defines how long a connection may go without receiving a new request before a PING frame is
+    /// sent to check that the client is still reachable. Connections that do not acknowledge the
+    /// PING within the [keep-alive timeout](Self::h2_keep_alive_timeout) are closed, so that
+    /// connections silently dropped by NATs or load balancers do not accumulate.
+    ///
+    /// Only applies to HTTP/2 connections. By default, the [keep-alive](Self::keep_alive) duration
+    /// is used and no PINGs are sent when keep-alive is disabled.
+    ///
+    /// # Panics
+    /// Panics if `interval` is zero.
+    pub fn h2_keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.h2 = self.h2.keep_alive_interval(interval);
+        self
+    }
+
+    /// Set HTTP/2 keep-alive PING timeout.
+    ///
+    /// Defines how long to wait for a PING acknowledgement before the connection is closed.
+    ///
+    /// Only applies to HTTP/2 connections. By default, the PING interval is used.
+    pub fn h2_keep_alive_timeout(mut self, timeout: Duration) -> Self {
+        self.h2 = self.h2.keep_alive_timeout(timeout);
+        self
+    }
+
+    /// Set clock for connection deadlines and `Date` headers.
+    ///
+    /// Embedded deployments can provide a coarser or cheaper monotonic clock, and tests can
+    /// provide a fixed one. See [`Clock`] for details.
+    ///
+    /// By default, [`SystemClock`](crate::SystemClock) is used.
+    pub fn clock<C: Clock>(mut self, clock: C) -> Self {
+        self.clock = Some(Rc::new(clock));
+        self
+    }
+
+    /// Set interval at which the cached date and time are refreshed from the clock.
+    ///
+    /// Connection deadlines and `Date` headers use the time cached at the last refresh, so a longer
+    /// interval reads the clock less often in exchange for coarser deadlines and `Date` values.
+    ///
+    /// By default, this is 500ms.
+    ///
+    /// # Panics
+    /// Panics if `interval` is zero.
+    pub fn date_update_interval(mut self, interval: Duration) -> Self {
+        assert!(!interval.is_zero(), "date update interval must be non-zero");
+        self.date_update_interval = interval;
+        self
+    }
+
+    /// Set connection observer.
+    ///
+    /// The observer receives lifecycle events, such as opened and closed connections, started and
+    /// finished requests, and transferred bytes, from both the HTTP/1 and HTTP/2 dispatchers. See
+    /// [`ConnectionObserver`] for details.
+    pub fn observer<O: ConnectionObserver>(mut self, observer: O) -> Self {
+        self.observer = Some(Rc::new(observer));
+        self
+    }
+
+    /// Set static responses that the dispatcher answers without calling the service.
+    ///
+    /// Matching requests are answered before the [request head callback](Self::on_request_head)
+    /// runs. See [`CannedResponses`] for details.
+    pub fn canned_responses(mut self, canned: CannedResponses) -> Self {
+        self.canned_responses = (!canned.is_empty()).then(|| Rc::new(canned));
+        self
+    }
+
+    /// Set cache of current entity tags that conditional requests are answered from.
+    ///
+    /// `GET` and `HEAD` requests for a resource in the cache whose `If-None-Match` header matches
+    /// its current entity tag are answered with `304 Not Modified`, after the
+    /// [request head callback](Self::on_request_head) runs and without calling the service. See
+    /// [`EtagCache`] for details.
+    pub fn etag_cache(mut self, etags: EtagCache) -> Self {
+        self.etag_cache = Some(etags);
+        self
+    }
+
+    /// Set callback to run once a request head has been parsed, before its payload is read.
+    ///
+    /// The callback classifies requests before the service is called. It can tag a request by
+    /// inserting into its [extensions](crate::HttpMessage::extensions_mut), reroute it by changing its
+    /// [head](Request::head_mut), e.g. its URI, or reject it by returning the response to send
+    /// instead. Rejecting is cheap: the service is not called and the request payload is never
+    /// read. On HTTP/1.1 connections, a rejected request that has a payload causes the connection
+    /// to be closed after the response has been sent.
+    ///
+    /// Applies to both HTTP/1 and HTTP/2 connections.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::convert::Infallible;
+    /// use actix_http::{header, HttpMessage as _, HttpService, Request, Response, StatusCode};
+    ///
+    /// // marker for requests that should be handled by the upload service
+    /// struct LargeUpload;
+    ///
+    /// # actix_rt::System::new().block_on(async {
+    /// HttpService::build()
+    ///     .on_request_head(|req: &mut Request| {
+    ///         let len = req
+    ///             .headers()
+    ///             .get(header::CONTENT_LENGTH)
+    ///             .and_then(|len| len.to_str().ok()?.parse::<u64>().ok());
+    ///
+    ///         match len {
+    ///             // bounce oversized uploads without reading them
+    ///             Some(len) if len > 1024 * 1024 * 1024 => {
+    ///                 Err(Response::new(StatusCode::PAYLOAD_TOO_LARGE))
+    ///             }
+    ///
+    ///             // tag large uploads so they can be diverted to dedicated handlers
+    ///             Some(len) if len > 1024 * 1024 => {
+    ///                 req.extensions_mut().insert(LargeUpload);
+    ///                 Ok(())
+    ///             }
+    ///
+    ///             _ => Ok(()),
+    ///         }
+    ///     })
+    ///     .finish(|req: Request| async move {
+    ///         let large = req.extensions().contains::<LargeUpload>();
+    ///         Ok::<_, Infallible>(Response::build(StatusCode::OK).body(format!("{large}")))
+    ///     })
+    ///     .tcp();
+    /// # })
+    /// ```
+    pub fn on_request_head<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut Request) -> Result<(), Response<BoxBody>> + 'static,
+    {
+        self.on_request_head = Some(Rc::new(f));
+        self
+    }
+
+    /// Set graceful shutdown signal.
+    ///
+    /// Once `signal` resolves, HTTP/2 connections are sent a GOAWAY frame with the last stream ID
+    /// they processed, stop accepting new streams, and are closed once their in-flight streams
+    /// complete. When used with `actix-server`, pass a future awaiting the signal returned by
+    /// `ServerBuilder::graceful_shutdown_signal()` so that connections drain within the server's
+    /// shutdown timeout instead of being dropped.
+    ///
+    /// The signal also notifies the [`GracefulShutdown`] handles in connection data, unless a
+    /// separate [session close signal](Self::session_close_signal) is set.
+    ///
+    /// The signal is shared by all connections of the service. By default, connections are not
+    /// notified of shutdowns.
+    ///
+    /// [`GracefulShutdown`]: crate::GracefulShutdown
+    pub fn shutdown_signal<F>(mut self, signal: F) -> Self
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        self.shutdown = self.shutdown.signal(signal);
+        self
+    }
+
+    /// Set signal that notifies long-lived sessions to close.
+    ///
+    /// Once `signal` resolves, the [`GracefulShutdown`] handles in connection data are notified,
+    /// so that sessions such as WebSockets can be closed with a notice to the client. Setting it
+    /// separates this step from the [shutdown signal](Self::shutdown_signal), which then only
+    /// drains connections, allowing servers to let in-flight requests complete before
+    /// long-lived sessions are closed.
+    ///
+    /// [`GracefulShutdown`]: crate::GracefulShutdown
+    pub fn session_close_signal<F>(mut self, signal: F) -> Self
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        self.shutdown = self.shutdown.session_signal(signal);
+        self
+    }
+
     /// Provide service for `EXPECT: 100-Continue` support.
     ///
     /// Service get called with request that contains `EXPECT` header.
     /// Service must return request in case of success, in that case
     /// request will be forwarded to main service.
+    ///
+    /// The request carries the client's [`peer_addr()`](Request::peer_addr) and the connection
+    /// data set by [on-connect callbacks](crate::HttpServiceBuilder::on_connect_ext) and TLS
+    /// acceptors, available through [`conn_data()`](Request::conn_data). This allows rejecting
+    /// uploads based on their headers and where they come from before the body is sent. On
+    /// error, the error response is sent in place of `100 Continue` and the connection is closed
+    /// without reading the body.
     pub fn expect<F, X1>(self, expect: F) -> HttpServiceBuilder<T, S, X1, U>
     where
         F: IntoServiceFactory<X1, Request>,
@@ -140,11 +648,35 @@ where
             keep_alive: self.keep_alive,
             client_request_timeout: self.client_request_timeout,
             client_disconnect_timeout: self.client_disconnect_timeout,
+            read_header_timeout: self.read_header_timeout,
+            read_body_timeout: self.read_body_timeout,
+            min_body_rate: self.min_body_rate,
+            write_timeout: self.write_timeout,
+            max_request_line_len: self.max_request_line_len,
+            max_headers: self.max_headers,
+            max_header_bytes: self.max_header_bytes,
+            max_chunk_size: self.max_chunk_size,
+            payload_buffer_size: self.payload_buffer_size,
+            write_high_water_mark: self.write_high_water_mark,
+            write_low_water_mark: self.write_low_water_mark,
+            pipelined_requests: self.pipelined_requests,
+            requests_per_poll: self.requests_per_poll,
+            message_pool_size: self.message_pool_size,
+            h2: self.h2,
+            observer: self.observer,
+            canned_responses: self.canned_responses,
+            on_request_head: self.on_request_head,
+            etag_cache: self.etag_cache,
+            on_disconnect: self.on_disconnect,
+            shutdown: self.shutdown,
+            clock: self.clock,
+            date_update_interval: self.date_update_interval,
             secure: self.secure,
             local_addr: self.local_addr,
             expect: expect.into_factory(),
             upgrade: self.upgrade,
             on_connect_ext: self.on_connect_ext,
+            diversions: self.diversions,
             _phantom: PhantomData,
         }
     }
@@ -153,6 +685,9 @@ where
     ///
     /// If service is provided then normal requests handling get halted
     /// and this service get called with original request and framed object.
+    ///
+    /// See [`h1::upgrade_fn()`](crate::h1::upgrade_fn) for implementing protocols on the upgraded
+    /// connection without handling the framed object.
     pub fn upgrade<F, U1>(self, upgrade: F) -> HttpServiceBuilder<T, S, X, U1>
     where
         F: IntoServiceFactory<U1, (Request, Framed<T, h1::Codec>)>,
@@ -164,15 +699,96 @@ where
             keep_alive: self.keep_alive,
             client_request_timeout: self.client_request_timeout,
             client_disconnect_timeout: self.client_disconnect_timeout,
+            read_header_timeout: self.read_header_timeout,
+            read_body_timeout: self.read_body_timeout,
+            min_body_rate: self.min_body_rate,
+            write_timeout: self.write_timeout,
+            max_request_line_len: self.max_request_line_len,
+            max_headers: self.max_headers,
+            max_header_bytes: self.max_header_bytes,
+            max_chunk_size: self.max_chunk_size,
+            payload_buffer_size: self.payload_buffer_size,
+            write_high_water_mark: self.write_high_water_mark,
+            write_low_water_mark: self.write_low_water_mark,
+            pipelined_requests: self.pipelined_requests,
+            requests_per_poll: self.requests_per_poll,
+            message_pool_size: self.message_pool_size,
+            h2: self.h2,
+            observer: self.observer,
+            canned_responses: self.canned_responses,
+            on_request_head: self.on_request_head,
+            etag_cache: self.etag_cache,
+            on_disconnect: self.on_disconnect,
+            shutdown: self.shutdown,
+            clock: self.clock,
+            date_update_interval: self.date_update_interval,
             secure: self.secure,
             local_addr: self.local_addr,
             expect: self.expect,
             upgrade: Some(upgrade.into_factory()),
             on_connect_ext: self.on_connect_ext,
+            diversions: self.diversions,
             _phantom: PhantomData,
         }
     }
 
+    /// Diverts HTTP/1 connections whose first bytes are `prefix` to a handler of another protocol.
+    ///
+    /// Before the first request on a connection is parsed, the bytes the client sent are compared
+    /// with the registered prefixes. Connections that start with a prefix are handed to its
+    /// handler, along with the client's address, instead of being served as HTTP. This lets
+    /// non-HTTP clients, such as HL7 MLLP senders or legacy health probes, share a port with
+    /// HTTP clients instead of having their connections closed with `400 Bad Request`.
+    ///
+    /// The handler is called with the connection's I/O as an [`Upgraded`](h1::Upgraded) stream,
+    /// whose first reads return the bytes that were already read, including the prefix. Errors
+    /// returned from the handler are logged by the dispatcher. Prefixes are checked in
+    /// registration order, and connections wait until they have sent enough bytes to tell whether
+    /// they match each prefix, so prefixes should not be the start of valid HTTP requests.
+    /// Registering a prefix again replaces its handler.
+    ///
+    /// Applies to HTTP/1 connections only, including those accepted by TLS finalizers once the
+    /// TLS handshake completes.
+    ///
+    /// # Panics
+    /// Panics if `prefix` is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::convert::Infallible;
+    /// use actix_http::{h1, HttpService, Request, Response};
+    /// use tokio::io::{AsyncBufReadExt as _, AsyncWriteExt as _, BufReader};
+    ///
+    /// # actix_rt::System::new().block_on(async {
+    /// HttpService::build()
+    ///     // HL7 MLLP frames start with a vertical tab and end with a file separator
+    ///     .divert(b"\x0b".as_slice(), |io: h1::Upgraded<_>, _peer_addr| async move {
+    ///         let mut io = BufReader::new(io);
+    ///         let mut frame = Vec::new();
+    ///
+    ///         while io.read_until(0x1c, &mut frame).await? > 0 {
+    ///             io.write_all(b"\x0bMSH|^~\\&|ACK\r\x1c\r").await?;
+    ///             io.flush().await?;
+    ///             frame.clear();
+    ///         }
+    ///
+    ///         Ok::<_, std::io::Error>(())
+    ///     })
+    ///     .finish(|_: Request| async { Ok::<_, Infallible>(Response::ok()) })
+    ///     .tcp();
+    /// # })
+    /// ```
+    pub fn divert<F, Fut, E>(mut self, prefix: impl Into<Bytes>, handler: F) -> Self
+    where
+        T: 'static,
+        F: Fn(h1::Upgraded<T>, Option<net::SocketAddr>) -> Fut + 'static,
+        Fut: Future<Output = Result<(), E>> + 'static,
+        E: Into<Box<dyn StdError>>,
+    {
+        self.diversions.insert(prefix.into(), handler);
+        self
+    }
+
     /// Sets the callback to be run on connection establishment.
     ///
     /// Has mutable access to a data container that will be merged into request extensions.
@@ -186,6 +802,107 @@ where
         self
     }
 
+    /// Sets the callback to be run once a connection has been closed.
+    ///
+    /// The callback receives the data container populated by the
+    /// [on-connect callback](Self::on_connect_ext), which is empty if none is set, and the reason
+    /// the connection was closed. It is called exactly once per connection, after the dispatcher
+    /// has stopped reading from it; in-flight HTTP/2 streams may still be completing. Connections
+    /// that are dropped without having been closed, such as when the server stops, are reported as
+    /// [`DisconnectReason::Aborted`].
+    ///
+    /// Applies to both HTTP/1 and HTTP/2 connections, including upgraded ones.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::convert::Infallible;
+    /// use std::time::Instant;
+    ///
+    /// use actix_http::{HttpService, Request, Response, StatusCode};
+    ///
+    /// struct ConnectedAt(Instant);
+    ///
+    /// # actix_rt::System::new().block_on(async {
+    /// HttpService::build()
+    ///     .on_connect_ext(|_, ext| {
+    ///         ext.insert(ConnectedAt(Instant::now()));
+    ///     })
+    ///     .on_disconnect(|ext, reason| {
+    ///         let connected_at = ext.get::<ConnectedAt>().unwrap();
+    ///         println!("session ended after {:?}: {reason:?}", connected_at.0.elapsed());
+    ///     })
+    ///     .finish(|_: Request| async move {
+    ///         Ok::<_, Infallible>(Response::build(StatusCode::OK).finish())
+    ///     })
+    ///     .tcp();
+    /// # })
+    /// ```
+    pub fn on_disconnect<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Extensions, DisconnectReason) + 'static,
+    {
+        self.on_disconnect = Some(Rc::new(f));
+        self
+    }
+
+    fn service_config(&self) -> ServiceConfig {
+        let mut builder = ServiceConfigBuilder::new()
+            .keep_alive(self.keep_alive)
+            .client_request_timeout(self.client_request_timeout)
+            .client_disconnect_timeout(self.client_disconnect_timeout)
+            .read_header_timeout(self.read_header_timeout)
+            .read_body_timeout(self.read_body_timeout)
+            .min_body_rate(self.min_body_rate.0, self.min_body_rate.1)
+            .write_timeout(self.write_timeout)
+            .max_request_line_len(self.max_request_line_len)
+            .max_headers(self.max_headers)
+            .max_header_bytes(self.max_header_bytes)
+            .max_chunk_size(self.max_chunk_size)
+            .payload_buffer_size(self.payload_buffer_size)
+            .write_high_water_mark(self.write_high_water_mark)
+            .write_low_water_mark(self.write_low_water_mark)
+            .pipelined_requests(self.pipelined_requests)
+            .requests_per_poll(self.requests_per_poll)
+            .message_pool_size(self.message_pool_size)
+            .h2_settings(self.h2)
+            .shutdown(self.shutdown.clone())
+            .date_update_interval(self.date_update_interval);
+
+        if let Some(ref clock) = self.clock {
+            builder = builder.clock(Rc::clone(clock));
+        }
+
+        if let Some(ref observer) = self.observer {
+            builder = builder.observer(Rc::clone(observer));
+        }
+
+        if let Some(ref canned) = self.canned_responses {
+            builder = builder.canned_response_set(Rc::clone(canned));
+        }
+
+        if let Some(ref callback) = self.on_request_head {
+            builder = builder.request_head_callback(Rc::clone(callback));
+        }
+
+        if let Some(ref etags) = self.etag_cache {
+            builder = builder.etag_cache(etags.clone());
+        }
+
+        if let Some(ref callback) = self.on_disconnect {
+            builder = builder.disconnect_callback(Rc::clone(callback));
+        }
+
+        if self.secure {
+            builder = builder.secure();
+        }
+
+        if let Some(addr) = self.local_addr {
+            builder = builder.local_addr(addr);
+        }
+
+        builder.build()
+    }
+
     /// Finish service configuration and create a service for the HTTP/1 protocol.
     pub fn h1<F, B>(self, service: F) -> H1Service<T, S, B, X, U>
     where
@@ -195,18 +912,13 @@ where
         S::InitError: fmt::Debug,
         S::Response: Into<Response<B>>,
     {
-        let cfg = ServiceConfig::new(
-            self.keep_alive,
-            self.client_request_timeout,
-            self.client_disconnect_timeout,
-            self.secure,
-            self.local_addr,
-        );
+        let cfg = self.service_config();
 
         H1Service::with_config(cfg, service.into_factory())
             .expect(self.expect)
             .upgrade(self.upgrade)
             .on_connect_ext(self.on_connect_ext)
+            .diversions((!self.diversions.is_empty()).then(|| Rc::new(self.diversions)))
     }
 
     /// Finish service configuration and create a service for the HTTP/2 protocol.
@@ -220,13 +932,7 @@ where
 
         B: MessageBody + 'static,
     {
-        let cfg = ServiceConfig::new(
-            self.keep_alive,
-            self.client_request_timeout,
-            self.client_disconnect_timeout,
-            self.secure,
-            self.local_addr,
-        );
+        let cfg = self.service_config();
 
         crate::h2::H2Service::with_config(cfg, service.into_factory())
             .on_connect_ext(self.on_connect_ext)
@@ -242,17 +948,12 @@ where
 
         B: MessageBody + 'static,
     {
-        let cfg = ServiceConfig::new(
-            self.keep_alive,
-            self.client_request_timeout,
-            self.client_disconnect_timeout,
-            self.secure,
-            self.local_addr,
-        );
+        let cfg = self.service_config();
 
         HttpService::with_config(cfg, service.into_factory())
             .expect(self.expect)
             .upgrade(self.upgrade)
             .on_connect_ext(self.on_connect_ext)
+            .diversions((!self.diversions.is_empty()).then(|| Rc::new(self.diversions)))
     }
 }
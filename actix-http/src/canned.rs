@@ -0,0 +1,177 @@
+//! Static responses served by the dispatcher.
+
+use std::collections::HashMap;
+
+use bytes::Bytes;
+
+use crate::{
+    body::BoxBody,
+    error::HttpError,
+    header::{self, HeaderMap, HeaderValue, TryIntoHeaderPair},
+    Method, Request, Response, StatusCode,
+};
+
+/// Static responses answered by the dispatcher without calling the service.
+///
+/// Canned responses are meant for high-volume requests with constant answers, such as load
+/// balancer health checks or `robots.txt`. `GET` and `HEAD` requests whose path exactly matches a
+/// registered path are answered as soon as their head has been parsed, before the
+/// [request head callback](crate::HttpServiceBuilder::on_request_head) and without calling the
+/// service, so they do not pass through any middleware. Responses are assembled once, when they
+/// are registered; answering a request only clones their headers and a handle to their body.
+///
+/// The query string is ignored when matching. Requests with other methods are passed to the
+/// service as usual.
+///
+/// Applies to both HTTP/1 and HTTP/2 connections.
+///
+/// # Examples
+/// ```
+/// # use std::convert::Infallible;
+/// use actix_http::{header, CannedResponses, HttpService, Response, StatusCode};
+///
+/// let canned = CannedResponses::new()
+///     .text("/healthz", "ok")
+///     .response(
+///         "/robots.txt",
+///         StatusCode::OK,
+///         [(header::CONTENT_TYPE, "text/plain"), (header::CACHE_CONTROL, "max-age=86400")],
+///         "User-agent: *\nDisallow: /\n",
+///     );
+///
+/// # actix_rt::System::new().block_on(async {
+/// HttpService::build()
+///     .canned_responses(canned)
+///     .finish(|_req| async { Ok::<_, Infallible>(Response::ok()) })
+///     .tcp();
+/// # })
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CannedResponses {
+    responses: HashMap<String, CannedResponse>,
+}
+
+#[derive(Debug, Clone)]
+struct CannedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl CannedResponses {
+    /// Constructs an empty set of canned responses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a response for requests to `path`.
+    ///
+    /// Replaces any response registered for `path` before.
+    ///
+    /// # Panics
+    /// Panics if a header can not be converted into a valid header name and value.
+    pub fn response<H>(
+        mut self,
+        path: impl Into<String>,
+        status: StatusCode,
+        headers: impl IntoIterator<Item = H>,
+        body: impl Into<Bytes>,
+    ) -> Self
+    where
+        H: TryIntoHeaderPair,
+    {
+        let mut map = HeaderMap::new();
+
+        for header in headers {
+            match header.try_into_pair() {
+                Ok((name, value)) => map.append(name, value),
+                Err(err) => {
+                    let err: HttpError = err.into();
+                    panic!("invalid canned response header: {err}");
+                }
+            }
+        }
+
+        self.responses.insert(
+            path.into(),
+            CannedResponse {
+                status,
+                headers: map,
+                body: body.into(),
+            },
+        );
+        self
+    }
+
+    /// Registers a `200 OK` plain text response for requests to `path`.
+    ///
+    /// Replaces any response registered for `path` before.
+    pub fn text(self, path: impl Into<String>, body: impl Into<Bytes>) -> Self {
+        self.response(
+            path,
+            StatusCode::OK,
+            [(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("text/plain; charset=utf-8"),
+            )],
+            body,
+        )
+    }
+
+    /// Returns true if no responses are registered.
+    pub fn is_empty(&self) -> bool {
+        self.responses.is_empty()
+    }
+
+    /// Returns the canned response for `req`, if there is one.
+    pub(crate) fn respond(&self, req: &Request) -> Option<Response<BoxBody>> {
+        if !matches!(*req.method(), Method::GET | Method::HEAD) {
+            return None;
+        }
+
+        let canned = self.responses.get(req.path())?;
+
+        let mut res = Response::with_body(canned.status, BoxBody::new(canned.body.clone()));
+        res.headers_mut().clone_from(&canned.headers);
+
+        Some(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::TestRequest;
+
+    #[test]
+    fn respond() {
+        let canned = CannedResponses::new().text("/healthz", "ok").response(
+            "/gone",
+            StatusCode::GONE,
+            [("x-reason", "retired")],
+            Bytes::new(),
+        );
+
+        let res = canned
+            .respond(&TestRequest::with_uri("/healthz?probe=1").finish())
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/plain; charset=utf-8"
+        );
+
+        let req = TestRequest::with_uri("/gone").method(Method::HEAD).finish();
+        let res = canned.respond(&req).unwrap();
+        assert_eq!(res.status(), StatusCode::GONE);
+        assert_eq!(res.headers().get("x-reason").unwrap(), "retired");
+
+        let req = TestRequest::with_uri("/healthz")
+            .method(Method::POST)
+            .finish();
+        assert!(canned.respond(&req).is_none());
+
+        let req = TestRequest::with_uri("/healthz/").finish();
+        assert!(canned.respond(&req).is_none());
+    }
+}
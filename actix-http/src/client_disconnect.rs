@@ -0,0 +1,142 @@
+//! Notification of clients closing their connection while a request is handled.
+
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+
+/// Handle that is notified when the client closes the connection a request was received on.
+///
+/// The HTTP/1 dispatcher places a `ClientDisconnect` handle in the extensions of each request
+/// before calling the service. When the dispatcher finds that the client has closed the
+/// connection, or that reading from it failed, all handles of the connection are notified.
+/// Services with long-running responses, such as long polling, can use this to stop waiting for
+/// a result the client will never receive.
+///
+/// The dispatcher keeps handling the request after the notification, in case the client only
+/// closed its sending side, but writing the response is likely to fail.
+///
+/// HTTP/2 requests do not receive a handle.
+///
+/// # Examples
+/// ```
+/// use actix_http::{ClientDisconnect, HttpMessage as _, Request, Response};
+///
+/// async fn handler(req: Request) -> Response<&'static str> {
+///     let disconnect = req.extensions().get::<ClientDisconnect>().cloned();
+///
+///     if let Some(disconnect) = disconnect {
+///         // stops waiting once the client is gone
+///         disconnect.await;
+///     }
+///
+///     Response::ok().set_body("done")
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ClientDisconnect {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    disconnected: Cell<bool>,
+    wakers: RefCell<Vec<Waker>>,
+}
+
+impl ClientDisconnect {
+    pub(crate) fn new() -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                disconnected: Cell::new(false),
+                wakers: RefCell::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Constructs a handle that is never notified.
+    ///
+    /// Stands in for the handle of requests whose connection is not watched, such as HTTP/2
+    /// requests, so that code waiting on a disconnect does not need to handle its absence.
+    pub fn never() -> Self {
+        Self::new()
+    }
+
+    /// Returns true if the client has closed the connection.
+    pub fn is_disconnected(&self) -> bool {
+        self.inner.disconnected.get()
+    }
+
+    /// Marks the connection as closed and wakes all tasks waiting on its handles.
+    pub(crate) fn notify(&self) {
+        if self.inner.disconnected.replace(true) {
+            return;
+        }
+
+        for waker in self.inner.wakers.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Future for ClientDisconnect {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.is_disconnected() {
+            return Poll::Ready(());
+        }
+
+        let mut wakers = self.inner.wakers.borrow_mut();
+
+        if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            wakers.push(cx.waker().clone());
+        }
+
+        Poll::Pending
+    }
+}
+
+impl fmt::Debug for ClientDisconnect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientDisconnect")
+            .field("disconnected", &self.is_disconnected())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{future::poll_fn, task::noop_waker};
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn notifies_all_handles() {
+        let disconnect = ClientDisconnect::new();
+        let mut handle = disconnect.clone();
+        assert!(!handle.is_disconnected());
+
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        assert!(Pin::new(&mut handle).poll(&mut cx).is_pending());
+
+        disconnect.notify();
+        assert!(handle.is_disconnected());
+        poll_fn(|cx| Pin::new(&mut handle).poll(cx)).await;
+        disconnect.clone().await;
+    }
+
+    #[test]
+    fn never_notified() {
+        let mut handle = ClientDisconnect::never();
+
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        assert!(Pin::new(&mut handle).poll(&mut cx).is_pending());
+        assert!(!handle.is_disconnected());
+    }
+}
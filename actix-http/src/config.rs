@@ -1,12 +1,27 @@
 use std::{
+    cell::Cell,
+    cmp, fmt,
+    future::Future,
     net,
     rc::Rc,
     time::{Duration, Instant},
 };
 
 use bytes::BytesMut;
+use futures_core::future::LocalBoxFuture;
+use tokio::sync::watch;
 
-use crate::{date::DateService, KeepAlive};
+use crate::{
+    body::BoxBody,
+    date::{Clock, DateService, SystemClock, DEFAULT_DATE_UPDATE_INTERVAL},
+    h1::{
+        MAX_BUFFER_SIZE, MAX_HEADERS, PAYLOAD_BUFFER_SIZE, WRITE_HIGH_WATER_MARK,
+        WRITE_LOW_WATER_MARK,
+    },
+    message::DEFAULT_POOL_SIZE,
+    CannedResponses, ConnectionObserver, DisconnectCallback, DisconnectReason, EtagCache,
+    Extensions, GracefulShutdown, KeepAlive, Request, RequestHeadCallback, Response,
+};
 
 /// HTTP service configuration.
 #[derive(Debug, Clone)]
@@ -17,6 +32,28 @@ struct Inner {
     keep_alive: KeepAlive,
     client_request_timeout: Duration,
     client_disconnect_timeout: Duration,
+    read_header_timeout: Duration,
+    read_body_timeout: Duration,
+    min_body_rate: u64,
+    min_body_rate_window: Duration,
+    write_timeout: Duration,
+    max_request_line_len: usize,
+    max_headers: usize,
+    max_header_bytes: usize,
+    max_chunk_size: u64,
+    payload_buffer_size: usize,
+    write_high_water_mark: usize,
+    write_low_water_mark: usize,
+    pipelined_requests: usize,
+    requests_per_poll: usize,
+    message_pool_size: usize,
+    h2: H2Settings,
+    observer: Observer,
+    canned_responses: Option<Rc<CannedResponses>>,
+    on_request_head: RequestHeadHook,
+    etag_cache: Option<EtagCache>,
+    on_disconnect: DisconnectHook,
+    shutdown: Shutdown,
     secure: bool,
     local_addr: Option<std::net::SocketAddr>,
     date_service: DateService,
@@ -24,18 +61,14 @@ struct Inner {
 
 impl Default for ServiceConfig {
     fn default() -> Self {
-        Self::new(
-            KeepAlive::default(),
-            Duration::from_secs(5),
-            Duration::ZERO,
-            false,
-            None,
-        )
+        ServiceConfigBuilder::default().build()
     }
 }
 
 impl ServiceConfig {
     /// Create instance of `ServiceConfig`.
+    ///
+    /// See [`ServiceConfigBuilder`] for setting further options.
     pub fn new(
         keep_alive: KeepAlive,
         client_request_timeout: Duration,
@@ -43,14 +76,20 @@ impl ServiceConfig {
         secure: bool,
         local_addr: Option<net::SocketAddr>,
     ) -> ServiceConfig {
-        ServiceConfig(Rc::new(Inner {
-            keep_alive: keep_alive.normalize(),
-            client_request_timeout,
-            client_disconnect_timeout,
-            secure,
-            local_addr,
-            date_service: DateService::new(),
-        }))
+        let mut builder = ServiceConfigBuilder::new()
+            .keep_alive(keep_alive)
+            .client_request_timeout(client_request_timeout)
+            .client_disconnect_timeout(client_disconnect_timeout);
+
+        if secure {
+            builder = builder.secure();
+        }
+
+        if let Some(addr) = local_addr {
+            builder = builder.local_addr(addr);
+        }
+
+        builder.build()
     }
 
     /// Returns `true` if connection is secure (i.e., using TLS / HTTPS).
@@ -100,6 +139,203 @@ impl ServiceConfig {
         (timeout != Duration::ZERO).then(|| self.now() + timeout)
     }
 
+    /// Creates a time object representing the deadline for the client to finish sending a request
+    /// head, once it has started sending it.
+    ///
+    /// Returns `None` if the read-header timeout is disabled.
+    pub fn read_header_deadline(&self) -> Option<Instant> {
+        let timeout = self.0.read_header_timeout;
+        (timeout != Duration::ZERO).then(|| self.now() + timeout)
+    }
+
+    /// Creates a time object representing the deadline for the client to send more of a request
+    /// body that is being read.
+    ///
+    /// Returns `None` if the read-body timeout is disabled.
+    pub fn read_body_deadline(&self) -> Option<Instant> {
+        let timeout = self.0.read_body_timeout;
+        (timeout != Duration::ZERO).then(|| self.now() + timeout)
+    }
+
+    /// Creates a time object representing the end of a request body throughput measurement window
+    /// starting now.
+    ///
+    /// Returns `None` if minimum request body rate enforcement is disabled.
+    pub fn min_body_rate_deadline(&self) -> Option<Instant> {
+        let window = self.0.min_body_rate_window;
+        (self.0.min_body_rate != 0 && window != Duration::ZERO).then(|| self.now() + window)
+    }
+
+    /// Returns the minimum number of request body bytes that must be received in each measurement
+    /// window.
+    pub(crate) fn min_body_window_bytes(&self) -> u64 {
+        let window = self.0.min_body_rate_window.as_secs_f64();
+        (self.0.min_body_rate as f64 * window) as u64
+    }
+
+    /// Creates a time object representing the deadline for the client to accept buffered response
+    /// data.
+    ///
+    /// Returns `None` if the write timeout is disabled.
+    pub fn write_deadline(&self) -> Option<Instant> {
+        let timeout = self.0.write_timeout;
+        (timeout != Duration::ZERO).then(|| self.now() + timeout)
+    }
+
+    /// Returns the maximum length, in bytes, of a request line.
+    #[inline]
+    pub fn max_request_line_len(&self) -> usize {
+        self.0.max_request_line_len
+    }
+
+    /// Returns the maximum number of headers in a request head.
+    #[inline]
+    pub fn max_headers(&self) -> usize {
+        self.0.max_headers
+    }
+
+    /// Returns the maximum size, in bytes, of a request head, including the request line.
+    #[inline]
+    pub fn max_header_bytes(&self) -> usize {
+        self.0.max_header_bytes
+    }
+
+    /// Returns the maximum size, in bytes, of a single chunk of a chunked request body.
+    #[inline]
+    pub fn max_chunk_size(&self) -> u64 {
+        self.0.max_chunk_size
+    }
+
+    /// Returns the number of request body bytes that are buffered before reading from the
+    /// connection is paused.
+    #[inline]
+    pub fn payload_buffer_size(&self) -> usize {
+        self.0.payload_buffer_size
+    }
+
+    /// Returns the number of response bytes that are buffered for writing before polling of the
+    /// response body is paused.
+    #[inline]
+    pub fn write_high_water_mark(&self) -> usize {
+        self.0.write_high_water_mark
+    }
+
+    /// Returns the number of buffered response bytes at or below which polling of a paused response
+    /// body is resumed.
+    #[inline]
+    pub fn write_low_water_mark(&self) -> usize {
+        self.0.write_low_water_mark
+    }
+
+    /// Returns the maximum number of pipelined HTTP/1.1 requests that are handled concurrently
+    /// with the request whose response is being written.
+    #[inline]
+    pub fn pipelined_requests(&self) -> usize {
+        self.0.pipelined_requests
+    }
+
+    /// Returns the maximum number of HTTP/1.1 requests that a connection passes to the service
+    /// before yielding to other connections on the worker.
+    #[inline]
+    pub fn requests_per_poll(&self) -> usize {
+        self.0.requests_per_poll
+    }
+
+    /// Returns the number of released request and response objects each worker thread keeps for
+    /// reuse.
+    #[inline]
+    pub fn message_pool_size(&self) -> usize {
+        self.0.message_pool_size
+    }
+
+    /// Returns the HTTP/2 connection settings.
+    #[allow(unused)] // used with `http2` feature flag
+    pub(crate) fn h2_settings(&self) -> &H2Settings {
+        &self.0.h2
+    }
+
+    /// Returns the idle duration after which a PING frame is sent on HTTP/2 connections, if
+    /// enabled.
+    ///
+    /// Falls back to the keep-alive duration when no HTTP/2 specific interval is set.
+    #[allow(unused)] // used with `http2` feature flag
+    pub(crate) fn h2_keep_alive_interval(&self) -> Option<Duration> {
+        self.0
+            .h2
+            .keep_alive_interval
+            .or_else(|| self.0.keep_alive.duration())
+    }
+
+    /// Returns the duration to wait for a PING acknowledgement before closing an HTTP/2
+    /// connection.
+    ///
+    /// Falls back to the PING interval when no timeout is set.
+    #[allow(unused)] // used with `http2` feature flag
+    pub(crate) fn h2_keep_alive_timeout(&self) -> Option<Duration> {
+        self.0
+            .h2
+            .keep_alive_timeout
+            .or_else(|| self.h2_keep_alive_interval())
+    }
+
+    /// Returns the connection observer, if one is configured.
+    pub(crate) fn observer(&self) -> Option<&Rc<dyn ConnectionObserver>> {
+        self.0.observer.0.as_ref()
+    }
+
+    /// Answers the request with a canned response, runs the request head callback, and answers
+    /// the request from the ETag cache, if they are configured.
+    ///
+    /// Returns the response to send instead of calling the service if the request is answered
+    /// or rejected.
+    pub(crate) fn on_request_head(&self, req: &mut Request) -> Result<(), Response<BoxBody>> {
+        if let Some(res) = self
+            .0
+            .canned_responses
+            .as_ref()
+            .and_then(|canned| canned.respond(req))
+        {
+            return Err(res);
+        }
+
+        if let Some(ref callback) = self.0.on_request_head.0 {
+            callback(req)?;
+        }
+
+        match self.0.etag_cache {
+            Some(ref etags) => etags.respond(req).map_or(Ok(()), Err),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the on-disconnect callback, if one is configured.
+    pub(crate) fn on_disconnect(&self) -> Option<&Rc<DisconnectCallback>> {
+        self.0.on_disconnect.0.as_ref()
+    }
+
+    /// Returns a future that resolves once a graceful shutdown has started, if a shutdown signal
+    /// is configured.
+    #[allow(unused)] // used with `http2` feature flag
+    pub(crate) fn shutdown_notified(&self) -> Option<LocalBoxFuture<'static, ()>> {
+        let inner = self.0.shutdown.0.as_ref()?;
+
+        if !inner.drains {
+            return None;
+        }
+
+        inner.await_signals();
+        let started = GracefulShutdown::new(inner.started.subscribe());
+        Some(Box::pin(started.notified()))
+    }
+
+    /// Returns a handle to the session close signal, if a shutdown or session close signal is
+    /// configured.
+    pub(crate) fn graceful_shutdown(&self) -> Option<GracefulShutdown> {
+        let inner = self.0.shutdown.0.as_ref()?;
+        inner.await_signals();
+        Some(GracefulShutdown::new(inner.closing.subscribe()))
+    }
+
     pub(crate) fn now(&self) -> Instant {
         self.0.date_service.now()
     }
@@ -130,6 +366,708 @@ impl ServiceConfig {
     }
 }
 
+/// A builder for creating a [`ServiceConfig`].
+///
+/// Defaults match [`ServiceConfig::default()`].
+#[derive(Debug, Clone)]
+pub struct ServiceConfigBuilder {
+    keep_alive: KeepAlive,
+    client_request_timeout: Duration,
+    client_disconnect_timeout: Duration,
+    read_header_timeout: Duration,
+    read_body_timeout: Duration,
+    min_body_rate: u64,
+    min_body_rate_window: Duration,
+    write_timeout: Duration,
+    max_request_line_len: usize,
+    max_headers: usize,
+    max_header_bytes: usize,
+    max_chunk_size: u64,
+    payload_buffer_size: usize,
+    write_high_water_mark: usize,
+    write_low_water_mark: usize,
+    pipelined_requests: usize,
+    requests_per_poll: usize,
+    message_pool_size: usize,
+    h2: H2Settings,
+    observer: Observer,
+    canned_responses: Option<Rc<CannedResponses>>,
+    on_request_head: RequestHeadHook,
+    etag_cache: Option<EtagCache>,
+    on_disconnect: DisconnectHook,
+    shutdown: Shutdown,
+    clock: ClockSource,
+    date_update_interval: Duration,
+    secure: bool,
+    local_addr: Option<net::SocketAddr>,
+}
+
+#[derive(Clone, Default)]
+struct Observer(Option<Rc<dyn ConnectionObserver>>);
+
+#[derive(Clone, Default)]
+struct RequestHeadHook(Option<Rc<RequestHeadCallback>>);
+
+#[derive(Clone, Default)]
+struct DisconnectHook(Option<Rc<DisconnectCallback>>);
+
+#[derive(Clone)]
+struct ClockSource(Rc<dyn Clock>);
+
+impl Default for ClockSource {
+    fn default() -> Self {
+        Self(Rc::new(SystemClock))
+    }
+}
+
+/// Graceful shutdown signals shared by all connections of a service.
+#[derive(Clone, Default)]
+pub(crate) struct Shutdown(Option<Rc<ShutdownInner>>);
+
+impl Shutdown {
+    /// Sets the signal that starts a graceful shutdown.
+    pub(crate) fn signal<F>(self, signal: F) -> Self
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        let session_signal = self.0.and_then(|inner| inner.session_signal.take());
+        Self::from_signals(Some(Box::pin(signal)), session_signal)
+    }
+
+    /// Sets the signal that notifies long-lived sessions to close.
+    pub(crate) fn session_signal<F>(self, signal: F) -> Self
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        let shutdown_signal = self.0.and_then(|inner| inner.signal.take());
+        Self::from_signals(shutdown_signal, Some(Box::pin(signal)))
+    }
+
+    fn from_signals(
+        signal: Option<LocalBoxFuture<'static, ()>>,
+        session_signal: Option<LocalBoxFuture<'static, ()>>,
+    ) -> Self {
+        Self(Some(Rc::new(ShutdownInner {
+            drains: signal.is_some(),
+            signal: Cell::new(signal),
+            session_signal: Cell::new(session_signal),
+            started: watch::channel(false).0,
+            closing: watch::channel(false).0,
+        })))
+    }
+}
+
+struct ShutdownInner {
+    /// True if a shutdown signal is set.
+    drains: bool,
+
+    /// Shutdown signal; taken once it is being awaited.
+    signal: Cell<Option<LocalBoxFuture<'static, ()>>>,
+
+    /// Session close signal; taken once it is being awaited.
+    session_signal: Cell<Option<LocalBoxFuture<'static, ()>>>,
+
+    /// Set to true once the shutdown signal has resolved.
+    started: watch::Sender<bool>,
+
+    /// Set to true once the session close signal has resolved or, without one, once the shutdown
+    /// signal has resolved.
+    closing: watch::Sender<bool>,
+}
+
+impl ShutdownInner {
+    /// Starts awaiting the signals, unless this was already done for a previous connection.
+    fn await_signals(self: &Rc<Self>) {
+        // both signals are taken by the first call
+        let session_signal = self.session_signal.take();
+        let separate = session_signal.is_some();
+
+        if let Some(signal) = self.signal.take() {
+            let inner = Rc::clone(self);
+
+            actix_rt::spawn(async move {
+                signal.await;
+                inner.started.send_replace(true);
+
+                if !separate {
+                    inner.closing.send_replace(true);
+                }
+            });
+        }
+
+        if let Some(signal) = session_signal {
+            let inner = Rc::clone(self);
+
+            actix_rt::spawn(async move {
+                signal.await;
+                inner.closing.send_replace(true);
+            });
+        }
+    }
+}
+
+const MAX_H2_WINDOW_SIZE: u32 = (1 << 31) - 1;
+const MIN_H2_FRAME_SIZE: u32 = 1 << 14;
+const MAX_H2_FRAME_SIZE: u32 = (1 << 24) - 1;
+
+/// HTTP/2 connection settings. Unset protocol settings use the `h2` crate defaults.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct H2Settings {
+    pub(crate) initial_window_size: Option<u32>,
+    pub(crate) initial_connection_window_size: Option<u32>,
+    pub(crate) max_concurrent_streams: Option<u32>,
+    pub(crate) max_frame_size: Option<u32>,
+    pub(crate) max_header_list_size: Option<u32>,
+    pub(crate) keep_alive_interval: Option<Duration>,
+    pub(crate) keep_alive_timeout: Option<Duration>,
+}
+
+impl H2Settings {
+    pub(crate) fn initial_window_size(mut self, size: u32) -> Self {
+        assert!(
+            size <= MAX_H2_WINDOW_SIZE,
+            "HTTP/2 window size must not exceed 2^31 - 1"
+        );
+        self.initial_window_size = Some(size);
+        self
+    }
+
+    pub(crate) fn initial_connection_window_size(mut self, size: u32) -> Self {
+        assert!(
+            size <= MAX_H2_WINDOW_SIZE,
+            "HTTP/2 window size must not exceed 2^31 - 1"
+        );
+        self.initial_connection_window_size = Some(size);
+        self
+    }
+
+    pub(crate) fn max_concurrent_streams(mut self, max: u32) -> Self {
+        self.max_concurrent_streams = Some(max);
+        self
+    }
+
+    pub(crate) fn max_frame_size(mut self, max: u32) -> Self {
+        assert!(
+            (MIN_H2_FRAME_SIZE..=MAX_H2_FRAME_SIZE).contains(&max),
+            "HTTP/2 max frame size must be between 2^14 and 2^24 - 1"
+        );
+        self.max_frame_size = Some(max);
+        self
+    }
+
+    pub(crate) fn max_header_list_size(mut self, max: u32) -> Self {
+        self.max_header_list_size = Some(max);
+        self
+    }
+
+    pub(crate) fn keep_alive_interval(mut self, interval: Duration) -> Self {
+        assert!(
+            !interval.is_zero(),
+            "HTTP/2 keep-alive interval must be non-zero"
+        );
+        self.keep_alive_interval = Some(interval);
+        self
+    }
+
+    pub(crate) fn keep_alive_timeout(mut self, timeout: Duration) -> Self {
+        self.keep_alive_timeout = Some(timeout);
+        self
+    }
+}
+
+impl fmt::Debug for ClockSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Clock")
+    }
+}
+
+impl fmt::Debug for Observer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(_) => f.write_str("Some(ConnectionObserver)"),
+            None => f.write_str("None"),
+        }
+    }
+}
+
+impl fmt::Debug for RequestHeadHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(_) => f.write_str("Some(RequestHeadCallback)"),
+            None => f.write_str("None"),
+        }
+    }
+}
+
+impl fmt::Debug for DisconnectHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(_) => f.write_str("Some(DisconnectCallback)"),
+            None => f.write_str("None"),
+        }
+    }
+}
+
+impl fmt::Debug for Shutdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(ref inner) => f
+                .debug_tuple("Some")
+                .field(&*inner.started.borrow())
+                .finish(),
+            None => f.write_str("None"),
+        }
+    }
+}
+
+impl Default for ServiceConfigBuilder {
+    fn default() -> Self {
+        Self {
+            keep_alive: KeepAlive::default(),
+            client_request_timeout: Duration::from_secs(5),
+            client_disconnect_timeout: Duration::ZERO,
+            read_header_timeout: Duration::ZERO,
+            read_body_timeout: Duration::ZERO,
+            min_body_rate: 0,
+            min_body_rate_window: Duration::ZERO,
+            write_timeout: Duration::ZERO,
+            max_request_line_len: MAX_BUFFER_SIZE,
+            max_headers: MAX_HEADERS,
+            max_header_bytes: MAX_BUFFER_SIZE,
+            max_chunk_size: u64::MAX,
+            payload_buffer_size: PAYLOAD_BUFFER_SIZE,
+            write_high_water_mark: WRITE_HIGH_WATER_MARK,
+            write_low_water_mark: WRITE_LOW_WATER_MARK,
+            pipelined_requests: 0,
+            requests_per_poll: usize::MAX,
+            message_pool_size: DEFAULT_POOL_SIZE,
+            h2: H2Settings::default(),
+            observer: Observer::default(),
+            canned_responses: None,
+            on_request_head: RequestHeadHook::default(),
+            etag_cache: None,
+            on_disconnect: DisconnectHook::default(),
+            shutdown: Shutdown::default(),
+            clock: ClockSource::default(),
+            date_update_interval: DEFAULT_DATE_UPDATE_INTERVAL,
+            secure: false,
+            local_addr: None,
+        }
+    }
+}
+
+impl ServiceConfigBuilder {
+    /// Constructs a new builder with default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets connection keep-alive setting.
+    pub fn keep_alive(mut self, keep_alive: KeepAlive) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// Sets the timeout for the client to send the head of its first request.
+    ///
+    /// A duration of zero disables the timeout.
+    pub fn client_request_timeout(mut self, dur: Duration) -> Self {
+        self.client_request_timeout = dur;
+        self
+    }
+
+    /// Sets the timeout for connection shutdown.
+    ///
+    /// A duration of zero disables the timeout.
+    pub fn client_disconnect_timeout(mut self, dur: Duration) -> Self {
+        self.client_disconnect_timeout = dur;
+        self
+    }
+
+    /// Sets the timeout for receiving the rest of a request head once the client has started
+    /// sending it. Unlike the client request timeout, this applies to every request on a
+    /// connection.
+    ///
+    /// A duration of zero disables the timeout.
+    pub fn read_header_timeout(mut self, dur: Duration) -> Self {
+        self.read_header_timeout = dur;
+        self
+    }
+
+    /// Sets the maximum time to wait for more request body data while a request body is being
+    /// read. The timer restarts whenever body data arrives.
+    ///
+    /// A duration of zero disables the timeout.
+    pub fn read_body_timeout(mut self, dur: Duration) -> Self {
+        self.read_body_timeout = dur;
+        self
+    }
+
+    /// Sets the minimum rate, in bytes per second, at which a request body must arrive while it is
+    /// being read.
+    ///
+    /// Throughput is measured over consecutive windows of the given length; if fewer than
+    /// `bytes_per_sec * window` bytes arrive in a window, reading the body fails. Windows in which
+    /// the service is not reading the body are not counted. A rate or window of zero disables
+    /// enforcement.
+    pub fn min_body_rate(mut self, bytes_per_sec: u64, window: Duration) -> Self {
+        self.min_body_rate = bytes_per_sec;
+        self.min_body_rate_window = window;
+        self
+    }
+
+    /// Sets the maximum time for the client to accept buffered response data. The timer restarts
+    /// each time the write buffer is fully drained.
+    ///
+    /// A duration of zero disables the timeout.
+    pub fn write_timeout(mut self, dur: Duration) -> Self {
+        self.write_timeout = dur;
+        self
+    }
+
+    /// Sets the maximum length, in bytes, of a request line (e.g., `GET /path HTTP/1.1`).
+    ///
+    /// Requests with longer request lines are rejected with `431 Request Header Fields Too Large`.
+    /// By default, the request line is only limited by the maximum head size.
+    pub fn max_request_line_len(mut self, len: usize) -> Self {
+        self.max_request_line_len = len;
+        self
+    }
+
+    /// Sets the maximum number of headers in a request head.
+    ///
+    /// Requests with more headers are rejected with `431 Request Header Fields Too Large`. By
+    /// default, 96 headers are allowed.
+    pub fn max_headers(mut self, count: usize) -> Self {
+        self.max_headers = count;
+        self
+    }
+
+    /// Sets the maximum size, in bytes, of a request head, including the request line.
+    ///
+    /// Requests with larger heads are rejected with `431 Request Header Fields Too Large`. By
+    /// default, this is 128KiB.
+    pub fn max_header_bytes(mut self, size: usize) -> Self {
+        self.max_header_bytes = size;
+        self
+    }
+
+    /// Sets the maximum size, in bytes, of a single chunk of a chunked request body.
+    ///
+    /// Requests with a larger chunk are failed with a payload error and the connection is closed.
+    /// This bounds the size of individual body items a service can receive. By default, chunks are
+    /// not limited.
+    pub fn max_chunk_size(mut self, size: u64) -> Self {
+        self.max_chunk_size = size;
+        self
+    }
+
+    /// Sets the number of request body bytes that are buffered before reading from the connection
+    /// is paused.
+    ///
+    /// Body bytes are queued for the service until it reads them. Once this many are queued, the
+    /// dispatcher stops reading from the connection until the service catches up, which bounds the
+    /// memory held by connections with slow consumers. By default, this is 32KiB.
+    pub fn payload_buffer_size(mut self, size: usize) -> Self {
+        self.payload_buffer_size = size;
+        self
+    }
+
+    /// Sets the number of response bytes that are buffered for writing before polling of the
+    /// response body is paused.
+    ///
+    /// Once a response body has filled the write buffer up to this size, the dispatcher stops
+    /// polling the body until the client has received enough of the buffer to bring it down to the
+    /// [low-water mark](Self::write_low_water_mark). This bounds the memory held by connections
+    /// with slow clients. By default, this is 32KiB.
+    pub fn write_high_water_mark(mut self, size: usize) -> Self {
+        self.write_high_water_mark = size;
+        self
+    }
+
+    /// Sets the number of buffered response bytes at or below which polling of a paused response
+    /// body is resumed.
+    ///
+    /// Values at or above the [high-water mark](Self::write_high_water_mark) are treated as one
+    /// byte below it. By default, this is 8KiB.
+    pub fn write_low_water_mark(mut self, size: usize) -> Self {
+        self.write_low_water_mark = size;
+        self
+    }
+
+    /// Sets the maximum number of pipelined HTTP/1.1 requests that are passed to the service
+    /// while an earlier response on the same connection is still being produced or written.
+    ///
+    /// Responses are always written in the order their requests were received. Requests with an
+    /// `Expect: 100-continue` header wait for all earlier responses. By default, this is zero and
+    /// pipelined requests are handled one at a time.
+    pub fn pipelined_requests(mut self, count: usize) -> Self {
+        self.pipelined_requests = count;
+        self
+    }
+
+    /// Sets the maximum number of HTTP/1.1 requests that a connection passes to the service before
+    /// yielding to other connections on the worker.
+    ///
+    /// A client that pipelines many requests with fast handlers can otherwise keep its connection
+    /// busy for as long as its requests are buffered, delaying all other connections on the same
+    /// worker. Once the limit is reached, the connection yields and continues with its remaining
+    /// requests when it is polled again. Zero is treated as one. By default, this is not limited.
+    pub fn requests_per_poll(mut self, count: usize) -> Self {
+        self.requests_per_poll = cmp::max(count, 1);
+        self
+    }
+
+    /// Sets the number of released request and response objects each worker thread keeps for
+    /// reuse by later messages.
+    ///
+    /// Request and response heads, including their header maps, and request and response
+    /// extensions are returned to thread-local pools once dropped; up to this many of each are
+    /// kept. Larger pools avoid allocations under highly concurrent load at the cost of memory held
+    /// by idle workers. Zero disables pooling. The pools are shared by all services on a thread, so
+    /// the size applies from the next connection accepted by a service using this config.
+    ///
+    /// By default, this is 128.
+    pub fn message_pool_size(mut self, size: usize) -> Self {
+        self.message_pool_size = size;
+        self
+    }
+
+    /// Sets the initial flow control window size, in bytes, of each stream for HTTP/2 connections.
+    ///
+    /// Values larger than 2<sup>31</sup> - 1 are not permitted. The `h2` default is 65,535 bytes.
+    ///
+    /// # Panics
+    /// Panics if the value is out of range.
+    pub fn h2_initial_window_size(mut self, size: u32) -> Self {
+        self.h2 = self.h2.initial_window_size(size);
+        self
+    }
+
+    /// Sets the initial flow control window size, in bytes, of each connection for HTTP/2
+    /// connections.
+    ///
+    /// Values larger than 2<sup>31</sup> - 1 are not permitted. The `h2` default is 65,535 bytes.
+    ///
+    /// # Panics
+    /// Panics if the value is out of range.
+    pub fn h2_initial_connection_window_size(mut self, size: u32) -> Self {
+        self.h2 = self.h2.initial_connection_window_size(size);
+        self
+    }
+
+    /// Sets the maximum number of concurrent streams a client may open on a connection for HTTP/2
+    /// connections.
+    ///
+    /// By default, the number of streams is not limited.
+    pub fn h2_max_concurrent_streams(mut self, max: u32) -> Self {
+        self.h2 = self.h2.max_concurrent_streams(max);
+        self
+    }
+
+    /// Sets the maximum frame payload size, in bytes, that the server accepts for HTTP/2
+    /// connections.
+    ///
+    /// Values must be between 16,384 and 16,777,215. The `h2` default is 16,384 bytes.
+    ///
+    /// # Panics
+    /// Panics if the value is out of range.
+    pub fn h2_max_frame_size(mut self, max: u32) -> Self {
+        self.h2 = self.h2.max_frame_size(max);
+        self
+    }
+
+    /// Sets the maximum size, in bytes, of a request header list that the server accepts for
+    /// HTTP/2 connections.
+    ///
+    /// The `h2` default is 16MiB.
+    pub fn h2_max_header_list_size(mut self, max: u32) -> Self {
+        self.h2 = self.h2.max_header_list_size(max);
+        self
+    }
+
+    /// Sets the duration an HTTP/2 connection may be idle before a PING frame is sent to check that
+    /// the client is still reachable.
+    ///
+    /// By default, the keep-alive duration is used. Setting an interval enables PINGs even when
+    /// keep-alive is disabled.
+    ///
+    /// # Panics
+    /// Panics if `interval` is zero.
+    pub fn h2_keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.h2 = self.h2.keep_alive_interval(interval);
+        self
+    }
+
+    /// Sets the duration to wait for a PING acknowledgement before an HTTP/2 connection is closed.
+    ///
+    /// By default, the PING interval is used.
+    pub fn h2_keep_alive_timeout(mut self, timeout: Duration) -> Self {
+        self.h2 = self.h2.keep_alive_timeout(timeout);
+        self
+    }
+
+    pub(crate) fn h2_settings(mut self, h2: H2Settings) -> Self {
+        self.h2 = h2;
+        self
+    }
+
+    /// Sets the observer that receives connection lifecycle events.
+    ///
+    /// See [`ConnectionObserver`] for the events reported.
+    pub fn observer(mut self, observer: Rc<dyn ConnectionObserver>) -> Self {
+        self.observer = Observer(Some(observer));
+        self
+    }
+
+    /// Sets the static responses that are answered without calling the service.
+    ///
+    /// See [`HttpServiceBuilder::canned_responses()`](crate::HttpServiceBuilder::canned_responses).
+    pub fn canned_responses(self, canned: CannedResponses) -> Self {
+        self.canned_response_set(Rc::new(canned))
+    }
+
+    pub(crate) fn canned_response_set(mut self, canned: Rc<CannedResponses>) -> Self {
+        self.canned_responses = (!canned.is_empty()).then_some(canned);
+        self
+    }
+
+    /// Sets the cache of current entity tags that conditional requests are answered from.
+    ///
+    /// See [`HttpServiceBuilder::etag_cache()`](crate::HttpServiceBuilder::etag_cache).
+    pub fn etag_cache(mut self, etags: EtagCache) -> Self {
+        self.etag_cache = Some(etags);
+        self
+    }
+
+    /// Sets the callback that runs once a request head has been parsed, before its payload is read.
+    ///
+    /// See [`HttpServiceBuilder::on_request_head()`](crate::HttpServiceBuilder::on_request_head).
+    pub fn on_request_head<F>(self, f: F) -> Self
+    where
+        F: Fn(&mut Request) -> Result<(), Response<BoxBody>> + 'static,
+    {
+        self.request_head_callback(Rc::new(f))
+    }
+
+    pub(crate) fn request_head_callback(mut self, callback: Rc<RequestHeadCallback>) -> Self {
+        self.on_request_head = RequestHeadHook(Some(callback));
+        self
+    }
+
+    /// Sets the callback that runs once a connection has been closed.
+    ///
+    /// See [`HttpServiceBuilder::on_disconnect()`](crate::HttpServiceBuilder::on_disconnect).
+    pub fn on_disconnect<F>(self, f: F) -> Self
+    where
+        F: Fn(&Extensions, DisconnectReason) + 'static,
+    {
+        self.disconnect_callback(Rc::new(f))
+    }
+
+    pub(crate) fn disconnect_callback(mut self, callback: Rc<DisconnectCallback>) -> Self {
+        self.on_disconnect = DisconnectHook(Some(callback));
+        self
+    }
+
+    /// Sets the signal that starts a graceful shutdown of connections.
+    ///
+    /// See [`HttpServiceBuilder::shutdown_signal()`](crate::HttpServiceBuilder::shutdown_signal).
+    pub fn shutdown_signal<F>(mut self, signal: F) -> Self
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        self.shutdown = self.shutdown.signal(signal);
+        self
+    }
+
+    /// Sets the signal that notifies long-lived sessions to close.
+    ///
+    /// See [`HttpServiceBuilder::session_close_signal()`].
+    ///
+    /// [`HttpServiceBuilder::session_close_signal()`]: crate::HttpServiceBuilder::session_close_signal
+    pub fn session_close_signal<F>(mut self, signal: F) -> Self
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        self.shutdown = self.shutdown.session_signal(signal);
+        self
+    }
+
+    pub(crate) fn shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Sets the clock that connection deadlines and `Date` headers are read from.
+    ///
+    /// See [`Clock`]. By default, [`SystemClock`] is used.
+    pub fn clock(mut self, clock: Rc<dyn Clock>) -> Self {
+        self.clock = ClockSource(clock);
+        self
+    }
+
+    /// Sets the interval at which the current time is read from the [clock](Self::clock).
+    ///
+    /// Connection deadlines and `Date` headers use the time cached at the last read, so a longer
+    /// interval reads the clock less often at the cost of coarser deadlines and `Date` values that
+    /// lag further behind. By default, this is 500ms.
+    ///
+    /// # Panics
+    /// Panics if `interval` is zero.
+    pub fn date_update_interval(mut self, interval: Duration) -> Self {
+        assert!(!interval.is_zero(), "date update interval must be non-zero");
+        self.date_update_interval = interval;
+        self
+    }
+
+    /// Sets connection secure state.
+    pub fn secure(mut self) -> Self {
+        self.secure = true;
+        self
+    }
+
+    /// Sets the local address that this service is bound to.
+    pub fn local_addr(mut self, addr: net::SocketAddr) -> Self {
+        self.local_addr = Some(addr);
+        self
+    }
+
+    /// Builds a [`ServiceConfig`] from this builder's options.
+    pub fn build(self) -> ServiceConfig {
+        ServiceConfig(Rc::new(Inner {
+            keep_alive: self.keep_alive.normalize(),
+            client_request_timeout: self.client_request_timeout,
+            client_disconnect_timeout: self.client_disconnect_timeout,
+            read_header_timeout: self.read_header_timeout,
+            read_body_timeout: self.read_body_timeout,
+            min_body_rate: self.min_body_rate,
+            min_body_rate_window: self.min_body_rate_window,
+            write_timeout: self.write_timeout,
+            max_request_line_len: self.max_request_line_len,
+            max_headers: self.max_headers,
+            max_header_bytes: self.max_header_bytes,
+            max_chunk_size: self.max_chunk_size,
+            payload_buffer_size: self.payload_buffer_size,
+            write_high_water_mark: self.write_high_water_mark,
+            write_low_water_mark: self.write_low_water_mark,
+            pipelined_requests: self.pipelined_requests,
+            requests_per_poll: self.requests_per_poll,
+            message_pool_size: self.message_pool_size,
+            h2: self.h2,
+            observer: self.observer,
+            canned_responses: self.canned_responses,
+            on_request_head: self.on_request_head,
+            etag_cache: self.etag_cache,
+            on_disconnect: self.on_disconnect,
+            shutdown: self.shutdown,
+            secure: self.secure,
+            local_addr: self.local_addr,
+            date_service: DateService::new(self.clock.0, self.date_update_interval),
+        }))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use actix_rt::{
@@ -176,7 +1114,10 @@ mod tests {
 
     #[actix_rt::test]
     async fn test_date_service_drop() {
-        let service = Rc::new(DateService::new());
+        let service = Rc::new(DateService::new(
+            Rc::new(SystemClock),
+            DEFAULT_DATE_UPDATE_INTERVAL,
+        ));
 
         // yield so date service have a chance to register the spawned timer update task.
         yield_now().await;
@@ -233,4 +1174,65 @@ mod tests {
         settings.write_date_header(&mut buf, true);
         assert!(memmem::find(&buf, b"Date:").is_some());
     }
+
+    #[actix_rt::test]
+    async fn test_custom_clock() {
+        struct FixedClock(Instant);
+
+        impl Clock for FixedClock {
+            fn now(&self) -> Instant {
+                self.0
+            }
+
+            fn system_time(&self) -> std::time::SystemTime {
+                std::time::SystemTime::UNIX_EPOCH
+            }
+        }
+
+        let start = Instant::now();
+        let settings = ServiceConfigBuilder::new()
+            .keep_alive(KeepAlive::Timeout(Duration::from_secs(5)))
+            .clock(Rc::new(FixedClock(start)))
+            .date_update_interval(Duration::from_millis(10))
+            .build();
+
+        sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(settings.now(), start);
+        assert_eq!(
+            settings.keep_alive_deadline(),
+            Some(start + Duration::from_secs(5))
+        );
+
+        let mut buf = BytesMut::new();
+        settings.write_date_header(&mut buf, false);
+        assert_eq!(buf, "date: Thu, 01 Jan 1970 00:00:00 GMT\r\n");
+    }
+
+    #[actix_rt::test]
+    async fn session_close_signal() {
+        let (drain_tx, drain_rx) = tokio::sync::oneshot::channel::<()>();
+        let (close_tx, close_rx) = tokio::sync::oneshot::channel::<()>();
+
+        let settings = ServiceConfigBuilder::new()
+            .session_close_signal(async {
+                let _ = close_rx.await;
+            })
+            .shutdown_signal(async {
+                let _ = drain_rx.await;
+            })
+            .build();
+
+        let mut drained = settings.shutdown_notified().unwrap();
+        let sessions = settings.graceful_shutdown().unwrap();
+
+        drain_tx.send(()).unwrap();
+        (&mut drained).await;
+        yield_now().await;
+        assert!(!sessions.is_started());
+
+        close_tx.send(()).unwrap();
+        sessions.notified().await;
+        assert!(sessions.is_started());
+    }
 }
@@ -10,6 +10,70 @@ use actix_rt::{task::JoinHandle, time::interval};
 /// "Thu, 01 Jan 1970 00:00:00 GMT".len()
 pub(crate) const DATE_VALUE_LENGTH: usize = 29;
 
+/// Default interval at which the cached date and time are refreshed.
+pub(crate) const DEFAULT_DATE_UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Source of the current time for a service's connection deadlines and `Date` headers.
+///
+/// The clock is read periodically by a task on each worker, and the cached values are used until
+/// the next read (see [`ServiceConfigBuilder::date_update_interval()`]). Deadlines computed from
+/// [`now()`](Self::now) are waited on with the runtime's timers, so they expire once the runtime's
+/// clock reaches them.
+///
+/// # Examples
+/// A clock that always reports the same time, e.g., for asserting `Date` headers in tests:
+/// ```
+/// use std::time::{Instant, SystemTime};
+///
+/// use actix_http::{Clock, ServiceConfigBuilder};
+///
+/// struct FixedClock(Instant, SystemTime);
+///
+/// impl Clock for FixedClock {
+///     fn now(&self) -> Instant {
+///         self.0
+///     }
+///
+///     fn system_time(&self) -> SystemTime {
+///         self.1
+///     }
+/// }
+///
+/// # actix_rt::System::new().block_on(async {
+/// let clock = FixedClock(Instant::now(), SystemTime::UNIX_EPOCH);
+/// let config = ServiceConfigBuilder::new()
+///     .clock(std::rc::Rc::new(clock))
+///     .build();
+/// # });
+/// ```
+///
+/// [`ServiceConfigBuilder::date_update_interval()`]:
+///     crate::ServiceConfigBuilder::date_update_interval
+pub trait Clock: 'static {
+    /// Returns the current monotonic time, from which connection deadlines are computed.
+    fn now(&self) -> Instant;
+
+    /// Returns the current wall-clock time, which is written in `Date` headers.
+    fn system_time(&self) -> SystemTime;
+}
+
+/// Clock that reads the runtime's monotonic clock and the system clock.
+///
+/// This is the default clock. Since it follows the runtime's clock, pausing and advancing time in
+/// Tokio's test utilities also applies to connection deadlines.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        actix_rt::time::Instant::now().into_std()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
 #[derive(Clone, Copy)]
 pub(crate) struct Date {
     pub(crate) bytes: [u8; DATE_VALUE_LENGTH],
@@ -17,19 +81,14 @@ pub(crate) struct Date {
 }
 
 impl Date {
-    fn new() -> Date {
+    fn new(time: SystemTime) -> Date {
         let mut date = Date {
             bytes: [0; DATE_VALUE_LENGTH],
             pos: 0,
         };
-        date.update();
+        write!(date, "{}", httpdate::HttpDate::from(time)).unwrap();
         date
     }
-
-    fn update(&mut self) {
-        self.pos = 0;
-        write!(self, "{}", httpdate::HttpDate::from(SystemTime::now())).unwrap();
-    }
 }
 
 impl fmt::Write for Date {
@@ -41,28 +100,27 @@ impl fmt::Write for Date {
     }
 }
 
-/// Service for update Date and Instant periodically at 500 millis interval.
+/// Service for update Date and Instant periodically from a clock.
 pub(crate) struct DateService {
     current: Rc<Cell<(Date, Instant)>>,
     handle: JoinHandle<()>,
 }
 
 impl DateService {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(clock: Rc<dyn Clock>, update_interval: Duration) -> Self {
         // shared date and timer for DateService and update async task.
-        let current = Rc::new(Cell::new((Date::new(), Instant::now())));
+        let current = Rc::new(Cell::new(read_clock(&*clock)));
         let current_clone = Rc::clone(&current);
-        // spawn an async task sleep for 500 millis and update current date/timer in a loop.
-        // handle is used to stop the task on DateService drop.
+        // spawn an async task sleep for the update interval and update current date/timer in a
+        // loop. handle is used to stop the task on DateService drop.
         let handle = actix_rt::spawn(async move {
             #[cfg(test)]
             let _notify = crate::notify_on_drop::NotifyOnDrop::new();
 
-            let mut interval = interval(Duration::from_millis(500));
+            let mut interval = interval(update_interval);
             loop {
-                let now = interval.tick().await;
-                let date = Date::new();
-                current_clone.set((date, now.into_std()));
+                interval.tick().await;
+                current_clone.set(read_clock(&*clock));
             }
         });
 
@@ -78,6 +136,10 @@ impl DateService {
     }
 }
 
+fn read_clock(clock: &dyn Clock) -> (Date, Instant) {
+    (Date::new(clock.system_time()), clock.now())
+}
+
 impl fmt::Debug for DateService {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("DateService").finish_non_exhaustive()
@@ -0,0 +1,170 @@
+//! `103 Early Hints` informational responses.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    fmt,
+    rc::Rc,
+    task::Waker,
+};
+
+use crate::header::{self, HeaderMap, HeaderValue};
+
+/// Handle for sending `103 Early Hints` informational responses ahead of the final response.
+///
+/// For requests where informational responses can be delivered, the HTTP/1.1 dispatcher places
+/// an `EarlyHints` handle in the request's extensions before calling the service. Hints sent
+/// through it are written to the connection, in order, while the service future is in progress.
+/// Once the final response has started, the handle is closed and further hints are rejected.
+///
+/// HTTP/1.0 requests and HTTP/2 connections do not receive a handle. Support for HTTP/2 is not
+/// implemented yet; it needs the `h2` crate to be able to send informational responses.
+///
+/// # Examples
+/// ```
+/// use actix_http::{header::HeaderValue, EarlyHints, HttpMessage as _, Request, Response};
+///
+/// async fn handler(req: Request) -> Response<&'static str> {
+///     if let Some(hints) = req.extensions().get::<EarlyHints>() {
+///         hints.link(HeaderValue::from_static("</style.css>; rel=preload; as=style"));
+///     }
+///
+///     // ...slow work...
+///
+///     Response::ok().set_body("done")
+/// }
+/// ```
+#[derive(Clone)]
+pub struct EarlyHints {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    queue: RefCell<VecDeque<HeaderMap>>,
+    waker: Cell<Option<Waker>>,
+    open: Cell<bool>,
+}
+
+impl EarlyHints {
+    pub(crate) fn new() -> Self {
+        Self::with_state(true)
+    }
+
+    /// Constructs a handle that rejects all hints.
+    ///
+    /// Useful as a fallback in contexts where the dispatcher did not provide a handle.
+    pub fn closed() -> Self {
+        Self::with_state(false)
+    }
+
+    fn with_state(open: bool) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                queue: RefCell::new(VecDeque::new()),
+                waker: Cell::new(None),
+                open: Cell::new(open),
+            }),
+        }
+    }
+
+    /// Returns true if hints sent through this handle can still be delivered.
+    pub fn is_open(&self) -> bool {
+        self.inner.open.get()
+    }
+
+    /// Queues a `103 Early Hints` response with the given headers.
+    ///
+    /// Returns false, without queueing anything, if the final response has already started or
+    /// the connection does not support informational responses.
+    pub fn send(&self, headers: HeaderMap) -> bool {
+        if !self.is_open() {
+            return false;
+        }
+
+        self.inner.queue.borrow_mut().push_back(headers);
+
+        if let Some(waker) = self.inner.waker.take() {
+            waker.wake();
+        }
+
+        true
+    }
+
+    /// Queues a `103 Early Hints` response containing a single `Link` header.
+    ///
+    /// See [`send`](Self::send) for the meaning of the return value.
+    pub fn link(&self, value: HeaderValue) -> bool {
+        let mut headers = HeaderMap::with_capacity(1);
+        headers.insert(header::LINK, value);
+        self.send(headers)
+    }
+
+    /// Takes the next queued hint, or registers the waker to be notified of new ones.
+    pub(crate) fn poll_next(&self, waker: &Waker) -> Option<HeaderMap> {
+        let next = self.inner.queue.borrow_mut().pop_front();
+
+        if next.is_none() {
+            self.inner.waker.set(Some(waker.clone()));
+        }
+
+        next
+    }
+
+    /// Takes the next queued hint without registering interest in new ones.
+    pub(crate) fn take_next(&self) -> Option<HeaderMap> {
+        self.inner.queue.borrow_mut().pop_front()
+    }
+
+    /// Rejects further hints and drops any that are still queued.
+    pub(crate) fn close(&self) {
+        self.inner.open.set(false);
+        self.inner.queue.borrow_mut().clear();
+        self.inner.waker.take();
+    }
+}
+
+impl fmt::Debug for EarlyHints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EarlyHints")
+            .field("open", &self.is_open())
+            .field("queued", &self.inner.queue.borrow().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::task::noop_waker;
+
+    use super::*;
+
+    #[test]
+    fn queue_in_order() {
+        let hints = EarlyHints::new();
+        let waker = noop_waker();
+
+        assert!(hints.link(HeaderValue::from_static("</a.css>; rel=preload")));
+        assert!(hints.link(HeaderValue::from_static("</b.js>; rel=preload")));
+
+        let first = hints.poll_next(&waker).unwrap();
+        assert_eq!(first.get(header::LINK).unwrap(), "</a.css>; rel=preload");
+        let second = hints.poll_next(&waker).unwrap();
+        assert_eq!(second.get(header::LINK).unwrap(), "</b.js>; rel=preload");
+        assert!(hints.poll_next(&waker).is_none());
+    }
+
+    #[test]
+    fn closed_rejects() {
+        let hints = EarlyHints::new();
+        let handle = hints.clone();
+
+        assert!(handle.link(HeaderValue::from_static("</a.css>; rel=preload")));
+        hints.close();
+
+        assert!(!handle.is_open());
+        assert!(!handle.link(HeaderValue::from_static("</b.js>; rel=preload")));
+        assert!(hints.take_next().is_none());
+
+        assert!(!EarlyHints::closed().send(HeaderMap::new()));
+    }
+}
@@ -5,6 +5,10 @@ use std::{
     future::Future,
     io::{self, Write as _},
     pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
 };
 
@@ -21,13 +25,161 @@ use zstd::stream::write::Encoder as ZstdEncoder;
 
 use super::Writer;
 use crate::{
-    body::{self, BodySize, MessageBody},
-    header::{self, ContentEncoding, HeaderValue, CONTENT_ENCODING},
+    body::{self, BodySize, MessageBody, SendFile},
+    header::{self, ContentEncoding, HeaderMap, HeaderValue, CONTENT_ENCODING},
     ResponseHead, StatusCode,
 };
 
 const MAX_CHUNK_SIZE_ENCODE_IN_PLACE: usize = 1024;
 
+/// Compression settings for a single response.
+///
+/// When inserted into a response's extensions, these override the compression middleware's
+/// defaults for that response only. Settings that are not given keep their defaults.
+///
+/// # Examples
+/// ```
+/// use actix_http::{encoding::CompressionOptions, header::ContentEncoding, Response};
+///
+/// let mut res = Response::ok();
+/// res.extensions_mut().insert(
+///     CompressionOptions::new()
+///         .encoding(ContentEncoding::Gzip)
+///         .level(9)
+///         .min_size(1024),
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressionOptions {
+    encoding: Option<ContentEncoding>,
+    level: Option<u32>,
+    min_size: u64,
+}
+
+impl CompressionOptions {
+    /// Constructs compression options that keep all of the defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the preferred content encoding.
+    ///
+    /// The encoding is only used if the client accepts it; otherwise the negotiated encoding is
+    /// used as normal. [`ContentEncoding::Identity`] disables compression of the response.
+    pub fn encoding(mut self, encoding: ContentEncoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// Sets the compression level.
+    ///
+    /// Higher levels produce smaller bodies but take longer to compress. The range of valid levels
+    /// depends on the encoding: 0–9 for gzip and deflate, 0–11 for brotli, and 1–22 for zstd.
+    /// Levels outside the range of the selected encoding are clamped to it.
+    ///
+    /// By default, gzip and deflate use level 1, and brotli and zstd use level 3.
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Sets the minimum size, in bytes, of bodies that are compressed.
+    ///
+    /// Bodies of a known size that are smaller than this are sent uncompressed. Streaming bodies
+    /// are always compressed. Defaults to 0.
+    pub fn min_size(mut self, min_size: u64) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    /// Returns the preferred content encoding, if set.
+    pub fn get_encoding(&self) -> Option<ContentEncoding> {
+        self.encoding
+    }
+}
+
+/// Compression statistics of a response body.
+///
+/// Obtained from [`Encoder::stats()`] before the body is sent; the counts are updated as the
+/// encoder produces the body. The compression middleware of `actix-web` inserts the statistics
+/// into response extensions, where middleware and loggers that outlive the body can read them once
+/// it has been sent. A [ratio](Self::ratio) close to or above 1 for a compressed response suggests
+/// the body was already compressed.
+///
+/// Clones share the same counts.
+#[derive(Debug, Clone)]
+pub struct CompressionStats {
+    inner: Arc<StatsInner>,
+}
+
+#[derive(Debug)]
+struct StatsInner {
+    encoding: ContentEncoding,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    complete: AtomicBool,
+}
+
+impl CompressionStats {
+    fn new(encoding: ContentEncoding) -> Self {
+        Self {
+            inner: Arc::new(StatsInner {
+                encoding,
+                bytes_in: AtomicU64::new(0),
+                bytes_out: AtomicU64::new(0),
+                complete: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Returns the encoding applied to the body.
+    ///
+    /// Returns [`ContentEncoding::Identity`] if the body is not compressed, e.g., because it was
+    /// too small or already had a `Content-Encoding`.
+    pub fn encoding(&self) -> ContentEncoding {
+        self.inner.encoding
+    }
+
+    /// Returns the number of bytes of the body read by the encoder so far.
+    pub fn bytes_in(&self) -> u64 {
+        self.inner.bytes_in.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of bytes produced by the encoder so far.
+    pub fn bytes_out(&self) -> u64 {
+        self.inner.bytes_out.load(Ordering::Relaxed)
+    }
+
+    /// Returns true if the whole body has been encoded.
+    pub fn is_complete(&self) -> bool {
+        self.inner.complete.load(Ordering::Relaxed)
+    }
+
+    /// Returns the ratio of bytes produced to bytes read, or `None` if no bytes were read.
+    pub fn ratio(&self) -> Option<f64> {
+        match self.bytes_in() {
+            0 => None,
+            bytes_in => Some(self.bytes_out() as f64 / bytes_in as f64),
+        }
+    }
+
+    fn record_in(&self, bytes: usize) {
+        self.inner
+            .bytes_in
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn record_out(&self, bytes: usize) {
+        self.inner
+            .bytes_out
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn record_complete(&self) {
+        self.inner.complete.store(true, Ordering::Relaxed);
+    }
+}
+
 pin_project! {
     pub struct Encoder<B> {
         #[pin]
@@ -35,6 +187,7 @@ pin_project! {
         encoder: Option<ContentEncoder>,
         fut: Option<JoinHandle<Result<ContentEncoder, io::Error>>>,
         eof: bool,
+        stats: CompressionStats,
     }
 }
 
@@ -47,6 +200,7 @@ impl<B: MessageBody> Encoder<B> {
             encoder: None,
             fut: None,
             eof: true,
+            stats: CompressionStats::new(ContentEncoding::Identity),
         }
     }
 
@@ -56,21 +210,36 @@ impl<B: MessageBody> Encoder<B> {
             encoder: None,
             fut: None,
             eof: true,
+            stats: CompressionStats::new(ContentEncoding::Identity),
         }
     }
 
     pub fn response(encoding: ContentEncoding, head: &mut ResponseHead, body: B) -> Self {
+        Self::response_with(encoding, head, body, &CompressionOptions::default())
+    }
+
+    /// Constructs an encoder for a response body using the level and size threshold of `options`.
+    ///
+    /// The preferred encoding of `options` is not considered; the caller negotiates `encoding`.
+    pub fn response_with(
+        encoding: ContentEncoding,
+        head: &mut ResponseHead,
+        body: B,
+        options: &CompressionOptions,
+    ) -> Self {
         // no need to compress empty bodies
-        match body.size() {
+        let too_small = match body.size() {
             BodySize::None => return Self::none(),
             BodySize::Sized(0) => return Self::empty(),
-            _ => {}
-        }
+            BodySize::Sized(len) => len < options.min_size,
+            BodySize::Stream => false,
+        };
 
         let should_encode = !(head.headers().contains_key(&CONTENT_ENCODING)
             || head.status == StatusCode::SWITCHING_PROTOCOLS
             || head.status == StatusCode::NO_CONTENT
-            || encoding == ContentEncoding::Identity);
+            || encoding == ContentEncoding::Identity
+            || too_small);
 
         let body = match body.try_into_bytes() {
             Ok(body) => EncoderBody::Full { body },
@@ -79,7 +248,7 @@ impl<B: MessageBody> Encoder<B> {
 
         if should_encode {
             // wrap body only if encoder is feature-enabled
-            if let Some(enc) = ContentEncoder::select(encoding) {
+            if let Some(enc) = ContentEncoder::select(encoding, options.level) {
                 update_head(encoding, head);
 
                 return Encoder {
@@ -87,6 +256,7 @@ impl<B: MessageBody> Encoder<B> {
                     encoder: Some(enc),
                     fut: None,
                     eof: false,
+                    stats: CompressionStats::new(encoding),
                 };
             }
         }
@@ -96,6 +266,88 @@ impl<B: MessageBody> Encoder<B> {
             encoder: None,
             fut: None,
             eof: false,
+            stats: CompressionStats::new(ContentEncoding::Identity),
+        }
+    }
+
+    /// Returns the compression statistics of the body.
+    pub fn stats(&self) -> CompressionStats {
+        self.stats.clone()
+    }
+
+    fn poll_encoded(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, EncoderError>>> {
+        let mut this = self.project();
+
+        loop {
+            if *this.eof {
+                return Poll::Ready(None);
+            }
+
+            if let Some(ref mut fut) = this.fut {
+                let mut encoder = ready!(Pin::new(fut).poll(cx))
+                    .map_err(|_| {
+                        EncoderError::Io(io::Error::new(
+                            io::ErrorKind::Other,
+                            "Blocking task was cancelled unexpectedly",
+                        ))
+                    })?
+                    .map_err(EncoderError::Io)?;
+
+                let chunk = encoder.take();
+                *this.encoder = Some(encoder);
+                this.fut.take();
+
+                if !chunk.is_empty() {
+                    return Poll::Ready(Some(Ok(chunk)));
+                }
+            }
+
+            let result = ready!(this.body.as_mut().poll_next(cx));
+
+            match result {
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+
+                Some(Ok(chunk)) => {
+                    this.stats.record_in(chunk.len());
+
+                    if let Some(mut encoder) = this.encoder.take() {
+                        if chunk.len() < MAX_CHUNK_SIZE_ENCODE_IN_PLACE {
+                            encoder.write(&chunk).map_err(EncoderError::Io)?;
+                            let chunk = encoder.take();
+                            *this.encoder = Some(encoder);
+
+                            if !chunk.is_empty() {
+                                return Poll::Ready(Some(Ok(chunk)));
+                            }
+                        } else {
+                            *this.fut = Some(spawn_blocking(move || {
+                                encoder.write(&chunk)?;
+                                Ok(encoder)
+                            }));
+                        }
+                    } else {
+                        return Poll::Ready(Some(Ok(chunk)));
+                    }
+                }
+
+                None => {
+                    if let Some(encoder) = this.encoder.take() {
+                        let chunk = encoder.finish().map_err(EncoderError::Io)?;
+
+                        if chunk.is_empty() {
+                            return Poll::Ready(None);
+                        } else {
+                            *this.eof = true;
+                            return Poll::Ready(Some(Ok(chunk)));
+                        }
+                    } else {
+                        return Poll::Ready(None);
+                    }
+                }
+            }
         }
     }
 }
@@ -152,6 +404,22 @@ where
             _ => Err(self),
         }
     }
+
+    #[inline]
+    fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+        match self.project() {
+            EncoderBodyProj::Stream { body } => body.take_trailers(),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn take_send_file(self: Pin<&mut Self>) -> Option<SendFile> {
+        match self.project() {
+            EncoderBodyProj::Stream { body } => body.take_send_file(),
+            _ => None,
+        }
+    }
 }
 
 impl<B> MessageBody for Encoder<B>
@@ -170,77 +438,18 @@ where
     }
 
     fn poll_next(
-        self: Pin<&mut Self>,
+        mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Bytes, Self::Error>>> {
-        let mut this = self.project();
-
-        loop {
-            if *this.eof {
-                return Poll::Ready(None);
-            }
-
-            if let Some(ref mut fut) = this.fut {
-                let mut encoder = ready!(Pin::new(fut).poll(cx))
-                    .map_err(|_| {
-                        EncoderError::Io(io::Error::new(
-                            io::ErrorKind::Other,
-                            "Blocking task was cancelled unexpectedly",
-                        ))
-                    })?
-                    .map_err(EncoderError::Io)?;
-
-                let chunk = encoder.take();
-                *this.encoder = Some(encoder);
-                this.fut.take();
-
-                if !chunk.is_empty() {
-                    return Poll::Ready(Some(Ok(chunk)));
-                }
-            }
-
-            let result = ready!(this.body.as_mut().poll_next(cx));
-
-            match result {
-                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+        let res = self.as_mut().poll_encoded(cx);
 
-                Some(Ok(chunk)) => {
-                    if let Some(mut encoder) = this.encoder.take() {
-                        if chunk.len() < MAX_CHUNK_SIZE_ENCODE_IN_PLACE {
-                            encoder.write(&chunk).map_err(EncoderError::Io)?;
-                            let chunk = encoder.take();
-                            *this.encoder = Some(encoder);
-
-                            if !chunk.is_empty() {
-                                return Poll::Ready(Some(Ok(chunk)));
-                            }
-                        } else {
-                            *this.fut = Some(spawn_blocking(move || {
-                                encoder.write(&chunk)?;
-                                Ok(encoder)
-                            }));
-                        }
-                    } else {
-                        return Poll::Ready(Some(Ok(chunk)));
-                    }
-                }
-
-                None => {
-                    if let Some(encoder) = this.encoder.take() {
-                        let chunk = encoder.finish().map_err(EncoderError::Io)?;
-
-                        if chunk.is_empty() {
-                            return Poll::Ready(None);
-                        } else {
-                            *this.eof = true;
-                            return Poll::Ready(Some(Ok(chunk)));
-                        }
-                    } else {
-                        return Poll::Ready(None);
-                    }
-                }
-            }
+        match res {
+            Poll::Ready(Some(Ok(ref chunk))) => self.stats.record_out(chunk.len()),
+            Poll::Ready(None) => self.stats.record_complete(),
+            _ => {}
         }
+
+        res
     }
 
     #[inline]
@@ -252,7 +461,12 @@ where
             Err(self)
         } else {
             match self.body.try_into_bytes() {
-                Ok(body) => Ok(body),
+                Ok(body) => {
+                    self.stats.record_in(body.len());
+                    self.stats.record_out(body.len());
+                    self.stats.record_complete();
+                    Ok(body)
+                }
                 Err(body) => {
                     self.body = body;
                     Err(self)
@@ -260,6 +474,21 @@ where
             }
         }
     }
+
+    #[inline]
+    fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+        self.project().body.take_trailers()
+    }
+
+    #[inline]
+    fn take_send_file(self: Pin<&mut Self>) -> Option<SendFile> {
+        // compressed bodies have to pass through the encoder
+        if self.encoder.is_some() {
+            return None;
+        }
+
+        self.project().body.take_send_file()
+    }
 }
 
 fn update_head(encoding: ContentEncoding, head: &mut ResponseHead) {
@@ -288,26 +517,30 @@ enum ContentEncoder {
 }
 
 impl ContentEncoder {
-    fn select(encoding: ContentEncoding) -> Option<Self> {
+    #[allow(unused_variables)] // only unused when no compress features enabled
+    fn select(encoding: ContentEncoding, level: Option<u32>) -> Option<Self> {
         match encoding {
             #[cfg(feature = "compress-gzip")]
             ContentEncoding::Deflate => Some(ContentEncoder::Deflate(ZlibEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                flate2_compression(level),
             ))),
 
             #[cfg(feature = "compress-gzip")]
             ContentEncoding::Gzip => Some(ContentEncoder::Gzip(GzEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                flate2_compression(level),
             ))),
 
             #[cfg(feature = "compress-brotli")]
-            ContentEncoding::Brotli => Some(ContentEncoder::Brotli(new_brotli_compressor())),
+            ContentEncoding::Brotli => Some(ContentEncoder::Brotli(new_brotli_compressor(
+                level.map_or(3, |level| level.min(11)),
+            ))),
 
             #[cfg(feature = "compress-zstd")]
             ContentEncoding::Zstd => {
-                let encoder = ZstdEncoder::new(Writer::new(), 3).ok()?;
+                let level = level.map_or(3, |level| level.clamp(1, 22) as i32);
+                let encoder = ZstdEncoder::new(Writer::new(), level).ok()?;
                 Some(ContentEncoder::Zstd(encoder))
             }
 
@@ -401,12 +634,20 @@ impl ContentEncoder {
     }
 }
 
+#[cfg(feature = "compress-gzip")]
+fn flate2_compression(level: Option<u32>) -> flate2::Compression {
+    match level {
+        Some(level) => flate2::Compression::new(level.min(9)),
+        None => flate2::Compression::fast(),
+    }
+}
+
 #[cfg(feature = "compress-brotli")]
-fn new_brotli_compressor() -> Box<brotli::CompressorWriter<Writer>> {
+fn new_brotli_compressor(quality: u32) -> Box<brotli::CompressorWriter<Writer>> {
     Box::new(brotli::CompressorWriter::new(
         Writer::new(),
         32 * 1024, // 32 KiB buffer
-        3,         // BROTLI_PARAM_QUALITY
+        quality,   // BROTLI_PARAM_QUALITY
         22,        // BROTLI_PARAM_LGWIN
     ))
 }
@@ -7,7 +7,10 @@ use bytes::{Bytes, BytesMut};
 mod decoder;
 mod encoder;
 
-pub use self::{decoder::Decoder, encoder::Encoder};
+pub use self::{
+    decoder::Decoder,
+    encoder::{CompressionOptions, CompressionStats, Encoder},
+};
 
 /// Special-purpose writer for streaming (de-)compression.
 ///
@@ -337,6 +337,11 @@ pub enum DispatchError {
     #[display("upgrade error")]
     Upgrade,
 
+    /// Error of the handler that a connection was diverted to by its first bytes.
+    #[display("diverted protocol error: {}", _0)]
+    #[from(skip)]
+    Diverted(Box<dyn StdError>),
+
     /// An `io::Error` that occurred while trying to read or write to a network stream.
     #[display("I/O error: {}", _0)]
     Io(io::Error),
@@ -358,6 +363,10 @@ pub enum DispatchError {
     #[display("connection shutdown timeout")]
     DisconnectTimeout,
 
+    /// The client did not accept buffered response data within the specified timeout.
+    #[display("response write timed out")]
+    WriteTimeout,
+
     /// Handler dropped payload before reading EOF.
     #[display("handler dropped payload before reading EOF")]
     HandlerDroppedPayload,
@@ -372,6 +381,7 @@ impl StdError for DispatchError {
         match self {
             DispatchError::Service(_res) => None,
             DispatchError::Body(err) => Some(&**err),
+            DispatchError::Diverted(err) => Some(&**err),
             DispatchError::Io(err) => Some(err),
             DispatchError::Parse(err) => Some(err),
 
@@ -0,0 +1,237 @@
+//! Shared cache of current entity tags, checked by the dispatcher.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    body::BoxBody,
+    header::{self, HeaderValue},
+    HttpMessage as _, Method, Request, Response, StatusCode,
+};
+
+/// Cache of the current entity tags of resources, used to answer conditional requests without
+/// calling the service.
+///
+/// When many clients poll the same resource with `If-None-Match`, most of their requests can be
+/// answered with `304 Not Modified` without running any handler. The application registers the
+/// current `ETag` of such a resource in the cache, and replaces or removes it whenever the
+/// resource changes. `GET` and `HEAD` requests whose path and query string exactly match a
+/// registered resource, and whose `If-None-Match` header matches its current entity tag, are then
+/// answered by the dispatcher as soon as their head has been parsed, after the
+/// [request head callback](crate::HttpServiceBuilder::on_request_head) has run. All other
+/// requests are passed to the service as usual.
+///
+/// Entity tags are compared with the weak comparison function, as required for `If-None-Match`.
+/// The `304` response only carries the `ETag` header. Because answered requests do not pass
+/// through any middleware, only register resources that every client may see.
+///
+/// Clones of a cache share its entries and it can be updated from any thread, so a single cache
+/// can serve all workers of a server while clones are kept by the handlers that update it.
+///
+/// Applies to both HTTP/1 and HTTP/2 connections.
+///
+/// # Examples
+/// ```
+/// # use std::convert::Infallible;
+/// use actix_http::{header::HeaderValue, EtagCache, HttpService, Response};
+///
+/// let etags = EtagCache::new();
+/// etags.insert("/schedule?clinic=3", HeaderValue::from_static("\"v42\""));
+///
+/// # actix_rt::System::new().block_on(async {
+/// HttpService::build()
+///     .etag_cache(etags.clone())
+///     .finish(|_req| async { Ok::<_, Infallible>(Response::ok()) })
+///     .tcp();
+/// # })
+/// ```
+#[derive(Clone, Default)]
+pub struct EtagCache {
+    entries: Arc<RwLock<HashMap<String, HeaderValue>>>,
+}
+
+impl EtagCache {
+    /// Constructs an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the current entity tag of `resource`, returning the previous one.
+    ///
+    /// The resource is a path, including the query string if there is one. The entity tag is the
+    /// value of the `ETag` header that the service sends for the resource, e.g. `"v42"` or
+    /// `W/"v42"`.
+    pub fn insert(&self, resource: impl Into<String>, etag: HeaderValue) -> Option<HeaderValue> {
+        self.entries.write().unwrap().insert(resource.into(), etag)
+    }
+
+    /// Removes `resource` from the cache, returning its entity tag.
+    ///
+    /// Requests for the resource are passed to the service until it is registered again.
+    pub fn remove(&self, resource: &str) -> Option<HeaderValue> {
+        self.entries.write().unwrap().remove(resource)
+    }
+
+    /// Returns the current entity tag of `resource`, if it is registered.
+    pub fn get(&self, resource: &str) -> Option<HeaderValue> {
+        self.entries.read().unwrap().get(resource).cloned()
+    }
+
+    /// Removes all resources from the cache.
+    pub fn clear(&self) {
+        self.entries.write().unwrap().clear();
+    }
+
+    /// Returns the number of registered resources.
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    /// Returns true if no resources are registered.
+    pub fn is_empty(&self) -> bool {
+        self.entries.read().unwrap().is_empty()
+    }
+
+    /// Returns a `304 Not Modified` response for `req`, if it is a conditional request for a
+    /// registered resource whose entity tag has not changed.
+    pub(crate) fn respond(&self, req: &Request) -> Option<Response<BoxBody>> {
+        if !matches!(*req.method(), Method::GET | Method::HEAD) {
+            return None;
+        }
+
+        let mut conditions = req.headers().get_all(header::IF_NONE_MATCH).peekable();
+        conditions.peek()?;
+
+        let uri = req.uri();
+        let resource = uri.path_and_query().map_or(uri.path(), |pq| pq.as_str());
+        let etag = self.entries.read().unwrap().get(resource)?.clone();
+
+        let fresh = conditions.any(|condition| {
+            condition
+                .to_str()
+                .is_ok_and(|condition| matches_any(condition, etag.as_bytes()))
+        });
+
+        if !fresh {
+            return None;
+        }
+
+        let mut res = Response::new(StatusCode::NOT_MODIFIED);
+        res.headers_mut().insert(header::ETAG, etag);
+        Some(res)
+    }
+}
+
+impl fmt::Debug for EtagCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EtagCache")
+            .field("resources", &self.len())
+            .finish()
+    }
+}
+
+/// Returns true if the `If-None-Match` list `condition` weakly matches `etag`.
+fn matches_any(condition: &str, etag: &[u8]) -> bool {
+    let etag = opaque_tag(etag);
+    let mut rest = condition.trim_start_matches([' ', '\t', ',']);
+
+    if rest.trim_end() == "*" {
+        return true;
+    }
+
+    while !rest.is_empty() {
+        // skip the weakness indicator, which weak comparison ignores
+        let tag = rest.strip_prefix("W/").unwrap_or(rest);
+
+        let Some(tag) = tag.strip_prefix('"') else {
+            return false;
+        };
+        let Some(end) = tag.find('"') else {
+            return false;
+        };
+
+        if &tag.as_bytes()[..end] == etag {
+            return true;
+        }
+
+        rest = tag[end + 1..].trim_start_matches([' ', '\t', ',']);
+    }
+
+    false
+}
+
+/// Returns the opaque tag of an entity tag, without the weakness indicator and quotes.
+fn opaque_tag(etag: &[u8]) -> &[u8] {
+    let etag = etag.strip_prefix(b"W/").unwrap_or(etag);
+
+    etag.strip_prefix(b"\"")
+        .and_then(|etag| etag.strip_suffix(b"\""))
+        .unwrap_or(etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::TestRequest;
+
+    #[test]
+    fn weak_comparison() {
+        assert!(matches_any("\"v1\"", b"\"v1\""));
+        assert!(matches_any("W/\"v1\"", b"\"v1\""));
+        assert!(matches_any("\"v1\"", b"W/\"v1\""));
+        assert!(matches_any("\"v0\", W/\"v1\"", b"\"v1\""));
+        assert!(matches_any("\"a,b\"", b"\"a,b\""));
+        assert!(matches_any("*", b"\"v1\""));
+
+        assert!(!matches_any("\"v0\"", b"\"v1\""));
+        assert!(!matches_any("\"v1", b"\"v1\""));
+        assert!(!matches_any("v1", b"\"v1\""));
+        assert!(!matches_any("", b"\"v1\""));
+    }
+
+    #[test]
+    fn respond() {
+        let etags = EtagCache::new();
+        etags.insert("/schedule?clinic=3", HeaderValue::from_static("\"v2\""));
+
+        let req = TestRequest::with_uri("/schedule?clinic=3")
+            .insert_header((header::IF_NONE_MATCH, "\"v1\", \"v2\""))
+            .finish();
+        let res = etags.respond(&req).unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers().get(header::ETAG).unwrap(), "\"v2\"");
+
+        // stale tag
+        let req = TestRequest::with_uri("/schedule?clinic=3")
+            .insert_header((header::IF_NONE_MATCH, "\"v1\""))
+            .finish();
+        assert!(etags.respond(&req).is_none());
+
+        // unconditional request
+        let req = TestRequest::with_uri("/schedule?clinic=3").finish();
+        assert!(etags.respond(&req).is_none());
+
+        // different query string
+        let req = TestRequest::with_uri("/schedule?clinic=4")
+            .insert_header((header::IF_NONE_MATCH, "\"v2\""))
+            .finish();
+        assert!(etags.respond(&req).is_none());
+
+        let req = TestRequest::with_uri("/schedule?clinic=3")
+            .method(Method::POST)
+            .insert_header((header::IF_NONE_MATCH, "\"v2\""))
+            .finish();
+        assert!(etags.respond(&req).is_none());
+
+        // clones share entries
+        etags.clone().remove("/schedule?clinic=3");
+        let req = TestRequest::with_uri("/schedule?clinic=3")
+            .insert_header((header::IF_NONE_MATCH, "\"v2\""))
+            .finish();
+        assert!(etags.respond(&req).is_none());
+        assert!(etags.is_empty());
+    }
+}
@@ -1,10 +1,18 @@
 use std::{
     any::{Any, TypeId},
+    cell::RefCell,
     collections::HashMap,
     fmt,
     hash::{BuildHasherDefault, Hasher},
+    mem, ops,
 };
 
+use crate::message::pool_size;
+
+thread_local! {
+    static EXTENSIONS_POOL: RefCell<Vec<Extensions>> = const { RefCell::new(Vec::new()) };
+}
+
 /// A hasher for `TypeId`s that takes advantage of its known characteristics.
 ///
 /// Author of `anymap` crate has done research on the topic:
@@ -151,6 +159,58 @@ impl fmt::Debug for Extensions {
     }
 }
 
+/// Extensions container of a request or response.
+///
+/// Taken from a thread-local pool when created and returned to it, cleared, when dropped, so the
+/// map's allocation is reused by later messages.
+pub(crate) struct PooledExtensions(RefCell<Extensions>);
+
+impl PooledExtensions {
+    pub(crate) fn new() -> Self {
+        let extensions = EXTENSIONS_POOL
+            .with(|pool| pool.borrow_mut().pop())
+            .unwrap_or_default();
+
+        Self(RefCell::new(extensions))
+    }
+}
+
+impl ops::Deref for PooledExtensions {
+    type Target = RefCell<Extensions>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl ops::DerefMut for PooledExtensions {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Drop for PooledExtensions {
+    fn drop(&mut self) {
+        let mut extensions = mem::take(self.0.get_mut());
+
+        // maps that never allocated are not worth keeping
+        if extensions.map.capacity() == 0 {
+            return;
+        }
+
+        extensions.clear();
+
+        // pool is unavailable while its thread-local is being destroyed
+        let _ = EXTENSIONS_POOL.try_with(|pool| {
+            let mut pool = pool.borrow_mut();
+
+            if pool.len() < pool_size() {
+                pool.push(extensions);
+            }
+        });
+    }
+}
+
 fn downcast_owned<T: 'static>(boxed: Box<dyn Any>) -> Option<T> {
     boxed.downcast().ok().map(|boxed| *boxed)
 }
@@ -159,6 +219,29 @@ fn downcast_owned<T: 'static>(boxed: Box<dyn Any>) -> Option<T> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn pooled_extensions_reuse_allocation() {
+        let ext = PooledExtensions::new();
+        ext.borrow_mut().insert(1u32);
+        let capacity = ext.borrow().map.capacity();
+        drop(ext);
+
+        let ext = PooledExtensions::new();
+        assert!(!ext.borrow().contains::<u32>());
+        assert_eq!(ext.borrow().map.capacity(), capacity);
+    }
+
+    #[test]
+    fn pooled_extensions_pool_size() {
+        crate::message::set_pool_size(0);
+
+        let ext = PooledExtensions::new();
+        ext.borrow_mut().insert(1u32);
+        drop(ext);
+
+        assert_eq!(PooledExtensions::new().borrow().map.capacity(), 0);
+    }
+
     #[test]
     fn test_remove() {
         let mut map = Extensions::new();
@@ -0,0 +1,70 @@
+//! Notification of graceful server shutdowns to long-lived connections.
+
+use std::{fmt, future::Future};
+
+use tokio::sync::watch;
+
+/// Handle that is notified when a graceful shutdown of the server starts.
+///
+/// When a [shutdown signal] is configured, a `GracefulShutdown` handle is placed in the
+/// connection data of each connection, available through [`conn_data()`]. Services that keep
+/// connections open for a long time, such as WebSocket sessions, can use it to end them cleanly
+/// before the server's shutdown timeout drops them.
+///
+/// Handles are notified when the shutdown signal resolves or, if a [session close signal] is
+/// configured, when that signal resolves.
+///
+/// # Examples
+/// ```
+/// use actix_http::{GracefulShutdown, Request, Response};
+///
+/// async fn handler(req: Request) -> Response<&'static str> {
+///     if let Some(shutdown) = req.conn_data::<GracefulShutdown>() {
+///         // resolves once the server starts shutting down
+///         shutdown.notified().await;
+///     }
+///
+///     Response::ok().set_body("bye")
+/// }
+/// ```
+///
+/// [shutdown signal]: crate::HttpServiceBuilder::shutdown_signal
+/// [session close signal]: crate::HttpServiceBuilder::session_close_signal
+/// [`conn_data()`]: crate::Request::conn_data
+#[derive(Clone)]
+pub struct GracefulShutdown {
+    started: watch::Receiver<bool>,
+}
+
+impl GracefulShutdown {
+    pub(crate) fn new(started: watch::Receiver<bool>) -> Self {
+        Self { started }
+    }
+
+    /// Returns true if a graceful shutdown has started and sessions should close.
+    pub fn is_started(&self) -> bool {
+        *self.started.borrow()
+    }
+
+    /// Returns a future that resolves once a graceful shutdown has started, or once the service
+    /// that created this handle is dropped.
+    pub fn notified(&self) -> impl Future<Output = ()> + 'static {
+        let mut started = self.started.clone();
+
+        async move {
+            while !*started.borrow_and_update() {
+                if started.changed().await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for GracefulShutdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GracefulShutdown")
+            .field("started", &self.is_started())
+            .finish()
+    }
+}
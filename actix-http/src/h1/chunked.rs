@@ -424,4 +424,28 @@ mod tests {
             .to_string()
             .contains("Invalid chunk size line: Size is too big"));
     }
+
+    #[test]
+    fn chunk_size_limit() {
+        let mut buf = BytesMut::from(
+            "GET / HTTP/1.1\r\n\
+            Transfer-Encoding: chunked\r\n\
+            \r\n\
+            4\r\n\
+            data\r\n\
+            10\r\n",
+        );
+
+        let mut reader = MessageDecoder::<Request>::default();
+        let (_msg, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let mut pl = pl.unwrap().max_chunk_size(4);
+
+        let chunk = pl.decode(&mut buf).unwrap().unwrap().chunk();
+        assert_eq!(chunk, Bytes::from_static(b"data"));
+
+        let err = pl.decode(&mut buf).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Invalid chunk size line: Size exceeds limit"));
+    }
 }
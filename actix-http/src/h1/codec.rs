@@ -1,4 +1,4 @@
-use std::{fmt, io};
+use std::{collections::VecDeque, fmt, io};
 
 use bitflags::bitflags;
 use bytes::BytesMut;
@@ -9,7 +9,10 @@ use super::{
     decoder::{self, PayloadDecoder, PayloadItem, PayloadType},
     encoder, Message, MessageType,
 };
-use crate::{body::BodySize, error::ParseError, ConnectionType, Request, Response, ServiceConfig};
+use crate::{
+    body::BodySize, error::ParseError, header::HeaderMap, ConnectionType, Request, Response,
+    ServiceConfig,
+};
 
 bitflags! {
     #[derive(Debug, Clone, Copy)]
@@ -20,6 +23,14 @@ bitflags! {
     }
 }
 
+/// Parts of a request head that determine how its response is encoded.
+#[derive(Debug, Clone, Copy)]
+struct RequestState {
+    head: bool,
+    version: Version,
+    conn_type: ConnectionType,
+}
+
 /// HTTP/1 Codec
 pub struct Codec {
     config: ServiceConfig,
@@ -27,6 +38,8 @@ pub struct Codec {
     payload: Option<PayloadDecoder>,
     version: Version,
     conn_type: ConnectionType,
+    // state of decoded requests that have not been responded to yet, oldest first
+    pending: VecDeque<RequestState>,
 
     // encoder part
     flags: Flags,
@@ -58,13 +71,20 @@ impl Codec {
             Flags::empty()
         };
 
+        let decoder = decoder::MessageDecoder::new(decoder::HeadLimits {
+            max_request_line_len: config.max_request_line_len(),
+            max_headers: config.max_headers(),
+            max_header_bytes: config.max_header_bytes(),
+        });
+
         Codec {
             config,
             flags,
-            decoder: decoder::MessageDecoder::default(),
+            decoder,
             payload: None,
             version: Version::HTTP_11,
             conn_type: ConnectionType::Close,
+            pending: VecDeque::new(),
             encoder: encoder::MessageEncoder::default(),
         }
     }
@@ -103,6 +123,41 @@ impl Codec {
     pub fn config(&self) -> &ServiceConfig {
         &self.config
     }
+
+    /// Returns the number of body bytes still expected for the response being written, if its
+    /// length is declared with `Content-Length`.
+    ///
+    /// Returns `Some(0)` when no body is sent at all, as is the case for responses to `HEAD`.
+    pub(crate) fn body_length(&self) -> Option<u64> {
+        self.encoder.body_length()
+    }
+
+    /// Returns true if every decoded request has been responded to, the last response has been
+    /// encoded in full, and no request body is left to decode.
+    ///
+    /// Request bodies of upgrade and `CONNECT` requests belong to the new protocol, so they are
+    /// never left to decode.
+    pub(crate) fn is_message_complete(&self) -> bool {
+        self.pending.is_empty()
+            && self.encoder.body_length() == Some(0)
+            && (self.payload.is_none() || self.flags.contains(Flags::STREAM))
+    }
+
+    /// Encodes the end of the response body followed by `trailers`.
+    ///
+    /// Trailers are dropped if the response is not using chunked transfer encoding.
+    pub(crate) fn encode_trailers(
+        &mut self,
+        trailers: &HeaderMap,
+        dst: &mut BytesMut,
+    ) -> io::Result<()> {
+        self.encoder.encode_trailers(trailers, dst)
+    }
+
+    /// Encodes a `103 Early Hints` informational response ahead of the final response.
+    pub(crate) fn encode_early_hints(&self, hints: &HeaderMap, dst: &mut BytesMut) {
+        encoder::encode_early_hints(hints, dst)
+    }
 }
 
 impl Decoder for Codec {
@@ -131,11 +186,19 @@ impl Decoder for Codec {
                 self.conn_type = ConnectionType::Close
             }
 
+            self.pending.push_back(RequestState {
+                head: self.flags.contains(Flags::HEAD),
+                version: self.version,
+                conn_type: self.conn_type,
+            });
+
+            let max_chunk_size = self.config.max_chunk_size();
+
             match payload {
                 PayloadType::None => self.payload = None,
-                PayloadType::Payload(pl) => self.payload = Some(pl),
+                PayloadType::Payload(pl) => self.payload = Some(pl.max_chunk_size(max_chunk_size)),
                 PayloadType::Stream(pl) => {
-                    self.payload = Some(pl);
+                    self.payload = Some(pl.max_chunk_size(max_chunk_size));
                     self.flags.insert(Flags::STREAM);
                 }
             }
@@ -156,6 +219,13 @@ impl Encoder<Message<(Response<()>, BodySize)>> for Codec {
     ) -> Result<(), Self::Error> {
         match item {
             Message::Item((mut res, length)) => {
+                // pipelined requests may have been decoded since this response's request was
+                if let Some(req) = self.pending.pop_front() {
+                    self.flags.set(Flags::HEAD, req.head);
+                    self.version = req.version;
+                    self.conn_type = req.conn_type;
+                }
+
                 // set response version
                 res.head_mut().version = self.version;
 
@@ -12,10 +12,45 @@ use super::chunked::ChunkedState;
 use crate::{error::ParseError, header::HeaderMap, ConnectionType, Request, ResponseHead};
 
 pub(crate) const MAX_BUFFER_SIZE: usize = 131_072;
-const MAX_HEADERS: usize = 96;
+pub(crate) const MAX_HEADERS: usize = 96;
+
+/// Size limits applied while decoding a message head.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HeadLimits {
+    /// Maximum length of the request line, excluding the line terminator.
+    pub(crate) max_request_line_len: usize,
+
+    /// Maximum number of headers.
+    pub(crate) max_headers: usize,
+
+    /// Maximum size of the head, including the start line.
+    pub(crate) max_header_bytes: usize,
+}
+
+impl Default for HeadLimits {
+    fn default() -> Self {
+        Self {
+            max_request_line_len: MAX_BUFFER_SIZE,
+            max_headers: MAX_HEADERS,
+            max_header_bytes: MAX_BUFFER_SIZE,
+        }
+    }
+}
 
 /// Incoming message decoder
-pub(crate) struct MessageDecoder<T: MessageType>(PhantomData<T>);
+pub(crate) struct MessageDecoder<T: MessageType> {
+    limits: HeadLimits,
+    _message: PhantomData<T>,
+}
+
+impl<T: MessageType> MessageDecoder<T> {
+    pub(crate) fn new(limits: HeadLimits) -> Self {
+        MessageDecoder {
+            limits,
+            _message: PhantomData,
+        }
+    }
+}
 
 #[derive(Debug)]
 /// Incoming request type
@@ -27,7 +62,7 @@ pub(crate) enum PayloadType {
 
 impl<T: MessageType> Default for MessageDecoder<T> {
     fn default() -> Self {
-        MessageDecoder(PhantomData)
+        MessageDecoder::new(HeadLimits::default())
     }
 }
 
@@ -36,7 +71,7 @@ impl<T: MessageType> Decoder for MessageDecoder<T> {
     type Error = ParseError;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        T::decode(src)
+        T::decode(src, &self.limits)
     }
 }
 
@@ -70,7 +105,10 @@ pub(crate) trait MessageType: Sized {
 
     fn headers_mut(&mut self) -> &mut HeaderMap;
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError>;
+    fn decode(
+        src: &mut BytesMut,
+        limits: &HeadLimits,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError>;
 
     fn set_headers(
         &mut self,
@@ -228,22 +266,28 @@ impl MessageType for Request {
         &mut self.head_mut().headers
     }
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError> {
-        let mut headers: [HeaderIndex; MAX_HEADERS] = EMPTY_HEADER_INDEX_ARRAY;
+    fn decode(
+        src: &mut BytesMut,
+        limits: &HeadLimits,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError> {
+        let mut header_buf = HeaderBuf::new(limits.max_headers);
+        let headers = header_buf.indices();
 
         let (len, method, uri, ver, h_len) = {
-            // SAFETY:
-            // Create an uninitialized array of `MaybeUninit`. The `assume_init` is safe because the
-            // type we are claiming to have initialized here is a bunch of `MaybeUninit`s, which
-            // do not require initialization.
-            let mut parsed = unsafe {
-                MaybeUninit::<[MaybeUninit<httparse::Header<'_>>; MAX_HEADERS]>::uninit()
-                    .assume_init()
-            };
-
+            let mut parsed_buf = ParsedHeaderBuf::new(limits.max_headers);
             let mut req = httparse::Request::new(&mut []);
 
-            match req.parse_with_uninit_headers(src, &mut parsed)? {
+            if request_line_len(src) > limits.max_request_line_len {
+                trace!("request line exceeds configured maximum length, closing");
+                return Err(ParseError::TooLarge);
+            }
+
+            match req.parse_with_uninit_headers(src, parsed_buf.headers())? {
+                httparse::Status::Complete(len) if len > limits.max_header_bytes => {
+                    trace!("request head exceeds configured maximum size, closing");
+                    return Err(ParseError::TooLarge);
+                }
+
                 httparse::Status::Complete(len) => {
                     let method = Method::from_bytes(req.method.unwrap().as_bytes())
                         .map_err(|_| ParseError::Method)?;
@@ -253,14 +297,14 @@ impl MessageType for Request {
                     } else {
                         Version::HTTP_10
                     };
-                    HeaderIndex::record(src, req.headers, &mut headers);
+                    HeaderIndex::record(src, req.headers, headers);
 
                     (len, method, uri, version, req.headers.len())
                 }
 
                 httparse::Status::Partial => {
-                    return if src.len() >= MAX_BUFFER_SIZE {
-                        trace!("MAX_BUFFER_SIZE unprocessed data reached, closing");
+                    return if src.len() >= limits.max_header_bytes {
+                        trace!("maximum head size of unprocessed data reached, closing");
                         Err(ParseError::TooLarge)
                     } else {
                         // Return None to notify more read are needed for parsing request
@@ -327,25 +371,21 @@ impl MessageType for ResponseHead {
         &mut self.headers
     }
 
-    fn decode(src: &mut BytesMut) -> Result<Option<(Self, PayloadType)>, ParseError> {
-        let mut headers: [HeaderIndex; MAX_HEADERS] = EMPTY_HEADER_INDEX_ARRAY;
+    fn decode(
+        src: &mut BytesMut,
+        limits: &HeadLimits,
+    ) -> Result<Option<(Self, PayloadType)>, ParseError> {
+        let mut header_buf = HeaderBuf::new(limits.max_headers);
+        let headers = header_buf.indices();
 
         let (len, ver, status, h_len) = {
-            // SAFETY:
-            // Create an uninitialized array of `MaybeUninit`. The `assume_init` is safe because the
-            // type we are claiming to have initialized here is a bunch of `MaybeUninit`s, which
-            // do not require initialization.
-            let mut parsed = unsafe {
-                MaybeUninit::<[MaybeUninit<httparse::Header<'_>>; MAX_HEADERS]>::uninit()
-                    .assume_init()
-            };
-
+            let mut parsed_buf = ParsedHeaderBuf::new(limits.max_headers);
             let mut res = httparse::Response::new(&mut []);
 
             let mut config = httparse::ParserConfig::default();
             config.allow_spaces_after_header_name_in_responses(true);
 
-            match config.parse_response_with_uninit_headers(&mut res, src, &mut parsed)? {
+            match config.parse_response_with_uninit_headers(&mut res, src, parsed_buf.headers())? {
                 httparse::Status::Complete(len) => {
                     let version = if res.version.unwrap() == 1 {
                         Version::HTTP_11
@@ -355,14 +395,14 @@ impl MessageType for ResponseHead {
 
                     let status =
                         StatusCode::from_u16(res.code.unwrap()).map_err(|_| ParseError::Status)?;
-                    HeaderIndex::record(src, res.headers, &mut headers);
+                    HeaderIndex::record(src, res.headers, headers);
 
                     (len, version, status, res.headers.len())
                 }
 
                 httparse::Status::Partial => {
-                    return if src.len() >= MAX_BUFFER_SIZE {
-                        error!("MAX_BUFFER_SIZE unprocessed data reached, closing");
+                    return if src.len() >= limits.max_header_bytes {
+                        error!("maximum head size of unprocessed data reached, closing");
                         Err(ParseError::TooLarge)
                     } else {
                         Ok(None)
@@ -418,6 +458,76 @@ pub(crate) const EMPTY_HEADER_INDEX: HeaderIndex = HeaderIndex {
 pub(crate) const EMPTY_HEADER_INDEX_ARRAY: [HeaderIndex; MAX_HEADERS] =
     [EMPTY_HEADER_INDEX; MAX_HEADERS];
 
+/// Header index storage that stays on the stack unless more than `MAX_HEADERS` are allowed.
+#[allow(clippy::large_enum_variant)] // avoids allocating for the default limits
+enum HeaderBuf {
+    Stack([HeaderIndex; MAX_HEADERS], usize),
+    Heap(Vec<HeaderIndex>),
+}
+
+impl HeaderBuf {
+    fn new(max_headers: usize) -> Self {
+        if max_headers <= MAX_HEADERS {
+            Self::Stack(EMPTY_HEADER_INDEX_ARRAY, max_headers)
+        } else {
+            Self::Heap(vec![EMPTY_HEADER_INDEX; max_headers])
+        }
+    }
+
+    fn indices(&mut self) -> &mut [HeaderIndex] {
+        match self {
+            Self::Stack(buf, len) => &mut buf[..*len],
+            Self::Heap(buf) => buf,
+        }
+    }
+}
+
+/// Uninitialized `httparse` header storage, with the same layout choice as [`HeaderBuf`].
+#[allow(clippy::large_enum_variant)] // avoids allocating for the default limits
+enum ParsedHeaderBuf<'a> {
+    Stack([MaybeUninit<httparse::Header<'a>>; MAX_HEADERS], usize),
+    Heap(Vec<MaybeUninit<httparse::Header<'a>>>),
+}
+
+impl<'a> ParsedHeaderBuf<'a> {
+    fn new(max_headers: usize) -> Self {
+        if max_headers <= MAX_HEADERS {
+            // SAFETY:
+            // Create an uninitialized array of `MaybeUninit`. The `assume_init` is safe because the
+            // type we are claiming to have initialized here is a bunch of `MaybeUninit`s, which
+            // do not require initialization.
+            let buf = unsafe {
+                MaybeUninit::<[MaybeUninit<httparse::Header<'a>>; MAX_HEADERS]>::uninit()
+                    .assume_init()
+            };
+
+            Self::Stack(buf, max_headers)
+        } else {
+            let mut buf = Vec::with_capacity(max_headers);
+            buf.resize_with(max_headers, MaybeUninit::uninit);
+            Self::Heap(buf)
+        }
+    }
+
+    fn headers(&mut self) -> &mut [MaybeUninit<httparse::Header<'a>>] {
+        match self {
+            Self::Stack(buf, len) => &mut buf[..*len],
+            Self::Heap(buf) => buf,
+        }
+    }
+}
+
+/// Returns the length of the request line at the start of `src`, excluding the line terminator.
+///
+/// If the line is not yet terminated, the length of the data received so far is returned.
+fn request_line_len(src: &[u8]) -> usize {
+    match src.iter().position(|&b| b == b'\n') {
+        Some(pos) if pos > 0 && src[pos - 1] == b'\r' => pos - 1,
+        Some(pos) => pos,
+        None => src.len(),
+    }
+}
+
 impl HeaderIndex {
     pub(crate) fn record(
         bytes: &[u8],
@@ -462,10 +572,20 @@ impl PayloadDecoder {
     /// Constructs a chunked encoding decoder.
     pub fn chunked() -> PayloadDecoder {
         PayloadDecoder {
-            kind: Kind::Chunked(ChunkedState::Size, 0),
+            kind: Kind::Chunked(ChunkedState::Size, 0, u64::MAX),
         }
     }
 
+    /// Sets the maximum size of a single chunk accepted by a chunked encoding decoder.
+    ///
+    /// Has no effect on other decoders.
+    pub(crate) fn max_chunk_size(mut self, max: u64) -> PayloadDecoder {
+        if let Kind::Chunked(_, _, ref mut max_size) = self.kind {
+            *max_size = max;
+        }
+        self
+    }
+
     /// Creates an decoder that yields chunks until the stream returns EOF.
     pub fn eof() -> PayloadDecoder {
         PayloadDecoder { kind: Kind::Eof }
@@ -478,7 +598,9 @@ enum Kind {
     Length(u64),
 
     /// A reader used when `Transfer-Encoding` is `chunked`.
-    Chunked(ChunkedState, u64),
+    ///
+    /// Holds the parser state, the size of the current chunk, and the maximum chunk size.
+    Chunked(ChunkedState, u64, u64),
 
     /// A reader used for responses that don't indicate a length or chunked.
     ///
@@ -524,7 +646,7 @@ impl Decoder for PayloadDecoder {
                 }
             }
 
-            Kind::Chunked(ref mut state, ref mut size) => {
+            Kind::Chunked(ref mut state, ref mut size, max_size) => {
                 loop {
                     let mut buf = None;
 
@@ -535,6 +657,15 @@ impl Decoder for PayloadDecoder {
                         Poll::Ready(Err(err)) => return Err(err),
                     };
 
+                    // rejects the chunk while its size line is read, before any of it is buffered
+                    if *size > max_size {
+                        debug!("chunk size exceeds limit of {max_size} bytes");
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "Invalid chunk size line: Size exceeds limit",
+                        ));
+                    }
+
                     if *state == ChunkedState::End {
                         trace!("End of chunked stream");
                         return Ok(Some(PayloadItem::Eof));
@@ -1182,4 +1313,57 @@ mod tests {
         let chunk = pl.decode(&mut buf).unwrap().unwrap();
         assert_eq!(chunk, PayloadItem::Chunk(Bytes::from_static(b"0\r\n")));
     }
+
+    #[test]
+    fn head_limits() {
+        let limits = HeadLimits {
+            max_request_line_len: 16,
+            max_headers: 2,
+            max_header_bytes: 64,
+        };
+
+        let mut reader = MessageDecoder::<Request>::new(limits);
+        let mut buf = BytesMut::from("GET /ok HTTP/1.1\r\na: 1\r\nb: 2\r\n\r\n");
+        assert!(reader.decode(&mut buf).unwrap().is_some());
+
+        // request line too long, even before it is complete
+        let mut buf = BytesMut::from("GET /very-long-path");
+        assert!(matches!(reader.decode(&mut buf), Err(ParseError::TooLarge)));
+
+        // too many headers
+        let mut buf = BytesMut::from("GET / HTTP/1.1\r\na: 1\r\nb: 2\r\nc: 3\r\n\r\n");
+        assert!(matches!(reader.decode(&mut buf), Err(ParseError::TooLarge)));
+
+        // head too large, both for complete and partial heads
+        let mut buf =
+            BytesMut::from(format!("GET / HTTP/1.1\r\na: {}\r\n\r\n", "x".repeat(64)).as_str());
+        assert!(matches!(reader.decode(&mut buf), Err(ParseError::TooLarge)));
+        let mut buf = BytesMut::from(format!("GET / HTTP/1.1\r\na: {}", "x".repeat(64)).as_str());
+        assert!(matches!(reader.decode(&mut buf), Err(ParseError::TooLarge)));
+    }
+
+    #[test]
+    fn head_limits_many_headers() {
+        let mut reader = MessageDecoder::<Request>::new(HeadLimits {
+            max_headers: 200,
+            ..HeadLimits::default()
+        });
+
+        let mut head = String::from("GET / HTTP/1.1\r\n");
+        for idx in 0..150 {
+            head.push_str(&format!("x-header-{idx}: {idx}\r\n"));
+        }
+        head.push_str("\r\n");
+
+        let mut buf = BytesMut::from(head.as_str());
+        let (req, _) = reader.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(req.headers().len(), 150);
+        assert_eq!(req.headers().get("x-header-149").unwrap(), "149");
+
+        let mut buf = BytesMut::from(head.as_str());
+        assert!(matches!(
+            MessageDecoder::<Request>::default().decode(&mut buf),
+            Err(ParseError::TooLarge)
+        ));
+    }
 }
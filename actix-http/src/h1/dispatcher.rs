@@ -1,4 +1,6 @@
 use std::{
+    any::Any,
+    cmp,
     collections::VecDeque,
     fmt,
     future::Future,
@@ -9,7 +11,7 @@ use std::{
 };
 
 use actix_codec::{Framed, FramedParts};
-use actix_rt::time::sleep_until;
+use actix_rt::{net::TcpStream, time::sleep_until};
 use actix_service::Service;
 use bitflags::bitflags;
 use bytes::{Buf, BytesMut};
@@ -22,22 +24,32 @@ use tracing::{error, trace};
 use super::{
     codec::Codec,
     decoder::MAX_BUFFER_SIZE,
+    divert::{Decision, Diversions, DivertFuture},
     payload::{Payload, PayloadSender, PayloadStatus},
     timer::TimerState,
-    Message, MessageType,
+    Message, MessageType, Upgraded,
 };
 use crate::{
-    body::{BodySize, BoxBody, MessageBody},
+    body::{BodySize, BoxBody, MessageBody, SendFile},
     config::ServiceConfig,
     error::{DispatchError, ParseError, PayloadError},
+    observer::ConnectionEvents,
     service::HttpFlow,
-    Error, Extensions, OnConnectData, Request, Response, StatusCode,
+    ClientDisconnect, ConnectionType, DisconnectReason, EarlyHints, Error, Extensions,
+    HttpMessage as _, OnConnectData, OnDisconnect, Protocol, Request, Response, StatusCode,
+    Version,
 };
 
 const LW_BUFFER_SIZE: usize = 1024;
 const HW_BUFFER_SIZE: usize = 1024 * 8;
 const MAX_PIPELINED_MESSAGES: usize = 16;
 
+/// Default number of buffered response bytes at which polling of the response body is paused.
+pub(crate) const WRITE_HIGH_WATER_MARK: usize = super::payload::MAX_BUFFER_SIZE;
+
+/// Default number of buffered response bytes at which polling of a paused response body resumes.
+pub(crate) const WRITE_LOW_WATER_MARK: usize = 8 * 1024;
+
 bitflags! {
     #[derive(Debug, Clone, Copy)]
     pub struct Flags: u8 {
@@ -58,6 +70,12 @@ bitflags! {
 
         /// Set if write-half is disconnected.
         const WRITE_DISCONNECT = 0b0010_0000;
+
+        /// Set if the connection is being closed because a timeout elapsed.
+        const TIMED_OUT        = 0b0100_0000;
+
+        /// Set if polling of the response body is paused until the write buffer drains.
+        const WRITE_PAUSED     = 0b1000_0000;
     }
 }
 
@@ -84,6 +102,9 @@ pin_project! {
     {
         #[pin]
         inner: DispatcherState<T, S, B, X, U>,
+        // kept for the whole connection, including after an upgrade
+        events: ConnectionEvents,
+        on_disconnect: OnDisconnect,
     }
 }
 
@@ -105,6 +126,9 @@ pin_project! {
     {
         #[pin]
         pub(super) inner: DispatcherState<T, S, B, X, U>,
+        // kept for the whole connection, including after an upgrade
+        events: ConnectionEvents,
+        on_disconnect: OnDisconnect,
 
         // used in tests
         pub(super) poll_count: u64,
@@ -128,6 +152,7 @@ pin_project! {
     {
         Normal { #[pin] inner: InnerDispatcher<T, S, B, X, U> },
         Upgrade { #[pin] fut: U::Future },
+        Divert { fut: DivertFuture },
     }
 }
 
@@ -152,16 +177,35 @@ pin_project! {
         conn_data: Option<Rc<Extensions>>,
         config: ServiceConfig,
         error: Option<DispatchError>,
+        events: ConnectionEvents,
+        // status of the response currently being sent
+        response_status: Option<StatusCode>,
 
         #[pin]
         pub(super) state: State<S, B, X>,
         // when Some(_) dispatcher is in state of receiving request payload
         payload: Option<PayloadSender>,
+        // when Some(_) in-flight service call can send 103 Early Hints
+        early_hints: Option<EarlyHints>,
+        // shared with all requests on the connection
+        disconnect: ClientDisconnect,
         messages: VecDeque<DispatcherMessage>,
+        // service calls for pipelined requests started ahead of the current response
+        pipeline: VecDeque<PipelinedCall<S>>,
+        // requests passed to the service during the current poll
+        polled_requests: usize,
+        // when Some(_) the first bytes of the connection are checked for diverted protocols
+        diversions: Option<Rc<Diversions<T>>>,
 
         head_timer: TimerState,
         ka_timer: TimerState,
         shutdown_timer: TimerState,
+        read_header_timer: TimerState,
+        read_body_timer: TimerState,
+        body_rate_timer: TimerState,
+        // request body bytes received in the current body rate window
+        body_window_bytes: u64,
+        write_timer: TimerState,
 
         pub(super) io: Option<T>,
         read_buf: BytesMut,
@@ -174,6 +218,12 @@ enum DispatcherMessage {
     Item(Request),
     Upgrade(Request),
     Error(Response<()>),
+    Rejected(Response<()>, BoxBody),
+}
+
+enum PipelinedCall<S: Service<Request>> {
+    Pending(Pin<Box<S::Future>>),
+    Ready(Result<S::Response, S::Error>),
 }
 
 pin_project! {
@@ -189,6 +239,7 @@ pin_project! {
         ServiceCall { #[pin] fut: S::Future },
         SendPayload { #[pin] body: B },
         SendErrorPayload { #[pin] body: BoxBody },
+        SendFile { file: SendFile },
     }
 }
 
@@ -222,6 +273,10 @@ where
             Self::SendErrorPayload { .. } => f
                 .debug_struct("State::SendErrorPayload")
                 .finish_non_exhaustive(),
+            Self::SendFile { file } => f
+                .debug_struct("State::SendFile")
+                .field("file", file)
+                .finish(),
         }
     }
 }
@@ -231,11 +286,12 @@ enum PollResponse {
     Upgrade(Request),
     DoNothing,
     DrainWriteBuf,
+    Yield,
 }
 
 impl<T, S, B, X, U> Dispatcher<T, S, B, X, U>
 where
-    T: AsyncRead + AsyncWrite + Unpin,
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
 
     S: Service<Request>,
     S::Error: Into<Response<BoxBody>>,
@@ -257,23 +313,39 @@ where
         peer_addr: Option<net::SocketAddr>,
         conn_data: OnConnectData,
     ) -> Self {
+        crate::message::set_pool_size(config.message_pool_size());
+        let events = ConnectionEvents::open(&config, Protocol::Http1, peer_addr);
+        let (conn_data, on_disconnect) = conn_data.into_parts();
+
         Dispatcher {
             inner: DispatcherState::Normal {
                 inner: InnerDispatcher {
                     flow,
                     flags: Flags::empty(),
                     peer_addr,
-                    conn_data: conn_data.0.map(Rc::new),
+                    conn_data,
                     config: config.clone(),
                     error: None,
+                    events: events.clone(),
+                    response_status: None,
 
                     state: State::None,
                     payload: None,
+                    early_hints: None,
+                    disconnect: ClientDisconnect::new(),
                     messages: VecDeque::new(),
+                    pipeline: VecDeque::new(),
+                    polled_requests: 0,
+                    diversions: None,
 
                     head_timer: TimerState::new(config.client_request_deadline().is_some()),
                     ka_timer: TimerState::new(config.keep_alive().enabled()),
                     shutdown_timer: TimerState::new(config.client_disconnect_deadline().is_some()),
+                    read_header_timer: TimerState::new(config.read_header_deadline().is_some()),
+                    read_body_timer: TimerState::new(config.read_body_deadline().is_some()),
+                    body_rate_timer: TimerState::new(config.min_body_rate_deadline().is_some()),
+                    body_window_bytes: 0,
+                    write_timer: TimerState::new(config.write_deadline().is_some()),
 
                     io: Some(io),
                     read_buf: BytesMut::with_capacity(HW_BUFFER_SIZE),
@@ -281,16 +353,27 @@ where
                     codec: Codec::new(config),
                 },
             },
+            events,
+            on_disconnect,
 
             #[cfg(test)]
             poll_count: 0,
         }
     }
+
+    /// Sets the protocols that the connection is diverted to by the first bytes the client sends.
+    pub(crate) fn diversions(mut self, diversions: Option<Rc<Diversions<T>>>) -> Self {
+        if let DispatcherState::Normal { ref mut inner } = self.inner {
+            inner.diversions = diversions.filter(|diversions| !diversions.is_empty());
+        }
+
+        self
+    }
 }
 
 impl<T, S, B, X, U> InnerDispatcher<T, S, B, X, U>
 where
-    T: AsyncRead + AsyncWrite + Unpin,
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
 
     S: Service<Request>,
     S::Error: Into<Response<BoxBody>>,
@@ -319,6 +402,7 @@ where
 
         this.flags
             .insert(Flags::READ_DISCONNECT | Flags::WRITE_DISCONNECT);
+        this.disconnect.notify();
 
         if let Some(mut payload) = this.payload.take() {
             payload.set_error(PayloadError::Incomplete(None));
@@ -326,7 +410,12 @@ where
     }
 
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
-        let InnerDispatcherProj { io, write_buf, .. } = self.project();
+        let InnerDispatcherProj {
+            io,
+            write_buf,
+            events,
+            ..
+        } = self.project();
         let mut io = Pin::new(io.as_mut().unwrap());
 
         let len = write_buf.len();
@@ -339,7 +428,10 @@ where
                     return Poll::Ready(Err(io::Error::new(io::ErrorKind::WriteZero, "")));
                 }
 
-                Poll::Ready(n) => written += n,
+                Poll::Ready(n) => {
+                    events.bytes_written(n);
+                    written += n;
+                }
 
                 Poll::Pending => {
                     write_buf.advance(written);
@@ -363,6 +455,7 @@ where
         let this = self.project();
 
         let size = body.size();
+        *this.response_status = Some(res.status());
 
         this.codec
             .encode(Message::Item((res, size)), this.write_buf)
@@ -383,18 +476,43 @@ where
         body: B,
     ) -> Result<(), DispatchError> {
         let size = self.as_mut().send_response_inner(res, &body)?;
-        let mut this = self.project();
+        let mut this = self.as_mut().project();
         this.state.set(match size {
             BodySize::None | BodySize::Sized(0) => {
-                this.flags.insert(Flags::FINISHED);
+                finish_response(this.flags, this.events, this.response_status);
                 State::None
             }
             _ => State::SendPayload { body },
         });
 
+        self.try_send_file();
+
         Ok(())
     }
 
+    /// Switches to copying the response body straight to the socket when it is a file and the
+    /// connection is plaintext TCP.
+    fn try_send_file(self: Pin<&mut Self>) {
+        let mut this = self.project();
+
+        let is_tcp = cfg!(target_os = "linux")
+            && this
+                .io
+                .as_ref()
+                .is_some_and(|io| (io as &dyn Any).is::<TcpStream>());
+
+        // responses without a payload, like those to HEAD requests, are left to the codec
+        if !is_tcp || !matches!(this.codec.body_length(), Some(len) if len > 0) {
+            return;
+        }
+
+        if let StateProj::SendPayload { body } = this.state.as_mut().project() {
+            if let Some(file) = body.take_send_file() {
+                this.state.set(State::SendFile { file });
+            }
+        }
+    }
+
     fn send_error_response(
         mut self: Pin<&mut Self>,
         res: Response<()>,
@@ -404,7 +522,7 @@ where
         let mut this = self.project();
         this.state.set(match size {
             BodySize::None | BodySize::Sized(0) => {
-                this.flags.insert(Flags::FINISHED);
+                finish_response(this.flags, this.events, this.response_status);
                 State::None
             }
             _ => State::SendErrorPayload { body },
@@ -413,6 +531,25 @@ where
         Ok(())
     }
 
+    /// Sends the response of a rejected `Expect: 100-continue` request.
+    ///
+    /// The client has not been told to send the body, so the connection is closed after the
+    /// response instead of reading and discarding it.
+    fn send_expect_error(
+        mut self: Pin<&mut Self>,
+        res: Response<BoxBody>,
+    ) -> Result<(), DispatchError> {
+        let (mut res, body) = res.replace_body(());
+        let this = self.as_mut().project();
+
+        if this.payload.take().is_some() {
+            res.head_mut().set_connection_type(ConnectionType::Close);
+            this.flags.insert(Flags::READ_DISCONNECT);
+        }
+
+        self.send_error_response(res, body)
+    }
+
     fn send_continue(self: Pin<&mut Self>) {
         self.project()
             .write_buf
@@ -426,10 +563,56 @@ where
         'res: loop {
             let mut this = self.as_mut().project();
             match this.state.as_mut().project() {
+                // no future is in InnerDispatcher state; send responses of pipelined requests
+                // in order before popping next message
+                StateProj::None if !this.pipeline.is_empty() => {
+                    if let Some(PipelinedCall::Pending(fut)) = this.pipeline.front_mut() {
+                        match fut.as_mut().poll(cx) {
+                            Poll::Ready(res) => this.pipeline[0] = PipelinedCall::Ready(res),
+
+                            // response order must be kept; wait for the oldest call to resolve
+                            Poll::Pending => {
+                                if !self.as_mut().poll_request(cx)? {
+                                    return Ok(PollResponse::DoNothing);
+                                }
+
+                                continue 'res;
+                            }
+                        }
+                    }
+
+                    let Some(PipelinedCall::Ready(res)) = this.pipeline.pop_front() else {
+                        unreachable!("oldest pipelined call should be resolved")
+                    };
+
+                    match res {
+                        Ok(res) => {
+                            let (res, body) = res.into().replace_body(());
+                            self.as_mut().send_response(res, body)?;
+                        }
+
+                        Err(err) => {
+                            let res: Response<BoxBody> = err.into();
+                            let (res, body) = res.replace_body(());
+                            self.as_mut().send_error_response(res, body)?;
+                        }
+                    }
+                }
+
+                // requests per poll limit is reached; let other connections on the worker run
+                StateProj::None
+                    if *this.polled_requests >= this.config.requests_per_poll()
+                        && matches!(this.messages.front(), Some(DispatcherMessage::Item(_))) =>
+                {
+                    return Ok(PollResponse::Yield);
+                }
+
                 // no future is in InnerDispatcher state; pop next message
                 StateProj::None => match this.messages.pop_front() {
                     // handle request message
-                    Some(DispatcherMessage::Item(req)) => {
+                    Some(DispatcherMessage::Item(mut req)) => {
+                        *this.polled_requests += 1;
+
                         // Handle `EXPECT: 100-Continue` header
                         if req.head().expect() {
                             // set InnerDispatcher state and continue loop to poll it
@@ -437,6 +620,7 @@ where
                             this.state.set(State::ExpectCall { fut });
                         } else {
                             // set InnerDispatcher state and continue loop to poll it
+                            attach_early_hints(this.early_hints, &mut req);
                             let fut = this.flow.service.call(req);
                             this.state.set(State::ServiceCall { fut });
                         };
@@ -450,6 +634,11 @@ where
                         self.as_mut().send_error_response(res, BoxBody::new(()))?;
                     }
 
+                    // handle request rejected before calling the service
+                    Some(DispatcherMessage::Rejected(res, body)) => {
+                        self.as_mut().send_error_response(res, body)?;
+                    }
+
                     // return with upgrade request and poll it exclusively
                     Some(DispatcherMessage::Upgrade(req)) => return Ok(PollResponse::Upgrade(req)),
 
@@ -463,7 +652,10 @@ where
                 },
 
                 StateProj::ServiceCall { fut } => {
-                    match fut.poll(cx) {
+                    let res = fut.poll(cx);
+                    write_early_hints(this.early_hints, this.codec, this.write_buf, &res, cx);
+
+                    match res {
                         // service call resolved. send response.
                         Poll::Ready(Ok(res)) => {
                             let (res, body) = res.into().replace_body(());
@@ -491,9 +683,13 @@ where
                 }
 
                 StateProj::SendPayload { mut body } => {
-                    // keep populate writer buffer until buffer size limit hit,
+                    if !poll_body_ready(this.flags, this.config, this.write_buf) {
+                        return Ok(PollResponse::DrainWriteBuf);
+                    }
+
+                    // keep populate writer buffer until high-water mark hit,
                     // get blocked or finished.
-                    while this.write_buf.len() < super::payload::MAX_BUFFER_SIZE {
+                    while this.write_buf.len() < write_high_water_mark(this.config) {
                         match body.as_mut().poll_next(cx) {
                             Poll::Ready(Some(Ok(item))) => {
                                 this.codec
@@ -501,12 +697,19 @@ where
                             }
 
                             Poll::Ready(None) => {
-                                this.codec.encode(Message::Chunk(None), this.write_buf)?;
+                                match body.as_mut().take_trailers() {
+                                    Some(trailers) => {
+                                        this.codec.encode_trailers(&trailers, this.write_buf)?
+                                    }
+                                    None => {
+                                        this.codec.encode(Message::Chunk(None), this.write_buf)?
+                                    }
+                                }
 
                                 // payload stream finished.
                                 // set state to None and handle next message
                                 this.state.set(State::None);
-                                this.flags.insert(Flags::FINISHED);
+                                finish_response(this.flags, this.events, this.response_status);
 
                                 continue 'res;
                             }
@@ -522,17 +725,22 @@ where
                         }
                     }
 
-                    // buffer is beyond max size
+                    // buffer is beyond high-water mark; pause body until it drains
                     // return and try to write the whole buffer to I/O stream.
+                    pause_body(this.flags, this.events, this.write_buf);
                     return Ok(PollResponse::DrainWriteBuf);
                 }
 
                 StateProj::SendErrorPayload { mut body } => {
                     // TODO: de-dupe impl with SendPayload
 
-                    // keep populate writer buffer until buffer size limit hit,
+                    if !poll_body_ready(this.flags, this.config, this.write_buf) {
+                        return Ok(PollResponse::DrainWriteBuf);
+                    }
+
+                    // keep populate writer buffer until high-water mark hit,
                     // get blocked or finished.
-                    while this.write_buf.len() < super::payload::MAX_BUFFER_SIZE {
+                    while this.write_buf.len() < write_high_water_mark(this.config) {
                         match body.as_mut().poll_next(cx) {
                             Poll::Ready(Some(Ok(item))) => {
                                 this.codec
@@ -540,12 +748,19 @@ where
                             }
 
                             Poll::Ready(None) => {
-                                this.codec.encode(Message::Chunk(None), this.write_buf)?;
+                                match body.as_mut().take_trailers() {
+                                    Some(trailers) => {
+                                        this.codec.encode_trailers(&trailers, this.write_buf)?
+                                    }
+                                    None => {
+                                        this.codec.encode(Message::Chunk(None), this.write_buf)?
+                                    }
+                                }
 
                                 // payload stream finished
                                 // set state to None and handle next message
                                 this.state.set(State::None);
-                                this.flags.insert(Flags::FINISHED);
+                                finish_response(this.flags, this.events, this.response_status);
 
                                 continue 'res;
                             }
@@ -562,29 +777,58 @@ where
                         }
                     }
 
-                    // buffer is beyond max size
+                    // buffer is beyond high-water mark; pause body until it drains
                     // return and try to write the whole buffer to stream
+                    pause_body(this.flags, this.events, this.write_buf);
                     return Ok(PollResponse::DrainWriteBuf);
                 }
 
+                StateProj::SendFile { file } => {
+                    // response head has to reach the socket before the file contents
+                    if !this.write_buf.is_empty() {
+                        return Ok(PollResponse::DrainWriteBuf);
+                    }
+
+                    let io = (this.io.as_ref().unwrap() as &dyn Any)
+                        .downcast_ref::<TcpStream>()
+                        .unwrap();
+
+                    while !file.is_empty() {
+                        match file.poll_send_to(cx, io) {
+                            Poll::Ready(Ok(n)) => this.events.bytes_written(n),
+
+                            Poll::Ready(Err(err)) => {
+                                tracing::error!("Response file send error: {err:?}");
+                                this.flags.insert(Flags::FINISHED);
+                                return Err(DispatchError::Io(err));
+                            }
+
+                            Poll::Pending => return Ok(PollResponse::DoNothing),
+                        }
+                    }
+
+                    // file sent; set state to None and handle next message
+                    this.state.set(State::None);
+                    finish_response(this.flags, this.events, this.response_status);
+                }
+
                 StateProj::ExpectCall { fut } => {
                     trace!("  calling expect service");
 
                     match fut.poll(cx) {
                         // expect resolved. write continue to buffer and set InnerDispatcher state
                         // to service call.
-                        Poll::Ready(Ok(req)) => {
+                        Poll::Ready(Ok(mut req)) => {
                             this.write_buf
                                 .extend_from_slice(b"HTTP/1.1 100 Continue\r\n\r\n");
+                            attach_early_hints(this.early_hints, &mut req);
                             let fut = this.flow.service.call(req);
                             this.state.set(State::ServiceCall { fut });
                         }
 
                         // send expect error as response
                         Poll::Ready(Err(err)) => {
-                            let res: Response<BoxBody> = err.into();
-                            let (res, body) = res.replace_body(());
-                            self.as_mut().send_error_response(res, body)?;
+                            self.as_mut().send_expect_error(err.into())?;
                         }
 
                         // expect must be solved before progress can be made.
@@ -597,12 +841,13 @@ where
 
     fn handle_request(
         mut self: Pin<&mut Self>,
-        req: Request,
+        mut req: Request,
         cx: &mut Context<'_>,
     ) -> Result<(), DispatchError> {
         // initialize dispatcher state
         {
             let mut this = self.as_mut().project();
+            *this.polled_requests += 1;
 
             // Handle `EXPECT: 100-Continue` header
             if req.head().expect() {
@@ -611,6 +856,7 @@ where
                 this.state.set(State::ExpectCall { fut });
             } else {
                 // set dispatcher state to call service handler
+                attach_early_hints(this.early_hints, &mut req);
                 let fut = this.flow.service.call(req);
                 this.state.set(State::ServiceCall { fut });
             };
@@ -622,10 +868,11 @@ where
                 StateProj::ExpectCall { fut } => {
                     match fut.poll(cx) {
                         // expect is resolved; continue loop and poll the service call branch.
-                        Poll::Ready(Ok(req)) => {
+                        Poll::Ready(Ok(mut req)) => {
                             self.as_mut().send_continue();
 
                             let mut this = self.as_mut().project();
+                            attach_early_hints(this.early_hints, &mut req);
                             let fut = this.flow.service.call(req);
                             this.state.set(State::ServiceCall { fut });
 
@@ -635,11 +882,7 @@ where
                         // future is error; send response and return a result
                         // on success to notify the dispatcher a new state is set and the outer loop
                         // should be continued
-                        Poll::Ready(Err(err)) => {
-                            let res: Response<BoxBody> = err.into();
-                            let (res, body) = res.replace_body(());
-                            return self.send_error_response(res, body);
-                        }
+                        Poll::Ready(Err(err)) => return self.send_expect_error(err.into()),
 
                         // future is pending; return Ok(()) to notify that a new state is
                         // set and the outer loop should be continue.
@@ -648,8 +891,12 @@ where
                 }
 
                 StateProj::ServiceCall { fut } => {
+                    let res = fut.poll(cx);
+                    let this = self.as_mut().project();
+                    write_early_hints(this.early_hints, this.codec, this.write_buf, &res, cx);
+
                     // return no matter the service call future's result.
-                    return match fut.poll(cx) {
+                    return match res {
                         // Future is resolved. Send response and return a result. On success
                         // to notify the dispatcher a new state is set and the outer loop
                         // should be continue.
@@ -681,7 +928,8 @@ where
     ///
     /// Returns true if any meaningful work was done.
     fn poll_request(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Result<bool, DispatchError> {
-        let pipeline_queue_full = self.messages.len() >= MAX_PIPELINED_MESSAGES;
+        let max_pipelined = cmp::max(MAX_PIPELINED_MESSAGES, self.config.pipelined_requests());
+        let pipeline_queue_full = self.messages.len() + self.pipeline.len() >= max_pipelined;
         let can_not_read = !self.can_read(cx);
 
         // limit amount of non-processed requests
@@ -704,11 +952,49 @@ where
                             // head timer only applies to first request on connection
                             this.head_timer.clear(line!());
 
+                            if this.read_header_timer.is_active() {
+                                this.read_header_timer.clear(line!());
+                            }
+
                             req.head_mut().peer_addr = *this.peer_addr;
+                            this.events.request_started(req.head());
 
                             req.conn_data.clone_from(this.conn_data);
+                            req.extensions_mut().insert(this.disconnect.clone());
+
+                            if let Err(res) = this.config.on_request_head(&mut req) {
+                                trace!("request answered before calling the service");
+
+                                let (mut res, body) = res.replace_body(());
+                                let has_payload =
+                                    !matches!(this.codec.message_type(), MessageType::None);
+
+                                // the payload is never read; close connection after responding
+                                if has_payload {
+                                    res.head_mut().set_connection_type(ConnectionType::Close);
+                                }
+
+                                this.messages
+                                    .push_back(DispatcherMessage::Rejected(res, body));
+
+                                if !has_payload {
+                                    continue;
+                                }
+
+                                this.flags.insert(Flags::READ_DISCONNECT);
+                                break;
+                            }
 
                             match this.codec.message_type() {
+                                // Request asks for a protocol upgrade other than WebSocket and
+                                // has no payload. Hand it to the upgrade service like above.
+                                MessageType::None
+                                    if req.upgrade() && this.flow.upgrade.is_some() =>
+                                {
+                                    this.messages.push_back(DispatcherMessage::Upgrade(req));
+                                    break;
+                                }
+
                                 // request has no payload
                                 MessageType::None => {}
 
@@ -727,14 +1013,37 @@ where
                                     // to sink new chunked request data to state. Payload is
                                     // attached to Request and passed to Service::call where the
                                     // state can be collected and consumed.
-                                    let (sender, payload) = Payload::create(false);
+                                    let (sender, payload) = Payload::create_with_buffer_size(
+                                        false,
+                                        this.config.payload_buffer_size(),
+                                    );
                                     *req.payload() = crate::Payload::H1 { payload };
                                     *this.payload = Some(sender);
+
+                                    if let Some(deadline) = this.config.read_body_deadline() {
+                                        this.read_body_timer.set_and_init(
+                                            cx,
+                                            sleep_until(deadline.into()),
+                                            line!(),
+                                        );
+                                    }
+
+                                    if let Some(deadline) = this.config.min_body_rate_deadline() {
+                                        *this.body_window_bytes = 0;
+                                        this.body_rate_timer.set_and_init(
+                                            cx,
+                                            sleep_until(deadline.into()),
+                                            line!(),
+                                        );
+                                    }
                                 }
                             }
 
                             // handle request early when no future in InnerDispatcher state.
-                            if this.state.is_none() {
+                            if this.state.is_none()
+                                && this.pipeline.is_empty()
+                                && this.messages.is_empty()
+                            {
                                 self.as_mut().handle_request(req, cx)?;
                                 this = self.as_mut().project();
                             } else {
@@ -744,7 +1053,17 @@ where
 
                         Message::Chunk(Some(chunk)) => {
                             if let Some(ref mut payload) = this.payload {
+                                *this.body_window_bytes += chunk.len() as u64;
                                 payload.feed_data(chunk);
+
+                                // body data arrived; restart read-body timer
+                                if let Some(deadline) = this.config.read_body_deadline() {
+                                    this.read_body_timer.set_and_init(
+                                        cx,
+                                        sleep_until(deadline.into()),
+                                        line!(),
+                                    );
+                                }
                             } else {
                                 error!("Internal server error: unexpected payload chunk");
                                 this.flags.insert(Flags::READ_DISCONNECT);
@@ -759,6 +1078,14 @@ where
                         Message::Chunk(None) => {
                             if let Some(mut payload) = this.payload.take() {
                                 payload.feed_eof();
+
+                                if this.read_body_timer.is_active() {
+                                    this.read_body_timer.clear(line!());
+                                }
+
+                                if this.body_rate_timer.is_active() {
+                                    this.body_rate_timer.clear(line!());
+                                }
                             } else {
                                 error!("Internal server error: unexpected eof");
                                 this.flags.insert(Flags::READ_DISCONNECT);
@@ -799,7 +1126,10 @@ where
                         )));
 
                     this.flags.insert(Flags::READ_DISCONNECT);
-                    *this.error = Some(ParseError::TooLarge.into());
+
+                    let err = ParseError::TooLarge.into();
+                    this.events.protocol_error(&err, this.read_buf);
+                    *this.error = Some(err);
 
                     break;
                 }
@@ -817,15 +1147,71 @@ where
                     ));
 
                     this.flags.insert(Flags::READ_DISCONNECT);
-                    *this.error = Some(err.into());
+
+                    let err = err.into();
+                    this.events.protocol_error(&err, this.read_buf);
+                    *this.error = Some(err);
                     break;
                 }
             }
         }
 
+        // part of a request head is buffered; start read-header timer
+        if this.payload.is_none()
+            && !this.read_buf.is_empty()
+            && matches!(this.read_header_timer, TimerState::Inactive)
+        {
+            if let Some(deadline) = this.config.read_header_deadline() {
+                this.read_header_timer
+                    .set_and_init(cx, sleep_until(deadline.into()), line!());
+            }
+        }
+
+        // start service calls for newly queued requests right away
+        if updated {
+            self.poll_pipeline(cx);
+        }
+
         Ok(updated)
     }
 
+    /// Calls the service for queued requests, up to the configured pipelining limit, and drives
+    /// their futures while an earlier response is in progress.
+    fn poll_pipeline(self: Pin<&mut Self>, cx: &mut Context<'_>) {
+        let this = self.project();
+
+        while this.pipeline.len() < this.config.pipelined_requests() {
+            // requests expecting 100-continue and other messages are kept in order behind the
+            // calls that have already been started
+            match this.messages.front() {
+                Some(DispatcherMessage::Item(req)) if !req.head().expect() => {}
+                _ => break,
+            }
+
+            // start remaining calls when polled again
+            if *this.polled_requests >= this.config.requests_per_poll() {
+                cx.waker().wake_by_ref();
+                break;
+            }
+
+            let Some(DispatcherMessage::Item(req)) = this.messages.pop_front() else {
+                unreachable!()
+            };
+
+            *this.polled_requests += 1;
+            let fut = Box::pin(this.flow.service.call(req));
+            this.pipeline.push_back(PipelinedCall::Pending(fut));
+        }
+
+        for call in this.pipeline.iter_mut() {
+            if let PipelinedCall::Pending(fut) = call {
+                if let Poll::Ready(res) = fut.as_mut().poll(cx) {
+                    *call = PipelinedCall::Ready(res);
+                }
+            }
+        }
+    }
+
     fn poll_head_timer(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -843,7 +1229,9 @@ where
                     BoxBody::new(()),
                 );
 
-                self.project().flags.insert(Flags::SHUTDOWN);
+                self.project()
+                    .flags
+                    .insert(Flags::SHUTDOWN | Flags::TIMED_OUT);
             }
         };
 
@@ -877,7 +1265,7 @@ where
             if timer.as_mut().poll(cx).is_ready() {
                 // no tasks at hand
                 trace!("timer timed out; closing connection");
-                this.flags.insert(Flags::SHUTDOWN);
+                this.flags.insert(Flags::SHUTDOWN | Flags::TIMED_OUT);
 
                 if let Some(deadline) = this.config.client_disconnect_deadline() {
                     // start shutdown timeout if enabled
@@ -914,11 +1302,144 @@ where
         Ok(())
     }
 
-    /// Poll head, keep-alive, and disconnect timer.
+    fn poll_read_header_timer(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Result<(), DispatchError> {
+        let this = self.as_mut().project();
+
+        if let TimerState::Active { timer } = this.read_header_timer {
+            if timer.as_mut().poll(cx).is_ready() {
+                this.read_header_timer.clear(line!());
+
+                if this.state.is_none() && this.messages.is_empty() && this.pipeline.is_empty() {
+                    trace!(
+                        "timed out reading request head; replying with 408 and closing connection"
+                    );
+
+                    let _ = self.as_mut().send_error_response(
+                        Response::with_body(StatusCode::REQUEST_TIMEOUT, ()),
+                        BoxBody::new(()),
+                    );
+
+                    self.project()
+                        .flags
+                        .insert(Flags::SHUTDOWN | Flags::TIMED_OUT);
+                } else {
+                    // do not interrupt in-flight responses; close once they are done
+                    trace!("timed out reading request head; closing connection after responses");
+                    this.flags.insert(Flags::READ_DISCONNECT | Flags::TIMED_OUT);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn poll_read_body_timer(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Result<(), DispatchError> {
+        let this = self.project();
+
+        if let TimerState::Active { timer } = this.read_body_timer {
+            if timer.as_mut().poll(cx).is_ready() {
+                trace!("timed out reading request body; closing connection after response");
+
+                this.read_body_timer.clear(line!());
+                this.flags.insert(Flags::READ_DISCONNECT | Flags::TIMED_OUT);
+
+                if let Some(mut payload) = this.payload.take() {
+                    payload.set_error(PayloadError::Io(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "timed out reading request body",
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn poll_body_rate_timer(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Result<(), DispatchError> {
+        let this = self.project();
+
+        if let TimerState::Active { timer } = this.body_rate_timer {
+            if timer.as_mut().poll(cx).is_ready() {
+                this.body_rate_timer.clear(line!());
+
+                let Some(payload) = this.payload else {
+                    return Ok(());
+                };
+
+                // windows in which the service applies backpressure are not counted
+                let too_slow = payload.need_read(cx) == PayloadStatus::Read
+                    && *this.body_window_bytes < this.config.min_body_window_bytes();
+
+                if too_slow {
+                    trace!("request body rate below minimum; closing connection after response");
+
+                    this.flags.insert(Flags::READ_DISCONNECT | Flags::TIMED_OUT);
+
+                    if let Some(mut payload) = this.payload.take() {
+                        payload.set_error(PayloadError::Io(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            "request body rate below minimum",
+                        )));
+                    }
+                } else if let Some(deadline) = this.config.min_body_rate_deadline() {
+                    // start the next measurement window
+                    *this.body_window_bytes = 0;
+                    this.body_rate_timer
+                        .set_and_init(cx, sleep_until(deadline.into()), line!());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn poll_write_timer(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Result<(), DispatchError> {
+        let this = self.project();
+
+        if let TimerState::Active { timer } = this.write_timer {
+            if timer.as_mut().poll(cx).is_ready() {
+                trace!("timed out writing response; dropping connection");
+                return Err(DispatchError::WriteTimeout);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Starts the write timer when a flush is blocked and clears it once the buffer is drained.
+    fn update_write_timer(self: Pin<&mut Self>, flushed: bool, cx: &mut Context<'_>) {
+        let this = self.project();
+
+        if flushed {
+            if this.write_timer.is_active() {
+                this.write_timer.clear(line!());
+            }
+        } else if matches!(this.write_timer, TimerState::Inactive) {
+            if let Some(deadline) = this.config.write_deadline() {
+                this.write_timer
+                    .set_and_init(cx, sleep_until(deadline.into()), line!());
+            }
+        }
+    }
+
+    /// Poll head, keep-alive, read, write, and disconnect timers.
     fn poll_timers(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Result<(), DispatchError> {
         self.as_mut().poll_head_timer(cx)?;
         self.as_mut().poll_ka_timer(cx)?;
         self.as_mut().poll_shutdown_timer(cx)?;
+        self.as_mut().poll_read_header_timer(cx)?;
+        self.as_mut().poll_read_body_timer(cx)?;
+        self.as_mut().poll_body_rate_timer(cx)?;
+        self.as_mut().poll_write_timer(cx)?;
 
         Ok(())
     }
@@ -940,9 +1461,12 @@ where
 
         let mut read_some = false;
 
+        // allow a configured maximum head size larger than the default buffer size to be read
+        let max_buffer_size = cmp::max(MAX_BUFFER_SIZE, this.config.max_header_bytes());
+
         loop {
             // Return early when read buf exceed decoder's max buffer size.
-            if this.read_buf.len() >= MAX_BUFFER_SIZE {
+            if this.read_buf.len() >= max_buffer_size {
                 // At this point it's not known IO stream is still scheduled to be waked up so
                 // force wake up dispatcher just in case.
                 //
@@ -997,6 +1521,7 @@ where
                         return Ok(true);
                     }
 
+                    this.events.bytes_read(n);
                     read_some = true;
                 }
 
@@ -1031,11 +1556,39 @@ where
         let framed = Framed::from_parts(parts);
         this.flow.upgrade.as_ref().unwrap().call((req, framed))
     }
+
+    /// Checks the first bytes of the connection against the diverted protocols, and hands the
+    /// connection to the handler of the protocol they match.
+    ///
+    /// Returns the future of the handler that the connection was handed to, if any.
+    fn divert(self: Pin<&mut Self>) -> Decision<DivertFuture> {
+        let this = self.project();
+
+        let Some(diversions) = this.diversions.clone() else {
+            return Decision::Http;
+        };
+
+        match diversions.decide(this.read_buf) {
+            Decision::Undecided => Decision::Undecided,
+
+            Decision::Http => {
+                *this.diversions = None;
+                Decision::Http
+            }
+
+            Decision::Divert(handler) => {
+                trace!("diverting connection to custom protocol handler");
+
+                let io = Upgraded::new(this.io.take().unwrap(), mem::take(this.read_buf));
+                Decision::Divert(handler(io, *this.peer_addr))
+            }
+        }
+    }
 }
 
-impl<T, S, B, X, U> Future for Dispatcher<T, S, B, X, U>
+impl<T, S, B, X, U> Dispatcher<T, S, B, X, U>
 where
-    T: AsyncRead + AsyncWrite + Unpin,
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
 
     S: Service<Request>,
     S::Error: Into<Response<BoxBody>>,
@@ -1049,10 +1602,10 @@ where
     U: Service<(Request, Framed<T, Codec>), Response = ()>,
     U::Error: fmt::Display,
 {
-    type Output = Result<(), DispatchError>;
-
-    #[inline]
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    fn poll_dispatcher(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), DispatchError>> {
         let this = self.as_mut().project();
 
         #[cfg(test)]
@@ -1066,9 +1619,16 @@ where
                 DispatchError::Upgrade
             }),
 
+            DispatcherStateProj::Divert { fut } => fut.as_mut().poll(cx).map_err(|err| {
+                error!("Diverted protocol handler error: {}", err);
+                DispatchError::Diverted(err)
+            }),
+
             DispatcherStateProj::Normal { mut inner } => {
                 trace!("start flags: {:?}", &inner.flags);
 
+                *inner.as_mut().project().polled_requests = 0;
+
                 trace_timer_states(
                     "start",
                     &inner.head_timer,
@@ -1111,7 +1671,21 @@ where
                         }
                     }
 
-                    inner.as_mut().poll_request(cx)?;
+                    // requests are only parsed once the connection is known to not be diverted
+                    match inner.as_mut().divert() {
+                        Decision::Undecided => {}
+                        Decision::Http => {
+                            inner.as_mut().poll_request(cx)?;
+                        }
+                        Decision::Divert(fut) => {
+                            self.as_mut()
+                                .project()
+                                .inner
+                                .set(DispatcherState::Divert { fut });
+                            return self.poll_dispatcher(cx);
+                        }
+                    }
+                    inner.as_mut().poll_pipeline(cx);
 
                     if should_disconnect {
                         // I/O stream should to be closed
@@ -1120,6 +1694,7 @@ where
                         if let Some(mut payload) = inner.payload.take() {
                             payload.feed_eof();
                         }
+                        inner.disconnect.notify();
                     };
 
                     loop {
@@ -1144,6 +1719,13 @@ where
                                 false
                             }
 
+                            // requests per poll limit is reached; continue with remaining
+                            // requests once other tasks had a chance to run
+                            PollResponse::Yield => {
+                                cx.waker().wake_by_ref();
+                                false
+                            }
+
                             // upgrade request and goes Upgrade variant of DispatcherState.
                             PollResponse::Upgrade(req) => {
                                 let upgrade = inner.upgrade(req);
@@ -1151,7 +1733,7 @@ where
                                     .project()
                                     .inner
                                     .set(DispatcherState::Upgrade { fut: upgrade });
-                                return self.poll(cx);
+                                return self.poll_dispatcher(cx);
                             }
                         };
 
@@ -1162,6 +1744,7 @@ where
                         // TODO: want to find a reference for this behavior
                         // see introduced commit: 3872d3ba
                         let flush_was_ready = inner.as_mut().poll_flush(cx)?.is_ready();
+                        inner.as_mut().update_write_timer(flush_was_ready, cx);
 
                         // this assert seems to always be true but not willing to commit to it until
                         // we understand what Nikolay meant when writing the above comment
@@ -1179,7 +1762,9 @@ where
                     }
 
                     let inner_p = inner.as_mut().project();
-                    let state_is_none = inner_p.state.is_none();
+                    let state_is_none = inner_p.state.is_none()
+                        && inner_p.pipeline.is_empty()
+                        && inner_p.messages.is_empty();
 
                     // read half is closed; we do not process any responses
                     if inner_p.flags.contains(Flags::READ_DISCONNECT) && state_is_none {
@@ -1200,12 +1785,12 @@ where
                         {
                             inner_p.flags.remove(Flags::FINISHED);
                             inner_p.flags.insert(Flags::SHUTDOWN);
-                            return self.poll(cx);
+                            return self.poll_dispatcher(cx);
                         }
 
                         // disconnect if shutdown
                         if inner_p.flags.contains(Flags::SHUTDOWN) {
-                            return self.poll(cx);
+                            return self.poll_dispatcher(cx);
                         }
                     }
 
@@ -1225,6 +1810,141 @@ where
             }
         }
     }
+
+    /// Classifies why the connection was closed, once the dispatcher has finished with `res`.
+    fn disconnect_reason(&self, res: &Result<(), DispatchError>) -> DisconnectReason {
+        let flags = match self.inner {
+            DispatcherState::Normal { ref inner } => inner.flags,
+            DispatcherState::Upgrade { .. } | DispatcherState::Divert { .. } => Flags::empty(),
+        };
+
+        match DisconnectReason::from_result(res) {
+            // the keep-alive timer only runs while the connection is idle
+            DisconnectReason::Closed | DisconnectReason::Timeout
+                if flags.contains(Flags::TIMED_OUT | Flags::KEEP_ALIVE) =>
+            {
+                DisconnectReason::KeepAliveTimeout
+            }
+            DisconnectReason::Closed if flags.contains(Flags::TIMED_OUT) => {
+                DisconnectReason::Timeout
+            }
+            reason => reason,
+        }
+    }
+}
+
+impl<T, S, B, X, U> Future for Dispatcher<T, S, B, X, U>
+where
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
+
+    S: Service<Request>,
+    S::Error: Into<Response<BoxBody>>,
+    S::Response: Into<Response<B>>,
+
+    B: MessageBody,
+
+    X: Service<Request, Response = Request>,
+    X::Error: Into<Response<BoxBody>>,
+
+    U: Service<(Request, Framed<T, Codec>), Response = ()>,
+    U::Error: fmt::Display,
+{
+    type Output = Result<(), DispatchError>;
+
+    #[inline]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let res = ready!(self.as_mut().poll_dispatcher(cx));
+
+        let reason = self.disconnect_reason(&res);
+        self.project().on_disconnect.notify(reason);
+
+        Poll::Ready(res)
+    }
+}
+
+/// Marks the current response as finished and reports it to the connection observer.
+fn finish_response(
+    flags: &mut Flags,
+    events: &ConnectionEvents,
+    response_status: &mut Option<StatusCode>,
+) {
+    flags.insert(Flags::FINISHED);
+
+    if let Some(status) = response_status.take() {
+        events.request_finished(status);
+    }
+}
+
+/// Returns the high-water mark of the write buffer, which is at least 1 byte.
+fn write_high_water_mark(config: &ServiceConfig) -> usize {
+    cmp::max(config.write_high_water_mark(), 1)
+}
+
+/// Pauses polling of the response body until the write buffer drains to the low-water mark.
+fn pause_body(flags: &mut Flags, events: &ConnectionEvents, write_buf: &BytesMut) {
+    flags.insert(Flags::WRITE_PAUSED);
+    events.write_paused(write_buf.len());
+}
+
+/// Returns true if the response body can be polled, resuming it if it was paused and the write
+/// buffer has drained to the low-water mark.
+fn poll_body_ready(flags: &mut Flags, config: &ServiceConfig, write_buf: &BytesMut) -> bool {
+    if flags.contains(Flags::WRITE_PAUSED) {
+        let low_water_mark = cmp::min(
+            config.write_low_water_mark(),
+            write_high_water_mark(config) - 1,
+        );
+
+        if write_buf.len() > low_water_mark {
+            return false;
+        }
+
+        flags.remove(Flags::WRITE_PAUSED);
+    }
+
+    true
+}
+
+/// Gives the request a handle for sending `103 Early Hints`, if its client can receive them.
+///
+/// Informational responses are not defined for HTTP/1.0 clients.
+fn attach_early_hints(slot: &mut Option<EarlyHints>, req: &mut Request) {
+    *slot = None;
+
+    if req.version() >= Version::HTTP_11 {
+        let hints = EarlyHints::new();
+        req.extensions_mut().insert(hints.clone());
+        *slot = Some(hints);
+    }
+}
+
+/// Writes hints queued during a poll of the service future ahead of the final response.
+///
+/// Once the service future resolves, the handle is closed and dropped so that no hints can follow
+/// the response head.
+fn write_early_hints<T>(
+    slot: &mut Option<EarlyHints>,
+    codec: &Codec,
+    write_buf: &mut BytesMut,
+    res: &Poll<T>,
+    cx: &Context<'_>,
+) {
+    let Some(hints) = slot else {
+        return;
+    };
+
+    if res.is_ready() {
+        while let Some(headers) = hints.take_next() {
+            codec.encode_early_hints(&headers, write_buf);
+        }
+
+        hints.close();
+        *slot = None;
+    } else {
+        while let Some(headers) = hints.poll_next(cx.waker()) {
+            codec.encode_early_hints(&headers, write_buf);
+        }
+    }
 }
 
 #[allow(dead_code)]
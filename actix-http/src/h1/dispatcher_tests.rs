@@ -1,4 +1,4 @@
-use std::{future::Future, str, task::Poll, time::Duration};
+use std::{future::Future, io, rc::Rc, str, task::Poll, time::Duration};
 
 use actix_codec::Framed;
 use actix_rt::{pin, time::sleep};
@@ -10,8 +10,8 @@ use futures_util::future::lazy;
 use super::dispatcher::{Dispatcher, DispatcherState, DispatcherStateProj, Flags};
 use crate::{
     body::MessageBody,
-    config::ServiceConfig,
-    h1::{Codec, ExpectHandler, UpgradeHandler},
+    config::{ServiceConfig, ServiceConfigBuilder},
+    h1::{Codec, Diversions, ExpectHandler, UpgradeHandler, Upgraded},
     service::HttpFlow,
     test::{TestBuffer, TestSeqBuffer},
     Error, HttpMessage, KeepAlive, Method, OnConnectData, Request, Response, StatusCode,
@@ -578,6 +578,49 @@ async fn pipelining_ok_then_bad() {
     .await;
 }
 
+#[actix_rt::test]
+async fn pipelining_requests_per_poll() {
+    lazy(|cx| {
+        let buf = TestBuffer::new(
+            "\
+                GET /abcd HTTP/1.1\r\n\r\n\
+                GET /def HTTP/1.1\r\n\r\n\
+                ",
+        );
+
+        let cfg = ServiceConfigBuilder::new().requests_per_poll(1).build();
+        let services = HttpFlow::new(echo_path_service(), ExpectHandler, None);
+
+        let h1 = Dispatcher::<_, _, _, _, UpgradeHandler>::new(
+            buf.clone(),
+            services,
+            cfg,
+            None,
+            OnConnectData::default(),
+        );
+
+        pin!(h1);
+
+        // first poll yields after the first request
+        assert!(h1.as_mut().poll(cx).is_pending());
+        let res = buf.take_write_buf();
+        assert!(
+            res.ends_with(b"\r\n\r\n/abcd"),
+            "unexpected response: {:?}",
+            String::from_utf8_lossy(&res)
+        );
+
+        assert!(h1.as_mut().poll(cx).is_pending());
+        let res = buf.take_write_buf();
+        assert!(
+            res.starts_with(b"HTTP/1.1 200 OK\r\n") && res.ends_with(b"\r\n\r\n/def"),
+            "unexpected response: {:?}",
+            String::from_utf8_lossy(&res)
+        );
+    })
+    .await;
+}
+
 #[actix_rt::test]
 async fn expect_handling() {
     lazy(|cx| {
@@ -780,6 +823,98 @@ async fn upgrade_handling() {
     .await;
 }
 
+#[actix_rt::test]
+async fn divert_handling() {
+    lazy(|cx| {
+        let mut buf = TestSeqBuffer::empty();
+        let cfg = ServiceConfig::new(
+            KeepAlive::Disabled,
+            Duration::ZERO,
+            Duration::ZERO,
+            false,
+            None,
+        );
+
+        let mut diversions = Diversions::default();
+        diversions.insert(
+            Bytes::from_static(b"PING\r\n"),
+            |mut io: Upgraded<TestSeqBuffer>, _| async move {
+                use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+                let mut ping = [0; 6];
+                io.read_exact(&mut ping).await?;
+                assert_eq!(&ping, b"PING\r\n");
+
+                io.write_all(b"+PONG\r\n").await?;
+                io.flush().await?;
+                Ok::<_, io::Error>(())
+            },
+        );
+
+        let services = HttpFlow::new(ok_service(), ExpectHandler, None);
+
+        let h1 = Dispatcher::<_, _, _, _, UpgradeHandler>::new(
+            buf.clone(),
+            services,
+            cfg,
+            None,
+            OnConnectData::default(),
+        )
+        .diversions(Some(Rc::new(diversions)));
+
+        pin!(h1);
+
+        // start of the prefix; not parsed as HTTP yet
+        buf.extend_read_buf("PI");
+        assert!(h1.as_mut().poll(cx).is_pending());
+        assert!(matches!(&h1.inner, DispatcherState::Normal { .. }));
+        assert!(buf.write_buf().is_empty());
+
+        buf.extend_read_buf("NG\r\n");
+        assert!(matches!(h1.as_mut().poll(cx), Poll::Ready(Ok(()))));
+        assert!(matches!(&h1.inner, DispatcherState::Divert { .. }));
+        assert_eq!(&buf.write_buf()[..], b"+PONG\r\n");
+    })
+    .await;
+
+    lazy(|cx| {
+        let buf = TestBuffer::new("GET / HTTP/1.1\r\nConnection: close\r\n\r\n");
+        let cfg = ServiceConfig::new(
+            KeepAlive::Disabled,
+            Duration::ZERO,
+            Duration::ZERO,
+            false,
+            None,
+        );
+
+        let mut diversions = Diversions::default();
+        diversions.insert(Bytes::from_static(b"PING\r\n"), |_, _| async {
+            Ok::<_, io::Error>(())
+        });
+
+        let services = HttpFlow::new(ok_service(), ExpectHandler, None);
+
+        let h1 = Dispatcher::<_, _, _, _, UpgradeHandler>::new(
+            buf.clone(),
+            services,
+            cfg,
+            None,
+            OnConnectData::default(),
+        )
+        .diversions(Some(Rc::new(diversions)));
+
+        pin!(h1);
+
+        // other bytes are served as HTTP
+        assert!(h1.as_mut().poll(cx).is_ready());
+        assert!(matches!(&h1.inner, DispatcherState::Normal { .. }));
+
+        let res = &buf.write_buf_slice()[..];
+        assert!(res.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    })
+    .await;
+}
+
 // fix in #2624 reverted temporarily
 // complete fix tracked in #2745
 #[ignore]
@@ -0,0 +1,125 @@
+//! Diversion of HTTP/1 connections to other protocols by the first bytes the client sends.
+
+use std::{error::Error as StdError, future::Future, net};
+
+use bytes::Bytes;
+use futures_core::future::LocalBoxFuture;
+
+use super::Upgraded;
+
+pub(super) type DivertFuture = LocalBoxFuture<'static, Result<(), Box<dyn StdError>>>;
+
+type DivertHandler<T> = dyn Fn(Upgraded<T>, Option<net::SocketAddr>) -> DivertFuture;
+
+/// Handlers of the protocols that connections are diverted to, keyed by byte prefix.
+pub(crate) struct Diversions<T> {
+    protocols: Vec<(Bytes, Box<DivertHandler<T>>)>,
+}
+
+impl<T> Default for Diversions<T> {
+    fn default() -> Self {
+        Self {
+            protocols: Vec::new(),
+        }
+    }
+}
+
+impl<T> Diversions<T> {
+    /// Registers `handler` for connections whose first bytes are `prefix`.
+    ///
+    /// Replaces any handler previously registered for the same prefix.
+    ///
+    /// # Panics
+    /// Panics if `prefix` is empty.
+    pub(crate) fn insert<F, Fut, E>(&mut self, prefix: Bytes, handler: F)
+    where
+        T: 'static,
+        F: Fn(Upgraded<T>, Option<net::SocketAddr>) -> Fut + 'static,
+        Fut: Future<Output = Result<(), E>> + 'static,
+        E: Into<Box<dyn StdError>>,
+    {
+        assert!(
+            !prefix.is_empty(),
+            "diverted protocol prefix must not be empty"
+        );
+
+        let handler = move |io, peer_addr| {
+            let fut = handler(io, peer_addr);
+            Box::pin(async move { fut.await.map_err(Into::into) }) as DivertFuture
+        };
+
+        self.protocols.retain(|(existing, _)| *existing != prefix);
+        self.protocols.push((prefix, Box::new(handler)));
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.protocols.is_empty()
+    }
+
+    /// Decides whether a connection whose first bytes are `buf` is diverted.
+    ///
+    /// Prefixes are checked in registration order, so a connection waits for enough bytes to
+    /// tell whether it matches a prefix before later prefixes are considered.
+    pub(super) fn decide(&self, buf: &[u8]) -> Decision<&DivertHandler<T>>
+    where
+        T: 'static,
+    {
+        for (prefix, handler) in &self.protocols {
+            if buf.starts_with(prefix) {
+                return Decision::Divert(handler);
+            }
+
+            if prefix.starts_with(buf) {
+                return Decision::Undecided;
+            }
+        }
+
+        Decision::Http
+    }
+}
+
+/// Outcome of checking the first bytes of a connection against the diverted protocols.
+pub(super) enum Decision<H> {
+    /// Bytes read so far are the start of a prefix.
+    Undecided,
+
+    /// Connection is served as HTTP/1.
+    Http,
+
+    /// Connection is diverted to a handler.
+    Divert(H),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+
+    #[test]
+    fn decide() {
+        let mut diversions = Diversions::<()>::default();
+        diversions.insert(Bytes::from_static(b"\x0b"), |_, _| async {
+            Ok::<_, io::Error>(())
+        });
+        diversions.insert(Bytes::from_static(b"PING\r\n"), |_, _| async {
+            Ok::<_, io::Error>(())
+        });
+
+        assert!(matches!(diversions.decide(b""), Decision::Undecided));
+        assert!(matches!(
+            diversions.decide(b"\x0bMSH|"),
+            Decision::Divert(_)
+        ));
+        assert!(matches!(diversions.decide(b"PI"), Decision::Undecided));
+        assert!(matches!(
+            diversions.decide(b"PING\r\n"),
+            Decision::Divert(_)
+        ));
+        assert!(matches!(
+            diversions.decide(b"POST / HTTP/1.1"),
+            Decision::Http
+        ));
+        assert!(matches!(diversions.decide(b"PUT"), Decision::Http));
+    }
+}
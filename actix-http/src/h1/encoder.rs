@@ -7,6 +7,7 @@ use std::{
 };
 
 use bytes::{BufMut, BytesMut};
+use tracing::trace;
 
 use crate::{
     body::BodySize,
@@ -299,11 +300,23 @@ impl MessageType for RequestHeadType {
     fn encode_status(&mut self, dst: &mut BytesMut) -> io::Result<()> {
         let head = self.as_ref();
         dst.reserve(256 + head.headers.len() * AVERAGE_HEADER_SIZE);
+
+        let path = head.uri.path_and_query().map(|u| u.as_str()).unwrap_or("/");
+
+        // requests to forward proxies name their target in absolute form
+        let origin = match (head.uri.scheme_str(), head.uri.authority()) {
+            (Some(scheme), Some(authority)) if head.absolute_form() => {
+                format!("{scheme}://{authority}")
+            }
+            _ => String::new(),
+        };
+
         write!(
             helpers::MutWriter(dst),
-            "{} {} {}",
+            "{} {}{} {}",
             head.method,
-            head.uri.path_and_query().map(|u| u.as_str()).unwrap_or("/"),
+            origin,
+            path,
             match head.version {
                 Version::HTTP_09 => "HTTP/0.9",
                 Version::HTTP_10 => "HTTP/1.0",
@@ -328,6 +341,16 @@ impl<T: MessageType> MessageEncoder<T> {
         self.te.encode_eof(buf)
     }
 
+    /// Returns the remaining body length when the message is delimited by `Content-Length`.
+    pub(crate) fn body_length(&self) -> Option<u64> {
+        self.te.remaining_length()
+    }
+
+    /// Encode EOF, followed by trailer fields if the transfer encoding supports them.
+    pub fn encode_trailers(&mut self, trailers: &HeaderMap, buf: &mut BytesMut) -> io::Result<()> {
+        self.te.encode_trailers(trailers, buf)
+    }
+
     /// Encode message.
     pub fn encode(
         &mut self,
@@ -459,6 +482,15 @@ impl TransferEncoding {
         }
     }
 
+    /// Returns the remaining body length when the body is delimited by `Content-Length`.
+    #[inline]
+    pub(crate) fn remaining_length(&self) -> Option<u64> {
+        match self.kind {
+            TransferEncodingKind::Length(len) => Some(len),
+            _ => None,
+        }
+    }
+
     /// Encode eof. Return `EOF` state of encoder
     #[inline]
     pub fn encode_eof(&mut self, buf: &mut BytesMut) -> io::Result<()> {
@@ -480,6 +512,50 @@ impl TransferEncoding {
             }
         }
     }
+
+    /// Encode eof with trailer fields.
+    ///
+    /// Trailers can only be sent using chunked encoding; for other transfer encodings they are
+    /// dropped and this is equivalent to [`encode_eof`](Self::encode_eof).
+    pub fn encode_trailers(&mut self, trailers: &HeaderMap, buf: &mut BytesMut) -> io::Result<()> {
+        match self.kind {
+            TransferEncodingKind::Chunked(ref mut eof) => {
+                if !*eof {
+                    *eof = true;
+                    buf.extend_from_slice(b"0\r\n");
+                    write_header_lines(trailers, buf);
+                    buf.extend_from_slice(b"\r\n");
+                }
+                Ok(())
+            }
+
+            _ => {
+                if !trailers.is_empty() {
+                    trace!("dropping trailers; response is not using chunked encoding");
+                }
+
+                self.encode_eof(buf)
+            }
+        }
+    }
+}
+
+/// Encodes a `103 Early Hints` informational response head.
+pub(crate) fn encode_early_hints(hints: &HeaderMap, buf: &mut BytesMut) {
+    buf.extend_from_slice(b"HTTP/1.1 103 Early Hints\r\n");
+    write_header_lines(hints, buf);
+    buf.extend_from_slice(b"\r\n");
+}
+
+/// Writes each header as a `name: value` line, without the terminating empty line.
+fn write_header_lines(headers: &HeaderMap, buf: &mut BytesMut) {
+    for (name, value) in headers {
+        buf.reserve(name.as_str().len() + value.len() + 4);
+        buf.extend_from_slice(name.as_str().as_bytes());
+        buf.extend_from_slice(b": ");
+        buf.extend_from_slice(value.as_bytes());
+        buf.extend_from_slice(b"\r\n");
+    }
 }
 
 /// # Safety
@@ -534,9 +610,37 @@ mod tests {
     use super::*;
     use crate::{
         header::{HeaderValue, CONTENT_TYPE},
-        RequestHead,
+        RequestHead, Uri,
     };
 
+    #[test]
+    fn test_chunked_te_trailers() {
+        let mut trailers = HeaderMap::new();
+        trailers.insert(
+            HeaderName::from_static("grpc-status"),
+            HeaderValue::from_static("0"),
+        );
+
+        let mut bytes = BytesMut::new();
+        let mut enc = TransferEncoding::chunked();
+        assert!(!enc.encode(b"test", &mut bytes).unwrap());
+        enc.encode_trailers(&trailers, &mut bytes).unwrap();
+        assert_eq!(
+            bytes.split().freeze(),
+            Bytes::from_static(b"4\r\ntest\r\n0\r\ngrpc-status: 0\r\n\r\n")
+        );
+
+        // further calls are no-ops
+        enc.encode_trailers(&trailers, &mut bytes).unwrap();
+        assert!(bytes.is_empty());
+
+        // trailers are dropped for non-chunked encodings
+        let mut enc = TransferEncoding::length(4);
+        enc.encode(b"test", &mut bytes).unwrap();
+        enc.encode_trailers(&trailers, &mut bytes).unwrap();
+        assert_eq!(bytes.split().freeze(), Bytes::from_static(b"test"));
+    }
+
     #[test]
     fn test_chunked_te() {
         let mut bytes = BytesMut::new();
@@ -648,6 +752,29 @@ mod tests {
         assert!(data.contains("date: date\r\n"));
     }
 
+    #[test]
+    fn test_absolute_form() {
+        let mut bytes = BytesMut::with_capacity(2048);
+
+        let mut head = RequestHead::default();
+        head.uri = Uri::from_static("http://example.com:8080/path?query");
+
+        let mut head = RequestHeadType::Owned(head);
+        head.encode_status(&mut bytes).unwrap();
+        assert_eq!(bytes.split(), "GET /path?query HTTP/1.1");
+
+        let RequestHeadType::Owned(ref mut inner) = head else {
+            unreachable!()
+        };
+        inner.set_absolute_form(true);
+
+        head.encode_status(&mut bytes).unwrap();
+        assert_eq!(
+            bytes.split(),
+            "GET http://example.com:8080/path?query HTTP/1.1"
+        );
+    }
+
     #[actix_rt::test]
     async fn test_no_content_length() {
         let mut bytes = BytesMut::with_capacity(2048);
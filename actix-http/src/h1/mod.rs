@@ -9,6 +9,7 @@ mod decoder;
 mod dispatcher;
 #[cfg(test)]
 mod dispatcher_tests;
+mod divert;
 mod encoder;
 mod expect;
 mod payload;
@@ -24,9 +25,18 @@ pub use self::{
     expect::ExpectHandler,
     payload::Payload,
     service::{H1Service, H1ServiceHandler},
-    upgrade::UpgradeHandler,
+    upgrade::{
+        connect_fn, upgrade_fn, ConnectFn, FramedExt, SwitchCodecError, UpgradeFn, UpgradeHandler,
+        Upgraded,
+    },
     utils::SendResponse,
 };
+pub(crate) use self::{
+    decoder::{MAX_BUFFER_SIZE, MAX_HEADERS},
+    dispatcher::{WRITE_HIGH_WATER_MARK, WRITE_LOW_WATER_MARK},
+    divert::Diversions,
+    payload::MAX_BUFFER_SIZE as PAYLOAD_BUFFER_SIZE,
+};
 
 #[derive(Debug)]
 /// Codec message
@@ -41,7 +41,16 @@ impl Payload {
     /// - `PayloadSender` - *Sender* side of the stream
     /// - `Payload` - *Receiver* side of the stream
     pub fn create(eof: bool) -> (PayloadSender, Payload) {
-        let shared = Rc::new(RefCell::new(Inner::new(eof)));
+        Self::create_with_buffer_size(eof, MAX_BUFFER_SIZE)
+    }
+
+    /// Creates a payload stream that asks for more data while it holds fewer than `buffer_size`
+    /// bytes.
+    pub(crate) fn create_with_buffer_size(
+        eof: bool,
+        buffer_size: usize,
+    ) -> (PayloadSender, Payload) {
+        let shared = Rc::new(RefCell::new(Inner::new(eof, buffer_size)));
 
         (
             PayloadSender::new(Rc::downgrade(&shared)),
@@ -52,7 +61,7 @@ impl Payload {
     /// Creates an empty payload.
     pub(crate) fn empty() -> Payload {
         Payload {
-            inner: Rc::new(RefCell::new(Inner::new(true))),
+            inner: Rc::new(RefCell::new(Inner::new(true, MAX_BUFFER_SIZE))),
         }
     }
 
@@ -138,6 +147,7 @@ impl PayloadSender {
 #[derive(Debug)]
 struct Inner {
     len: usize,
+    buffer_size: usize,
     eof: bool,
     err: Option<PayloadError>,
     need_read: bool,
@@ -147,10 +157,11 @@ struct Inner {
 }
 
 impl Inner {
-    fn new(eof: bool) -> Self {
+    fn new(eof: bool, buffer_size: usize) -> Self {
         Inner {
             eof,
             len: 0,
+            buffer_size,
             err: None,
             items: VecDeque::new(),
             need_read: true,
@@ -211,7 +222,7 @@ impl Inner {
     fn feed_data(&mut self, data: Bytes) {
         self.len += data.len();
         self.items.push_back(data);
-        self.need_read = self.len < MAX_BUFFER_SIZE;
+        self.need_read = self.len < self.buffer_size;
         self.wake();
     }
 
@@ -226,7 +237,7 @@ impl Inner {
     ) -> Poll<Option<Result<Bytes, PayloadError>>> {
         if let Some(data) = self.items.pop_front() {
             self.len -= data.len();
-            self.need_read = self.len < MAX_BUFFER_SIZE;
+            self.need_read = self.len < self.buffer_size;
 
             if self.need_read && !self.eof {
                 self.register(cx);
@@ -279,4 +290,23 @@ mod tests {
                 .unwrap()
         );
     }
+
+    #[actix_rt::test]
+    async fn buffer_size() {
+        let (mut sender, mut payload) = Payload::create_with_buffer_size(false, 8);
+        let waker = futures_util::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        sender.feed_data(Bytes::from_static(b"data"));
+        assert_eq!(sender.need_read(&mut cx), PayloadStatus::Read);
+
+        sender.feed_data(Bytes::from_static(b"line"));
+        assert_eq!(sender.need_read(&mut cx), PayloadStatus::Pause);
+
+        poll_fn(|cx| Pin::new(&mut payload).poll_next(cx))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(sender.need_read(&mut cx), PayloadStatus::Read);
+    }
 }
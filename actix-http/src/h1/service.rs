@@ -15,7 +15,7 @@ use actix_utils::future::ready;
 use futures_core::future::LocalBoxFuture;
 use tracing::error;
 
-use super::{codec::Codec, dispatcher::Dispatcher, ExpectHandler, UpgradeHandler};
+use super::{codec::Codec, dispatcher::Dispatcher, Diversions, ExpectHandler, UpgradeHandler};
 use crate::{
     body::{BoxBody, MessageBody},
     config::ServiceConfig,
@@ -31,6 +31,7 @@ pub struct H1Service<T, S, B, X = ExpectHandler, U = UpgradeHandler> {
     expect: X,
     upgrade: Option<U>,
     on_connect_ext: Option<Rc<ConnectCallback<T>>>,
+    diversions: Option<Rc<Diversions<T>>>,
     _phantom: PhantomData<B>,
 }
 
@@ -53,6 +54,7 @@ where
             expect: ExpectHandler,
             upgrade: None,
             on_connect_ext: None,
+            diversions: None,
             _phantom: PhantomData,
         }
     }
@@ -91,6 +93,104 @@ where
     }
 }
 
+#[cfg(feature = "experimental-io-uring")]
+mod uring {
+    use super::*;
+    use crate::UringStream;
+
+    impl<S, B, X, U> H1Service<UringStream, S, B, X, U>
+    where
+        S: ServiceFactory<Request, Config = ()>,
+        S::Future: 'static,
+        S::Error: Into<Response<BoxBody>>,
+        S::InitError: fmt::Debug,
+        S::Response: Into<Response<B>>,
+
+        B: MessageBody,
+
+        X: ServiceFactory<Request, Config = (), Response = Request>,
+        X::Future: 'static,
+        X::Error: Into<Response<BoxBody>>,
+        X::InitError: fmt::Debug,
+
+        U: ServiceFactory<(Request, Framed<UringStream, Codec>), Config = (), Response = ()>,
+        U::Future: 'static,
+        U::Error: fmt::Display + Into<Response<BoxBody>>,
+        U::InitError: fmt::Debug,
+    {
+        /// Create tcp stream service that serves accepted streams as [`UringStream`]s.
+        ///
+        /// The resulting service must be run on a `tokio-uring` runtime, which is what
+        /// `actix-server` provides when its `io-uring` feature is enabled.
+        pub fn tcp_uring(
+            self,
+        ) -> impl ServiceFactory<
+            TcpStream,
+            Config = (),
+            Response = (),
+            Error = DispatchError,
+            InitError = (),
+        > {
+            fn_service(|io: TcpStream| {
+                let peer_addr = io.peer_addr().ok();
+                ready(
+                    UringStream::from_tokio(io)
+                        .map(|io| (io, peer_addr))
+                        .map_err(DispatchError::Io),
+                )
+            })
+            .and_then(self)
+        }
+    }
+}
+
+#[cfg(unix)]
+mod uds {
+    use actix_rt::net::UnixStream;
+
+    use super::*;
+    use crate::uds_info::with_uds_info;
+
+    impl<S, B, X, U> H1Service<UnixStream, S, B, X, U>
+    where
+        S: ServiceFactory<Request, Config = ()>,
+        S::Future: 'static,
+        S::Error: Into<Response<BoxBody>>,
+        S::InitError: fmt::Debug,
+        S::Response: Into<Response<B>>,
+
+        B: MessageBody,
+
+        X: ServiceFactory<Request, Config = (), Response = Request>,
+        X::Future: 'static,
+        X::Error: Into<Response<BoxBody>>,
+        X::InitError: fmt::Debug,
+
+        U: ServiceFactory<(Request, Framed<UnixStream, Codec>), Config = (), Response = ()>,
+        U::Future: 'static,
+        U::Error: fmt::Display + Into<Response<BoxBody>>,
+        U::InitError: fmt::Debug,
+    {
+        /// Create simple Unix domain socket stream service.
+        ///
+        /// The peer details of each connection are available from its requests as
+        /// [`UdsInfo`](crate::UdsInfo) connection data.
+        pub fn uds(
+            mut self,
+        ) -> impl ServiceFactory<
+            UnixStream,
+            Config = (),
+            Response = (),
+            Error = DispatchError,
+            InitError = (),
+        > {
+            self.on_connect_ext = with_uds_info(self.on_connect_ext.take());
+
+            fn_service(|io: UnixStream| ready(Ok((io, None)))).and_then(self)
+        }
+    }
+}
+
 #[cfg(feature = "openssl")]
 mod openssl {
     use actix_tls::accept::{
@@ -102,6 +202,7 @@ mod openssl {
     };
 
     use super::*;
+    use crate::{service::TlsAcceptorConfig, tls_info::with_tls_info, TlsInfo};
 
     impl<S, B, X, U> H1Service<TlsStream<TcpStream>, S, B, X, U>
     where
@@ -138,7 +239,33 @@ mod openssl {
             Error = TlsError<SslError, DispatchError>,
             InitError = (),
         > {
-            Acceptor::new(acceptor)
+            self.openssl_with_config(acceptor, TlsAcceptorConfig::default())
+        }
+
+        /// Create OpenSSL based service with custom TLS acceptor configuration.
+        ///
+        /// Use [`TlsAcceptorConfig::handshake_timeout()`] to limit how long a client may take to
+        /// complete the TLS handshake. Connections that exceed it fail with [`TlsError::Timeout`].
+        pub fn openssl_with_config(
+            mut self,
+            acceptor: SslAcceptor,
+            tls_acceptor_config: TlsAcceptorConfig,
+        ) -> impl ServiceFactory<
+            TcpStream,
+            Config = (),
+            Response = (),
+            Error = TlsError<SslError, DispatchError>,
+            InitError = (),
+        > {
+            self.on_connect_ext = with_tls_info(self.on_connect_ext.take(), TlsInfo::from_openssl);
+
+            let mut acceptor = Acceptor::new(acceptor);
+
+            if let Some(handshake_timeout) = tls_acceptor_config.handshake_timeout {
+                acceptor.set_handshake_timeout(handshake_timeout);
+            }
+
+            acceptor
                 .map_init_err(|_| {
                     unreachable!("TLS acceptor service factory does not error on init")
                 })
@@ -163,6 +290,7 @@ mod rustls_0_20 {
     };
 
     use super::*;
+    use crate::{service::TlsAcceptorConfig, tls_info::with_tls_info, TlsInfo};
 
     impl<S, B, X, U> H1Service<TlsStream<TcpStream>, S, B, X, U>
     where
@@ -199,7 +327,34 @@ mod rustls_0_20 {
             Error = TlsError<io::Error, DispatchError>,
             InitError = (),
         > {
-            Acceptor::new(config)
+            self.rustls_with_config(config, TlsAcceptorConfig::default())
+        }
+
+        /// Create Rustls v0.20 based service with custom TLS acceptor configuration.
+        ///
+        /// Use [`TlsAcceptorConfig::handshake_timeout()`] to limit how long a client may take to
+        /// complete the TLS handshake. Connections that exceed it fail with [`TlsError::Timeout`].
+        pub fn rustls_with_config(
+            mut self,
+            config: ServerConfig,
+            tls_acceptor_config: TlsAcceptorConfig,
+        ) -> impl ServiceFactory<
+            TcpStream,
+            Config = (),
+            Response = (),
+            Error = TlsError<io::Error, DispatchError>,
+            InitError = (),
+        > {
+            self.on_connect_ext =
+                with_tls_info(self.on_connect_ext.take(), TlsInfo::from_rustls_0_20);
+
+            let mut acceptor = Acceptor::new(config);
+
+            if let Some(handshake_timeout) = tls_acceptor_config.handshake_timeout {
+                acceptor.set_handshake_timeout(handshake_timeout);
+            }
+
+            acceptor
                 .map_init_err(|_| {
                     unreachable!("TLS acceptor service factory does not error on init")
                 })
@@ -224,6 +379,7 @@ mod rustls_0_21 {
     };
 
     use super::*;
+    use crate::{service::TlsAcceptorConfig, tls_info::with_tls_info, TlsInfo};
 
     impl<S, B, X, U> H1Service<TlsStream<TcpStream>, S, B, X, U>
     where
@@ -260,7 +416,34 @@ mod rustls_0_21 {
             Error = TlsError<io::Error, DispatchError>,
             InitError = (),
         > {
-            Acceptor::new(config)
+            self.rustls_021_with_config(config, TlsAcceptorConfig::default())
+        }
+
+        /// Create Rustls v0.21 based service with custom TLS acceptor configuration.
+        ///
+        /// Use [`TlsAcceptorConfig::handshake_timeout()`] to limit how long a client may take to
+        /// complete the TLS handshake. Connections that exceed it fail with [`TlsError::Timeout`].
+        pub fn rustls_021_with_config(
+            mut self,
+            config: ServerConfig,
+            tls_acceptor_config: TlsAcceptorConfig,
+        ) -> impl ServiceFactory<
+            TcpStream,
+            Config = (),
+            Response = (),
+            Error = TlsError<io::Error, DispatchError>,
+            InitError = (),
+        > {
+            self.on_connect_ext =
+                with_tls_info(self.on_connect_ext.take(), TlsInfo::from_rustls_0_21);
+
+            let mut acceptor = Acceptor::new(config);
+
+            if let Some(handshake_timeout) = tls_acceptor_config.handshake_timeout {
+                acceptor.set_handshake_timeout(handshake_timeout);
+            }
+
+            acceptor
                 .map_init_err(|_| {
                     unreachable!("TLS acceptor service factory does not error on init")
                 })
@@ -285,6 +468,7 @@ mod rustls_0_22 {
     };
 
     use super::*;
+    use crate::{service::TlsAcceptorConfig, tls_info::with_tls_info, TlsInfo};
 
     impl<S, B, X, U> H1Service<TlsStream<TcpStream>, S, B, X, U>
     where
@@ -321,7 +505,34 @@ mod rustls_0_22 {
             Error = TlsError<io::Error, DispatchError>,
             InitError = (),
         > {
-            Acceptor::new(config)
+            self.rustls_0_22_with_config(config, TlsAcceptorConfig::default())
+        }
+
+        /// Create Rustls v0.22 based service with custom TLS acceptor configuration.
+        ///
+        /// Use [`TlsAcceptorConfig::handshake_timeout()`] to limit how long a client may take to
+        /// complete the TLS handshake. Connections that exceed it fail with [`TlsError::Timeout`].
+        pub fn rustls_0_22_with_config(
+            mut self,
+            config: ServerConfig,
+            tls_acceptor_config: TlsAcceptorConfig,
+        ) -> impl ServiceFactory<
+            TcpStream,
+            Config = (),
+            Response = (),
+            Error = TlsError<io::Error, DispatchError>,
+            InitError = (),
+        > {
+            self.on_connect_ext =
+                with_tls_info(self.on_connect_ext.take(), TlsInfo::from_rustls_0_22);
+
+            let mut acceptor = Acceptor::new(config);
+
+            if let Some(handshake_timeout) = tls_acceptor_config.handshake_timeout {
+                acceptor.set_handshake_timeout(handshake_timeout);
+            }
+
+            acceptor
                 .map_init_err(|_| {
                     unreachable!("TLS acceptor service factory does not error on init")
                 })
@@ -346,6 +557,7 @@ mod rustls_0_23 {
     };
 
     use super::*;
+    use crate::{service::TlsAcceptorConfig, tls_info::with_tls_info, TlsInfo};
 
     impl<S, B, X, U> H1Service<TlsStream<TcpStream>, S, B, X, U>
     where
@@ -382,7 +594,34 @@ mod rustls_0_23 {
             Error = TlsError<io::Error, DispatchError>,
             InitError = (),
         > {
-            Acceptor::new(config)
+            self.rustls_0_23_with_config(config, TlsAcceptorConfig::default())
+        }
+
+        /// Create Rustls v0.23 based service with custom TLS acceptor configuration.
+        ///
+        /// Use [`TlsAcceptorConfig::handshake_timeout()`] to limit how long a client may take to
+        /// complete the TLS handshake. Connections that exceed it fail with [`TlsError::Timeout`].
+        pub fn rustls_0_23_with_config(
+            mut self,
+            config: ServerConfig,
+            tls_acceptor_config: TlsAcceptorConfig,
+        ) -> impl ServiceFactory<
+            TcpStream,
+            Config = (),
+            Response = (),
+            Error = TlsError<io::Error, DispatchError>,
+            InitError = (),
+        > {
+            self.on_connect_ext =
+                with_tls_info(self.on_connect_ext.take(), TlsInfo::from_rustls_0_23);
+
+            let mut acceptor = Acceptor::new(config);
+
+            if let Some(handshake_timeout) = tls_acceptor_config.handshake_timeout {
+                acceptor.set_handshake_timeout(handshake_timeout);
+            }
+
+            acceptor
                 .map_init_err(|_| {
                     unreachable!("TLS acceptor service factory does not error on init")
                 })
@@ -404,6 +643,17 @@ where
     S::InitError: fmt::Debug,
     B: MessageBody,
 {
+    /// Sets service for `Expect: 100-Continue` handling.
+    ///
+    /// An expect service is called with requests that contain an `Expect` header. A successful
+    /// response type is also a request which will be forwarded to the main service.
+    ///
+    /// The request carries the client's [`peer_addr()`](Request::peer_addr) and the connection
+    /// data set by [on-connect callbacks](crate::HttpServiceBuilder::on_connect_ext) and TLS
+    /// acceptors, available through [`conn_data()`](Request::conn_data). This allows rejecting
+    /// uploads based on their headers and where they come from before the body is sent. On
+    /// error, the error response is sent in place of `100 Continue` and the connection is closed
+    /// without reading the body.
     pub fn expect<X1>(self, expect: X1) -> H1Service<T, S, B, X1, U>
     where
         X1: ServiceFactory<Request, Response = Request>,
@@ -416,6 +666,7 @@ where
             srv: self.srv,
             upgrade: self.upgrade,
             on_connect_ext: self.on_connect_ext,
+            diversions: self.diversions,
             _phantom: PhantomData,
         }
     }
@@ -432,6 +683,7 @@ where
             srv: self.srv,
             expect: self.expect,
             on_connect_ext: self.on_connect_ext,
+            diversions: self.diversions,
             _phantom: PhantomData,
         }
     }
@@ -441,6 +693,12 @@ where
         self.on_connect_ext = f;
         self
     }
+
+    /// Set protocols that connections are diverted to by their first bytes.
+    pub(crate) fn diversions(mut self, diversions: Option<Rc<Diversions<T>>>) -> Self {
+        self.diversions = diversions;
+        self
+    }
 }
 
 impl<T, S, B, X, U> ServiceFactory<(T, Option<net::SocketAddr>)> for H1Service<T, S, B, X, U>
@@ -477,6 +735,7 @@ where
         let expect = self.expect.new_service(());
         let upgrade = self.upgrade.as_ref().map(|s| s.new_service(()));
         let on_connect_ext = self.on_connect_ext.clone();
+        let diversions = self.diversions.clone();
         let cfg = self.cfg.clone();
 
         Box::pin(async move {
@@ -504,6 +763,7 @@ where
                 expect,
                 upgrade,
                 on_connect_ext,
+                diversions,
             ))
         })
     }
@@ -514,7 +774,7 @@ pub type H1ServiceHandler<T, S, B, X, U> = HttpServiceHandler<T, S, B, X, U>;
 
 impl<T, S, B, X, U> Service<(T, Option<net::SocketAddr>)> for HttpServiceHandler<T, S, B, X, U>
 where
-    T: AsyncRead + AsyncWrite + Unpin,
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
 
     S: Service<Request>,
     S::Error: Into<Response<BoxBody>>,
@@ -540,7 +800,8 @@ where
     }
 
     fn call(&self, (io, addr): (T, Option<net::SocketAddr>)) -> Self::Future {
-        let conn_data = OnConnectData::from_io(&io, self.on_connect_ext.as_deref());
+        let conn_data = OnConnectData::from_io(&io, self.on_connect_ext.as_deref(), &self.cfg);
         Dispatcher::new(io, Rc::clone(&self.flow), self.cfg.clone(), addr, conn_data)
+            .diversions(self.diversions.clone())
     }
 }
@@ -23,6 +23,10 @@ impl TimerState {
         matches!(self, Self::Active { .. } | Self::Inactive)
     }
 
+    pub(super) fn is_active(&self) -> bool {
+        matches!(self, Self::Active { .. })
+    }
+
     pub(super) fn set(&mut self, timer: Sleep, line: u32) {
         if matches!(self, Self::Disabled) {
             trace!("setting disabled timer from line {}", line);
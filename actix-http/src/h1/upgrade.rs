@@ -1,8 +1,25 @@
-use actix_codec::Framed;
+use std::{
+    error::Error as StdError,
+    fmt,
+    future::Future,
+    io,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_codec::{AsyncRead, AsyncWrite, Framed, ReadBuf};
 use actix_service::{Service, ServiceFactory};
+use actix_utils::future::{ready, Ready};
+use bytes::{Buf as _, BytesMut};
 use futures_core::future::LocalBoxFuture;
+use pin_project_lite::pin_project;
 
-use crate::{h1::Codec, Error, Request};
+use crate::{
+    body::{self, BoxBody},
+    h1::{Codec, SendResponse},
+    Error, Method, Request, Response, StatusCode, Uri,
+};
 
 pub struct UpgradeHandler;
 
@@ -30,3 +47,457 @@ impl<T> Service<(Request, Framed<T, Codec>)> for UpgradeHandler {
         unimplemented!()
     }
 }
+
+/// Creates an upgrade service from an async function that accepts or rejects upgrade requests.
+///
+/// The function is called with each request that asks for a protocol upgrade. To accept it, the
+/// function resolves to the response to send, usually `101 Switching Protocols`, and a protocol
+/// handler. Once the response is written, the handler is called with the connection's I/O as an
+/// [`Upgraded`] stream and runs until the protocol is done. To reject it, the function resolves
+/// to an error response, which is sent before the connection is closed.
+///
+/// Errors returned from the handler are logged by the dispatcher. The handler should flush its
+/// writes before returning, since some transports only complete buffered writes when flushed.
+///
+/// The returned service can be passed to [`HttpServiceBuilder::upgrade()`], and to the `upgrade`
+/// methods of [`HttpService`](crate::HttpService) and [`H1Service`](super::H1Service).
+///
+/// # Examples
+/// ```
+/// use actix_http::{h1, HttpMessage as _, HttpService, Request, Response, StatusCode};
+/// use tokio::io::{AsyncBufReadExt as _, AsyncWriteExt as _, BufReader};
+///
+/// # actix_rt::System::new().block_on(async {
+/// // a line-based echo protocol
+/// let srv = HttpService::build()
+///     .upgrade(h1::upgrade_fn(|req: Request| async move {
+///         if req.headers().get("upgrade").map_or(true, |proto| proto != "echo") {
+///             return Err(Response::bad_request());
+///         }
+///
+///         let res = Response::build(StatusCode::SWITCHING_PROTOCOLS)
+///             .insert_header(("upgrade", "echo"))
+///             .finish()
+///             .drop_body();
+///
+///         Ok((res, |io: h1::Upgraded<_>| async move {
+///             let mut io = BufReader::new(io);
+///             let mut line = String::new();
+///
+///             while io.read_line(&mut line).await? > 0 {
+///                 io.write_all(line.as_bytes()).await?;
+///                 io.flush().await?;
+///                 line.clear();
+///             }
+///
+///             Ok::<_, std::io::Error>(())
+///         }))
+///     }))
+///     .finish(|_: Request| async { Ok::<_, std::convert::Infallible>(Response::ok()) })
+///     .tcp();
+/// # })
+/// ```
+///
+/// [`HttpServiceBuilder::upgrade()`]: crate::HttpServiceBuilder::upgrade
+pub fn upgrade_fn<F, T>(f: F) -> UpgradeFn<F, T> {
+    UpgradeFn {
+        f,
+        _phantom: PhantomData,
+    }
+}
+
+/// Upgrade service created by [`upgrade_fn()`].
+pub struct UpgradeFn<F, T> {
+    f: F,
+    _phantom: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, T> Clone for UpgradeFn<F, T> {
+    fn clone(&self) -> Self {
+        upgrade_fn(self.f.clone())
+    }
+}
+
+impl<F, Fut, H, HFut, R, E, T> ServiceFactory<(Request, Framed<T, Codec>)> for UpgradeFn<F, T>
+where
+    F: Fn(Request) -> Fut + Clone,
+    Fut: Future<Output = Result<(Response<()>, H), R>> + 'static,
+    H: FnOnce(Upgraded<T>) -> HFut + 'static,
+    HFut: Future<Output = Result<(), E>>,
+    R: Into<Response<BoxBody>>,
+    E: Into<Box<dyn StdError>>,
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    type Response = ();
+    type Error = Error;
+    type Config = ();
+    type Service = Self;
+    type InitError = ();
+    type Future = Ready<Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        ready(Ok(self.clone()))
+    }
+}
+
+impl<F, Fut, H, HFut, R, E, T> Service<(Request, Framed<T, Codec>)> for UpgradeFn<F, T>
+where
+    F: Fn(Request) -> Fut,
+    Fut: Future<Output = Result<(Response<()>, H), R>> + 'static,
+    H: FnOnce(Upgraded<T>) -> HFut + 'static,
+    HFut: Future<Output = Result<(), E>>,
+    R: Into<Response<BoxBody>>,
+    E: Into<Box<dyn StdError>>,
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    type Response = ();
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::always_ready!();
+
+    fn call(&self, (req, framed): (Request, Framed<T, Codec>)) -> Self::Future {
+        let fut = (self.f)(req);
+
+        Box::pin(async move {
+            match fut.await {
+                Ok((res, handler)) => {
+                    let parts = SendResponse::new(framed, res).await?.into_parts();
+
+                    let io = Upgraded {
+                        io: parts.io,
+                        read_buf: parts.read_buf,
+                    };
+
+                    handler(io)
+                        .await
+                        .map_err(|err| Error::new_io().with_cause(err))
+                }
+
+                Err(res) => send_rejection(framed, res.into()).await,
+            }
+        })
+    }
+}
+
+/// Creates a tunnel service for `CONNECT` requests from an async function that accepts or rejects
+/// them.
+///
+/// This is the building block of a forward proxy. The function is called with each `CONNECT`
+/// request whose target is in authority form (`host:port`), available from
+/// [`Request::uri()`](crate::Request::uri). To accept it, the function resolves to a tunnel
+/// handler; a `200 OK` response without payload is sent and the handler is called with the
+/// connection's I/O as an [`Upgraded`] stream, to be connected to the target. To reject it, for
+/// example with `407 Proxy Authentication Required` when the `Proxy-Authorization` header is
+/// missing, the function resolves to an error response, which is sent before the connection is
+/// closed.
+///
+/// Other requests that are handed to the upgrade service are rejected without calling the
+/// function: `CONNECT` requests with a target in any other form with `400 Bad Request`, and
+/// protocol upgrade requests with `501 Not Implemented`.
+///
+/// The returned service can be passed to [`HttpServiceBuilder::upgrade()`], and to the `upgrade`
+/// methods of [`HttpService`](crate::HttpService) and [`H1Service`](super::H1Service). Without an
+/// upgrade service, `CONNECT` requests are passed to the regular service.
+///
+/// # Examples
+/// ```
+/// use actix_http::{h1, header, HttpMessage as _, HttpService, Request, Response, StatusCode};
+///
+/// # actix_rt::System::new().block_on(async {
+/// let srv = HttpService::build()
+///     .upgrade(h1::connect_fn(|req: Request| async move {
+///         if !req.headers().contains_key(header::PROXY_AUTHORIZATION) {
+///             return Err(Response::build(StatusCode::PROXY_AUTHENTICATION_REQUIRED)
+///                 .insert_header((header::PROXY_AUTHENTICATE, "Basic"))
+///                 .finish()
+///                 .map_into_boxed_body());
+///         }
+///
+///         let target = req.uri().authority().unwrap().to_string();
+///
+///         Ok(|mut io: h1::Upgraded<_>| async move {
+///             let mut upstream = tokio::net::TcpStream::connect(target).await?;
+///             tokio::io::copy_bidirectional(&mut io, &mut upstream).await?;
+///             Ok::<_, std::io::Error>(())
+///         })
+///     }))
+///     .finish(|_: Request| async { Ok::<_, std::convert::Infallible>(Response::ok()) })
+///     .tcp();
+/// # })
+/// ```
+///
+/// [`HttpServiceBuilder::upgrade()`]: crate::HttpServiceBuilder::upgrade
+pub fn connect_fn<F, T>(f: F) -> ConnectFn<F, T> {
+    ConnectFn {
+        f,
+        _phantom: PhantomData,
+    }
+}
+
+/// Tunnel service created by [`connect_fn()`].
+pub struct ConnectFn<F, T> {
+    f: F,
+    _phantom: PhantomData<fn(T)>,
+}
+
+impl<F: Clone, T> Clone for ConnectFn<F, T> {
+    fn clone(&self) -> Self {
+        connect_fn(self.f.clone())
+    }
+}
+
+impl<F, Fut, H, HFut, R, E, T> ServiceFactory<(Request, Framed<T, Codec>)> for ConnectFn<F, T>
+where
+    F: Fn(Request) -> Fut + Clone,
+    Fut: Future<Output = Result<H, R>> + 'static,
+    H: FnOnce(Upgraded<T>) -> HFut + 'static,
+    HFut: Future<Output = Result<(), E>>,
+    R: Into<Response<BoxBody>>,
+    E: Into<Box<dyn StdError>>,
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    type Response = ();
+    type Error = Error;
+    type Config = ();
+    type Service = Self;
+    type InitError = ();
+    type Future = Ready<Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        ready(Ok(self.clone()))
+    }
+}
+
+impl<F, Fut, H, HFut, R, E, T> Service<(Request, Framed<T, Codec>)> for ConnectFn<F, T>
+where
+    F: Fn(Request) -> Fut,
+    Fut: Future<Output = Result<H, R>> + 'static,
+    H: FnOnce(Upgraded<T>) -> HFut + 'static,
+    HFut: Future<Output = Result<(), E>>,
+    R: Into<Response<BoxBody>>,
+    E: Into<Box<dyn StdError>>,
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    type Response = ();
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::always_ready!();
+
+    fn call(&self, (req, framed): (Request, Framed<T, Codec>)) -> Self::Future {
+        if req.method() != Method::CONNECT {
+            let res = Response::new(StatusCode::NOT_IMPLEMENTED);
+            return Box::pin(send_rejection(framed, res));
+        }
+
+        if !is_authority_form(req.uri()) {
+            return Box::pin(send_rejection(framed, Response::bad_request()));
+        }
+
+        let fut = (self.f)(req);
+
+        Box::pin(async move {
+            match fut.await {
+                Ok(handler) => {
+                    // 2xx responses to CONNECT must not have content-length or transfer-encoding
+                    let res = Response::with_body(StatusCode::OK, body::None::new());
+                    let parts = SendResponse::new(framed, res).await?.into_parts();
+
+                    let io = Upgraded {
+                        io: parts.io,
+                        read_buf: parts.read_buf,
+                    };
+
+                    handler(io)
+                        .await
+                        .map_err(|err| Error::new_io().with_cause(err))
+                }
+
+                Err(res) => send_rejection(framed, res.into()).await,
+            }
+        })
+    }
+}
+
+/// Returns true if the URI is a `CONNECT` target in authority form, i.e. `host:port`.
+fn is_authority_form(uri: &Uri) -> bool {
+    uri.scheme().is_none()
+        && uri.path_and_query().is_none()
+        && uri
+            .authority()
+            .is_some_and(|auth| auth.port_u16().is_some())
+}
+
+async fn send_rejection<T>(framed: Framed<T, Codec>, res: Response<BoxBody>) -> Result<(), Error>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let (res, body) = res.into_parts();
+    let body = body::to_bytes(body)
+        .await
+        .map_err(|err| Error::new_body().with_cause(err))?;
+    SendResponse::new(framed, res.set_body(body)).await?;
+    Ok(())
+}
+
+pin_project! {
+    /// I/O of a connection after a protocol upgrade.
+    ///
+    /// Bytes that the client sent after the upgrade request, and that were already read by the
+    /// HTTP/1 dispatcher, are returned by the first reads before reading from the connection.
+    pub struct Upgraded<T> {
+        #[pin]
+        io: T,
+        read_buf: BytesMut,
+    }
+}
+
+impl<T> Upgraded<T> {
+    pub(super) fn new(io: T, read_buf: BytesMut) -> Self {
+        Self { io, read_buf }
+    }
+
+    /// Returns a reference to the connection's I/O.
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    /// Returns the connection's I/O and the bytes that were read from it but not returned yet.
+    pub fn into_parts(self) -> (T, BytesMut) {
+        (self.io, self.read_buf)
+    }
+}
+
+impl<T: AsyncRead> AsyncRead for Upgraded<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.project();
+
+        if this.read_buf.is_empty() {
+            return this.io.poll_read(cx, buf);
+        }
+
+        let len = this.read_buf.len().min(buf.remaining());
+        buf.put_slice(&this.read_buf[..len]);
+        this.read_buf.advance(len);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncWrite> AsyncWrite for Upgraded<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.project().io.poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.project().io.poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_shutdown(cx)
+    }
+}
+
+/// Extension trait for switching an HTTP/1 [`Framed`] transport to another protocol's codec.
+///
+/// Upgrade services receive the connection as a `Framed` transport using the HTTP/1 [`Codec`].
+/// Once the upgrade response has been written to it, [`switch_codec()`](Self::switch_codec)
+/// replaces the codec while keeping the transport's read and write buffers. Bytes that the client
+/// sent after the upgrade request are decoded with the new codec, and the upgrade response is
+/// still flushed if it was not already.
+///
+/// # Examples
+/// ```
+/// use actix_codec::{AsyncRead, AsyncWrite, Framed, LinesCodec};
+/// use actix_http::{body::BodySize, h1::{self, FramedExt as _}, Request, Response, StatusCode};
+/// use futures_util::{SinkExt as _, StreamExt as _};
+///
+/// async fn echo<T>(req: Request, mut framed: Framed<T, h1::Codec>) -> std::io::Result<()>
+/// where
+///     T: AsyncRead + AsyncWrite + Unpin,
+/// {
+///     let res = Response::build(StatusCode::SWITCHING_PROTOCOLS)
+///         .upgrade("echo")
+///         .finish()
+///         .drop_body();
+///     framed.send((res, BodySize::None).into()).await?;
+///
+///     let mut framed = framed
+///         .switch_codec(LinesCodec::default())
+///         .map_err(|_| std::io::ErrorKind::InvalidData)?;
+///
+///     while let Some(line) = framed.next().await {
+///         framed.send(line?).await?;
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub trait FramedExt<T> {
+    /// Replaces the HTTP/1 codec with `codec`, keeping buffered bytes.
+    ///
+    /// # Errors
+    /// Returns an error, from which the transport can be recovered, if a request has not been
+    /// responded to yet or the body of the last response has not been written in full. Switching
+    /// codecs in the middle of a message would mix HTTP/1 framing into the new protocol's stream.
+    fn switch_codec<C>(self, codec: C) -> Result<Framed<T, C>, SwitchCodecError<T>>;
+}
+
+impl<T> FramedExt<T> for Framed<T, Codec> {
+    fn switch_codec<C>(self, codec: C) -> Result<Framed<T, C>, SwitchCodecError<T>> {
+        if !self.codec_ref().is_message_complete() {
+            return Err(SwitchCodecError {
+                framed: Box::new(self),
+            });
+        }
+
+        Ok(self.replace_codec(codec))
+    }
+}
+
+/// Error returned by [`FramedExt::switch_codec()`] while an HTTP/1 message is not complete.
+pub struct SwitchCodecError<T> {
+    framed: Box<Framed<T, Codec>>,
+}
+
+impl<T> SwitchCodecError<T> {
+    /// Returns the transport that was not switched.
+    pub fn into_inner(self) -> Framed<T, Codec> {
+        *self.framed
+    }
+}
+
+impl<T> fmt::Debug for SwitchCodecError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SwitchCodecError").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for SwitchCodecError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("HTTP/1 message is not complete")
+    }
+}
+
+impl<T> StdError for SwitchCodecError<T> {}
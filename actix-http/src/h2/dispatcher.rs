@@ -7,6 +7,7 @@ use std::{
     pin::{pin, Pin},
     rc::Rc,
     task::{Context, Poll},
+    time::{Duration, Instant},
 };
 
 use actix_codec::{AsyncRead, AsyncWrite};
@@ -14,21 +15,24 @@ use actix_rt::time::{sleep, Sleep};
 use actix_service::Service;
 use actix_utils::future::poll_fn;
 use bytes::{Bytes, BytesMut};
-use futures_core::ready;
+use futures_core::{future::LocalBoxFuture, ready};
 use h2::{
     server::{Connection, SendResponse},
     Ping, PingPong,
 };
 use pin_project_lite::pin_project;
 
+use super::ObservedIo;
 use crate::{
     body::{BodySize, BoxBody, MessageBody},
     config::ServiceConfig,
     header::{
         HeaderName, HeaderValue, CONNECTION, CONTENT_LENGTH, DATE, TRANSFER_ENCODING, UPGRADE,
     },
+    observer::ConnectionEvents,
     service::HttpFlow,
-    Extensions, Method, OnConnectData, Payload, Request, Response, ResponseHead,
+    DisconnectReason, Extensions, Method, OnConnectData, OnDisconnect, Payload, Request, Response,
+    ResponseHead,
 };
 
 const CHUNK_SIZE: usize = 16_384;
@@ -37,11 +41,14 @@ pin_project! {
     /// Dispatcher for HTTP/2 protocol.
     pub struct Dispatcher<T, S, B, X, U> {
         flow: Rc<HttpFlow<S, X, U>>,
-        connection: Connection<T, Bytes>,
+        connection: Connection<ObservedIo<T>, Bytes>,
         conn_data: Option<Rc<Extensions>>,
         config: ServiceConfig,
         peer_addr: Option<net::SocketAddr>,
         ping_pong: Option<H2PingPong>,
+        shutdown: Option<LocalBoxFuture<'static, ()>>,
+        events: ConnectionEvents,
+        on_disconnect: OnDisconnect,
         _phantom: PhantomData<B>
     }
 }
@@ -51,32 +58,41 @@ where
     T: AsyncRead + AsyncWrite + Unpin,
 {
     pub(crate) fn new(
-        mut conn: Connection<T, Bytes>,
+        mut conn: Connection<ObservedIo<T>, Bytes>,
         flow: Rc<HttpFlow<S, X, U>>,
         config: ServiceConfig,
         peer_addr: Option<net::SocketAddr>,
         conn_data: OnConnectData,
         timer: Option<Pin<Box<Sleep>>>,
+        events: ConnectionEvents,
     ) -> Self {
-        let ping_pong = config.keep_alive().duration().map(|dur| H2PingPong {
+        crate::message::set_pool_size(config.message_pool_size());
+        let (conn_data, on_disconnect) = conn_data.into_parts();
+
+        let ping_pong = config.h2_keep_alive_interval().map(|interval| H2PingPong {
             timer: timer
                 .map(|mut timer| {
                     // reuse timer slot if it was initialized for handshake
-                    timer.as_mut().reset((config.now() + dur).into());
+                    timer.as_mut().reset((Instant::now() + interval).into());
                     timer
                 })
-                .unwrap_or_else(|| Box::pin(sleep(dur))),
+                .unwrap_or_else(|| Box::pin(sleep(interval))),
             in_flight: false,
             ping_pong: conn.ping_pong().unwrap(),
+            interval,
+            timeout: config.h2_keep_alive_timeout().unwrap_or(interval),
         });
 
         Self {
             flow,
+            shutdown: config.shutdown_notified(),
             config,
             peer_addr,
             connection: conn,
-            conn_data: conn_data.0.map(Rc::new),
+            conn_data,
             ping_pong,
+            events,
+            on_disconnect,
             _phantom: PhantomData,
         }
     }
@@ -89,11 +105,20 @@ struct H2PingPong {
     /// True when a ping has been sent and is waiting for a reply.
     in_flight: bool,
 
-    /// Timeout for pong response.
+    /// Timer for sending the next ping or, while a ping is in flight, for the pong response.
+    ///
+    /// Deadlines use the current time rather than the cached date service time since ping
+    /// intervals may be shorter than the date service's update interval.
     timer: Pin<Box<Sleep>>,
+
+    /// Idle duration after which a ping is sent.
+    interval: Duration,
+
+    /// Duration to wait for a pong response before closing the connection.
+    timeout: Duration,
 }
 
-impl<T, S, B, X, U> Future for Dispatcher<T, S, B, X, U>
+impl<T, S, B, X, U> Dispatcher<T, S, B, X, U>
 where
     T: AsyncRead + AsyncWrite + Unpin,
 
@@ -104,15 +129,42 @@ where
 
     B: MessageBody,
 {
-    type Output = Result<(), crate::error::DispatchError>;
-
-    #[inline]
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = self.get_mut();
+    fn poll_dispatcher(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), crate::error::DispatchError>> {
+        let this = self;
+
+        if let Some(shutdown) = this.shutdown.as_mut() {
+            if shutdown.as_mut().poll(cx).is_ready() {
+                // send GOAWAY and close the connection once in-flight streams have completed
+                tracing::trace!("graceful shutdown started; draining connection");
+                this.shutdown = None;
+                this.connection.graceful_shutdown();
+            }
+        }
 
         loop {
-            match Pin::new(&mut this.connection).poll_accept(cx)? {
+            let accept = Pin::new(&mut this.connection).poll_accept(cx);
+            let accept = match accept {
+                Poll::Ready(Some(Err(err))) => {
+                    return Poll::Ready(Err(super::protocol_error(&this.events, err)));
+                }
+                Poll::Ready(Some(Ok(item))) => Poll::Ready(Some(item)),
+                Poll::Ready(None) => Poll::Ready(None),
+                Poll::Pending => Poll::Pending,
+            };
+
+            match accept {
                 Poll::Ready(Some((req, tx))) => {
+                    // connection is not idle; postpone the next ping
+                    if let Some(ping_pong) = this.ping_pong.as_mut() {
+                        if !ping_pong.in_flight {
+                            let deadline = Instant::now() + ping_pong.interval;
+                            ping_pong.timer.as_mut().reset(deadline.into());
+                        }
+                    }
+
                     let (parts, body) = req.into_parts();
                     let payload = crate::h2::Payload::new(body);
                     let pl = Payload::H2 { payload };
@@ -127,19 +179,33 @@ where
                     head.peer_addr = this.peer_addr;
 
                     req.conn_data.clone_from(&this.conn_data);
+                    this.events.request_started(req.head());
+
+                    // TODO: attach an `EarlyHints` handle, as the h1 dispatcher does, once the h2
+                    // crate supports sending informational responses
 
-                    let fut = this.flow.service.call(req);
+                    // rejected requests are answered without calling the service
+                    let fut = match this.config.on_request_head(&mut req) {
+                        Ok(()) => Ok(this.flow.service.call(req)),
+                        Err(res) => Err(res),
+                    };
                     let config = this.config.clone();
+                    let events = this.events.clone();
 
                     // multiplex request handling with spawn task
                     actix_rt::spawn(async move {
                         // resolve service call and send response.
-                        let res = match fut.await {
-                            Ok(res) => handle_response(res.into(), tx, config, head_req).await,
-                            Err(err) => {
-                                let res: Response<BoxBody> = err.into();
-                                handle_response(res, tx, config, head_req).await
-                            }
+                        let res = match fut {
+                            Ok(fut) => match fut.await {
+                                Ok(res) => {
+                                    handle_response(res.into(), tx, config, &events, head_req).await
+                                }
+                                Err(err) => {
+                                    let res: Response<BoxBody> = err.into();
+                                    handle_response(res, tx, config, &events, head_req).await
+                                }
+                            },
+                            Err(res) => handle_response(res, tx, config, &events, head_req).await,
                         };
 
                         // log error.
@@ -170,11 +236,16 @@ where
                                 Poll::Ready(_) => {
                                     ping_pong.in_flight = false;
 
-                                    let dead_line = this.config.keep_alive_deadline().unwrap();
-                                    ping_pong.timer.as_mut().reset(dead_line.into());
+                                    let deadline = Instant::now() + ping_pong.interval;
+                                    ping_pong.timer.as_mut().reset(deadline.into());
                                 }
                                 Poll::Pending => {
-                                    return ping_pong.timer.as_mut().poll(cx).map(|_| Ok(()));
+                                    ready!(ping_pong.timer.as_mut().poll(cx));
+                                    tracing::trace!(
+                                        "ping was not acknowledged in time; closing connection"
+                                    );
+                                    this.on_disconnect.notify(DisconnectReason::Timeout);
+                                    return Poll::Ready(Ok(()));
                                 }
                             }
                         } else {
@@ -185,8 +256,8 @@ where
 
                             ping_pong.ping_pong.send_ping(Ping::opaque())?;
 
-                            let dead_line = this.config.keep_alive_deadline().unwrap();
-                            ping_pong.timer.as_mut().reset(dead_line.into());
+                            let deadline = Instant::now() + ping_pong.timeout;
+                            ping_pong.timer.as_mut().reset(deadline.into());
 
                             ping_pong.in_flight = true;
                         }
@@ -198,6 +269,31 @@ where
     }
 }
 
+impl<T, S, B, X, U> Future for Dispatcher<T, S, B, X, U>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+
+    S: Service<Request>,
+    S::Error: Into<Response<BoxBody>>,
+    S::Future: 'static,
+    S::Response: Into<Response<B>>,
+
+    B: MessageBody,
+{
+    type Output = Result<(), crate::error::DispatchError>;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        let res = ready!(this.poll_dispatcher(cx));
+        this.on_disconnect
+            .notify(DisconnectReason::from_result(&res));
+
+        Poll::Ready(res)
+    }
+}
+
 enum DispatchError {
     SendResponse(h2::Error),
     SendData(h2::Error),
@@ -208,12 +304,14 @@ async fn handle_response<B>(
     res: Response<B>,
     mut tx: SendResponse<Bytes>,
     config: ServiceConfig,
+    events: &ConnectionEvents,
     head_req: bool,
 ) -> Result<(), DispatchError>
 where
     B: MessageBody,
 {
     let (res, body) = res.replace_body(());
+    let status = res.status();
 
     // prepare response.
     let mut size = body.size();
@@ -226,6 +324,7 @@ where
         .map_err(DispatchError::SendResponse)?;
 
     if eof_or_head {
+        events.request_finished(status);
         return Ok(());
     }
 
@@ -265,10 +364,18 @@ where
         }
     }
 
-    // response body streaming finished. send end of stream and return.
-    stream
-        .send_data(Bytes::new(), true)
-        .map_err(DispatchError::SendData)?;
+    // response body streaming finished. send trailers or end of stream and return.
+    match body.as_mut().take_trailers() {
+        Some(trailers) => stream
+            .send_trailers(trailers.into())
+            .map_err(DispatchError::SendData)?,
+
+        None => stream
+            .send_data(Bytes::new(), true)
+            .map_err(DispatchError::SendData)?,
+    }
+
+    events.request_finished(status);
 
     Ok(())
 }
@@ -2,22 +2,25 @@
 
 use std::{
     future::Future,
+    io, net,
     pin::Pin,
     task::{Context, Poll},
 };
 
-use actix_codec::{AsyncRead, AsyncWrite};
+use actix_codec::{AsyncRead, AsyncWrite, ReadBuf};
 use actix_rt::time::{sleep_until, Sleep};
 use bytes::Bytes;
 use futures_core::{ready, Stream};
 use h2::{
-    server::{handshake, Connection, Handshake},
+    server::{Builder, Connection, Handshake},
     RecvStream,
 };
 
 use crate::{
     config::ServiceConfig,
     error::{DispatchError, PayloadError},
+    observer::ConnectionEvents,
+    Protocol,
 };
 
 mod dispatcher;
@@ -57,35 +60,140 @@ impl Stream for Payload {
     }
 }
 
-pub(crate) fn handshake_with_timeout<T>(io: T, config: &ServiceConfig) -> HandshakeWithTimeout<T>
+/// I/O stream that reports transferred bytes to the connection observer.
+pub(crate) struct ObservedIo<T> {
+    io: T,
+    events: ConnectionEvents,
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for ObservedIo<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let filled = buf.filled().len();
+
+        ready!(Pin::new(&mut this.io).poll_read(cx, buf))?;
+
+        let n = buf.filled().len() - filled;
+        if n > 0 {
+            this.events.bytes_read(n);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for ObservedIo<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let n = ready!(Pin::new(&mut this.io).poll_write(cx, buf))?;
+        this.events.bytes_written(n);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let n = ready!(Pin::new(&mut this.io).poll_write_vectored(cx, bufs))?;
+        this.events.bytes_written(n);
+        Poll::Ready(Ok(n))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
+
+pub(crate) fn handshake_with_timeout<T>(
+    io: T,
+    config: &ServiceConfig,
+    peer_addr: Option<net::SocketAddr>,
+) -> HandshakeWithTimeout<T>
 where
     T: AsyncRead + AsyncWrite + Unpin,
 {
+    let events = ConnectionEvents::open(config, Protocol::Http2, peer_addr);
+
+    let settings = config.h2_settings();
+    let mut builder = Builder::new();
+
+    if let Some(size) = settings.initial_window_size {
+        builder.initial_window_size(size);
+    }
+    if let Some(size) = settings.initial_connection_window_size {
+        builder.initial_connection_window_size(size);
+    }
+    if let Some(max) = settings.max_concurrent_streams {
+        builder.max_concurrent_streams(max);
+    }
+    if let Some(max) = settings.max_frame_size {
+        builder.max_frame_size(max);
+    }
+    if let Some(max) = settings.max_header_list_size {
+        builder.max_header_list_size(max);
+    }
+
     HandshakeWithTimeout {
-        handshake: handshake(io),
+        handshake: builder.handshake(ObservedIo {
+            io,
+            events: events.clone(),
+        }),
         timer: config
             .client_request_deadline()
             .map(|deadline| Box::pin(sleep_until(deadline.into()))),
+        events,
     }
 }
 
 pub(crate) struct HandshakeWithTimeout<T: AsyncRead + AsyncWrite + Unpin> {
-    handshake: Handshake<T>,
+    handshake: Handshake<ObservedIo<T>>,
     timer: Option<Pin<Box<Sleep>>>,
+    events: ConnectionEvents,
 }
 
 impl<T> Future for HandshakeWithTimeout<T>
 where
     T: AsyncRead + AsyncWrite + Unpin,
 {
-    type Output = Result<(Connection<T, Bytes>, Option<Pin<Box<Sleep>>>), DispatchError>;
+    #[allow(clippy::type_complexity)]
+    type Output = Result<
+        (
+            Connection<ObservedIo<T>, Bytes>,
+            Option<Pin<Box<Sleep>>>,
+            ConnectionEvents,
+        ),
+        DispatchError,
+    >;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.get_mut();
 
-        match Pin::new(&mut this.handshake).poll(cx)? {
+        match Pin::new(&mut this.handshake).poll(cx) {
             // return the timer on success handshake; its slot can be re-used for h2 ping-pong
-            Poll::Ready(conn) => Poll::Ready(Ok((conn, this.timer.take()))),
+            Poll::Ready(Ok(conn)) => {
+                Poll::Ready(Ok((conn, this.timer.take(), this.events.clone())))
+            }
+
+            Poll::Ready(Err(err)) => Poll::Ready(Err(protocol_error(&this.events, err))),
+
             Poll::Pending => match this.timer.as_mut() {
                 Some(timer) => {
                     ready!(timer.as_mut().poll(cx));
@@ -97,6 +205,18 @@ where
     }
 }
 
+/// Converts an h2 error, reporting it to the connection observer unless it is an I/O error.
+pub(crate) fn protocol_error(events: &ConnectionEvents, err: h2::Error) -> DispatchError {
+    let is_io = err.is_io();
+    let err = DispatchError::H2(err);
+
+    if !is_io {
+        events.protocol_error(&err, &[]);
+    }
+
+    err
+}
+
 #[cfg(test)]
 mod tests {
     use static_assertions::assert_impl_all;
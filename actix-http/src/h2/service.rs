@@ -104,6 +104,7 @@ mod openssl {
     };
 
     use super::*;
+    use crate::{tls_info::with_tls_info, TlsInfo};
 
     impl<S, B> H2Service<TlsStream<TcpStream>, S, B>
     where
@@ -117,7 +118,7 @@ mod openssl {
     {
         /// Create OpenSSL based service.
         pub fn openssl(
-            self,
+            mut self,
             acceptor: SslAcceptor,
         ) -> impl ServiceFactory<
             TcpStream,
@@ -126,6 +127,8 @@ mod openssl {
             Error = TlsError<SslError, DispatchError>,
             InitError = S::InitError,
         > {
+            self.on_connect_ext = with_tls_info(self.on_connect_ext.take(), TlsInfo::from_openssl);
+
             Acceptor::new(acceptor)
                 .map_init_err(|_| {
                     unreachable!("TLS acceptor service factory does not error on init")
@@ -151,6 +154,7 @@ mod rustls_0_20 {
     };
 
     use super::*;
+    use crate::{tls_info::with_tls_info, TlsInfo};
 
     impl<S, B> H2Service<TlsStream<TcpStream>, S, B>
     where
@@ -164,7 +168,7 @@ mod rustls_0_20 {
     {
         /// Create Rustls v0.20 based service.
         pub fn rustls(
-            self,
+            mut self,
             mut config: ServerConfig,
         ) -> impl ServiceFactory<
             TcpStream,
@@ -173,6 +177,9 @@ mod rustls_0_20 {
             Error = TlsError<io::Error, DispatchError>,
             InitError = S::InitError,
         > {
+            self.on_connect_ext =
+                with_tls_info(self.on_connect_ext.take(), TlsInfo::from_rustls_0_20);
+
             let mut protos = vec![b"h2".to_vec()];
             protos.extend_from_slice(&config.alpn_protocols);
             config.alpn_protocols = protos;
@@ -202,6 +209,7 @@ mod rustls_0_21 {
     };
 
     use super::*;
+    use crate::{tls_info::with_tls_info, TlsInfo};
 
     impl<S, B> H2Service<TlsStream<TcpStream>, S, B>
     where
@@ -215,7 +223,7 @@ mod rustls_0_21 {
     {
         /// Create Rustls v0.21 based service.
         pub fn rustls_021(
-            self,
+            mut self,
             mut config: ServerConfig,
         ) -> impl ServiceFactory<
             TcpStream,
@@ -224,6 +232,9 @@ mod rustls_0_21 {
             Error = TlsError<io::Error, DispatchError>,
             InitError = S::InitError,
         > {
+            self.on_connect_ext =
+                with_tls_info(self.on_connect_ext.take(), TlsInfo::from_rustls_0_21);
+
             let mut protos = vec![b"h2".to_vec()];
             protos.extend_from_slice(&config.alpn_protocols);
             config.alpn_protocols = protos;
@@ -253,6 +264,7 @@ mod rustls_0_22 {
     };
 
     use super::*;
+    use crate::{tls_info::with_tls_info, TlsInfo};
 
     impl<S, B> H2Service<TlsStream<TcpStream>, S, B>
     where
@@ -266,7 +278,7 @@ mod rustls_0_22 {
     {
         /// Create Rustls v0.22 based service.
         pub fn rustls_0_22(
-            self,
+            mut self,
             mut config: ServerConfig,
         ) -> impl ServiceFactory<
             TcpStream,
@@ -275,6 +287,9 @@ mod rustls_0_22 {
             Error = TlsError<io::Error, DispatchError>,
             InitError = S::InitError,
         > {
+            self.on_connect_ext =
+                with_tls_info(self.on_connect_ext.take(), TlsInfo::from_rustls_0_22);
+
             let mut protos = vec![b"h2".to_vec()];
             protos.extend_from_slice(&config.alpn_protocols);
             config.alpn_protocols = protos;
@@ -304,6 +319,7 @@ mod rustls_0_23 {
     };
 
     use super::*;
+    use crate::{tls_info::with_tls_info, TlsInfo};
 
     impl<S, B> H2Service<TlsStream<TcpStream>, S, B>
     where
@@ -317,7 +333,7 @@ mod rustls_0_23 {
     {
         /// Create Rustls v0.23 based service.
         pub fn rustls_0_23(
-            self,
+            mut self,
             mut config: ServerConfig,
         ) -> impl ServiceFactory<
             TcpStream,
@@ -326,6 +342,9 @@ mod rustls_0_23 {
             Error = TlsError<io::Error, DispatchError>,
             InitError = S::InitError,
         > {
+            self.on_connect_ext =
+                with_tls_info(self.on_connect_ext.take(), TlsInfo::from_rustls_0_23);
+
             let mut protos = vec![b"h2".to_vec()];
             protos.extend_from_slice(&config.alpn_protocols);
             config.alpn_protocols = protos;
@@ -430,7 +449,8 @@ where
     }
 
     fn call(&self, (io, addr): (T, Option<net::SocketAddr>)) -> Self::Future {
-        let on_connect_data = OnConnectData::from_io(&io, self.on_connect_ext.as_deref());
+        let on_connect_data =
+            OnConnectData::from_io(&io, self.on_connect_ext.as_deref(), &self.cfg);
 
         H2ServiceHandlerResponse {
             state: State::Handshake(
@@ -438,7 +458,7 @@ where
                 Some(self.cfg.clone()),
                 addr,
                 on_connect_data,
-                handshake_with_timeout(io, &self.cfg),
+                handshake_with_timeout(io, &self.cfg, addr),
             ),
         }
     }
@@ -491,7 +511,7 @@ where
                 ref mut conn_data,
                 ref mut handshake,
             ) => match ready!(Pin::new(handshake).poll(cx)) {
-                Ok((conn, timer)) => {
+                Ok((conn, timer, events)) => {
                     let on_connect_data = mem::take(conn_data);
 
                     self.state = State::Established(Dispatcher::new(
@@ -501,6 +521,7 @@ where
                         *peer_addr,
                         on_connect_data,
                         timer,
+                        events,
                     ));
 
                     self.poll(cx)
@@ -508,7 +529,9 @@ where
 
                 Err(err) => {
                     trace!("H2 handshake error: {}", err);
-                    Poll::Ready(Err(err))
+                    let res = Err(err);
+                    conn_data.disconnected(&res);
+                    Poll::Ready(res)
                 }
             },
 
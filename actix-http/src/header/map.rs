@@ -6,7 +6,8 @@ use ahash::AHashMap;
 use http::header::{HeaderName, HeaderValue};
 use smallvec::{smallvec, SmallVec};
 
-use super::AsHeaderName;
+use super::{AsHeaderName, TryIntoHeaderPair};
+use crate::error::HttpError;
 
 /// A multi-map of HTTP headers.
 ///
@@ -420,6 +421,36 @@ impl HeaderMap {
         };
     }
 
+    /// Returns an entry for in-place insertion or modification of the values of a header name.
+    ///
+    /// Unlike separate calls to [`get_mut`](Self::get_mut) and [`insert`](Self::insert), the
+    /// header name is only looked up once.
+    ///
+    /// # Examples
+    /// ```
+    /// # use actix_http::header::{self, HeaderMap, HeaderValue};
+    /// let mut map = HeaderMap::new();
+    ///
+    /// map.entry(header::VARY)
+    ///     .or_insert(HeaderValue::from_static("accept-encoding"));
+    ///
+    /// // add to the existing value
+    /// map.entry(header::VARY)
+    ///     .and_modify(|val| {
+    ///         let vary = format!("{}, origin", val.to_str().unwrap());
+    ///         *val = HeaderValue::try_from(vary).unwrap();
+    ///     })
+    ///     .or_insert(HeaderValue::from_static("origin"));
+    ///
+    /// assert_eq!(map.get(header::VARY).unwrap(), "accept-encoding, origin");
+    /// ```
+    pub fn entry(&mut self, key: HeaderName) -> Entry<'_> {
+        match self.inner.entry(key) {
+            hash_map::Entry::Occupied(entry) => Entry::Occupied(OccupiedEntry { inner: entry }),
+            hash_map::Entry::Vacant(entry) => Entry::Vacant(VacantEntry { inner: entry }),
+        }
+    }
+
     /// Removes all headers for a particular header name from the map.
     ///
     /// Providing an invalid header names (as a string argument) will have no effect and return
@@ -466,6 +497,43 @@ impl HeaderMap {
         Removed::new(value)
     }
 
+    /// Removes all headers whose name starts with `prefix`, compared case-insensitively.
+    ///
+    /// Returns the number of values that were removed.
+    ///
+    /// # Examples
+    /// ```
+    /// # use actix_http::header::{self, HeaderMap, HeaderValue};
+    /// let mut map = HeaderMap::new();
+    ///
+    /// map.append(header::HOST, HeaderValue::from_static("duck.com"));
+    /// map.append("x-internal-user".parse().unwrap(), HeaderValue::from_static("1"));
+    /// map.append("x-internal-trace".parse().unwrap(), HeaderValue::from_static("a"));
+    ///
+    /// assert_eq!(map.remove_prefixed("X-Internal-"), 2);
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn remove_prefixed(&mut self, prefix: &str) -> usize {
+        let prefix = prefix.as_bytes();
+        let mut removed = 0;
+
+        self.inner.retain(|name, vals| {
+            let name = name.as_str().as_bytes();
+
+            // header names are stored in lowercase
+            let matches =
+                name.len() >= prefix.len() && name[..prefix.len()].eq_ignore_ascii_case(prefix);
+
+            if matches {
+                removed += vals.len();
+            }
+
+            !matches
+        });
+
+        removed
+    }
+
     /// Returns the number of single-value headers the map can hold without needing to reallocate.
     ///
     /// Since this is a multi-value map, the actual capacity is much larger when considering
@@ -508,6 +576,48 @@ impl HeaderMap {
         self.inner.reserve(additional)
     }
 
+    /// Appends each header from an iterator of name-value pairs or typed headers to the map.
+    ///
+    /// Uses the append strategy, like the [`Extend`] implementation, so values are added to those
+    /// already associated with a header name. Capacity is reserved up front for the headers the
+    /// iterator reports.
+    ///
+    /// # Errors
+    /// Returns the first error that occurs when converting a header. Headers converted before the
+    /// error have already been appended.
+    ///
+    /// # Examples
+    /// ```
+    /// # use actix_http::header::{self, ContentEncoding, HeaderMap};
+    /// let mut map = HeaderMap::new();
+    ///
+    /// map.try_extend([
+    ///     (header::CACHE_CONTROL, "no-cache"),
+    ///     (header::PRAGMA, "no-cache"),
+    /// ])
+    /// .unwrap();
+    ///
+    /// map.try_extend([ContentEncoding::Gzip]).unwrap();
+    ///
+    /// assert_eq!(map.len(), 3);
+    /// assert_eq!(map.get(header::CONTENT_ENCODING).unwrap(), "gzip");
+    /// ```
+    pub fn try_extend<I>(&mut self, headers: I) -> Result<(), HttpError>
+    where
+        I: IntoIterator,
+        I::Item: TryIntoHeaderPair,
+    {
+        let headers = headers.into_iter();
+        self.reserve(headers.size_hint().0);
+
+        for header in headers {
+            let (name, value) = header.try_into_pair().map_err(Into::into)?;
+            self.append(name, value);
+        }
+
+        Ok(())
+    }
+
     /// An iterator over all name-value pairs.
     ///
     /// Names will be yielded for each associated value. So, if a key has 3 associated values, it
@@ -662,6 +772,18 @@ impl FromIterator<(HeaderName, HeaderValue)> for HeaderMap {
     }
 }
 
+/// Uses the append strategy, so values are added to those already associated with a header name.
+impl Extend<(HeaderName, HeaderValue)> for HeaderMap {
+    fn extend<T: IntoIterator<Item = (HeaderName, HeaderValue)>>(&mut self, iter: T) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+
+        for (name, value) in iter {
+            self.append(name, value);
+        }
+    }
+}
+
 /// Convert a `http::HeaderMap` to our `HeaderMap`.
 impl From<http::HeaderMap> for HeaderMap {
     fn from(mut map: http::HeaderMap) -> Self {
@@ -732,6 +854,129 @@ impl ExactSizeIterator for Removed {}
 
 impl iter::FusedIterator for Removed {}
 
+/// A view into a single header name's entry in a [`HeaderMap`].
+///
+/// Returned from [`HeaderMap::entry`].
+#[derive(Debug)]
+pub enum Entry<'a> {
+    /// An entry for a header name that has values.
+    Occupied(OccupiedEntry<'a>),
+
+    /// An entry for a header name that has no values.
+    Vacant(VacantEntry<'a>),
+}
+
+impl<'a> Entry<'a> {
+    /// Returns the header name of this entry.
+    pub fn key(&self) -> &HeaderName {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Inserts `val` if the entry is vacant, and returns a mutable reference to the first value.
+    pub fn or_insert(self, val: HeaderValue) -> &'a mut HeaderValue {
+        self.or_insert_with(|| val)
+    }
+
+    /// Inserts the value returned by `f` if the entry is vacant, and returns a mutable reference
+    /// to the first value.
+    pub fn or_insert_with<F>(self, f: F) -> &'a mut HeaderValue
+    where
+        F: FnOnce() -> HeaderValue,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+
+    /// Calls `f` with the first value if the entry is occupied.
+    pub fn and_modify<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut HeaderValue),
+    {
+        if let Entry::Occupied(ref mut entry) = self {
+            f(entry.get_mut());
+        }
+
+        self
+    }
+}
+
+/// A view into an entry for a header name that has values.
+#[derive(Debug)]
+pub struct OccupiedEntry<'a> {
+    inner: hash_map::OccupiedEntry<'a, HeaderName, Value>,
+}
+
+impl<'a> OccupiedEntry<'a> {
+    /// Returns the header name of this entry.
+    pub fn key(&self) -> &HeaderName {
+        self.inner.key()
+    }
+
+    /// Returns a reference to the first value.
+    pub fn get(&self) -> &HeaderValue {
+        self.inner.get().first()
+    }
+
+    /// Returns a mutable reference to the first value.
+    pub fn get_mut(&mut self) -> &mut HeaderValue {
+        self.inner.get_mut().first_mut()
+    }
+
+    /// Converts the entry into a mutable reference to the first value.
+    pub fn into_mut(self) -> &'a mut HeaderValue {
+        self.inner.into_mut().first_mut()
+    }
+
+    /// Returns an iterator over all values of this entry, in insertion order.
+    pub fn iter(&self) -> std::slice::Iter<'_, HeaderValue> {
+        self.inner.get().iter()
+    }
+
+    /// Replaces all values of this entry with `val`, returning the previous values.
+    pub fn insert(&mut self, val: HeaderValue) -> Removed {
+        let value = std::mem::replace(self.inner.get_mut(), Value::one(val));
+        Removed::new(Some(value))
+    }
+
+    /// Adds `val` to the values of this entry.
+    pub fn append(&mut self, val: HeaderValue) {
+        self.inner.get_mut().append(val)
+    }
+
+    /// Removes this entry from the map, returning its values.
+    pub fn remove(self) -> Removed {
+        Removed::new(Some(self.inner.remove()))
+    }
+}
+
+/// A view into an entry for a header name that has no values.
+#[derive(Debug)]
+pub struct VacantEntry<'a> {
+    inner: hash_map::VacantEntry<'a, HeaderName, Value>,
+}
+
+impl<'a> VacantEntry<'a> {
+    /// Returns the header name of this entry.
+    pub fn key(&self) -> &HeaderName {
+        self.inner.key()
+    }
+
+    /// Takes ownership of the header name.
+    pub fn into_key(self) -> HeaderName {
+        self.inner.into_key()
+    }
+
+    /// Inserts `val` into the map, returning a mutable reference to it.
+    pub fn insert(self, val: HeaderValue) -> &'a mut HeaderValue {
+        self.inner.insert(Value::one(val)).first_mut()
+    }
+}
+
 /// Iterator over all names in the map.
 #[derive(Debug)]
 pub struct Keys<'a>(hash_map::Keys<'a, HeaderName, Value>);
@@ -955,6 +1200,79 @@ mod tests {
         assert_eq!(map.len(), 1);
     }
 
+    #[test]
+    fn entry() {
+        let mut map = HeaderMap::new();
+
+        let val = map
+            .entry(header::VARY)
+            .and_modify(|_| panic!("entry should be vacant"))
+            .or_insert(HeaderValue::from_static("origin"));
+        assert_eq!(val, "origin");
+
+        match map.entry(header::VARY) {
+            Entry::Occupied(mut entry) => {
+                entry.append(HeaderValue::from_static("accept"));
+                assert_eq!(entry.iter().collect::<Vec<_>>(), ["origin", "accept"]);
+
+                let removed = entry.insert(HeaderValue::from_static("cookie"));
+                assert_eq!(removed.collect::<Vec<_>>(), ["origin", "accept"]);
+            }
+            Entry::Vacant(_) => panic!("entry should be occupied"),
+        }
+
+        assert_eq!(map.get_all(header::VARY).collect::<Vec<_>>(), ["cookie"]);
+
+        let Entry::Occupied(entry) = map.entry(header::VARY) else {
+            panic!("entry should be occupied");
+        };
+        assert_eq!(entry.remove().len(), 1);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn extend() {
+        let mut map = HeaderMap::new();
+        map.insert(header::COOKIE, HeaderValue::from_static("one=1"));
+
+        map.extend([
+            (header::COOKIE, HeaderValue::from_static("two=2")),
+            (header::HOST, HeaderValue::from_static("duck.com")),
+        ]);
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get_all(header::COOKIE).count(), 2);
+
+        let err = map.try_extend([(header::ACCEPT, "text/html"), (header::ORIGIN, "\n")]);
+        assert!(err.is_err());
+        assert_eq!(map.get(header::ACCEPT).unwrap(), "text/html");
+        assert!(!map.contains_key(header::ORIGIN));
+    }
+
+    #[test]
+    fn remove_prefixed() {
+        let mut map = HeaderMap::new();
+
+        map.append(header::HOST, HeaderValue::from_static("duck.com"));
+        map.append(
+            HeaderName::from_static("x-forwarded-for"),
+            HeaderValue::from_static("1.2.3.4"),
+        );
+        map.append(
+            HeaderName::from_static("x-forwarded-for"),
+            HeaderValue::from_static("5.6.7.8"),
+        );
+        map.append(
+            HeaderName::from_static("x-forwarded-proto"),
+            HeaderValue::from_static("https"),
+        );
+
+        assert_eq!(map.remove_prefixed("X-FORWARDED-"), 3);
+        assert_eq!(map.remove_prefixed("x-forwarded-"), 0);
+        assert_eq!(map.remove_prefixed("host-and-more"), 0);
+        assert_eq!(map.len(), 1);
+        assert!(map.contains_key(header::HOST));
+    }
+
     #[test]
     fn contains() {
         let mut map = HeaderMap::new();
@@ -2,18 +2,19 @@
 //!
 //! ## Crate Features
 //!
-//! | Feature             | Functionality                               |
-//! | ------------------- | ------------------------------------------- |
-//! | `http2`             | HTTP/2 support via [h2].                    |
-//! | `openssl`           | TLS support via [OpenSSL].                  |
-//! | `rustls-0_20`       | TLS support via rustls 0.20.                |
-//! | `rustls-0_21`       | TLS support via rustls 0.21.                |
-//! | `rustls-0_22`       | TLS support via rustls 0.22.                |
-//! | `rustls-0_23`       | TLS support via [rustls] 0.23.              |
-//! | `compress-brotli`   | Payload compression support: Brotli.        |
-//! | `compress-gzip`     | Payload compression support: Deflate, Gzip. |
-//! | `compress-zstd`     | Payload compression support: Zstd.          |
-//! | `trust-dns`         | Use [trust-dns] as the client DNS resolver. |
+//! | Feature                 | Functionality                                   |
+//! | ----------------------- | ----------------------------------------------- |
+//! | `http2`                 | HTTP/2 support via [h2].                        |
+//! | `openssl`               | TLS support via [OpenSSL].                      |
+//! | `rustls-0_20`           | TLS support via rustls 0.20.                    |
+//! | `rustls-0_21`           | TLS support via rustls 0.21.                    |
+//! | `rustls-0_22`           | TLS support via rustls 0.22.                    |
+//! | `rustls-0_23`           | TLS support via [rustls] 0.23.                  |
+//! | `compress-brotli`       | Payload compression support: Brotli.            |
+//! | `compress-gzip`         | Payload compression support: Deflate, Gzip.     |
+//! | `compress-zstd`         | Payload compression support: Zstd.              |
+//! | `experimental-io-uring` | io_uring-backed plaintext TCP I/O (Linux only). |
+//! | `trust-dns`             | Use [trust-dns] as the client DNS resolver.     |
 //!
 //! [h2]: https://crates.io/crates/h2
 //! [OpenSSL]: https://crates.io/crates/openssl
@@ -29,16 +30,25 @@
 #![doc(html_favicon_url = "https://actix.rs/favicon.ico")]
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 
+use std::rc::Rc;
+
 pub use http::{uri, uri::Uri, Method, StatusCode, Version};
 
+#[cfg(feature = "__tls")]
+mod alpn;
 pub mod body;
 mod builder;
+mod canned;
+mod client_disconnect;
 mod config;
 mod date;
+mod early_hints;
 #[cfg(feature = "__compress")]
 pub mod encoding;
 pub mod error;
+mod etag_cache;
 mod extensions;
+mod graceful_shutdown;
 pub mod h1;
 #[cfg(feature = "http2")]
 pub mod h2;
@@ -49,11 +59,18 @@ mod keep_alive;
 mod message;
 #[cfg(test)]
 mod notify_on_drop;
+mod observer;
 mod payload;
 mod requests;
 mod responses;
 mod service;
 pub mod test;
+#[cfg(feature = "__tls")]
+mod tls_info;
+#[cfg(unix)]
+mod uds_info;
+#[cfg(feature = "experimental-io-uring")]
+mod uring;
 #[cfg(feature = "ws")]
 pub mod ws;
 
@@ -61,15 +78,28 @@ pub mod ws;
 pub use self::payload::PayloadStream;
 #[cfg(feature = "__tls")]
 pub use self::service::TlsAcceptorConfig;
+#[cfg(feature = "__tls")]
+pub use self::tls_info::TlsInfo;
+#[cfg(unix)]
+pub use self::uds_info::UdsInfo;
+#[cfg(feature = "experimental-io-uring")]
+pub use self::uring::UringStream;
 pub use self::{
     builder::HttpServiceBuilder,
-    config::ServiceConfig,
+    canned::CannedResponses,
+    client_disconnect::ClientDisconnect,
+    config::{ServiceConfig, ServiceConfigBuilder},
+    date::{Clock, SystemClock},
+    early_hints::EarlyHints,
     error::Error,
+    etag_cache::EtagCache,
     extensions::Extensions,
+    graceful_shutdown::GracefulShutdown,
     header::ContentEncoding,
     http_message::HttpMessage,
     keep_alive::KeepAlive,
     message::{ConnectionType, Message},
+    observer::{ConnectionInfo, ConnectionObserver},
     payload::{BoxedPayloadStream, Payload},
     requests::{Request, RequestHead, RequestHeadType},
     responses::{Response, ResponseBuilder, ResponseHead},
@@ -87,22 +117,129 @@ pub enum Protocol {
 
 type ConnectCallback<IO> = dyn Fn(&IO, &mut Extensions);
 
+type DisconnectCallback = dyn Fn(&Extensions, DisconnectReason);
+
+type RequestHeadCallback = dyn Fn(&mut Request) -> Result<(), Response<body::BoxBody>>;
+
+/// Reason a connection was closed, passed to the
+/// [on-disconnect callback](HttpServiceBuilder::on_disconnect).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DisconnectReason {
+    /// The connection was closed by the peer or because it was not kept alive after a response.
+    Closed,
+
+    /// The connection was idle for longer than the keep-alive timeout.
+    KeepAliveTimeout,
+
+    /// The peer was too slow to send a request or to read a response, or the connection did not
+    /// shut down in time.
+    Timeout,
+
+    /// The connection failed because of an I/O or protocol error.
+    Error,
+
+    /// The connection was dropped before it was closed, e.g., because the server stopped.
+    Aborted,
+}
+
+impl DisconnectReason {
+    /// Classifies the result a dispatcher finished with.
+    pub(crate) fn from_result(res: &Result<(), error::DispatchError>) -> Self {
+        use error::DispatchError;
+
+        match res {
+            Ok(()) => Self::Closed,
+            Err(
+                DispatchError::SlowRequestTimeout
+                | DispatchError::DisconnectTimeout
+                | DispatchError::WriteTimeout,
+            ) => Self::Timeout,
+            Err(_) => Self::Error,
+        }
+    }
+}
+
 /// Container for data that extract with ConnectCallback.
 ///
 /// # Implementation Details
 /// Uses Option to reduce necessary allocations when merging with request extensions.
 #[derive(Default)]
-pub(crate) struct OnConnectData(Option<Extensions>);
+pub(crate) struct OnConnectData {
+    ext: Option<Rc<Extensions>>,
+    on_disconnect: OnDisconnect,
+}
 
 impl OnConnectData {
     /// Construct by calling the on-connect callback with the underlying transport I/O.
-    pub(crate) fn from_io<T>(io: &T, on_connect_ext: Option<&ConnectCallback<T>>) -> Self {
-        let ext = on_connect_ext.map(|handler| {
+    pub(crate) fn from_io<T>(
+        io: &T,
+        on_connect_ext: Option<&ConnectCallback<T>>,
+        config: &ServiceConfig,
+    ) -> Self {
+        let shutdown = config.graceful_shutdown();
+
+        let ext = if on_connect_ext.is_some() || shutdown.is_some() {
             let mut extensions = Extensions::default();
-            handler(io, &mut extensions);
-            extensions
-        });
 
-        Self(ext)
+            if let Some(shutdown) = shutdown {
+                extensions.insert(shutdown);
+            }
+
+            if let Some(handler) = on_connect_ext {
+                handler(io, &mut extensions);
+            }
+
+            Some(Rc::new(extensions))
+        } else {
+            None
+        };
+
+        let on_disconnect = OnDisconnect::new(config, ext.as_ref());
+
+        Self { ext, on_disconnect }
+    }
+
+    /// Splits into the shared connection extensions and the on-disconnect notifier.
+    pub(crate) fn into_parts(self) -> (Option<Rc<Extensions>>, OnDisconnect) {
+        (self.ext, self.on_disconnect)
+    }
+
+    /// Runs the on-disconnect callback for a connection that failed before being dispatched.
+    #[allow(unused)] // used with `http2` feature flag
+    pub(crate) fn disconnected(&mut self, res: &Result<(), error::DispatchError>) {
+        self.on_disconnect
+            .notify(DisconnectReason::from_result(res));
+    }
+}
+
+/// Runs the on-disconnect callback once, when the connection is closed.
+///
+/// Connections dropped without being notified are reported as [`DisconnectReason::Aborted`].
+#[derive(Default)]
+pub(crate) struct OnDisconnect(Option<(Rc<DisconnectCallback>, Rc<Extensions>)>);
+
+impl OnDisconnect {
+    fn new(config: &ServiceConfig, ext: Option<&Rc<Extensions>>) -> Self {
+        Self(config.on_disconnect().map(|callback| {
+            let ext = match ext {
+                Some(ext) => Rc::clone(ext),
+                None => Rc::new(Extensions::new()),
+            };
+
+            (Rc::clone(callback), ext)
+        }))
+    }
+
+    pub(crate) fn notify(&mut self, reason: DisconnectReason) {
+        if let Some((callback, ext)) = self.0.take() {
+            callback(&ext, reason);
+        }
+    }
+}
+
+impl Drop for OnDisconnect {
+    fn drop(&mut self) {
+        self.notify(DisconnectReason::Aborted);
     }
 }
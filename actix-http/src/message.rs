@@ -1,7 +1,30 @@
-use std::{cell::RefCell, ops, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    ops,
+    rc::Rc,
+};
 
 use bitflags::bitflags;
 
+/// Default number of released objects kept by each thread-local pool.
+pub(crate) const DEFAULT_POOL_SIZE: usize = 128;
+
+thread_local! {
+    static POOL_SIZE: Cell<usize> = const { Cell::new(DEFAULT_POOL_SIZE) };
+}
+
+/// Sets the number of released objects kept by each of the current thread's pools.
+///
+/// Applies to request heads, response heads, and request and response extensions.
+pub(crate) fn set_pool_size(size: usize) {
+    POOL_SIZE.with(|pool_size| pool_size.set(size));
+}
+
+/// Returns the number of released objects kept by each of the current thread's pools.
+pub(crate) fn pool_size() -> usize {
+    POOL_SIZE.with(Cell::get)
+}
+
 /// Represents various types of connection
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConnectionType {
@@ -24,6 +47,7 @@ bitflags! {
         const EXPECT      = 0b0000_1000;
         const NO_CHUNKING = 0b0001_0000;
         const CAMEL_CASE  = 0b0010_0000;
+        const ABSOLUTE_FORM = 0b0100_0000;
     }
 }
 
@@ -76,7 +100,7 @@ pub struct MessagePool<T: Head>(RefCell<Vec<Rc<T>>>);
 
 impl<T: Head> MessagePool<T> {
     pub(crate) fn create() -> MessagePool<T> {
-        MessagePool(RefCell::new(Vec::with_capacity(128)))
+        MessagePool(RefCell::new(Vec::with_capacity(DEFAULT_POOL_SIZE)))
     }
 
     /// Get message from the pool
@@ -100,7 +124,7 @@ impl<T: Head> MessagePool<T> {
     /// Release message instance
     fn release(&self, msg: Rc<T>) {
         let pool = &mut self.0.borrow_mut();
-        if pool.len() < 128 {
+        if pool.len() < pool_size() {
             pool.push(msg);
         }
     }
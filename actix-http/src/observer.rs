@@ -0,0 +1,205 @@
+//! Connection lifecycle events.
+
+use std::{net, rc::Rc, time::Instant};
+
+use crate::{error::DispatchError, Protocol, RequestHead, ServiceConfig, StatusCode};
+
+/// Maximum number of bytes of offending input passed to [`ConnectionObserver::protocol_error()`].
+const PROTOCOL_ERROR_DATA_LEN: usize = 64;
+
+/// Receives connection lifecycle events from the HTTP/1 and HTTP/2 dispatchers.
+///
+/// An observer is registered with [`HttpServiceBuilder::observer()`] and is shared by all
+/// connections handled by the service on a worker. Every method has an empty default
+/// implementation, so implementors only need to override the events they are interested in.
+///
+/// Methods are called synchronously from the dispatcher and should not block.
+///
+/// # Examples
+/// ```
+/// use std::cell::Cell;
+///
+/// use actix_http::{ConnectionInfo, ConnectionObserver, StatusCode};
+///
+/// #[derive(Default)]
+/// struct Metrics {
+///     open_connections: Cell<u64>,
+///     bytes_written: Cell<u64>,
+/// }
+///
+/// impl ConnectionObserver for Metrics {
+///     fn connection_opened(&self, _conn: &ConnectionInfo) {
+///         self.open_connections.set(self.open_connections.get() + 1);
+///     }
+///
+///     fn connection_closed(&self, _conn: &ConnectionInfo) {
+///         self.open_connections.set(self.open_connections.get() - 1);
+///     }
+///
+///     fn bytes_written(&self, _conn: &ConnectionInfo, n: usize) {
+///         self.bytes_written.set(self.bytes_written.get() + n as u64);
+///     }
+/// }
+/// ```
+///
+/// [`HttpServiceBuilder::observer()`]: crate::HttpServiceBuilder::observer
+pub trait ConnectionObserver: 'static {
+    /// Called when a connection has been accepted, before any data is read from it.
+    fn connection_opened(&self, conn: &ConnectionInfo) {
+        let _ = conn;
+    }
+
+    /// Called once a connection has been closed and all of its in-flight requests were dropped.
+    fn connection_closed(&self, conn: &ConnectionInfo) {
+        let _ = conn;
+    }
+
+    /// Called when a request head has been received, before the service is called.
+    fn request_started(&self, conn: &ConnectionInfo, req: &RequestHead) {
+        let _ = (conn, req);
+    }
+
+    /// Called when a complete response has been handed to the connection.
+    ///
+    /// This includes error responses generated by the dispatcher itself, such as `400 Bad Request`
+    /// for malformed requests. It is not called for responses whose body failed or was abandoned
+    /// because the connection was closed.
+    fn request_finished(&self, conn: &ConnectionInfo, status: StatusCode) {
+        let _ = (conn, status);
+    }
+
+    /// Called when data has been read from the connection.
+    fn bytes_read(&self, conn: &ConnectionInfo, n: usize) {
+        let _ = (conn, n);
+    }
+
+    /// Called when data has been written to the connection.
+    fn bytes_written(&self, conn: &ConnectionInfo, n: usize) {
+        let _ = (conn, n);
+    }
+
+    /// Called when polling of a response body is paused because the write buffer reached the
+    /// [high-water mark](crate::HttpServiceBuilder::write_high_water_mark).
+    ///
+    /// `buffered` is the number of response bytes waiting to be written. How often this is called
+    /// indicates how often clients read responses slower than they are produced. Only called by
+    /// the HTTP/1 dispatcher.
+    fn write_paused(&self, conn: &ConnectionInfo, buffered: usize) {
+        let _ = (conn, buffered);
+    }
+
+    /// Called when the client violated the protocol, e.g., by sending a malformed request.
+    ///
+    /// `data` holds up to the first 64 bytes of the input that was being parsed when the error was
+    /// found, such as the start of a malformed HTTP/1 request head. It is sent by the client as-is,
+    /// so it may not be valid UTF-8 and should be escaped before being logged. It is empty for
+    /// HTTP/2 errors.
+    ///
+    /// I/O errors and timeouts are not reported.
+    fn protocol_error(&self, conn: &ConnectionInfo, err: &DispatchError, data: &[u8]) {
+        let _ = (conn, err, data);
+    }
+}
+
+/// Information about the connection an event belongs to.
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    protocol: Protocol,
+    peer_addr: Option<net::SocketAddr>,
+    opened_at: Instant,
+}
+
+impl ConnectionInfo {
+    /// Returns the HTTP protocol used by the connection.
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    /// Returns the peer address of the connection, if known.
+    pub fn peer_addr(&self) -> Option<net::SocketAddr> {
+        self.peer_addr
+    }
+
+    /// Returns the time the connection was opened.
+    pub fn opened_at(&self) -> Instant {
+        self.opened_at
+    }
+}
+
+/// Cheaply cloneable handle used by dispatchers to report events to the configured observer.
+///
+/// The `connection_closed` event is sent when the last handle for the connection is dropped.
+#[derive(Clone)]
+pub(crate) struct ConnectionEvents(Option<Rc<Observed>>);
+
+struct Observed {
+    observer: Rc<dyn ConnectionObserver>,
+    conn: ConnectionInfo,
+}
+
+impl Drop for Observed {
+    fn drop(&mut self) {
+        self.observer.connection_closed(&self.conn);
+    }
+}
+
+impl ConnectionEvents {
+    /// Reports a newly accepted connection if an observer is configured.
+    pub(crate) fn open(
+        config: &ServiceConfig,
+        protocol: Protocol,
+        peer_addr: Option<net::SocketAddr>,
+    ) -> Self {
+        Self(config.observer().map(|observer| {
+            let conn = ConnectionInfo {
+                protocol,
+                peer_addr,
+                opened_at: Instant::now(),
+            };
+
+            observer.connection_opened(&conn);
+
+            Rc::new(Observed {
+                observer: Rc::clone(observer),
+                conn,
+            })
+        }))
+    }
+
+    pub(crate) fn request_started(&self, req: &RequestHead) {
+        if let Some(ref inner) = self.0 {
+            inner.observer.request_started(&inner.conn, req);
+        }
+    }
+
+    pub(crate) fn request_finished(&self, status: StatusCode) {
+        if let Some(ref inner) = self.0 {
+            inner.observer.request_finished(&inner.conn, status);
+        }
+    }
+
+    pub(crate) fn bytes_read(&self, n: usize) {
+        if let Some(ref inner) = self.0 {
+            inner.observer.bytes_read(&inner.conn, n);
+        }
+    }
+
+    pub(crate) fn bytes_written(&self, n: usize) {
+        if let Some(ref inner) = self.0 {
+            inner.observer.bytes_written(&inner.conn, n);
+        }
+    }
+
+    pub(crate) fn write_paused(&self, buffered: usize) {
+        if let Some(ref inner) = self.0 {
+            inner.observer.write_paused(&inner.conn, buffered);
+        }
+    }
+
+    pub(crate) fn protocol_error(&self, err: &DispatchError, data: &[u8]) {
+        if let Some(ref inner) = self.0 {
+            let data = &data[..data.len().min(PROTOCOL_ERROR_DATA_LEN)];
+            inner.observer.protocol_error(&inner.conn, err, data);
+        }
+    }
+}
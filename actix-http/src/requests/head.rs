@@ -78,6 +78,28 @@ impl RequestHead {
         }
     }
 
+    /// Returns true if the request target is sent in absolute form.
+    ///
+    /// Default is `false`.
+    #[inline]
+    pub fn absolute_form(&self) -> bool {
+        self.flags.contains(Flags::ABSOLUTE_FORM)
+    }
+
+    /// Set `true` to send the request target in absolute form, including scheme and authority,
+    /// instead of only the path and query.
+    ///
+    /// Requests sent to a forward proxy use the absolute form. Only applies to HTTP/1 requests
+    /// whose URI has a scheme and authority.
+    #[inline]
+    pub fn set_absolute_form(&mut self, val: bool) {
+        if val {
+            self.flags.insert(Flags::ABSOLUTE_FORM);
+        } else {
+            self.flags.remove(Flags::ABSOLUTE_FORM);
+        }
+    }
+
     #[inline]
     /// Set connection type of the message
     pub fn set_connection_type(&mut self, ctype: ConnectionType) {
@@ -1,7 +1,7 @@
 //! HTTP requests.
 
 use std::{
-    cell::{Ref, RefCell, RefMut},
+    cell::{Ref, RefMut},
     fmt, mem, net,
     rc::Rc,
     str,
@@ -10,7 +10,8 @@ use std::{
 use http::{header, Method, Uri, Version};
 
 use crate::{
-    header::HeaderMap, BoxedPayloadStream, Extensions, HttpMessage, Message, Payload, RequestHead,
+    extensions::PooledExtensions, header::HeaderMap, BoxedPayloadStream, Extensions, HttpMessage,
+    Message, Payload, RequestHead,
 };
 
 /// An HTTP request.
@@ -18,7 +19,7 @@ pub struct Request<P = BoxedPayloadStream> {
     pub(crate) payload: Payload<P>,
     pub(crate) head: Message<RequestHead>,
     pub(crate) conn_data: Option<Rc<Extensions>>,
-    pub(crate) extensions: RefCell<Extensions>,
+    pub(crate) extensions: PooledExtensions,
 }
 
 impl<P> HttpMessage for Request<P> {
@@ -49,7 +50,7 @@ impl From<Message<RequestHead>> for Request<BoxedPayloadStream> {
         Request {
             head,
             payload: Payload::None,
-            extensions: RefCell::new(Extensions::default()),
+            extensions: PooledExtensions::new(),
             conn_data: None,
         }
     }
@@ -62,7 +63,7 @@ impl Request<BoxedPayloadStream> {
         Request {
             head: Message::new(),
             payload: Payload::None,
-            extensions: RefCell::new(Extensions::default()),
+            extensions: PooledExtensions::new(),
             conn_data: None,
         }
     }
@@ -74,7 +75,7 @@ impl<P> Request<P> {
         Request {
             payload,
             head: Message::new(),
-            extensions: RefCell::new(Extensions::default()),
+            extensions: PooledExtensions::new(),
             conn_data: None,
         }
     }
@@ -181,6 +182,9 @@ impl<P> Request<P> {
 
     /// Returns a reference a piece of connection data set in an [on-connect] callback.
     ///
+    /// Connection data also holds a [`GracefulShutdown`](crate::GracefulShutdown) handle when a
+    /// shutdown signal is configured.
+    ///
     /// ```ignore
     /// let opt_t = req.conn_data::<PeerCertificate>();
     /// ```
@@ -192,8 +196,8 @@ impl<P> Request<P> {
             .and_then(|container| container.get::<T>())
     }
 
-    /// Returns the connection-level data/extensions container if an [on-connect] callback was
-    /// registered, leaving an empty one in its place.
+    /// Returns the connection-level data/extensions container if an [on-connect] callback or a
+    /// shutdown signal was registered, leaving an empty one in its place.
     ///
     /// [on-connect]: crate::HttpServiceBuilder::on_connect_ext
     pub fn take_conn_data(&mut self) -> Option<Rc<Extensions>> {
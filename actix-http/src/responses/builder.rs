@@ -1,13 +1,14 @@
 //! HTTP response builder.
 
-use std::{cell::RefCell, fmt, str};
+use std::{fmt, str};
 
 use crate::{
     body::{EitherBody, MessageBody},
     error::{Error, HttpError},
+    extensions::PooledExtensions,
     header::{self, TryIntoHeaderPair, TryIntoHeaderValue},
     responses::{BoxedResponseHead, ResponseHead},
-    ConnectionType, Extensions, Response, StatusCode,
+    ConnectionType, Response, StatusCode,
 };
 
 /// An HTTP response builder.
@@ -225,7 +226,7 @@ impl ResponseBuilder {
         Ok(Response {
             head,
             body,
-            extensions: RefCell::new(Extensions::new()),
+            extensions: PooledExtensions::new(),
         })
     }
 
@@ -2,7 +2,11 @@
 
 use std::{cell::RefCell, ops};
 
-use crate::{header::HeaderMap, message::Flags, ConnectionType, StatusCode, Version};
+use crate::{
+    header::HeaderMap,
+    message::{pool_size, Flags, DEFAULT_POOL_SIZE},
+    ConnectionType, StatusCode, Version,
+};
 
 thread_local! {
     static RESPONSE_POOL: BoxedResponsePool = BoxedResponsePool::create();
@@ -170,7 +174,7 @@ pub struct BoxedResponsePool(#[allow(clippy::vec_box)] RefCell<Vec<Box<ResponseH
 
 impl BoxedResponsePool {
     fn create() -> BoxedResponsePool {
-        BoxedResponsePool(RefCell::new(Vec::with_capacity(128)))
+        BoxedResponsePool(RefCell::new(Vec::with_capacity(DEFAULT_POOL_SIZE)))
     }
 
     /// Get message from the pool.
@@ -194,7 +198,7 @@ impl BoxedResponsePool {
     fn release(&self, msg: Box<ResponseHead>) {
         let pool = &mut self.0.borrow_mut();
 
-        if pool.len() < 128 {
+        if pool.len() < pool_size() {
             pool.push(msg);
         }
     }
@@ -1,7 +1,7 @@
 //! HTTP response.
 
 use std::{
-    cell::{Ref, RefCell, RefMut},
+    cell::{Ref, RefMut},
     fmt, str,
 };
 
@@ -10,6 +10,7 @@ use bytestring::ByteString;
 
 use crate::{
     body::{BoxBody, EitherBody, MessageBody},
+    extensions::PooledExtensions,
     header::{self, HeaderMap, TryIntoHeaderValue},
     responses::BoxedResponseHead,
     Error, Extensions, ResponseBuilder, ResponseHead, StatusCode,
@@ -19,7 +20,7 @@ use crate::{
 pub struct Response<B> {
     pub(crate) head: BoxedResponseHead,
     pub(crate) body: B,
-    pub(crate) extensions: RefCell<Extensions>,
+    pub(crate) extensions: PooledExtensions,
 }
 
 impl Response<BoxBody> {
@@ -29,7 +30,7 @@ impl Response<BoxBody> {
         Response {
             head: BoxedResponseHead::new(status),
             body: BoxBody::new(()),
-            extensions: RefCell::new(Extensions::new()),
+            extensions: PooledExtensions::new(),
         }
     }
 
@@ -76,7 +77,7 @@ impl<B> Response<B> {
         Response {
             head: BoxedResponseHead::new(status),
             body,
-            extensions: RefCell::new(Extensions::new()),
+            extensions: PooledExtensions::new(),
         }
     }
 
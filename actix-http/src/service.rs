@@ -63,6 +63,9 @@ pub struct HttpService<T, S, B, X = h1::ExpectHandler, U = h1::UpgradeHandler> {
     expect: X,
     upgrade: Option<U>,
     on_connect_ext: Option<Rc<ConnectCallback<T>>>,
+    diversions: Option<Rc<h1::Diversions<T>>>,
+    #[cfg(feature = "__tls")]
+    alpn: crate::alpn::AlpnProtocols<T>,
     _phantom: PhantomData<B>,
 }
 
@@ -98,6 +101,9 @@ where
             expect: h1::ExpectHandler,
             upgrade: None,
             on_connect_ext: None,
+            diversions: None,
+            #[cfg(feature = "__tls")]
+            alpn: Default::default(),
             _phantom: PhantomData,
         }
     }
@@ -113,6 +119,9 @@ where
             expect: h1::ExpectHandler,
             upgrade: None,
             on_connect_ext: None,
+            diversions: None,
+            #[cfg(feature = "__tls")]
+            alpn: Default::default(),
             _phantom: PhantomData,
         }
     }
@@ -131,6 +140,13 @@ where
     ///
     /// An expect service is called with requests that contain an `Expect` header. A successful
     /// response type is also a request which will be forwarded to the main service.
+    ///
+    /// The request carries the client's [`peer_addr()`](Request::peer_addr) and the connection
+    /// data set by [on-connect callbacks](crate::HttpServiceBuilder::on_connect_ext) and TLS
+    /// acceptors, available through [`conn_data()`](Request::conn_data). This allows rejecting
+    /// uploads based on their headers and where they come from before the body is sent. On
+    /// error, the error response is sent in place of `100 Continue` and the connection is closed
+    /// without reading the body.
     pub fn expect<X1>(self, expect: X1) -> HttpService<T, S, B, X1, U>
     where
         X1: ServiceFactory<Request, Config = (), Response = Request>,
@@ -143,6 +159,9 @@ where
             srv: self.srv,
             upgrade: self.upgrade,
             on_connect_ext: self.on_connect_ext,
+            diversions: self.diversions,
+            #[cfg(feature = "__tls")]
+            alpn: self.alpn,
             _phantom: PhantomData,
         }
     }
@@ -151,6 +170,9 @@ where
     ///
     /// If service is provided then normal requests handling get halted and this service get called
     /// with original request and framed object.
+    ///
+    /// See [`h1::upgrade_fn()`](crate::h1::upgrade_fn) for implementing protocols on the upgraded
+    /// connection without handling the framed object.
     pub fn upgrade<U1>(self, upgrade: Option<U1>) -> HttpService<T, S, B, X, U1>
     where
         U1: ServiceFactory<(Request, Framed<T, h1::Codec>), Config = (), Response = ()>,
@@ -163,15 +185,78 @@ where
             srv: self.srv,
             expect: self.expect,
             on_connect_ext: self.on_connect_ext,
+            diversions: self.diversions,
+            #[cfg(feature = "__tls")]
+            alpn: self.alpn,
             _phantom: PhantomData,
         }
     }
 
+    /// Registers a service for TLS connections that negotiate a custom ALPN protocol.
+    ///
+    /// Connections accepted by the OpenSSL and Rustls finalizers (e.g., [`openssl()`] and
+    /// [`rustls_0_23()`]) whose negotiated ALPN protocol is `protocol` are passed to `service`,
+    /// along with the client's address, instead of being served as HTTP. This lets protocols other
+    /// than HTTP share the listener and TLS setup. Registering a protocol again replaces its
+    /// service.
+    ///
+    /// The Rustls finalizers advertise registered protocols ahead of `h2` and `http/1.1`. OpenSSL
+    /// acceptors negotiate protocols in their own ALPN select callback, which must be set up to
+    /// select `protocol` for those connections to be routed here.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # #[cfg(feature = "rustls-0_23")]
+    /// # fn example() {
+    /// # use std::convert::Infallible;
+    /// use actix_http::{HttpService, Request, Response};
+    /// use actix_service::fn_service;
+    /// use actix_tls::accept::rustls_0_23::{reexports::ServerConfig, TlsStream};
+    /// use tokio::{io::AsyncWriteExt as _, net::TcpStream};
+    ///
+    /// # fn tls_config() -> ServerConfig { unimplemented!() }
+    /// HttpService::build()
+    ///     .finish(|_: Request| async { Ok::<_, Infallible>(Response::ok()) })
+    ///     .alpn_protocol(
+    ///         b"x-greeting".as_slice(),
+    ///         fn_service(|(mut io, _peer_addr): (TlsStream<TcpStream>, _)| async move {
+    ///             io.write_all(b"hello\n").await?;
+    ///             io.shutdown().await
+    ///         }),
+    ///     )
+    ///     .rustls_0_23(tls_config());
+    /// # }
+    /// ```
+    ///
+    /// [`openssl()`]: Self::openssl
+    /// [`rustls_0_23()`]: Self::rustls_0_23
+    #[cfg(feature = "__tls")]
+    pub fn alpn_protocol<F, P>(mut self, protocol: impl Into<Vec<u8>>, service: F) -> Self
+    where
+        T: 'static,
+        F: IntoServiceFactory<P, (T, Option<net::SocketAddr>)>,
+        P: ServiceFactory<(T, Option<net::SocketAddr>), Config = (), Response = ()> + 'static,
+        P::Future: 'static,
+        P::Service: 'static,
+        <P::Service as Service<(T, Option<net::SocketAddr>)>>::Future: 'static,
+        P::Error: Into<DispatchError>,
+        P::InitError: fmt::Debug,
+    {
+        self.alpn.insert(protocol.into(), service);
+        self
+    }
+
     /// Set connect callback with mutable access to request data container.
     pub(crate) fn on_connect_ext(mut self, f: Option<Rc<ConnectCallback<T>>>) -> Self {
         self.on_connect_ext = f;
         self
     }
+
+    /// Set protocols that HTTP/1 connections are diverted to by their first bytes.
+    pub(crate) fn diversions(mut self, diversions: Option<Rc<h1::Diversions<T>>>) -> Self {
+        self.diversions = diversions;
+        self
+    }
 }
 
 impl<S, B, X, U> HttpService<TcpStream, S, B, X, U>
@@ -240,6 +325,103 @@ where
     }
 }
 
+#[cfg(feature = "experimental-io-uring")]
+mod uring {
+    use super::*;
+    use crate::UringStream;
+
+    impl<S, B, X, U> HttpService<UringStream, S, B, X, U>
+    where
+        S: ServiceFactory<Request, Config = ()>,
+        S::Future: 'static,
+        S::Error: Into<Response<BoxBody>> + 'static,
+        S::InitError: fmt::Debug,
+        S::Response: Into<Response<B>> + 'static,
+        <S::Service as Service<Request>>::Future: 'static,
+
+        B: MessageBody + 'static,
+
+        X: ServiceFactory<Request, Config = (), Response = Request>,
+        X::Future: 'static,
+        X::Error: Into<Response<BoxBody>>,
+        X::InitError: fmt::Debug,
+
+        U: ServiceFactory<(Request, Framed<UringStream, h1::Codec>), Config = (), Response = ()>,
+        U::Future: 'static,
+        U::Error: fmt::Display + Into<Response<BoxBody>>,
+        U::InitError: fmt::Debug,
+    {
+        /// Creates TCP stream service from HTTP service that serves accepted streams as
+        /// [`UringStream`]s.
+        ///
+        /// The resulting service only supports HTTP/1.x. It must be run on a `tokio-uring` runtime,
+        /// which is what `actix-server` provides when its `io-uring` feature is enabled.
+        pub fn tcp_uring(
+            self,
+        ) -> impl ServiceFactory<
+            TcpStream,
+            Config = (),
+            Response = (),
+            Error = DispatchError,
+            InitError = (),
+        > {
+            fn_service(|io: TcpStream| async {
+                let peer_addr = io.peer_addr().ok();
+                Ok((UringStream::from_tokio(io)?, Protocol::Http1, peer_addr))
+            })
+            .and_then(self)
+        }
+    }
+}
+
+#[cfg(unix)]
+mod uds {
+    use actix_rt::net::UnixStream;
+
+    use super::*;
+    use crate::uds_info::with_uds_info;
+
+    impl<S, B, X, U> HttpService<UnixStream, S, B, X, U>
+    where
+        S: ServiceFactory<Request, Config = ()>,
+        S::Future: 'static,
+        S::Error: Into<Response<BoxBody>> + 'static,
+        S::InitError: fmt::Debug,
+        S::Response: Into<Response<B>> + 'static,
+        <S::Service as Service<Request>>::Future: 'static,
+
+        B: MessageBody + 'static,
+
+        X: ServiceFactory<Request, Config = (), Response = Request>,
+        X::Future: 'static,
+        X::Error: Into<Response<BoxBody>>,
+        X::InitError: fmt::Debug,
+
+        U: ServiceFactory<(Request, Framed<UnixStream, h1::Codec>), Config = (), Response = ()>,
+        U::Future: 'static,
+        U::Error: fmt::Display + Into<Response<BoxBody>>,
+        U::InitError: fmt::Debug,
+    {
+        /// Creates Unix domain socket stream service from HTTP service.
+        ///
+        /// The resulting service only supports HTTP/1.x. The peer details of each connection are
+        /// available from its requests as [`UdsInfo`](crate::UdsInfo) connection data.
+        pub fn uds(
+            mut self,
+        ) -> impl ServiceFactory<
+            UnixStream,
+            Config = (),
+            Response = (),
+            Error = DispatchError,
+            InitError = (),
+        > {
+            self.on_connect_ext = with_uds_info(self.on_connect_ext.take());
+
+            fn_service(|io: UnixStream| async { Ok((io, Protocol::Http1, None)) }).and_then(self)
+        }
+    }
+}
+
 /// Configuration options used when accepting TLS connection.
 #[cfg(feature = "__tls")]
 #[derive(Debug, Default)]
@@ -270,6 +452,7 @@ mod openssl {
     };
 
     use super::*;
+    use crate::{alpn::AlpnDispatch, tls_info::with_tls_info, TlsInfo};
 
     impl<S, B, X, U> HttpService<TlsStream<TcpStream>, S, B, X, U>
     where
@@ -312,7 +495,7 @@ mod openssl {
 
         /// Create OpenSSL based service with custom TLS acceptor configuration.
         pub fn openssl_with_config(
-            self,
+            mut self,
             acceptor: SslAcceptor,
             tls_acceptor_config: TlsAcceptorConfig,
         ) -> impl ServiceFactory<
@@ -322,6 +505,9 @@ mod openssl {
             Error = TlsError<SslError, DispatchError>,
             InitError = (),
         > {
+            self.on_connect_ext = with_tls_info(self.on_connect_ext.take(), TlsInfo::from_openssl);
+            let alpn = std::mem::take(&mut self.alpn);
+
             let mut acceptor = Acceptor::new(acceptor);
 
             if let Some(handshake_timeout) = tls_acceptor_config.handshake_timeout {
@@ -334,20 +520,11 @@ mod openssl {
                 })
                 .map_err(TlsError::into_service_error)
                 .map(|io: TlsStream<TcpStream>| {
-                    let proto = if let Some(protos) = io.ssl().selected_alpn_protocol() {
-                        if protos.windows(2).any(|window| window == b"h2") {
-                            Protocol::Http2
-                        } else {
-                            Protocol::Http1
-                        }
-                    } else {
-                        Protocol::Http1
-                    };
-
+                    let alpn = io.ssl().selected_alpn_protocol().map(<[u8]>::to_vec);
                     let peer_addr = io.get_ref().peer_addr().ok();
-                    (io, proto, peer_addr)
+                    (io, alpn, peer_addr)
                 })
-                .and_then(self.map_err(TlsError::Service))
+                .and_then(AlpnDispatch::new(alpn, self).map_err(TlsError::Service))
         }
     }
 }
@@ -363,6 +540,7 @@ mod rustls_0_20 {
     };
 
     use super::*;
+    use crate::{alpn::AlpnDispatch, tls_info::with_tls_info, TlsInfo};
 
     impl<S, B, X, U> HttpService<TlsStream<TcpStream>, S, B, X, U>
     where
@@ -405,7 +583,7 @@ mod rustls_0_20 {
 
         /// Create Rustls v0.20 based service with custom TLS acceptor configuration.
         pub fn rustls_with_config(
-            self,
+            mut self,
             mut config: ServerConfig,
             tls_acceptor_config: TlsAcceptorConfig,
         ) -> impl ServiceFactory<
@@ -415,7 +593,13 @@ mod rustls_0_20 {
             Error = TlsError<io::Error, DispatchError>,
             InitError = (),
         > {
-            let mut protos = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+            self.on_connect_ext =
+                with_tls_info(self.on_connect_ext.take(), TlsInfo::from_rustls_0_20);
+
+            let alpn = std::mem::take(&mut self.alpn);
+
+            let mut protos: Vec<_> = alpn.ids().map(<[u8]>::to_vec).collect();
+            protos.extend([b"h2".to_vec(), b"http/1.1".to_vec()]);
             protos.extend_from_slice(&config.alpn_protocols);
             config.alpn_protocols = protos;
 
@@ -431,19 +615,11 @@ mod rustls_0_20 {
                 })
                 .map_err(TlsError::into_service_error)
                 .and_then(|io: TlsStream<TcpStream>| async {
-                    let proto = if let Some(protos) = io.get_ref().1.alpn_protocol() {
-                        if protos.windows(2).any(|window| window == b"h2") {
-                            Protocol::Http2
-                        } else {
-                            Protocol::Http1
-                        }
-                    } else {
-                        Protocol::Http1
-                    };
+                    let alpn = io.get_ref().1.alpn_protocol().map(<[u8]>::to_vec);
                     let peer_addr = io.get_ref().0.peer_addr().ok();
-                    Ok((io, proto, peer_addr))
+                    Ok((io, alpn, peer_addr))
                 })
-                .and_then(self.map_err(TlsError::Service))
+                .and_then(AlpnDispatch::new(alpn, self).map_err(TlsError::Service))
         }
     }
 }
@@ -459,6 +635,7 @@ mod rustls_0_21 {
     };
 
     use super::*;
+    use crate::{alpn::AlpnDispatch, tls_info::with_tls_info, TlsInfo};
 
     impl<S, B, X, U> HttpService<TlsStream<TcpStream>, S, B, X, U>
     where
@@ -501,7 +678,7 @@ mod rustls_0_21 {
 
         /// Create Rustls v0.21 based service with custom TLS acceptor configuration.
         pub fn rustls_021_with_config(
-            self,
+            mut self,
             mut config: ServerConfig,
             tls_acceptor_config: TlsAcceptorConfig,
         ) -> impl ServiceFactory<
@@ -511,7 +688,13 @@ mod rustls_0_21 {
             Error = TlsError<io::Error, DispatchError>,
             InitError = (),
         > {
-            let mut protos = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+            self.on_connect_ext =
+                with_tls_info(self.on_connect_ext.take(), TlsInfo::from_rustls_0_21);
+
+            let alpn = std::mem::take(&mut self.alpn);
+
+            let mut protos: Vec<_> = alpn.ids().map(<[u8]>::to_vec).collect();
+            protos.extend([b"h2".to_vec(), b"http/1.1".to_vec()]);
             protos.extend_from_slice(&config.alpn_protocols);
             config.alpn_protocols = protos;
 
@@ -527,19 +710,11 @@ mod rustls_0_21 {
                 })
                 .map_err(TlsError::into_service_error)
                 .and_then(|io: TlsStream<TcpStream>| async {
-                    let proto = if let Some(protos) = io.get_ref().1.alpn_protocol() {
-                        if protos.windows(2).any(|window| window == b"h2") {
-                            Protocol::Http2
-                        } else {
-                            Protocol::Http1
-                        }
-                    } else {
-                        Protocol::Http1
-                    };
+                    let alpn = io.get_ref().1.alpn_protocol().map(<[u8]>::to_vec);
                     let peer_addr = io.get_ref().0.peer_addr().ok();
-                    Ok((io, proto, peer_addr))
+                    Ok((io, alpn, peer_addr))
                 })
-                .and_then(self.map_err(TlsError::Service))
+                .and_then(AlpnDispatch::new(alpn, self).map_err(TlsError::Service))
         }
     }
 }
@@ -555,6 +730,7 @@ mod rustls_0_22 {
     };
 
     use super::*;
+    use crate::{alpn::AlpnDispatch, tls_info::with_tls_info, TlsInfo};
 
     impl<S, B, X, U> HttpService<TlsStream<TcpStream>, S, B, X, U>
     where
@@ -597,7 +773,7 @@ mod rustls_0_22 {
 
         /// Create Rustls v0.22 based service with custom TLS acceptor configuration.
         pub fn rustls_0_22_with_config(
-            self,
+            mut self,
             mut config: ServerConfig,
             tls_acceptor_config: TlsAcceptorConfig,
         ) -> impl ServiceFactory<
@@ -607,7 +783,13 @@ mod rustls_0_22 {
             Error = TlsError<io::Error, DispatchError>,
             InitError = (),
         > {
-            let mut protos = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+            self.on_connect_ext =
+                with_tls_info(self.on_connect_ext.take(), TlsInfo::from_rustls_0_22);
+
+            let alpn = std::mem::take(&mut self.alpn);
+
+            let mut protos: Vec<_> = alpn.ids().map(<[u8]>::to_vec).collect();
+            protos.extend([b"h2".to_vec(), b"http/1.1".to_vec()]);
             protos.extend_from_slice(&config.alpn_protocols);
             config.alpn_protocols = protos;
 
@@ -623,19 +805,11 @@ mod rustls_0_22 {
                 })
                 .map_err(TlsError::into_service_error)
                 .and_then(|io: TlsStream<TcpStream>| async {
-                    let proto = if let Some(protos) = io.get_ref().1.alpn_protocol() {
-                        if protos.windows(2).any(|window| window == b"h2") {
-                            Protocol::Http2
-                        } else {
-                            Protocol::Http1
-                        }
-                    } else {
-                        Protocol::Http1
-                    };
+                    let alpn = io.get_ref().1.alpn_protocol().map(<[u8]>::to_vec);
                     let peer_addr = io.get_ref().0.peer_addr().ok();
-                    Ok((io, proto, peer_addr))
+                    Ok((io, alpn, peer_addr))
                 })
-                .and_then(self.map_err(TlsError::Service))
+                .and_then(AlpnDispatch::new(alpn, self).map_err(TlsError::Service))
         }
     }
 }
@@ -651,6 +825,7 @@ mod rustls_0_23 {
     };
 
     use super::*;
+    use crate::{alpn::AlpnDispatch, tls_info::with_tls_info, TlsInfo};
 
     impl<S, B, X, U> HttpService<TlsStream<TcpStream>, S, B, X, U>
     where
@@ -693,7 +868,7 @@ mod rustls_0_23 {
 
         /// Create Rustls v0.23 based service with custom TLS acceptor configuration.
         pub fn rustls_0_23_with_config(
-            self,
+            mut self,
             mut config: ServerConfig,
             tls_acceptor_config: TlsAcceptorConfig,
         ) -> impl ServiceFactory<
@@ -703,7 +878,13 @@ mod rustls_0_23 {
             Error = TlsError<io::Error, DispatchError>,
             InitError = (),
         > {
-            let mut protos = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+            self.on_connect_ext =
+                with_tls_info(self.on_connect_ext.take(), TlsInfo::from_rustls_0_23);
+
+            let alpn = std::mem::take(&mut self.alpn);
+
+            let mut protos: Vec<_> = alpn.ids().map(<[u8]>::to_vec).collect();
+            protos.extend([b"h2".to_vec(), b"http/1.1".to_vec()]);
             protos.extend_from_slice(&config.alpn_protocols);
             config.alpn_protocols = protos;
 
@@ -719,19 +900,11 @@ mod rustls_0_23 {
                 })
                 .map_err(TlsError::into_service_error)
                 .and_then(|io: TlsStream<TcpStream>| async {
-                    let proto = if let Some(protos) = io.get_ref().1.alpn_protocol() {
-                        if protos.windows(2).any(|window| window == b"h2") {
-                            Protocol::Http2
-                        } else {
-                            Protocol::Http1
-                        }
-                    } else {
-                        Protocol::Http1
-                    };
+                    let alpn = io.get_ref().1.alpn_protocol().map(<[u8]>::to_vec);
                     let peer_addr = io.get_ref().0.peer_addr().ok();
-                    Ok((io, proto, peer_addr))
+                    Ok((io, alpn, peer_addr))
                 })
-                .and_then(self.map_err(TlsError::Service))
+                .and_then(AlpnDispatch::new(alpn, self).map_err(TlsError::Service))
         }
     }
 }
@@ -772,6 +945,7 @@ where
         let expect = self.expect.new_service(());
         let upgrade = self.upgrade.as_ref().map(|s| s.new_service(()));
         let on_connect_ext = self.on_connect_ext.clone();
+        let diversions = self.diversions.clone();
         let cfg = self.cfg.clone();
 
         Box::pin(async move {
@@ -799,6 +973,7 @@ where
                 expect,
                 upgrade,
                 on_connect_ext,
+                diversions,
             ))
         })
     }
@@ -814,6 +989,7 @@ where
     pub(super) flow: Rc<HttpFlow<S, X, U>>,
     pub(super) cfg: ServiceConfig,
     pub(super) on_connect_ext: Option<Rc<ConnectCallback<T>>>,
+    pub(super) diversions: Option<Rc<h1::Diversions<T>>>,
     _phantom: PhantomData<B>,
 }
 
@@ -832,10 +1008,12 @@ where
         expect: X,
         upgrade: Option<U>,
         on_connect_ext: Option<Rc<ConnectCallback<T>>>,
+        diversions: Option<Rc<h1::Diversions<T>>>,
     ) -> HttpServiceHandler<T, S, B, X, U> {
         HttpServiceHandler {
             cfg,
             on_connect_ext,
+            diversions,
             flow: HttpFlow::new(service, expect, upgrade),
             _phantom: PhantomData,
         }
@@ -874,7 +1052,7 @@ impl<S, X, U> HttpFlow<S, X, U> {
 impl<T, S, B, X, U> Service<(T, Protocol, Option<net::SocketAddr>)>
     for HttpServiceHandler<T, S, B, X, U>
 where
-    T: AsyncRead + AsyncWrite + Unpin,
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
 
     S: Service<Request>,
     S::Error: Into<Response<BoxBody>> + 'static,
@@ -901,14 +1079,14 @@ where
     }
 
     fn call(&self, (io, proto, peer_addr): (T, Protocol, Option<net::SocketAddr>)) -> Self::Future {
-        let conn_data = OnConnectData::from_io(&io, self.on_connect_ext.as_deref());
+        let conn_data = OnConnectData::from_io(&io, self.on_connect_ext.as_deref(), &self.cfg);
 
         match proto {
             #[cfg(feature = "http2")]
             Protocol::Http2 => HttpServiceHandlerResponse {
                 state: State::H2Handshake {
                     handshake: Some((
-                        crate::h2::handshake_with_timeout(io, &self.cfg),
+                        crate::h2::handshake_with_timeout(io, &self.cfg, peer_addr),
                         self.cfg.clone(),
                         Rc::clone(&self.flow),
                         conn_data,
@@ -930,7 +1108,8 @@ where
                         self.cfg.clone(),
                         peer_addr,
                         conn_data,
-                    ),
+                    )
+                    .diversions(self.diversions.clone()),
                 },
             },
 
@@ -1030,7 +1209,7 @@ pin_project! {
 
 impl<T, S, B, X, U> Future for HttpServiceHandlerResponse<T, S, B, X, U>
 where
-    T: AsyncRead + AsyncWrite + Unpin,
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
 
     S: Service<Request>,
     S::Error: Into<Response<BoxBody>> + 'static,
@@ -1057,19 +1236,21 @@ where
             #[cfg(feature = "http2")]
             StateProj::H2Handshake { handshake: data } => {
                 match ready!(Pin::new(&mut data.as_mut().unwrap().0).poll(cx)) {
-                    Ok((conn, timer)) => {
+                    Ok((conn, timer, events)) => {
                         let (_, config, flow, conn_data, peer_addr) = data.take().unwrap();
 
                         self.as_mut().project().state.set(State::H2 {
                             dispatcher: crate::h2::Dispatcher::new(
-                                conn, flow, config, peer_addr, conn_data, timer,
+                                conn, flow, config, peer_addr, conn_data, timer, events,
                             ),
                         });
                         self.poll(cx)
                     }
                     Err(err) => {
                         tracing::trace!("H2 handshake error: {}", err);
-                        Poll::Ready(Err(err))
+                        let res = Err(err);
+                        data.as_mut().unwrap().3.disconnected(&res);
+                        Poll::Ready(res)
                     }
                 }
             }
@@ -0,0 +1,192 @@
+//! Details of the TLS session of a connection.
+
+use std::rc::Rc;
+
+use crate::{ConnectCallback, Extensions};
+
+/// Details of the TLS session a connection was accepted with.
+///
+/// The built-in OpenSSL and Rustls acceptors (e.g., [`HttpService::openssl()`] and
+/// [`HttpService::rustls_0_23()`]) insert this into the connection data of every connection they
+/// accept, before any [on-connect callback](crate::HttpServiceBuilder::on_connect_ext) runs. It can
+/// be retrieved from each request on that connection with [`Request::conn_data()`].
+///
+/// # Examples
+/// ```
+/// # use actix_http::{Request, TlsInfo};
+/// fn log_client(req: &Request) {
+///     if let Some(tls) = req.conn_data::<TlsInfo>() {
+///         println!("{} ({})", tls.protocol_version(), tls.cipher_suite().unwrap_or("unknown"));
+///     }
+/// }
+/// ```
+///
+/// [`HttpService::openssl()`]: crate::HttpService::openssl
+/// [`HttpService::rustls_0_23()`]: crate::HttpService::rustls_0_23
+/// [`Request::conn_data()`]: crate::Request::conn_data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsInfo {
+    protocol_version: &'static str,
+    cipher_suite: Option<&'static str>,
+    server_name: Option<String>,
+    alpn_protocol: Option<Vec<u8>>,
+    peer_certificate: Option<Vec<u8>>,
+}
+
+impl TlsInfo {
+    /// Returns the negotiated protocol version, e.g., `TLSv1.3`.
+    pub fn protocol_version(&self) -> &'static str {
+        self.protocol_version
+    }
+
+    /// Returns the name of the negotiated cipher suite.
+    ///
+    /// Names are as reported by the TLS backend, so the same suite may be named differently by
+    /// OpenSSL and Rustls.
+    pub fn cipher_suite(&self) -> Option<&'static str> {
+        self.cipher_suite
+    }
+
+    /// Returns the host name the client requested using Server Name Indication (SNI), if any.
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+
+    /// Returns the protocol negotiated using ALPN, if any.
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.alpn_protocol.as_deref()
+    }
+
+    /// Returns the DER-encoded end-entity certificate presented by the client, if any.
+    ///
+    /// Clients only present certificates when the acceptor is configured to request them.
+    pub fn peer_certificate(&self) -> Option<&[u8]> {
+        self.peer_certificate.as_deref()
+    }
+}
+
+/// Wraps an on-connect callback so that it inserts the stream's [`TlsInfo`] before running.
+pub(crate) fn with_tls_info<T: 'static>(
+    on_connect_ext: Option<Rc<ConnectCallback<T>>>,
+    tls_info: fn(&T) -> TlsInfo,
+) -> Option<Rc<ConnectCallback<T>>> {
+    Some(Rc::new(move |io: &T, ext: &mut Extensions| {
+        ext.insert(tls_info(io));
+
+        if let Some(on_connect_ext) = &on_connect_ext {
+            on_connect_ext(io, ext);
+        }
+    }))
+}
+
+/// Maps Rustls protocol version names to the names OpenSSL uses.
+#[cfg(any(
+    feature = "rustls-0_20",
+    feature = "rustls-0_21",
+    feature = "rustls-0_22",
+    feature = "rustls-0_23"
+))]
+fn rustls_protocol_version(name: Option<&'static str>) -> &'static str {
+    match name {
+        Some("TLSv1_2") => "TLSv1.2",
+        Some("TLSv1_3") => "TLSv1.3",
+        Some(name) => name,
+        None => "unknown",
+    }
+}
+
+#[cfg(feature = "openssl")]
+mod openssl {
+    use actix_rt::net::TcpStream;
+    use actix_tls::accept::openssl::TlsStream;
+    use tls_openssl::ssl::NameType;
+
+    use super::*;
+
+    impl TlsInfo {
+        pub(crate) fn from_openssl(io: &TlsStream<TcpStream>) -> Self {
+            let ssl = io.ssl();
+
+            Self {
+                protocol_version: ssl.version_str(),
+                cipher_suite: ssl.current_cipher().map(|cipher| cipher.name()),
+                server_name: ssl.servername(NameType::HOST_NAME).map(str::to_owned),
+                alpn_protocol: ssl.selected_alpn_protocol().map(<[u8]>::to_vec),
+                peer_certificate: ssl.peer_certificate().and_then(|cert| cert.to_der().ok()),
+            }
+        }
+    }
+}
+
+/// Implements a constructor for a Rustls version's `TlsStream`.
+///
+/// The certificate type differs between versions but all of them are `AsRef<[u8]>` of the DER
+/// bytes. Rustls v0.20 names the SNI accessor `sni_hostname`.
+#[cfg(any(
+    feature = "rustls-0_20",
+    feature = "rustls-0_21",
+    feature = "rustls-0_22",
+    feature = "rustls-0_23"
+))]
+macro_rules! impl_from_rustls {
+    ($module:ident, $name:ident, $server_name:ident) => {
+        impl TlsInfo {
+            pub(crate) fn $name(
+                io: &actix_tls::accept::$module::TlsStream<actix_rt::net::TcpStream>,
+            ) -> Self {
+                let (_, conn) = io.get_ref();
+
+                Self {
+                    protocol_version: rustls_protocol_version(
+                        conn.protocol_version().and_then(|version| version.as_str()),
+                    ),
+                    cipher_suite: conn
+                        .negotiated_cipher_suite()
+                        .and_then(|suite| suite.suite().as_str()),
+                    server_name: conn.$server_name().map(str::to_owned),
+                    alpn_protocol: conn.alpn_protocol().map(<[u8]>::to_vec),
+                    peer_certificate: conn
+                        .peer_certificates()
+                        .and_then(|certs| certs.first())
+                        .map(|cert| AsRef::<[u8]>::as_ref(cert).to_vec()),
+                }
+            }
+        }
+    };
+}
+
+#[cfg(feature = "rustls-0_20")]
+impl_from_rustls!(rustls_0_20, from_rustls_0_20, sni_hostname);
+#[cfg(feature = "rustls-0_21")]
+impl_from_rustls!(rustls_0_21, from_rustls_0_21, server_name);
+#[cfg(feature = "rustls-0_22")]
+impl_from_rustls!(rustls_0_22, from_rustls_0_22, server_name);
+#[cfg(feature = "rustls-0_23")]
+impl_from_rustls!(rustls_0_23, from_rustls_0_23, server_name);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn callback_runs_after_insert() {
+        let cb = with_tls_info::<()>(
+            Some(Rc::new(|_, ext: &mut Extensions| {
+                let version = ext.get::<TlsInfo>().unwrap().protocol_version();
+                ext.insert(version);
+            })),
+            |_| TlsInfo {
+                protocol_version: "TLSv1.3",
+                cipher_suite: None,
+                server_name: None,
+                alpn_protocol: None,
+                peer_certificate: None,
+            },
+        )
+        .unwrap();
+
+        let mut ext = Extensions::new();
+        cb(&(), &mut ext);
+        assert_eq!(ext.get::<&str>(), Some(&"TLSv1.3"));
+    }
+}
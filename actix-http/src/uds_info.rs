@@ -0,0 +1,90 @@
+//! Details of the peer of a Unix domain socket connection.
+
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use actix_rt::net::UnixStream;
+
+use crate::{ConnectCallback, Extensions};
+
+/// Details of the peer of a Unix domain socket (UDS) connection.
+///
+/// The UDS services (e.g., [`HttpService::uds()`]) insert this into the connection data of every
+/// connection they accept, before any [on-connect callback](crate::HttpServiceBuilder::on_connect_ext)
+/// runs. It can be retrieved from each request on that connection with [`Request::conn_data()`].
+///
+/// UDS connections have no IP address, so [`Request::peer_addr()`] returns `None` for them.
+///
+/// # Examples
+/// ```
+/// # use actix_http::{Request, UdsInfo};
+/// fn is_root(req: &Request) -> bool {
+///     req.conn_data::<UdsInfo>().and_then(UdsInfo::uid) == Some(0)
+/// }
+/// ```
+///
+/// [`HttpService::uds()`]: crate::HttpService::uds
+/// [`Request::conn_data()`]: crate::Request::conn_data
+/// [`Request::peer_addr()`]: crate::Request::peer_addr
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UdsInfo {
+    peer_addr: Option<PathBuf>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    pid: Option<i32>,
+}
+
+impl UdsInfo {
+    pub(crate) fn from_stream(io: &UnixStream) -> Self {
+        let peer_addr = io
+            .peer_addr()
+            .ok()
+            .and_then(|addr| addr.as_pathname().map(Path::to_path_buf));
+
+        let cred = io.peer_cred().ok();
+
+        Self {
+            peer_addr,
+            uid: cred.map(|cred| cred.uid()),
+            gid: cred.map(|cred| cred.gid()),
+            pid: cred.and_then(|cred| cred.pid()),
+        }
+    }
+
+    /// Returns the path the peer's socket is bound to, if any.
+    ///
+    /// Client sockets are usually not bound to a path.
+    pub fn peer_addr(&self) -> Option<&Path> {
+        self.peer_addr.as_deref()
+    }
+
+    /// Returns the user ID of the peer process, if the platform reports it.
+    pub fn uid(&self) -> Option<u32> {
+        self.uid
+    }
+
+    /// Returns the group ID of the peer process, if the platform reports it.
+    pub fn gid(&self) -> Option<u32> {
+        self.gid
+    }
+
+    /// Returns the process ID of the peer process, if the platform reports it.
+    pub fn pid(&self) -> Option<i32> {
+        self.pid
+    }
+}
+
+/// Wraps an on-connect callback so that it inserts the stream's [`UdsInfo`] before running.
+pub(crate) fn with_uds_info(
+    on_connect_ext: Option<Rc<ConnectCallback<UnixStream>>>,
+) -> Option<Rc<ConnectCallback<UnixStream>>> {
+    Some(Rc::new(move |io: &UnixStream, ext: &mut Extensions| {
+        ext.insert(UdsInfo::from_stream(io));
+
+        if let Some(on_connect_ext) = &on_connect_ext {
+            on_connect_ext(io, ext);
+        }
+    }))
+}
@@ -0,0 +1,192 @@
+//! io_uring-backed TCP transport.
+
+use std::{
+    fmt,
+    future::Future as _,
+    io, mem,
+    net::Shutdown,
+    pin::Pin,
+    rc::Rc,
+    task::{ready, Context, Poll},
+};
+
+use actix_codec::{AsyncRead, AsyncWrite, ReadBuf};
+use futures_core::future::LocalBoxFuture;
+use tokio_uring::BufResult;
+
+const READ_BUF_SIZE: usize = 32_768;
+
+type BufOp = LocalBoxFuture<'static, BufResult<usize, Vec<u8>>>;
+
+/// TCP stream whose reads and writes are submitted to the worker's io_uring instance.
+///
+/// Produced by the io_uring TCP services (e.g., [`H1Service::tcp_uring()`]). Completion-based I/O
+/// needs owned buffers, so this type keeps one read buffer and one write buffer per connection and
+/// adapts them to the [`AsyncRead`] and [`AsyncWrite`] traits expected by the dispatchers. Writes
+/// are accepted as soon as they are submitted; [`poll_flush`](AsyncWrite::poll_flush) waits for
+/// completion.
+///
+/// Connections accepted this way must be served from a `tokio-uring` runtime, which is what
+/// `actix-server` provides when its `io-uring` feature is enabled.
+///
+/// [`H1Service::tcp_uring()`]: crate::h1::H1Service::tcp_uring
+pub struct UringStream {
+    io: Rc<tokio_uring::net::TcpStream>,
+    read: ReadState,
+    write: WriteState,
+}
+
+enum ReadState {
+    /// Holds bytes received by the last completed read, starting at `pos`.
+    Idle {
+        buf: Vec<u8>,
+        pos: usize,
+    },
+    Pending(BufOp),
+}
+
+enum WriteState {
+    Idle(Vec<u8>),
+    Pending(BufOp),
+}
+
+impl UringStream {
+    /// Moves an accepted Tokio stream onto the current thread's io_uring instance.
+    pub(crate) fn from_tokio(io: actix_rt::net::TcpStream) -> io::Result<Self> {
+        let io = tokio_uring::net::TcpStream::from_std(io.into_std()?);
+
+        Ok(Self {
+            io: Rc::new(io),
+            read: ReadState::Idle {
+                buf: Vec::with_capacity(READ_BUF_SIZE),
+                pos: 0,
+            },
+            write: WriteState::Idle(Vec::new()),
+        })
+    }
+
+    /// Returns a reference to the underlying `tokio-uring` stream.
+    pub fn get_ref(&self) -> &tokio_uring::net::TcpStream {
+        &self.io
+    }
+
+    fn submit_write(&mut self, buf: Vec<u8>) {
+        let io = Rc::clone(&self.io);
+        self.write = WriteState::Pending(Box::pin(async move { io.write(buf).submit().await }));
+    }
+}
+
+impl Drop for UringStream {
+    fn drop(&mut self) {
+        // in-flight operations keep the socket open until they complete; shutting down the read
+        // half completes a pending read while letting a submitted write finish before closing
+        let _ = self.io.shutdown(Shutdown::Read);
+    }
+}
+
+impl fmt::Debug for UringStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UringStream").finish_non_exhaustive()
+    }
+}
+
+impl AsyncRead for UringStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        out: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        loop {
+            match &mut this.read {
+                ReadState::Idle { buf, pos } if *pos < buf.len() => {
+                    let len = out.remaining().min(buf.len() - *pos);
+                    out.put_slice(&buf[*pos..*pos + len]);
+                    *pos += len;
+                    return Poll::Ready(Ok(()));
+                }
+
+                ReadState::Idle { buf, .. } => {
+                    let mut buf = mem::take(buf);
+                    buf.clear();
+
+                    let io = Rc::clone(&this.io);
+                    this.read = ReadState::Pending(Box::pin(async move { io.read(buf).await }));
+                }
+
+                ReadState::Pending(op) => {
+                    let (res, buf) = ready!(Pin::new(op).poll(cx));
+                    this.read = ReadState::Idle { buf, pos: 0 };
+
+                    // zero bytes read signals EOF to the caller by leaving `out` untouched
+                    if res? == 0 {
+                        return Poll::Ready(Ok(()));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl AsyncWrite for UringStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        data: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if data.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        // only one write is kept in flight; wait for the previous one to complete
+        ready!(Pin::new(&mut *this).poll_flush(cx))?;
+
+        let WriteState::Idle(buf) = &mut this.write else {
+            unreachable!("write buffer is idle after flushing");
+        };
+
+        let mut buf = mem::take(buf);
+        buf.extend_from_slice(data);
+        this.submit_write(buf);
+
+        Poll::Ready(Ok(data.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        loop {
+            let WriteState::Pending(op) = &mut this.write else {
+                return Poll::Ready(Ok(()));
+            };
+
+            let (res, mut buf) = ready!(Pin::new(op).poll(cx));
+
+            let written = match res {
+                Ok(0) => Err(io::ErrorKind::WriteZero.into()),
+                res => res,
+            };
+
+            match written {
+                Ok(n) if n < buf.len() => {
+                    buf.drain(..n);
+                    this.submit_write(buf);
+                }
+
+                res => {
+                    buf.clear();
+                    this.write = WriteState::Idle(buf);
+                    return Poll::Ready(res.map(|_| ()));
+                }
+            }
+        }
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        Poll::Ready(self.io.shutdown(Shutdown::Write))
+    }
+}
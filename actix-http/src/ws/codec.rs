@@ -7,6 +7,7 @@ use tracing::error;
 use super::{
     frame::Parser,
     proto::{CloseReason, OpCode},
+    quota::{Quota, QuotaState},
     ProtocolError,
 };
 
@@ -71,6 +72,7 @@ pub enum Item {
 pub struct Codec {
     flags: Flags,
     max_size: usize,
+    quota: Option<QuotaState>,
 }
 
 bitflags! {
@@ -88,6 +90,7 @@ impl Codec {
         Codec {
             max_size: 65_536,
             flags: Flags::SERVER,
+            quota: None,
         }
     }
 
@@ -100,6 +103,15 @@ impl Codec {
         self
     }
 
+    /// Set quota for decoded messages.
+    ///
+    /// By default, messages are not limited. Clones of the codec count messages separately.
+    #[must_use = "This returns the a new Codec, without modifying the original."]
+    pub fn quota(mut self, quota: Quota) -> Self {
+        self.quota = Some(QuotaState::new(quota));
+        self
+    }
+
     /// Set decoder to client mode.
     ///
     /// By default decoder works in server mode.
@@ -220,83 +232,104 @@ impl Decoder for Codec {
     type Error = ProtocolError;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        match Parser::parse(src, self.flags.contains(Flags::SERVER), self.max_size) {
-            Ok(Some((finished, opcode, payload))) => {
-                // continuation is not supported
-                if !finished {
-                    return match opcode {
-                        OpCode::Continue => {
-                            if self.flags.contains(Flags::CONTINUATION) {
-                                Ok(Some(Frame::Continuation(Item::Continue(
-                                    payload.map(|pl| pl.freeze()).unwrap_or_else(Bytes::new),
-                                ))))
-                            } else {
-                                Err(ProtocolError::ContinuationNotStarted)
-                            }
-                        }
-                        OpCode::Binary => {
-                            if !self.flags.contains(Flags::CONTINUATION) {
-                                self.flags.insert(Flags::CONTINUATION);
-                                Ok(Some(Frame::Continuation(Item::FirstBinary(
-                                    payload.map(|pl| pl.freeze()).unwrap_or_else(Bytes::new),
-                                ))))
-                            } else {
-                                Err(ProtocolError::ContinuationStarted)
-                            }
-                        }
-                        OpCode::Text => {
-                            if !self.flags.contains(Flags::CONTINUATION) {
-                                self.flags.insert(Flags::CONTINUATION);
-                                Ok(Some(Frame::Continuation(Item::FirstText(
-                                    payload.map(|pl| pl.freeze()).unwrap_or_else(Bytes::new),
-                                ))))
-                            } else {
-                                Err(ProtocolError::ContinuationStarted)
-                            }
-                        }
-                        _ => {
-                            error!("Unfinished fragment {:?}", opcode);
-                            Err(ProtocolError::ContinuationFragment(opcode))
-                        }
-                    };
+        loop {
+            let (finished, opcode, payload) =
+                match Parser::parse(src, self.flags.contains(Flags::SERVER), self.max_size)? {
+                    Some(frame) => frame,
+                    None => return Ok(None),
+                };
+
+            if let Some(quota) = &mut self.quota {
+                let len = payload.as_ref().map_or(0, |pl| pl.len());
+
+                if !quota.admit(finished, opcode, len)? {
+                    continue;
                 }
+            }
 
-                match opcode {
-                    OpCode::Continue => {
-                        if self.flags.contains(Flags::CONTINUATION) {
-                            self.flags.remove(Flags::CONTINUATION);
-                            Ok(Some(Frame::Continuation(Item::Last(
-                                payload.map(|pl| pl.freeze()).unwrap_or_else(Bytes::new),
-                            ))))
-                        } else {
-                            Err(ProtocolError::ContinuationNotStarted)
-                        }
+            return self.decode_frame(finished, opcode, payload);
+        }
+    }
+}
+
+impl Codec {
+    fn decode_frame(
+        &mut self,
+        finished: bool,
+        opcode: OpCode,
+        payload: Option<BytesMut>,
+    ) -> Result<Option<Frame>, ProtocolError> {
+        // continuation is not supported
+        if !finished {
+            return match opcode {
+                OpCode::Continue => {
+                    if self.flags.contains(Flags::CONTINUATION) {
+                        Ok(Some(Frame::Continuation(Item::Continue(
+                            payload.map(|pl| pl.freeze()).unwrap_or_else(Bytes::new),
+                        ))))
+                    } else {
+                        Err(ProtocolError::ContinuationNotStarted)
                     }
-                    OpCode::Bad => Err(ProtocolError::BadOpCode),
-                    OpCode::Close => {
-                        if let Some(ref pl) = payload {
-                            let close_reason = Parser::parse_close_payload(pl);
-                            Ok(Some(Frame::Close(close_reason)))
-                        } else {
-                            Ok(Some(Frame::Close(None)))
-                        }
+                }
+                OpCode::Binary => {
+                    if !self.flags.contains(Flags::CONTINUATION) {
+                        self.flags.insert(Flags::CONTINUATION);
+                        Ok(Some(Frame::Continuation(Item::FirstBinary(
+                            payload.map(|pl| pl.freeze()).unwrap_or_else(Bytes::new),
+                        ))))
+                    } else {
+                        Err(ProtocolError::ContinuationStarted)
                     }
-                    OpCode::Ping => Ok(Some(Frame::Ping(
-                        payload.map(|pl| pl.freeze()).unwrap_or_else(Bytes::new),
-                    ))),
-                    OpCode::Pong => Ok(Some(Frame::Pong(
-                        payload.map(|pl| pl.freeze()).unwrap_or_else(Bytes::new),
-                    ))),
-                    OpCode::Binary => Ok(Some(Frame::Binary(
-                        payload.map(|pl| pl.freeze()).unwrap_or_else(Bytes::new),
-                    ))),
-                    OpCode::Text => Ok(Some(Frame::Text(
+                }
+                OpCode::Text => {
+                    if !self.flags.contains(Flags::CONTINUATION) {
+                        self.flags.insert(Flags::CONTINUATION);
+                        Ok(Some(Frame::Continuation(Item::FirstText(
+                            payload.map(|pl| pl.freeze()).unwrap_or_else(Bytes::new),
+                        ))))
+                    } else {
+                        Err(ProtocolError::ContinuationStarted)
+                    }
+                }
+                _ => {
+                    error!("Unfinished fragment {:?}", opcode);
+                    Err(ProtocolError::ContinuationFragment(opcode))
+                }
+            };
+        }
+
+        match opcode {
+            OpCode::Continue => {
+                if self.flags.contains(Flags::CONTINUATION) {
+                    self.flags.remove(Flags::CONTINUATION);
+                    Ok(Some(Frame::Continuation(Item::Last(
                         payload.map(|pl| pl.freeze()).unwrap_or_else(Bytes::new),
-                    ))),
+                    ))))
+                } else {
+                    Err(ProtocolError::ContinuationNotStarted)
+                }
+            }
+            OpCode::Bad => Err(ProtocolError::BadOpCode),
+            OpCode::Close => {
+                if let Some(ref pl) = payload {
+                    let close_reason = Parser::parse_close_payload(pl);
+                    Ok(Some(Frame::Close(close_reason)))
+                } else {
+                    Ok(Some(Frame::Close(None)))
                 }
             }
-            Ok(None) => Ok(None),
-            Err(err) => Err(err),
+            OpCode::Ping => Ok(Some(Frame::Ping(
+                payload.map(|pl| pl.freeze()).unwrap_or_else(Bytes::new),
+            ))),
+            OpCode::Pong => Ok(Some(Frame::Pong(
+                payload.map(|pl| pl.freeze()).unwrap_or_else(Bytes::new),
+            ))),
+            OpCode::Binary => Ok(Some(Frame::Binary(
+                payload.map(|pl| pl.freeze()).unwrap_or_else(Bytes::new),
+            ))),
+            OpCode::Text => Ok(Some(Frame::Text(
+                payload.map(|pl| pl.freeze()).unwrap_or_else(Bytes::new),
+            ))),
         }
     }
 }
@@ -1,14 +1,19 @@
 use std::{
     future::Future,
     pin::Pin,
-    task::{Context, Poll},
+    task::{ready, Context, Poll},
+    time::Duration,
 };
 
 use actix_codec::{AsyncRead, AsyncWrite, Framed};
+use actix_rt::time::{sleep, Sleep};
 use actix_service::{IntoService, Service};
+use futures_core::future::LocalBoxFuture;
 use pin_project_lite::pin_project;
+use tracing::debug;
 
-use super::{Codec, Frame, Message};
+use super::{CloseCode, CloseReason, Codec, Frame, Message, ProtocolError};
+use crate::GracefulShutdown;
 
 pin_project! {
     pub struct Dispatcher<S, T>
@@ -20,6 +25,10 @@ pin_project! {
     {
         #[pin]
         inner: inner::Dispatcher<S, T, Codec, Message>,
+        closing: Option<inner::DispatcherError<S::Error, Codec, Message>>,
+        shutdown: Option<LocalBoxFuture<'static, ()>>,
+        grace_period: Duration,
+        draining: Option<Pin<Box<Sleep>>>,
     }
 }
 
@@ -31,16 +40,34 @@ where
     S::Error: 'static,
 {
     pub fn new<F: IntoService<S, Frame>>(io: T, service: F) -> Self {
-        Dispatcher {
-            inner: inner::Dispatcher::new(Framed::new(io, Codec::new()), service),
-        }
+        Self::with(Framed::new(io, Codec::new()), service)
     }
 
     pub fn with<F: IntoService<S, Frame>>(framed: Framed<T, Codec>, service: F) -> Self {
         Dispatcher {
             inner: inner::Dispatcher::new(framed, service),
+            closing: None,
+            shutdown: None,
+            grace_period: Duration::ZERO,
+            draining: None,
         }
     }
+
+    /// Closes the session when a graceful shutdown starts.
+    ///
+    /// Once `shutdown` is notified, the dispatcher stops passing frames to the service, sends a
+    /// close frame with the [`CloseCode::Away`] (1001) code, and waits up to `grace_period` for
+    /// the peer's close frame. That frame is passed to the service, so that it can see the peer's
+    /// close code and reason. The dispatcher then resolves successfully.
+    pub fn close_on_shutdown(
+        mut self,
+        shutdown: &GracefulShutdown,
+        grace_period: Duration,
+    ) -> Self {
+        self.shutdown = Some(Box::pin(shutdown.notified()));
+        self.grace_period = grace_period;
+        self
+    }
 }
 
 impl<S, T> Future for Dispatcher<S, T>
@@ -53,7 +80,104 @@ where
     type Output = Result<(), inner::DispatcherError<S::Error, Codec, Message>>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.project().inner.poll(cx)
+        let mut this = self.project();
+
+        loop {
+            if this.closing.is_some() {
+                let framed = this.inner.as_mut().framed_pin();
+
+                if !framed.is_write_buf_empty() {
+                    if let Err(err) = ready!(framed.flush(cx)) {
+                        debug!("Error sending close frame: {:?}", err);
+                    }
+                }
+
+                return Poll::Ready(Err(this.closing.take().unwrap()));
+            }
+
+            if let Some(deadline) = this.draining {
+                let mut framed = this.inner.as_mut().framed_pin();
+
+                if !framed.is_write_buf_empty() {
+                    if let Poll::Ready(Err(err)) = framed.as_mut().flush(cx) {
+                        debug!("Error sending close frame: {:?}", err);
+                        return Poll::Ready(Ok(()));
+                    }
+                }
+
+                let reason = loop {
+                    match framed.as_mut().next_item(cx) {
+                        Poll::Ready(Some(Ok(Frame::Close(reason)))) => break Some(reason),
+                        // frames received after sending the close frame are discarded
+                        Poll::Ready(Some(Ok(_))) => {}
+                        Poll::Ready(Some(Err(_)) | None) => return Poll::Ready(Ok(())),
+                        Poll::Pending => break None,
+                    }
+                };
+
+                // the peer only replies once it has received our close frame, so nothing is left
+                // to flush
+                if let Some(reason) = reason {
+                    let fut = this.inner.service().call(Frame::Close(reason));
+
+                    actix_rt::spawn(async move {
+                        let _ = fut.await;
+                    });
+
+                    return Poll::Ready(Ok(()));
+                }
+
+                ready!(deadline.as_mut().poll(cx));
+                return Poll::Ready(Ok(()));
+            }
+
+            if let Some(shutdown) = this.shutdown {
+                if shutdown.as_mut().poll(cx).is_ready() {
+                    *this.shutdown = None;
+
+                    let reason = CloseReason::from(CloseCode::Away);
+
+                    return match this
+                        .inner
+                        .as_mut()
+                        .framed_pin()
+                        .write(Message::Close(Some(reason)))
+                    {
+                        Ok(()) => {
+                            *this.draining = Some(Box::pin(sleep(*this.grace_period)));
+                            continue;
+                        }
+                        Err(err) => Poll::Ready(Err(inner::DispatcherError::Encoder(err))),
+                    };
+                }
+            }
+
+            return match ready!(this.inner.as_mut().poll(cx)) {
+                // close the session with a policy violation before reporting the error
+                Err(inner::DispatcherError::Decoder(ProtocolError::QuotaExceeded(err))) => {
+                    let reason = CloseReason {
+                        code: CloseCode::Policy,
+                        description: Some(err.to_string()),
+                    };
+
+                    match this
+                        .inner
+                        .as_mut()
+                        .framed_pin()
+                        .write(Message::Close(Some(reason)))
+                    {
+                        Ok(()) => {
+                            *this.closing = Some(inner::DispatcherError::Decoder(
+                                ProtocolError::QuotaExceeded(err),
+                            ));
+                            continue;
+                        }
+                        Err(err) => Poll::Ready(Err(inner::DispatcherError::Encoder(err))),
+                    }
+                }
+                res => Poll::Ready(res),
+            };
+        }
     }
 }
 
@@ -289,6 +413,11 @@ mod inner {
             &mut self.framed
         }
 
+        /// Get pinned mutable reference to a framed instance wrapped by `Dispatcher` instance.
+        pub fn framed_pin(self: Pin<&mut Self>) -> Pin<&mut Framed<T, U>> {
+            self.project().framed
+        }
+
         /// Read from framed object.
         fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> bool
         where
@@ -0,0 +1,244 @@
+use std::{
+    cmp,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use bytes::Bytes;
+use futures_core::Stream;
+use pin_project_lite::pin_project;
+use tokio::io::AsyncRead;
+use tokio_util::io::ReaderStream;
+
+use super::{Item, Message};
+
+pin_project! {
+    /// Stream of the fragments of a single WebSocket message.
+    ///
+    /// Splits the data of a byte stream into continuation messages of at most `fragment_size`
+    /// bytes, so that messages larger than memory can be sent through the [`Codec`]. Data is read
+    /// from the inner stream one chunk at a time, as fragments are polled.
+    ///
+    /// Binary messages that fit in a single fragment are sent unfragmented. Fragments of text
+    /// messages are not checked to be valid UTF-8.
+    ///
+    /// # Examples
+    /// ```
+    /// # use actix_http::ws::{Fragments, Item, Message};
+    /// # use bytes::Bytes;
+    /// # use futures_util::StreamExt as _;
+    /// # actix_rt::System::new().block_on(async {
+    /// let data = futures_util::stream::iter([Ok::<_, ()>(Bytes::from_static(b"abcde"))]);
+    /// let mut fragments = Fragments::new(data, 2);
+    ///
+    /// assert_eq!(
+    ///     fragments.next().await.unwrap().unwrap(),
+    ///     Message::Continuation(Item::FirstBinary(Bytes::from_static(b"ab"))),
+    /// );
+    /// # });
+    /// ```
+    ///
+    /// [`Codec`]: super::Codec
+    #[derive(Debug)]
+    pub struct Fragments<S> {
+        #[pin]
+        stream: S,
+        fragment_size: usize,
+        text: bool,
+        rest: Bytes,
+        pending: Option<Bytes>,
+        started: bool,
+        eof: bool,
+    }
+}
+
+impl<S, E> Fragments<S>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+{
+    /// Constructs a binary message stream from a stream of bytes.
+    ///
+    /// # Panics
+    /// Panics if `fragment_size` is 0.
+    pub fn new(stream: S, fragment_size: usize) -> Self {
+        assert!(fragment_size > 0, "fragment size must be greater than 0");
+
+        Self {
+            stream,
+            fragment_size,
+            text: false,
+            rest: Bytes::new(),
+            pending: None,
+            started: false,
+            eof: false,
+        }
+    }
+
+    /// Sends the data as a text message instead of a binary message.
+    ///
+    /// The data of the whole message must be valid UTF-8.
+    pub fn text(mut self) -> Self {
+        self.text = true;
+        self
+    }
+}
+
+impl<R: AsyncRead> Fragments<ReaderStream<R>> {
+    /// Constructs a binary message stream from an `AsyncRead`.
+    ///
+    /// # Panics
+    /// Panics if `fragment_size` is 0.
+    pub fn from_reader(reader: R, fragment_size: usize) -> Self {
+        Self::new(
+            ReaderStream::with_capacity(reader, fragment_size),
+            fragment_size,
+        )
+    }
+}
+
+impl<S> Fragments<S> {
+    fn message(self: Pin<&mut Self>, data: Bytes, last: bool) -> Message {
+        let this = self.project();
+
+        if !*this.started {
+            *this.started = true;
+
+            if !last {
+                Message::Continuation(if *this.text {
+                    Item::FirstText(data)
+                } else {
+                    Item::FirstBinary(data)
+                })
+            } else if *this.text {
+                // whole text messages would need to be validated; close with an empty fragment
+                *this.pending = Some(Bytes::new());
+                Message::Continuation(Item::FirstText(data))
+            } else {
+                Message::Binary(data)
+            }
+        } else if last {
+            Message::Continuation(Item::Last(data))
+        } else {
+            Message::Continuation(Item::Continue(data))
+        }
+    }
+}
+
+impl<S, E> Stream for Fragments<S>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+{
+    type Item = Result<Message, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let mut this = self.as_mut().project();
+
+            if this.rest.is_empty() && !*this.eof {
+                match ready!(this.stream.as_mut().poll_next(cx)) {
+                    Some(Ok(chunk)) => *this.rest = chunk,
+                    Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                    None => *this.eof = true,
+                }
+
+                continue;
+            }
+
+            if !this.rest.is_empty() {
+                let len = cmp::min(*this.fragment_size, this.rest.len());
+                let fragment = this.rest.split_to(len);
+
+                // the previous fragment is now known not to be the last one
+                if let Some(prev) = this.pending.replace(fragment) {
+                    return Poll::Ready(Some(Ok(self.as_mut().message(prev, false))));
+                }
+
+                continue;
+            }
+
+            return match this.pending.take() {
+                Some(data) => Poll::Ready(Some(Ok(self.message(data, true)))),
+
+                // empty stream is sent as an empty message
+                None if !*this.started => Poll::Ready(Some(Ok(self.message(Bytes::new(), true)))),
+
+                None => Poll::Ready(None),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use futures_util::{stream, StreamExt as _};
+
+    use super::*;
+
+    async fn collect(chunks: &[&'static [u8]], fragment_size: usize, text: bool) -> Vec<Message> {
+        let data = stream::iter(
+            chunks
+                .iter()
+                .map(|chunk| Ok::<_, Infallible>(Bytes::from_static(chunk))),
+        );
+
+        let mut fragments = Fragments::new(data, fragment_size);
+
+        if text {
+            fragments = fragments.text();
+        }
+
+        fragments.map(Result::unwrap).collect().await
+    }
+
+    #[actix_rt::test]
+    async fn splits_chunks() {
+        assert_eq!(
+            collect(&[b"abc", b"", b"defg"], 2, false).await,
+            vec![
+                Message::Continuation(Item::FirstBinary(Bytes::from_static(b"ab"))),
+                Message::Continuation(Item::Continue(Bytes::from_static(b"c"))),
+                Message::Continuation(Item::Continue(Bytes::from_static(b"de"))),
+                Message::Continuation(Item::Last(Bytes::from_static(b"fg"))),
+            ],
+        );
+    }
+
+    #[actix_rt::test]
+    async fn single_fragment() {
+        assert_eq!(
+            collect(&[b"abc"], 4, false).await,
+            vec![Message::Binary(Bytes::from_static(b"abc"))],
+        );
+
+        assert_eq!(
+            collect(&[b"abc"], 4, true).await,
+            vec![
+                Message::Continuation(Item::FirstText(Bytes::from_static(b"abc"))),
+                Message::Continuation(Item::Last(Bytes::new())),
+            ],
+        );
+
+        assert_eq!(
+            collect(&[], 4, false).await,
+            vec![Message::Binary(Bytes::new())]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn from_reader() {
+        let fragments = Fragments::from_reader(&b"abcde"[..], 3)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(
+            fragments,
+            vec![
+                Message::Continuation(Item::FirstBinary(Bytes::from_static(b"abc"))),
+                Message::Continuation(Item::Last(Bytes::from_static(b"de"))),
+            ],
+        );
+    }
+}
@@ -12,15 +12,21 @@ use crate::{body::BoxBody, header::HeaderValue, RequestHead, Response, ResponseB
 
 mod codec;
 mod dispatcher;
+mod fragment;
 mod frame;
 mod mask;
 mod proto;
+mod quota;
+mod raw;
 
 pub use self::{
     codec::{Codec, Frame, Item, Message},
     dispatcher::Dispatcher,
+    fragment::Fragments,
     frame::Parser,
     proto::{hash_key, CloseCode, CloseReason, OpCode},
+    quota::{Quota, QuotaAction, QuotaExceeded, QuotaLimit},
+    raw::RawStream,
 };
 
 /// WebSocket protocol errors.
@@ -62,6 +68,10 @@ pub enum ProtocolError {
     #[display("unknown continuation fragment: {}", _0)]
     ContinuationFragment(#[error(not(source))] OpCode),
 
+    /// A received message exceeded the codec's [`Quota`].
+    #[display("{}", _0)]
+    QuotaExceeded(QuotaExceeded),
+
     /// I/O error.
     #[display("I/O error: {}", _0)]
     Io(io::Error),
@@ -151,6 +161,37 @@ pub fn handshake(req: &RequestHead) -> Result<ResponseBuilder, HandshakeError> {
     Ok(handshake_response(req))
 }
 
+/// Verify WebSocket handshake request and create handshake response with a subprotocol.
+///
+/// The first subprotocol in the request's `Sec-WebSocket-Protocol` header that is also in
+/// `protocols` is selected and set in the response. No subprotocol is set if none match.
+pub fn handshake_with_protocols(
+    req: &RequestHead,
+    protocols: &[&str],
+) -> Result<ResponseBuilder, HandshakeError> {
+    verify_handshake(req)?;
+
+    let mut res = handshake_response(req);
+
+    if let Some(protocol) = select_protocol(req, protocols) {
+        res.insert_header((header::SEC_WEBSOCKET_PROTOCOL, protocol));
+    }
+
+    Ok(res)
+}
+
+/// Returns the first subprotocol requested by the client that is also in `protocols`.
+pub fn select_protocol<'a>(req: &RequestHead, protocols: &[&'a str]) -> Option<&'a str> {
+    let req_protocols = req.headers().get(header::SEC_WEBSOCKET_PROTOCOL)?;
+
+    req_protocols
+        .to_str()
+        .ok()?
+        .split(',')
+        .map(str::trim)
+        .find_map(|req_p| protocols.iter().find(|p| **p == req_p).copied())
+}
+
 /// Verify WebSocket handshake request.
 pub fn verify_handshake(req: &RequestHead) -> Result<(), HandshakeError> {
     // WebSocket accepts only GET
@@ -333,6 +374,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_handshake_with_protocols() {
+        let req = TestRequest::default()
+            .insert_header((header::UPGRADE, "websocket"))
+            .insert_header((header::CONNECTION, "upgrade"))
+            .insert_header((header::SEC_WEBSOCKET_VERSION, "13"))
+            .insert_header((header::SEC_WEBSOCKET_KEY, "13"))
+            .insert_header((header::SEC_WEBSOCKET_PROTOCOL, "graphql-ws, mqtt"))
+            .finish();
+
+        assert_eq!(
+            select_protocol(req.head(), &["mqttv3.1", "mqtt"]),
+            Some("mqtt")
+        );
+        assert_eq!(select_protocol(req.head(), &["wamp"]), None);
+
+        let res = handshake_with_protocols(req.head(), &["mqtt"])
+            .unwrap()
+            .finish();
+        assert_eq!(res.status(), StatusCode::SWITCHING_PROTOCOLS);
+        assert_eq!(
+            res.headers().get(header::SEC_WEBSOCKET_PROTOCOL).unwrap(),
+            "mqtt"
+        );
+
+        let res = handshake_with_protocols(req.head(), &["wamp"])
+            .unwrap()
+            .finish();
+        assert!(!res.headers().contains_key(header::SEC_WEBSOCKET_PROTOCOL));
+    }
+
     #[test]
     fn test_ws_error_http_response() {
         let resp: Response<BoxBody> = HandshakeError::GetMethodRequired.into();
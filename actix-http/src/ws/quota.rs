@@ -0,0 +1,270 @@
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use derive_more::derive::{Display, Error};
+
+use super::{OpCode, ProtocolError};
+
+/// Limits on the messages received by a WebSocket [`Codec`] within a time window.
+///
+/// Text and binary messages are counted when their first frame is decoded, and the payload bytes
+/// of all of their frames are added up. Control frames are not counted. Counters reset at the end
+/// of each window, which starts with the first data frame received after the previous one ended.
+///
+/// Once a limit is exceeded, the [policy](Self::policy) decides what happens to the frame. By
+/// default, decoding fails with [`ProtocolError::QuotaExceeded`], which makes the
+/// [`Dispatcher`](super::Dispatcher) close the connection with [`CloseCode::Policy`] (1008).
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use actix_http::ws::{Codec, Quota, QuotaAction};
+///
+/// let quota = Quota::new(Duration::from_secs(1))
+///     .max_messages(20)
+///     .max_bytes(256 * 1024)
+///     .policy(|exceeded| {
+///         eprintln!("chatty client: {exceeded}");
+///         QuotaAction::Drop
+///     });
+///
+/// let codec = Codec::new().quota(quota);
+/// ```
+///
+/// [`Codec`]: super::Codec
+/// [`CloseCode::Policy`]: super::CloseCode::Policy
+#[derive(Clone)]
+pub struct Quota {
+    window: Duration,
+    max_messages: Option<u64>,
+    max_bytes: Option<u64>,
+    policy: Arc<dyn Fn(&QuotaExceeded) -> QuotaAction + Send + Sync>,
+}
+
+impl Quota {
+    /// Constructs a quota with the given window and no limits.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            max_messages: None,
+            max_bytes: None,
+            policy: Arc::new(|_| QuotaAction::Close),
+        }
+    }
+
+    /// Sets the maximum number of messages received per window.
+    pub fn max_messages(mut self, max: u64) -> Self {
+        self.max_messages = Some(max);
+        self
+    }
+
+    /// Sets the maximum number of message payload bytes received per window.
+    pub fn max_bytes(mut self, max: u64) -> Self {
+        self.max_bytes = Some(max);
+        self
+    }
+
+    /// Sets the callback that decides what happens to frames that exceed a limit.
+    ///
+    /// The callback is called for each such frame, so it can also be used to log or count them.
+    /// By default, every frame that exceeds a limit returns [`QuotaAction::Close`].
+    pub fn policy<F>(mut self, policy: F) -> Self
+    where
+        F: Fn(&QuotaExceeded) -> QuotaAction + Send + Sync + 'static,
+    {
+        self.policy = Arc::new(policy);
+        self
+    }
+}
+
+impl fmt::Debug for Quota {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Quota")
+            .field("window", &self.window)
+            .field("max_messages", &self.max_messages)
+            .field("max_bytes", &self.max_bytes)
+            .finish_non_exhaustive()
+    }
+}
+
+/// What to do with a frame that exceeds a [`Quota`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaAction {
+    /// Decode the frame as if the quota was not exceeded.
+    Warn,
+
+    /// Discard the message.
+    ///
+    /// Only unfragmented messages can be discarded. Frames of fragmented messages are handled as
+    /// with [`Close`](Self::Close).
+    Drop,
+
+    /// Fail decoding with [`ProtocolError::QuotaExceeded`].
+    Close,
+}
+
+/// Limit of a [`Quota`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum QuotaLimit {
+    /// Maximum number of messages per window.
+    #[display("{} messages", _0)]
+    Messages(u64),
+
+    /// Maximum number of message payload bytes per window.
+    #[display("{} bytes", _0)]
+    Bytes(u64),
+}
+
+/// Details of a frame that exceeded a [`Quota`].
+#[derive(Debug, Clone, PartialEq, Eq, Display, Error)]
+#[display("quota of {} per window exceeded", limit)]
+pub struct QuotaExceeded {
+    limit: QuotaLimit,
+    messages: u64,
+    bytes: u64,
+}
+
+impl QuotaExceeded {
+    /// Returns the limit that was exceeded.
+    pub fn limit(&self) -> QuotaLimit {
+        self.limit
+    }
+
+    /// Returns the number of messages received in the current window, including this one.
+    pub fn messages(&self) -> u64 {
+        self.messages
+    }
+
+    /// Returns the number of message payload bytes received in the current window, including
+    /// those of this frame.
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+/// Per-connection counters of a [`Quota`].
+#[derive(Debug, Clone)]
+pub(super) struct QuotaState {
+    quota: Quota,
+    window_start: Option<Instant>,
+    messages: u64,
+    bytes: u64,
+}
+
+impl QuotaState {
+    pub(super) fn new(quota: Quota) -> Self {
+        Self {
+            quota,
+            window_start: None,
+            messages: 0,
+            bytes: 0,
+        }
+    }
+
+    /// Counts a decoded frame against the quota.
+    ///
+    /// Returns `Ok(false)` if the frame should be discarded.
+    pub(super) fn admit(
+        &mut self,
+        finished: bool,
+        opcode: OpCode,
+        len: usize,
+    ) -> Result<bool, ProtocolError> {
+        let first_frame = match opcode {
+            OpCode::Text | OpCode::Binary => true,
+            OpCode::Continue => false,
+            _ => return Ok(true),
+        };
+
+        let now = Instant::now();
+
+        if self
+            .window_start
+            .map_or(true, |start| now.duration_since(start) >= self.quota.window)
+        {
+            self.window_start = Some(now);
+            self.messages = 0;
+            self.bytes = 0;
+        }
+
+        if first_frame {
+            self.messages += 1;
+        }
+        self.bytes += len as u64;
+
+        let limit = match (self.quota.max_messages, self.quota.max_bytes) {
+            (Some(max), _) if self.messages > max => QuotaLimit::Messages(max),
+            (_, Some(max)) if self.bytes > max => QuotaLimit::Bytes(max),
+            _ => return Ok(true),
+        };
+
+        let exceeded = QuotaExceeded {
+            limit,
+            messages: self.messages,
+            bytes: self.bytes,
+        };
+
+        match (self.quota.policy)(&exceeded) {
+            QuotaAction::Warn => Ok(true),
+            QuotaAction::Drop if first_frame && finished => Ok(false),
+            QuotaAction::Drop | QuotaAction::Close => Err(ProtocolError::QuotaExceeded(exceeded)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_limit() {
+        let mut state = QuotaState::new(Quota::new(Duration::from_secs(60)).max_messages(2));
+
+        assert!(state.admit(true, OpCode::Text, 10).unwrap());
+        assert!(state.admit(true, OpCode::Ping, 10).unwrap());
+        assert!(state.admit(false, OpCode::Binary, 10).unwrap());
+        assert!(state.admit(true, OpCode::Continue, 10).unwrap());
+
+        match state.admit(true, OpCode::Text, 10) {
+            Err(ProtocolError::QuotaExceeded(err)) => {
+                assert_eq!(err.limit(), QuotaLimit::Messages(2));
+                assert_eq!(err.messages(), 3);
+                assert_eq!(err.bytes(), 40);
+            }
+            res => panic!("unexpected result: {res:?}"),
+        }
+    }
+
+    #[test]
+    fn byte_limit_policy() {
+        let quota = Quota::new(Duration::from_secs(60))
+            .max_bytes(16)
+            .policy(|exceeded| match exceeded.bytes() {
+                ..=20 => QuotaAction::Warn,
+                _ => QuotaAction::Drop,
+            });
+        let mut state = QuotaState::new(quota);
+
+        assert!(state.admit(true, OpCode::Binary, 16).unwrap());
+        assert!(state.admit(true, OpCode::Binary, 4).unwrap());
+        assert!(!state.admit(true, OpCode::Binary, 4).unwrap());
+
+        // fragments can not be dropped
+        assert!(matches!(
+            state.admit(false, OpCode::Binary, 4),
+            Err(ProtocolError::QuotaExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn window_reset() {
+        let mut state = QuotaState::new(Quota::new(Duration::ZERO).max_messages(1));
+
+        assert!(state.admit(true, OpCode::Text, 1).unwrap());
+        assert!(state.admit(true, OpCode::Text, 1).unwrap());
+    }
+}
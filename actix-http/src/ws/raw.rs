@@ -0,0 +1,227 @@
+use std::{
+    cmp, io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use actix_codec::Framed;
+use bytes::Bytes;
+use pin_project_lite::pin_project;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::{CloseCode, CloseReason, Codec, Frame, Item, Message, ProtocolError};
+
+pin_project! {
+    /// Byte stream over the message payloads of a WebSocket session.
+    ///
+    /// Meant for tunneling binary subprotocols, such as MQTT, whose data is not split along
+    /// message boundaries. Reads yield the payloads of received text, binary, and continuation
+    /// frames, in order and without checking text payloads for valid UTF-8. Writes send each
+    /// buffer as a binary message.
+    ///
+    /// Pings are answered with pongs. A received close frame is answered with the same close code
+    /// and ends the read side; its reason is available from [`close_reason()`]. Shutting down the
+    /// write side sends a [`Normal`](CloseCode::Normal) close frame, unless one was already sent,
+    /// and closes the connection.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_codec::{AsyncRead, AsyncWrite, Framed};
+    /// use actix_http::{body::BodySize, h1, ws, Request};
+    /// use futures_util::SinkExt as _;
+    ///
+    /// async fn mqtt<T>(req: Request, mut framed: Framed<T, h1::Codec>) -> std::io::Result<()>
+    /// where
+    ///     T: AsyncRead + AsyncWrite + Unpin,
+    /// {
+    ///     let res = ws::handshake_with_protocols(req.head(), &["mqtt"])
+    ///         .map_err(std::io::Error::other)?
+    ///         .message_body(())
+    ///         .unwrap();
+    ///     framed.send((res, BodySize::None).into()).await?;
+    ///
+    ///     let stream = ws::RawStream::new(framed.replace_codec(ws::Codec::new()));
+    ///
+    ///     // hand the stream to an MQTT broker connection
+    ///     # drop(stream);
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// [`close_reason()`]: Self::close_reason
+    #[derive(Debug)]
+    pub struct RawStream<T> {
+        #[pin]
+        framed: Framed<T, Codec>,
+        read_buf: Bytes,
+        close_reason: Option<CloseReason>,
+        read_closed: bool,
+        close_sent: bool,
+        flush_pending: bool,
+    }
+}
+
+impl<T> RawStream<T>
+where
+    T: AsyncRead + AsyncWrite,
+{
+    /// Constructs a byte stream over a WebSocket session that has completed its handshake.
+    pub fn new(framed: Framed<T, Codec>) -> Self {
+        Self {
+            framed,
+            read_buf: Bytes::new(),
+            close_reason: None,
+            read_closed: false,
+            close_sent: false,
+            flush_pending: false,
+        }
+    }
+
+    /// Returns the reason of the close frame received from the peer, if any.
+    pub fn close_reason(&self) -> Option<&CloseReason> {
+        self.close_reason.as_ref()
+    }
+
+    /// Returns a reference to the underlying framed transport.
+    pub fn get_ref(&self) -> &Framed<T, Codec> {
+        &self.framed
+    }
+
+    /// Unwraps the underlying framed transport.
+    ///
+    /// Data that was received but not read yet is discarded.
+    pub fn into_inner(self) -> Framed<T, Codec> {
+        self.framed
+    }
+}
+
+fn io_error(err: ProtocolError) -> io::Error {
+    match err {
+        ProtocolError::Io(err) => err,
+        err => io::Error::new(io::ErrorKind::InvalidData, err),
+    }
+}
+
+impl<T> AsyncRead for RawStream<T>
+where
+    T: AsyncRead + AsyncWrite,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+
+        loop {
+            // send control frame replies without waiting for the next write
+            if *this.flush_pending && this.framed.as_mut().flush(cx).is_ready() {
+                *this.flush_pending = false;
+            }
+
+            if !this.read_buf.is_empty() {
+                let len = cmp::min(buf.remaining(), this.read_buf.len());
+                buf.put_slice(&this.read_buf.split_to(len));
+                return Poll::Ready(Ok(()));
+            }
+
+            if *this.read_closed {
+                return Poll::Ready(Ok(()));
+            }
+
+            let reply = match ready!(this.framed.as_mut().next_item(cx)) {
+                Some(Ok(Frame::Text(data) | Frame::Binary(data))) => {
+                    *this.read_buf = data;
+                    continue;
+                }
+
+                Some(Ok(Frame::Continuation(
+                    Item::FirstText(data)
+                    | Item::FirstBinary(data)
+                    | Item::Continue(data)
+                    | Item::Last(data),
+                ))) => {
+                    *this.read_buf = data;
+                    continue;
+                }
+
+                Some(Ok(Frame::Ping(data))) => Message::Pong(data),
+                Some(Ok(Frame::Pong(_))) => continue,
+
+                Some(Ok(Frame::Close(reason))) => {
+                    *this.read_closed = true;
+
+                    if *this.close_sent {
+                        *this.close_reason = reason;
+                        continue;
+                    }
+
+                    *this.close_sent = true;
+                    let reply = reason.as_ref().map(|reason| CloseReason::from(reason.code));
+                    *this.close_reason = reason;
+                    Message::Close(reply)
+                }
+
+                Some(Err(err)) => return Poll::Ready(Err(io_error(err))),
+
+                None => {
+                    *this.read_closed = true;
+                    continue;
+                }
+            };
+
+            this.framed.as_mut().write(reply).map_err(io_error)?;
+            *this.flush_pending = true;
+        }
+    }
+}
+
+impl<T> AsyncWrite for RawStream<T>
+where
+    T: AsyncRead + AsyncWrite,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut this = self.project();
+
+        if *this.close_sent {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+
+        if this.framed.is_write_buf_full() {
+            ready!(this.framed.as_mut().flush(cx)).map_err(io_error)?;
+        }
+
+        this.framed
+            .as_mut()
+            .write(Message::Binary(Bytes::copy_from_slice(buf)))
+            .map_err(io_error)?;
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.project();
+        ready!(this.framed.flush(cx)).map_err(io_error)?;
+        *this.flush_pending = false;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+
+        if !*this.close_sent {
+            *this.close_sent = true;
+
+            this.framed
+                .as_mut()
+                .write(Message::Close(Some(CloseCode::Normal.into())))
+                .map_err(io_error)?;
+        }
+
+        this.framed.close(cx).map_err(io_error)
+    }
+}
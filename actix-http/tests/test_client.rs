@@ -1,6 +1,6 @@
 use std::convert::Infallible;
 
-use actix_http::{body::BoxBody, HttpMessage, HttpService, Request, Response, StatusCode};
+use actix_http::{body::BoxBody, header, HttpMessage, HttpService, Request, Response, StatusCode};
 use actix_http_test::test_server;
 use actix_service::ServiceFactoryExt;
 use actix_utils::future;
@@ -154,3 +154,53 @@ async fn h1_expect() {
     let response = request.send_body("expect body").await.unwrap();
     assert!(response.status().is_success());
 }
+
+#[derive(Debug, Clone, Copy)]
+struct UploadLimit(u64);
+
+#[actix_rt::test]
+async fn h1_expect_conn_info() {
+    let srv = test_server(move || {
+        HttpService::build()
+            .on_connect_ext(|_, data| {
+                data.insert(UploadLimit(4));
+            })
+            .expect(|req: Request| async move {
+                assert!(req.peer_addr().unwrap().ip().is_loopback());
+
+                let limit = req.conn_data::<UploadLimit>().unwrap().0;
+                let len = req
+                    .headers()
+                    .get(header::CONTENT_LENGTH)
+                    .and_then(|len| len.to_str().ok())
+                    .and_then(|len| len.parse::<u64>().ok());
+
+                if len.is_some_and(|len| len <= limit) {
+                    Ok(req)
+                } else {
+                    Err(ExpectFailed)
+                }
+            })
+            .h1(|_| future::ok::<_, Infallible>(Response::ok()))
+            .tcp()
+    })
+    .await;
+
+    let response = srv
+        .post("/")
+        .insert_header(("Expect", "100-continue"))
+        .send_body("body")
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    // rejected before the body is read; connection is not reused
+    let response = srv
+        .post("/")
+        .insert_header(("Expect", "100-continue"))
+        .send_body("expect body")
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::EXPECTATION_FAILED);
+    assert_eq!(response.headers().get(header::CONNECTION).unwrap(), "close");
+}
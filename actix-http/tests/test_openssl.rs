@@ -2,13 +2,19 @@
 
 extern crate tls_openssl as openssl;
 
-use std::{convert::Infallible, io, time::Duration};
+use std::{
+    convert::Infallible,
+    io,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use actix_http::{
     body::{BodyStream, BoxBody, SizedStream},
     error::PayloadError,
     header::{self, HeaderValue},
-    Error, HttpService, Method, Request, Response, StatusCode, TlsAcceptorConfig, Version,
+    ConnectionInfo, ConnectionObserver, Error, HttpService, Method, Request, RequestHead, Response,
+    StatusCode, TlsAcceptorConfig, TlsInfo, Version,
 };
 use actix_http_test::test_server;
 use actix_service::{fn_service, ServiceFactoryExt};
@@ -446,3 +452,183 @@ async fn h2_on_connect() {
     let response = srv.sget("/").send().await.unwrap();
     assert!(response.status().is_success());
 }
+
+#[actix_rt::test]
+async fn h2_tls_info() {
+    let srv = test_server(move || {
+        HttpService::build()
+            .on_connect_ext(|_, data| {
+                // inserted before user callbacks run
+                assert!(data.contains::<TlsInfo>());
+            })
+            .h2(|req: Request| {
+                let tls = req.conn_data::<TlsInfo>().unwrap();
+                assert!(tls.protocol_version().starts_with("TLSv1"));
+                assert!(tls.cipher_suite().is_some());
+                assert_eq!(tls.server_name(), Some("localhost"));
+                assert_eq!(tls.alpn_protocol(), Some(&b"h2"[..]));
+                assert!(tls.peer_certificate().is_none());
+                ok::<_, Infallible>(Response::ok())
+            })
+            .openssl(tls_config())
+            .map_err(|_| ())
+    })
+    .await;
+
+    let response = srv.sget("/").send().await.unwrap();
+    assert!(response.status().is_success());
+}
+
+#[derive(Clone, Default)]
+struct EventLog(Arc<Mutex<Vec<String>>>);
+
+impl ConnectionObserver for EventLog {
+    fn connection_opened(&self, conn: &ConnectionInfo) {
+        let mut events = self.0.lock().unwrap();
+        events.push(format!("opened {:?}", conn.protocol()));
+    }
+
+    fn request_started(&self, _conn: &ConnectionInfo, req: &RequestHead) {
+        let mut events = self.0.lock().unwrap();
+        events.push(format!("started {}", req.uri.path()));
+    }
+
+    fn request_finished(&self, _conn: &ConnectionInfo, status: StatusCode) {
+        let mut events = self.0.lock().unwrap();
+        events.push(format!("finished {}", status.as_u16()));
+    }
+
+    fn bytes_written(&self, _conn: &ConnectionInfo, n: usize) {
+        assert!(n > 0);
+        let mut events = self.0.lock().unwrap();
+        if !events.iter().any(|ev| ev == "written") {
+            events.push("written".to_owned());
+        }
+    }
+}
+
+#[actix_rt::test]
+async fn h2_connection_observer() {
+    let log = EventLog::default();
+
+    let srv = test_server({
+        let log = log.clone();
+        move || {
+            HttpService::build()
+                .observer(log.clone())
+                .h2(|_| ok::<_, Infallible>(Response::ok().set_body("hello")))
+                .openssl(tls_config())
+                .map_err(|_| ())
+        }
+    })
+    .await;
+
+    let response = srv.sget("/test").send().await.unwrap();
+    assert!(response.status().is_success());
+
+    let events = log.0.lock().unwrap().clone();
+    assert_eq!(events[0], "opened Http2");
+    assert!(events.contains(&"written".to_owned()), "{events:?}");
+    assert!(events.contains(&"started /test".to_owned()), "{events:?}");
+    assert!(events.contains(&"finished 200".to_owned()), "{events:?}");
+}
+
+#[actix_rt::test]
+async fn h1_tls_handshake_timeout() {
+    use std::io::Read as _;
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let errors2 = Arc::clone(&errors);
+
+    let srv = test_server(move || {
+        let errors = Arc::clone(&errors2);
+
+        HttpService::build()
+            .h1(|_| ok::<_, Error>(Response::ok()))
+            .openssl_with_config(
+                tls_config(),
+                TlsAcceptorConfig::default().handshake_timeout(Duration::from_millis(200)),
+            )
+            .map_err(move |err| {
+                let timed_out = matches!(err, actix_tls::accept::TlsError::Timeout);
+                errors.lock().unwrap().push(timed_out);
+            })
+    })
+    .await;
+
+    // connect without ever starting the handshake
+    let mut stream = std::net::TcpStream::connect(srv.addr()).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    let mut buf = [0; 16];
+    assert_eq!(stream.read(&mut buf).unwrap(), 0);
+
+    assert_eq!(*errors.lock().unwrap(), vec![true]);
+}
+
+#[actix_rt::test]
+async fn alpn_protocol_service() {
+    use std::{io::Read as _, net::SocketAddr};
+
+    use actix_tls::accept::openssl::TlsStream;
+    use openssl::ssl::{SslConnector, SslVerifyMode};
+    use tokio::io::AsyncWriteExt as _;
+
+    const GREETING: &[u8] = b"x-greeting";
+
+    let srv = test_server(move || {
+        let rcgen::CertifiedKey { cert, key_pair } =
+            rcgen::generate_simple_self_signed(["localhost".to_owned()]).unwrap();
+        let cert = X509::from_pem(cert.pem().as_bytes()).unwrap();
+        let key = PKey::private_key_from_pem(key_pair.serialize_pem().as_bytes()).unwrap();
+
+        let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+        builder.set_certificate(&cert).unwrap();
+        builder.set_private_key(&key).unwrap();
+        builder.set_alpn_select_callback(|_, protos| {
+            openssl::ssl::select_next_proto(b"\x0ax-greeting\x02h2", protos)
+                .ok_or(openssl::ssl::AlpnError::NOACK)
+        });
+
+        HttpService::build()
+            .finish(|_| ok::<_, Error>(Response::ok()))
+            .alpn_protocol(
+                GREETING,
+                fn_service(
+                    |(mut io, peer_addr): (TlsStream<_>, Option<SocketAddr>)| async move {
+                        assert!(peer_addr.is_some());
+                        io.write_all(b"hello\n").await?;
+                        io.shutdown().await?;
+                        Ok::<_, io::Error>(())
+                    },
+                ),
+            )
+            .openssl(builder.build())
+            .map_err(|_| ())
+    })
+    .await;
+
+    let addr = srv.addr();
+    let greeting = actix_rt::task::spawn_blocking(move || {
+        let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+        builder.set_verify(SslVerifyMode::NONE);
+        builder.set_alpn_protos(b"\x0ax-greeting").unwrap();
+
+        let tcp = std::net::TcpStream::connect(addr).unwrap();
+        let mut stream = builder.build().connect("localhost", tcp).unwrap();
+        assert_eq!(stream.ssl().selected_alpn_protocol(), Some(GREETING));
+
+        let mut buf = Vec::new();
+        stream.read_to_end(&mut buf).unwrap();
+        buf
+    })
+    .await
+    .unwrap();
+    assert_eq!(greeting, b"hello\n");
+
+    // other connections are still served as HTTP
+    let response = srv.sget("/").send().await.unwrap();
+    assert!(response.status().is_success());
+}
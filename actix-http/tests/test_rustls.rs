@@ -15,7 +15,7 @@ use actix_http::{
     body::{BodyStream, BoxBody, SizedStream},
     error::PayloadError,
     header::{self, HeaderName, HeaderValue},
-    Error, HttpService, Method, Request, Response, StatusCode, TlsAcceptorConfig, Version,
+    Error, HttpService, Method, Request, Response, StatusCode, TlsAcceptorConfig, TlsInfo, Version,
 };
 use actix_http_test::test_server;
 use actix_rt::pin;
@@ -171,6 +171,28 @@ async fn h2_1() -> io::Result<()> {
     Ok(())
 }
 
+#[actix_rt::test]
+async fn h1_tls_info() -> io::Result<()> {
+    let srv = test_server(move || {
+        HttpService::build()
+            .h1(|req: Request| {
+                let tls = req.conn_data::<TlsInfo>().unwrap();
+                assert!(tls.protocol_version().starts_with("TLSv1."));
+                assert!(tls.cipher_suite().is_some());
+                assert_eq!(tls.server_name(), Some("localhost"));
+                assert_eq!(tls.alpn_protocol(), Some(HTTP1_1_ALPN_PROTOCOL));
+                assert!(tls.peer_certificate().is_none());
+                ok::<_, Error>(Response::ok())
+            })
+            .rustls_0_23(tls_config())
+    })
+    .await;
+
+    let response = srv.sget("/").send().await.unwrap();
+    assert!(response.status().is_success());
+    Ok(())
+}
+
 #[actix_rt::test]
 async fn h2_body1() -> io::Result<()> {
     let data = "HELLOWORLD".to_owned().repeat(64 * 1024);
@@ -1,17 +1,22 @@
 use std::{
     convert::Infallible,
     io::{Read, Write},
-    net, thread,
+    net,
+    sync::{Arc, Mutex},
+    thread,
     time::{Duration, Instant},
 };
 
 use actix_http::{
-    body::{self, BodyStream, BoxBody, SizedStream},
-    header, Error, HttpService, KeepAlive, Request, Response, StatusCode, Version,
+    body::{self, BodyStream, BoxBody, SizedStream, TrailerBody},
+    error::DispatchError,
+    header, CannedResponses, ClientDisconnect, ConnectionInfo, ConnectionObserver,
+    DisconnectReason, EarlyHints, Error, EtagCache, HttpMessage as _, HttpService, KeepAlive,
+    Request, RequestHead, Response, StatusCode, Version,
 };
 use actix_http_test::test_server;
 use actix_rt::{net::TcpStream, time::sleep};
-use actix_service::fn_service;
+use actix_service::{fn_service, ServiceFactoryExt as _};
 use actix_utils::future::{err, ok, ready};
 use bytes::Bytes;
 use derive_more::derive::{Display, Error};
@@ -772,6 +777,231 @@ async fn h1_on_connect() {
     srv.stop().await;
 }
 
+#[actix_rt::test]
+async fn h1_on_disconnect() {
+    let disconnects = Arc::new(Mutex::new(Vec::new()));
+
+    let mut srv = test_server({
+        let disconnects = Arc::clone(&disconnects);
+        move || {
+            let disconnects = Arc::clone(&disconnects);
+            HttpService::build()
+                .keep_alive(Duration::from_millis(200))
+                .on_connect_ext(|_, data| {
+                    data.insert(20isize);
+                })
+                .on_disconnect(move |data, reason| {
+                    disconnects
+                        .lock()
+                        .unwrap()
+                        .push((data.get::<isize>().copied(), reason));
+                })
+                .h1(|_| ok::<_, Infallible>(Response::ok()))
+                .tcp()
+        }
+    })
+    .await;
+
+    // closed by the client
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\nconnection: close\r\n\r\n");
+    let _ = stream.read_to_end(&mut Vec::new());
+
+    // left idle until the keep-alive timeout elapses
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\n\r\n");
+    let _ = stream.read_to_end(&mut Vec::new());
+
+    for _ in 0..10 {
+        if disconnects.lock().unwrap().len() == 2 {
+            break;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+
+    assert_eq!(
+        *disconnects.lock().unwrap(),
+        [
+            (Some(20), DisconnectReason::Closed),
+            (Some(20), DisconnectReason::KeepAliveTimeout),
+        ]
+    );
+
+    srv.stop().await;
+}
+
+#[cfg(feature = "experimental-io-uring")]
+#[actix_rt::test]
+async fn h1_io_uring() {
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .on_connect_ext(|io: &actix_http::UringStream, data| {
+                data.insert(io.get_ref().set_nodelay(true).is_ok());
+            })
+            .h1(|req: Request| {
+                assert_eq!(req.conn_data::<bool>(), Some(&true));
+                ok::<_, Infallible>(Response::ok().set_body(STR.repeat(1024)))
+            })
+            .tcp_uring()
+    })
+    .await;
+
+    for _ in 0..2 {
+        let mut response = srv.get("/").send().await.unwrap();
+        assert!(response.status().is_success());
+
+        let bytes = response.body().limit(usize::MAX).await.unwrap();
+        assert_eq!(bytes, STR.repeat(1024));
+    }
+
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h1_on_request_head() {
+    let calls = Arc::new(Mutex::new(0));
+
+    let mut srv = test_server({
+        let calls = Arc::clone(&calls);
+
+        move || {
+            let calls = Arc::clone(&calls);
+
+            HttpService::build()
+                .on_request_head(|req: &mut Request| match req.path() {
+                    "/reject" => Err(Response::build(StatusCode::FORBIDDEN)
+                        .body("denied")
+                        .map_into_boxed_body()),
+                    "/old" => {
+                        req.head_mut().uri = "/new".parse().unwrap();
+                        req.extensions_mut().insert("rerouted");
+                        Ok(())
+                    }
+                    _ => Ok(()),
+                })
+                .h1(move |req: Request| {
+                    *calls.lock().unwrap() += 1;
+                    let tag = req.extensions().get::<&str>().copied().unwrap_or("none");
+                    ok::<_, Infallible>(Response::ok().set_body(format!("{} {}", req.path(), tag)))
+                })
+                .tcp()
+        }
+    })
+    .await;
+
+    // rejected requests without a payload keep the connection open
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream
+        .write_all(b"GET /reject HTTP/1.1\r\n\r\nGET /old HTTP/1.1\r\nconnection: close\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 403 Forbidden\r\n"), "{data}");
+    assert!(data.contains("denied"), "{data}");
+    assert!(data.contains("HTTP/1.1 200 OK\r\n"), "{data}");
+    assert!(data.ends_with("/new rerouted"), "{data}");
+    assert_eq!(*calls.lock().unwrap(), 1);
+
+    // rejected requests with a payload close the connection without reading it
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"POST /reject HTTP/1.1\r\ncontent-length: 5\r\n\r\nhelloGET / HTTP/1.1\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 403 Forbidden\r\n"), "{data}");
+    assert!(data.contains("connection: close\r\n"), "{data}");
+    assert!(!data.contains("200 OK"), "{data}");
+    assert_eq!(*calls.lock().unwrap(), 1);
+
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h1_canned_responses() {
+    let calls = Arc::new(Mutex::new(0));
+
+    let mut srv = test_server({
+        let calls = Arc::clone(&calls);
+
+        move || {
+            let calls = Arc::clone(&calls);
+
+            HttpService::build()
+                .canned_responses(CannedResponses::new().text("/healthz", "ok"))
+                .on_request_head(|_| Err(Response::new(StatusCode::FORBIDDEN)))
+                .h1(move |_| {
+                    *calls.lock().unwrap() += 1;
+                    ok::<_, Infallible>(Response::ok())
+                })
+                .tcp()
+        }
+    })
+    .await;
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"GET /healthz HTTP/1.1\r\n\r\nHEAD /healthz?probe HTTP/1.1\r\n\r\n\
+        GET / HTTP/1.1\r\nconnection: close\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+
+    let (get, rest) = data.split_once("ok").unwrap();
+    assert!(get.starts_with("HTTP/1.1 200 OK\r\n"), "{data}");
+    assert!(get.contains("content-length: 2\r\n"), "{data}");
+    assert!(get.contains("text/plain; charset=utf-8\r\n"), "{data}");
+    assert!(rest.starts_with("HTTP/1.1 200 OK\r\n"), "{data}");
+    assert!(!rest.contains("ok"), "{data}");
+    assert!(rest.contains("HTTP/1.1 403 Forbidden\r\n"), "{data}");
+    assert_eq!(*calls.lock().unwrap(), 0);
+
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h1_etag_cache() {
+    let calls = Arc::new(Mutex::new(0));
+    let etags = EtagCache::new();
+    etags.insert("/schedule", header::HeaderValue::from_static("\"v1\""));
+
+    let mut srv = test_server({
+        let calls = Arc::clone(&calls);
+        let etags = etags.clone();
+
+        move || {
+            let calls = Arc::clone(&calls);
+
+            HttpService::build()
+                .etag_cache(etags.clone())
+                .h1(move |_| {
+                    *calls.lock().unwrap() += 1;
+                    ok::<_, Infallible>(Response::ok())
+                })
+                .tcp()
+        }
+    })
+    .await;
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"GET /schedule HTTP/1.1\r\nif-none-match: W/\"v1\"\r\n\r\n\
+        GET /schedule HTTP/1.1\r\nif-none-match: \"v0\"\r\nconnection: close\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+
+    let (not_modified, rest) = data.split_once("\r\n\r\n").unwrap();
+    assert!(
+        not_modified.starts_with("HTTP/1.1 304 Not Modified\r\n"),
+        "{data}"
+    );
+    assert!(not_modified.contains("etag: \"v1\""), "{data}");
+    assert!(rest.starts_with("HTTP/1.1 200 OK\r\n"), "{data}");
+    assert_eq!(*calls.lock().unwrap(), 1);
+
+    srv.stop().await;
+}
+
 /// Tests compliance with 304 Not Modified spec in RFC 7232 §4.1.
 /// https://datatracker.ietf.org/doc/html/rfc7232#section-4.1
 #[actix_rt::test]
@@ -895,3 +1125,968 @@ async fn h2c_auto() {
 
     srv.stop().await;
 }
+
+#[actix_rt::test]
+async fn h2c_settings() {
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .h2_max_concurrent_streams(3)
+            .h2_initial_window_size(1 << 20)
+            .h2_max_frame_size(1 << 15)
+            .finish(|_| ok::<_, Infallible>(Response::ok()))
+            .tcp_auto_h2c()
+    })
+    .await;
+
+    let tcp = TcpStream::connect(srv.addr()).await.unwrap();
+    let (h2, mut connection) = h2::client::handshake(tcp).await.unwrap();
+
+    // drive the connection in place so its negotiated settings can be inspected afterwards
+    let request = ::http::Request::new(());
+    let res = tokio::select! {
+        res = async {
+            let mut h2 = h2.ready().await.unwrap();
+            let (response, _) = h2.send_request(request, true).unwrap();
+            response.await.unwrap()
+        } => res,
+        _ = &mut connection => panic!("connection closed before response"),
+    };
+    assert!(res.status().is_success());
+
+    // server settings have been received by the time the response arrives
+    assert_eq!(connection.max_concurrent_send_streams(), 3);
+
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h2c_on_request_head() {
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .on_request_head(|_| {
+                Err(Response::build(StatusCode::FORBIDDEN)
+                    .body("denied")
+                    .map_into_boxed_body())
+            })
+            .finish(|_| async {
+                panic!("service should not be called");
+                #[allow(unreachable_code)]
+                Ok::<_, Infallible>(Response::ok())
+            })
+            .tcp_auto_h2c()
+    })
+    .await;
+
+    let tcp = TcpStream::connect(srv.addr()).await.unwrap();
+    let (h2, connection) = h2::client::handshake(tcp).await.unwrap();
+    tokio::spawn(async move { connection.await.unwrap() });
+
+    // the response is sent before any of the request payload
+    let request = ::http::Request::post("/upload").body(()).unwrap();
+    let mut h2 = h2.ready().await.unwrap();
+    let (response, _send) = h2.send_request(request, false).unwrap();
+    let res = response.await.unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let mut body = res.into_body();
+    let chunk = body.data().await.unwrap().unwrap();
+    assert_eq!(chunk, "denied");
+
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h2c_keep_alive_ping() {
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .keep_alive(KeepAlive::Disabled)
+            .h2_keep_alive_interval(Duration::from_millis(100))
+            .h2_keep_alive_timeout(Duration::from_millis(100))
+            .finish(|_| ok::<_, Infallible>(Response::ok()))
+            .tcp_auto_h2c()
+    })
+    .await;
+
+    // responsive client acknowledges pings and keeps the connection open
+    let tcp = TcpStream::connect(srv.addr()).await.unwrap();
+    let (h2, connection) = h2::client::handshake(tcp).await.unwrap();
+    tokio::spawn(async move { connection.await.unwrap() });
+    sleep(Duration::from_millis(600)).await;
+
+    let mut h2 = h2.ready().await.unwrap();
+    let (response, _) = h2.send_request(::http::Request::new(()), true).unwrap();
+    assert!(response.await.unwrap().status().is_success());
+
+    // unresponsive client never acknowledges the ping, so the server closes the connection
+    let tcp = TcpStream::connect(srv.addr()).await.unwrap();
+    let (_h2, connection) = h2::client::handshake(tcp).await.unwrap();
+    sleep(Duration::from_millis(600)).await;
+
+    let closed = actix_rt::time::timeout(Duration::from_millis(500), connection).await;
+    assert!(closed.is_ok());
+
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h2c_graceful_shutdown() {
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+
+    let mut srv = test_server(move || {
+        let mut shutdown_rx = shutdown_rx.clone();
+
+        HttpService::build()
+            .shutdown_signal(async move {
+                let _ = shutdown_rx.wait_for(|started| *started).await;
+            })
+            .finish(|_| async {
+                sleep(Duration::from_millis(300)).await;
+                Ok::<_, Infallible>(Response::ok().set_body("done"))
+            })
+            .tcp_auto_h2c()
+    })
+    .await;
+
+    let tcp = TcpStream::connect(srv.addr()).await.unwrap();
+    let (h2, connection) = h2::client::handshake(tcp).await.unwrap();
+    let connection = tokio::spawn(connection);
+    let mut h2 = h2.ready().await.unwrap();
+
+    let (response, _) = h2.send_request(::http::Request::new(()), true).unwrap();
+    sleep(Duration::from_millis(100)).await;
+    shutdown_tx.send_replace(true);
+
+    // in-flight stream completes after shutdown has started
+    let (head, mut body) = response.await.unwrap().into_parts();
+    assert!(head.status.is_success());
+    assert_eq!(body.data().await.unwrap().unwrap(), &b"done"[..]);
+
+    // server closes the connection with GOAWAY instead of resetting it, while the client would
+    // otherwise keep it open
+    let closed = actix_rt::time::timeout(Duration::from_secs(1), connection).await;
+    closed.unwrap().unwrap().unwrap();
+    assert!(h2.send_request(::http::Request::new(()), true).is_err());
+
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h1_response_trailers() {
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .h1(|_| {
+                let mut trailers = header::HeaderMap::new();
+                trailers.insert(
+                    header::HeaderName::from_static("grpc-status"),
+                    header::HeaderValue::from_static("0"),
+                );
+                let body = TrailerBody::new(
+                    BodyStream::new(once(ok::<_, Infallible>(Bytes::from_static(b"hello")))),
+                    trailers,
+                );
+                ok::<_, Infallible>(Response::ok().set_body(body))
+            })
+            .tcp()
+    })
+    .await;
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+
+    assert!(data.contains("transfer-encoding: chunked\r\n"));
+    assert!(data.ends_with("5\r\nhello\r\n0\r\ngrpc-status: 0\r\n\r\n"));
+
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h2c_response_trailers() {
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .h2(|_| {
+                let mut trailers = header::HeaderMap::new();
+                trailers.insert(
+                    header::HeaderName::from_static("grpc-status"),
+                    header::HeaderValue::from_static("0"),
+                );
+                let body = TrailerBody::new("hello", trailers);
+                ok::<_, Infallible>(Response::ok().set_body(body))
+            })
+            .tcp()
+    })
+    .await;
+
+    let tcp = TcpStream::connect(srv.addr()).await.unwrap();
+    let (h2, connection) = h2::client::handshake(tcp).await.unwrap();
+    tokio::spawn(async move { connection.await.unwrap() });
+    let mut h2 = h2.ready().await.unwrap();
+
+    let request = ::http::Request::new(());
+    let (response, _) = h2.send_request(request, true).unwrap();
+    let (head, mut body) = response.await.unwrap().into_parts();
+    let data = body.data().await.unwrap().unwrap();
+    let trailers = body.trailers().await.unwrap().unwrap();
+
+    assert!(head.status.is_success());
+    assert_eq!(data, &b"hello"[..]);
+    assert_eq!(trailers.get("grpc-status").unwrap(), "0");
+
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h1_early_hints() {
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .h1(|req: Request| async move {
+                let Some(hints) = req.extensions().get::<EarlyHints>().cloned() else {
+                    return Ok::<_, Infallible>(Response::ok().set_body("no hints"));
+                };
+
+                assert!(hints.link(header::HeaderValue::from_static(
+                    "</style.css>; rel=preload; as=style"
+                )));
+
+                sleep(Duration::from_millis(50)).await;
+                assert!(hints.link(header::HeaderValue::from_static(
+                    "</app.js>; rel=preload; as=script"
+                )));
+
+                Ok(Response::ok().set_body("done"))
+            })
+            .tcp()
+    })
+    .await;
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+
+    assert!(data.starts_with(
+        "HTTP/1.1 103 Early Hints\r\nlink: </style.css>; rel=preload; as=style\r\n\r\n\
+         HTTP/1.1 103 Early Hints\r\nlink: </app.js>; rel=preload; as=script\r\n\r\n\
+         HTTP/1.1 200 OK\r\n"
+    ));
+    assert!(data.ends_with("done"));
+
+    // HTTP/1.0 clients do not get a hints handle
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.0\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.0 200 OK\r\n"));
+    assert!(data.ends_with("no hints"));
+
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h1_read_header_timeout() {
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .keep_alive(Duration::from_secs(5))
+            .read_header_timeout(Duration::from_millis(200))
+            .h1(|_| ok::<_, Infallible>(Response::ok()))
+            .tcp()
+    })
+    .await;
+
+    let start = Instant::now();
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\n\r\nGET /slow HTTP/1.1\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(
+        data.contains("HTTP/1.1 408 Request Timeout\r\n"),
+        "missing 408: {}",
+        data
+    );
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h1_pipelined_requests() {
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .pipelined_requests(2)
+            .h1(|req: Request| async move {
+                sleep(Duration::from_millis(500)).await;
+                let body = req.path().trim_start_matches('/').to_owned();
+                Ok::<_, Infallible>(Response::ok().set_body(body))
+            })
+            .tcp()
+    })
+    .await;
+
+    let start = Instant::now();
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"GET /a HTTP/1.1\r\n\r\nHEAD /b HTTP/1.1\r\n\r\nGET /c HTTP/1.1\r\nconnection: close\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+
+    // requests are handled concurrently
+    assert!(
+        start.elapsed() < Duration::from_millis(1200),
+        "{:?}",
+        start.elapsed()
+    );
+
+    // responses are written in order, each encoded for its own request
+    let responses = data
+        .split("HTTP/1.1 200 OK\r\n")
+        .skip(1)
+        .collect::<Vec<_>>();
+    assert_eq!(responses.len(), 3, "{}", data);
+    assert!(responses[0].ends_with("\r\n\r\na"), "{}", data);
+    assert!(responses[1].ends_with("\r\n\r\n"), "{}", data);
+    assert!(responses[2].ends_with("\r\n\r\nc"), "{}", data);
+
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h1_send_file() {
+    let contents = (0..1_000_000u32)
+        .map(|n| (n % 251) as u8)
+        .collect::<Vec<_>>();
+    let path = std::env::temp_dir().join(format!("actix-http-send-file-{}", std::process::id()));
+    std::fs::write(&path, &contents).unwrap();
+
+    let file_path = path.clone();
+    let mut srv = test_server(move || {
+        let path = file_path.clone();
+
+        // accept plain TCP streams as-is so the zero-copy path is used regardless of crate features
+        fn_service(|io: TcpStream| ok::<_, DispatchError>((io, None))).and_then(
+            HttpService::build().h1(move |_| {
+                let body = body::SendFile::new(std::fs::File::open(&path).unwrap(), 100, 900_000);
+                ok::<_, Infallible>(Response::ok().set_body(body))
+            }),
+        )
+    })
+    .await;
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"GET / HTTP/1.1\r\n\r\nHEAD / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\nconnection: close\r\n\r\n",
+    );
+    let mut data = Vec::new();
+    let _ = stream.read_to_end(&mut data);
+
+    // file contents are followed directly by the next response on the connection
+    let head = b"HTTP/1.1 200 OK\r\ncontent-length: 900000\r\n";
+    let mut rest = &data[..];
+    for body_len in [900_000, 0, 900_000] {
+        assert!(
+            rest.starts_with(head),
+            "{}",
+            String::from_utf8_lossy(&rest[..100])
+        );
+        let head_end = rest
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .unwrap()
+            + 4;
+        let body_end = head_end + body_len;
+        assert_eq!(rest[head_end..body_end], contents[100..100 + body_len]);
+        rest = &rest[body_end..];
+    }
+    assert!(rest.is_empty());
+
+    srv.stop().await;
+    let _ = std::fs::remove_file(&path);
+}
+
+#[actix_rt::test]
+async fn h1_read_body_timeout() {
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .read_body_timeout(Duration::from_millis(200))
+            .h1(|mut req: Request| async move {
+                let mut pl = req.take_payload();
+
+                while let Some(chunk) = pl.next().await {
+                    if let Err(err) = chunk {
+                        let res = Response::bad_request().set_body(err.to_string());
+                        return Ok::<_, Infallible>(res);
+                    }
+                }
+
+                Ok(Response::ok().set_body("complete".to_owned()))
+            })
+            .tcp()
+    })
+    .await;
+
+    let start = Instant::now();
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\nabc");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+
+    assert!(data.starts_with("HTTP/1.1 400 Bad Request\r\n"), "{}", data);
+    assert!(data.contains("timed out reading request body"));
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h1_min_body_rate() {
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .min_body_rate(1000, Duration::from_millis(200))
+            .h1(|mut req: Request| async move {
+                let mut pl = req.take_payload();
+
+                while let Some(chunk) = pl.next().await {
+                    if let Err(err) = chunk {
+                        let res = Response::bad_request().set_body(err.to_string());
+                        return Ok::<_, Infallible>(res);
+                    }
+                }
+
+                Ok(Response::ok().set_body("complete".to_owned()))
+            })
+            .tcp()
+    })
+    .await;
+
+    // body sent at full speed is accepted
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"POST / HTTP/1.1\r\nContent-Length: 10\r\nConnection: close\r\n\r\n");
+    let _ = stream.write_all(b"0123456789");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"), "{}", data);
+    assert!(data.ends_with("complete"));
+
+    // body trickled at 100 bytes/sec is rejected
+    let start = Instant::now();
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"POST / HTTP/1.1\r\nContent-Length: 1000\r\n\r\n");
+
+    for _ in 0..20 {
+        if stream.write_all(b"0123456789").is_err() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+
+    assert!(data.starts_with("HTTP/1.1 400 Bad Request\r\n"), "{}", data);
+    assert!(data.contains("request body rate below minimum"));
+    assert!(start.elapsed() < Duration::from_secs(3));
+
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h1_write_timeout() {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    struct DropFlag(Arc<AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let dropped = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&dropped);
+
+    let mut srv = test_server(move || {
+        let flag = Arc::clone(&flag);
+
+        HttpService::build()
+            .write_timeout(Duration::from_millis(200))
+            .h1(move |_| {
+                // endless body that is only dropped when the dispatcher gives up
+                let guard = DropFlag(Arc::clone(&flag));
+                let body = futures_util::stream::unfold(guard, |guard| async move {
+                    Some((
+                        Ok::<_, Infallible>(Bytes::from(vec![b'x'; 64 * 1024])),
+                        guard,
+                    ))
+                });
+
+                ok::<_, Infallible>(Response::ok().set_body(BodyStream::new(body)))
+            })
+            .tcp()
+    })
+    .await;
+
+    // client sends a request but never reads the response
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\n\r\n");
+
+    let start = Instant::now();
+    while !dropped.load(Ordering::SeqCst) {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "response was not abandoned"
+        );
+        sleep(Duration::from_millis(50)).await;
+    }
+
+    drop(stream);
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h1_write_backpressure() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Clone, Default)]
+    struct Pauses(Arc<AtomicUsize>);
+
+    impl ConnectionObserver for Pauses {
+        fn write_paused(&self, _conn: &ConnectionInfo, buffered: usize) {
+            assert!(buffered >= 16 * 1024);
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let pauses = Pauses::default();
+    let produced = Arc::new(AtomicUsize::new(0));
+
+    let mut srv = test_server({
+        let pauses = pauses.clone();
+        let produced = Arc::clone(&produced);
+
+        move || {
+            let produced = Arc::clone(&produced);
+
+            HttpService::build()
+                .observer(pauses.clone())
+                .write_high_water_mark(16 * 1024)
+                .write_low_water_mark(4 * 1024)
+                .h1(move |_| {
+                    // endless body that counts the bytes taken from it
+                    let produced = Arc::clone(&produced);
+                    let body = futures_util::stream::repeat_with(move || {
+                        produced.fetch_add(1024, Ordering::SeqCst);
+                        Ok::<_, Infallible>(Bytes::from(vec![b'x'; 1024]))
+                    });
+
+                    ok::<_, Infallible>(Response::ok().set_body(BodyStream::new(body)))
+                })
+                .tcp()
+        }
+    })
+    .await;
+
+    // client sends a request but does not read the response yet
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\n\r\n");
+
+    // body is no longer polled once the socket buffers are full
+    let start = Instant::now();
+    let mut last = 0;
+    loop {
+        sleep(Duration::from_millis(200)).await;
+
+        let now = produced.load(Ordering::SeqCst);
+        if now > 0 && now == last {
+            break;
+        }
+        last = now;
+
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "body was not paused"
+        );
+    }
+    assert!(pauses.0.load(Ordering::SeqCst) > 0);
+
+    // reading the response resumes the body
+    let mut buf = vec![0; last + 64 * 1024];
+    stream.read_exact(&mut buf).unwrap();
+    assert!(buf.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    assert!(produced.load(Ordering::SeqCst) > last);
+
+    drop(stream);
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h1_head_limits_431() {
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .max_request_line_len(32)
+            .max_headers(4)
+            .max_header_bytes(256)
+            .h1(|_| ok::<_, Infallible>(Response::ok()))
+            .tcp()
+    })
+    .await;
+
+    let requests = [
+        format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(40)),
+        "GET / HTTP/1.1\r\na: 1\r\nb: 2\r\nc: 3\r\nd: 4\r\ne: 5\r\n\r\n".to_owned(),
+        format!("GET / HTTP/1.1\r\nx-big: {}\r\n\r\n", "b".repeat(300)),
+    ];
+
+    for req in requests {
+        let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+        let _ = stream.write_all(req.as_bytes());
+        let mut data = String::new();
+        let _ = stream.read_to_string(&mut data);
+        assert!(
+            data.starts_with("HTTP/1.1 431 Request Header Fields Too Large"),
+            "unexpected response: {}",
+            data
+        );
+    }
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /ok HTTP/1.1\r\nConnection: close\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+
+    srv.stop().await;
+}
+
+#[derive(Clone, Default)]
+struct EventLog(Arc<Mutex<Vec<String>>>);
+
+impl EventLog {
+    fn events(&self) -> Vec<String> {
+        self.0.lock().unwrap().clone()
+    }
+
+    fn push(&self, event: String) {
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+impl ConnectionObserver for EventLog {
+    fn connection_opened(&self, conn: &ConnectionInfo) {
+        assert!(conn.peer_addr().is_some());
+        self.push(format!("opened {:?}", conn.protocol()));
+    }
+
+    fn connection_closed(&self, _conn: &ConnectionInfo) {
+        self.push("closed".to_owned());
+    }
+
+    fn request_started(&self, _conn: &ConnectionInfo, req: &RequestHead) {
+        self.push(format!("started {}", req.uri));
+    }
+
+    fn request_finished(&self, _conn: &ConnectionInfo, status: StatusCode) {
+        self.push(format!("finished {}", status.as_u16()));
+    }
+
+    fn bytes_read(&self, _conn: &ConnectionInfo, n: usize) {
+        assert!(n > 0);
+    }
+
+    fn bytes_written(&self, _conn: &ConnectionInfo, n: usize) {
+        assert!(n > 0);
+        let mut events = self.0.lock().unwrap();
+        if events.last().map(String::as_str) != Some("written") {
+            events.push("written".to_owned());
+        }
+    }
+
+    fn protocol_error(&self, _conn: &ConnectionInfo, err: &DispatchError, data: &[u8]) {
+        self.push(format!(
+            "protocol error: {err}: {}",
+            String::from_utf8_lossy(data)
+        ));
+    }
+}
+
+#[actix_rt::test]
+async fn h1_connection_observer() {
+    let log = EventLog::default();
+
+    let mut srv = test_server({
+        let log = log.clone();
+        move || {
+            HttpService::build()
+                .observer(log.clone())
+                .h1(|_| ok::<_, Infallible>(Response::ok().set_body("hello")))
+                .tcp()
+        }
+    })
+    .await;
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ =
+        stream.write_all(b"GET /test HTTP/1.1\r\n\r\nGET /bad HTTP/1.1\r\nx-bad header\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(data.contains("HTTP/1.1 400 Bad Request\r\n"));
+
+    // connection is closed by the server after the error response has been written
+    for _ in 0..10 {
+        if log.events().last().map(String::as_str) == Some("closed") {
+            break;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+
+    let events = log.events();
+    assert_eq!(events[..2], ["opened Http1", "started /test"]);
+    assert!(events.iter().any(|ev| ev.starts_with("protocol error: ")
+        && ev.ends_with(": GET /bad HTTP/1.1\r\nx-bad header\r\n\r\n")));
+    assert!(events.contains(&"written".to_owned()));
+    assert_eq!(events.last().unwrap(), "closed");
+
+    let finished = events
+        .iter()
+        .filter(|ev| ev.starts_with("finished"))
+        .collect::<Vec<_>>();
+    assert_eq!(finished, ["finished 200", "finished 400"]);
+
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h1_upgrade_fn() {
+    use actix_http::h1;
+    use tokio::io::{AsyncBufReadExt as _, AsyncWriteExt as _, BufReader};
+
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .upgrade(h1::upgrade_fn(|req: Request| async move {
+                if req.headers().get(header::UPGRADE).unwrap() != "echo" {
+                    return Err(Response::bad_request());
+                }
+
+                let res = Response::build(StatusCode::SWITCHING_PROTOCOLS)
+                    .insert_header((header::UPGRADE, "echo"))
+                    .finish()
+                    .drop_body();
+
+                Ok((res, |io: h1::Upgraded<_>| async move {
+                    let mut io = BufReader::new(io);
+                    let mut line = String::new();
+
+                    while io.read_line(&mut line).await? > 0 {
+                        io.write_all(line.to_uppercase().as_bytes()).await?;
+                        io.flush().await?;
+                        line.clear();
+                    }
+
+                    Ok::<_, std::io::Error>(())
+                }))
+            }))
+            .finish(|_| ok::<_, Infallible>(Response::ok()))
+            .tcp()
+    })
+    .await;
+
+    // bytes sent along with the upgrade request are passed to the protocol handler
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nconnection: upgrade\r\nupgrade: echo\r\n\r\nhello\n")
+        .unwrap();
+    stream.flush().unwrap();
+    thread::sleep(Duration::from_millis(50));
+    stream.write_all(b"world\n").unwrap();
+    stream.shutdown(net::Shutdown::Write).unwrap();
+
+    let mut data = String::new();
+    stream.read_to_string(&mut data).unwrap();
+    assert!(data.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+    assert!(data.ends_with("\r\n\r\nHELLO\nWORLD\n"), "{data:?}");
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nconnection: upgrade\r\nupgrade: other\r\n\r\n")
+        .unwrap();
+
+    let mut data = String::new();
+    stream.read_to_string(&mut data).unwrap();
+    assert!(data.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h1_switch_codec() {
+    use actix_codec::{Framed, LinesCodec};
+    use actix_http::{body::BodySize, error::ParseError, h1, h1::FramedExt as _};
+    use futures_util::SinkExt as _;
+
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .upgrade(fn_service(
+                |(_req, framed): (Request, Framed<TcpStream, h1::Codec>)| async move {
+                    // switching before the upgrade response is sent fails
+                    let Err(err) = framed.switch_codec(LinesCodec::default()) else {
+                        panic!("codec switched before response");
+                    };
+                    let mut framed = err.into_inner();
+
+                    let res = Response::build(StatusCode::SWITCHING_PROTOCOLS)
+                        .upgrade("lines")
+                        .finish()
+                        .drop_body();
+                    framed.send((res, BodySize::None).into()).await?;
+
+                    let mut framed = framed.switch_codec(LinesCodec::default()).unwrap();
+
+                    while let Some(line) = framed.next().await {
+                        framed.send(line?.to_uppercase()).await?;
+                    }
+
+                    Ok::<_, ParseError>(())
+                },
+            ))
+            .finish(|_| ok::<_, Infallible>(Response::ok()))
+            .tcp()
+    })
+    .await;
+
+    // bytes sent along with the upgrade request are decoded by the new codec
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nconnection: upgrade\r\nupgrade: lines\r\n\r\nhello\n")
+        .unwrap();
+    stream.flush().unwrap();
+    thread::sleep(Duration::from_millis(50));
+    stream.write_all(b"world\n").unwrap();
+    stream.shutdown(net::Shutdown::Write).unwrap();
+
+    let mut data = String::new();
+    stream.read_to_string(&mut data).unwrap();
+    assert!(data.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+    assert!(data.ends_with("\r\n\r\nHELLO\nWORLD\n"), "{data:?}");
+
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h1_connect_fn() {
+    use actix_http::h1;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .upgrade(h1::connect_fn(|req: Request| async move {
+                if !req.headers().contains_key(header::PROXY_AUTHORIZATION) {
+                    return Err(Response::new(StatusCode::PROXY_AUTHENTICATION_REQUIRED));
+                }
+
+                let target = req.uri().authority().unwrap().to_string();
+
+                Ok(|mut io: h1::Upgraded<_>| async move {
+                    // echo the target, then the tunneled bytes
+                    io.write_all(target.as_bytes()).await?;
+
+                    let mut data = Vec::new();
+                    io.read_to_end(&mut data).await?;
+                    io.write_all(&data).await?;
+                    io.flush().await?;
+
+                    Ok::<_, std::io::Error>(())
+                })
+            }))
+            .finish(|_| ok::<_, Infallible>(Response::ok()))
+            .tcp()
+    })
+    .await;
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    stream
+        .write_all(
+            b"CONNECT example.com:443 HTTP/1.1\r\nhost: example.com:443\r\n\
+            proxy-authorization: Basic dXNlcjpwYXNz\r\n\r\nping",
+        )
+        .unwrap();
+    stream.shutdown(net::Shutdown::Write).unwrap();
+
+    let mut data = String::new();
+    stream.read_to_string(&mut data).unwrap();
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"), "{data:?}");
+    assert!(!data.contains("content-length"), "{data:?}");
+    assert!(data.ends_with("\r\n\r\nexample.com:443ping"), "{data:?}");
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    stream
+        .write_all(b"CONNECT example.com:443 HTTP/1.1\r\nhost: example.com:443\r\n\r\n")
+        .unwrap();
+
+    let mut data = String::new();
+    stream.read_to_string(&mut data).unwrap();
+    assert!(data.starts_with("HTTP/1.1 407 Proxy Authentication Required\r\n"));
+
+    // targets must be in authority form
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    stream
+        .write_all(b"CONNECT /path HTTP/1.1\r\nhost: example.com\r\n\r\n")
+        .unwrap();
+
+    let mut data = String::new();
+    stream.read_to_string(&mut data).unwrap();
+    assert!(data.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+    // other upgrades are not handled by the tunnel service
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nconnection: upgrade\r\nupgrade: echo\r\n\r\n")
+        .unwrap();
+
+    let mut data = String::new();
+    stream.read_to_string(&mut data).unwrap();
+    assert!(data.starts_with("HTTP/1.1 501 Not Implemented\r\n"));
+
+    srv.stop().await;
+}
+
+#[actix_rt::test]
+async fn h1_client_disconnect() {
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    let mut srv = test_server(move || {
+        let tx = tx.clone();
+
+        HttpService::build()
+            .h1(move |req: Request| {
+                let disconnect = req.extensions().get::<ClientDisconnect>().cloned().unwrap();
+                let tx = tx.clone();
+
+                async move {
+                    disconnect.await;
+                    tx.send(()).unwrap();
+                    Ok::<_, Infallible>(Response::ok())
+                }
+            })
+            .tcp()
+    })
+    .await;
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    thread::sleep(Duration::from_millis(50));
+    drop(stream);
+
+    rx.recv_timeout(Duration::from_secs(5)).unwrap();
+
+    srv.stop().await;
+}
@@ -1,7 +1,9 @@
 use std::{
     cell::Cell,
     convert::Infallible,
+    pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
 
 use actix_codec::{AsyncRead, AsyncWrite, Framed};
@@ -9,10 +11,10 @@ use actix_http::{
     body::{BodySize, BoxBody},
     h1,
     ws::{self, CloseCode, Frame, Item, Message},
-    Error, HttpService, Request, Response,
+    Error, GracefulShutdown, HttpService, Request, Response,
 };
 use actix_http_test::test_server;
-use actix_service::{fn_factory, Service};
+use actix_service::{fn_factory, fn_service, Service};
 use bytes::Bytes;
 use derive_more::derive::{Display, Error, From};
 use futures_core::future::LocalBoxFuture;
@@ -193,3 +195,171 @@ async fn simple() {
     let item = framed.next().await.unwrap().unwrap();
     assert_eq!(item, Frame::Close(Some(CloseCode::Normal.into())));
 }
+
+#[actix_rt::test]
+async fn quota_close() {
+    async fn upgrade<T>(
+        (req, mut framed): (Request, Framed<T, h1::Codec>),
+    ) -> Result<(), WsServiceError>
+    where
+        T: AsyncRead + AsyncWrite + Unpin,
+    {
+        let res = ws::handshake(req.head())?.message_body(())?;
+        framed.send((res, BodySize::None).into()).await?;
+
+        let quota = ws::Quota::new(Duration::from_secs(60)).max_messages(1);
+        let framed = framed.replace_codec(ws::Codec::new().quota(quota));
+
+        let _ = ws::Dispatcher::with(framed, service).await;
+
+        Ok(())
+    }
+
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .upgrade(fn_service(upgrade))
+            .finish(|_| async { Ok::<_, Infallible>(Response::not_found()) })
+            .tcp()
+    })
+    .await;
+
+    let mut framed = srv.ws().await.unwrap();
+
+    framed.send(Message::Text("first".into())).await.unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, Frame::Text(Bytes::from_static(b"first")));
+
+    framed.send(Message::Text("second".into())).await.unwrap();
+    match framed.next().await.unwrap().unwrap() {
+        Frame::Close(Some(reason)) => assert_eq!(reason.code, CloseCode::Policy),
+        item => panic!("unexpected frame: {item:?}"),
+    }
+}
+
+#[actix_rt::test]
+async fn graceful_shutdown_close() {
+    async fn upgrade<T>(
+        (req, mut framed): (Request, Framed<T, h1::Codec>),
+    ) -> Result<(), WsServiceError>
+    where
+        T: AsyncRead + AsyncWrite + Unpin,
+    {
+        let res = ws::handshake(req.head())?.message_body(())?;
+        framed.send((res, BodySize::None).into()).await?;
+
+        let shutdown = req.conn_data::<GracefulShutdown>().cloned().unwrap();
+        let framed = framed.replace_codec(ws::Codec::new());
+
+        ws::Dispatcher::with(framed, service)
+            .close_on_shutdown(&shutdown, Duration::from_secs(1))
+            .await
+            .map_err(|_| WsServiceError::Dispatcher)?;
+
+        Ok(())
+    }
+
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+
+    let mut srv = test_server(move || {
+        let mut shutdown_rx = shutdown_rx.clone();
+
+        HttpService::build()
+            .shutdown_signal(async move {
+                let _ = shutdown_rx.wait_for(|started| *started).await;
+            })
+            .upgrade(fn_service(upgrade))
+            .finish(|_| async { Ok::<_, Infallible>(Response::not_found()) })
+            .tcp()
+    })
+    .await;
+
+    let mut framed = srv.ws().await.unwrap();
+
+    framed.send(Message::Text("text".into())).await.unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, Frame::Text(Bytes::from_static(b"text")));
+
+    shutdown_tx.send_replace(true);
+
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, Frame::Close(Some(CloseCode::Away.into())));
+
+    // server ends the session once it receives the reply
+    framed
+        .send(Message::Close(Some(CloseCode::Normal.into())))
+        .await
+        .unwrap();
+    assert!(matches!(framed.next().await, None | Some(Err(_))));
+}
+
+#[actix_rt::test]
+async fn raw_stream() {
+    use std::future::poll_fn;
+
+    use tokio::io::ReadBuf;
+
+    async fn upgrade<T>(
+        (req, mut framed): (Request, Framed<T, h1::Codec>),
+    ) -> Result<(), WsServiceError>
+    where
+        T: AsyncRead + AsyncWrite + Unpin,
+    {
+        let res = ws::handshake_with_protocols(req.head(), &["mqtt"])?.message_body(())?;
+        framed.send((res, BodySize::None).into()).await?;
+
+        let mut stream = ws::RawStream::new(framed.replace_codec(ws::Codec::new()));
+        let mut data = Vec::new();
+
+        // read the data of all messages until the peer closes the session
+        loop {
+            let mut buf = [0; 4];
+            let mut buf = ReadBuf::new(&mut buf);
+            poll_fn(|cx| Pin::new(&mut stream).poll_read(cx, &mut buf)).await?;
+
+            if buf.filled().is_empty() {
+                break;
+            }
+
+            data.extend_from_slice(buf.filled());
+        }
+
+        assert_eq!(data, b"abcdefgh");
+        assert_eq!(stream.close_reason(), Some(&CloseCode::Normal.into()));
+        assert!(poll_fn(|cx| Pin::new(&mut stream).poll_write(cx, &data))
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    let mut srv = test_server(|| {
+        HttpService::build()
+            .upgrade(fn_service(upgrade))
+            .finish(|_| async { Ok::<_, Infallible>(Response::not_found()) })
+            .tcp()
+    })
+    .await;
+
+    let mut framed = srv.ws().await.unwrap();
+
+    framed.send(Message::Binary("ab".into())).await.unwrap();
+    framed
+        .send(Message::Continuation(Item::FirstText("cdefg".into())))
+        .await
+        .unwrap();
+    framed.send(Message::Ping("ping".into())).await.unwrap();
+    framed
+        .send(Message::Continuation(Item::Last("h".into())))
+        .await
+        .unwrap();
+
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, Frame::Pong(Bytes::from_static(b"ping")));
+
+    framed
+        .send(Message::Close(Some(CloseCode::Normal.into())))
+        .await
+        .unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, Frame::Close(Some(CloseCode::Normal.into())));
+}
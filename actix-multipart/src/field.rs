@@ -45,6 +45,9 @@ pub struct Field {
     // INVARIANT: always non-empty when request content-type is multipart/form-data.
     pub(crate) form_field_name: String,
 
+    /// Charset named by the form's `_charset_` field, used when the field does not declare one.
+    pub(crate) default_charset: Option<String>,
+
     /// Field's header map.
     headers: HeaderMap,
 
@@ -65,6 +68,7 @@ impl Field {
             content_type,
             content_disposition,
             form_field_name: form_field_name.unwrap_or_default(),
+            default_charset: None,
             headers,
             inner,
             safety,
@@ -85,6 +89,21 @@ impl Field {
         self.content_type.as_ref()
     }
 
+    /// Returns the name of the character encoding of the field's text, if it is known.
+    ///
+    /// This is the `charset` parameter of the field's content type. When it is absent and the field
+    /// is read by the [`MultipartForm`](crate::form::MultipartForm) extractor, this is the charset
+    /// named by the form's `_charset_` field, if it has one, as described in
+    /// [RFC 7578 §4.6](https://www.rfc-editor.org/rfc/rfc7578#section-4.6). Otherwise, text
+    /// fields should be assumed to be UTF-8.
+    pub fn charset(&self) -> Option<&str> {
+        self.content_type
+            .as_ref()
+            .and_then(|mime| mime.get_param(mime::CHARSET))
+            .map(|charset| charset.as_str())
+            .or(self.default_charset.as_deref())
+    }
+
     /// Returns this field's parsed Content-Disposition header, if set.
     ///
     /// # Validation
@@ -12,7 +12,7 @@ use derive_more::derive::{Deref, DerefMut};
 use futures_core::future::LocalBoxFuture;
 use futures_util::{TryFutureExt as _, TryStreamExt as _};
 
-use crate::{Field, Multipart, MultipartError};
+use crate::{form::bytes::Bytes, Field, Multipart, MultipartError};
 
 pub mod bytes;
 pub mod json;
@@ -283,6 +283,11 @@ impl Limits {
 /// `multipart/related`, or non-multipart media types.
 ///
 /// Add a [`MultipartFormConfig`] to your app data to configure extraction.
+///
+/// A `_charset_` field sets the charset of the text fields that follow it and do not declare their
+/// own, as described in [RFC 7578 §4.6]. It is consumed by the extractor and is not passed to `T`.
+///
+/// [RFC 7578 §4.6]: https://www.rfc-editor.org/rfc/rfc7578#section-4.6
 #[derive(Deref, DerefMut)]
 pub struct MultipartForm<T: MultipartCollect>(pub T);
 
@@ -328,12 +333,25 @@ where
                 // ensure limits are shared for all fields with this name
                 let mut field_limits = HashMap::<String, Option<usize>>::new();
 
-                while let Some(field) = multipart.try_next().await? {
+                // charset of text fields that do not declare one, named by a `_charset_` field
+                let mut default_charset = None;
+
+                while let Some(mut field) = multipart.try_next().await? {
                     debug_assert!(
                         !field.form_field_name.is_empty(),
                         "multipart form fields should have names",
                     );
 
+                    if field.form_field_name == CHARSET_FIELD_NAME {
+                        limits.field_limit_remaining = None;
+                        let charset = Bytes::read_field(&req, field, &mut limits).await?;
+                        default_charset =
+                            Some(String::from_utf8_lossy(&charset.data).trim().to_owned());
+                        continue;
+                    }
+
+                    field.default_charset.clone_from(&default_charset);
+
                     // Retrieve the limit for this field
                     let entry = field_limits
                         .entry(field.form_field_name.clone())
@@ -361,6 +379,11 @@ where
     }
 }
 
+/// Name of the field that sets the default charset of a form's text fields.
+///
+/// See <https://www.rfc-editor.org/rfc/rfc7578#section-4.6>.
+const CHARSET_FIELD_NAME: &str = "_charset_";
+
 type MultipartFormErrorHandler =
     Option<Arc<dyn Fn(MultipartError, &HttpRequest) -> Error + Send + Sync>>;
 
@@ -1,12 +1,23 @@
 //! Writes a field to a temporary file on disk.
 
 use std::{
-    io,
+    cell::{Cell, RefCell},
+    fmt, fs,
+    io::{self, Read, Seek, SeekFrom},
     path::{Path, PathBuf},
-    sync::Arc,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
-use actix_web::{http::StatusCode, web, Error, HttpRequest, ResponseError};
+use actix_utils::future::{ready, Ready};
+use actix_web::{
+    dev, error, http::StatusCode, middleware::CompletionHooks, web, Error, FromRequest,
+    HttpMessage as _, HttpRequest, ResponseError,
+};
 use derive_more::derive::{Display, Error};
 use futures_core::future::LocalBoxFuture;
 use futures_util::TryStreamExt as _;
@@ -21,9 +32,14 @@ use crate::{
 };
 
 /// Write the field to a temporary file on disk.
+///
+/// When a [cipher](TempFileConfig::cipher) is configured, the file is encrypted and its contents
+/// must be read through [`reader`](Self::reader).
 #[derive(Debug)]
 pub struct TempFile {
     /// The temporary file on disk.
+    ///
+    /// Encrypted when a [cipher](TempFileConfig::cipher) is configured.
     pub file: NamedTempFile,
 
     /// The value of the `content-type` header.
@@ -34,6 +50,28 @@ pub struct TempFile {
 
     /// The size in bytes of the file.
     pub size: usize,
+
+    /// Keystream that the file is encrypted with, if any.
+    keystream: Option<Keystream>,
+}
+
+impl TempFile {
+    /// Returns a reader of the decrypted contents of the file, starting at its beginning.
+    ///
+    /// Reads the contents of the file as is when no [cipher](TempFileConfig::cipher) is
+    /// configured.
+    pub fn reader(&self) -> io::Result<TempFileReader> {
+        Ok(TempFileReader {
+            file: self.file.reopen()?,
+            keystream: self.keystream.clone(),
+            pos: 0,
+        })
+    }
+
+    /// Returns true if the file is encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        self.keystream.is_some()
+    }
 }
 
 impl<'t> FieldReader<'t> for TempFile {
@@ -42,9 +80,13 @@ impl<'t> FieldReader<'t> for TempFile {
     fn read_field(req: &'t HttpRequest, mut field: Field, limits: &'t mut Limits) -> Self::Future {
         Box::pin(async move {
             let config = TempFileConfig::from_req(req);
+            let keystream = config
+                .cipher
+                .as_ref()
+                .map(|cipher| Keystream(Arc::from(cipher.keystream())));
             let mut size = 0;
 
-            let file = config.create_tempfile().map_err(|err| {
+            let file = config.create_tempfile(req).map_err(|err| {
                 config.map_error(req, &field.form_field_name, TempFileError::FileIo(err))
             })?;
 
@@ -54,10 +96,31 @@ impl<'t> FieldReader<'t> for TempFile {
 
             while let Some(chunk) = field.try_next().await? {
                 limits.try_consume_limits(chunk.len(), false)?;
-                size += chunk.len();
-                file_async.write_all(chunk.as_ref()).await.map_err(|err| {
+
+                if let Some(ref store) = config.store {
+                    if !store.reserve(req, chunk.len()) {
+                        return Err(config.map_error(
+                            req,
+                            &field.form_field_name,
+                            TempFileError::QuotaExceeded,
+                        ));
+                    }
+                }
+
+                let res = match keystream {
+                    Some(ref keystream) => {
+                        let mut buf = chunk.to_vec();
+                        keystream.0.apply(size as u64, &mut buf);
+                        file_async.write_all(&buf).await
+                    }
+                    None => file_async.write_all(chunk.as_ref()).await,
+                };
+
+                res.map_err(|err| {
                     config.map_error(req, &field.form_field_name, TempFileError::FileIo(err))
                 })?;
+
+                size += chunk.len();
             }
 
             file_async.flush().await.map_err(|err| {
@@ -73,6 +136,7 @@ impl<'t> FieldReader<'t> for TempFile {
                     .get_filename()
                     .map(ToOwned::to_owned),
                 size,
+                keystream,
             })
         })
     }
@@ -84,11 +148,183 @@ pub enum TempFileError {
     /// File I/O Error
     #[display("File I/O error: {}", _0)]
     FileIo(std::io::Error),
+
+    /// The [`TempFileStore`] quota would be exceeded by this field.
+    #[display("Temporary file storage quota exceeded")]
+    QuotaExceeded,
 }
 
 impl ResponseError for TempFileError {
     fn status_code(&self) -> StatusCode {
-        StatusCode::INTERNAL_SERVER_ERROR
+        match self {
+            TempFileError::FileIo(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            TempFileError::QuotaExceeded => StatusCode::INSUFFICIENT_STORAGE,
+        }
+    }
+}
+
+/// Reader of the decrypted contents of a [`TempFile`].
+///
+/// Created by [`TempFile::reader()`].
+#[derive(Debug)]
+pub struct TempFileReader {
+    file: fs::File,
+    keystream: Option<Keystream>,
+    pos: u64,
+}
+
+impl Read for TempFileReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.file.read(buf)?;
+
+        if let Some(ref keystream) = self.keystream {
+            keystream.0.apply(self.pos, &mut buf[..n]);
+        }
+
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for TempFileReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = self.file.seek(pos)?;
+        Ok(self.pos)
+    }
+}
+
+/// Cipher that [`TempFile`] fields are encrypted with before they are written to disk.
+///
+/// Each file is encrypted with a keystream of its own, which is kept in memory alongside the
+/// `TempFile` of the field and never written to disk. Keystreams are applied to the contents of
+/// files at arbitrary offsets, which lets readers seek within them; stream ciphers and block
+/// ciphers in counter mode can be used.
+///
+/// With the `encryption` crate feature, [`ChaCha20Cipher`] implements this trait.
+///
+/// # Examples
+/// ```
+/// use actix_multipart::form::tempfile::{
+///     TempFileCipher, TempFileConfig, TempFileKeystream,
+/// };
+/// use actix_web::App;
+///
+/// /// Keystream of a hardware security module.
+/// struct HsmKeystream {
+///     // ...
+/// }
+///
+/// impl TempFileKeystream for HsmKeystream {
+///     fn apply(&self, offset: u64, buf: &mut [u8]) {
+///         // ...
+///     }
+/// }
+///
+/// struct HsmCipher;
+///
+/// impl TempFileCipher for HsmCipher {
+///     fn keystream(&self) -> Box<dyn TempFileKeystream> {
+///         Box::new(HsmKeystream {})
+///     }
+/// }
+///
+/// let app = App::new().app_data(TempFileConfig::default().cipher(HsmCipher));
+/// ```
+pub trait TempFileCipher: Send + Sync {
+    /// Returns the keystream of a new file.
+    ///
+    /// Keystreams must be unique; the same keystream must never be used for two files.
+    fn keystream(&self) -> Box<dyn TempFileKeystream>;
+}
+
+/// Keystream that a [`TempFile`] is encrypted with.
+///
+/// See [`TempFileCipher`].
+pub trait TempFileKeystream: Send + Sync {
+    /// Encrypts or decrypts `buf`, which is at `offset` in the file, in place.
+    fn apply(&self, offset: u64, buf: &mut [u8]);
+}
+
+/// Keystream of an encrypted file.
+#[derive(Clone)]
+struct Keystream(Arc<dyn TempFileKeystream>);
+
+impl fmt::Debug for Keystream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Keystream")
+    }
+}
+
+/// [`TempFileCipher`] using ChaCha20, as described by [RFC 8439].
+///
+/// Each file is encrypted with a random nonce. Files are limited to 256 GiB.
+///
+/// # Examples
+/// ```
+/// use actix_multipart::form::tempfile::{ChaCha20Cipher, TempFileConfig};
+/// use actix_web::App;
+///
+/// # let key_from_kms = || [0; 32];
+/// let cipher = ChaCha20Cipher::new(key_from_kms());
+/// let app = App::new().app_data(TempFileConfig::default().cipher(cipher));
+/// ```
+///
+/// [RFC 8439]: https://www.rfc-editor.org/rfc/rfc8439
+#[cfg(feature = "encryption")]
+#[derive(Clone)]
+pub struct ChaCha20Cipher {
+    key: [u8; 32],
+}
+
+#[cfg(feature = "encryption")]
+impl ChaCha20Cipher {
+    /// Constructs a cipher with a 256-bit key.
+    pub fn new(key: [u8; 32]) -> Self {
+        Self { key }
+    }
+
+    /// Constructs a cipher with a random 256-bit key.
+    ///
+    /// Files can only be decrypted by the process that wrote them.
+    pub fn random() -> Self {
+        Self::new(rand::random())
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl fmt::Debug for ChaCha20Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChaCha20Cipher").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl TempFileCipher for ChaCha20Cipher {
+    fn keystream(&self) -> Box<dyn TempFileKeystream> {
+        Box::new(ChaCha20Keystream {
+            key: self.key,
+            nonce: rand::random(),
+        })
+    }
+}
+
+#[cfg(feature = "encryption")]
+struct ChaCha20Keystream {
+    key: [u8; 32],
+    nonce: [u8; 12],
+}
+
+#[cfg(feature = "encryption")]
+impl TempFileKeystream for ChaCha20Keystream {
+    fn apply(&self, offset: u64, buf: &mut [u8]) {
+        use chacha20::{
+            cipher::{KeyIvInit as _, StreamCipher as _, StreamCipherSeek as _},
+            ChaCha20,
+        };
+
+        let mut cipher = ChaCha20::new(&self.key.into(), &self.nonce.into());
+        cipher.seek(offset);
+        cipher.apply_keystream(buf);
     }
 }
 
@@ -97,11 +333,15 @@ impl ResponseError for TempFileError {
 pub struct TempFileConfig {
     err_handler: FieldErrorHandler<TempFileError>,
     directory: Option<PathBuf>,
+    store: Option<TempFileStore>,
+    cipher: Option<Arc<dyn TempFileCipher>>,
 }
 
 impl TempFileConfig {
-    fn create_tempfile(&self) -> io::Result<NamedTempFile> {
-        if let Some(ref dir) = self.directory {
+    fn create_tempfile(&self, req: &HttpRequest) -> io::Result<NamedTempFile> {
+        if let Some(ref store) = self.store {
+            store.create(req)
+        } else if let Some(ref dir) = self.directory {
             NamedTempFile::new_in(dir)
         } else {
             NamedTempFile::new()
@@ -142,16 +382,38 @@ impl TempFileConfig {
 
     /// Sets the directory that temp files will be created in.
     ///
-    /// The default temporary file location is platform dependent.
+    /// The default temporary file location is platform dependent. Ignored when a
+    /// [store](Self::store) is set.
     pub fn directory(mut self, dir: impl AsRef<Path>) -> Self {
         self.directory = Some(dir.as_ref().to_owned());
         self
     }
+
+    /// Sets the store that temp files will be created in.
+    ///
+    /// The store is also made available to handlers through its extractor.
+    pub fn store(mut self, store: TempFileStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Sets the cipher that temp files are encrypted with before they are written to disk.
+    ///
+    /// Encrypted files are read through [`TempFile::reader()`]. Files are still removed once
+    /// their `TempFile` is dropped, or by a [store](Self::store).
+    ///
+    /// By default, files are not encrypted.
+    pub fn cipher(mut self, cipher: impl TempFileCipher + 'static) -> Self {
+        self.cipher = Some(Arc::new(cipher));
+        self
+    }
 }
 
 const DEFAULT_CONFIG: TempFileConfig = TempFileConfig {
     err_handler: None,
     directory: None,
+    store: None,
+    cipher: None,
 };
 
 impl Default for TempFileConfig {
@@ -160,13 +422,308 @@ impl Default for TempFileConfig {
     }
 }
 
+/// File name prefix used to recognize files created by a [`TempFileStore`].
+const STORE_FILE_PREFIX: &str = ".actix-upload-";
+
+/// Managed directory for temporary upload files.
+///
+/// Files created through a store are:
+/// - removed once the response to the request that created them has completed, unless they have
+///   been persisted to another path. When the [`OnResponseComplete`] middleware is installed this
+///   happens after the response body has been sent; otherwise, when the request is dropped;
+/// - counted against an optional quota of bytes written by in-flight requests. Multipart fields
+///   that would exceed the quota fail with [`TempFileError::QuotaExceeded`];
+/// - removed by [`sweep`](Self::sweep) if they have not been modified for longer than the
+///   configured maximum age. This also cleans up files left behind by a crashed process, so it is
+///   a good idea to call it on startup. Sweeping also happens automatically, at most once per
+///   maximum age, as new files are created.
+///
+/// Register the store on a [`TempFileConfig`] to use it for [`TempFile`] fields. Handlers can
+/// then extract it to create their own request-scoped temp files.
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use actix_multipart::form::tempfile::{TempFileConfig, TempFileStore};
+/// use actix_web::App;
+///
+/// let store = TempFileStore::new("/var/tmp/uploads")
+///     .quota(1024 * 1024 * 1024)
+///     .max_age(Duration::from_secs(60 * 60));
+///
+/// // remove files orphaned by a previous run
+/// store.sweep().unwrap();
+///
+/// let app = App::new().app_data(TempFileConfig::default().store(store));
+/// ```
+///
+/// [`OnResponseComplete`]: actix_web::middleware::OnResponseComplete
+#[derive(Debug, Clone)]
+pub struct TempFileStore {
+    directory: PathBuf,
+    quota: Option<u64>,
+    max_age: Option<Duration>,
+    state: Arc<StoreState>,
+}
+
+#[derive(Debug, Default)]
+struct StoreState {
+    /// Bytes reserved by in-flight requests.
+    used: AtomicU64,
+
+    /// Time of the last automatic sweep.
+    last_sweep: Mutex<Option<Instant>>,
+}
+
+impl TempFileStore {
+    /// Constructs a store that creates files in `directory`.
+    ///
+    /// The directory is created, if needed, when the first file is created.
+    pub fn new(directory: impl AsRef<Path>) -> Self {
+        Self {
+            directory: directory.as_ref().to_owned(),
+            quota: None,
+            max_age: None,
+            state: Arc::new(StoreState::default()),
+        }
+    }
+
+    /// Sets the maximum number of bytes that in-flight requests may write to the store.
+    ///
+    /// By default, there is no quota.
+    pub fn quota(mut self, bytes: u64) -> Self {
+        self.quota = Some(bytes);
+        self
+    }
+
+    /// Sets the age after which unmodified files are removed by [`sweep`](Self::sweep).
+    ///
+    /// By default, files are never swept.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Returns the directory that files are created in.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Returns the number of bytes currently reserved by in-flight requests.
+    pub fn used(&self) -> u64 {
+        self.state.used.load(Ordering::Acquire)
+    }
+
+    /// Creates a new temp file that is removed once the response to `req` has completed.
+    ///
+    /// Persist the file to another path to keep it. Bytes written to files created by this method
+    /// are not counted against the quota.
+    pub fn create(&self, req: &HttpRequest) -> io::Result<NamedTempFile> {
+        self.maybe_sweep();
+
+        fs::create_dir_all(&self.directory)?;
+
+        let file = tempfile::Builder::new()
+            .prefix(STORE_FILE_PREFIX)
+            .tempfile_in(&self.directory)?;
+
+        self.request_uploads(req)
+            .paths
+            .borrow_mut()
+            .push(file.path().to_owned());
+
+        Ok(file)
+    }
+
+    /// Removes files in the store's directory that have not been modified for longer than the
+    /// configured maximum age, returning the number of files removed.
+    ///
+    /// Only files created by a store are considered. Does nothing if no maximum age is set.
+    pub fn sweep(&self) -> io::Result<usize> {
+        let Some(max_age) = self.max_age else {
+            return Ok(0);
+        };
+
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err),
+        };
+
+        let mut removed = 0;
+
+        for entry in entries {
+            let entry = entry?;
+
+            if !entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(STORE_FILE_PREFIX)
+            {
+                continue;
+            }
+
+            let meta = entry.metadata()?;
+            let stale = meta.is_file()
+                && meta
+                    .modified()?
+                    .elapsed()
+                    .is_ok_and(|modified_ago| modified_ago >= max_age);
+
+            if stale && fs::remove_file(entry.path()).is_ok() {
+                removed += 1;
+            }
+        }
+
+        if removed > 0 {
+            log::debug!(
+                "Removed {removed} stale temp file(s) from {}",
+                self.directory.display()
+            );
+        }
+
+        Ok(removed)
+    }
+
+    /// Sweeps the directory in the background if a sweep has not happened within the maximum age.
+    fn maybe_sweep(&self) {
+        let Some(max_age) = self.max_age else {
+            return;
+        };
+
+        {
+            let mut last_sweep = self.state.last_sweep.lock().unwrap();
+
+            if last_sweep.is_some_and(|last| last.elapsed() < max_age) {
+                return;
+            }
+
+            *last_sweep = Some(Instant::now());
+        }
+
+        let store = self.clone();
+        actix_web::rt::task::spawn_blocking(move || {
+            if let Err(err) = store.sweep() {
+                log::warn!("Failed to sweep temp files: {err}");
+            }
+        });
+    }
+
+    /// Reserves `bytes` of the quota for `req`, returning false if the quota would be exceeded.
+    fn reserve(&self, req: &HttpRequest, bytes: usize) -> bool {
+        let bytes = bytes as u64;
+
+        if let Some(quota) = self.quota {
+            let res = self
+                .state
+                .used
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                    used.checked_add(bytes).filter(|&total| total <= quota)
+                });
+
+            if res.is_err() {
+                return false;
+            }
+        } else {
+            self.state.used.fetch_add(bytes, Ordering::AcqRel);
+        }
+
+        let uploads = self.request_uploads(req);
+        uploads.reserved.set(uploads.reserved.get() + bytes);
+
+        true
+    }
+
+    /// Returns the upload tracker for `req`, creating it if necessary.
+    fn request_uploads(&self, req: &HttpRequest) -> Rc<RequestUploads> {
+        if let Some(uploads) = req.extensions().get::<Rc<RequestUploads>>() {
+            return Rc::clone(uploads);
+        }
+
+        let uploads = Rc::new(RequestUploads {
+            store: self.clone(),
+            paths: RefCell::new(Vec::new()),
+            reserved: Cell::new(0),
+        });
+
+        // keep uploads alive until the response has been sent, when possible
+        if let Some(hooks) = req.extensions().get::<CompletionHooks>() {
+            let uploads = Rc::clone(&uploads);
+            hooks.on_response_complete(move |_| drop(uploads));
+        }
+
+        req.extensions_mut().insert(Rc::clone(&uploads));
+
+        uploads
+    }
+}
+
+/// Files and quota reserved by a single request. Cleaned up when dropped.
+struct RequestUploads {
+    store: TempFileStore,
+    paths: RefCell<Vec<PathBuf>>,
+    reserved: Cell<u64>,
+}
+
+impl Drop for RequestUploads {
+    fn drop(&mut self) {
+        for path in self.paths.get_mut().drain(..) {
+            match fs::remove_file(&path) {
+                Ok(()) => {}
+                // already removed or persisted elsewhere
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => log::warn!("Failed to remove temp file {}: {err}", path.display()),
+            }
+        }
+
+        self.store
+            .state
+            .used
+            .fetch_sub(self.reserved.get(), Ordering::AcqRel);
+    }
+}
+
+/// Extracts the store registered on the [`TempFileConfig`].
+///
+/// Extraction fails with a `500 Internal Server Error` if no store is registered.
+impl FromRequest for TempFileStore {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut dev::Payload) -> Self::Future {
+        match TempFileConfig::from_req(req).store {
+            Some(ref store) => ready(Ok(store.clone())),
+            None => {
+                log::debug!(
+                    "Failed to extract `TempFileStore` for `{}` handler. Register one using \
+                    `TempFileConfig::store()` in app data to use this extractor.",
+                    req.match_name().unwrap_or_else(|| req.path())
+                );
+
+                ready(Err(error::ErrorInternalServerError(
+                    "Temp file store is not configured.",
+                )))
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::io::{Cursor, Read};
+    use std::{
+        io::{Cursor, Read, Seek as _, SeekFrom},
+        path::PathBuf,
+        sync::Mutex,
+        time::Duration,
+    };
 
     use actix_multipart_rfc7578::client::multipart;
     use actix_web::{http::StatusCode, web, App, HttpResponse, Responder};
 
+    use super::{
+        TempFileCipher, TempFileConfig, TempFileKeystream, TempFileStore, STORE_FILE_PREFIX,
+    };
     use crate::form::{tempfile::TempFile, tests::send_form, MultipartForm};
 
     #[derive(MultipartForm)]
@@ -194,4 +751,175 @@ mod tests {
         let response = send_form(&srv, form, "/").await;
         assert_eq!(response.status(), StatusCode::OK);
     }
+
+    async fn leak_file_route(
+        form: MultipartForm<FileForm>,
+        leaked: web::Data<Mutex<Option<PathBuf>>>,
+    ) -> impl Responder {
+        // keep the file on disk at its temporary path; the store should still remove it
+        let (_file, path) = form.into_inner().file.file.keep().unwrap();
+        *leaked.lock().unwrap() = Some(path);
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_store_cleanup() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = TempFileStore::new(dir.path());
+        let leaked = web::Data::new(Mutex::new(None::<PathBuf>));
+
+        let srv = actix_test::start({
+            let store = store.clone();
+            let leaked = leaked.clone();
+
+            move || {
+                App::new()
+                    .app_data(TempFileConfig::default().store(store.clone()))
+                    .app_data(leaked.clone())
+                    .route("/", web::post().to(leak_file_route))
+            }
+        });
+
+        let mut form = multipart::Form::default();
+        form.add_reader_file("file", Cursor::new("Hello, world!"), "testfile.txt");
+        let response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let path = leaked.lock().unwrap().take().unwrap();
+        assert!(path.starts_with(dir.path()));
+
+        let mut tries = 0;
+        while path.exists() || store.used() != 0 {
+            tries += 1;
+            assert!(tries < 20, "temp file was not cleaned up");
+            actix_rt::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_store_quota() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = TempFileStore::new(dir.path()).quota(8);
+
+        let srv = actix_test::start(move || {
+            App::new()
+                .app_data(TempFileConfig::default().store(store.clone()))
+                .route("/", web::post().to(test_file_route))
+        });
+
+        let mut form = multipart::Form::default();
+        form.add_reader_file_with_mime(
+            "file",
+            Cursor::new("Hello, world!"),
+            "testfile.txt",
+            mime::TEXT_PLAIN,
+        );
+        let response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::INSUFFICIENT_STORAGE);
+    }
+
+    #[test]
+    fn test_store_sweep() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let stale = dir.path().join(format!("{STORE_FILE_PREFIX}stale"));
+        let other = dir.path().join("other");
+        std::fs::write(&stale, "stale").unwrap();
+        std::fs::write(&other, "other").unwrap();
+
+        // no max age; nothing is swept
+        assert_eq!(TempFileStore::new(dir.path()).sweep().unwrap(), 0);
+
+        let store = TempFileStore::new(dir.path()).max_age(Duration::ZERO);
+        assert_eq!(store.sweep().unwrap(), 1);
+        assert!(!stale.exists());
+        assert!(other.exists());
+
+        // missing directories are treated as empty
+        let store = TempFileStore::new(dir.path().join("missing")).max_age(Duration::ZERO);
+        assert_eq!(store.sweep().unwrap(), 0);
+    }
+
+    /// Cipher that adds the offset of each byte to it, for checking that offsets are applied.
+    struct OffsetCipher;
+
+    struct OffsetKeystream;
+
+    impl TempFileKeystream for OffsetKeystream {
+        fn apply(&self, offset: u64, buf: &mut [u8]) {
+            for (i, byte) in buf.iter_mut().enumerate() {
+                *byte ^= (offset + i as u64) as u8 | 0x80;
+            }
+        }
+    }
+
+    impl TempFileCipher for OffsetCipher {
+        fn keystream(&self) -> Box<dyn TempFileKeystream> {
+            Box::new(OffsetKeystream)
+        }
+    }
+
+    async fn encrypted_file_route(form: MultipartForm<FileForm>) -> impl Responder {
+        let mut form = form.into_inner();
+        assert!(form.file.is_encrypted());
+
+        let mut raw = Vec::new();
+        form.file.file.read_to_end(&mut raw).unwrap();
+        assert_eq!(raw.len(), 13);
+        assert_ne!(raw, b"Hello, world!");
+
+        let mut reader = form.file.reader().unwrap();
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "Hello, world!");
+
+        reader.seek(SeekFrom::Start(7)).unwrap();
+        contents.clear();
+        reader.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "world!");
+
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_encrypted_upload() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .app_data(TempFileConfig::default().cipher(OffsetCipher))
+                .route("/", web::post().to(encrypted_file_route))
+        });
+
+        let mut form = multipart::Form::default();
+        form.add_reader_file("file", Cursor::new("Hello, world!"), "testfile.txt");
+        let response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_chacha20_keystream() {
+        use super::ChaCha20Cipher;
+
+        let cipher = ChaCha20Cipher::random();
+        let keystream = cipher.keystream();
+
+        let mut whole = *b"Hello, world!";
+        keystream.apply(0, &mut whole);
+        assert_ne!(&whole, b"Hello, world!");
+
+        // keystreams can be applied in parts, at any offset
+        let mut parts = *b"Hello, world!";
+        let (head, tail) = parts.split_at_mut(5);
+        keystream.apply(0, head);
+        keystream.apply(5, tail);
+        assert_eq!(parts, whole);
+
+        keystream.apply(0, &mut whole);
+        assert_eq!(&whole, b"Hello, world!");
+
+        // files have keystreams of their own
+        let mut other = *b"Hello, world!";
+        cipher.keystream().apply(0, &mut other);
+        assert_ne!(other, parts);
+    }
 }
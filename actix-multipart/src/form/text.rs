@@ -1,9 +1,10 @@
 //! Deserializes a field from plain text.
 
-use std::{str, sync::Arc};
+use std::{borrow::Cow, str, sync::Arc};
 
 use actix_web::{http::StatusCode, web, Error, HttpRequest, ResponseError};
 use derive_more::derive::{Deref, DerefMut, Display, Error};
+use encoding_rs::{Encoding, UTF_8};
 use futures_core::future::LocalBoxFuture;
 use serde::de::DeserializeOwned;
 
@@ -17,6 +18,10 @@ use crate::{
 ///
 /// Internally this uses [`serde_plain`] for deserialization, which supports primitive types
 /// including strings, numbers, and simple enums.
+///
+/// The field's text is decoded according to its [charset](Field::charset), which defaults to
+/// UTF-8. Fields with a charset that is not supported, or with text that is not valid in their
+/// charset, are rejected.
 #[derive(Debug, Deref, DerefMut)]
 pub struct Text<T: DeserializeOwned>(pub T);
 
@@ -56,14 +61,36 @@ where
 
             let form_field_name = field.form_field_name.clone();
 
-            let bytes = Bytes::read_field(req, field, limits).await?;
+            let encoding = match field.charset() {
+                Some(charset) => Encoding::for_label(charset.as_bytes()).ok_or_else(|| {
+                    MultipartError::Field {
+                        name: form_field_name.clone(),
+                        source: config
+                            .map_error(req, TextError::UnsupportedCharset(charset.to_owned())),
+                    }
+                })?,
+                None => UTF_8,
+            };
 
-            let text = str::from_utf8(&bytes.data).map_err(|err| MultipartError::Field {
-                name: form_field_name.clone(),
-                source: config.map_error(req, TextError::Utf8Error(err)),
-            })?;
+            let bytes = Bytes::read_field(req, field, limits).await?;
 
-            Ok(Text(serde_plain::from_str(text).map_err(|err| {
+            let text = if encoding == UTF_8 {
+                Cow::Borrowed(
+                    str::from_utf8(&bytes.data).map_err(|err| MultipartError::Field {
+                        name: form_field_name.clone(),
+                        source: config.map_error(req, TextError::Utf8Error(err)),
+                    })?,
+                )
+            } else {
+                encoding
+                    .decode_without_bom_handling_and_without_replacement(&bytes.data)
+                    .ok_or_else(|| MultipartError::Field {
+                        name: form_field_name.clone(),
+                        source: config.map_error(req, TextError::Decode(encoding.name())),
+                    })?
+            };
+
+            Ok(Text(serde_plain::from_str(&text).map_err(|err| {
                 MultipartError::Field {
                     name: form_field_name,
                     source: config.map_error(req, TextError::Deserialize(err)),
@@ -80,6 +107,14 @@ pub enum TextError {
     #[display("UTF-8 decoding error: {}", _0)]
     Utf8Error(str::Utf8Error),
 
+    /// Charset is not supported.
+    #[display("Unsupported charset: {}", _0)]
+    UnsupportedCharset(#[error(not(source))] String),
+
+    /// Text is not valid in its charset.
+    #[display("Text is not valid {}", _0)]
+    Decode(#[error(not(source))] &'static str),
+
     /// Deserialize error.
     #[display("Plain text deserialize error: {}", _0)]
     Deserialize(serde_plain::Error),
@@ -194,4 +229,47 @@ mod tests {
         let response = send_form(&srv, form, "/").await;
         assert_eq!(response.status(), StatusCode::OK);
     }
+
+    #[derive(MultipartForm)]
+    struct NameForm {
+        name: Text<String>,
+    }
+
+    async fn test_charset_route(form: MultipartForm<NameForm>) -> impl Responder {
+        form.into_inner().name.into_inner()
+    }
+
+    #[actix_rt::test]
+    async fn test_charset_decoding() {
+        let srv = actix_test::start(|| App::new().route("/", web::post().to(test_charset_route)));
+
+        let windows_1252: mime::Mime = "text/plain; charset=windows-1252".parse().unwrap();
+
+        // "Zoë" in Windows-1252
+        let mut form = multipart::Form::default();
+        form.add_reader_file_with_mime("name", Cursor::new(b"Zo\xEB"), "", windows_1252);
+        let mut response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body().await.unwrap(), "Zoë");
+
+        // charset named by the `_charset_` field
+        let mut form = multipart::Form::default();
+        form.add_text("_charset_", "windows-1252");
+        form.add_reader_file_with_mime("name", Cursor::new(b"Zo\xEB"), "", mime::TEXT_PLAIN);
+        let mut response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body().await.unwrap(), "Zoë");
+
+        // not valid UTF-8
+        let mut form = multipart::Form::default();
+        form.add_reader_file_with_mime("name", Cursor::new(b"Zo\xEB"), "", mime::TEXT_PLAIN);
+        let response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let unknown: mime::Mime = "text/plain; charset=x-unknown".parse().unwrap();
+        let mut form = multipart::Form::default();
+        form.add_reader_file_with_mime("name", Cursor::new("Zoe"), "", unknown);
+        let response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
 }
@@ -0,0 +1,246 @@
+//! Streaming of received fragmented WebSocket messages.
+
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_http::ws::{Item, Message, ProtocolError};
+use bytes::Bytes;
+use futures_core::Stream;
+use tokio::sync::mpsc;
+
+/// Turns the fragments of fragmented messages received by a WebSocket actor into streams.
+///
+/// The [`handle()`](Self::handle) method is meant to be called with each message received by the
+/// actor's stream handler. When the first fragment of a message arrives, it returns a
+/// [`FragmentStream`] that yields the data of that and each following fragment as it is handled,
+/// so that large messages can be processed, or written elsewhere, without buffering them whole.
+///
+/// Fragments are held by the stream until they are consumed. Dropping the stream discards the
+/// remaining fragments of its message.
+///
+/// # Examples
+/// ```
+/// # use actix::{Actor, ActorFutureExt as _, AsyncContext as _, StreamHandler, WrapFuture as _};
+/// # use actix_web_actors::ws;
+/// # use futures_util::StreamExt as _;
+/// #[derive(Default)]
+/// struct Upload {
+///     fragments: ws::FragmentedMessages,
+/// }
+///
+/// # impl Actor for Upload {
+/// #     type Context = ws::WebsocketContext<Self>;
+/// # }
+/// impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Upload {
+///     fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+///         match msg.and_then(|msg| self.fragments.handle(msg)) {
+///             Ok(Some(ws::Incoming::Binary(mut stream))) => {
+///                 ctx.spawn(
+///                     async move {
+///                         let mut len = 0;
+///
+///                         while let Some(Ok(chunk)) = stream.next().await {
+///                             len += chunk.len();
+///                         }
+///
+///                         len
+///                     }
+///                     .into_actor(self)
+///                     .map(|len, _, ctx| ctx.text(len.to_string())),
+///                 );
+///             }
+///             Ok(_) => {}
+///             Err(_) => ctx.close(Some(ws::CloseCode::Protocol.into())),
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct FragmentedMessages {
+    tx: Option<mpsc::UnboundedSender<(Bytes, bool)>>,
+}
+
+/// Message returned by [`FragmentedMessages::handle()`].
+#[derive(Debug)]
+pub enum Incoming {
+    /// Unfragmented message.
+    Message(Message),
+
+    /// Fragmented text message.
+    ///
+    /// The fragments are not checked to be valid UTF-8.
+    Text(FragmentStream),
+
+    /// Fragmented binary message.
+    Binary(FragmentStream),
+}
+
+impl FragmentedMessages {
+    /// Handles a received message.
+    ///
+    /// Returns the first fragment of a message as an [`Incoming::Text`] or [`Incoming::Binary`]
+    /// stream and other messages as [`Incoming::Message`]. Returns `Ok(None)` for the following
+    /// fragments, which are passed on to the stream of their message.
+    ///
+    /// # Errors
+    /// Returns an error for fragments that do not continue a message and for first fragments
+    /// received before the previous message's last fragment.
+    pub fn handle(&mut self, msg: Message) -> Result<Option<Incoming>, ProtocolError> {
+        let (data, last) = match msg {
+            Message::Continuation(Item::FirstText(data)) => {
+                return self.start(data).map(|stream| Some(Incoming::Text(stream)));
+            }
+            Message::Continuation(Item::FirstBinary(data)) => {
+                return self
+                    .start(data)
+                    .map(|stream| Some(Incoming::Binary(stream)));
+            }
+            Message::Continuation(Item::Continue(data)) => (data, false),
+            Message::Continuation(Item::Last(data)) => (data, true),
+            msg => return Ok(Some(Incoming::Message(msg))),
+        };
+
+        let tx = self
+            .tx
+            .as_ref()
+            .ok_or(ProtocolError::ContinuationNotStarted)?;
+
+        // a dropped stream discards the rest of its message
+        let _ = tx.send((data, last));
+
+        if last {
+            self.tx = None;
+        }
+
+        Ok(None)
+    }
+
+    /// Returns true if the last fragment of a fragmented message has not been handled yet.
+    pub fn in_progress(&self) -> bool {
+        self.tx.is_some()
+    }
+
+    fn start(&mut self, data: Bytes) -> Result<FragmentStream, ProtocolError> {
+        if self.tx.is_some() {
+            return Err(ProtocolError::ContinuationStarted);
+        }
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let _ = tx.send((data, false));
+        self.tx = Some(tx);
+
+        Ok(FragmentStream { rx, done: false })
+    }
+}
+
+/// Stream of the data of the fragments of a received message.
+///
+/// Ends after the last fragment. Yields an [`UnexpectedEof`](io::ErrorKind::UnexpectedEof) error
+/// if the [`FragmentedMessages`] it came from is dropped first, such as when the session ends
+/// before the message is complete.
+#[derive(Debug)]
+pub struct FragmentStream {
+    rx: mpsc::UnboundedReceiver<(Bytes, bool)>,
+    done: bool,
+}
+
+impl Stream for FragmentStream {
+    type Item = Result<Bytes, ProtocolError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        match self.rx.poll_recv(cx) {
+            Poll::Ready(Some((data, last))) => {
+                self.done = last;
+                Poll::Ready(Some(Ok(data)))
+            }
+
+            Poll::Ready(None) => {
+                self.done = true;
+
+                Poll::Ready(Some(Err(ProtocolError::Io(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "message ended before its last fragment",
+                )))))
+            }
+
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt as _;
+
+    use super::*;
+
+    fn fragment(item: Item) -> Message {
+        Message::Continuation(item)
+    }
+
+    #[actix_rt::test]
+    async fn streams_fragments() {
+        let mut fragments = FragmentedMessages::default();
+
+        let mut stream =
+            match fragments.handle(fragment(Item::FirstBinary(Bytes::from_static(b"ab")))) {
+                Ok(Some(Incoming::Binary(stream))) => stream,
+                res => panic!("unexpected result: {res:?}"),
+            };
+        assert!(fragments.in_progress());
+
+        assert!(matches!(
+            fragments.handle(Message::Ping(Bytes::new())),
+            Ok(Some(Incoming::Message(Message::Ping(_))))
+        ));
+        assert!(fragments
+            .handle(fragment(Item::Continue(Bytes::from_static(b"cd"))))
+            .unwrap()
+            .is_none());
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), "ab");
+        assert_eq!(stream.next().await.unwrap().unwrap(), "cd");
+
+        assert!(fragments
+            .handle(fragment(Item::Last(Bytes::from_static(b"e"))))
+            .unwrap()
+            .is_none());
+        assert!(!fragments.in_progress());
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), "e");
+        assert!(stream.next().await.is_none());
+    }
+
+    #[actix_rt::test]
+    async fn continuation_errors() {
+        let mut fragments = FragmentedMessages::default();
+
+        assert!(matches!(
+            fragments.handle(fragment(Item::Last(Bytes::new()))),
+            Err(ProtocolError::ContinuationNotStarted)
+        ));
+
+        let mut stream = match fragments.handle(fragment(Item::FirstText(Bytes::new()))) {
+            Ok(Some(Incoming::Text(stream))) => stream,
+            res => panic!("unexpected result: {res:?}"),
+        };
+
+        assert!(matches!(
+            fragments.handle(fragment(Item::FirstBinary(Bytes::new()))),
+            Err(ProtocolError::ContinuationStarted)
+        ));
+
+        drop(fragments);
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), "");
+        assert!(stream.next().await.unwrap().is_err());
+        assert!(stream.next().await.is_none());
+    }
+}
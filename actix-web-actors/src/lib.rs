@@ -62,6 +62,8 @@
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 
 mod context;
+mod fragment;
+mod typed;
 pub mod ws;
 
 pub use self::context::HttpContext;
@@ -0,0 +1,416 @@
+//! Typed WebSocket messages.
+
+use std::{error::Error as StdError, fmt, marker::PhantomData};
+
+use actix::Actor;
+use actix_http::ws::{CloseCode, CloseReason, Message};
+use actix_web::{http::header, HttpRequest};
+use bytes::Bytes;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::ws::WebsocketContext;
+
+/// Serialization format of typed WebSocket messages.
+///
+/// Each format has a WebSocket subprotocol name, which clients use to request it in the
+/// `Sec-WebSocket-Protocol` header of the handshake. See [`Format::negotiate()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Format {
+    /// JSON, sent as text messages. Subprotocol `json`.
+    Json,
+
+    /// CBOR, sent as binary messages. Subprotocol `cbor`.
+    #[cfg(feature = "cbor")]
+    Cbor,
+
+    /// MessagePack, sent as binary messages. Subprotocol `msgpack`.
+    ///
+    /// Structs are serialized as maps, so that peers do not depend on field order.
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+}
+
+impl Format {
+    /// Returns the WebSocket subprotocol name of this format.
+    pub const fn protocol(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            #[cfg(feature = "cbor")]
+            Format::Cbor => "cbor",
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => "msgpack",
+        }
+    }
+
+    /// Returns the format with the given WebSocket subprotocol name.
+    pub fn from_protocol(protocol: &str) -> Option<Self> {
+        match protocol {
+            "json" => Some(Format::Json),
+            #[cfg(feature = "cbor")]
+            "cbor" => Some(Format::Cbor),
+            #[cfg(feature = "msgpack")]
+            "msgpack" => Some(Format::MessagePack),
+            _ => None,
+        }
+    }
+
+    /// Selects the format of a WebSocket session from the subprotocols requested by the client.
+    ///
+    /// Returns the first subprotocol in the request's `Sec-WebSocket-Protocol` header that names
+    /// one of `formats`, or `None` if the client did not request any of them. This is the
+    /// protocol that [`handshake_with_protocols()`](crate::ws::handshake_with_protocols) would
+    /// select, so the format's name can be used as the only protocol of the handshake.
+    pub fn negotiate(req: &HttpRequest, formats: &[Format]) -> Option<Self> {
+        let protocols = req
+            .headers()
+            .get(&header::SEC_WEBSOCKET_PROTOCOL)?
+            .to_str()
+            .ok()?;
+
+        protocols
+            .split(',')
+            .filter_map(|protocol| Format::from_protocol(protocol.trim()))
+            .find(|format| formats.contains(format))
+    }
+
+    fn serialize<M: Serialize>(self, msg: &M) -> Result<Message, Box<dyn StdError>> {
+        Ok(match self {
+            Format::Json => Message::Text(serde_json::to_string(msg)?.into()),
+
+            #[cfg(feature = "cbor")]
+            Format::Cbor => {
+                let mut buf = Vec::new();
+                ciborium::into_writer(msg, &mut buf)?;
+                Message::Binary(buf.into())
+            }
+
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => Message::Binary(rmp_serde::to_vec_named(msg)?.into()),
+        })
+    }
+
+    fn deserialize<T: DeserializeOwned>(self, data: &Bytes) -> Result<T, Box<dyn StdError>> {
+        Ok(match self {
+            Format::Json => serde_json::from_slice(data)?,
+
+            #[cfg(feature = "cbor")]
+            Format::Cbor => ciborium::from_reader(data.as_ref())?,
+
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => rmp_serde::from_slice(data)?,
+        })
+    }
+
+    /// Returns true if data in this format is sent as text messages.
+    fn is_text(self) -> bool {
+        matches!(self, Format::Json)
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.protocol())
+    }
+}
+
+/// Handling of messages that can not be deserialized by [`Typed::decode()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum UnknownMessage {
+    /// Skip the message. This is the default.
+    #[default]
+    Ignore,
+
+    /// Close the session.
+    ///
+    /// Messages of the wrong type for the format, such as text messages in a CBOR session, are
+    /// closed with [`CloseCode::Unsupported`]. Messages that fail to deserialize are closed with
+    /// [`CloseCode::Invalid`].
+    Close,
+
+    /// Return an error from [`Typed::decode()`].
+    Error,
+}
+
+/// Errors from serializing or deserializing typed WebSocket messages.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TypedError {
+    /// A message could not be serialized.
+    Serialize(Box<dyn StdError>),
+
+    /// A message was of the wrong type for the format or could not be deserialized.
+    Deserialize(Box<dyn StdError>),
+}
+
+impl fmt::Display for TypedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypedError::Serialize(err) => write!(f, "WebSocket message serialize error: {err}"),
+            TypedError::Deserialize(err) => {
+                write!(f, "WebSocket message deserialize error: {err}")
+            }
+        }
+    }
+}
+
+impl StdError for TypedError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            TypedError::Serialize(err) | TypedError::Deserialize(err) => Some(err.as_ref()),
+        }
+    }
+}
+
+/// Serializes and deserializes the messages of a WebSocket session as `T`.
+///
+/// `Typed` is kept by WebSocket actors in place of hand-written (de)serialization. Its
+/// [`decode()`](Self::decode) method turns the text and binary messages received by the actor's
+/// stream handler into `T` values, usually an enum of the messages the client can send. Its
+/// [`send()`](Self::send) method writes any serializable value to the session's context, in the
+/// same format.
+///
+/// Messages that can not be deserialized as `T` are handled according to the
+/// [unknown message policy](Self::unknown_messages).
+///
+/// # Examples
+/// ```no_run
+/// use actix::{Actor, StreamHandler};
+/// use actix_web::{get, web, Error, HttpRequest, HttpResponse};
+/// use actix_web_actors::ws;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize)]
+/// #[serde(tag = "type", rename_all = "snake_case")]
+/// enum ClientMessage {
+///     Join { room: String },
+///     Leave,
+/// }
+///
+/// #[derive(Serialize)]
+/// #[serde(tag = "type", rename_all = "snake_case")]
+/// enum ServerMessage<'a> {
+///     Joined { room: &'a str },
+/// }
+///
+/// struct Chat {
+///     typed: ws::Typed<ClientMessage>,
+/// }
+///
+/// impl Actor for Chat {
+///     type Context = ws::WebsocketContext<Self>;
+/// }
+///
+/// impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Chat {
+///     fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+///         let Ok(msg) = msg else {
+///             return;
+///         };
+///
+///         match self.typed.decode(&msg, ctx) {
+///             Ok(Some(ClientMessage::Join { room })) => {
+///                 self.typed
+///                     .send(ctx, &ServerMessage::Joined { room: &room })
+///                     .unwrap();
+///             }
+///             Ok(Some(ClientMessage::Leave)) => ctx.close(None),
+///             _ => {
+///                 if let ws::Message::Ping(ping) = msg {
+///                     ctx.pong(&ping);
+///                 }
+///             }
+///         }
+///     }
+/// }
+///
+/// #[get("/chat")]
+/// async fn chat(req: HttpRequest, stream: web::Payload) -> Result<HttpResponse, Error> {
+///     let format = ws::Format::negotiate(&req, &[ws::Format::Json]).unwrap_or(ws::Format::Json);
+///     let typed = ws::Typed::new(format).unknown_messages(ws::UnknownMessage::Close);
+///
+///     ws::WsResponseBuilder::new(Chat { typed }, &req, stream)
+///         .protocols(&[format.protocol()])
+///         .start()
+/// }
+/// ```
+pub struct Typed<T> {
+    format: Format,
+    unknown: UnknownMessage,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> Typed<T> {
+    /// Constructs a `Typed` for a session in the given format.
+    pub fn new(format: Format) -> Self {
+        Self {
+            format,
+            unknown: UnknownMessage::default(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Sets the handling of messages that can not be deserialized as `T`.
+    ///
+    /// Defaults to [`UnknownMessage::Ignore`].
+    pub fn unknown_messages(mut self, policy: UnknownMessage) -> Self {
+        self.unknown = policy;
+        self
+    }
+
+    /// Returns the format of the session.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Serializes `msg` into a text or binary message, depending on the format.
+    pub fn encode<M: Serialize>(&self, msg: &M) -> Result<Message, TypedError> {
+        self.format.serialize(msg).map_err(TypedError::Serialize)
+    }
+
+    /// Serializes `msg` and writes it to the session.
+    pub fn send<A, M>(&self, ctx: &mut WebsocketContext<A>, msg: &M) -> Result<(), TypedError>
+    where
+        A: Actor<Context = WebsocketContext<A>>,
+        M: Serialize,
+    {
+        ctx.write_raw(self.encode(msg)?);
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned> Typed<T> {
+    /// Deserializes a message received by the session.
+    ///
+    /// Returns `Ok(None)` for messages other than text and binary messages, such as pings and
+    /// close messages, which are left to the caller. When a text or binary message can not be
+    /// deserialized, the [unknown message policy](Self::unknown_messages) decides whether it is
+    /// skipped with `Ok(None)`, the session is closed and `Ok(None)` returned, or an error is
+    /// returned.
+    pub fn decode<A>(
+        &self,
+        msg: &Message,
+        ctx: &mut WebsocketContext<A>,
+    ) -> Result<Option<T>, TypedError>
+    where
+        A: Actor<Context = WebsocketContext<A>>,
+    {
+        let (data, is_text) = match msg {
+            Message::Text(text) => (text.as_bytes(), true),
+            Message::Binary(data) => (data, false),
+            _ => return Ok(None),
+        };
+
+        let (res, code) = if is_text != self.format.is_text() {
+            let err = format!(
+                "unexpected {} message in {} session",
+                if is_text { "text" } else { "binary" },
+                self.format,
+            );
+
+            (Err(err.into()), CloseCode::Unsupported)
+        } else {
+            (self.format.deserialize(data), CloseCode::Invalid)
+        };
+
+        match res {
+            Ok(msg) => Ok(Some(msg)),
+
+            Err(err) => match self.unknown {
+                UnknownMessage::Ignore => Ok(None),
+
+                UnknownMessage::Close => {
+                    ctx.close(Some(CloseReason {
+                        code,
+                        description: Some(err.to_string()),
+                    }));
+                    Ok(None)
+                }
+
+                UnknownMessage::Error => Err(TypedError::Deserialize(err)),
+            },
+        }
+    }
+}
+
+impl<T> Clone for Typed<T> {
+    fn clone(&self) -> Self {
+        Self {
+            format: self.format,
+            unknown: self.unknown,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Typed<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Typed")
+            .field("format", &self.format)
+            .field("unknown", &self.unknown)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Event {
+        Join { room: String },
+        Leave,
+    }
+
+    fn formats() -> Vec<Format> {
+        vec![
+            Format::Json,
+            #[cfg(feature = "cbor")]
+            Format::Cbor,
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack,
+        ]
+    }
+
+    #[test]
+    fn negotiate() {
+        let req = TestRequest::default()
+            .insert_header((header::SEC_WEBSOCKET_PROTOCOL, "graphql-ws, json"))
+            .to_http_request();
+        assert_eq!(Format::negotiate(&req, &[Format::Json]), Some(Format::Json));
+        assert_eq!(Format::negotiate(&req, &[]), None);
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(Format::negotiate(&req, &[Format::Json]), None);
+
+        for format in formats() {
+            assert_eq!(Format::from_protocol(format.protocol()), Some(format));
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for format in formats() {
+            let msg = Event::Join {
+                room: "lobby".to_owned(),
+            };
+
+            let data = match Typed::<Event>::new(format).encode(&msg).unwrap() {
+                Message::Text(text) => {
+                    assert!(format.is_text());
+                    text.into_bytes()
+                }
+                Message::Binary(data) => {
+                    assert!(!format.is_text());
+                    data
+                }
+                msg => panic!("unexpected message: {msg:?}"),
+            };
+
+            assert_eq!(format.deserialize::<Event>(&data).unwrap(), msg);
+        }
+    }
+}
@@ -62,18 +62,24 @@ use std::{
     io, mem,
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
 
 use actix::{
     dev::{
         AsyncContextParts, ContextFut, ContextParts, Envelope, Mailbox, StreamHandler, ToEnvelope,
     },
-    fut::ActorFuture,
+    fut::{self, ActorFuture, ActorFutureExt as _},
     Actor, ActorContext, ActorState, Addr, AsyncContext, Handler, Message as ActixMessage,
     SpawnHandle,
 };
-use actix_http::ws::{hash_key, Codec};
-pub use actix_http::ws::{CloseCode, CloseReason, Frame, HandshakeError, Message, ProtocolError};
+pub use actix_http::ws::{
+    CloseCode, CloseReason, Fragments, Frame, HandshakeError, Item, Message, ProtocolError,
+};
+use actix_http::{
+    ws::{hash_key, Codec},
+    GracefulShutdown,
+};
 use actix_web::{
     error::{Error, PayloadError},
     http::{
@@ -84,13 +90,26 @@ use actix_web::{
 };
 use bytes::{Bytes, BytesMut};
 use bytestring::ByteString;
-use futures_core::Stream;
+use futures_core::{stream::LocalBoxStream, Stream};
+use futures_util::StreamExt as _;
 use pin_project_lite::pin_project;
 use tokio::sync::oneshot;
 use tokio_util::codec::{Decoder as _, Encoder as _};
 
+pub use crate::{
+    fragment::{FragmentStream, FragmentedMessages, Incoming},
+    typed::{Format, Typed, TypedError, UnknownMessage},
+};
+
+/// Default time sessions are given to complete the closing handshake during a graceful shutdown.
+const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
 /// Builder for Websocket session response.
 ///
+/// When the server starts a graceful shutdown, sessions started by the builder are sent a close
+/// frame with the [`CloseCode::Away`] (1001) code. The actor keeps running, so that it receives the
+/// peer's close frame, until the [grace period](Self::shutdown_grace) ends.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -150,6 +169,7 @@ where
     codec: Option<Codec>,
     protocols: Option<&'a [&'a str]>,
     frame_size: Option<usize>,
+    shutdown_grace: Duration,
 }
 
 impl<'a, A, T> WsResponseBuilder<'a, A, T>
@@ -168,6 +188,7 @@ where
             codec: None,
             protocols: None,
             frame_size: None,
+            shutdown_grace: DEFAULT_SHUTDOWN_GRACE,
         }
     }
 
@@ -192,6 +213,16 @@ where
         self
     }
 
+    /// Set the time the session is given to complete the closing handshake once the server starts
+    /// a graceful shutdown, after which the actor is stopped.
+    ///
+    /// By default, the grace period is 5 seconds. It should be shorter than the server's shutdown
+    /// timeout.
+    pub fn shutdown_grace(mut self, grace: Duration) -> Self {
+        self.shutdown_grace = grace;
+        self
+    }
+
     fn handshake_resp(&self) -> Result<HttpResponseBuilder, HandshakeError> {
         match self.protocols {
             Some(protocols) => handshake_with_protocols(self.req, protocols),
@@ -225,6 +256,7 @@ where
         actor: A,
         stream: S,
         codec: Codec,
+        shutdown: Option<(&GracefulShutdown, Duration)>,
     ) -> (Addr<A>, impl Stream<Item = Result<Bytes, Error>>)
     where
         A: StreamHandler<Result<Message, ProtocolError>>,
@@ -237,6 +269,15 @@ where
         };
         ctx.add_stream(WsStream::new(stream, codec.clone()));
 
+        if let Some((shutdown, grace)) = shutdown {
+            ctx.spawn(fut::wrap_future(shutdown.notified()).map(
+                move |(), _, ctx: &mut WebsocketContext<A>| {
+                    ctx.close(Some(CloseCode::Away.into()));
+                    ctx.run_later(grace, |_, ctx| ctx.stop());
+                },
+            ));
+        }
+
         let addr = ctx.address();
 
         (addr, WebsocketContextFut::new(ctx, actor, mb, codec))
@@ -252,20 +293,9 @@ where
     ///
     /// If successful, consume the [`WsResponseBuilder`] and return a [`HttpResponse`] wrapped in
     /// a [`Result`].
-    pub fn start(mut self) -> Result<HttpResponse, Error> {
-        let mut res = self.handshake_resp()?;
-        self.set_frame_size();
-
-        match self.codec {
-            Some(codec) => {
-                let out_stream = WebsocketContext::with_codec(self.actor, self.stream, codec);
-                Ok(res.streaming(out_stream))
-            }
-            None => {
-                let out_stream = WebsocketContext::create(self.actor, self.stream);
-                Ok(res.streaming(out_stream))
-            }
-        }
+    pub fn start(self) -> Result<HttpResponse, Error> {
+        let (_, res) = self.start_with_addr()?;
+        Ok(res)
     }
 
     /// Perform WebSocket handshake and start actor.
@@ -282,18 +312,16 @@ where
         let mut res = self.handshake_resp()?;
         self.set_frame_size();
 
-        match self.codec {
-            Some(codec) => {
-                let (addr, out_stream) =
-                    Self::create_with_codec_addr(self.actor, self.stream, codec);
-                Ok((addr, res.streaming(out_stream)))
-            }
-            None => {
-                let (addr, out_stream) =
-                    WebsocketContext::create_with_addr(self.actor, self.stream);
-                Ok((addr, res.streaming(out_stream)))
-            }
-        }
+        let shutdown = self.req.conn_data::<GracefulShutdown>();
+
+        let (addr, out_stream) = Self::create_with_codec_addr(
+            self.actor,
+            self.stream,
+            self.codec.unwrap_or_default(),
+            shutdown.map(|shutdown| (shutdown, self.shutdown_grace)),
+        );
+
+        Ok((addr, res.streaming(out_stream)))
     }
 }
 
@@ -305,8 +333,7 @@ where
     A: Actor<Context = WebsocketContext<A>> + StreamHandler<Result<Message, ProtocolError>>,
     T: Stream<Item = Result<Bytes, PayloadError>> + 'static,
 {
-    let mut res = handshake(req)?;
-    Ok(res.streaming(WebsocketContext::create(actor, stream)))
+    WsResponseBuilder::new(actor, req, stream).start()
 }
 
 /// Perform WebSocket handshake and start actor.
@@ -456,7 +483,13 @@ where
     A: Actor<Context = WebsocketContext<A>>,
 {
     inner: ContextParts<A>,
-    messages: VecDeque<Option<Message>>,
+    messages: VecDeque<Option<Outgoing>>,
+}
+
+/// Item in the outgoing message queue of a [`WebsocketContext`].
+enum Outgoing {
+    Message(Message),
+    Stream(LocalBoxStream<'static, Result<Message, Error>>),
 }
 
 impl<A> ActorContext for WebsocketContext<A>
@@ -604,7 +637,42 @@ where
     /// that handle the framing for you.
     #[inline]
     pub fn write_raw(&mut self, msg: Message) {
-        self.messages.push_back(Some(msg));
+        self.messages.push_back(Some(Outgoing::Message(msg)));
+    }
+
+    /// Write stream of messages
+    ///
+    /// The messages are written, in order, after the messages already queued. Messages queued while
+    /// the stream is written, including control frames such as pongs, are delayed until it ends.
+    /// The next message is only pulled from the stream once the previous ones have been written to
+    /// the connection, so this is suitable for sending large fragmented messages created with
+    /// [`Fragments`] without reading them into memory.
+    ///
+    /// # Examples
+    /// ```
+    /// # use actix::{Actor, StreamHandler};
+    /// # use actix_web_actors::ws;
+    /// # struct MyWs;
+    /// # impl Actor for MyWs {
+    /// #     type Context = ws::WebsocketContext<Self>;
+    /// # }
+    /// impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for MyWs {
+    ///     fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+    ///         if let Ok(ws::Message::Text(_)) = msg {
+    ///             let waveform = &[0x12, 0x34, 0x56, 0x78][..];
+    ///             ctx.write_stream(ws::Fragments::from_reader(waveform, 16_384));
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn write_stream<S, E>(&mut self, stream: S)
+    where
+        S: Stream<Item = Result<Message, E>> + 'static,
+        E: Into<Error> + 'static,
+    {
+        let stream = stream.map(|res| res.map_err(Into::into));
+        self.messages
+            .push_back(Some(Outgoing::Stream(Box::pin(stream))));
     }
 
     /// Send text frame
@@ -668,6 +736,7 @@ where
     fut: ContextFut<A, WebsocketContext<A>>,
     encoder: Codec,
     buf: BytesMut,
+    stream: Option<LocalBoxStream<'static, Result<Message, Error>>>,
     closed: bool,
 }
 
@@ -681,6 +750,7 @@ where
             fut,
             encoder: codec,
             buf: BytesMut::new(),
+            stream: None,
             closed: false,
         }
     }
@@ -700,18 +770,37 @@ where
         }
 
         // encode messages
-        while let Some(item) = this.fut.ctx().messages.pop_front() {
-            if let Some(msg) = item {
-                this.encoder.encode(msg, &mut this.buf)?;
-            } else {
-                this.closed = true;
-                break;
+        loop {
+            if let Some(stream) = &mut this.stream {
+                // only pull from the stream once the previous messages have been written
+                if !this.buf.is_empty() {
+                    break;
+                }
+
+                match stream.as_mut().poll_next(cx) {
+                    Poll::Ready(Some(Ok(msg))) => this.encoder.encode(msg, &mut this.buf)?,
+                    Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                    Poll::Ready(None) => this.stream = None,
+                    Poll::Pending => break,
+                }
+
+                continue;
+            }
+
+            match this.fut.ctx().messages.pop_front() {
+                Some(Some(Outgoing::Message(msg))) => this.encoder.encode(msg, &mut this.buf)?,
+                Some(Some(Outgoing::Stream(stream))) => this.stream = Some(stream),
+                Some(None) => {
+                    this.closed = true;
+                    break;
+                }
+                None => break,
             }
         }
 
         if !this.buf.is_empty() {
             Poll::Ready(Some(Ok(std::mem::take(&mut this.buf).freeze())))
-        } else if this.fut.alive() && !this.closed {
+        } else if (this.fut.alive() || this.stream.is_some()) && !this.closed {
             Poll::Pending
         } else {
             Poll::Ready(None)
@@ -184,3 +184,149 @@ async fn simple_start() {
 
     common_test_code(srv, DEFAULT_FRAME_SIZE).await;
 }
+
+#[derive(serde::Deserialize)]
+enum Request {
+    Echo(String),
+}
+
+#[derive(serde::Serialize)]
+enum Reply<'a> {
+    Echoed(&'a str),
+}
+
+struct TypedWs {
+    typed: ws::Typed<Request>,
+}
+
+impl Actor for TypedWs {
+    type Context = ws::WebsocketContext<Self>;
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for TypedWs {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match self.typed.decode(&msg.unwrap(), ctx) {
+            Ok(Some(Request::Echo(text))) => self.typed.send(ctx, &Reply::Echoed(&text)).unwrap(),
+            Ok(None) => {}
+            Err(err) => panic!("unexpected error: {err}"),
+        }
+    }
+}
+
+#[actix_rt::test]
+async fn typed_messages() {
+    let srv = actix_test::start(|| {
+        App::new().service(web::resource("/").to(
+            |req: HttpRequest, stream: web::Payload| async move {
+                let format = ws::Format::negotiate(&req, &[ws::Format::Json]).unwrap();
+                let typed = ws::Typed::new(format).unknown_messages(ws::UnknownMessage::Close);
+
+                ws::WsResponseBuilder::new(TypedWs { typed }, &req, stream)
+                    .protocols(&[format.protocol()])
+                    .start()
+            },
+        ))
+    });
+
+    let (res, mut framed) = awc::Client::new()
+        .ws(srv.url("/"))
+        .protocols(["json"])
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(res.headers().get("sec-websocket-protocol").unwrap(), "json");
+
+    framed
+        .send(ws::Message::Text(r#"{"Echo":"hello"}"#.into()))
+        .await
+        .unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(
+        item,
+        ws::Frame::Text(Bytes::from_static(br#"{"Echoed":"hello"}"#))
+    );
+
+    // unknown messages close the session
+    framed
+        .send(ws::Message::Text(r#"{"Shout":"hello"}"#.into()))
+        .await
+        .unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    let close_reason = match item {
+        ws::Frame::Close(Some(reason)) => reason,
+        _ => panic!("close frame expected"),
+    };
+    assert_eq!(close_reason.code, ws::CloseCode::Invalid);
+}
+
+/// Echoes fragmented binary messages back in fragments of 3 bytes.
+#[derive(Default)]
+struct FragmentWs {
+    fragments: ws::FragmentedMessages,
+}
+
+impl Actor for FragmentWs {
+    type Context = ws::WebsocketContext<Self>;
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for FragmentWs {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match self.fragments.handle(msg.unwrap()).unwrap() {
+            Some(ws::Incoming::Binary(stream)) => {
+                ctx.write_stream(ws::Fragments::new(stream, 3));
+            }
+            Some(ws::Incoming::Message(ws::Message::Binary(bin))) => {
+                ctx.write_stream(ws::Fragments::new(
+                    futures_util::stream::iter([Ok::<_, ws::ProtocolError>(bin)]),
+                    3,
+                ));
+            }
+            Some(msg) => panic!("unexpected message: {msg:?}"),
+            None => {}
+        }
+    }
+}
+
+#[actix_rt::test]
+async fn fragmented_messages() {
+    let mut srv = actix_test::start(|| {
+        App::new().service(web::resource("/").to(
+            |req: HttpRequest, stream: web::Payload| async move {
+                ws::start(FragmentWs::default(), &req, stream)
+            },
+        ))
+    });
+
+    let mut framed = srv.ws().await.unwrap();
+
+    framed
+        .send(ws::Message::Binary(Bytes::from_static(b"abcdefg")))
+        .await
+        .unwrap();
+
+    for item in [
+        ws::Item::FirstBinary(Bytes::from_static(b"abc")),
+        ws::Item::Continue(Bytes::from_static(b"def")),
+        ws::Item::Last(Bytes::from_static(b"g")),
+    ] {
+        let frame = framed.next().await.unwrap().unwrap();
+        assert_eq!(frame, ws::Frame::Continuation(item));
+    }
+
+    for item in [
+        ws::Item::FirstBinary(Bytes::from_static(b"ab")),
+        ws::Item::Continue(Bytes::from_static(b"cd")),
+        ws::Item::Last(Bytes::from_static(b"e")),
+    ] {
+        framed.send(ws::Message::Continuation(item)).await.unwrap();
+    }
+
+    for item in [
+        ws::Item::FirstBinary(Bytes::from_static(b"ab")),
+        ws::Item::Continue(Bytes::from_static(b"cd")),
+        ws::Item::Last(Bytes::from_static(b"e")),
+    ] {
+        let frame = framed.next().await.unwrap().unwrap();
+        assert_eq!(frame, ws::Frame::Continuation(item));
+    }
+}
@@ -1,6 +1,6 @@
 use std::{cell::RefCell, mem, rc::Rc};
 
-use actix_http::Request;
+use actix_http::{HttpMessage as _, Request};
 use actix_router::{Path, ResourceDef, Router, Url};
 use actix_service::{boxed, fn_service, Service, ServiceFactory};
 use futures_core::future::LocalBoxFuture;
@@ -198,29 +198,42 @@ where
     actix_service::forward_ready!(service);
 
     fn call(&self, mut req: Request) -> Self::Future {
-        let extensions = Rc::new(RefCell::new(req.take_req_data()));
         let conn_data = req.take_conn_data();
-        let (head, payload) = req.into_parts();
 
-        let req = match self.app_state.pool().pop() {
-            Some(mut req) => {
-                let inner = Rc::get_mut(&mut req.inner).unwrap();
+        let (req, payload) = match self.app_state.pool().pop() {
+            Some(mut http_req) => {
+                let inner = Rc::get_mut(&mut http_req.inner).unwrap();
+
+                // swap request data into the pooled container; the cleared map left behind is
+                // returned to the request extensions pool along with `req`
+                mem::swap(
+                    Rc::get_mut(&mut inner.extensions).unwrap().get_mut(),
+                    &mut *req.extensions_mut(),
+                );
+
+                let (head, payload) = req.into_parts();
                 inner.path.get_mut().update(&head.uri);
                 inner.path.reset();
                 inner.head = head;
                 inner.conn_data = conn_data;
-                inner.extensions = extensions;
-                req
+                (http_req, payload)
             }
 
-            None => HttpRequest::new(
-                Path::new(Url::new(head.uri.clone())),
-                head,
-                Rc::clone(&self.app_state),
-                Rc::clone(&self.app_data),
-                conn_data,
-                extensions,
-            ),
+            None => {
+                let extensions = Rc::new(RefCell::new(req.take_req_data()));
+                let (head, payload) = req.into_parts();
+
+                let http_req = HttpRequest::new(
+                    Path::new(Url::new(head.uri.clone())),
+                    head,
+                    Rc::clone(&self.app_state),
+                    Rc::clone(&self.app_data),
+                    conn_data,
+                    extensions,
+                );
+
+                (http_req, payload)
+            }
         };
 
         self.service.call(ServiceRequest::new(req, payload))
@@ -9,7 +9,13 @@
 
 #[cfg(feature = "__compress")]
 pub use actix_http::encoding::Decoder as Decompress;
-pub use actix_http::{Extensions, Payload, RequestHead, Response, ResponseHead};
+#[cfg(feature = "__tls")]
+pub use actix_http::TlsInfo;
+#[cfg(unix)]
+pub use actix_http::UdsInfo;
+pub use actix_http::{
+    EtagCache, Extensions, GracefulShutdown, Payload, Request, RequestHead, Response, ResponseHead,
+};
 use actix_router::Patterns;
 pub use actix_router::{Path, ResourceDef, ResourcePath, Url};
 pub use actix_server::{Server, ServerHandle};
@@ -1,6 +1,9 @@
 use std::{error::Error as StdError, fmt};
 
-use actix_http::{body::BoxBody, Response};
+use actix_http::{
+    body::{BoxBody, MessageBody as _},
+    Response,
+};
 
 use crate::{HttpResponse, ResponseError};
 
@@ -14,6 +17,7 @@ use crate::{HttpResponse, ResponseError};
 /// you can always get a `ResponseError` reference from it.
 pub struct Error {
     cause: Box<dyn ResponseError>,
+    failed_extractor: Option<Box<FailedExtractor>>,
 }
 
 impl Error {
@@ -27,21 +31,94 @@ impl Error {
         <dyn ResponseError>::downcast_ref(self.cause.as_ref())
     }
 
+    /// Returns the handler argument whose extraction caused this error, if it was caused by one.
+    pub fn failed_extractor(&self) -> Option<&FailedExtractor> {
+        self.failed_extractor.as_deref()
+    }
+
+    /// Records that this error was caused by extracting the handler argument at `position`.
+    pub(crate) fn with_failed_extractor(
+        mut self,
+        position: usize,
+        type_name: &'static str,
+    ) -> Self {
+        self.failed_extractor = Some(Box::new(FailedExtractor {
+            position,
+            type_name,
+        }));
+        self
+    }
+
     /// Shortcut for creating an `HttpResponse`.
+    ///
+    /// When the error was caused by an extractor and the underlying error's response has the
+    /// default plain text body, the body also identifies the [failed extractor].
+    ///
+    /// [failed extractor]: Self::failed_extractor
     pub fn error_response(&self) -> HttpResponse {
-        self.cause.error_response()
+        let res = self.cause.error_response();
+
+        if self.failed_extractor.is_none() {
+            return res;
+        }
+
+        let (res, body) = res.into_parts();
+
+        match body.try_into_bytes() {
+            Ok(body) if body == self.cause.to_string().as_bytes() => {
+                res.set_body(BoxBody::new(self.to_string()))
+            }
+            Ok(body) => res.set_body(BoxBody::new(body)),
+            Err(body) => res.set_body(body),
+        }
     }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt::Display::fmt(&self.cause, f)
+        match self.failed_extractor {
+            Some(ref extractor) => write!(f, "Failed to extract {}: {}", extractor, self.cause),
+            None => fmt::Display::fmt(&self.cause, f),
+        }
     }
 }
 
 impl fmt::Debug for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", &self.cause)
+        match self.failed_extractor {
+            Some(ref extractor) => write!(f, "{:?} (extracting {})", &self.cause, extractor),
+            None => write!(f, "{:?}", &self.cause),
+        }
+    }
+}
+
+/// Handler argument whose extraction failed.
+///
+/// Returned by [`Error::failed_extractor()`] for errors returned by the [`FromRequest`]
+/// implementation of tuples, which is how handler arguments are extracted.
+///
+/// [`FromRequest`]: crate::FromRequest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FailedExtractor {
+    position: usize,
+    type_name: &'static str,
+}
+
+impl FailedExtractor {
+    /// Returns the one-based position of the argument in the handler's parameter list.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the type name of the argument, as given by [`std::any::type_name()`].
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl fmt::Display for FailedExtractor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "argument {} (`{}`)", self.position, self.type_name)
     }
 }
 
@@ -56,13 +133,17 @@ impl<T: ResponseError + 'static> From<T> for Error {
     fn from(err: T) -> Error {
         Error {
             cause: Box::new(err),
+            failed_extractor: None,
         }
     }
 }
 
 impl From<Box<dyn ResponseError>> for Error {
     fn from(value: Box<dyn ResponseError>) -> Self {
-        Error { cause: value }
+        Error {
+            cause: value,
+            failed_extractor: None,
+        }
     }
 }
 
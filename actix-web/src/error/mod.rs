@@ -11,7 +11,7 @@ use serde_json::error::Error as JsonError;
 use serde_urlencoded::{de::Error as FormDeError, ser::Error as FormError};
 use url::ParseError as UrlParseError;
 
-use crate::http::StatusCode;
+use crate::http::{header::HeaderName, StatusCode};
 
 #[allow(clippy::module_inception)]
 mod error;
@@ -20,7 +20,11 @@ mod macros;
 mod response_error;
 
 pub(crate) use self::macros::{downcast_dyn, downcast_get_type_id};
-pub use self::{error::Error, internal::*, response_error::ResponseError};
+pub use self::{
+    error::{Error, FailedExtractor},
+    internal::*,
+    response_error::ResponseError,
+};
 
 /// A convenience [`Result`](std::result::Result) for Actix Web operations.
 ///
@@ -192,6 +196,68 @@ impl ResponseError for QueryPayloadError {
     }
 }
 
+/// A set of errors that can occur when extracting typed request headers.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum HeaderError {
+    /// Header is missing from the request.
+    #[display("Missing `{}` header", name)]
+    Missing {
+        /// Name of the missing header.
+        name: HeaderName,
+    },
+
+    /// Header value could not be parsed.
+    #[display("Invalid `{}` header", name)]
+    Invalid {
+        /// Name of the invalid header.
+        name: HeaderName,
+    },
+}
+
+impl HeaderError {
+    /// Returns the name of the header that could not be extracted.
+    pub fn name(&self) -> &HeaderName {
+        match self {
+            Self::Missing { name } | Self::Invalid { name } => name,
+        }
+    }
+}
+
+impl ResponseError for HeaderError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// Error returned when a request's [`MemoryBudget`](crate::middleware::MemoryBudget) has too few
+/// bytes left for a reservation.
+#[derive(Debug, Display, Error)]
+#[display(
+    "Memory budget of {} bytes exceeded: {} bytes requested, {} bytes remaining",
+    limit,
+    requested,
+    remaining
+)]
+#[non_exhaustive]
+pub struct MemoryBudgetExceeded {
+    /// Total number of bytes in the budget.
+    pub limit: usize,
+
+    /// Number of bytes that could not be reserved.
+    pub requested: usize,
+
+    /// Number of bytes left in the budget.
+    pub remaining: usize,
+}
+
+/// Return `InsufficientStorage` for `MemoryBudgetExceeded`
+impl ResponseError for MemoryBudgetExceeded {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::INSUFFICIENT_STORAGE
+    }
+}
+
 /// Error type returned when reading body as lines.
 #[derive(Debug, Display, Error, From)]
 #[non_exhaustive]
@@ -222,6 +288,59 @@ impl ResponseError for ReadlinesError {
     }
 }
 
+/// Errors which can occur when streaming the output of a [`Process`](crate::web::Process).
+#[cfg(feature = "process")]
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum ProcessError {
+    /// Command could not be started.
+    #[display("Failed to start command: {}", _0)]
+    Spawn(std::io::Error),
+
+    /// Reading the output of the command failed.
+    #[display("Failed to read command output: {}", _0)]
+    Io(std::io::Error),
+
+    /// Command exited unsuccessfully.
+    #[display("Command failed with {}: {}", status, String::from_utf8_lossy(stderr).trim_end())]
+    Exit {
+        /// Exit status of the command.
+        status: std::process::ExitStatus,
+
+        /// Start of the command's standard error output, up to the configured limit.
+        stderr: Vec<u8>,
+    },
+}
+
+#[cfg(feature = "process")]
+impl ResponseError for ProcessError {}
+
+/// Errors which can occur when verifying a signed URL with a [`UrlSigner`](crate::UrlSigner).
+#[cfg(feature = "signed-urls")]
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum SignedUrlError {
+    /// URL has no signature or expiry time.
+    #[display("URL is not signed")]
+    Missing,
+
+    /// Signature does not match the URL, request method, or client address.
+    #[display("URL signature is invalid")]
+    Invalid,
+
+    /// URL has expired.
+    #[display("Signed URL has expired")]
+    Expired,
+}
+
+/// Return `Forbidden` for `SignedUrlError`
+#[cfg(feature = "signed-urls")]
+impl ResponseError for SignedUrlError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::FORBIDDEN
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
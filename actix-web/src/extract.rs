@@ -8,12 +8,12 @@ use std::{
     task::{Context, Poll},
 };
 
-use actix_http::{Method, Uri};
+use actix_http::{ClientDisconnect, EarlyHints, Method, Uri};
 use actix_utils::future::{ok, Ready};
 use futures_core::ready;
 use pin_project_lite::pin_project;
 
-use crate::{dev::Payload, Error, HttpRequest};
+use crate::{dev::Payload, Error, HttpMessage as _, HttpRequest};
 
 /// A type that implements [`FromRequest`] is called an **extractor** and can extract data from
 /// the request. Some types that implement this trait are: [`Json`], [`Header`], and [`Path`].
@@ -307,9 +307,79 @@ impl FromRequest for Method {
     }
 }
 
+/// Extract a handle for sending `103 Early Hints` before the final response.
+///
+/// When the connection cannot carry informational responses (e.g., HTTP/1.0 or HTTP/2), a closed
+/// handle is returned and hints sent through it are discarded.
+///
+/// # Examples
+/// ```
+/// use actix_web::{
+///     http::{header::HeaderValue, EarlyHints},
+///     web, App, HttpResponse, Responder,
+/// };
+///
+/// async fn handler(hints: EarlyHints) -> impl Responder {
+///     hints.link(HeaderValue::from_static("</app.js>; rel=preload; as=script"));
+///
+///     // ...slow work...
+///
+///     HttpResponse::Ok().body("done")
+/// }
+///
+/// let app = App::new().default_service(web::to(handler));
+/// ```
+impl FromRequest for EarlyHints {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ok(req
+            .extensions()
+            .get::<EarlyHints>()
+            .cloned()
+            .unwrap_or_else(EarlyHints::closed))
+    }
+}
+
+/// Extract a handle that is notified when the client closes the connection.
+///
+/// Requests whose connection is not watched (e.g., HTTP/2 requests) receive a handle that is
+/// never notified. See [`ClientDisconnect`] for details.
+///
+/// # Examples
+/// ```
+/// use actix_web::{http::ClientDisconnect, web, App, HttpResponse, Responder};
+///
+/// async fn handler(disconnect: ClientDisconnect) -> impl Responder {
+///     // ...slow work, checking whether the client is still there...
+///     if disconnect.is_disconnected() {
+///         return HttpResponse::NoContent().finish();
+///     }
+///
+///     HttpResponse::Ok().body("done")
+/// }
+///
+/// let app = App::new().default_service(web::to(handler));
+/// ```
+impl FromRequest for ClientDisconnect {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ok(req
+            .extensions()
+            .get::<ClientDisconnect>()
+            .cloned()
+            .unwrap_or_else(ClientDisconnect::never))
+    }
+}
+
 #[doc(hidden)]
 #[allow(non_snake_case)]
 mod tuple_from_req {
+    use std::any::type_name;
+
     use super::*;
 
     macro_rules! tuple_from_req {
@@ -349,13 +419,20 @@ mod tuple_from_req {
                     let mut this = self.project();
 
                     let mut ready = true;
+                    let mut position = 0;
                     $(
+                        position += 1;
                         match this.$T.as_mut().project() {
                             ExtractProj::Future { fut } => match fut.poll(cx) {
                                 Poll::Ready(Ok(output)) => {
                                     let _ = this.$T.as_mut().project_replace(ExtractFuture::Done { output });
                                 },
-                                Poll::Ready(Err(err)) => return Poll::Ready(Err(err.into())),
+                                Poll::Ready(Err(err)) => {
+                                    let err: Error = err.into();
+                                    let name = type_name::<$T>();
+                                    let err = err.with_failed_extractor(position, name);
+                                    return Poll::Ready(Err(err));
+                                },
                                 Poll::Pending => ready = false,
                             },
                             ExtractProj::Done { .. } => {},
@@ -420,6 +497,24 @@ mod tuple_from_req {
     tuple_from_req! { TupleFromRequest14; A, B, C, D, E, F, G, H, I, J, K, L, M, N }
     tuple_from_req! { TupleFromRequest15; A, B, C, D, E, F, G, H, I, J, K, L, M, N, O }
     tuple_from_req! { TupleFromRequest16; A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P }
+    tuple_from_req! { TupleFromRequest17; A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q }
+    tuple_from_req! { TupleFromRequest18; A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R }
+    tuple_from_req! { TupleFromRequest19; A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S }
+    tuple_from_req! {
+        TupleFromRequest20; A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T
+    }
+    tuple_from_req! {
+        TupleFromRequest21; A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U
+    }
+    tuple_from_req! {
+        TupleFromRequest22; A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V
+    }
+    tuple_from_req! {
+        TupleFromRequest23; A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W
+    }
+    tuple_from_req! {
+        TupleFromRequest24; A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X
+    }
 }
 
 #[cfg(test)]
@@ -531,6 +626,20 @@ mod tests {
         assert_eq!(method, Method::GET);
     }
 
+    #[actix_rt::test]
+    async fn test_early_hints_closed_without_dispatcher() {
+        let req = TestRequest::default().to_http_request();
+        let hints = EarlyHints::extract(&req).await.unwrap();
+        assert!(!hints.is_open());
+    }
+
+    #[actix_rt::test]
+    async fn test_client_disconnect_without_dispatcher() {
+        let req = TestRequest::default().to_http_request();
+        let disconnect = ClientDisconnect::extract(&req).await.unwrap();
+        assert!(!disconnect.is_disconnected());
+    }
+
     #[actix_rt::test]
     async fn test_concurrent() {
         let (req, mut pl) = TestRequest::default()
@@ -552,4 +661,29 @@ mod tests {
             })
         );
     }
+
+    #[actix_rt::test]
+    async fn test_tuple_failed_extractor() {
+        let (req, mut pl) = TestRequest::default()
+            .insert_header((header::CONTENT_TYPE, "application/x-www-form-urlencoded"))
+            .insert_header((header::CONTENT_LENGTH, "9"))
+            .set_payload(Bytes::from_static(b"bye=world"))
+            .to_http_parts();
+
+        let err = <(Method, Form<Info>)>::from_request(&req, &mut pl)
+            .await
+            .unwrap_err();
+
+        let extractor = err.failed_extractor().unwrap();
+        assert_eq!(extractor.position(), 2);
+        assert_eq!(extractor.type_name(), std::any::type_name::<Form<Info>>());
+        assert!(err
+            .to_string()
+            .starts_with("Failed to extract argument 2 ("));
+
+        let res = err.error_response();
+        assert_eq!(res.status(), crate::http::StatusCode::BAD_REQUEST);
+        let body = actix_http::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, err.to_string());
+    }
 }
@@ -17,7 +17,7 @@ use crate::{
 /// In particular, a request handler has three requirements:
 ///
 /// 1. It is an async function (or a function/closure that returns an appropriate future);
-/// 1. The function parameters (up to 12) implement [`FromRequest`];
+/// 1. The function parameters (up to 24) implement [`FromRequest`];
 /// 1. The async function (or future) resolves to a type that can be converted into an
 ///    [`HttpResponse`] (i.e., it implements the [`Responder`] trait).
 ///
@@ -34,9 +34,9 @@ use crate::{
 ///
 /// Rest assured there is no macro magic here; it's just traits.
 ///
-/// The first thing to note is that [`FromRequest`] is implemented for tuples (up to 12 in length).
+/// The first thing to note is that [`FromRequest`] is implemented for tuples (up to 24 in length).
 ///
-/// Secondly, the `Handler` trait is implemented for functions (up to an [arity] of 12) in a way
+/// Secondly, the `Handler` trait is implemented for functions (up to an [arity] of 24) in a way
 /// that aligns their parameter positions with a corresponding tuple of types (becoming the `Args`
 /// type parameter for this trait).
 ///
@@ -162,6 +162,14 @@ factory_tuple! { A B C D E F G H I J K L M }
 factory_tuple! { A B C D E F G H I J K L M N }
 factory_tuple! { A B C D E F G H I J K L M N O }
 factory_tuple! { A B C D E F G H I J K L M N O P }
+factory_tuple! { A B C D E F G H I J K L M N O P Q }
+factory_tuple! { A B C D E F G H I J K L M N O P Q R }
+factory_tuple! { A B C D E F G H I J K L M N O P Q R S }
+factory_tuple! { A B C D E F G H I J K L M N O P Q R S T }
+factory_tuple! { A B C D E F G H I J K L M N O P Q R S T U }
+factory_tuple! { A B C D E F G H I J K L M N O P Q R S T U V }
+factory_tuple! { A B C D E F G H I J K L M N O P Q R S T U V W }
+factory_tuple! { A B C D E F G H I J K L M N O P Q R S T U V W X }
 
 #[cfg(test)]
 mod tests {
@@ -178,7 +186,8 @@ mod tests {
         async fn handler_max(
             _01: (), _02: (), _03: (), _04: (), _05: (), _06: (),
             _07: (), _08: (), _09: (), _10: (), _11: (), _12: (),
-            _13: (), _14: (), _15: (), _16: (),
+            _13: (), _14: (), _15: (), _16: (), _17: (), _18: (),
+            _19: (), _20: (), _21: (), _22: (), _23: (), _24: (),
         ) {}
 
         assert_impl_handler(handler_min);
@@ -2,4 +2,7 @@
 
 pub mod header;
 
-pub use actix_http::{uri, ConnectionType, Error, KeepAlive, Method, StatusCode, Uri, Version};
+pub use actix_http::{
+    uri, ClientDisconnect, ConnectionType, EarlyHints, Error, KeepAlive, Method, StatusCode, Uri,
+    Version,
+};
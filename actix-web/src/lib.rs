@@ -69,6 +69,9 @@
 //! - `rustls-0_22` - HTTPS support via `rustls` 0.22 crate, supports `HTTP/2`
 //! - `rustls-0_23` - HTTPS support via `rustls` 0.23 crate, supports `HTTP/2`
 //! - `secure-cookies` - secure cookies support
+//! - `fault-injection` - fault injection middleware for resilience testing
+//! - `process` - responder that streams the output of a subprocess
+//! - `signed-urls` - signing and verification of temporary access URLs
 
 #![doc(html_logo_url = "https://actix.rs/img/logo.png")]
 #![doc(html_favicon_url = "https://actix.rs/favicon.ico")]
@@ -91,7 +94,12 @@ mod handler;
 mod helpers;
 pub mod http;
 mod info;
+mod load;
+mod long_poll;
 pub mod middleware;
+#[cfg(feature = "process")]
+mod process;
+mod redact;
 mod redirect;
 mod request;
 mod request_data;
@@ -103,6 +111,10 @@ pub mod rt;
 mod scope;
 mod server;
 mod service;
+mod settings;
+mod shutdown;
+#[cfg(feature = "signed-urls")]
+mod signed_url;
 pub mod test;
 mod thin_data;
 pub(crate) mod types;
@@ -115,14 +127,20 @@ pub use crate::{
     error::{Error, ResponseError},
     extract::FromRequest,
     handler::Handler,
+    load::{ListenerLoad, LoadMonitor, LoadReport, WorkerLoad},
+    redact::RedactionPolicy,
     request::HttpRequest,
     resource::Resource,
     response::{CustomizeResponder, HttpResponse, HttpResponseBuilder, Responder},
     route::Route,
     scope::Scope,
     server::HttpServer,
+    settings::{RuntimeLimits, ServerSettings, SettingsHandle, TlsSettings},
+    shutdown::{ShutdownSequence, ShutdownStage, StageReport},
     types::Either,
 };
+#[cfg(feature = "signed-urls")]
+pub use crate::signed_url::{SignUrl, UrlSigner};
 
 macro_rules! codegen_reexport {
     ($name:ident) => {
@@ -0,0 +1,584 @@
+//! Machine-readable load signal of an [`HttpServer`](crate::HttpServer).
+
+use std::{
+    future::Future,
+    io, net,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, Weak,
+    },
+    thread::{self, ThreadId},
+    time::{Duration, Instant},
+};
+
+use actix_rt::{net::UdpSocket, task::JoinHandle};
+use serde::{Serialize, Serializer};
+
+use crate::{
+    body::EitherBody, error::JsonPayloadError, http::header, HttpRequest, HttpResponse, Responder,
+};
+
+/// Handle to the load of a server's workers and listeners.
+///
+/// Once set with [`HttpServer::load_monitor()`], the monitor tracks, for each worker thread, the
+/// number of requests whose handlers have not returned a response yet and how late the worker's
+/// event loop runs timers, which grows when handlers block the thread or the worker has more work
+/// than it can keep up with. On Linux, it also reads how many connections are waiting in the
+/// accept queue of each listener.
+///
+/// The server's [`ServerHandle`](crate::dev::ServerHandle) controls the server but knows nothing
+/// about requests, so keep a clone of the monitor next to it and call [`report()`](Self::report)
+/// whenever a load signal is needed. The [`LoadReport`] can be served as JSON from an endpoint of
+/// the app itself, for example for the metrics API scaler of KEDA or an external metrics adapter
+/// of the Kubernetes horizontal pod autoscaler, or pushed to a collector over UDP with
+/// [`report_udp()`](Self::report_udp), without running a full metrics stack.
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use actix_web::{web, App, HttpServer, LoadMonitor};
+///
+/// # #[actix_web::main]
+/// # async fn main() -> std::io::Result<()> {
+/// let monitor = LoadMonitor::new();
+///
+/// // push a report to a collector every second
+/// monitor.report_udp("10.0.0.5:8125".parse().unwrap(), Duration::from_secs(1));
+///
+/// let app_monitor = monitor.clone();
+///
+/// HttpServer::new(move || {
+///     let monitor = app_monitor.clone();
+///
+///     App::new().route(
+///         "/load",
+///         web::get().to(move || {
+///             let report = monitor.report();
+///             async move { report }
+///         }),
+///     )
+/// })
+/// .load_monitor(monitor)
+/// .bind(("127.0.0.1", 8080))?
+/// .run()
+/// .await
+/// # }
+/// ```
+///
+/// [`HttpServer::load_monitor()`]: crate::HttpServer::load_monitor
+#[derive(Debug, Clone)]
+pub struct LoadMonitor {
+    lag_interval: Duration,
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    next_worker_id: AtomicUsize,
+    workers: Mutex<Vec<Weak<WorkerState>>>,
+    listeners: Mutex<Vec<net::SocketAddr>>,
+}
+
+impl LoadMonitor {
+    /// Constructs a load monitor that measures event loop lag every 100 milliseconds.
+    pub fn new() -> Self {
+        Self {
+            lag_interval: Duration::from_millis(100),
+            shared: Arc::new(Shared::default()),
+        }
+    }
+
+    /// Sets how often the event loop lag of each worker is measured.
+    ///
+    /// Each measurement sleeps for this long and records by how much the worker overslept. Since
+    /// timers have a resolution of one millisecond, lags of up to a millisecond are expected on
+    /// idle workers.
+    ///
+    /// Must be called before the monitor is passed to [`HttpServer::load_monitor()`].
+    ///
+    /// # Panics
+    /// Panics if `interval` is zero.
+    ///
+    /// [`HttpServer::load_monitor()`]: crate::HttpServer::load_monitor
+    pub fn lag_interval(mut self, interval: Duration) -> Self {
+        assert!(!interval.is_zero(), "lag interval must be non-zero");
+        self.lag_interval = interval;
+        self
+    }
+
+    /// Returns the current load of the server's workers and listeners.
+    ///
+    /// Workers appear once they have started. Workers that have stopped are left out.
+    pub fn report(&self) -> LoadReport {
+        let mut workers = self
+            .shared
+            .workers
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(|worker| WorkerLoad {
+                id: worker.id,
+                in_flight: worker.in_flight.load(Ordering::Acquire),
+                event_loop_lag: Duration::from_micros(worker.lag_micros.load(Ordering::Acquire)),
+            })
+            .collect::<Vec<_>>();
+        workers.sort_by_key(|worker| worker.id);
+
+        let queues = listen_queues();
+        let listeners = self
+            .shared
+            .listeners
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|&addr| ListenerLoad::new(addr, &queues))
+            .collect::<Vec<_>>();
+
+        LoadReport {
+            in_flight: workers.iter().map(|worker| worker.in_flight).sum(),
+            accept_queue: listeners.iter().map(|listener| listener.accept_queue).sum(),
+            max_event_loop_lag: workers
+                .iter()
+                .map(|worker| worker.event_loop_lag)
+                .max()
+                .unwrap_or_default(),
+            workers,
+            listeners,
+        }
+    }
+
+    /// Sends a report as a JSON datagram to `target` every `interval`.
+    ///
+    /// Failures to send are logged and the next report is sent as usual. The reporter runs until
+    /// the returned handle is aborted or the runtime it was spawned on stops.
+    ///
+    /// # Panics
+    /// Panics if called outside a Tokio runtime.
+    pub fn report_udp(&self, target: net::SocketAddr, interval: Duration) -> JoinHandle<()> {
+        let monitor = self.clone();
+
+        actix_rt::spawn(async move {
+            let socket = match bind_udp(target).await {
+                Ok(socket) => socket,
+                Err(err) => {
+                    log::error!("failed to bind load report socket: {}", err);
+                    return;
+                }
+            };
+
+            let mut ticks = actix_rt::time::interval(interval);
+
+            loop {
+                ticks.tick().await;
+
+                let report = match serde_json::to_vec(&monitor.report()) {
+                    Ok(report) => report,
+                    Err(err) => {
+                        log::error!("failed to serialize load report: {}", err);
+                        continue;
+                    }
+                };
+
+                if let Err(err) = socket.send_to(&report, target).await {
+                    log::warn!("failed to send load report to {}: {}", target, err);
+                }
+            }
+        })
+    }
+
+    /// Returns the load of the worker running on the current thread, starting to track it if it
+    /// is new.
+    pub(crate) fn worker(&self) -> Arc<WorkerState> {
+        let thread = thread::current().id();
+        let mut workers = self.shared.workers.lock().unwrap();
+
+        workers.retain(|worker| worker.strong_count() > 0);
+
+        if let Some(worker) = workers
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|worker| worker.thread == thread)
+        {
+            return worker;
+        }
+
+        let worker = Arc::new(WorkerState {
+            id: self.shared.next_worker_id.fetch_add(1, Ordering::Relaxed),
+            thread,
+            in_flight: AtomicUsize::new(0),
+            lag_micros: AtomicU64::new(0),
+        });
+        workers.push(Arc::downgrade(&worker));
+
+        // the probe keeps the worker's load alive until the worker's runtime stops
+        actix_rt::spawn(probe_lag(Arc::clone(&worker), self.lag_interval));
+
+        worker
+    }
+
+    /// Adds a TCP listener whose accept queue is reported.
+    pub(crate) fn add_listener(&self, addr: net::SocketAddr) {
+        self.shared.listeners.lock().unwrap().push(addr);
+    }
+}
+
+impl Default for LoadMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Load of a single worker, updated by its requests and lag probe.
+#[derive(Debug)]
+pub(crate) struct WorkerState {
+    id: usize,
+    thread: ThreadId,
+    in_flight: AtomicUsize,
+    lag_micros: AtomicU64,
+}
+
+/// Counts a request as in flight on `worker` until its handler returns.
+pub(crate) fn track<Fut: Future>(
+    worker: Option<Arc<WorkerState>>,
+    fut: Fut,
+) -> impl Future<Output = Fut::Output> {
+    let guard = worker.map(InFlight::new);
+
+    async move {
+        let res = fut.await;
+        drop(guard);
+        res
+    }
+}
+
+struct InFlight(Arc<WorkerState>);
+
+impl InFlight {
+    fn new(worker: Arc<WorkerState>) -> Self {
+        worker.in_flight.fetch_add(1, Ordering::AcqRel);
+        Self(worker)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Measures how late the current event loop wakes up from sleeping for `interval`.
+async fn probe_lag(worker: Arc<WorkerState>, interval: Duration) {
+    loop {
+        let start = Instant::now();
+        actix_rt::time::sleep(interval).await;
+
+        let lag = start.elapsed().saturating_sub(interval);
+        let lag = u64::try_from(lag.as_micros()).unwrap_or(u64::MAX);
+        worker.lag_micros.store(lag, Ordering::Release);
+    }
+}
+
+async fn bind_udp(target: net::SocketAddr) -> io::Result<UdpSocket> {
+    let local: net::SocketAddr = match target {
+        net::SocketAddr::V4(_) => (net::Ipv4Addr::UNSPECIFIED, 0).into(),
+        net::SocketAddr::V6(_) => (net::Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+
+    UdpSocket::bind(local).await
+}
+
+/// Load of a server at one point in time, returned by [`LoadMonitor::report()`].
+///
+/// Serializes to JSON such as:
+///
+/// ```json
+/// {
+///   "in_flight": 3,
+///   "accept_queue": 0,
+///   "max_event_loop_lag_ms": 0.4,
+///   "workers": [
+///     { "id": 0, "in_flight": 2, "event_loop_lag_ms": 0.4 },
+///     { "id": 1, "in_flight": 1, "event_loop_lag_ms": 0.2 }
+///   ],
+///   "listeners": [
+///     { "addr": "0.0.0.0:8080", "accept_queue": 0, "backlog": 1024 }
+///   ]
+/// }
+/// ```
+///
+/// As a [`Responder`], it is sent as such a JSON body that is not cached.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LoadReport {
+    in_flight: usize,
+    accept_queue: Option<u32>,
+    #[serde(rename = "max_event_loop_lag_ms", serialize_with = "as_millis")]
+    max_event_loop_lag: Duration,
+    workers: Vec<WorkerLoad>,
+    listeners: Vec<ListenerLoad>,
+}
+
+impl LoadReport {
+    /// Returns the number of in-flight requests of all workers.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight
+    }
+
+    /// Returns the number of connections waiting in the accept queues of all listeners, if it is
+    /// known for all of them.
+    pub fn accept_queue(&self) -> Option<u32> {
+        self.accept_queue
+    }
+
+    /// Returns the largest event loop lag of all workers.
+    pub fn max_event_loop_lag(&self) -> Duration {
+        self.max_event_loop_lag
+    }
+
+    /// Returns the load of each worker.
+    pub fn workers(&self) -> &[WorkerLoad] {
+        &self.workers
+    }
+
+    /// Returns the load of each listener.
+    pub fn listeners(&self) -> &[ListenerLoad] {
+        &self.listeners
+    }
+}
+
+impl Responder for LoadReport {
+    type Body = EitherBody<String>;
+
+    fn respond_to(self, _: &HttpRequest) -> HttpResponse<Self::Body> {
+        match serde_json::to_string(&self) {
+            Ok(body) => match HttpResponse::Ok()
+                .content_type(mime::APPLICATION_JSON)
+                .insert_header((header::CACHE_CONTROL, "no-store"))
+                .message_body(body)
+            {
+                Ok(res) => res.map_into_left_body(),
+                Err(err) => HttpResponse::from_error(err).map_into_right_body(),
+            },
+
+            Err(err) => {
+                HttpResponse::from_error(JsonPayloadError::Serialize(err)).map_into_right_body()
+            }
+        }
+    }
+}
+
+/// Load of a single worker, see [`LoadReport`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WorkerLoad {
+    id: usize,
+    in_flight: usize,
+    #[serde(rename = "event_loop_lag_ms", serialize_with = "as_millis")]
+    event_loop_lag: Duration,
+}
+
+impl WorkerLoad {
+    /// Returns the ID of the worker, assigned in the order workers started.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Returns the number of requests whose handlers have not returned a response yet.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight
+    }
+
+    /// Returns by how much the worker's last lag measurement overslept.
+    pub fn event_loop_lag(&self) -> Duration {
+        self.event_loop_lag
+    }
+}
+
+/// Load of a single listener, see [`LoadReport`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ListenerLoad {
+    addr: net::SocketAddr,
+    accept_queue: Option<u32>,
+    backlog: Option<u32>,
+}
+
+impl ListenerLoad {
+    fn new(addr: net::SocketAddr, queues: &[(net::SocketAddr, u32, u32)]) -> Self {
+        let mut accept_queue = None;
+        let mut backlog = None;
+
+        // sockets sharing an address with `SO_REUSEPORT` have their own queues
+        for &(_, queue, max) in queues.iter().filter(|(queue_addr, ..)| *queue_addr == addr) {
+            *accept_queue.get_or_insert(0) += queue;
+            *backlog.get_or_insert(0) += max;
+        }
+
+        Self {
+            addr,
+            accept_queue,
+            backlog,
+        }
+    }
+
+    /// Returns the address of the listener.
+    pub fn addr(&self) -> net::SocketAddr {
+        self.addr
+    }
+
+    /// Returns the number of established connections waiting to be accepted.
+    ///
+    /// Only known on Linux.
+    pub fn accept_queue(&self) -> Option<u32> {
+        self.accept_queue
+    }
+
+    /// Returns the maximum length of the accept queue.
+    ///
+    /// Only known on Linux.
+    pub fn backlog(&self) -> Option<u32> {
+        self.backlog
+    }
+}
+
+fn as_millis<S: Serializer>(dur: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(dur.as_secs_f64() * 1000.0)
+}
+
+/// Returns the address, accept queue length, and backlog of listening TCP sockets.
+#[cfg(target_os = "linux")]
+fn listen_queues() -> Vec<(net::SocketAddr, u32, u32)> {
+    let mut queues = Vec::new();
+
+    for path in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        if let Ok(table) = std::fs::read_to_string(path) {
+            queues.extend(table.lines().skip(1).filter_map(parse_listen_socket));
+        }
+    }
+
+    queues
+}
+
+#[cfg(not(target_os = "linux"))]
+fn listen_queues() -> Vec<(net::SocketAddr, u32, u32)> {
+    Vec::new()
+}
+
+/// Parses a line of `/proc/net/tcp` or `/proc/net/tcp6`.
+///
+/// For listening sockets, the receive queue column holds the accept queue length and the
+/// transmit queue column holds the backlog.
+#[cfg(target_os = "linux")]
+fn parse_listen_socket(line: &str) -> Option<(net::SocketAddr, u32, u32)> {
+    const TCP_LISTEN: &str = "0A";
+
+    let mut fields = line.split_whitespace().skip(1);
+    let local = fields.next()?;
+    let _remote = fields.next()?;
+
+    if fields.next()? != TCP_LISTEN {
+        return None;
+    }
+
+    let (tx_queue, rx_queue) = fields.next()?.split_once(':')?;
+    let (ip, port) = local.split_once(':')?;
+
+    // addresses are printed as 32-bit words in native byte order
+    let ip: net::IpAddr = match ip.len() {
+        8 => net::Ipv4Addr::from(u32::from_str_radix(ip, 16).ok()?.to_ne_bytes()).into(),
+        32 => {
+            let mut octets = [0; 16];
+
+            for (idx, word) in octets.chunks_mut(4).enumerate() {
+                let hex = &ip[idx * 8..idx * 8 + 8];
+                word.copy_from_slice(&u32::from_str_radix(hex, 16).ok()?.to_ne_bytes());
+            }
+
+            net::Ipv6Addr::from(octets).into()
+        }
+        _ => return None,
+    };
+
+    Some((
+        net::SocketAddr::new(ip, u16::from_str_radix(port, 16).ok()?),
+        u32::from_str_radix(rx_queue, 16).ok()?,
+        u32::from_str_radix(tx_queue, 16).ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_rt::test]
+    async fn in_flight() {
+        let monitor = LoadMonitor::new();
+        let worker = monitor.worker();
+
+        // a worker is registered once per thread
+        assert!(Arc::ptr_eq(&worker, &monitor.worker()));
+
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let req = actix_rt::spawn(track(Some(Arc::clone(&worker)), rx));
+
+        let report = monitor.report();
+        assert_eq!(report.in_flight(), 1);
+        assert_eq!(report.workers().len(), 1);
+        assert_eq!(report.workers()[0].in_flight(), 1);
+
+        tx.send(()).unwrap();
+        req.await.unwrap().unwrap();
+        assert_eq!(monitor.report().in_flight(), 0);
+    }
+
+    #[test]
+    fn report_json() {
+        let report = LoadReport {
+            in_flight: 1,
+            accept_queue: None,
+            max_event_loop_lag: Duration::from_micros(1500),
+            workers: vec![WorkerLoad {
+                id: 0,
+                in_flight: 1,
+                event_loop_lag: Duration::from_micros(1500),
+            }],
+            listeners: vec![ListenerLoad::new("127.0.0.1:8080".parse().unwrap(), &[])],
+        };
+
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "in_flight": 1,
+                "accept_queue": null,
+                "max_event_loop_lag_ms": 1.5,
+                "workers": [{ "id": 0, "in_flight": 1, "event_loop_lag_ms": 1.5 }],
+                "listeners": [{ "addr": "127.0.0.1:8080", "accept_queue": null, "backlog": null }],
+            })
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn proc_net_tcp() {
+        let ip = u32::from_ne_bytes([127, 0, 0, 1]);
+        let line = format!(
+            "   0: {:08X}:1F90 00000000:0000 0A 00000400:00000003 00:00000000 00000000 0 0 12345",
+            ip,
+        );
+
+        assert_eq!(
+            parse_listen_socket(&line),
+            Some(("127.0.0.1:8080".parse().unwrap(), 3, 1024))
+        );
+
+        // established connections are skipped
+        let line = line.replace(" 0A ", " 01 ");
+        assert_eq!(parse_listen_socket(&line), None);
+
+        let line = "   0: 00000000000000000000000000000000:1F90 \
+            00000000000000000000000000000000:0000 0A 00000080:00000000 00:00000000 00000000 0";
+        assert_eq!(
+            parse_listen_socket(line),
+            Some(("[::]:8080".parse().unwrap(), 0, 128))
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! See [`LongPoll`] for documentation.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_http::ClientDisconnect;
+use actix_rt::time::{sleep, Sleep};
+use pin_project_lite::pin_project;
+
+use crate::{Either, HttpMessage as _, HttpRequest, HttpResponse, Responder};
+
+/// Default time a long poll waits for a notification.
+const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(30);
+
+pin_project! {
+    /// A future that waits for a notification on behalf of a long-polling handler.
+    ///
+    /// `LongPoll` wraps a future that completes when there is something to tell the client, such
+    /// as the receiving end of a channel. It resolves to the response produced by that future or,
+    /// if nothing arrives within the [maximum wait](Self::max_wait), to a `204 No Content`
+    /// response that tells the client to poll again. The `204` response keeps the connection
+    /// alive when the client allows it, so the next poll can reuse it. The maximum wait defaults
+    /// to 30 seconds, which is below the idle timeout of most proxies and load balancers.
+    ///
+    /// If the client closes its HTTP/1 connection while the handler is waiting, the wrapped future
+    /// is dropped and the poll resolves to a `204` response straight away, releasing whatever the
+    /// future was holding. This response is not expected to reach the client. HTTP/2 polls are
+    /// only ended by a notification or the maximum wait.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+    /// use tokio::sync::broadcast;
+    ///
+    /// #[get("/events")]
+    /// async fn events(
+    ///     req: HttpRequest,
+    ///     events: web::Data<broadcast::Sender<String>>,
+    /// ) -> impl Responder {
+    ///     let mut rx = events.subscribe();
+    ///
+    ///     web::LongPoll::new(&req, async move {
+    ///         match rx.recv().await {
+    ///             Ok(event) => HttpResponse::Ok().body(event),
+    ///             Err(_) => HttpResponse::ServiceUnavailable().finish(),
+    ///         }
+    ///     })
+    ///     .max_wait(Duration::from_secs(20))
+    ///     .await
+    /// }
+    /// ```
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct LongPoll<F> {
+        #[pin]
+        fut: F,
+        #[pin]
+        timeout: Sleep,
+        disconnect: Option<ClientDisconnect>,
+    }
+}
+
+impl<F> LongPoll<F>
+where
+    F: Future,
+    F::Output: Responder,
+{
+    /// Constructs a long poll of `req` that waits for `fut`.
+    ///
+    /// The maximum wait is counted from when the long poll is constructed.
+    pub fn new(req: &HttpRequest, fut: F) -> Self {
+        Self {
+            fut,
+            timeout: sleep(DEFAULT_MAX_WAIT),
+            disconnect: req.extensions().get::<ClientDisconnect>().cloned(),
+        }
+    }
+
+    /// Sets the maximum time to wait for `fut` before responding with `204 No Content`.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn max_wait(mut self, dur: Duration) -> Self {
+        self.timeout = sleep(dur);
+        self
+    }
+}
+
+impl<F> Future for LongPoll<F>
+where
+    F: Future,
+    F::Output: Responder,
+{
+    type Output = Either<F::Output, HttpResponse>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Some(disconnect) = this.disconnect {
+            if Pin::new(disconnect).poll(cx).is_ready() {
+                return Poll::Ready(Either::Right(HttpResponse::NoContent().finish()));
+            }
+        }
+
+        if let Poll::Ready(res) = this.fut.poll(cx) {
+            return Poll::Ready(Either::Left(res));
+        }
+
+        if this.timeout.poll(cx).is_ready() {
+            return Poll::Ready(Either::Right(HttpResponse::NoContent().finish()));
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::pending;
+
+    use super::*;
+    use crate::{http::StatusCode, test::TestRequest};
+
+    #[actix_rt::test]
+    async fn notified() {
+        let req = TestRequest::default().to_http_request();
+
+        let res = LongPoll::new(&req, async { "event" })
+            .await
+            .respond_to(&req);
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn timeout() {
+        let req = TestRequest::default().to_http_request();
+
+        let res = LongPoll::new(&req, pending::<HttpResponse>())
+            .max_wait(Duration::from_millis(10))
+            .await
+            .respond_to(&req);
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+}
@@ -2,12 +2,14 @@
 
 use std::{
     future::Future,
+    iter,
     marker::PhantomData,
     pin::Pin,
     task::{Context, Poll},
 };
 
 use actix_http::encoding::Encoder;
+pub use actix_http::encoding::{CompressionOptions, CompressionStats};
 use actix_service::{Service, Transform};
 use actix_utils::future::{ok, Either, Ready};
 use futures_core::ready;
@@ -46,6 +48,17 @@ use crate::{
 ///
 /// A (naïve) example serving an pre-compressed Gzip file is included below.
 ///
+/// # Per-Response Options
+/// The compression level, the minimum size of compressed bodies, and a preferred encoding can be
+/// set for individual responses by inserting [`CompressionOptions`] into their extensions. A
+/// preferred encoding is only used if the client accepts it.
+///
+/// # Compression Statistics
+/// A [`CompressionStats`] handle is inserted into the extensions of each response, with the
+/// encoding applied to its body and the number of bytes read and produced by the encoder. The
+/// counts are final once the body has been sent, so they are best read by middleware that wrap
+/// `Compress` and outlive the body, such as [`Logger`](super::Logger) with the `%z` format.
+///
 /// # Examples
 /// To enable automatic payload compression just include `Compress` as a top-level middleware:
 /// ```
@@ -71,6 +84,23 @@ use crate::{
 ///     .default_service(web::to(index_handler));
 /// ```
 ///
+/// Large export compressed with the best gzip level, when the client accepts gzip:
+/// ```
+/// use actix_web::{
+///     http::header::ContentEncoding, middleware::CompressionOptions, HttpResponse,
+/// };
+///
+/// async fn export_handler() -> HttpResponse {
+///     let mut res = HttpResponse::Ok().body("...");
+///     res.extensions_mut().insert(
+///         CompressionOptions::new()
+///             .encoding(ContentEncoding::Gzip)
+///             .level(9),
+///     );
+///     res
+/// }
+/// ```
+///
 /// [feature flags]: ../index.html#crate-features
 #[derive(Debug, Clone, Default)]
 #[non_exhaustive]
@@ -118,6 +148,7 @@ where
             None => {
                 return Either::left(CompressResponse {
                     encoding: Encoding::identity(),
+                    accept_encoding: None,
                     fut: self.service.call(req),
                     _phantom: PhantomData,
                 })
@@ -146,6 +177,7 @@ where
             Some(encoding) => Either::left(CompressResponse {
                 fut: self.service.call(req),
                 encoding,
+                accept_encoding: Some(accept_encoding),
                 _phantom: PhantomData,
             }),
         }
@@ -160,6 +192,7 @@ pin_project! {
         #[pin]
         fut: S::Future,
         encoding: Encoding,
+        accept_encoding: Option<AcceptEncoding>,
         _phantom: PhantomData<B>,
     }
 }
@@ -183,7 +216,39 @@ where
                     }
                 };
 
-                Poll::Ready(Ok(resp.map_body(move |head, body| {
+                let options = resp
+                    .response()
+                    .extensions()
+                    .get::<CompressionOptions>()
+                    .copied()
+                    .unwrap_or_default();
+
+                let enc = match options.get_encoding() {
+                    Some(ContentEncoding::Identity) => ContentEncoding::Identity,
+
+                    // only use the preferred encoding if it is supported and the client accepts it
+                    Some(preferred) => {
+                        let preferred_enc = Encoding::Known(preferred);
+
+                        let acceptable = SUPPORTED_ENCODINGS.contains(&preferred_enc)
+                            && this.accept_encoding.as_ref().is_some_and(|accept| {
+                                accept.negotiate(iter::once(&preferred_enc))
+                                    == Some(preferred_enc.clone())
+                            });
+
+                        if acceptable {
+                            preferred
+                        } else {
+                            enc
+                        }
+                    }
+
+                    None => enc,
+                };
+
+                let mut stats = None;
+
+                let mut res = resp.map_body(|head, body| {
                     let content_type = head.headers.get(header::CONTENT_TYPE);
 
                     fn default_compress_predicate(content_type: Option<&HeaderValue>) -> bool {
@@ -205,8 +270,17 @@ where
                         ContentEncoding::Identity
                     };
 
-                    EitherBody::left(Encoder::response(enc, head, body))
-                })))
+                    let encoder = Encoder::response_with(enc, head, body, &options);
+                    stats = Some(encoder.stats());
+
+                    EitherBody::left(encoder)
+                });
+
+                if let Some(stats) = stats {
+                    res.response_mut().extensions_mut().insert(stats);
+                }
+
+                Poll::Ready(Ok(res))
             }
 
             Err(err) => Poll::Ready(Err(err)),
@@ -430,6 +504,111 @@ mod tests {
         assert_eq!(test::read_body(res).await, TEXT_DATA.as_bytes());
     }
 
+    #[actix_rt::test]
+    async fn per_response_options() {
+        fn with_options(options: CompressionOptions) -> HttpResponse {
+            let mut res = HttpResponse::Ok().body(TEXT_DATA);
+            res.extensions_mut().insert(options);
+            res
+        }
+
+        let app = test::init_service({
+            App::new()
+                .wrap(Compress::default())
+                .route(
+                    "/small",
+                    web::get().to(|| {
+                        with_options(CompressionOptions::new().min_size(TEXT_DATA.len() as u64 + 1))
+                    }),
+                )
+                .route(
+                    "/identity",
+                    web::get().to(|| {
+                        with_options(CompressionOptions::new().encoding(ContentEncoding::Identity))
+                    }),
+                )
+                .route(
+                    "/deflate",
+                    web::get().to(|| {
+                        with_options(CompressionOptions::new().encoding(ContentEncoding::Deflate))
+                    }),
+                )
+                .route(
+                    "/stored",
+                    web::get().to(|| with_options(CompressionOptions::new().level(0))),
+                )
+        })
+        .await;
+
+        let req = |uri, accept_encoding| {
+            test::TestRequest::with_uri(uri)
+                .insert_header((header::ACCEPT_ENCODING, accept_encoding))
+                .to_request()
+        };
+
+        for uri in ["/small", "/identity"] {
+            let res = test::call_service(&app, req(uri, "gzip")).await;
+            assert!(!res.headers().contains_key(header::CONTENT_ENCODING));
+            assert_eq!(test::read_body(res).await, TEXT_DATA.as_bytes());
+        }
+
+        let res = test::call_service(&app, req("/deflate", "gzip, deflate;q=0.5")).await;
+        assert_eq!(
+            res.headers().get(header::CONTENT_ENCODING).unwrap(),
+            "deflate"
+        );
+
+        // preferred encoding is not acceptable to the client
+        let res = test::call_service(&app, req("/deflate", "gzip")).await;
+        assert_eq!(res.headers().get(header::CONTENT_ENCODING).unwrap(), "gzip");
+
+        let res = test::call_service(&app, req("/stored", "gzip")).await;
+        assert_eq!(res.headers().get(header::CONTENT_ENCODING).unwrap(), "gzip");
+        let bytes = test::read_body(res).await;
+        assert!(bytes.len() > TEXT_DATA.len());
+        assert_eq!(gzip_decode(bytes), TEXT_DATA.as_bytes());
+    }
+
+    #[actix_rt::test]
+    async fn compression_stats() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Compress::default())
+                .configure(configure_predicate_test),
+        )
+        .await;
+
+        let stats = |res: &ServiceResponse<_>| {
+            res.response()
+                .extensions()
+                .get::<CompressionStats>()
+                .cloned()
+                .unwrap()
+        };
+
+        let req =
+            test::TestRequest::with_uri("/html").insert_header((header::ACCEPT_ENCODING, "gzip"));
+        let res = test::call_service(&app, req.to_request()).await;
+        let html_stats = stats(&res);
+        assert_eq!(html_stats.encoding(), ContentEncoding::Gzip);
+        assert!(!html_stats.is_complete());
+
+        let body = test::read_body(res).await;
+        assert!(html_stats.is_complete());
+        assert_eq!(html_stats.bytes_in(), HTML_DATA.len() as u64);
+        assert_eq!(html_stats.bytes_out(), body.len() as u64);
+        assert!(html_stats.ratio().unwrap() < 0.5);
+
+        let req =
+            test::TestRequest::with_uri("/image").insert_header((header::ACCEPT_ENCODING, "gzip"));
+        let res = test::call_service(&app, req.to_request()).await;
+        let image_stats = stats(&res);
+        test::read_body(res).await;
+        assert_eq!(image_stats.encoding(), ContentEncoding::Identity);
+        assert_eq!(image_stats.bytes_in(), image_stats.bytes_out());
+        assert_eq!(image_stats.ratio(), Some(1.0));
+    }
+
     #[actix_rt::test]
     async fn prevents_compression_empty() {
         let app = test::init_service({
@@ -0,0 +1,271 @@
+//! For middleware documentation, see [`ResponseContract`].
+
+use std::{borrow::Cow, fmt, rc::Rc};
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use futures_core::future::LocalBoxFuture;
+
+use crate::{
+    body::{EitherBody, MessageBody},
+    dev::ResponseHead,
+    http::header,
+    service::{ServiceRequest, ServiceResponse},
+    Error, HttpRequest, HttpResponse,
+};
+
+/// Middleware for checking that outgoing responses uphold configured invariants, e.g., to catch
+/// caching or cookie policy regressions in development and review environments.
+///
+/// Each invariant is a named check of the request and the response head. Responses that violate
+/// one or more invariants are logged at the `error` level with the names of the violated
+/// invariants. With [`fail()`](Self::fail), they are also replaced by a `500 Internal Server
+/// Error` response listing the violated invariants, so that regressions can not go unnoticed.
+///
+/// Checking every response has a cost, so this middleware is best enabled only where it is
+/// needed, e.g., with [`Condition`](super::Condition).
+///
+/// # Examples
+/// ```
+/// use actix_web::{
+///     http::header,
+///     middleware::{Condition, ResponseContract},
+///     App,
+/// };
+///
+/// let contract = ResponseContract::new()
+///     .invariant("successful JSON responses set Cache-Control", |_req, res| {
+///         let is_json = res
+///             .headers
+///             .get(header::CONTENT_TYPE)
+///             .is_some_and(|ct| ct.as_bytes().starts_with(b"application/json"));
+///
+///         !(res.status.is_success() && is_json) || res.headers.contains_key(header::CACHE_CONTROL)
+///     })
+///     .secure_cookies()
+///     .fail();
+///
+/// let app = App::new().wrap(Condition::new(cfg!(debug_assertions), contract));
+/// ```
+#[derive(Clone, Default)]
+pub struct ResponseContract {
+    invariants: Vec<Invariant>,
+    fail: bool,
+}
+
+type Check = Rc<dyn Fn(&HttpRequest, &ResponseHead) -> bool>;
+
+#[derive(Clone)]
+struct Invariant {
+    name: Cow<'static, str>,
+    check: Check,
+}
+
+impl ResponseContract {
+    /// Constructs a `ResponseContract` middleware without any invariants.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an invariant that responses must uphold.
+    ///
+    /// `check` is called with the request and the head of its response and returns `false` if the
+    /// response violates the invariant. `name` identifies the invariant in logs and failure
+    /// responses.
+    pub fn invariant<F>(mut self, name: impl Into<Cow<'static, str>>, check: F) -> Self
+    where
+        F: Fn(&HttpRequest, &ResponseHead) -> bool + 'static,
+    {
+        self.invariants.push(Invariant {
+            name: name.into(),
+            check: Rc::new(check),
+        });
+        self
+    }
+
+    /// Adds an invariant that every cookie set by a response has the `Secure` attribute.
+    pub fn secure_cookies(self) -> Self {
+        self.invariant("cookies are set with the Secure attribute", |_, res| {
+            res.headers.get_all(header::SET_COOKIE).all(|cookie| {
+                cookie.to_str().is_ok_and(|cookie| {
+                    cookie
+                        .split(';')
+                        .skip(1)
+                        .any(|attr| attr.trim().eq_ignore_ascii_case("secure"))
+                })
+            })
+        })
+    }
+
+    /// Replaces responses that violate an invariant with a `500 Internal Server Error` response.
+    ///
+    /// By default, violations are only logged.
+    pub fn fail(mut self) -> Self {
+        self.fail = true;
+        self
+    }
+}
+
+impl fmt::Debug for ResponseContract {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseContract")
+            .field(
+                "invariants",
+                &self
+                    .invariants
+                    .iter()
+                    .map(|inv| &inv.name)
+                    .collect::<Vec<_>>(),
+            )
+            .field("fail", &self.fail)
+            .finish()
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ResponseContract
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = ResponseContractMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ResponseContractMiddleware {
+            service,
+            contract: Rc::new(self.clone()),
+        }))
+    }
+}
+
+/// Response contract middleware service.
+pub struct ResponseContractMiddleware<S> {
+    service: S,
+    contract: Rc<ResponseContract>,
+}
+
+impl<S, B> Service<ServiceRequest> for ResponseContractMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let fut = self.service.call(req);
+        let contract = Rc::clone(&self.contract);
+
+        Box::pin(async move {
+            let res = fut.await?;
+
+            let violated = contract
+                .invariants
+                .iter()
+                .filter(|inv| !(inv.check)(res.request(), res.response().head()))
+                .map(|inv| inv.name.as_ref())
+                .collect::<Vec<_>>();
+
+            if violated.is_empty() {
+                return Ok(res.map_into_left_body());
+            }
+
+            let violated = violated.join("; ");
+
+            log::error!(
+                "response to {} {} with status {} violates contract: {}",
+                res.request().method(),
+                res.request().path(),
+                res.status(),
+                violated
+            );
+
+            if !contract.fail {
+                return Ok(res.map_into_left_body());
+            }
+
+            let failure = HttpResponse::InternalServerError()
+                .body(format!("Response contract violated: {violated}"));
+
+            Ok(res.into_response(failure).map_into_right_body())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::StatusCode,
+        test::{call_service, init_service, read_body, TestRequest},
+        web, App,
+    };
+
+    #[actix_rt::test]
+    async fn secure_cookies() {
+        let srv = init_service(
+            App::new()
+                .wrap(ResponseContract::new().secure_cookies().fail())
+                .route(
+                    "/secure",
+                    web::to(|| {
+                        HttpResponse::Ok()
+                            .insert_header((header::SET_COOKIE, "id=1; Path=/; SECURE; HttpOnly"))
+                            .finish()
+                    }),
+                )
+                .route(
+                    "/insecure",
+                    web::to(|| {
+                        HttpResponse::Ok()
+                            .append_header((header::SET_COOKIE, "a=1; Secure"))
+                            .append_header((header::SET_COOKIE, "secure=1"))
+                            .finish()
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/secure").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::get().uri("/insecure").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!res.headers().contains_key(header::SET_COOKIE));
+        assert_eq!(
+            read_body(res).await,
+            "Response contract violated: cookies are set with the Secure attribute"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn log_only() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    ResponseContract::new()
+                        .invariant("responses set Cache-Control", |_, res| {
+                            res.headers.contains_key(header::CACHE_CONTROL)
+                        })
+                        .invariant("only GET is served", |req, _| {
+                            req.method().as_str() == "GET"
+                        }),
+                )
+                .default_service(web::to(|| async { "ok" })),
+        )
+        .await;
+
+        let req = TestRequest::get().to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, "ok");
+    }
+}
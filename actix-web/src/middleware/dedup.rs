@@ -0,0 +1,465 @@
+//! For middleware documentation, see [`Deduplicate`].
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use futures_core::future::LocalBoxFuture;
+
+use crate::{
+    body::EitherBody,
+    http::{header::HeaderName, StatusCode},
+    service::{ServiceRequest, ServiceResponse},
+    Error, HttpResponse,
+};
+
+/// Default name of the header holding the message ID.
+const DEFAULT_HEADER: HeaderName = HeaderName::from_static("x-message-id");
+
+/// Default time for which message IDs are remembered.
+const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Default time for which message IDs are remembered while their request is being handled.
+const DEFAULT_IN_FLIGHT_TTL: Duration = Duration::from_secs(60);
+
+/// Minimum number of entries kept by [`MemoryDedupStore`] before expired ones are purged.
+const MIN_PURGE_LEN: usize = 64;
+
+/// Recorded state of a message ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupEntry {
+    /// A request with the ID is currently being handled.
+    InFlight,
+
+    /// A request with the ID was handled with the given response status.
+    Completed(StatusCode),
+}
+
+/// Storage of the message IDs seen by [`Deduplicate`].
+///
+/// A store should be shared by all workers of a server, so that retries are detected no matter
+/// which worker they reach. Implementations backed by an external database, like Redis, should make
+/// [`begin()`](Self::begin) atomic so that concurrent duplicates can not both be handled.
+pub trait DedupStore: 'static {
+    /// Error returned by the store operations.
+    type Error: Into<Error>;
+
+    /// Records `id` as in flight for `ttl`, unless it is already recorded.
+    ///
+    /// Returns the existing entry if the ID was already recorded, and `None` otherwise.
+    fn begin(
+        &self,
+        id: &str,
+        ttl: Duration,
+    ) -> LocalBoxFuture<'static, Result<Option<DedupEntry>, Self::Error>>;
+
+    /// Records that the request with `id` was handled with `status`, replacing its in-flight entry
+    /// and remembering it for `ttl`.
+    fn complete(
+        &self,
+        id: &str,
+        status: StatusCode,
+        ttl: Duration,
+    ) -> LocalBoxFuture<'static, Result<(), Self::Error>>;
+
+    /// Removes `id` so that a retry of the request is handled again.
+    fn remove(&self, id: &str) -> LocalBoxFuture<'static, Result<(), Self::Error>>;
+}
+
+/// Middleware for handling retried requests only once.
+///
+/// Devices that deliver messages at least once—often through gateways that retry aggressively—tag
+/// every message with an ID in a request header (`x-message-id` by default). The first request
+/// with an ID is passed to the wrapped service and its response status is recorded in the
+/// [`DedupStore`]. Duplicates received within the [TTL](Self::ttl) are not passed on but answered
+/// with the recorded status and an empty body, so that the device stops retrying without the
+/// message being processed twice.
+///
+/// Duplicates received while the original request is still being handled receive a
+/// `409 Conflict` response. In-flight IDs are only remembered for the
+/// [in-flight TTL](Self::in_flight_ttl), so that a request whose handling was interrupted, e.g.
+/// by a crash, can be retried. If the wrapped service returns an error or a server error response,
+/// the ID is forgotten so that the retry is handled normally. Requests without the header are
+/// always passed on.
+///
+/// IDs are not scoped by path or method, so devices must not reuse them across endpoints.
+///
+/// # Examples
+/// ```
+/// use actix_web::{
+///     middleware::{Deduplicate, MemoryDedupStore},
+///     web, App, HttpResponse,
+/// };
+///
+/// // create outside of the `HttpServer::new` closure to share with all workers
+/// let store = MemoryDedupStore::new();
+///
+/// let app = App::new()
+///     .wrap(Deduplicate::new(store.clone()).header("x-reading-id"))
+///     .route("/vitals", web::post().to(HttpResponse::Accepted));
+/// ```
+pub struct Deduplicate<S> {
+    store: Rc<S>,
+    header: HeaderName,
+    ttl: Duration,
+    in_flight_ttl: Duration,
+}
+
+impl<S: DedupStore> Deduplicate<S> {
+    /// Constructs a middleware that records message IDs in `store`.
+    pub fn new(store: S) -> Self {
+        Self {
+            store: Rc::new(store),
+            header: DEFAULT_HEADER,
+            ttl: DEFAULT_TTL,
+            in_flight_ttl: DEFAULT_IN_FLIGHT_TTL,
+        }
+    }
+
+    /// Sets the name of the header holding the message ID.
+    ///
+    /// Defaults to `x-message-id`.
+    ///
+    /// # Panics
+    /// Panics if `header` is not a valid header name.
+    pub fn header(mut self, header: &str) -> Self {
+        self.header = HeaderName::try_from(header).expect("Invalid message ID header name");
+        self
+    }
+
+    /// Sets the time for which message IDs are remembered after their request was handled.
+    ///
+    /// Defaults to 24 hours.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets the time for which message IDs are remembered while their request is being handled.
+    ///
+    /// This should be longer than requests take to handle; duplicates received after it has
+    /// elapsed are handled concurrently with the original. Defaults to 60 seconds.
+    pub fn in_flight_ttl(mut self, ttl: Duration) -> Self {
+        self.in_flight_ttl = ttl;
+        self
+    }
+}
+
+impl<S> Clone for Deduplicate<S> {
+    fn clone(&self) -> Self {
+        Self {
+            store: Rc::clone(&self.store),
+            header: self.header.clone(),
+            ttl: self.ttl,
+            in_flight_ttl: self.in_flight_ttl,
+        }
+    }
+}
+
+impl<S> fmt::Debug for Deduplicate<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Deduplicate")
+            .field("header", &self.header)
+            .field("ttl", &self.ttl)
+            .field("in_flight_ttl", &self.in_flight_ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S, B, D> Transform<S, ServiceRequest> for Deduplicate<D>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+    D: DedupStore,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = DeduplicateMiddleware<S, D>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(DeduplicateMiddleware {
+            service: Rc::new(service),
+            store: Rc::clone(&self.store),
+            header: self.header.clone(),
+            ttl: self.ttl,
+            in_flight_ttl: self.in_flight_ttl,
+        }))
+    }
+}
+
+/// Deduplication middleware service.
+pub struct DeduplicateMiddleware<S, D> {
+    service: Rc<S>,
+    store: Rc<D>,
+    header: HeaderName,
+    ttl: Duration,
+    in_flight_ttl: Duration,
+}
+
+impl<S, B, D> Service<ServiceRequest> for DeduplicateMiddleware<S, D>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+    D: DedupStore,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let store = Rc::clone(&self.store);
+        let ttl = self.ttl;
+        let in_flight_ttl = self.in_flight_ttl;
+
+        let id = req
+            .headers()
+            .get(&self.header)
+            .and_then(|id| id.to_str().ok())
+            .filter(|id| !id.is_empty())
+            .map(str::to_owned);
+
+        Box::pin(async move {
+            let Some(id) = id else {
+                return service
+                    .call(req)
+                    .await
+                    .map(ServiceResponse::map_into_left_body);
+            };
+
+            match store.begin(&id, in_flight_ttl).await.map_err(Into::into)? {
+                Some(DedupEntry::Completed(status)) => {
+                    log::debug!("Answering duplicate of message {id} with recorded status");
+                    let res = HttpResponse::new(status);
+                    return Ok(req.into_response(res).map_into_right_body());
+                }
+
+                Some(DedupEntry::InFlight) => {
+                    let res = HttpResponse::new(StatusCode::CONFLICT);
+                    return Ok(req.into_response(res).map_into_right_body());
+                }
+
+                None => {}
+            }
+
+            let res = service.call(req).await;
+
+            let recorded = match &res {
+                Ok(res) if !res.status().is_server_error() => {
+                    store.complete(&id, res.status(), ttl).await
+                }
+                _ => store.remove(&id).await,
+            };
+
+            // the request has been handled; failing now would only cause the retry it prevents
+            if let Err(err) = recorded {
+                let err: Error = err.into();
+                log::error!("Failed to record handling of message {id}: {err}");
+            }
+
+            res.map(ServiceResponse::map_into_left_body)
+        })
+    }
+}
+
+/// In-memory [`DedupStore`].
+///
+/// Clones share their entries, so one store can be created outside of the `HttpServer::new`
+/// closure and cloned into the app of each worker. Entries are lost when the process exits, and
+/// expired entries are purged as new ones are added.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryDedupStore {
+    inner: Arc<Mutex<MemoryEntries>>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryEntries {
+    entries: HashMap<String, (DedupEntry, Instant)>,
+    purge_len: usize,
+}
+
+impl MemoryDedupStore {
+    /// Constructs an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_entries<T>(&self, f: impl FnOnce(&mut MemoryEntries) -> T) -> T {
+        let mut inner = self.inner.lock().unwrap_or_else(|err| err.into_inner());
+        f(&mut inner)
+    }
+}
+
+impl DedupStore for MemoryDedupStore {
+    type Error = Infallible;
+
+    fn begin(
+        &self,
+        id: &str,
+        ttl: Duration,
+    ) -> LocalBoxFuture<'static, Result<Option<DedupEntry>, Self::Error>> {
+        let now = Instant::now();
+
+        let existing = self.with_entries(|inner| {
+            if inner.entries.len() >= inner.purge_len.max(MIN_PURGE_LEN) {
+                inner.entries.retain(|_, (_, expiry)| *expiry > now);
+                inner.purge_len = inner.entries.len() * 2;
+            }
+
+            match inner.entries.get(id) {
+                Some((entry, expiry)) if *expiry > now => Some(*entry),
+                _ => {
+                    let entry = (DedupEntry::InFlight, now + ttl);
+                    inner.entries.insert(id.to_owned(), entry);
+                    None
+                }
+            }
+        });
+
+        Box::pin(ready(Ok(existing)))
+    }
+
+    fn complete(
+        &self,
+        id: &str,
+        status: StatusCode,
+        ttl: Duration,
+    ) -> LocalBoxFuture<'static, Result<(), Self::Error>> {
+        let entry = (DedupEntry::Completed(status), Instant::now() + ttl);
+        self.with_entries(|inner| inner.entries.insert(id.to_owned(), entry));
+        Box::pin(ready(Ok(())))
+    }
+
+    fn remove(&self, id: &str) -> LocalBoxFuture<'static, Result<(), Self::Error>> {
+        self.with_entries(|inner| inner.entries.remove(id));
+        Box::pin(ready(Ok(())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::{
+        error,
+        test::{self, TestRequest},
+        web, App,
+    };
+
+    #[actix_rt::test]
+    async fn duplicates_get_recorded_status() {
+        let calls = Rc::new(Cell::new(0));
+
+        let app = test::init_service({
+            let calls = Rc::clone(&calls);
+
+            App::new()
+                .wrap(Deduplicate::new(MemoryDedupStore::new()))
+                .default_service(web::to(move || {
+                    calls.set(calls.get() + 1);
+                    HttpResponse::Created().body("stored")
+                }))
+        })
+        .await;
+
+        let req = || TestRequest::post().insert_header(("x-message-id", "reading-1"));
+
+        let res = test::call_service(&app, req().to_request()).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(test::read_body(res).await, "stored");
+
+        let res = test::call_service(&app, req().to_request()).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert!(test::read_body(res).await.is_empty());
+        assert_eq!(calls.get(), 1);
+
+        // other and missing IDs are handled
+        let req = TestRequest::post().insert_header(("x-message-id", "reading-2"));
+        test::call_service(&app, req.to_request()).await;
+        test::call_service(&app, TestRequest::post().to_request()).await;
+        test::call_service(&app, TestRequest::post().to_request()).await;
+        assert_eq!(calls.get(), 4);
+    }
+
+    #[actix_rt::test]
+    async fn failures_are_retried() {
+        let calls = Rc::new(Cell::new(0));
+
+        let app = test::init_service({
+            let calls = Rc::clone(&calls);
+
+            App::new()
+                .wrap(Deduplicate::new(MemoryDedupStore::new()).header("x-reading-id"))
+                .default_service(web::to(move || {
+                    calls.set(calls.get() + 1);
+
+                    let res = match calls.get() {
+                        1 => Err(error::ErrorInternalServerError("database unavailable")),
+                        2 => Ok(HttpResponse::ServiceUnavailable().finish()),
+                        _ => Ok(HttpResponse::Accepted().finish()),
+                    };
+
+                    ready(res)
+                }))
+        })
+        .await;
+
+        let req = || {
+            TestRequest::post()
+                .insert_header(("x-reading-id", "reading-1"))
+                .to_request()
+        };
+
+        for status in [
+            StatusCode::INTERNAL_SERVER_ERROR,
+            StatusCode::SERVICE_UNAVAILABLE,
+            StatusCode::ACCEPTED,
+            StatusCode::ACCEPTED,
+        ] {
+            let res = test::call_service(&app, req()).await;
+            assert_eq!(res.status(), status);
+        }
+
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[actix_rt::test]
+    async fn memory_store() {
+        let store = MemoryDedupStore::new();
+        let ttl = Duration::from_secs(60);
+
+        assert_eq!(store.begin("a", ttl).await, Ok(None));
+        assert_eq!(
+            store.clone().begin("a", ttl).await,
+            Ok(Some(DedupEntry::InFlight))
+        );
+
+        store.complete("a", StatusCode::OK, ttl).await.unwrap();
+        assert_eq!(
+            store.begin("a", ttl).await,
+            Ok(Some(DedupEntry::Completed(StatusCode::OK)))
+        );
+
+        store.remove("a").await.unwrap();
+        assert_eq!(store.begin("a", ttl).await, Ok(None));
+
+        // expired entries are replaced
+        store
+            .complete("b", StatusCode::OK, Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(store.begin("b", ttl).await, Ok(None));
+    }
+}
@@ -0,0 +1,544 @@
+//! For middleware documentation, see [`ErrorReporting`].
+
+use std::{
+    error::Error as StdError,
+    fmt,
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
+
+use actix_rt::time::{timeout, Instant};
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use futures_core::future::LocalBoxFuture;
+use tokio::sync::mpsc;
+
+use crate::{
+    http::{Method, StatusCode, Uri},
+    middleware::LogContext,
+    service::{ServiceRequest, ServiceResponse},
+    Error, HttpMessage as _, HttpRequest, RedactionPolicy,
+};
+
+/// Receives batches of error reports from the [`ErrorReporting`] middleware, e.g., to forward
+/// them to an error tracking service.
+///
+/// # Examples
+/// ```
+/// use actix_web::middleware::{ErrorReport, ErrorReporter};
+/// use futures_core::future::LocalBoxFuture;
+///
+/// struct StderrReporter;
+///
+/// impl ErrorReporter for StderrReporter {
+///     fn report(&self, batch: Vec<ErrorReport>) -> LocalBoxFuture<'static, ()> {
+///         Box::pin(async move {
+///             for report in batch {
+///                 eprintln!("{} {}: {}", report.method(), report.path(), report.message());
+///             }
+///         })
+///     }
+/// }
+/// ```
+pub trait ErrorReporter: 'static {
+    /// Sends a batch of reports.
+    ///
+    /// Batches are sent one at a time; the next batch is collected while this future runs.
+    fn report(&self, batch: Vec<ErrorReport>) -> LocalBoxFuture<'static, ()>;
+}
+
+/// An error that occurred while handling a request. See [`ErrorReporting`].
+///
+/// Header values and query parameters are redacted according to the app's [`RedactionPolicy`].
+#[derive(Debug, Clone)]
+pub struct ErrorReport {
+    status: StatusCode,
+    chain: Vec<String>,
+    details: String,
+    method: Method,
+    path: String,
+    query: Option<String>,
+    route: Option<String>,
+    request_id: Option<String>,
+    headers: Vec<(String, String)>,
+    timestamp: SystemTime,
+}
+
+impl ErrorReport {
+    fn new(err: &Error, status: StatusCode, method: Method, uri: &Uri) -> Self {
+        let mut chain = vec![err.to_string()];
+
+        if let Some(err) = err.as_error::<Box<dyn StdError + 'static>>() {
+            let mut source = err.source();
+
+            while let Some(err) = source {
+                chain.push(err.to_string());
+                source = err.source();
+            }
+        }
+
+        Self {
+            status,
+            chain,
+            details: format!("{:?}", err),
+            method,
+            path: uri.path().to_owned(),
+            query: None,
+            route: None,
+            request_id: None,
+            headers: Vec::new(),
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    fn with_request(mut self, req: &HttpRequest) -> Self {
+        let policy = RedactionPolicy::for_request(req);
+
+        if !req.query_string().is_empty() {
+            self.query = Some(policy.redact_query(req.query_string()).into_owned());
+        }
+
+        self.route = req.match_pattern();
+        self.headers = req
+            .headers()
+            .iter()
+            .map(|(name, val)| {
+                let val = policy.redact_header(name, val.to_str().unwrap_or("<non-utf8>"));
+                (name.as_str().to_owned(), val.to_owned())
+            })
+            .collect();
+
+        self
+    }
+
+    /// Returns the status code of the error response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Returns the error message.
+    pub fn message(&self) -> &str {
+        &self.chain[0]
+    }
+
+    /// Returns the error message followed by the messages of its sources, if any.
+    ///
+    /// Sources are available for errors converted from `Box<dyn std::error::Error>`.
+    pub fn chain(&self) -> &[String] {
+        &self.chain
+    }
+
+    /// Returns the `Debug` representation of the error.
+    pub fn details(&self) -> &str {
+        &self.details
+    }
+
+    /// Returns the request method.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// Returns the request path.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the redacted query string, if the request had one.
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    /// Returns the matched route pattern, if any.
+    pub fn route(&self) -> Option<&str> {
+        self.route.as_deref()
+    }
+
+    /// Returns the `request_id` field of the request's [`LogContext`], if set.
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// Returns the redacted request headers.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// Returns the time the error was captured.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+}
+
+/// Middleware for capturing request errors and sending them to an [`ErrorReporter`].
+///
+/// Errors returned by handlers and inner middleware are captured once, without wrapping individual
+/// handlers. By default, only errors resulting in a `5xx` response are reported; see
+/// [`client_errors()`](Self::client_errors).
+///
+/// Reports are queued and sent in batches by a background task on each worker. A batch is sent
+/// once it reaches the [batch size](Self::batch_size) or the [flush
+/// interval](Self::flush_interval) has elapsed since its first report. If the queue is full
+/// because the reporter cannot keep up, further reports are dropped.
+///
+/// Reports include the request method, path, matched route, headers, query string, and the
+/// `request_id` of the request's [`LogContext`]. Headers and query parameters are redacted
+/// according to the app's [`RedactionPolicy`].
+///
+/// # Examples
+/// ```
+/// # use actix_web::middleware::{ErrorReport, ErrorReporter};
+/// # use futures_core::future::LocalBoxFuture;
+/// # struct StderrReporter;
+/// # impl ErrorReporter for StderrReporter {
+/// #     fn report(&self, _: Vec<ErrorReport>) -> LocalBoxFuture<'static, ()> {
+/// #         Box::pin(async {})
+/// #     }
+/// # }
+/// use actix_web::{middleware::ErrorReporting, App};
+///
+/// let app = App::new().wrap(ErrorReporting::new(StderrReporter).batch_size(50));
+/// ```
+pub struct ErrorReporting<R> {
+    reporter: Rc<R>,
+    batch_size: usize,
+    flush_interval: Duration,
+    queue_capacity: usize,
+    client_errors: bool,
+}
+
+impl<R: ErrorReporter> ErrorReporting<R> {
+    /// Constructs a middleware that sends reports to `reporter`.
+    pub fn new(reporter: R) -> Self {
+        Self {
+            reporter: Rc::new(reporter),
+            batch_size: 32,
+            flush_interval: Duration::from_secs(1),
+            queue_capacity: 1024,
+            client_errors: false,
+        }
+    }
+
+    /// Sets the maximum number of reports per batch.
+    ///
+    /// Defaults to 32.
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batch_size = size.max(1);
+        self
+    }
+
+    /// Sets the maximum time a report waits for its batch to fill up.
+    ///
+    /// Defaults to 1 second.
+    pub fn flush_interval(mut self, dur: Duration) -> Self {
+        self.flush_interval = dur;
+        self
+    }
+
+    /// Sets the maximum number of reports waiting to be sent, per worker.
+    ///
+    /// Defaults to 1024.
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = capacity.max(1);
+        self
+    }
+
+    /// Sets whether errors resulting in a `4xx` response are also reported.
+    ///
+    /// Defaults to `false`.
+    pub fn client_errors(mut self, enabled: bool) -> Self {
+        self.client_errors = enabled;
+        self
+    }
+}
+
+impl<R> Clone for ErrorReporting<R> {
+    fn clone(&self) -> Self {
+        Self {
+            reporter: Rc::clone(&self.reporter),
+            batch_size: self.batch_size,
+            flush_interval: self.flush_interval,
+            queue_capacity: self.queue_capacity,
+            client_errors: self.client_errors,
+        }
+    }
+}
+
+impl<R> fmt::Debug for ErrorReporting<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorReporting")
+            .field("batch_size", &self.batch_size)
+            .field("flush_interval", &self.flush_interval)
+            .field("queue_capacity", &self.queue_capacity)
+            .field("client_errors", &self.client_errors)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S, B, R> Transform<S, ServiceRequest> for ErrorReporting<R>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+    R: ErrorReporter,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = ErrorReportingMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let (tx, rx) = mpsc::channel(self.queue_capacity);
+
+        actix_rt::spawn(dispatch_reports(
+            Rc::clone(&self.reporter),
+            rx,
+            self.batch_size,
+            self.flush_interval,
+        ));
+
+        ready(Ok(ErrorReportingMiddleware {
+            service: Rc::new(service),
+            queue: tx,
+            client_errors: self.client_errors,
+        }))
+    }
+}
+
+/// Error reporting middleware service.
+pub struct ErrorReportingMiddleware<S> {
+    service: Rc<S>,
+    queue: mpsc::Sender<ErrorReport>,
+    client_errors: bool,
+}
+
+impl<S, B> Service<ServiceRequest> for ErrorReportingMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let queue = self.queue.clone();
+        let client_errors = self.client_errors;
+
+        Box::pin(async move {
+            // the request is gone if an error is returned, rather than attached to a response
+            let method = req.method().clone();
+            let uri = req.uri().clone();
+            let context = req.extensions().get::<LogContext>().cloned();
+
+            let should_report = |status: StatusCode| {
+                status.is_server_error() || (client_errors && status.is_client_error())
+            };
+
+            let res = service.call(req).await;
+
+            let report = match res {
+                Ok(ref res) => res
+                    .response()
+                    .error()
+                    .filter(|_| should_report(res.status()))
+                    .map(|err| {
+                        ErrorReport::new(err, res.status(), method, &uri)
+                            .with_request(res.request())
+                    }),
+
+                Err(ref err) => {
+                    let status = err.as_response_error().status_code();
+                    should_report(status).then(|| ErrorReport::new(err, status, method, &uri))
+                }
+            };
+
+            if let Some(mut report) = report {
+                let context = context.or_else(|| match res {
+                    Ok(ref res) => res.request().extensions().get::<LogContext>().cloned(),
+                    Err(_) => None,
+                });
+                report.request_id = context.and_then(|ctx| ctx.get("request_id"));
+
+                if let Err(mpsc::error::TrySendError::Full(_)) = queue.try_send(report) {
+                    log::warn!("Error report queue is full; dropping report");
+                }
+            }
+
+            res
+        })
+    }
+}
+
+/// Collects queued reports into batches and sends them until the middleware is dropped.
+async fn dispatch_reports<R: ErrorReporter>(
+    reporter: Rc<R>,
+    mut queue: mpsc::Receiver<ErrorReport>,
+    batch_size: usize,
+    flush_interval: Duration,
+) {
+    while let Some(report) = queue.recv().await {
+        let deadline = Instant::now() + flush_interval;
+        let mut batch = vec![report];
+
+        while batch.len() < batch_size {
+            match timeout(
+                deadline.saturating_duration_since(Instant::now()),
+                queue.recv(),
+            )
+            .await
+            {
+                Ok(Some(report)) => batch.push(report),
+
+                // flush interval elapsed or middleware dropped
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        reporter.report(batch).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, io};
+
+    use super::*;
+    use crate::{
+        error,
+        middleware::Logger,
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    #[derive(Clone, Default)]
+    struct Recorder {
+        batches: Rc<RefCell<Vec<Vec<ErrorReport>>>>,
+    }
+
+    impl ErrorReporter for Recorder {
+        fn report(&self, batch: Vec<ErrorReport>) -> LocalBoxFuture<'static, ()> {
+            self.batches.borrow_mut().push(batch);
+            Box::pin(async {})
+        }
+    }
+
+    async fn failing(path: web::Path<u16>) -> Result<HttpResponse, Error> {
+        let status = StatusCode::from_u16(path.into_inner()).unwrap();
+
+        let source: Box<dyn StdError> = Box::new(io::Error::other("disk unavailable"));
+        let err = error::InternalError::new(format!("{source}"), status);
+
+        if status == StatusCode::BAD_GATEWAY {
+            let err: Box<dyn StdError> = Box::new(WrappedError(source));
+            return Err(err.into());
+        }
+
+        Err(err.into())
+    }
+
+    #[derive(Debug)]
+    struct WrappedError(Box<dyn StdError>);
+
+    impl fmt::Display for WrappedError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("upstream failed")
+        }
+    }
+
+    impl StdError for WrappedError {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            Some(self.0.as_ref())
+        }
+    }
+
+    #[actix_rt::test]
+    async fn reports_server_errors_in_batches() {
+        let recorder = Recorder::default();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(RedactionPolicy::new().query_param("ssn"))
+                .wrap(
+                    ErrorReporting::new(recorder.clone())
+                        .batch_size(2)
+                        .flush_interval(Duration::from_millis(50)),
+                )
+                .wrap(Logger::default())
+                .route("/fail/{status}", web::get().to(failing))
+                .route("/ok", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        for uri in [
+            "/fail/500?ssn=1&a=b",
+            "/fail/404",
+            "/ok",
+            "/fail/502",
+            "/fail/503",
+        ] {
+            let req = TestRequest::get()
+                .uri(uri)
+                .insert_header(("authorization", "Bearer secret"))
+                .insert_header(("x-request-id", "req-1"))
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+
+        actix_rt::time::sleep(Duration::from_millis(100)).await;
+
+        let batches = recorder.batches.borrow();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].len(), 2);
+        assert_eq!(batches[1].len(), 1);
+
+        let report = &batches[0][0];
+        assert_eq!(report.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(report.message(), "disk unavailable");
+        assert_eq!(*report.method(), Method::GET);
+        assert_eq!(report.path(), "/fail/500");
+        assert_eq!(report.query(), Some("ssn=*redacted*&a=b"));
+        assert_eq!(report.route(), Some("/fail/{status}"));
+        assert_eq!(report.request_id(), Some("req-1"));
+        assert!(report
+            .headers()
+            .contains(&("authorization".to_owned(), "*redacted*".to_owned())));
+
+        let report = &batches[0][1];
+        assert_eq!(report.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            report.chain(),
+            ["upstream failed".to_owned(), "disk unavailable".to_owned()]
+        );
+
+        assert_eq!(batches[1][0].path(), "/fail/503");
+    }
+
+    #[actix_rt::test]
+    async fn client_errors() {
+        let recorder = Recorder::default();
+
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    ErrorReporting::new(recorder.clone())
+                        .client_errors(true)
+                        .flush_interval(Duration::from_millis(10)),
+                )
+                .route("/fail/{status}", web::get().to(failing)),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/fail/404").to_request();
+        test::call_service(&app, req).await;
+
+        actix_rt::time::sleep(Duration::from_millis(50)).await;
+
+        let batches = recorder.batches.borrow();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0][0].status(), StatusCode::NOT_FOUND);
+        assert_eq!(batches[0][0].request_id(), None);
+    }
+}
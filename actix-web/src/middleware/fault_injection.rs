@@ -0,0 +1,457 @@
+//! For middleware documentation, see [`FaultInjection`].
+
+use std::{
+    error::Error as StdError,
+    io,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_rt::time::sleep;
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use bytes::Bytes;
+use futures_core::{future::LocalBoxFuture, ready};
+use pin_project_lite::pin_project;
+
+use crate::{
+    body::{BodySize, BoxBody, EitherBody, MessageBody},
+    http::{
+        header::{HeaderName, HeaderValue, TryIntoHeaderPair},
+        StatusCode,
+    },
+    service::{ServiceRequest, ServiceResponse},
+    Error, HttpResponse,
+};
+
+/// Middleware for injecting faults into responses, e.g., to test the resilience of clients in a
+/// staging environment.
+///
+/// Each [`Fault`] is triggered for requests matching all of its conditions: an optional path
+/// prefix, an optional request header, and a probability. Latency faults are cumulative; of the
+/// remaining faults, only the first one triggered is applied. To limit faults to a single route,
+/// either use a path condition or wrap the route's resource or scope with this middleware.
+///
+/// Requires the `fault-injection` feature, which should not be enabled in production builds.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use actix_web::{
+///     http::StatusCode,
+///     middleware::{Fault, FaultInjection},
+///     App,
+/// };
+///
+/// let app = App::new().wrap(
+///     FaultInjection::new()
+///         .fault(Fault::latency(Duration::from_millis(250)).path("/api").probability(0.1))
+///         .fault(Fault::error(StatusCode::SERVICE_UNAVAILABLE).header(("x-fault", "unavailable")))
+///         .fault(Fault::truncate_body(64).header(("x-fault", "truncate")))
+///         .fault(Fault::drop_connection().header(("x-fault", "drop"))),
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FaultInjection {
+    faults: Vec<Fault>,
+}
+
+impl FaultInjection {
+    /// Constructs a `FaultInjection` middleware without any faults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a fault.
+    pub fn fault(mut self, fault: Fault) -> Self {
+        self.faults.push(fault);
+        self
+    }
+}
+
+/// A fault injected by the [`FaultInjection`] middleware.
+///
+/// By default, a fault is triggered for every request.
+#[derive(Debug, Clone)]
+pub struct Fault {
+    kind: FaultKind,
+    path: Option<String>,
+    header: Option<(HeaderName, HeaderValue)>,
+    probability: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FaultKind {
+    Latency(Duration),
+    Error(StatusCode),
+    Truncate(usize),
+    Drop,
+}
+
+impl Fault {
+    fn new(kind: FaultKind) -> Self {
+        Self {
+            kind,
+            path: None,
+            header: None,
+            probability: 1.0,
+        }
+    }
+
+    /// Constructs a fault that delays the request by `delay` before it is handled.
+    pub fn latency(delay: Duration) -> Self {
+        Self::new(FaultKind::Latency(delay))
+    }
+
+    /// Constructs a fault that responds with an empty response with `status` instead of calling
+    /// the service.
+    pub fn error(status: StatusCode) -> Self {
+        Self::new(FaultKind::Error(status))
+    }
+
+    /// Constructs a fault that cuts off the response body after `len` bytes.
+    ///
+    /// The response keeps its original `Content-Length`, if any. Once the truncated body has been
+    /// sent, the connection is closed (HTTP/1) or the stream is reset (HTTP/2).
+    pub fn truncate_body(len: usize) -> Self {
+        Self::new(FaultKind::Truncate(len))
+    }
+
+    /// Constructs a fault that closes the connection (HTTP/1) or resets the stream (HTTP/2)
+    /// without sending a response. The service is not called.
+    pub fn drop_connection() -> Self {
+        Self::new(FaultKind::Drop)
+    }
+
+    /// Only triggers the fault for requests whose path starts with `prefix`.
+    pub fn path(mut self, prefix: impl Into<String>) -> Self {
+        self.path = Some(prefix.into());
+        self
+    }
+
+    /// Only triggers the fault for requests containing `header` with the given value.
+    ///
+    /// # Panics
+    /// Panics when resolved header name or value is invalid.
+    pub fn header(mut self, header: impl TryIntoHeaderPair) -> Self {
+        match header.try_into_pair() {
+            Ok(pair) => self.header = Some(pair),
+            Err(err) => panic!("Invalid header: {}", err.into()),
+        }
+
+        self
+    }
+
+    /// Sets the probability, between 0.0 and 1.0, of triggering the fault for a matching request.
+    ///
+    /// # Panics
+    /// Panics if `probability` is not between 0.0 and 1.0.
+    pub fn probability(mut self, probability: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "fault probability must be between 0.0 and 1.0"
+        );
+
+        self.probability = probability;
+        self
+    }
+
+    fn is_triggered(&self, req: &ServiceRequest) -> bool {
+        if let Some(ref prefix) = self.path {
+            if !req.path().starts_with(prefix.as_str()) {
+                return false;
+            }
+        }
+
+        if let Some((ref name, ref value)) = self.header {
+            if !req.headers().get_all(name).any(|val| val == value) {
+                return false;
+            }
+        }
+
+        self.probability >= 1.0 || rand::random::<f64>() < self.probability
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for FaultInjection
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = FaultInjectionMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(FaultInjectionMiddleware {
+            service: Rc::new(service),
+            faults: Rc::from(self.faults.as_slice()),
+        }))
+    }
+}
+
+/// Fault injection middleware service.
+pub struct FaultInjectionMiddleware<S> {
+    service: Rc<S>,
+    faults: Rc<[Fault]>,
+}
+
+impl<S, B> Service<ServiceRequest> for FaultInjectionMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let mut delay = Duration::ZERO;
+        let mut fault = None;
+
+        for triggered in self.faults.iter().filter(|fault| fault.is_triggered(&req)) {
+            match triggered.kind {
+                FaultKind::Latency(dur) => delay += dur,
+                kind if fault.is_none() => fault = Some(kind),
+                _ => {}
+            }
+        }
+
+        if !delay.is_zero() || fault.is_some() {
+            log::debug!(
+                "injecting faults into {} {}: delay {:?}, {:?}",
+                req.method(),
+                req.path(),
+                delay,
+                fault
+            );
+        }
+
+        let service = Rc::clone(&self.service);
+
+        Box::pin(async move {
+            if !delay.is_zero() {
+                sleep(delay).await;
+            }
+
+            match fault {
+                Some(FaultKind::Error(status)) => {
+                    let res = HttpResponse::new(status);
+                    Ok(req.into_response(res).map_into_right_body())
+                }
+
+                Some(FaultKind::Drop) => {
+                    let res = HttpResponse::new(StatusCode::OK).set_body(BoxBody::new(DroppedBody));
+                    Ok(req.into_response(res).map_into_right_body())
+                }
+
+                Some(FaultKind::Truncate(len)) => {
+                    let res = service.call(req).await?;
+                    Ok(res.map_body(|_, body| {
+                        EitherBody::right(BoxBody::new(TruncatedBody {
+                            body,
+                            remaining: len,
+                            cut: false,
+                        }))
+                    }))
+                }
+
+                Some(FaultKind::Latency(_)) | None => service
+                    .call(req)
+                    .await
+                    .map(ServiceResponse::map_into_left_body),
+            }
+        })
+    }
+}
+
+fn fault_error(msg: &str) -> Box<dyn StdError> {
+    Box::new(io::Error::other(format!("fault injection: {msg}")))
+}
+
+/// Body that fails immediately, causing the connection to be dropped.
+struct DroppedBody;
+
+impl MessageBody for DroppedBody {
+    type Error = Box<dyn StdError>;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        Poll::Ready(Some(Err(fault_error("connection dropped"))))
+    }
+}
+
+pin_project! {
+    /// Body that fails after `remaining` bytes of the inner body have been yielded.
+    struct TruncatedBody<B> {
+        #[pin]
+        body: B,
+        remaining: usize,
+        cut: bool,
+    }
+}
+
+impl<B: MessageBody> MessageBody for TruncatedBody<B> {
+    type Error = Box<dyn StdError>;
+
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.project();
+
+        if *this.cut {
+            return Poll::Ready(Some(Err(fault_error("response body truncated"))));
+        }
+
+        if *this.remaining == 0 {
+            // yield once so the dispatcher flushes the truncated body before the connection fails
+            *this.cut = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        match ready!(this.body.poll_next(cx)) {
+            Some(Ok(mut chunk)) => {
+                chunk.truncate(*this.remaining);
+                *this.remaining -= chunk.len();
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Some(Err(err)) => Poll::Ready(Some(Err(err.into()))),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use futures_util::future::poll_fn;
+
+    use super::*;
+    use crate::{
+        test::{call_service, init_service, read_body, TestRequest},
+        web, App,
+    };
+
+    async fn read_until_error<B: MessageBody>(body: B) -> (Vec<u8>, bool) {
+        let mut body = Box::pin(body);
+        let mut buf = Vec::new();
+
+        while let Some(chunk) = poll_fn(|cx| body.as_mut().poll_next(cx)).await {
+            match chunk {
+                Ok(chunk) => buf.extend_from_slice(&chunk),
+                Err(_) => return (buf, true),
+            }
+        }
+
+        (buf, false)
+    }
+
+    #[actix_rt::test]
+    async fn triggers() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    FaultInjection::new()
+                        .fault(Fault::error(StatusCode::BAD_GATEWAY).path("/api"))
+                        .fault(
+                            Fault::error(StatusCode::SERVICE_UNAVAILABLE)
+                                .header(("x-fault", "unavailable")),
+                        )
+                        .fault(Fault::error(StatusCode::IM_A_TEAPOT).probability(0.0)),
+                )
+                .default_service(web::to(|| async { "ok" })),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/api/orders").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+
+        let req = TestRequest::get()
+            .uri("/other")
+            .insert_header(("x-fault", "unavailable"))
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let req = TestRequest::get()
+            .uri("/other")
+            .insert_header(("x-fault", "other"))
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, "ok");
+    }
+
+    #[actix_rt::test]
+    async fn latency() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    FaultInjection::new()
+                        .fault(Fault::latency(Duration::from_millis(50)))
+                        .fault(Fault::latency(Duration::from_millis(50)).path("/slow")),
+                )
+                .default_service(web::to(|| async { "ok" })),
+        )
+        .await;
+
+        let start = Instant::now();
+        let req = TestRequest::get().uri("/slow").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[actix_rt::test]
+    async fn truncate_and_drop() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    FaultInjection::new()
+                        .fault(Fault::truncate_body(5).path("/truncate"))
+                        .fault(Fault::drop_connection().path("/drop")),
+                )
+                .default_service(web::to(|| async { "hello world" })),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/truncate").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.response().body().size(), BodySize::Sized(11));
+        assert_eq!(
+            read_until_error(res.into_body()).await,
+            (b"hello".to_vec(), true)
+        );
+
+        let req = TestRequest::get().uri("/drop").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_until_error(res.into_body()).await, (Vec::new(), true));
+    }
+
+    #[test]
+    #[should_panic = "fault probability"]
+    fn invalid_probability() {
+        let _ = Fault::drop_connection().probability(1.5);
+    }
+}
@@ -0,0 +1,310 @@
+//! For middleware documentation, see [`Limiter`].
+
+use std::{
+    cell::Cell,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use actix_http::error::PayloadError;
+use actix_service::{Service, Transform};
+use actix_utils::future::{ok, ready, Either, Ready};
+use bytes::Bytes;
+use futures_core::{ready, Stream};
+use pin_project_lite::pin_project;
+use tokio::sync::watch;
+
+use crate::{
+    body::EitherBody,
+    dev::Payload,
+    http::{
+        header::{self, HeaderValue},
+        StatusCode,
+    },
+    service::{ServiceRequest, ServiceResponse},
+    settings::{RuntimeLimits, SettingsHandle},
+    Error, HttpMessage as _, HttpResponse,
+};
+
+/// Middleware for enforcing [`RuntimeLimits`] that can change while the server is running.
+///
+/// The limits are read from a [`SettingsHandle`] on every request, so updates take effect
+/// immediately without restarting the server:
+/// - while maintenance mode is enabled, requests are rejected with `503 Service Unavailable`;
+/// - requests over the rate limit are rejected with `429 Too Many Requests`. The limit is counted
+///   per worker over one second windows;
+/// - requests with a body larger than the body limit are rejected with `413 Payload Too Large`
+///   when they declare a `Content-Length`. Otherwise, the request payload yields
+///   [`PayloadError::Overflow`] once the limit is exceeded.
+///
+/// # Examples
+/// ```
+/// use actix_web::{middleware::Limiter, App, RuntimeLimits, SettingsHandle};
+///
+/// let handle = SettingsHandle::new(RuntimeLimits::new());
+/// let app = App::new().wrap(Limiter::new(&handle));
+///
+/// handle.update(|limits| limits.rate_limit = Some(100));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Limiter {
+    limits: watch::Receiver<RuntimeLimits>,
+}
+
+impl Limiter {
+    /// Constructs a `Limiter` middleware that follows the limits published through `handle`.
+    pub fn new(handle: &SettingsHandle) -> Self {
+        Self {
+            limits: handle.subscribe(),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Limiter
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = LimiterMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(LimiterMiddleware {
+            service,
+            limits: self.limits.clone(),
+            window: Cell::new(None),
+        }))
+    }
+}
+
+/// Limiter middleware service.
+pub struct LimiterMiddleware<S> {
+    service: S,
+    limits: watch::Receiver<RuntimeLimits>,
+    // start of the current rate limiting window and number of requests accepted during it
+    window: Cell<Option<(Instant, u32)>>,
+}
+
+impl<S> LimiterMiddleware<S> {
+    /// Counts a request against the rate limit, returning false if it should be rejected.
+    fn acquire(&self, rate_limit: u32) -> bool {
+        let now = Instant::now();
+
+        let (start, count) = match self.window.get() {
+            Some((start, count)) if now.duration_since(start) < Duration::from_secs(1) => {
+                (start, count)
+            }
+            _ => (now, 0),
+        };
+
+        if count >= rate_limit {
+            self.window.set(Some((start, count)));
+            return false;
+        }
+
+        self.window.set(Some((start, count + 1)));
+        true
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for LimiterMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    #[allow(clippy::type_complexity)]
+    type Future = Either<LimiterResponse<S, B>, Ready<Result<Self::Response, Self::Error>>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let (maintenance, rate_limit, body_limit) = {
+            let limits = self.limits.borrow();
+            (limits.maintenance, limits.rate_limit, limits.body_limit)
+        };
+
+        if maintenance {
+            let res = HttpResponse::new(StatusCode::SERVICE_UNAVAILABLE);
+            return Either::right(ok(req.into_response(res).map_into_right_body()));
+        }
+
+        if let Some(rate_limit) = rate_limit {
+            if !self.acquire(rate_limit) {
+                let mut res = HttpResponse::new(StatusCode::TOO_MANY_REQUESTS);
+                res.headers_mut()
+                    .insert(header::RETRY_AFTER, HeaderValue::from_static("1"));
+                return Either::right(ok(req.into_response(res).map_into_right_body()));
+            }
+        }
+
+        if let Some(limit) = body_limit {
+            let declared_len = req
+                .headers()
+                .get(header::CONTENT_LENGTH)
+                .and_then(|len| len.to_str().ok())
+                .and_then(|len| len.parse::<usize>().ok());
+
+            if declared_len.is_some_and(|len| len > limit) {
+                let res = HttpResponse::new(StatusCode::PAYLOAD_TOO_LARGE);
+                return Either::right(ok(req.into_response(res).map_into_right_body()));
+            }
+
+            let payload = req.take_payload();
+            req.set_payload(Payload::from(Box::pin(LimitedPayload {
+                payload,
+                remaining: limit,
+            })
+                as actix_http::BoxedPayloadStream));
+        }
+
+        Either::left(LimiterResponse {
+            fut: self.service.call(req),
+            _body: PhantomData,
+        })
+    }
+}
+
+pin_project! {
+    pub struct LimiterResponse<S, B>
+    where
+        S: Service<ServiceRequest>,
+    {
+        #[pin]
+        fut: S::Future,
+        _body: PhantomData<B>,
+    }
+}
+
+impl<S, B> Future for LimiterResponse<S, B>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Output = Result<ServiceResponse<EitherBody<B>>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let res = ready!(self.project().fut.poll(cx))?;
+        Poll::Ready(Ok(res.map_into_left_body()))
+    }
+}
+
+pin_project! {
+    /// Request payload that errors once more than `remaining` bytes have been received.
+    struct LimitedPayload {
+        #[pin]
+        payload: Payload,
+        remaining: usize,
+    }
+}
+
+impl Stream for LimitedPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+
+        match ready!(this.payload.poll_next(cx)) {
+            Some(Ok(chunk)) if chunk.len() > *this.remaining => {
+                *this.remaining = 0;
+                Poll::Ready(Some(Err(PayloadError::Overflow)))
+            }
+
+            Some(Ok(chunk)) => {
+                *this.remaining -= chunk.len();
+                Poll::Ready(Some(Ok(chunk)))
+            }
+
+            item => Poll::Ready(item),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_service::IntoService;
+
+    use super::*;
+    use crate::{
+        test::{self, TestRequest},
+        web::{self, Bytes},
+        App,
+    };
+
+    #[actix_rt::test]
+    async fn maintenance_mode() {
+        let handle = SettingsHandle::default();
+        let srv = |req: ServiceRequest| ok(req.into_response(HttpResponse::Ok().finish()));
+        let mw = Limiter::new(&handle)
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let res = test::call_service(&mw, TestRequest::default().to_srv_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        handle.update(|limits| limits.maintenance = true);
+        let res = test::call_service(&mw, TestRequest::default().to_srv_request()).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        handle.update(|limits| limits.maintenance = false);
+        let res = test::call_service(&mw, TestRequest::default().to_srv_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn rate_limit() {
+        let handle = SettingsHandle::default();
+        let srv = |req: ServiceRequest| ok(req.into_response(HttpResponse::Ok().finish()));
+        let mw = Limiter::new(&handle)
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        handle.update(|limits| limits.rate_limit = Some(2));
+
+        for _ in 0..2 {
+            let res = test::call_service(&mw, TestRequest::default().to_srv_request()).await;
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+
+        let res = test::call_service(&mw, TestRequest::default().to_srv_request()).await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers().get(header::RETRY_AFTER).unwrap(), "1");
+
+        handle.update(|limits| limits.rate_limit = None);
+        let res = test::call_service(&mw, TestRequest::default().to_srv_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn body_limit() {
+        let handle = SettingsHandle::default();
+        handle.update(|limits| limits.body_limit = Some(4));
+
+        let app = test::init_service(
+            App::new()
+                .wrap(Limiter::new(&handle))
+                .default_service(web::to(|body: Bytes| async move { body })),
+        )
+        .await;
+
+        let req = TestRequest::default().set_payload("abc").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::default().set_payload("abcdef").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // undeclared length is limited while streaming
+        let mut req = TestRequest::default().set_payload("abcdef").to_request();
+        req.headers_mut().remove(header::CONTENT_LENGTH);
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
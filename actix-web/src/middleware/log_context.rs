@@ -0,0 +1,211 @@
+//! For middleware documentation, see [`LogContext`].
+
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    fmt,
+    rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use actix_utils::future::{ok, Ready};
+use once_cell::sync::Lazy;
+
+use crate::{dev::Payload, FromRequest, HttpMessage, HttpRequest};
+
+/// Request-scoped set of structured logging fields.
+///
+/// A `LogContext` is attached to every request passing through the [`Logger`] middleware, which
+/// enriches it with a `request_id` before calling the inner service and with the matched `route`
+/// once a response is available. Additional fields, such as a tenant or user identity, can be
+/// added by other middleware using [`Logger::context_field()`] or by handlers using the extractor.
+///
+/// All fields are written as part of the single access log record that `Logger` emits when the
+/// response completes, using the `%{key}c` (single field) or `%C` (all fields) format tokens.
+/// Since `Logger` uses the `log` crate, applications using `tracing` can receive these records
+/// through the `tracing-log` bridge.
+///
+/// Clones share the same fields. Extracting a `LogContext` never fails; if no `Logger` is
+/// registered, an empty context is attached to the request.
+///
+/// # Examples
+/// ```
+/// use actix_web::{get, middleware::LogContext, App, HttpResponse};
+///
+/// #[get("/orders/{id}")]
+/// async fn order(ctx: LogContext) -> HttpResponse {
+///     ctx.insert("cache", "miss");
+///     HttpResponse::Ok().finish()
+/// }
+///
+/// let app = App::new()
+///     .wrap(actix_web::middleware::Logger::new("%r %s %C"))
+///     .service(order);
+/// ```
+///
+/// [`Logger`]: crate::middleware::Logger
+/// [`Logger::context_field()`]: crate::middleware::Logger::context_field
+#[derive(Clone, Default)]
+pub struct LogContext {
+    fields: Rc<RefCell<Fields>>,
+}
+
+type Fields = Vec<(Cow<'static, str>, String)>;
+
+impl LogContext {
+    /// Constructs an empty `LogContext`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the field `key` to `value`, replacing any previous value with the same key.
+    ///
+    /// Fields are kept in the order they were first inserted.
+    pub fn insert(&self, key: impl Into<Cow<'static, str>>, value: impl fmt::Display) {
+        let key = key.into();
+        let value = value.to_string();
+
+        let mut fields = self.fields.borrow_mut();
+
+        match fields.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, val)) => *val = value,
+            None => fields.push((key, value)),
+        }
+    }
+
+    /// Returns the value of field `key`, if set.
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.fields
+            .borrow()
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, val)| val.clone())
+    }
+
+    /// Returns true if field `key` is set.
+    pub fn contains(&self, key: &str) -> bool {
+        self.fields.borrow().iter().any(|(k, _)| k == key)
+    }
+
+    /// Returns a snapshot of all fields in insertion order.
+    pub fn fields(&self) -> Vec<(Cow<'static, str>, String)> {
+        self.fields.borrow().clone()
+    }
+
+    /// Returns the request's log context, attaching a new one if none exists.
+    ///
+    /// The returned flag is true if the context was created by this call.
+    pub(crate) fn get_or_attach(req: &impl HttpMessage) -> (Self, bool) {
+        if let Some(ctx) = req.extensions().get::<Self>() {
+            return (ctx.clone(), false);
+        }
+
+        let ctx = Self::new();
+        req.extensions_mut().insert(ctx.clone());
+        (ctx, true)
+    }
+}
+
+/// Formats the fields as space-separated `key=value` pairs.
+///
+/// Values containing whitespace, quotes, or `=` are quoted and escaped.
+impl fmt::Display for LogContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, (key, val)) in self.fields.borrow().iter().enumerate() {
+            if idx > 0 {
+                f.write_str(" ")?;
+            }
+
+            let needs_quotes = val.is_empty()
+                || val
+                    .chars()
+                    .any(|c| c.is_whitespace() || c == '"' || c == '=');
+
+            if needs_quotes {
+                write!(f, "{key}={val:?}")?;
+            } else {
+                write!(f, "{key}={val}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for LogContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.fields.borrow().iter().map(|(k, v)| (k, v)))
+            .finish()
+    }
+}
+
+impl FromRequest for LogContext {
+    type Error = crate::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ok(Self::get_or_attach(req).0)
+    }
+}
+
+/// Generates a process-unique request ID.
+pub(crate) fn generate_request_id() -> String {
+    static PREFIX: Lazy<u32> = Lazy::new(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |dur| dur.subsec_nanos() ^ dur.as_secs() as u32)
+    });
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{:08x}-{:08x}", *PREFIX, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::TestRequest;
+
+    #[test]
+    fn insert_replaces_existing() {
+        let ctx = LogContext::new();
+        ctx.insert("tenant", "acme");
+        ctx.insert("user", 42);
+        ctx.insert("tenant", "globex");
+
+        assert_eq!(ctx.get("tenant").as_deref(), Some("globex"));
+        assert_eq!(ctx.get("user").as_deref(), Some("42"));
+        assert!(ctx.get("missing").is_none());
+        assert_eq!(ctx.fields().len(), 2);
+        assert_eq!(ctx.to_string(), "tenant=globex user=42");
+    }
+
+    #[test]
+    fn display_quotes_values() {
+        let ctx = LogContext::new();
+        ctx.insert("msg", "hello world");
+        ctx.insert("eq", "a=b");
+        ctx.insert("empty", "");
+
+        assert_eq!(ctx.to_string(), r#"msg="hello world" eq="a=b" empty="""#);
+    }
+
+    #[actix_rt::test]
+    async fn extractor_shares_fields() {
+        let req = TestRequest::default().to_http_request();
+
+        let ctx = LogContext::extract(&req).await.unwrap();
+        ctx.insert("step", "one");
+
+        let ctx2 = LogContext::extract(&req).await.unwrap();
+        assert_eq!(ctx2.get("step").as_deref(), Some("one"));
+    }
+
+    #[test]
+    fn request_ids_are_unique() {
+        assert_ne!(generate_request_id(), generate_request_id());
+    }
+}
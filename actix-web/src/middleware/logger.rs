@@ -2,6 +2,7 @@
 
 use std::{
     borrow::Cow,
+    cell::Cell,
     collections::HashSet,
     env,
     fmt::{self, Display as _},
@@ -23,12 +24,17 @@ use regex::Regex;
 #[cfg(not(feature = "unicode"))]
 use regex_lite::Regex;
 use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tokio::sync::watch;
 
+#[cfg(feature = "__compress")]
+use crate::middleware::CompressionStats;
 use crate::{
     body::{BodySize, MessageBody},
-    http::header::HeaderName,
+    http::header::{HeaderMap, HeaderName},
+    middleware::{log_context::generate_request_id, LogContext},
     service::{ServiceRequest, ServiceResponse},
-    Error, Result,
+    settings::{RuntimeLimits, SettingsHandle},
+    Error, HttpRequest, RedactionPolicy, Result,
 };
 
 /// Middleware for logging request and response summaries to the terminal.
@@ -76,6 +82,23 @@ use crate::{
 /// `%{FOO}e` | `env_var["FOO"]`
 /// `%{FOO}xi` | [Custom request replacement](Logger::custom_request_replace) labelled "FOO"
 /// `%{FOO}xo` | [Custom response replacement](Logger::custom_response_replace) labelled "FOO"
+/// `%{FOO}c` | Field "FOO" of the request's [`LogContext`]
+/// `%C` | All fields of the request's [`LogContext`] as `key=value` pairs
+/// `%z` | Response [compression statistics](Compress), e.g. `gzip 4096>1024` **\*\***
+///
+/// Header values and query parameters are masked according to the app's [`RedactionPolicy`]. By
+/// default, `Authorization`, `Proxy-Authorization`, `Cookie`, and `Set-Cookie` header values are
+/// never logged. Output of custom replacement functions is not redacted.
+///
+/// **\*\*** The encoding applied by the [`Compress`] middleware, followed by the size of the body
+/// before and after compression. `Logger` must wrap `Compress` for the statistics to be available;
+/// otherwise, `-` is logged.
+///
+/// # Log Context
+/// Every request is given a [`LogContext`] holding a `request_id` field, taken from the
+/// `X-Request-Id` request header or generated if absent. The matched `route` pattern and any
+/// [context fields](Logger::context_field) are added once the response is available, along with
+/// fields inserted by handlers and other middleware.
 ///
 /// # Security
 /// **\*** "Real IP" remote address is calculated using
@@ -83,6 +106,8 @@ use crate::{
 ///
 /// If you use this value, ensure that all requests come from trusted hosts. Otherwise, it is
 /// trivial for the remote client to falsify their source IP address.
+///
+/// [`Compress`]: crate::middleware::Compress
 #[derive(Debug)]
 pub struct Logger(Rc<Inner>);
 
@@ -92,6 +117,8 @@ struct Inner {
     exclude: HashSet<String>,
     exclude_regex: Vec<Regex>,
     log_target: Cow<'static, str>,
+    sampling: Option<watch::Receiver<RuntimeLimits>>,
+    context_fields: Vec<ContextFieldFn>,
 }
 
 impl Logger {
@@ -102,6 +129,8 @@ impl Logger {
             exclude: HashSet::new(),
             exclude_regex: Vec::new(),
             log_target: Cow::Borrowed(module_path!()),
+            sampling: None,
+            context_fields: Vec::new(),
         }))
     }
 
@@ -139,6 +168,45 @@ impl Logger {
         self
     }
 
+    /// Only logs the fraction of requests given by the handle's
+    /// [`log_sample_rate`](RuntimeLimits::log_sample_rate).
+    ///
+    /// The rate is re-read on every request, so it can be adjusted while the server is running.
+    /// Sampling is evenly spaced; with a rate of `0.25`, every fourth request is logged.
+    pub fn sampling(mut self, handle: &SettingsHandle) -> Self {
+        let inner = Rc::get_mut(&mut self.0).unwrap();
+        inner.sampling = Some(handle.subscribe());
+        self
+    }
+
+    /// Register a function that adds a field to the request's [`LogContext`].
+    ///
+    /// The function is called once the response is available, so it can read request extensions
+    /// set by inner middleware such as authentication. If it returns `None`, the field is not set.
+    ///
+    /// # Examples
+    /// ```
+    /// # use actix_web::middleware::Logger;
+    /// Logger::new("%r %s %C").context_field("tenant", |req| {
+    ///     req.headers()
+    ///         .get("X-Tenant")
+    ///         .and_then(|val| val.to_str().ok())
+    ///         .map(str::to_owned)
+    /// });
+    /// ```
+    pub fn context_field(
+        mut self,
+        key: impl Into<Cow<'static, str>>,
+        f: impl Fn(&HttpRequest) -> Option<String> + 'static,
+    ) -> Self {
+        let inner = Rc::get_mut(&mut self.0).unwrap();
+        inner.context_fields.push(ContextFieldFn {
+            key: key.into(),
+            inner_fn: Rc::new(f),
+        });
+        self
+    }
+
     /// Register a function that receives a ServiceRequest and returns a String for use in the
     /// log line. The label passed as the first argument should match a replacement substring in
     /// the logger format like `%{label}xi`.
@@ -242,6 +310,8 @@ impl Default for Logger {
             exclude: HashSet::new(),
             exclude_regex: Vec::new(),
             log_target: Cow::Borrowed(module_path!()),
+            sampling: None,
+            context_fields: Vec::new(),
         }))
     }
 }
@@ -277,6 +347,7 @@ where
         ready(Ok(LoggerMiddleware {
             service,
             inner: Rc::clone(&self.0),
+            sample_credit: Cell::new(0.0),
         }))
     }
 }
@@ -285,6 +356,28 @@ where
 pub struct LoggerMiddleware<S> {
     inner: Rc<Inner>,
     service: S,
+    // accumulated sample rate; a request is logged each time it reaches 1
+    sample_credit: Cell<f64>,
+}
+
+impl<S> LoggerMiddleware<S> {
+    /// Returns true if the current request should be logged under the sampling rate.
+    fn sampled(&self) -> bool {
+        let Some(sampling) = &self.inner.sampling else {
+            return true;
+        };
+
+        let rate = sampling.borrow().log_sample_rate.clamp(0.0, 1.0);
+        let credit = self.sample_credit.get() + rate;
+
+        if credit >= 1.0 {
+            self.sample_credit.set(credit - 1.0);
+            true
+        } else {
+            self.sample_credit.set(credit);
+            false
+        }
+    }
 }
 
 impl<S, B> Service<ServiceRequest> for LoggerMiddleware<S>
@@ -299,12 +392,25 @@ where
     actix_service::forward_ready!(service);
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
+        let (context, created) = LogContext::get_or_attach(&req);
+
+        if created {
+            let request_id = req
+                .headers()
+                .get("x-request-id")
+                .and_then(|val| val.to_str().ok())
+                .map_or_else(generate_request_id, str::to_owned);
+
+            context.insert("request_id", request_id);
+        }
+
         let excluded = self.inner.exclude.contains(req.path())
             || self
                 .inner
                 .exclude_regex
                 .iter()
-                .any(|r| r.is_match(req.path()));
+                .any(|r| r.is_match(req.path()))
+            || !self.sampled();
 
         if excluded {
             LoggerResponse {
@@ -312,6 +418,8 @@ where
                 format: None,
                 time: OffsetDateTime::now_utc(),
                 log_target: Cow::Borrowed(""),
+                context,
+                inner: Rc::clone(&self.inner),
                 _phantom: PhantomData,
             }
         } else {
@@ -327,6 +435,8 @@ where
                 format: Some(format),
                 time: now,
                 log_target: self.inner.log_target.clone(),
+                context,
+                inner: Rc::clone(&self.inner),
                 _phantom: PhantomData,
             }
         }
@@ -344,6 +454,8 @@ pin_project! {
         time: OffsetDateTime,
         format: Option<Format>,
         log_target: Cow<'static, str>,
+        context: LogContext,
+        inner: Rc<Inner>,
         _phantom: PhantomData<B>,
     }
 }
@@ -367,6 +479,16 @@ where
             debug!("Error in response: {:?}", error);
         }
 
+        if let Some(route) = res.request().match_pattern() {
+            this.context.insert("route", route);
+        }
+
+        for field in &this.inner.context_fields {
+            if let Some(val) = field.call(res.request()) {
+                this.context.insert(field.key.clone(), val);
+            }
+        }
+
         let res = if let Some(ref mut format) = this.format {
             // to avoid polluting all the Logger types with the body parameter we swap the body
             // out temporarily since it's not usable in custom response functions anyway
@@ -377,7 +499,7 @@ where
             let temp_res = ServiceResponse::new(req, res.map_into_boxed_body());
 
             for unit in &mut format.0 {
-                unit.render_response(&temp_res);
+                unit.render_response(&temp_res, this.context);
             }
 
             // re-construct original service response
@@ -453,6 +575,11 @@ impl<B: MessageBody> MessageBody for StreamLog<B> {
             None => Poll::Ready(None),
         }
     }
+
+    #[inline]
+    fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+        self.project().body.take_trailers()
+    }
 }
 
 /// A formatting style for the `Logger` consisting of multiple concatenated `FormatText` items.
@@ -472,7 +599,7 @@ impl Format {
     /// Returns `None` if the format string syntax is incorrect.
     pub fn new(s: &str) -> Format {
         log::trace!("Access log format: {}", s);
-        let fmt = Regex::new(r"%(\{([A-Za-z0-9\-_]+)\}([aioe]|x[io])|[%atPrUsbTD]?)").unwrap();
+        let fmt = Regex::new(r"%(\{([A-Za-z0-9\-_]+)\}([aioec]|x[io])|[%atPrUsbTDCz]?)").unwrap();
 
         let mut idx = 0;
         let mut results = Vec::new();
@@ -496,6 +623,7 @@ impl Format {
                     "i" => FormatText::RequestHeader(HeaderName::try_from(key.as_str()).unwrap()),
                     "o" => FormatText::ResponseHeader(HeaderName::try_from(key.as_str()).unwrap()),
                     "e" => FormatText::EnvironHeader(key.as_str().to_owned()),
+                    "c" => FormatText::ContextField(key.as_str().to_owned()),
                     "xi" => FormatText::CustomRequest(key.as_str().to_owned(), None),
                     "xo" => FormatText::CustomResponse(key.as_str().to_owned(), None),
                     _ => unreachable!(),
@@ -512,6 +640,11 @@ impl Format {
                     "U" => FormatText::UrlPath,
                     "T" => FormatText::Time,
                     "D" => FormatText::TimeMillis,
+                    "C" => FormatText::ContextFields,
+                    #[cfg(feature = "__compress")]
+                    "z" => FormatText::Compression(None),
+                    #[cfg(not(feature = "__compress"))]
+                    "z" => FormatText::Str("-".to_owned()),
                     _ => FormatText::Str(m.as_str().to_owned()),
                 });
             }
@@ -544,6 +677,10 @@ enum FormatText {
     RequestHeader(HeaderName),
     ResponseHeader(HeaderName),
     EnvironHeader(String),
+    ContextField(String),
+    ContextFields,
+    #[cfg(feature = "__compress")]
+    Compression(Option<CompressionStats>),
     CustomRequest(String, Option<CustomRequestFn>),
     CustomResponse(String, Option<CustomResponseFn>),
 }
@@ -582,6 +719,28 @@ impl fmt::Debug for CustomResponseFn {
     }
 }
 
+#[derive(Clone)]
+struct ContextFieldFn {
+    key: Cow<'static, str>,
+    inner_fn: Rc<ContextFn>,
+}
+
+type ContextFn = dyn Fn(&HttpRequest) -> Option<String>;
+
+impl ContextFieldFn {
+    fn call(&self, req: &HttpRequest) -> Option<String> {
+        (self.inner_fn)(req)
+    }
+}
+
+impl fmt::Debug for ContextFieldFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContextFieldFn")
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
 impl FormatText {
     fn render(
         &self,
@@ -610,11 +769,20 @@ impl FormatText {
                     "-".fmt(fmt)
                 }
             }
+            #[cfg(feature = "__compress")]
+            FormatText::Compression(Some(ref stats)) => fmt.write_fmt(format_args!(
+                "{} {}>{}",
+                stats.encoding().as_str(),
+                stats.bytes_in(),
+                stats.bytes_out()
+            )),
+            #[cfg(feature = "__compress")]
+            FormatText::Compression(None) => "-".fmt(fmt),
             _ => Ok(()),
         }
     }
 
-    fn render_response(&mut self, res: &ServiceResponse) {
+    fn render_response(&mut self, res: &ServiceResponse, context: &LogContext) {
         match self {
             FormatText::ResponseStatus => {
                 *self = FormatText::Str(format!("{}", res.status().as_u16()))
@@ -622,13 +790,30 @@ impl FormatText {
 
             FormatText::ResponseHeader(ref name) => {
                 let s = if let Some(val) = res.headers().get(name) {
-                    val.to_str().unwrap_or("-")
+                    let policy = RedactionPolicy::for_request(res.request());
+                    policy.redact_header(name, val.to_str().unwrap_or("-"))
                 } else {
                     "-"
                 };
                 *self = FormatText::Str(s.to_string())
             }
 
+            FormatText::ContextField(ref key) => {
+                *self = FormatText::Str(context.get(key).unwrap_or_else(|| "-".to_owned()))
+            }
+
+            FormatText::ContextFields => *self = FormatText::Str(context.to_string()),
+
+            // rendered once the body has been sent, when compression is complete
+            #[cfg(feature = "__compress")]
+            FormatText::Compression(ref mut stats) => {
+                *stats = res
+                    .response()
+                    .extensions()
+                    .get::<CompressionStats>()
+                    .cloned();
+            }
+
             FormatText::CustomResponse(_, res_fn) => {
                 let text = match res_fn {
                     Some(res_fn) => FormatText::Str(res_fn.call(res)),
@@ -643,6 +828,8 @@ impl FormatText {
     }
 
     fn render_request(&mut self, now: OffsetDateTime, req: &ServiceRequest) {
+        let policy = RedactionPolicy::for_request(req.request());
+
         match self {
             FormatText::RequestLine => {
                 *self = if req.query_string().is_empty() {
@@ -657,7 +844,7 @@ impl FormatText {
                         "{} {}?{} {:?}",
                         req.method(),
                         req.path(),
-                        req.query_string(),
+                        policy.redact_query(req.query_string()),
                         req.version()
                     ))
                 };
@@ -666,7 +853,7 @@ impl FormatText {
             FormatText::RequestTime => *self = FormatText::Str(now.format(&Rfc3339).unwrap()),
             FormatText::RequestHeader(ref name) => {
                 let s = if let Some(val) = req.headers().get(name) {
-                    val.to_str().unwrap_or("-")
+                    policy.redact_header(name, val.to_str().unwrap_or("-"))
                 } else {
                     "-"
                 };
@@ -719,7 +906,7 @@ mod tests {
     use crate::{
         http::{header, StatusCode},
         test::{self, TestRequest},
-        HttpResponse,
+        HttpMessage as _, HttpResponse,
     };
 
     #[actix_rt::test]
@@ -767,6 +954,27 @@ mod tests {
         let _res = srv.call(req).await.unwrap();
     }
 
+    #[actix_rt::test]
+    async fn test_logger_sampling() {
+        let srv = |req: ServiceRequest| ok(req.into_response(HttpResponse::Ok().finish()));
+
+        let handle = SettingsHandle::default();
+        let logger = Logger::default().sampling(&handle);
+        let srv = logger.new_transform(srv.into_service()).await.unwrap();
+
+        assert!((0..4).all(|_| srv.sampled()));
+
+        handle.update(|limits| limits.log_sample_rate = 0.25);
+        let sampled = (0..8).map(|_| srv.sampled()).collect::<Vec<_>>();
+        assert_eq!(
+            sampled,
+            [false, false, false, true, false, false, false, true]
+        );
+
+        handle.update(|limits| limits.log_sample_rate = 0.0);
+        assert!((0..4).all(|_| !srv.sampled()));
+    }
+
     #[actix_rt::test]
     async fn test_escape_percent() {
         let mut format = Format::new("%%{r}a");
@@ -786,7 +994,7 @@ mod tests {
         let req = TestRequest::default().to_http_request();
         let res = ServiceResponse::new(req, HttpResponse::Ok().finish());
         for unit in &mut format.0 {
-            unit.render_response(&res);
+            unit.render_response(&res, &LogContext::new());
         }
 
         let entry_time = OffsetDateTime::now_utc();
@@ -819,7 +1027,7 @@ mod tests {
         let req = TestRequest::default().to_http_request();
         let res = ServiceResponse::new(req, HttpResponse::Ok().force_close().finish());
         for unit in &mut format.0 {
-            unit.render_response(&res);
+            unit.render_response(&res, &LogContext::new());
         }
 
         let render = |fmt: &mut fmt::Formatter<'_>| {
@@ -852,7 +1060,7 @@ mod tests {
         let req = TestRequest::default().to_http_request();
         let res = ServiceResponse::new(req, HttpResponse::Ok().force_close().finish());
         for unit in &mut format.0 {
-            unit.render_response(&res);
+            unit.render_response(&res, &LogContext::new());
         }
 
         let entry_time = OffsetDateTime::now_utc();
@@ -882,7 +1090,7 @@ mod tests {
         let req = TestRequest::default().to_http_request();
         let res = ServiceResponse::new(req, HttpResponse::Ok().force_close().finish());
         for unit in &mut format.0 {
-            unit.render_response(&res);
+            unit.render_response(&res, &LogContext::new());
         }
 
         let render = |fmt: &mut fmt::Formatter<'_>| {
@@ -914,7 +1122,7 @@ mod tests {
         let req = TestRequest::default().to_http_request();
         let res = ServiceResponse::new(req, HttpResponse::Ok().finish());
         for unit in &mut format.0 {
-            unit.render_response(&res);
+            unit.render_response(&res, &LogContext::new());
         }
 
         let entry_time = OffsetDateTime::now_utc();
@@ -978,7 +1186,7 @@ mod tests {
         let req = TestRequest::default().to_http_request();
         let resp_ok = ServiceResponse::new(req, HttpResponse::Ok().finish());
         let now = OffsetDateTime::now_utc();
-        unit.render_response(&resp_ok);
+        unit.render_response(&resp_ok, &LogContext::new());
 
         let render = |fmt: &mut fmt::Formatter<'_>| unit.render(fmt, 1024, now);
 
@@ -1000,4 +1208,115 @@ mod tests {
         let req = TestRequest::default().to_srv_request();
         srv.call(req).await.unwrap();
     }
+
+    #[actix_rt::test]
+    async fn test_redaction() {
+        let mut format = Format::new("%r %{Authorization}i %{X-Patient}i %{Set-Cookie}o");
+
+        let req = TestRequest::default()
+            .uri("/records?ssn=123-45-6789&page=2")
+            .insert_header((header::AUTHORIZATION, "Bearer secret"))
+            .insert_header(("x-patient", "42"))
+            .app_data(
+                RedactionPolicy::new()
+                    .header(HeaderName::from_static("x-patient"))
+                    .query_param("ssn"),
+            )
+            .to_srv_request();
+
+        let now = OffsetDateTime::now_utc();
+        for unit in &mut format.0 {
+            unit.render_request(now, &req);
+        }
+
+        let res = req.into_response(
+            HttpResponse::Ok()
+                .insert_header((header::SET_COOKIE, "id=1"))
+                .finish(),
+        );
+        for unit in &mut format.0 {
+            unit.render_response(&res, &LogContext::new());
+        }
+
+        let render = |fmt: &mut fmt::Formatter<'_>| {
+            for unit in &format.0 {
+                unit.render(fmt, 1024, now)?;
+            }
+            Ok(())
+        };
+
+        let s = format!("{}", FormatDisplay(&render));
+        assert_eq!(
+            s,
+            "GET /records?ssn=*redacted*&page=2 HTTP/1.1 *redacted* *redacted* *redacted*"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_context_format() {
+        let mut format = Format::new("%{tenant}c %{missing}c [%C]");
+
+        let context = LogContext::new();
+        context.insert("request_id", "abc");
+        context.insert("tenant", "acme corp");
+
+        let req = TestRequest::default().to_http_request();
+        let res = ServiceResponse::new(req, HttpResponse::Ok().finish());
+        for unit in &mut format.0 {
+            unit.render_response(&res, &context);
+        }
+
+        let now = OffsetDateTime::now_utc();
+        let render = |fmt: &mut fmt::Formatter<'_>| {
+            for unit in &format.0 {
+                unit.render(fmt, 1024, now)?;
+            }
+            Ok(())
+        };
+
+        let s = format!("{}", FormatDisplay(&render));
+        assert_eq!(s, r#"acme corp - [request_id=abc tenant="acme corp"]"#);
+    }
+
+    #[actix_rt::test]
+    async fn test_context_enrichment() {
+        let app = test::init_service(
+            crate::App::new()
+                .wrap(Logger::new("%C").context_field("tenant", |req| {
+                    req.headers()
+                        .get("x-tenant")
+                        .map(|val| val.to_str().unwrap().to_owned())
+                }))
+                .route(
+                    "/users/{id}",
+                    crate::web::get().to(|ctx: LogContext| async move {
+                        ctx.insert("handler", "users");
+                        "ok"
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/users/42")
+            .insert_header(("x-request-id", "req-1"))
+            .insert_header(("x-tenant", "acme"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        let context = res.request().extensions().get::<LogContext>().cloned();
+        let context = context.unwrap();
+        assert_eq!(
+            context.to_string(),
+            "request_id=req-1 handler=users route=/users/{id} tenant=acme"
+        );
+
+        let req = TestRequest::get().uri("/users/42").to_request();
+        let res = test::call_service(&app, req).await;
+
+        let context = res.request().extensions().get::<LogContext>().cloned();
+        let context = context.unwrap();
+        assert!(context.contains("request_id"));
+        assert!(!context.contains("tenant"));
+    }
 }
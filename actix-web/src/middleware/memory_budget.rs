@@ -0,0 +1,301 @@
+//! For middleware documentation, see [`MemoryLimit`].
+
+use std::{cell::Cell, rc::Rc};
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use futures_core::future::LocalBoxFuture;
+
+use crate::{
+    body::{BodySize, EitherBody, MessageBody},
+    dev::Payload,
+    error::MemoryBudgetExceeded,
+    service::{ServiceRequest, ServiceResponse},
+    Error, FromRequest, HttpMessage as _, HttpRequest, HttpResponse,
+};
+
+/// Middleware for bounding the memory buffered on behalf of each request.
+///
+/// Each request is given a [`MemoryBudget`] of `limit` bytes, stored in its extensions, that is
+/// shared by everything buffering data for the request:
+/// - The payload extractors that buffer the whole payload, such as [`Bytes`], [`String`],
+///   [`Json`], and [`Form`], reserve each chunk they receive. Once the budget is exhausted,
+///   extraction fails with a `413 Payload Too Large` response, even if the payload is within the
+///   extractor's own limit.
+/// - Handlers and other middleware can reserve the memory they buffer themselves with
+///   [`MemoryBudget::try_reserve()`].
+/// - Response bodies of known size, which are held in memory until they are sent, are reserved
+///   once the response is returned. Responses that do not fit are replaced by a
+///   `507 Insufficient Storage` response. Error responses are not reserved and never replaced.
+///
+/// Reservations last until the request is finished, unless they are [released] early.
+///
+/// # Examples
+/// ```
+/// use actix_web::{middleware::MemoryLimit, web, App};
+///
+/// let app = App::new()
+///     .wrap(MemoryLimit::new(4 * 1024 * 1024))
+///     .route("/", web::post().to(|body: String| async move { body }));
+/// ```
+///
+/// [`Bytes`]: crate::web::Bytes
+/// [`Json`]: crate::web::Json
+/// [`Form`]: crate::web::Form
+/// [released]: MemoryBudget::release
+#[derive(Debug, Clone)]
+pub struct MemoryLimit {
+    limit: usize,
+}
+
+impl MemoryLimit {
+    /// Constructs a `MemoryLimit` middleware that gives each request a budget of `limit` bytes.
+    pub fn new(limit: usize) -> Self {
+        Self { limit }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for MemoryLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = MemoryLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MemoryLimitMiddleware {
+            service,
+            limit: self.limit,
+        }))
+    }
+}
+
+/// Memory limit middleware service.
+pub struct MemoryLimitMiddleware<S> {
+    service: S,
+    limit: usize,
+}
+
+impl<S, B> Service<ServiceRequest> for MemoryLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let budget = MemoryBudget::new(self.limit);
+        req.extensions_mut().insert(budget.clone());
+
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let res = fut.await?;
+
+            // error responses are sent as they are so that their status is not lost
+            if res.response().error().is_some() {
+                return Ok(res.map_into_left_body());
+            }
+
+            if let BodySize::Sized(size) = res.response().body().size() {
+                let size = usize::try_from(size).unwrap_or(usize::MAX);
+
+                if let Err(err) = budget.try_reserve(size) {
+                    log::debug!(
+                        "response to {} {} exceeds memory budget: {}",
+                        res.request().method(),
+                        res.request().path(),
+                        err
+                    );
+
+                    let res = res.into_response(HttpResponse::from_error(err));
+                    return Ok(res.map_into_right_body());
+                }
+            }
+
+            Ok(res.map_into_left_body())
+        })
+    }
+}
+
+/// Budget of memory that may be buffered on behalf of a request.
+///
+/// Budgets are given to requests by the [`MemoryLimit`] middleware and can be extracted in
+/// handlers. Extracting a budget for a request that was not given one returns an unlimited budget.
+/// Clones share the same budget.
+///
+/// # Examples
+/// ```
+/// use actix_web::{middleware::MemoryBudget, web, Error};
+///
+/// async fn handler(budget: MemoryBudget, body: web::Bytes) -> Result<String, Error> {
+///     // make room for a decompressed copy of the body
+///     budget.try_reserve(body.len() * 4)?;
+///
+///     Ok(format!("{} bytes left", budget.remaining()))
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MemoryBudget {
+    inner: Rc<BudgetInner>,
+}
+
+#[derive(Debug)]
+struct BudgetInner {
+    limit: usize,
+    used: Cell<usize>,
+}
+
+impl MemoryBudget {
+    /// Constructs a budget of `limit` bytes.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            inner: Rc::new(BudgetInner {
+                limit,
+                used: Cell::new(0),
+            }),
+        }
+    }
+
+    /// Returns the budget for `req`, if it was given one.
+    pub(crate) fn of(req: &HttpRequest) -> Option<Self> {
+        req.extensions().get::<Self>().cloned()
+    }
+
+    /// Returns the total number of bytes in the budget.
+    pub fn limit(&self) -> usize {
+        self.inner.limit
+    }
+
+    /// Returns the number of bytes currently reserved.
+    pub fn used(&self) -> usize {
+        self.inner.used.get()
+    }
+
+    /// Returns the number of bytes that can still be reserved.
+    pub fn remaining(&self) -> usize {
+        self.inner.limit - self.used()
+    }
+
+    /// Reserves `bytes` from the budget.
+    ///
+    /// Fails, without reserving anything, if fewer than `bytes` bytes remain. The error responds
+    /// with `507 Insufficient Storage`.
+    pub fn try_reserve(&self, bytes: usize) -> Result<(), MemoryBudgetExceeded> {
+        let remaining = self.remaining();
+
+        if bytes > remaining {
+            return Err(MemoryBudgetExceeded {
+                limit: self.limit(),
+                requested: bytes,
+                remaining,
+            });
+        }
+
+        self.inner.used.set(self.used() + bytes);
+        Ok(())
+    }
+
+    /// Returns `bytes` previously reserved to the budget, e.g., once a buffer has been dropped.
+    pub fn release(&self, bytes: usize) {
+        self.inner.used.set(self.used().saturating_sub(bytes));
+    }
+}
+
+impl FromRequest for MemoryBudget {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(Self::of(req).unwrap_or_else(|| Self::new(usize::MAX))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::StatusCode,
+        test::{call_service, init_service, read_body, TestRequest},
+        web, App,
+    };
+
+    #[test]
+    fn reserve_release() {
+        let budget = MemoryBudget::new(10);
+
+        budget.try_reserve(6).unwrap();
+        let err = budget.clone().try_reserve(5).unwrap_err();
+        assert_eq!(err.remaining, 4);
+        assert_eq!(budget.used(), 6);
+
+        budget.release(6);
+        budget.try_reserve(10).unwrap();
+        assert_eq!(budget.remaining(), 0);
+    }
+
+    #[actix_rt::test]
+    async fn payload_and_response() {
+        let srv = init_service(
+            App::new()
+                .wrap(MemoryLimit::new(8))
+                .route("/echo", web::post().to(|body: String| async move { body }))
+                .route(
+                    "/json",
+                    web::post()
+                        .to(|body: web::Json<Vec<u8>>| async move { body.len().to_string() }),
+                )
+                .route("/large", web::get().to(|| async { "0123456789" }))
+                .route(
+                    "/budget",
+                    web::get().to(|budget: MemoryBudget| async move {
+                        budget.try_reserve(5)?;
+                        Ok::<_, Error>(budget.remaining().to_string())
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri("/echo")
+            .set_payload("1234")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, "1234");
+
+        // payload and response together exceed the budget
+        let req = TestRequest::post()
+            .uri("/echo")
+            .set_payload("123456")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::INSUFFICIENT_STORAGE);
+
+        let req = TestRequest::post()
+            .uri("/json")
+            .insert_header(("content-type", "application/json"))
+            .set_payload("[1,2,3,4,5]")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let req = TestRequest::get().uri("/large").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::INSUFFICIENT_STORAGE);
+
+        let req = TestRequest::get().uri("/budget").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, "3");
+    }
+}
@@ -246,24 +246,52 @@ mod compat;
 #[cfg(feature = "__compress")]
 mod compress;
 mod condition;
+mod contract;
+mod dedup;
 mod default_headers;
 mod err_handlers;
+mod error_reporting;
+#[cfg(feature = "fault-injection")]
+mod fault_injection;
 mod from_fn;
 mod identity;
+mod limiter;
+mod log_context;
 mod logger;
+mod memory_budget;
 mod normalize;
+mod on_complete;
+mod scheduler;
+mod throttle;
+mod transaction;
+#[cfg(feature = "signed-urls")]
+mod verify_signed_url;
 
 #[cfg(feature = "__compress")]
-pub use self::compress::Compress;
+pub use self::compress::{Compress, CompressionOptions, CompressionStats};
+#[cfg(feature = "fault-injection")]
+pub use self::fault_injection::{Fault, FaultInjection};
+#[cfg(feature = "signed-urls")]
+pub use self::verify_signed_url::{VerifySignedUrl, VerifySignedUrlMiddleware};
 pub use self::{
     compat::Compat,
     condition::Condition,
+    contract::ResponseContract,
+    dedup::{DedupEntry, DedupStore, Deduplicate, MemoryDedupStore},
     default_headers::DefaultHeaders,
     err_handlers::{ErrorHandlerResponse, ErrorHandlers},
+    error_reporting::{ErrorReport, ErrorReporter, ErrorReporting},
     from_fn::{from_fn, Next},
     identity::Identity,
+    limiter::Limiter,
+    log_context::LogContext,
     logger::Logger,
+    memory_budget::{MemoryBudget, MemoryLimit},
     normalize::{NormalizePath, TrailingSlash},
+    on_complete::{CompletionHooks, OnResponseComplete, ResponseCompletion, SendOutcome},
+    scheduler::{Prioritized, Priority, Scheduler},
+    throttle::Throttle,
+    transaction::{Transaction, TransactionManager, Transactional},
 };
 
 #[cfg(test)]
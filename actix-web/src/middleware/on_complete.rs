@@ -0,0 +1,508 @@
+//! For middleware documentation, see [`OnResponseComplete`].
+
+use std::{
+    cell::RefCell,
+    fmt,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{err, ok, ready, Ready};
+use bytes::Bytes;
+use futures_core::ready;
+use pin_project_lite::pin_project;
+
+use crate::{
+    body::{BodySize, MessageBody},
+    dev::Payload,
+    error,
+    http::{header::HeaderMap, StatusCode},
+    service::{ServiceRequest, ServiceResponse},
+    Error, FromRequest, HttpMessage as _, HttpRequest,
+};
+
+type Hook = Rc<dyn Fn(&ResponseCompletion)>;
+type RequestHook = Box<dyn FnOnce(&ResponseCompletion)>;
+
+/// How sending a response ended. See [`ResponseCompletion::outcome()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SendOutcome {
+    /// The whole response body was handed to the connection.
+    Completed,
+
+    /// The response body stream returned an error.
+    BodyError,
+
+    /// The response was dropped before its body was finished, typically because the client
+    /// disconnected.
+    Disconnected,
+
+    /// The wrapped service returned an error instead of a response.
+    ServiceError,
+}
+
+/// Summary of a finished response, passed to response completion hooks.
+#[derive(Debug, Clone)]
+pub struct ResponseCompletion {
+    status: StatusCode,
+    outcome: SendOutcome,
+    body_bytes: u64,
+    elapsed: Duration,
+}
+
+impl ResponseCompletion {
+    /// Returns the response status code.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Returns how sending the response ended.
+    pub fn outcome(&self) -> SendOutcome {
+        self.outcome
+    }
+
+    /// Returns the number of response body bytes handed to the connection.
+    pub fn body_bytes(&self) -> u64 {
+        self.body_bytes
+    }
+
+    /// Returns the time between the request reaching this middleware and the response finishing.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+/// Middleware for running hooks once a response has finished sending.
+///
+/// Hooks fire exactly once per request: when the last body chunk has been handed to the
+/// connection, when the body stream errors, or when the response is dropped early because the
+/// client disconnected. The [`ResponseCompletion`] passed to them carries the outcome, status,
+/// and number of body bytes sent, which makes them suitable for usage accounting and for cleaning
+/// up per-request resources.
+///
+/// Besides hooks registered on the middleware, handlers and inner middleware can register
+/// per-request hooks through the [`CompletionHooks`] extractor.
+///
+/// # Examples
+/// ```
+/// use actix_web::{
+///     middleware::{CompletionHooks, OnResponseComplete},
+///     web, App, HttpResponse,
+/// };
+///
+/// let app = App::new()
+///     .wrap(OnResponseComplete::new().hook(|done| {
+///         log::info!("sent {} bytes ({:?})", done.body_bytes(), done.outcome());
+///     }))
+///     .route(
+///         "/",
+///         web::get().to(|hooks: CompletionHooks| async move {
+///             hooks.on_response_complete(|_| {
+///                 // clean up per-request resources
+///             });
+///
+///             HttpResponse::Ok()
+///         }),
+///     );
+/// ```
+#[derive(Clone, Default)]
+pub struct OnResponseComplete {
+    hooks: Rc<Vec<Hook>>,
+}
+
+impl OnResponseComplete {
+    /// Constructs a middleware with no hooks of its own.
+    ///
+    /// Per-request hooks registered through [`CompletionHooks`] still run.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a hook that runs for every response.
+    pub fn hook(mut self, hook: impl Fn(&ResponseCompletion) + 'static) -> Self {
+        Rc::get_mut(&mut self.hooks)
+            .expect("All hooks must be registered before cloning.")
+            .push(Rc::new(hook));
+        self
+    }
+}
+
+impl fmt::Debug for OnResponseComplete {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OnResponseComplete")
+            .field("hooks", &self.hooks.len())
+            .finish()
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for OnResponseComplete
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    type Response = ServiceResponse<CompletionBody<B>>;
+    type Error = Error;
+    type Transform = OnResponseCompleteMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(OnResponseCompleteMiddleware {
+            service,
+            hooks: Rc::clone(&self.hooks),
+        }))
+    }
+}
+
+/// Response completion middleware service.
+pub struct OnResponseCompleteMiddleware<S> {
+    service: S,
+    hooks: Rc<Vec<Hook>>,
+}
+
+impl<S, B> Service<ServiceRequest> for OnResponseCompleteMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    type Response = ServiceResponse<CompletionBody<B>>;
+    type Error = Error;
+    type Future = OnResponseCompleteFuture<S, B>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let request_hooks = CompletionHooks::default();
+        req.extensions_mut().insert(request_hooks.clone());
+
+        OnResponseCompleteFuture {
+            fut: self.service.call(req),
+            pending: Some(PendingCompletion {
+                hooks: Rc::clone(&self.hooks),
+                request_hooks,
+                started: Instant::now(),
+            }),
+            _body: PhantomData,
+        }
+    }
+}
+
+pin_project! {
+    pub struct OnResponseCompleteFuture<S, B>
+    where
+        S: Service<ServiceRequest>,
+    {
+        #[pin]
+        fut: S::Future,
+        pending: Option<PendingCompletion>,
+        _body: PhantomData<B>,
+    }
+}
+
+impl<S, B> Future for OnResponseCompleteFuture<S, B>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    type Output = Result<ServiceResponse<CompletionBody<B>>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let res = match ready!(this.fut.poll(cx)) {
+            Ok(res) => res,
+            Err(err) => {
+                if let Some(pending) = this.pending.take() {
+                    let status = err.as_response_error().status_code();
+                    pending.complete(status, SendOutcome::ServiceError, 0);
+                }
+
+                return Poll::Ready(Err(err));
+            }
+        };
+
+        let status = res.status();
+        let pending = this.pending.take();
+
+        Poll::Ready(Ok(res.map_body(move |_, body| CompletionBody {
+            // bodies without content are never polled by the dispatchers
+            empty: matches!(body.size(), BodySize::None | BodySize::Sized(0)),
+            body,
+            status,
+            body_bytes: 0,
+            pending,
+        })))
+    }
+}
+
+/// State needed to run the hooks once the response finishes.
+struct PendingCompletion {
+    hooks: Rc<Vec<Hook>>,
+    request_hooks: CompletionHooks,
+    started: Instant,
+}
+
+impl PendingCompletion {
+    fn complete(self, status: StatusCode, outcome: SendOutcome, body_bytes: u64) {
+        let completion = ResponseCompletion {
+            status,
+            outcome,
+            body_bytes,
+            elapsed: self.started.elapsed(),
+        };
+
+        for hook in self.request_hooks.take() {
+            hook(&completion);
+        }
+
+        for hook in self.hooks.iter() {
+            hook(&completion);
+        }
+    }
+}
+
+pin_project! {
+    /// Response body wrapper that runs completion hooks when the body ends or is dropped.
+    pub struct CompletionBody<B> {
+        #[pin]
+        body: B,
+        empty: bool,
+        status: StatusCode,
+        body_bytes: u64,
+        pending: Option<PendingCompletion>,
+    }
+
+    impl<B> PinnedDrop for CompletionBody<B> {
+        fn drop(this: Pin<&mut Self>) {
+            let this = this.project();
+
+            if let Some(pending) = this.pending.take() {
+                let outcome = if *this.empty {
+                    SendOutcome::Completed
+                } else {
+                    SendOutcome::Disconnected
+                };
+
+                pending.complete(*this.status, outcome, *this.body_bytes);
+            }
+        }
+    }
+}
+
+impl<B: MessageBody> MessageBody for CompletionBody<B> {
+    type Error = B::Error;
+
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.project();
+
+        // hooks have already fired; avoid polling a finished body again
+        if this.pending.is_none() {
+            return Poll::Ready(None);
+        }
+
+        match ready!(this.body.poll_next(cx)) {
+            Some(Ok(chunk)) => {
+                *this.body_bytes += chunk.len() as u64;
+                Poll::Ready(Some(Ok(chunk)))
+            }
+
+            Some(Err(err)) => {
+                if let Some(pending) = this.pending.take() {
+                    pending.complete(*this.status, SendOutcome::BodyError, *this.body_bytes);
+                }
+
+                Poll::Ready(Some(Err(err)))
+            }
+
+            None => {
+                if let Some(pending) = this.pending.take() {
+                    pending.complete(*this.status, SendOutcome::Completed, *this.body_bytes);
+                }
+
+                Poll::Ready(None)
+            }
+        }
+    }
+
+    fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+        self.project().body.take_trailers()
+    }
+}
+
+/// Per-request registry of response completion hooks.
+///
+/// Available as an extractor, and in request extensions, when the [`OnResponseComplete`]
+/// middleware wraps the service. Hooks registered here run once the response has finished, before
+/// the hooks registered on the middleware itself.
+///
+/// Extraction fails with a `500 Internal Server Error` if the middleware is not installed.
+#[derive(Clone, Default)]
+pub struct CompletionHooks {
+    hooks: Rc<RefCell<Vec<RequestHook>>>,
+}
+
+impl CompletionHooks {
+    /// Registers a hook to run once the current response has finished.
+    pub fn on_response_complete(&self, hook: impl FnOnce(&ResponseCompletion) + 'static) {
+        self.hooks.borrow_mut().push(Box::new(hook));
+    }
+
+    fn take(&self) -> Vec<RequestHook> {
+        std::mem::take(&mut *self.hooks.borrow_mut())
+    }
+}
+
+impl fmt::Debug for CompletionHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompletionHooks")
+            .field("hooks", &self.hooks.borrow().len())
+            .finish()
+    }
+}
+
+impl FromRequest for CompletionHooks {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match req.extensions().get::<CompletionHooks>() {
+            Some(hooks) => ok(hooks.clone()),
+            None => {
+                log::debug!(
+                    "Failed to extract `CompletionHooks` for `{}` handler. Wrap the app or scope \
+                    with the `OnResponseComplete` middleware to use this extractor.",
+                    req.match_name().unwrap_or_else(|| req.path())
+                );
+
+                err(error::ErrorInternalServerError(
+                    "Response completion hooks are not configured.",
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use actix_service::IntoService;
+    use futures_util::stream;
+
+    use super::*;
+    use crate::{
+        body::{self, BodyStream},
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    fn recorder() -> (Rc<RefCell<Vec<ResponseCompletion>>>, OnResponseComplete) {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let seen2 = Rc::clone(&seen);
+        let mw = OnResponseComplete::new().hook(move |done| seen2.borrow_mut().push(done.clone()));
+        (seen, mw)
+    }
+
+    #[actix_rt::test]
+    async fn completed_body() {
+        let (seen, mw) = recorder();
+        let srv = |req: ServiceRequest| ok(req.into_response(HttpResponse::Ok().body("hello")));
+        let srv = mw.new_transform(srv.into_service()).await.unwrap();
+
+        let res = test::call_service(&srv, TestRequest::default().to_srv_request()).await;
+        assert!(seen.borrow().is_empty());
+
+        let body = body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, "hello");
+
+        let seen = seen.borrow();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].status(), StatusCode::OK);
+        assert_eq!(seen[0].outcome(), SendOutcome::Completed);
+        assert_eq!(seen[0].body_bytes(), 5);
+    }
+
+    #[actix_rt::test]
+    async fn empty_body_completes_on_drop() {
+        let (seen, mw) = recorder();
+        let srv = |req: ServiceRequest| ok(req.into_response(HttpResponse::NoContent().finish()));
+        let srv = mw.new_transform(srv.into_service()).await.unwrap();
+
+        let res = test::call_service(&srv, TestRequest::default().to_srv_request()).await;
+        drop(res);
+
+        assert_eq!(seen.borrow()[0].outcome(), SendOutcome::Completed);
+        assert_eq!(seen.borrow()[0].status(), StatusCode::NO_CONTENT);
+    }
+
+    #[actix_rt::test]
+    async fn disconnect_and_body_error() {
+        let (seen, mw) = recorder();
+        let srv = |req: ServiceRequest| {
+            let body = BodyStream::new(stream::iter([
+                Ok::<_, Error>(Bytes::from_static(b"abc")),
+                Err(error::ErrorBadRequest("boom")),
+            ]));
+            ok(req.into_response(HttpResponse::Ok().body(body)))
+        };
+        let srv = mw.new_transform(srv.into_service()).await.unwrap();
+
+        // dropped before the body finishes
+        let res = test::call_service(&srv, TestRequest::default().to_srv_request()).await;
+        drop(res);
+        assert_eq!(seen.borrow()[0].outcome(), SendOutcome::Disconnected);
+        assert_eq!(seen.borrow()[0].body_bytes(), 0);
+
+        // body stream errors part way through
+        let res = test::call_service(&srv, TestRequest::default().to_srv_request()).await;
+        body::to_bytes(res.into_body()).await.unwrap_err();
+        assert_eq!(seen.borrow()[1].outcome(), SendOutcome::BodyError);
+        assert_eq!(seen.borrow()[1].body_bytes(), 3);
+        assert_eq!(seen.borrow().len(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn request_hooks() {
+        let fired = Rc::new(Cell::new(0));
+        let fired2 = Rc::clone(&fired);
+
+        let app = test::init_service(App::new().wrap(OnResponseComplete::new()).route(
+            "/",
+            web::to(move |hooks: CompletionHooks| {
+                let fired = Rc::clone(&fired2);
+                async move {
+                    hooks.on_response_complete(move |done| {
+                        assert_eq!(done.outcome(), SendOutcome::Completed);
+                        fired.set(fired.get() + 1);
+                    });
+                    "ok"
+                }
+            }),
+        ))
+        .await;
+
+        let res = test::call_and_read_body(&app, TestRequest::default().to_request()).await;
+        assert_eq!(res, "ok");
+        assert_eq!(fired.get(), 1);
+
+        // extractor requires the middleware
+        let app =
+            test::init_service(App::new().route("/", web::to(|_: CompletionHooks| async { "" })))
+                .await;
+        let res = test::call_service(&app, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,481 @@
+//! For middleware documentation, see [`Scheduler`].
+
+use std::{
+    cell::{Cell, RefCell},
+    cmp::Ordering,
+    collections::BinaryHeap,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use futures_core::future::LocalBoxFuture;
+
+use crate::{
+    body::EitherBody,
+    http::StatusCode,
+    service::{ServiceRequest, ServiceResponse},
+    Error, HttpResponse,
+};
+
+/// Scheduling priority of a route. Higher priorities are admitted first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Priority(u8);
+
+impl Priority {
+    /// Priority for background work, such as batch exports.
+    pub const LOW: Self = Self(64);
+
+    /// Default priority.
+    pub const NORMAL: Self = Self(128);
+
+    /// Priority for latency-sensitive work, such as real-time signaling.
+    pub const HIGH: Self = Self(192);
+
+    /// Constructs a priority from its numeric value.
+    pub const fn new(value: u8) -> Self {
+        Self(value)
+    }
+
+    /// Returns the numeric value of the priority.
+    pub const fn value(self) -> u8 {
+        self.0
+    }
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Self::NORMAL
+    }
+}
+
+/// Admission controller that limits the number of requests handled concurrently by a worker and,
+/// when that budget is contended, admits waiting requests in order of their route's priority.
+///
+/// Routes take part in scheduling by wrapping them with the middleware returned by
+/// [`priority()`](Self::priority). Requests wait until a slot of the in-flight budget is free;
+/// requests of the same priority are admitted in arrival order. Requests to routes that are not
+/// wrapped are not counted against the budget.
+///
+/// A slot is held until the route's service has produced a response; streaming the response body
+/// does not count. When the number of waiting requests reaches the
+/// [queue limit](Self::max_queued), further requests are rejected with
+/// `503 Service Unavailable`.
+///
+/// A scheduler should be constructed in the app factory closure so that each worker gets its own
+/// budget.
+///
+/// # Examples
+/// ```
+/// use actix_web::{
+///     middleware::{Priority, Scheduler},
+///     web, App, HttpServer,
+/// };
+///
+/// # fn run() -> std::io::Result<()> {
+/// HttpServer::new(|| {
+///     let scheduler = Scheduler::new(64);
+///
+///     App::new()
+///         .service(
+///             web::resource("/sessions/{id}/signal")
+///                 .wrap(scheduler.priority(Priority::HIGH))
+///                 .to(|| async { "ok" }),
+///         )
+///         .service(
+///             web::scope("/exports")
+///                 .wrap(scheduler.priority(Priority::LOW))
+///                 .route("", web::get().to(|| async { "ok" })),
+///         )
+/// })
+/// # ; Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    state: Rc<State>,
+}
+
+impl Scheduler {
+    /// Constructs a scheduler that admits up to `max_in_flight` requests at a time.
+    ///
+    /// # Panics
+    /// Panics if `max_in_flight` is zero.
+    pub fn new(max_in_flight: usize) -> Self {
+        assert!(max_in_flight > 0, "in-flight budget must be non-zero");
+
+        Self {
+            state: Rc::new(State {
+                max_in_flight,
+                max_queued: Cell::new(usize::MAX),
+                in_flight: Cell::new(0),
+                queue: RefCell::new(BinaryHeap::new()),
+                seq: Cell::new(0),
+            }),
+        }
+    }
+
+    /// Sets the maximum number of requests waiting for admission.
+    ///
+    /// By default, the queue is unbounded.
+    pub fn max_queued(self, max: usize) -> Self {
+        self.state.max_queued.set(max);
+        self
+    }
+
+    /// Returns a middleware that schedules requests to the wrapped routes with `priority`.
+    pub fn priority(&self, priority: Priority) -> Prioritized {
+        Prioritized {
+            state: Rc::clone(&self.state),
+            priority,
+        }
+    }
+
+    /// Returns the number of admitted requests that have not produced a response yet.
+    pub fn in_flight(&self) -> usize {
+        self.state.in_flight.get()
+    }
+
+    /// Returns the number of requests waiting for admission.
+    pub fn queued(&self) -> usize {
+        self.state.queued()
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    max_in_flight: usize,
+    max_queued: Cell<usize>,
+    in_flight: Cell<usize>,
+    queue: RefCell<BinaryHeap<Queued>>,
+    // arrival counter used to admit requests of equal priority in order
+    seq: Cell<u64>,
+}
+
+impl State {
+    fn queued(&self) -> usize {
+        self.queue
+            .borrow()
+            .iter()
+            .filter(|queued| !queued.waiter.cancelled.get())
+            .count()
+    }
+
+    /// Requests a slot, returning `None` if the queue is full.
+    fn acquire(self: &Rc<Self>, priority: Priority) -> Option<Acquire> {
+        let mut queue = self.queue.borrow_mut();
+
+        if self.in_flight.get() < self.max_in_flight && queue.is_empty() {
+            self.in_flight.set(self.in_flight.get() + 1);
+
+            return Some(Acquire {
+                state: Rc::clone(self),
+                waiter: None,
+            });
+        }
+
+        if queue.len() >= self.max_queued.get() {
+            // cancelled requests may still occupy the queue
+            queue.retain(|queued| !queued.waiter.cancelled.get());
+
+            if queue.len() >= self.max_queued.get() {
+                return None;
+            }
+        }
+
+        let seq = self.seq.get();
+        self.seq.set(seq + 1);
+
+        let waiter = Rc::new(Waiter::default());
+
+        queue.push(Queued {
+            priority,
+            seq,
+            waiter: Rc::clone(&waiter),
+        });
+
+        Some(Acquire {
+            state: Rc::clone(self),
+            waiter: Some(waiter),
+        })
+    }
+
+    /// Hands a released slot to the next waiting request or returns it to the budget.
+    fn release(&self) {
+        let mut queue = self.queue.borrow_mut();
+
+        while let Some(queued) = queue.pop() {
+            if queued.waiter.cancelled.get() {
+                continue;
+            }
+
+            queued.waiter.granted.set(true);
+
+            if let Some(waker) = queued.waiter.waker.take() {
+                waker.wake();
+            }
+
+            return;
+        }
+
+        self.in_flight.set(self.in_flight.get() - 1);
+    }
+}
+
+#[derive(Debug, Default)]
+struct Waiter {
+    granted: Cell<bool>,
+    cancelled: Cell<bool>,
+    waker: RefCell<Option<Waker>>,
+}
+
+#[derive(Debug)]
+struct Queued {
+    priority: Priority,
+    seq: u64,
+    waiter: Rc<Waiter>,
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // highest priority first, then earliest arrival
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+/// Future resolving to a [`Permit`] once the request has been admitted.
+struct Acquire {
+    state: Rc<State>,
+    waiter: Option<Rc<Waiter>>,
+}
+
+impl Future for Acquire {
+    type Output = Permit;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(ref waiter) = self.waiter {
+            if !waiter.granted.get() {
+                *waiter.waker.borrow_mut() = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+        }
+
+        self.waiter = None;
+
+        Poll::Ready(Permit {
+            state: Rc::clone(&self.state),
+        })
+    }
+}
+
+impl Drop for Acquire {
+    fn drop(&mut self) {
+        if let Some(ref waiter) = self.waiter {
+            if waiter.granted.get() {
+                // slot was handed over but never used
+                self.state.release();
+            } else {
+                waiter.cancelled.set(true);
+            }
+        }
+    }
+}
+
+/// Slot of the in-flight budget, released when dropped.
+struct Permit {
+    state: Rc<State>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.state.release();
+    }
+}
+
+/// Middleware that schedules requests with a fixed priority. See [`Scheduler::priority()`].
+#[derive(Debug, Clone)]
+pub struct Prioritized {
+    state: Rc<State>,
+    priority: Priority,
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Prioritized
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = PrioritizedMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(PrioritizedMiddleware {
+            service: Rc::new(service),
+            state: Rc::clone(&self.state),
+            priority: self.priority,
+        }))
+    }
+}
+
+/// Prioritized middleware service.
+pub struct PrioritizedMiddleware<S> {
+    service: Rc<S>,
+    state: Rc<State>,
+    priority: Priority,
+}
+
+impl<S, B> Service<ServiceRequest> for PrioritizedMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let Some(acquire) = self.state.acquire(self.priority) else {
+            let res = HttpResponse::new(StatusCode::SERVICE_UNAVAILABLE);
+            return Box::pin(ready(Ok(req.into_response(res).map_into_right_body())));
+        };
+
+        let service = Rc::clone(&self.service);
+
+        Box::pin(async move {
+            let permit = acquire.await;
+            let res = service.call(req).await;
+            drop(permit);
+
+            res.map(ServiceResponse::map_into_left_body)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_rt::time::sleep;
+    use futures_util::future::join_all;
+
+    use super::*;
+    use crate::{
+        test::{call_service, init_service, TestRequest},
+        web, App,
+    };
+
+    #[actix_rt::test]
+    async fn admits_by_priority() {
+        let scheduler = Scheduler::new(1);
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        let handler = |name: &'static str| {
+            let order = Rc::clone(&order);
+            web::to(move || {
+                let order = Rc::clone(&order);
+                async move {
+                    order.borrow_mut().push(name);
+                    sleep(Duration::from_millis(10)).await;
+                    "ok"
+                }
+            })
+        };
+
+        let srv = init_service(
+            App::new()
+                .service(
+                    web::resource("/high")
+                        .wrap(scheduler.priority(Priority::HIGH))
+                        .route(handler("high")),
+                )
+                .service(
+                    web::resource("/low")
+                        .wrap(scheduler.priority(Priority::LOW))
+                        .route(handler("low")),
+                ),
+        )
+        .await;
+
+        let reqs = ["/low", "/low", "/low", "/high", "/high"]
+            .into_iter()
+            .map(|path| call_service(&srv, TestRequest::get().uri(path).to_request()));
+        let responses = join_all(reqs).await;
+
+        assert!(responses.iter().all(|res| res.status() == StatusCode::OK));
+        assert_eq!(*order.borrow(), ["low", "high", "high", "low", "low"]);
+        assert_eq!(scheduler.in_flight(), 0);
+        assert_eq!(scheduler.queued(), 0);
+    }
+
+    #[actix_rt::test]
+    async fn full_queue() {
+        let scheduler = Scheduler::new(1).max_queued(1);
+
+        let srv = init_service(
+            App::new().service(
+                web::resource("/")
+                    .wrap(scheduler.priority(Priority::NORMAL))
+                    .to(|| async {
+                        sleep(Duration::from_millis(10)).await;
+                        "ok"
+                    }),
+            ),
+        )
+        .await;
+
+        let reqs = (0..3).map(|_| call_service(&srv, TestRequest::get().to_request()));
+        let statuses = join_all(reqs)
+            .await
+            .into_iter()
+            .map(|res| res.status())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            statuses,
+            [
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::SERVICE_UNAVAILABLE
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn cancelled_waiter() {
+        let scheduler = Scheduler::new(1);
+        let state = &scheduler.state;
+
+        let permit = state.acquire(Priority::NORMAL).unwrap().await;
+        let waiting = state.acquire(Priority::HIGH).unwrap();
+        assert_eq!(scheduler.queued(), 1);
+
+        drop(waiting);
+        assert_eq!(scheduler.queued(), 0);
+
+        drop(permit);
+        assert_eq!(scheduler.in_flight(), 0);
+    }
+}
@@ -0,0 +1,515 @@
+//! For middleware documentation, see [`Throttle`].
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use actix_http::error::PayloadError;
+use actix_rt::time::{sleep, Sleep};
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Ready};
+use bytes::Bytes;
+use futures_core::{ready, Stream};
+use pin_project_lite::pin_project;
+
+use crate::{
+    body::{BodySize, MessageBody},
+    dev::Payload,
+    service::{ServiceRequest, ServiceResponse},
+    Error, HttpMessage as _,
+};
+
+/// Smallest chunk, in bytes, released by a throttled stream unless the chunk is smaller.
+const MIN_GRANT: usize = 1024;
+
+type KeyFn = dyn Fn(&ServiceRequest) -> Option<String>;
+
+/// Middleware for limiting the bandwidth of request and response bodies.
+///
+/// Bandwidth is shaped using a token bucket per key. By default, requests are keyed by the peer
+/// address of their connection, so that all requests on a connection share its bandwidth. Use
+/// [`key()`](Self::key) to share bandwidth by other identities instead, e.g., an API token.
+/// Requests without a key get a bucket of their own.
+///
+/// Buckets are kept per worker thread and are dropped once they are unused and have refilled.
+///
+/// # Examples
+/// ```
+/// use actix_web::{middleware::Throttle, web, App};
+///
+/// let app = App::new().service(
+///     web::scope("/exports")
+///         // 1 MiB/s per client for downloads and 256 KiB/s for uploads
+///         .wrap(
+///             Throttle::new()
+///                 .response_rate(1024 * 1024)
+///                 .request_rate(256 * 1024)
+///                 .key(|req| {
+///                     req.headers()
+///                         .get("x-api-key")
+///                         .and_then(|key| key.to_str().ok())
+///                         .map(str::to_owned)
+///                 }),
+///         ),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct Throttle {
+    request_rate: Option<u64>,
+    response_rate: Option<u64>,
+    burst: Option<u64>,
+    key: Rc<KeyFn>,
+}
+
+impl Throttle {
+    /// Constructs a `Throttle` middleware that does not limit any bandwidth until a rate is set.
+    pub fn new() -> Self {
+        Self {
+            request_rate: None,
+            response_rate: None,
+            burst: None,
+            key: Rc::new(|req| req.peer_addr().map(|addr| addr.to_string())),
+        }
+    }
+
+    /// Limits request payloads to `bytes_per_sec` per key.
+    ///
+    /// # Panics
+    /// Panics if `bytes_per_sec` is zero.
+    pub fn request_rate(mut self, bytes_per_sec: u64) -> Self {
+        assert!(bytes_per_sec > 0, "throttle rate must be non-zero");
+        self.request_rate = Some(bytes_per_sec);
+        self
+    }
+
+    /// Limits response bodies to `bytes_per_sec` per key.
+    ///
+    /// # Panics
+    /// Panics if `bytes_per_sec` is zero.
+    pub fn response_rate(mut self, bytes_per_sec: u64) -> Self {
+        assert!(bytes_per_sec > 0, "throttle rate must be non-zero");
+        self.response_rate = Some(bytes_per_sec);
+        self
+    }
+
+    /// Sets the number of bytes that can be sent at once after a period of inactivity.
+    ///
+    /// By default, the burst size is one second worth of the rate.
+    ///
+    /// # Panics
+    /// Panics if `bytes` is zero.
+    pub fn burst(mut self, bytes: u64) -> Self {
+        assert!(bytes > 0, "throttle burst size must be non-zero");
+        self.burst = Some(bytes);
+        self
+    }
+
+    /// Sets the function used to determine which requests share bandwidth.
+    ///
+    /// Requests for which `key` returns `None` are throttled individually.
+    pub fn key<F>(mut self, key: F) -> Self
+    where
+        F: Fn(&ServiceRequest) -> Option<String> + 'static,
+    {
+        self.key = Rc::new(key);
+        self
+    }
+
+    fn bucket(&self, rate: Option<u64>) -> Option<TokenBucket> {
+        rate.map(|rate| TokenBucket::new(rate, self.burst.unwrap_or(rate)))
+    }
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Throttle
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    type Response = ServiceResponse<ThrottledBody<B>>;
+    type Error = Error;
+    type Transform = ThrottleMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ThrottleMiddleware {
+            service,
+            config: self.clone(),
+            buckets: RefCell::new(HashMap::new()),
+            purge_at: Cell::new(64),
+        }))
+    }
+}
+
+/// Throttle middleware service.
+pub struct ThrottleMiddleware<S> {
+    service: S,
+    config: Throttle,
+    buckets: RefCell<HashMap<String, Rc<Buckets>>>,
+    // bucket count at which unused and refilled buckets are removed
+    purge_at: Cell<usize>,
+}
+
+impl<S> ThrottleMiddleware<S> {
+    /// Returns the buckets shared by requests with the same key as `req`.
+    fn buckets(&self, req: &ServiceRequest) -> Rc<Buckets> {
+        let new_buckets = || {
+            Rc::new(Buckets {
+                request: self.config.bucket(self.config.request_rate),
+                response: self.config.bucket(self.config.response_rate),
+            })
+        };
+
+        let Some(key) = (self.config.key)(req) else {
+            return new_buckets();
+        };
+
+        let mut buckets = self.buckets.borrow_mut();
+
+        if let Some(shared) = buckets.get(&key) {
+            return Rc::clone(shared);
+        }
+
+        if buckets.len() >= self.purge_at.get() {
+            // a refilled bucket behaves the same as a new one so it can be recreated on demand
+            buckets.retain(|_, shared| Rc::strong_count(shared) > 1 || !shared.is_full());
+            self.purge_at.set((buckets.len() * 2).max(64));
+        }
+
+        let shared = new_buckets();
+        buckets.insert(key, Rc::clone(&shared));
+        shared
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for ThrottleMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    type Response = ServiceResponse<ThrottledBody<B>>;
+    type Error = Error;
+    type Future = ThrottleResponse<S, B>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let buckets = (self.config.request_rate.is_some() || self.config.response_rate.is_some())
+            .then(|| self.buckets(&req));
+
+        if let Some(buckets) = buckets.as_ref().filter(|buckets| buckets.request.is_some()) {
+            let payload = req.take_payload();
+            req.set_payload(Payload::from(Box::pin(ThrottledPayload {
+                payload,
+                shaper: Shaper::new(Rc::clone(buckets), Direction::Request),
+            })
+                as actix_http::BoxedPayloadStream));
+        }
+
+        ThrottleResponse {
+            fut: self.service.call(req),
+            buckets: buckets.filter(|buckets| buckets.response.is_some()),
+        }
+    }
+}
+
+pin_project! {
+    pub struct ThrottleResponse<S, B>
+    where
+        S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    {
+        #[pin]
+        fut: S::Future,
+        buckets: Option<Rc<Buckets>>,
+    }
+}
+
+impl<S, B> Future for ThrottleResponse<S, B>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    type Output = Result<ServiceResponse<ThrottledBody<B>>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.fut.poll(cx))?;
+
+        let shaper = this
+            .buckets
+            .take()
+            .map(|buckets| Shaper::new(buckets, Direction::Response));
+
+        Poll::Ready(Ok(res.map_body(|_, body| ThrottledBody { body, shaper })))
+    }
+}
+
+/// Token bucket refilled at a constant rate, up to its burst size.
+struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: Cell<f64>,
+    updated: Cell<Instant>,
+}
+
+impl TokenBucket {
+    fn new(rate: u64, burst: u64) -> Self {
+        Self {
+            rate: rate as f64,
+            burst: burst as f64,
+            tokens: Cell::new(burst as f64),
+            updated: Cell::new(Instant::now()),
+        }
+    }
+
+    /// Returns true if the bucket has been refilled up to its burst size.
+    fn is_full(&self) -> bool {
+        let elapsed = self.updated.get().elapsed().as_secs_f64();
+        self.tokens.get() + elapsed * self.rate >= self.burst
+    }
+
+    /// Takes up to `len` tokens, returning the number taken or, if too few tokens are available,
+    /// how long to wait before trying again.
+    fn take(&self, len: usize) -> Result<usize, Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated.get()).as_secs_f64();
+        let tokens = (self.tokens.get() + elapsed * self.rate).min(self.burst);
+        self.updated.set(now);
+
+        // avoid releasing tiny chunks when the bucket is nearly empty
+        let min = (len.min(MIN_GRANT) as f64).min(self.burst);
+
+        if tokens >= min {
+            let taken = len.min(tokens as usize);
+            self.tokens.set(tokens - taken as f64);
+            Ok(taken)
+        } else {
+            self.tokens.set(tokens);
+            Err(Duration::from_secs_f64((min - tokens) / self.rate))
+        }
+    }
+}
+
+/// Request and response buckets shared by requests with the same key.
+struct Buckets {
+    request: Option<TokenBucket>,
+    response: Option<TokenBucket>,
+}
+
+impl Buckets {
+    fn is_full(&self) -> bool {
+        [&self.request, &self.response]
+            .into_iter()
+            .flatten()
+            .all(TokenBucket::is_full)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Direction {
+    Request,
+    Response,
+}
+
+/// Releases chunks of a byte stream at the rate allowed by its bucket.
+struct Shaper {
+    buckets: Rc<Buckets>,
+    direction: Direction,
+    pending: Option<Bytes>,
+    delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl Shaper {
+    fn new(buckets: Rc<Buckets>, direction: Direction) -> Self {
+        Self {
+            buckets,
+            direction,
+            pending: None,
+            delay: None,
+        }
+    }
+
+    fn poll_shaped<E>(
+        &mut self,
+        cx: &mut Context<'_>,
+        mut poll_inner: impl FnMut(&mut Context<'_>) -> Poll<Option<Result<Bytes, E>>>,
+    ) -> Poll<Option<Result<Bytes, E>>> {
+        loop {
+            if let Some(delay) = self.delay.as_mut() {
+                ready!(delay.as_mut().poll(cx));
+                self.delay = None;
+            }
+
+            let mut chunk = match self.pending.take() {
+                Some(chunk) => chunk,
+                None => match ready!(poll_inner(cx)) {
+                    Some(Ok(chunk)) if !chunk.is_empty() => chunk,
+                    item => return Poll::Ready(item),
+                },
+            };
+
+            let bucket = match self.direction {
+                Direction::Request => self.buckets.request.as_ref(),
+                Direction::Response => self.buckets.response.as_ref(),
+            };
+
+            let Some(bucket) = bucket else {
+                return Poll::Ready(Some(Ok(chunk)));
+            };
+
+            match bucket.take(chunk.len()) {
+                Ok(len) => {
+                    let granted = chunk.split_to(len);
+
+                    if !chunk.is_empty() {
+                        self.pending = Some(chunk);
+                    }
+
+                    return Poll::Ready(Some(Ok(granted)));
+                }
+
+                Err(wait) => {
+                    self.pending = Some(chunk);
+                    self.delay = Some(Box::pin(sleep(wait)));
+                }
+            }
+        }
+    }
+}
+
+pin_project! {
+    /// Response body whose chunks are released at the throttled rate.
+    pub struct ThrottledBody<B> {
+        #[pin]
+        body: B,
+        shaper: Option<Shaper>,
+    }
+}
+
+impl<B: MessageBody> MessageBody for ThrottledBody<B> {
+    type Error = B::Error;
+
+    #[inline]
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let mut this = self.project();
+
+        match this.shaper {
+            Some(shaper) => shaper.poll_shaped(cx, |cx| this.body.as_mut().poll_next(cx)),
+            None => this.body.poll_next(cx),
+        }
+    }
+}
+
+pin_project! {
+    /// Request payload whose chunks are released at the throttled rate.
+    struct ThrottledPayload {
+        #[pin]
+        payload: Payload,
+        shaper: Shaper,
+    }
+}
+
+impl Stream for ThrottledPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        this.shaper
+            .poll_shaped(cx, |cx| this.payload.as_mut().poll_next(cx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test::{call_service, init_service, read_body, TestRequest},
+        web, App,
+    };
+
+    #[test]
+    fn token_bucket() {
+        let bucket = TokenBucket::new(1000, 2000);
+
+        assert_eq!(bucket.take(1500), Ok(1500));
+        assert_eq!(bucket.take(400), Ok(400));
+
+        // too few tokens to release a chunk of the minimum size
+        assert!(bucket.take(1500).is_err());
+
+        assert_eq!(bucket.take(100), Ok(100));
+
+        let wait = bucket.take(100).unwrap_err();
+        assert!(wait <= Duration::from_millis(100));
+        assert!(wait >= Duration::from_millis(90));
+    }
+
+    #[actix_rt::test]
+    async fn response_rate() {
+        let srv = init_service(
+            App::new()
+                .wrap(Throttle::new().response_rate(20_000).burst(2_000))
+                .default_service(web::to(|| async { vec![b'a'; 4_000] })),
+        )
+        .await;
+
+        let start = Instant::now();
+        let req = TestRequest::default().to_request();
+        let body = read_body(call_service(&srv, req).await).await;
+        assert_eq!(body.len(), 4_000);
+
+        // 2kB burst, then 2kB at 20kB/s
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[actix_rt::test]
+    async fn request_rate_shared_by_key() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    Throttle::new()
+                        .request_rate(20_000)
+                        .burst(2_000)
+                        .key(|_| Some("shared".to_owned())),
+                )
+                .default_service(web::to(|body: Bytes| async move { body.len().to_string() })),
+        )
+        .await;
+
+        let start = Instant::now();
+        let req = TestRequest::default()
+            .set_payload(vec![b'a'; 2_000])
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, "2000");
+        assert!(start.elapsed() < Duration::from_millis(50));
+
+        // burst has been used up by the previous request with the same key
+        let start = Instant::now();
+        let req = TestRequest::default()
+            .set_payload(vec![b'a'; 2_000])
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, "2000");
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+}
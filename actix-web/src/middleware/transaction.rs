@@ -0,0 +1,469 @@
+//! For middleware documentation, see [`Transactional`].
+
+use std::{fmt, rc::Rc};
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{err, ok, ready, Ready};
+use futures_core::future::LocalBoxFuture;
+use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
+
+use crate::{
+    dev::Payload,
+    error,
+    http::StatusCode,
+    service::{ServiceRequest, ServiceResponse},
+    Error, FromRequest, HttpMessage as _, HttpRequest,
+};
+
+/// Hooks for beginning and finishing the per-request transactions of [`Transactional`].
+///
+/// # Examples
+/// ```
+/// use std::{cell::RefCell, rc::Rc};
+///
+/// use actix_web::{dev::ServiceRequest, middleware::TransactionManager, Error};
+/// use futures_core::future::LocalBoxFuture;
+///
+/// /// Collects the statements of a request and applies them all at once on commit.
+/// #[derive(Clone, Default)]
+/// struct Batches {
+///     applied: Rc<RefCell<Vec<String>>>,
+/// }
+///
+/// impl TransactionManager for Batches {
+///     type Transaction = Vec<String>;
+///     type Error = Error;
+///
+///     fn begin(
+///         &self,
+///         _req: &ServiceRequest,
+///     ) -> LocalBoxFuture<'static, Result<Vec<String>, Error>> {
+///         Box::pin(async { Ok(Vec::new()) })
+///     }
+///
+///     fn commit(&self, tx: Vec<String>) -> LocalBoxFuture<'static, Result<(), Error>> {
+///         let applied = Rc::clone(&self.applied);
+///         Box::pin(async move {
+///             applied.borrow_mut().extend(tx);
+///             Ok(())
+///         })
+///     }
+///
+///     fn rollback(&self, _tx: Vec<String>) -> LocalBoxFuture<'static, Result<(), Error>> {
+///         Box::pin(async { Ok(()) })
+///     }
+/// }
+/// ```
+pub trait TransactionManager: 'static {
+    /// Transaction handle that is made available to handlers through the [`Transaction`]
+    /// extractor.
+    type Transaction: 'static;
+
+    /// Error returned by the hooks.
+    type Error: Into<Error>;
+
+    /// Begins a transaction for the request, before the wrapped service is called.
+    ///
+    /// If this fails, the request is not passed to the wrapped service and the error is returned
+    /// instead.
+    fn begin(
+        &self,
+        req: &ServiceRequest,
+    ) -> LocalBoxFuture<'static, Result<Self::Transaction, Self::Error>>;
+
+    /// Commits the transaction after a response that [should be committed](Self::should_commit).
+    ///
+    /// If this fails, the error is returned instead of the response.
+    fn commit(&self, tx: Self::Transaction) -> LocalBoxFuture<'static, Result<(), Self::Error>>;
+
+    /// Rolls back the transaction after an error or a response that should not be committed.
+    ///
+    /// If this fails, the error is logged and the original response is returned.
+    fn rollback(&self, tx: Self::Transaction) -> LocalBoxFuture<'static, Result<(), Self::Error>>;
+
+    /// Returns true if a response with `status` should commit the transaction.
+    ///
+    /// By default, transactions are rolled back for client and server error responses.
+    fn should_commit(&self, status: StatusCode) -> bool {
+        !status.is_client_error() && !status.is_server_error()
+    }
+}
+
+/// Middleware for bracketing request handling in a transaction.
+///
+/// For every request, the [`TransactionManager`] begins a transaction before the wrapped service
+/// is called. Handlers access it through the [`Transaction`] extractor. Once the service has
+/// returned, the transaction is committed or rolled back based on the response status, and always
+/// rolled back if the service returned an error.
+///
+/// The transaction is finished before the response body is sent, so streaming bodies should not
+/// depend on it.
+///
+/// # Examples
+/// ```
+/// # use std::{cell::RefCell, rc::Rc};
+/// # use actix_web::{dev::ServiceRequest, middleware::TransactionManager, Error};
+/// # use futures_core::future::LocalBoxFuture;
+/// # #[derive(Clone, Default)]
+/// # struct Batches(Rc<RefCell<Vec<String>>>);
+/// # impl TransactionManager for Batches {
+/// #     type Transaction = Vec<String>;
+/// #     type Error = Error;
+/// #     fn begin(&self, _: &ServiceRequest) -> LocalBoxFuture<'static, Result<Vec<String>, Error>> {
+/// #         Box::pin(async { Ok(Vec::new()) })
+/// #     }
+/// #     fn commit(&self, _: Vec<String>) -> LocalBoxFuture<'static, Result<(), Error>> {
+/// #         Box::pin(async { Ok(()) })
+/// #     }
+/// #     fn rollback(&self, _: Vec<String>) -> LocalBoxFuture<'static, Result<(), Error>> {
+/// #         Box::pin(async { Ok(()) })
+/// #     }
+/// # }
+/// use actix_web::{
+///     middleware::{Transaction, Transactional},
+///     web, App, HttpResponse,
+/// };
+///
+/// let app = App::new()
+///     .wrap(Transactional::new(Batches::default()))
+///     .route(
+///         "/",
+///         web::post().to(|tx: Transaction<Vec<String>>| async move {
+///             tx.lock().await.push("INSERT INTO visits DEFAULT VALUES".to_owned());
+///             HttpResponse::Created()
+///         }),
+///     );
+/// ```
+pub struct Transactional<M> {
+    manager: Rc<M>,
+}
+
+impl<M: TransactionManager> Transactional<M> {
+    /// Constructs a middleware that manages transactions with `manager`.
+    pub fn new(manager: M) -> Self {
+        Self {
+            manager: Rc::new(manager),
+        }
+    }
+}
+
+impl<M> Clone for Transactional<M> {
+    fn clone(&self) -> Self {
+        Self {
+            manager: Rc::clone(&self.manager),
+        }
+    }
+}
+
+impl<M> fmt::Debug for Transactional<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transactional").finish_non_exhaustive()
+    }
+}
+
+impl<S, B, M> Transform<S, ServiceRequest> for Transactional<M>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+    M: TransactionManager,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = TransactionalMiddleware<S, M>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TransactionalMiddleware {
+            service: Rc::new(service),
+            manager: Rc::clone(&self.manager),
+        }))
+    }
+}
+
+/// Transaction middleware service.
+pub struct TransactionalMiddleware<S, M> {
+    service: Rc<S>,
+    manager: Rc<M>,
+}
+
+impl<S, B, M> Service<ServiceRequest> for TransactionalMiddleware<S, M>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+    M: TransactionManager,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let manager = Rc::clone(&self.manager);
+
+        Box::pin(async move {
+            let tx = manager.begin(&req).await.map_err(Into::into)?;
+
+            let handle = Transaction {
+                slot: Rc::new(Mutex::new(Some(tx))),
+            };
+            req.extensions_mut().insert(handle.clone());
+
+            let res = service.call(req).await;
+
+            let tx = handle
+                .slot
+                .try_lock()
+                .expect("Transaction should not be locked after the handler has returned")
+                .take()
+                .expect("Transaction should only be finished by the middleware");
+
+            match res {
+                Ok(res) if manager.should_commit(res.status()) => {
+                    manager.commit(tx).await.map_err(Into::into)?;
+                    Ok(res)
+                }
+
+                res => {
+                    if let Err(err) = manager.rollback(tx).await {
+                        let err: Error = err.into();
+                        log::error!("Failed to roll back request transaction: {err}");
+                    }
+
+                    res
+                }
+            }
+        })
+    }
+}
+
+/// Handle to the current request's transaction.
+///
+/// Available as an extractor, and in request extensions, when the [`Transactional`] middleware
+/// wraps the service. The type parameter must match the manager's
+/// [`Transaction`](TransactionManager::Transaction) type.
+///
+/// The transaction is owned by the middleware; handlers borrow it through [`lock()`]. The guard may
+/// be held across `.await` points but must be released before the handler returns.
+///
+/// Extraction fails with a `500 Internal Server Error` if the middleware is not installed.
+///
+/// [`lock()`]: Self::lock
+pub struct Transaction<T> {
+    slot: Rc<Mutex<Option<T>>>,
+}
+
+impl<T> Transaction<T> {
+    /// Locks the transaction for use by the handler.
+    pub async fn lock(&self) -> MappedMutexGuard<'_, T> {
+        MutexGuard::map(self.slot.lock().await, |tx| {
+            tx.as_mut()
+                .expect("Transaction should not be used after the request has been handled")
+        })
+    }
+}
+
+impl<T> Clone for Transaction<T> {
+    fn clone(&self) -> Self {
+        Self {
+            slot: Rc::clone(&self.slot),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Transaction<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transaction").finish_non_exhaustive()
+    }
+}
+
+impl<T: 'static> FromRequest for Transaction<T> {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match req.extensions().get::<Transaction<T>>() {
+            Some(tx) => ok(tx.clone()),
+            None => {
+                log::debug!(
+                    "Failed to extract `Transaction<{}>` for `{}` handler. Wrap the app or scope \
+                    with a `Transactional` middleware of a matching manager to use this extractor.",
+                    std::any::type_name::<T>(),
+                    req.match_name().unwrap_or_else(|| req.path())
+                );
+
+                err(error::ErrorInternalServerError(
+                    "Request transaction is not configured.",
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::{
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Rc<RefCell<Vec<String>>>,
+        fail_begin: bool,
+        fail_commit: bool,
+    }
+
+    impl TransactionManager for Recorder {
+        type Transaction = Vec<&'static str>;
+        type Error = Error;
+
+        fn begin(
+            &self,
+            req: &ServiceRequest,
+        ) -> LocalBoxFuture<'static, Result<Self::Transaction, Error>> {
+            self.events
+                .borrow_mut()
+                .push(format!("begin {}", req.path()));
+            let fail = self.fail_begin;
+
+            Box::pin(async move {
+                if fail {
+                    Err(error::ErrorServiceUnavailable("no connection"))
+                } else {
+                    Ok(Vec::new())
+                }
+            })
+        }
+
+        fn commit(&self, tx: Self::Transaction) -> LocalBoxFuture<'static, Result<(), Error>> {
+            self.events.borrow_mut().push(format!("commit {tx:?}"));
+            let fail = self.fail_commit;
+
+            Box::pin(async move {
+                if fail {
+                    Err(error::ErrorConflict("serialization failure"))
+                } else {
+                    Ok(())
+                }
+            })
+        }
+
+        fn rollback(&self, tx: Self::Transaction) -> LocalBoxFuture<'static, Result<(), Error>> {
+            self.events.borrow_mut().push(format!("rollback {tx:?}"));
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    async fn handler(tx: Transaction<Vec<&'static str>>, path: web::Path<u16>) -> HttpResponse {
+        tx.lock().await.push("insert");
+
+        match path.into_inner() {
+            0 => HttpResponse::Ok().finish(),
+            code => HttpResponse::build(StatusCode::from_u16(code).unwrap()).finish(),
+        }
+    }
+
+    async fn failing_handler(tx: Transaction<Vec<&'static str>>) -> Result<HttpResponse, Error> {
+        tx.lock().await.push("insert");
+        Err(error::ErrorBadRequest("invalid"))
+    }
+
+    #[actix_rt::test]
+    async fn commit_or_rollback_by_status() {
+        let recorder = Recorder::default();
+        let events = Rc::clone(&recorder.events);
+
+        let app = test::init_service(
+            App::new()
+                .wrap(Transactional::new(recorder))
+                .route("/fail", web::post().to(failing_handler))
+                .route("/{status}", web::post().to(handler)),
+        )
+        .await;
+
+        let req = TestRequest::post().uri("/0").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::post().uri("/303").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::SEE_OTHER);
+
+        let req = TestRequest::post().uri("/404").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::post().uri("/fail").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        assert_eq!(
+            *events.borrow(),
+            [
+                "begin /0",
+                "commit [\"insert\"]",
+                "begin /303",
+                "commit [\"insert\"]",
+                "begin /404",
+                "rollback [\"insert\"]",
+                "begin /fail",
+                "rollback [\"insert\"]",
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn hook_errors() {
+        let recorder = Recorder {
+            fail_begin: true,
+            ..Default::default()
+        };
+        let events = Rc::clone(&recorder.events);
+
+        let app = test::init_service(
+            App::new()
+                .wrap(Transactional::new(recorder))
+                .route("/{status}", web::post().to(handler)),
+        )
+        .await;
+
+        let req = TestRequest::post().uri("/0").to_request();
+        let err = test::try_call_service(&app, req).await.unwrap_err();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(*events.borrow(), ["begin /0"]);
+
+        let recorder = Recorder {
+            fail_commit: true,
+            ..Default::default()
+        };
+
+        let app = test::init_service(
+            App::new()
+                .wrap(Transactional::new(recorder))
+                .route("/{status}", web::post().to(handler)),
+        )
+        .await;
+
+        let req = TestRequest::post().uri("/0").to_request();
+        let err = test::try_call_service(&app, req).await.unwrap_err();
+        assert_eq!(err.as_response_error().status_code(), StatusCode::CONFLICT);
+    }
+
+    #[actix_rt::test]
+    async fn missing_middleware() {
+        let app = test::init_service(App::new().route("/{status}", web::post().to(handler))).await;
+
+        let req = TestRequest::post().uri("/0").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,125 @@
+//! For middleware documentation, see [`VerifySignedUrl`].
+
+use actix_service::{Service, Transform};
+use actix_utils::future::{ready, Either, Ready};
+
+use crate::{
+    service::{ServiceRequest, ServiceResponse},
+    Error, UrlSigner,
+};
+
+/// Middleware for rejecting requests whose URL is not signed by a [`UrlSigner`], has expired, or
+/// was issued for another method or client address.
+///
+/// Rejected requests fail with a [`SignedUrlError`](crate::error::SignedUrlError), which responds
+/// with `403 Forbidden`.
+///
+/// Requires the `signed-urls` feature.
+///
+/// # Examples
+/// ```
+/// use actix_web::{middleware::VerifySignedUrl, web, App, HttpResponse, UrlSigner};
+///
+/// let signer = UrlSigner::new(b"a secret key of at least 32 bytes");
+///
+/// let app = App::new().service(
+///     web::scope("/downloads")
+///         .wrap(VerifySignedUrl::new(signer))
+///         .route("/{id}", web::get().to(HttpResponse::Ok)),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct VerifySignedUrl {
+    signer: UrlSigner,
+}
+
+impl VerifySignedUrl {
+    /// Constructs a middleware verifying request URLs with `signer`.
+    pub fn new(signer: UrlSigner) -> Self {
+        Self { signer }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for VerifySignedUrl
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = VerifySignedUrlMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(VerifySignedUrlMiddleware {
+            service,
+            signer: self.signer.clone(),
+        }))
+    }
+}
+
+/// Signed URL verification middleware service.
+pub struct VerifySignedUrlMiddleware<S> {
+    service: S,
+    signer: UrlSigner,
+}
+
+impl<S, B> Service<ServiceRequest> for VerifySignedUrlMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        match self.signer.verify(req.request()) {
+            Ok(()) => Either::left(self.service.call(req)),
+            Err(err) => Either::right(ready(Err(err.into()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        http::StatusCode,
+        test::{self, TestRequest},
+        web, App, HttpResponse,
+    };
+
+    #[actix_rt::test]
+    async fn verifies_urls() {
+        let signer = UrlSigner::new([42; 32]);
+
+        let app = test::init_service(
+            App::new().service(
+                web::scope("/downloads")
+                    .wrap(VerifySignedUrl::new(signer.clone()))
+                    .route("/{id}", web::get().to(HttpResponse::Ok)),
+            ),
+        )
+        .await;
+
+        let url = signer
+            .sign("/downloads/7", Duration::from_secs(60))
+            .finish();
+        let req = TestRequest::get().uri(&url).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::get().uri("/downloads/7").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let url = signer.sign("/downloads/8", Duration::ZERO).finish();
+        let req = TestRequest::get().uri(&url).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,292 @@
+//! See [`Process`] for responder documentation.
+
+use std::{
+    io,
+    pin::Pin,
+    process::{ExitStatus, Stdio},
+    task::{ready, Context, Poll},
+};
+
+use actix_http::body::ReaderBody;
+use bytes::Bytes;
+use futures_core::future::LocalBoxFuture;
+use pin_project_lite::pin_project;
+use tokio::{
+    io::AsyncReadExt as _,
+    process::{ChildStdout, Command},
+};
+
+use crate::{
+    body::{BodySize, BoxBody, MessageBody},
+    error::ProcessError,
+    http::header::{HeaderMap, HeaderName, HeaderValue},
+    HttpRequest, HttpResponse, Responder,
+};
+
+/// Name of the trailer field holding the exit code of the command.
+const EXIT_STATUS: HeaderName = HeaderName::from_static("exit-status");
+
+/// Default number of bytes of standard error output that are kept.
+const DEFAULT_STDERR_LIMIT: usize = 8 * 1024;
+
+/// A responder that runs a command and streams its standard output as the response body.
+///
+/// The command is started when the response is created, with its standard input closed. It is
+/// killed if the response body is dropped before the command exits, for example because the client
+/// disconnected.
+///
+/// Standard error output is read while the command runs and the start of it, up to
+/// [`stderr_limit()`](Self::stderr_limit) bytes, is kept. If the command exits unsuccessfully, the
+/// response body fails with a [`ProcessError::Exit`] holding the exit status and the kept output,
+/// which aborts the response so that clients can not mistake it for complete output. This can be
+/// turned off with [`error_on_failure()`](Self::error_on_failure).
+///
+/// When the body is complete, the exit code of the command is sent in an `exit-status` trailer
+/// field. Trailers are only sent for HTTP/2 responses and chunked HTTP/1.1 responses. If the
+/// command is terminated by a signal, the field is `signal`.
+///
+/// If the command can not be started, a `500 Internal Server Error` response is returned.
+///
+/// # Examples
+/// ```no_run
+/// use actix_web::{get, web, Responder};
+///
+/// #[get("/reports/{id}.pdf")]
+/// async fn report(id: web::Path<u32>) -> impl Responder {
+///     let mut cmd = std::process::Command::new("render-report");
+///     cmd.arg(id.to_string());
+///
+///     web::Process::new(cmd).content_type(mime::APPLICATION_PDF)
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Process {
+    command: Command,
+    content_type: mime::Mime,
+    stderr_limit: usize,
+    error_on_failure: bool,
+}
+
+impl Process {
+    /// Constructs a responder that runs `command`.
+    ///
+    /// The standard input and output of the command are overridden.
+    pub fn new(command: impl Into<Command>) -> Self {
+        Self {
+            command: command.into(),
+            content_type: mime::APPLICATION_OCTET_STREAM,
+            stderr_limit: DEFAULT_STDERR_LIMIT,
+            error_on_failure: true,
+        }
+    }
+
+    /// Sets the content type of the response.
+    ///
+    /// Defaults to `application/octet-stream`.
+    pub fn content_type(mut self, content_type: mime::Mime) -> Self {
+        self.content_type = content_type;
+        self
+    }
+
+    /// Sets the number of bytes of standard error output that are kept.
+    ///
+    /// Output past the limit is discarded. Defaults to 8 KiB.
+    pub fn stderr_limit(mut self, limit: usize) -> Self {
+        self.stderr_limit = limit;
+        self
+    }
+
+    /// Sets whether the response body fails when the command exits unsuccessfully.
+    ///
+    /// When disabled, the body ends normally and the exit status is only reported in the
+    /// `exit-status` trailer. Defaults to true.
+    pub fn error_on_failure(mut self, error_on_failure: bool) -> Self {
+        self.error_on_failure = error_on_failure;
+        self
+    }
+}
+
+impl Responder for Process {
+    type Body = BoxBody;
+
+    fn respond_to(mut self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        self.command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        let mut child = match self.command.spawn() {
+            Ok(child) => child,
+            Err(err) => return HttpResponse::from_error(ProcessError::Spawn(err)),
+        };
+
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let mut stderr = child.stderr.take().expect("stderr is piped");
+        let stderr_limit = self.stderr_limit;
+
+        // owns the child, so dropping the body kills the command
+        let exit = Box::pin(async move {
+            let mut captured = Vec::new();
+            (&mut stderr)
+                .take(stderr_limit as u64)
+                .read_to_end(&mut captured)
+                .await?;
+
+            // keep draining so the command is not blocked on a full pipe
+            tokio::io::copy(&mut stderr, &mut tokio::io::sink()).await?;
+
+            let status = child.wait().await?;
+            Ok((status, captured))
+        });
+
+        HttpResponse::Ok()
+            .content_type(self.content_type)
+            .body(ProcessBody {
+                stdout: ReaderBody::new(stdout),
+                exit: Some(exit),
+                exited: None,
+                error_on_failure: self.error_on_failure,
+                trailers: None,
+            })
+    }
+}
+
+pin_project! {
+    struct ProcessBody {
+        #[pin]
+        stdout: ReaderBody<ChildStdout>,
+        exit: Option<LocalBoxFuture<'static, io::Result<(ExitStatus, Vec<u8>)>>>,
+        exited: Option<(ExitStatus, Vec<u8>)>,
+        error_on_failure: bool,
+        trailers: Option<HeaderMap>,
+    }
+}
+
+impl MessageBody for ProcessBody {
+    type Error = ProcessError;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.project();
+
+        // drive the stderr reader along with stdout
+        if let Some(exit) = this.exit {
+            if let Poll::Ready(res) = exit.as_mut().poll(cx) {
+                *this.exit = None;
+                *this.exited = Some(res.map_err(ProcessError::Io)?);
+            }
+        }
+
+        if let Some(chunk) = ready!(this.stdout.poll_next(cx)) {
+            return Poll::Ready(Some(chunk.map_err(ProcessError::Io)));
+        }
+
+        if this.exit.is_some() {
+            return Poll::Pending;
+        }
+
+        let Some((status, stderr)) = this.exited.take() else {
+            return Poll::Ready(None);
+        };
+
+        if !status.success() && *this.error_on_failure {
+            return Poll::Ready(Some(Err(ProcessError::Exit { status, stderr })));
+        }
+
+        let code = match status.code() {
+            Some(code) => HeaderValue::from(code),
+            None => HeaderValue::from_static("signal"),
+        };
+
+        let mut trailers = HeaderMap::new();
+        trailers.insert(EXIT_STATUS, code);
+        *this.trailers = Some(trailers);
+
+        Poll::Ready(None)
+    }
+
+    fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+        self.project().trailers.take()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use actix_http::body::to_bytes;
+    use actix_utils::future::poll_fn;
+
+    use super::*;
+    use crate::{http::StatusCode, test::TestRequest};
+
+    fn sh(script: &str) -> Process {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script);
+        Process::new(cmd)
+    }
+
+    async fn read_with_trailers(
+        mut body: BoxBody,
+    ) -> Result<(Vec<u8>, Option<HeaderMap>), Box<dyn std::error::Error>> {
+        let mut buf = Vec::new();
+
+        while let Some(chunk) = poll_fn(|cx| Pin::new(&mut body).poll_next(cx)).await {
+            buf.extend_from_slice(&chunk?);
+        }
+
+        Ok((buf, Pin::new(&mut body).take_trailers()))
+    }
+
+    #[actix_rt::test]
+    async fn streams_stdout() {
+        let req = TestRequest::default().to_http_request();
+        let res = sh("echo hello; echo oops >&2; echo world")
+            .content_type(mime::TEXT_PLAIN)
+            .respond_to(&req);
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get("content-type").unwrap(), "text/plain");
+
+        let (body, trailers) = read_with_trailers(res.into_body()).await.unwrap();
+        assert_eq!(body, b"hello\nworld\n");
+        assert_eq!(trailers.unwrap().get(EXIT_STATUS).unwrap(), "0");
+    }
+
+    #[actix_rt::test]
+    async fn failed_command() {
+        let req = TestRequest::default().to_http_request();
+
+        let res = sh("echo partial; echo 'no such report' >&2; exit 3")
+            .stderr_limit(9)
+            .respond_to(&req);
+        let err = to_bytes(res.into_body()).await.unwrap_err();
+
+        match err.downcast_ref::<ProcessError>().unwrap() {
+            ProcessError::Exit { status, stderr } => {
+                assert_eq!(status.code(), Some(3));
+                assert_eq!(stderr, b"no such r");
+            }
+            err => panic!("unexpected error: {err}"),
+        }
+
+        let res = sh("echo partial; exit 3")
+            .error_on_failure(false)
+            .respond_to(&req);
+        let (body, trailers) = read_with_trailers(res.into_body()).await.unwrap();
+        assert_eq!(body, b"partial\n");
+        assert_eq!(trailers.unwrap().get(EXIT_STATUS).unwrap(), "3");
+    }
+
+    #[actix_rt::test]
+    async fn spawn_error() {
+        let req = TestRequest::default().to_http_request();
+        let res = Process::new(Command::new("/nonexistent/command")).respond_to(&req);
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,274 @@
+//! Redaction of sensitive request data.
+
+use std::{borrow::Cow, collections::HashSet};
+
+use once_cell::sync::Lazy;
+use serde_json::Value;
+
+use crate::{
+    http::header::{self, HeaderName},
+    HttpRequest,
+};
+
+/// Application-wide policy describing which request data must never be written to logs or other
+/// diagnostic sinks.
+///
+/// A policy lists sensitive header names, query parameters, and JSON paths. It is registered once
+/// using [`App::app_data()`](crate::App::app_data) and is looked up by every component that
+/// records request data, such as the [`Logger`](crate::middleware::Logger) middleware and the
+/// `Debug` output of [`HttpRequest`], so that these sinks cannot drift apart. Use
+/// [`for_request()`](Self::for_request) to apply the same policy in custom middleware.
+///
+/// When no policy is registered, the [default](Self::default) policy is used, which redacts the
+/// `Authorization`, `Proxy-Authorization`, `Cookie`, and `Set-Cookie` headers.
+///
+/// # Examples
+/// ```
+/// use actix_web::{http::header::HeaderName, middleware::Logger, App, RedactionPolicy};
+///
+/// let policy = RedactionPolicy::new()
+///     .header(HeaderName::from_static("x-patient-id"))
+///     .query_param("ssn")
+///     .json_path("patient.name");
+///
+/// let app = App::new()
+///     .app_data(policy)
+///     .wrap(Logger::default());
+/// ```
+#[derive(Debug, Clone)]
+pub struct RedactionPolicy {
+    headers: HashSet<HeaderName>,
+    query_params: HashSet<String>,
+    json_paths: Vec<Vec<String>>,
+}
+
+static DEFAULT_POLICY: Lazy<RedactionPolicy> = Lazy::new(RedactionPolicy::default);
+
+impl RedactionPolicy {
+    /// Replacement text for redacted values.
+    pub const MASK: &'static str = "*redacted*";
+
+    /// Constructs a policy containing the default sensitive headers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Constructs a policy that redacts nothing.
+    pub fn empty() -> Self {
+        Self {
+            headers: HashSet::new(),
+            query_params: HashSet::new(),
+            json_paths: Vec::new(),
+        }
+    }
+
+    /// Marks a header as sensitive. Its value is never recorded.
+    pub fn header(mut self, name: HeaderName) -> Self {
+        self.headers.insert(name);
+        self
+    }
+
+    /// Marks a query parameter as sensitive. Its value is never recorded.
+    ///
+    /// Parameter names are compared exactly, without percent-decoding.
+    pub fn query_param(mut self, name: impl Into<String>) -> Self {
+        self.query_params.insert(name.into());
+        self
+    }
+
+    /// Marks a location in JSON documents as sensitive.
+    ///
+    /// Paths are dot-separated object keys or array indices, optionally prefixed by `$.`. A `*`
+    /// segment matches every element of an array or every value of an object. For example,
+    /// `patients.*.ssn` redacts the `ssn` field of each entry in the `patients` array.
+    pub fn json_path(mut self, path: &str) -> Self {
+        let path = path.strip_prefix("$.").unwrap_or(path);
+        self.json_paths
+            .push(path.split('.').map(str::to_owned).collect());
+        self
+    }
+
+    /// Returns the policy registered for the request's application, or the default policy if none
+    /// is registered.
+    pub fn for_request(req: &HttpRequest) -> &RedactionPolicy {
+        req.app_data::<RedactionPolicy>().unwrap_or(&DEFAULT_POLICY)
+    }
+
+    /// Returns true if values of header `name` must be redacted.
+    pub fn is_sensitive_header(&self, name: &HeaderName) -> bool {
+        self.headers.contains(name)
+    }
+
+    /// Returns true if values of query parameter `name` must be redacted.
+    pub fn is_sensitive_query_param(&self, name: &str) -> bool {
+        self.query_params.contains(name)
+    }
+
+    /// Returns `value`, or the [mask](Self::MASK) if header `name` is sensitive.
+    pub fn redact_header<'a>(&self, name: &HeaderName, value: &'a str) -> &'a str {
+        if self.is_sensitive_header(name) {
+            Self::MASK
+        } else {
+            value
+        }
+    }
+
+    /// Returns `query` with the values of sensitive parameters masked.
+    pub fn redact_query<'a>(&self, query: &'a str) -> Cow<'a, str> {
+        let is_sensitive = |pair: &str| {
+            let name = pair.split_once('=').map_or(pair, |(name, _)| name);
+            self.is_sensitive_query_param(name)
+        };
+
+        if self.query_params.is_empty() || !query.split('&').any(is_sensitive) {
+            return Cow::Borrowed(query);
+        }
+
+        let pairs = query.split('&').map(|pair| {
+            if is_sensitive(pair) {
+                let name = pair.split_once('=').map_or(pair, |(name, _)| name);
+                Cow::Owned(format!("{}={}", name, Self::MASK))
+            } else {
+                Cow::Borrowed(pair)
+            }
+        });
+
+        Cow::Owned(pairs.collect::<Vec<_>>().join("&"))
+    }
+
+    /// Replaces the values at all sensitive JSON paths in `value` with the [mask](Self::MASK).
+    pub fn redact_json(&self, value: &mut Value) {
+        for path in &self.json_paths {
+            redact_json_path(value, path);
+        }
+    }
+}
+
+impl Default for RedactionPolicy {
+    /// Constructs a policy that redacts the `Authorization`, `Proxy-Authorization`, `Cookie`, and
+    /// `Set-Cookie` headers.
+    fn default() -> Self {
+        Self::empty()
+            .header(header::AUTHORIZATION)
+            .header(header::PROXY_AUTHORIZATION)
+            .header(header::COOKIE)
+            .header(header::SET_COOKIE)
+    }
+}
+
+fn redact_json_path(value: &mut Value, path: &[String]) {
+    let Some((segment, rest)) = path.split_first() else {
+        *value = Value::String(RedactionPolicy::MASK.to_owned());
+        return;
+    };
+
+    match value {
+        Value::Object(map) if segment == "*" => {
+            for val in map.values_mut() {
+                redact_json_path(val, rest);
+            }
+        }
+        Value::Object(map) => {
+            if let Some(val) = map.get_mut(segment) {
+                redact_json_path(val, rest);
+            }
+        }
+        Value::Array(items) if segment == "*" => {
+            for val in items {
+                redact_json_path(val, rest);
+            }
+        }
+        Value::Array(items) => {
+            if let Some(val) = segment
+                .parse()
+                .ok()
+                .and_then(|idx: usize| items.get_mut(idx))
+            {
+                redact_json_path(val, rest);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test::TestRequest;
+
+    #[test]
+    fn headers() {
+        let policy = RedactionPolicy::new().header(HeaderName::from_static("x-patient-id"));
+
+        assert_eq!(
+            policy.redact_header(&header::AUTHORIZATION, "Bearer abc"),
+            RedactionPolicy::MASK
+        );
+        assert_eq!(
+            policy.redact_header(&HeaderName::from_static("x-patient-id"), "42"),
+            RedactionPolicy::MASK
+        );
+        assert_eq!(policy.redact_header(&header::ACCEPT, "*/*"), "*/*");
+
+        let policy = RedactionPolicy::empty();
+        assert!(!policy.is_sensitive_header(&header::AUTHORIZATION));
+    }
+
+    #[test]
+    fn query() {
+        let policy = RedactionPolicy::new()
+            .query_param("ssn")
+            .query_param("token");
+
+        assert!(matches!(
+            policy.redact_query("a=1&b=2"),
+            Cow::Borrowed("a=1&b=2")
+        ));
+        assert_eq!(
+            policy.redact_query("a=1&ssn=123-45-6789&token&b=2"),
+            "a=1&ssn=*redacted*&token=*redacted*&b=2"
+        );
+        assert_eq!(policy.redact_query(""), "");
+    }
+
+    #[test]
+    fn json() {
+        let policy = RedactionPolicy::new()
+            .json_path("$.patient.ssn")
+            .json_path("visits.*.notes")
+            .json_path("codes.1")
+            .json_path("missing.field");
+
+        let mut value = json!({
+            "patient": { "name": "Jane", "ssn": "123-45-6789" },
+            "visits": [{ "notes": "a", "id": 1 }, { "notes": "b", "id": 2 }],
+            "codes": ["x", "y"],
+        });
+
+        policy.redact_json(&mut value);
+
+        assert_eq!(
+            value,
+            json!({
+                "patient": { "name": "Jane", "ssn": "*redacted*" },
+                "visits": [{ "notes": "*redacted*", "id": 1 }, { "notes": "*redacted*", "id": 2 }],
+                "codes": ["x", "*redacted*"],
+            })
+        );
+    }
+
+    #[test]
+    fn for_request() {
+        let req = TestRequest::default().to_http_request();
+        assert!(RedactionPolicy::for_request(&req).is_sensitive_header(&header::COOKIE));
+
+        let req = TestRequest::default()
+            .app_data(RedactionPolicy::empty().query_param("ssn"))
+            .to_http_request();
+        let policy = RedactionPolicy::for_request(&req);
+        assert!(!policy.is_sensitive_header(&header::COOKIE));
+        assert!(policy.is_sensitive_query_param("ssn"));
+    }
+}
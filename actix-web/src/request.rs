@@ -20,7 +20,7 @@ use crate::{
     http::{header::HeaderMap, Method, Uri, Version},
     info::ConnectionInfo,
     rmap::ResourceMap,
-    Error, FromRequest, HttpMessage,
+    Error, FromRequest, HttpMessage, RedactionPolicy,
 };
 
 #[cfg(feature = "cookies")]
@@ -202,6 +202,9 @@ impl HttpRequest {
     /// let opt_t = req.conn_data::<PeerCertificate>();
     /// ```
     ///
+    /// Connections accepted by the built-in OpenSSL and Rustls acceptors also carry a
+    /// `dev::TlsInfo` describing their TLS session.
+    ///
     /// [on-connect]: crate::HttpServer::on_connect
     pub fn conn_data<T: 'static>(&self) -> Option<&T> {
         self.inner
@@ -465,8 +468,14 @@ impl fmt::Debug for HttpRequest {
             self.path()
         )?;
 
+        let policy = RedactionPolicy::for_request(self);
+
         if !self.query_string().is_empty() {
-            writeln!(f, "  query: ?{:?}", self.query_string())?;
+            writeln!(
+                f,
+                "  query: ?{:?}",
+                policy.redact_query(self.query_string())
+            )?;
         }
 
         if !self.match_info().is_empty() {
@@ -476,13 +485,11 @@ impl fmt::Debug for HttpRequest {
         writeln!(f, "  headers:")?;
 
         for (key, val) in self.headers().iter() {
-            match key {
-                // redact sensitive header values from debug output
-                &crate::http::header::AUTHORIZATION
-                | &crate::http::header::PROXY_AUTHORIZATION
-                | &crate::http::header::COOKIE => writeln!(f, "    {:?}: {:?}", key, "*redacted*")?,
-
-                _ => writeln!(f, "    {:?}: {:?}", key, val)?,
+            // redact sensitive header values from debug output
+            if policy.is_sensitive_header(key) {
+                writeln!(f, "    {:?}: {:?}", key, RedactionPolicy::MASK)?;
+            } else {
+                writeln!(f, "    {:?}: {:?}", key, val)?;
             }
         }
 
@@ -45,7 +45,7 @@ impl HttpResponse<BoxBody> {
     #[inline]
     pub fn from_error(error: impl Into<Error>) -> Self {
         let error = error.into();
-        let mut response = error.as_response_error().error_response();
+        let mut response = error.error_response();
         response.error = Some(error);
         response
     }
@@ -1,6 +1,8 @@
 use std::{
     any::Any,
-    cmp, fmt, io,
+    cmp, fmt,
+    future::Future,
+    io,
     marker::PhantomData,
     net,
     sync::{Arc, Mutex},
@@ -9,15 +11,24 @@ use std::{
 
 #[cfg(feature = "__tls")]
 use actix_http::TlsAcceptorConfig;
-use actix_http::{body::MessageBody, Extensions, HttpService, KeepAlive, Request, Response};
-use actix_server::{Server, ServerBuilder};
+use actix_http::{
+    body::MessageBody, error::DispatchError, ConnectionInfo, ConnectionObserver, EtagCache,
+    Extensions, HttpService, KeepAlive, Request, Response,
+};
+use actix_server::{GracefulShutdownSignal, Server, ServerBuilder};
 use actix_service::{
-    map_config, IntoServiceFactory, Service, ServiceFactory, ServiceFactoryExt as _,
+    apply_fn_factory, map_config, IntoServiceFactory, Service, ServiceFactory,
+    ServiceFactoryExt as _,
 };
 #[cfg(feature = "openssl")]
 use actix_tls::accept::openssl::reexports::{AlpnError, SslAcceptor, SslAcceptorBuilder};
 
-use crate::{config::AppConfig, Error};
+use crate::{
+    config::AppConfig,
+    load::{self, LoadMonitor},
+    shutdown::{self, ShutdownSequence, ShutdownStage, ShutdownTracker},
+    Error, HttpResponse,
+};
 
 struct Socket {
     scheme: &'static str,
@@ -31,6 +42,8 @@ struct Config {
     client_disconnect_timeout: Duration,
     #[allow(dead_code)] // only dead when no TLS features are enabled
     tls_handshake_timeout: Option<Duration>,
+    #[allow(dead_code)] // only dead when the tls-key-log feature is disabled
+    tls_key_log: bool,
 }
 
 /// An HTTP Server.
@@ -81,6 +94,12 @@ where
     builder: ServerBuilder,
     #[allow(clippy::type_complexity)]
     on_connect_fn: Option<Arc<dyn Fn(&dyn Any, &mut Extensions) + Send + Sync>>,
+    #[allow(clippy::type_complexity)]
+    on_request_head_fn: Option<Arc<dyn Fn(&mut Request) -> Result<(), HttpResponse> + Send + Sync>>,
+    etag_cache: Option<EtagCache>,
+    on_protocol_error_fn: Option<ProtocolErrorFn>,
+    shutdown_sequence: Option<ShutdownSequence>,
+    load_monitor: Option<LoadMonitor>,
     _phantom: PhantomData<(S, B)>,
 }
 
@@ -114,11 +133,17 @@ where
                 client_request_timeout: Duration::from_secs(5),
                 client_disconnect_timeout: Duration::from_secs(1),
                 tls_handshake_timeout: None,
+                tls_key_log: cfg!(debug_assertions),
             })),
             backlog: 1024,
             sockets: Vec::new(),
             builder: ServerBuilder::default(),
             on_connect_fn: None,
+            on_request_head_fn: None,
+            etag_cache: None,
+            on_protocol_error_fn: None,
+            shutdown_sequence: None,
+            load_monitor: None,
             _phantom: PhantomData,
         }
     }
@@ -248,6 +273,21 @@ where
         self
     }
 
+    /// Sets whether TLS listeners write the session secrets of connections to a key log file.
+    ///
+    /// When enabled and the `SSLKEYLOGFILE` environment variable is set, the secrets of each TLS
+    /// connection are appended to the file it names, in the NSS key log format. Tools such as
+    /// Wireshark can then decrypt captured traffic, which helps with debugging interoperability
+    /// issues with clients. Anyone who can read the file can decrypt those connections.
+    ///
+    /// Applies to TLS listeners bound after this call. By default, key logging is enabled in debug
+    /// builds and disabled in release builds.
+    #[cfg(all(feature = "__tls", feature = "tls-key-log"))]
+    pub fn tls_key_log(self, enabled: bool) -> Self {
+        self.config.lock().unwrap().tls_key_log = enabled;
+        self
+    }
+
     #[doc(hidden)]
     #[deprecated(since = "4.0.0", note = "Renamed to `client_disconnect_timeout`.")]
     pub fn client_shutdown(self, dur: u64) -> Self {
@@ -270,6 +310,8 @@ where
     /// - `actix_tls::accept::rustls_0_23::TlsStream<actix_web::rt::net::TcpStream>` when using
     ///   Rustls v0.23.
     /// - `actix_web::rt::net::TcpStream` when no encryption is used.
+    /// - `actix_http::UringStream` when using [`bind_uring()`](Self::bind_uring()) or
+    ///   [`listen_uring()`](Self::listen_uring()).
     ///
     /// See the `on_connect` example for additional details.
     pub fn on_connect<CB>(self, f: CB) -> HttpServer<F, I, S, B>
@@ -283,10 +325,153 @@ where
             sockets: self.sockets,
             builder: self.builder,
             on_connect_fn: Some(Arc::new(f)),
+            on_request_head_fn: self.on_request_head_fn,
+            etag_cache: self.etag_cache,
+            on_protocol_error_fn: self.on_protocol_error_fn,
+            shutdown_sequence: self.shutdown_sequence,
+            load_monitor: self.load_monitor,
             _phantom: PhantomData,
         }
     }
 
+    /// Sets function that will be called once a request head has been parsed, before its payload
+    /// is read.
+    ///
+    /// The function classifies requests before the application is called. It can tag a request by
+    /// inserting into its extensions, which are then available through
+    /// [`HttpRequest::extensions()`](crate::HttpRequest::extensions), reroute it by changing its
+    /// URI, or reject it by returning the response to send instead. The application is not called
+    /// for rejected requests and their payloads are never read, which makes this suitable for
+    /// cheaply bouncing requests that are obviously bad or directing large uploads to dedicated
+    /// handlers.
+    ///
+    /// See [`HttpServiceBuilder::on_request_head()`](actix_http::HttpServiceBuilder::on_request_head)
+    /// for details.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use actix_web::{dev::Request, http::header, web, App, HttpMessage as _, HttpResponse, HttpServer};
+    ///
+    /// # async fn upload() -> &'static str { "" }
+    /// # async fn index() -> &'static str { "" }
+    /// # #[actix_web::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// HttpServer::new(|| {
+    ///     App::new()
+    ///         .route("/uploads/large", web::post().to(upload))
+    ///         .default_service(web::to(index))
+    /// })
+    /// .on_request_head(|req: &mut Request| {
+    ///     if !req.headers().contains_key(header::AUTHORIZATION) {
+    ///         return Err(HttpResponse::Unauthorized().finish());
+    ///     }
+    ///
+    ///     // divert large uploads to the dedicated upload handler
+    ///     if req.headers().contains_key(header::CONTENT_LENGTH) && req.path() == "/upload" {
+    ///         req.head_mut().uri = "/uploads/large".parse().unwrap();
+    ///     }
+    ///
+    ///     Ok(())
+    /// })
+    /// .bind(("127.0.0.1", 8080))?
+    /// .run()
+    /// .await
+    /// # }
+    /// ```
+    pub fn on_request_head<CB>(mut self, f: CB) -> Self
+    where
+        CB: Fn(&mut Request) -> Result<(), HttpResponse> + Send + Sync + 'static,
+    {
+        self.on_request_head_fn = Some(Arc::new(f));
+        self
+    }
+
+    /// Sets the cache of current entity tags that conditional requests are answered from.
+    ///
+    /// `GET` and `HEAD` requests for a resource in the cache whose `If-None-Match` header matches
+    /// its current entity tag are answered with `304 Not Modified` without calling the
+    /// application, after the [request head callback](Self::on_request_head) has run. This spares
+    /// the handlers of widely-polled resources from answering each poll. Handlers keep a clone of
+    /// the cache, e.g. in app data, and update the entity tag of a resource whenever it changes.
+    ///
+    /// See [`EtagCache`](crate::dev::EtagCache) for details.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use actix_web::{
+    ///     dev::EtagCache,
+    ///     get,
+    ///     http::header::{self, HeaderValue},
+    ///     web, App, HttpResponse, HttpServer,
+    /// };
+    ///
+    /// #[get("/schedule")]
+    /// async fn schedule(etags: web::Data<EtagCache>) -> HttpResponse {
+    ///     let version = 42; // e.g., loaded with the schedule
+    ///     let etag = HeaderValue::from_str(&format!("\"{version}\"")).unwrap();
+    ///
+    ///     etags.insert("/schedule", etag.clone());
+    ///
+    ///     HttpResponse::Ok()
+    ///         .insert_header((header::ETAG, etag))
+    ///         .body("...")
+    /// }
+    ///
+    /// # #[actix_web::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// let etags = EtagCache::new();
+    /// let data = web::Data::new(etags.clone());
+    ///
+    /// HttpServer::new(move || App::new().app_data(data.clone()).service(schedule))
+    ///     .etag_cache(etags)
+    ///     .bind(("127.0.0.1", 8080))?
+    ///     .run()
+    ///     .await
+    /// # }
+    /// ```
+    pub fn etag_cache(mut self, etags: EtagCache) -> Self {
+        self.etag_cache = Some(etags);
+        self
+    }
+
+    /// Sets function that will be called when a client violates the HTTP protocol, e.g., by sending
+    /// a malformed request.
+    ///
+    /// The function receives the client's peer address, if known, the error, and up to the first
+    /// 64 bytes of the input that was being parsed when the error was found. This allows logging
+    /// and alerting on spikes of malformed requests, which are otherwise only answered with an
+    /// error response. I/O errors and timeouts are not reported.
+    ///
+    /// See [`ConnectionObserver::protocol_error()`] for details.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use actix_web::{web, App, HttpServer};
+    ///
+    /// # #[actix_web::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// HttpServer::new(|| App::new().route("/", web::get().to(|| async { "hello" })))
+    ///     .on_protocol_error(|peer_addr, err, data| {
+    ///         log::warn!(
+    ///             "malformed request from {peer_addr:?}: {err}; data: {:?}",
+    ///             data.escape_ascii().to_string(),
+    ///         );
+    ///     })
+    ///     .bind(("127.0.0.1", 8080))?
+    ///     .run()
+    ///     .await
+    /// # }
+    /// ```
+    ///
+    /// [`ConnectionObserver::protocol_error()`]: actix_http::ConnectionObserver::protocol_error
+    pub fn on_protocol_error<CB>(mut self, f: CB) -> Self
+    where
+        CB: Fn(Option<net::SocketAddr>, &DispatchError, &[u8]) + Send + Sync + 'static,
+    {
+        self.on_protocol_error_fn = Some(Arc::new(f));
+        self
+    }
+
     /// Sets server host name.
     ///
     /// Host name is used by application router as a hostname for url generation. Check
@@ -323,6 +508,28 @@ where
         self
     }
 
+    /// Sets the stages of graceful shutdowns.
+    ///
+    /// Without a shutdown sequence, HTTP/2 connections are drained and long-lived sessions are
+    /// notified to close as soon as a graceful shutdown starts. With one, they are notified in
+    /// separate stages, in between which in-flight requests are awaited and hooks are run. See
+    /// [`ShutdownSequence`] for details.
+    ///
+    /// Must be called before binding any listeners, and requires the server to be run within an
+    /// Actix system, such as one started by `#[actix_web::main]`.
+    pub fn shutdown_sequence(mut self, sequence: ShutdownSequence) -> Self {
+        self.shutdown_sequence = Some(sequence);
+        self
+    }
+
+    /// Sets a monitor that tracks the load of the server's workers and listeners.
+    ///
+    /// See [`LoadMonitor`] for details. Must be called before binding any listeners.
+    pub fn load_monitor(mut self, monitor: LoadMonitor) -> Self {
+        self.load_monitor = Some(monitor);
+        self
+    }
+
     /// Returns addresses of bound sockets.
     pub fn addrs(&self) -> Vec<net::SocketAddr> {
         self.sockets.iter().map(|s| s.addr).collect()
@@ -408,6 +615,22 @@ where
         Ok(self)
     }
 
+    /// Resolves socket address(es) and binds server to created listener(s) for plaintext HTTP/1.x
+    /// connections served using io_uring-backed reads and writes.
+    ///
+    /// See [`bind()`](Self::bind()) for more details on `addrs` argument and
+    /// [`listen_uring()`](Self::listen_uring()) for more details on the connections.
+    #[cfg(feature = "experimental-io-uring")]
+    pub fn bind_uring<A: net::ToSocketAddrs>(mut self, addrs: A) -> io::Result<Self> {
+        let sockets = bind_addrs(addrs, self.backlog)?;
+
+        for lst in sockets {
+            self = self.listen_uring(lst)?;
+        }
+
+        Ok(self)
+    }
+
     /// Resolves socket address(es) and binds server to created listener(s) for TLS connections
     /// using Rustls v0.20.
     ///
@@ -496,7 +719,7 @@ where
         A: net::ToSocketAddrs,
     {
         let sockets = bind_addrs(addrs, self.backlog)?;
-        let acceptor = openssl_acceptor(builder)?;
+        let acceptor = self.build_openssl_acceptor(builder)?;
 
         for lst in sockets {
             self = self.listen_openssl_inner(lst, acceptor.clone())?;
@@ -519,7 +742,20 @@ where
             scheme: "http",
         });
 
+        if let Some(monitor) = &self.load_monitor {
+            monitor.add_listener(addr);
+        }
+
         let on_connect_fn = self.on_connect_fn.clone();
+        let on_request_head_fn = self.on_request_head_fn.clone();
+        let etag_cache = self.etag_cache.clone();
+        let on_protocol_error_fn = self.on_protocol_error_fn.clone();
+        let shutdown_tracker = self
+            .shutdown_sequence
+            .as_ref()
+            .map(ShutdownSequence::tracker);
+        let load_monitor = self.load_monitor.clone();
+        let shutdown = self.builder.graceful_shutdown_signal();
 
         self.builder =
             self.builder
@@ -528,6 +764,16 @@ where
                     let host = cfg.host.clone().unwrap_or_else(|| format!("{}", addr));
 
                     let mut svc = HttpService::build()
+                        .shutdown_signal(shutdown_stage(
+                            &shutdown,
+                            &shutdown_tracker,
+                            ShutdownStage::Drain,
+                        ))
+                        .session_close_signal(shutdown_stage(
+                            &shutdown,
+                            &shutdown_tracker,
+                            ShutdownStage::CloseSessions,
+                        ))
                         .keep_alive(cfg.keep_alive)
                         .client_request_timeout(cfg.client_request_timeout)
                         .client_disconnect_timeout(cfg.client_disconnect_timeout)
@@ -538,9 +784,28 @@ where
                             svc.on_connect_ext(move |io: &_, ext: _| (handler)(io as &dyn Any, ext))
                     };
 
+                    if let Some(handler) = on_request_head_fn.clone() {
+                        svc = svc
+                            .on_request_head(move |req: &mut _| (handler)(req).map_err(Into::into));
+                    }
+
+                    if let Some(ref etags) = etag_cache {
+                        svc = svc.etag_cache(etags.clone());
+                    }
+
+                    if let Some(handler) = on_protocol_error_fn.clone() {
+                        svc = svc.observer(ProtocolErrorObserver(handler));
+                    }
+
                     let fac = factory()
                         .into_factory()
                         .map_err(|err| err.into().error_response());
+                    let tracker = shutdown_tracker.clone();
+                    let worker = load_monitor.as_ref().map(LoadMonitor::worker);
+                    let fac = apply_fn_factory(fac, move |req: Request, srv| {
+                        let fut = load::track(worker.clone(), srv.call(req));
+                        shutdown::track::<_, _, _, B>(tracker.clone(), fut)
+                    });
 
                     svc.finish(map_config(fac, move |_| {
                         AppConfig::new(false, host.clone(), addr)
@@ -563,7 +828,20 @@ where
             scheme: "http",
         });
 
+        if let Some(monitor) = &self.load_monitor {
+            monitor.add_listener(addr);
+        }
+
         let on_connect_fn = self.on_connect_fn.clone();
+        let on_request_head_fn = self.on_request_head_fn.clone();
+        let etag_cache = self.etag_cache.clone();
+        let on_protocol_error_fn = self.on_protocol_error_fn.clone();
+        let shutdown_tracker = self
+            .shutdown_sequence
+            .as_ref()
+            .map(ShutdownSequence::tracker);
+        let load_monitor = self.load_monitor.clone();
+        let shutdown = self.builder.graceful_shutdown_signal();
 
         self.builder =
             self.builder
@@ -572,6 +850,16 @@ where
                     let host = cfg.host.clone().unwrap_or_else(|| format!("{}", addr));
 
                     let mut svc = HttpService::build()
+                        .shutdown_signal(shutdown_stage(
+                            &shutdown,
+                            &shutdown_tracker,
+                            ShutdownStage::Drain,
+                        ))
+                        .session_close_signal(shutdown_stage(
+                            &shutdown,
+                            &shutdown_tracker,
+                            ShutdownStage::CloseSessions,
+                        ))
                         .keep_alive(cfg.keep_alive)
                         .client_request_timeout(cfg.client_request_timeout)
                         .client_disconnect_timeout(cfg.client_disconnect_timeout)
@@ -582,9 +870,28 @@ where
                             svc.on_connect_ext(move |io: &_, ext: _| (handler)(io as &dyn Any, ext))
                     };
 
+                    if let Some(handler) = on_request_head_fn.clone() {
+                        svc = svc
+                            .on_request_head(move |req: &mut _| (handler)(req).map_err(Into::into));
+                    }
+
+                    if let Some(ref etags) = etag_cache {
+                        svc = svc.etag_cache(etags.clone());
+                    }
+
+                    if let Some(handler) = on_protocol_error_fn.clone() {
+                        svc = svc.observer(ProtocolErrorObserver(handler));
+                    }
+
                     let fac = factory()
                         .into_factory()
                         .map_err(|err| err.into().error_response());
+                    let tracker = shutdown_tracker.clone();
+                    let worker = load_monitor.as_ref().map(LoadMonitor::worker);
+                    let fac = apply_fn_factory(fac, move |req: Request, srv| {
+                        let fut = load::track(worker.clone(), srv.call(req));
+                        shutdown::track::<_, _, _, B>(tracker.clone(), fut)
+                    });
 
                     svc.finish(map_config(fac, move |_| {
                         AppConfig::new(false, host.clone(), addr)
@@ -595,6 +902,98 @@ where
         Ok(self)
     }
 
+    /// Binds to existing listener for accepting incoming plaintext HTTP/1.x connections that are
+    /// served using io_uring-backed reads and writes.
+    ///
+    /// Accepted connections are moved onto the worker's io_uring instance and passed to
+    /// [`on_connect()`](Self::on_connect()) as `actix_http::UringStream`s.
+    ///
+    /// See [`listen()`](Self::listen) for more details on the `lst` argument.
+    #[cfg(feature = "experimental-io-uring")]
+    pub fn listen_uring(mut self, lst: net::TcpListener) -> io::Result<Self> {
+        let cfg = Arc::clone(&self.config);
+        let factory = self.factory.clone();
+        let addr = lst.local_addr().unwrap();
+
+        self.sockets.push(Socket {
+            addr,
+            scheme: "http",
+        });
+
+        if let Some(monitor) = &self.load_monitor {
+            monitor.add_listener(addr);
+        }
+
+        let on_connect_fn = self.on_connect_fn.clone();
+        let on_request_head_fn = self.on_request_head_fn.clone();
+        let etag_cache = self.etag_cache.clone();
+        let on_protocol_error_fn = self.on_protocol_error_fn.clone();
+        let shutdown_tracker = self
+            .shutdown_sequence
+            .as_ref()
+            .map(ShutdownSequence::tracker);
+        let load_monitor = self.load_monitor.clone();
+        let shutdown = self.builder.graceful_shutdown_signal();
+
+        self.builder =
+            self.builder
+                .listen(format!("actix-web-service-{}", addr), lst, move || {
+                    let cfg = cfg.lock().unwrap();
+                    let host = cfg.host.clone().unwrap_or_else(|| format!("{}", addr));
+
+                    let mut svc = HttpService::build()
+                        .shutdown_signal(shutdown_stage(
+                            &shutdown,
+                            &shutdown_tracker,
+                            ShutdownStage::Drain,
+                        ))
+                        .session_close_signal(shutdown_stage(
+                            &shutdown,
+                            &shutdown_tracker,
+                            ShutdownStage::CloseSessions,
+                        ))
+                        .keep_alive(cfg.keep_alive)
+                        .client_request_timeout(cfg.client_request_timeout)
+                        .client_disconnect_timeout(cfg.client_disconnect_timeout)
+                        .local_addr(addr);
+
+                    if let Some(handler) = on_connect_fn.clone() {
+                        svc =
+                            svc.on_connect_ext(move |io: &_, ext: _| (handler)(io as &dyn Any, ext))
+                    };
+
+                    if let Some(handler) = on_request_head_fn.clone() {
+                        svc = svc
+                            .on_request_head(move |req: &mut _| (handler)(req).map_err(Into::into));
+                    }
+
+                    if let Some(ref etags) = etag_cache {
+                        svc = svc.etag_cache(etags.clone());
+                    }
+
+                    if let Some(handler) = on_protocol_error_fn.clone() {
+                        svc = svc.observer(ProtocolErrorObserver(handler));
+                    }
+
+                    let fac = factory()
+                        .into_factory()
+                        .map_err(|err| err.into().error_response());
+                    let tracker = shutdown_tracker.clone();
+                    let worker = load_monitor.as_ref().map(LoadMonitor::worker);
+                    let fac = apply_fn_factory(fac, move |req: Request, srv| {
+                        let fut = load::track(worker.clone(), srv.call(req));
+                        shutdown::track::<_, _, _, B>(tracker.clone(), fut)
+                    });
+
+                    svc.finish(map_config(fac, move |_| {
+                        AppConfig::new(false, host.clone(), addr)
+                    }))
+                    .tcp_uring()
+                })?;
+
+        Ok(self)
+    }
+
     /// Binds to existing listener for accepting incoming TLS connection requests using Rustls
     /// v0.20.
     ///
@@ -631,6 +1030,15 @@ where
         lst: net::TcpListener,
         config: actix_tls::accept::rustls_0_20::reexports::ServerConfig,
     ) -> io::Result<Self> {
+        #[cfg(feature = "tls-key-log")]
+        let config = {
+            let mut config = config;
+            if self.config.lock().unwrap().tls_key_log {
+                config.key_log = Arc::new(tls_rustls_0_20::KeyLogFile::new());
+            }
+            config
+        };
+
         let factory = self.factory.clone();
         let cfg = Arc::clone(&self.config);
         let addr = lst.local_addr().unwrap();
@@ -639,7 +1047,20 @@ where
             scheme: "https",
         });
 
+        if let Some(monitor) = &self.load_monitor {
+            monitor.add_listener(addr);
+        }
+
         let on_connect_fn = self.on_connect_fn.clone();
+        let on_request_head_fn = self.on_request_head_fn.clone();
+        let etag_cache = self.etag_cache.clone();
+        let on_protocol_error_fn = self.on_protocol_error_fn.clone();
+        let shutdown_tracker = self
+            .shutdown_sequence
+            .as_ref()
+            .map(ShutdownSequence::tracker);
+        let load_monitor = self.load_monitor.clone();
+        let shutdown = self.builder.graceful_shutdown_signal();
 
         self.builder =
             self.builder
@@ -648,6 +1069,16 @@ where
                     let host = c.host.clone().unwrap_or_else(|| format!("{}", addr));
 
                     let svc = HttpService::build()
+                        .shutdown_signal(shutdown_stage(
+                            &shutdown,
+                            &shutdown_tracker,
+                            ShutdownStage::Drain,
+                        ))
+                        .session_close_signal(shutdown_stage(
+                            &shutdown,
+                            &shutdown_tracker,
+                            ShutdownStage::CloseSessions,
+                        ))
                         .keep_alive(c.keep_alive)
                         .client_request_timeout(c.client_request_timeout)
                         .client_disconnect_timeout(c.client_disconnect_timeout);
@@ -658,9 +1089,33 @@ where
                         svc
                     };
 
+                    let svc = if let Some(handler) = on_request_head_fn.clone() {
+                        svc.on_request_head(move |req: &mut _| (handler)(req).map_err(Into::into))
+                    } else {
+                        svc
+                    };
+
+                    let svc = if let Some(ref etags) = etag_cache {
+                        svc.etag_cache(etags.clone())
+                    } else {
+                        svc
+                    };
+
+                    let svc = if let Some(handler) = on_protocol_error_fn.clone() {
+                        svc.observer(ProtocolErrorObserver(handler))
+                    } else {
+                        svc
+                    };
+
                     let fac = factory()
                         .into_factory()
                         .map_err(|err| err.into().error_response());
+                    let tracker = shutdown_tracker.clone();
+                    let worker = load_monitor.as_ref().map(LoadMonitor::worker);
+                    let fac = apply_fn_factory(fac, move |req: Request, srv| {
+                        let fut = load::track(worker.clone(), srv.call(req));
+                        shutdown::track::<_, _, _, B>(tracker.clone(), fut)
+                    });
 
                     let acceptor_config = match c.tls_handshake_timeout {
                         Some(dur) => TlsAcceptorConfig::default().handshake_timeout(dur),
@@ -682,6 +1137,15 @@ where
         lst: net::TcpListener,
         config: actix_tls::accept::rustls_0_21::reexports::ServerConfig,
     ) -> io::Result<Self> {
+        #[cfg(feature = "tls-key-log")]
+        let config = {
+            let mut config = config;
+            if self.config.lock().unwrap().tls_key_log {
+                config.key_log = Arc::new(tls_rustls_0_21::KeyLogFile::new());
+            }
+            config
+        };
+
         let factory = self.factory.clone();
         let cfg = Arc::clone(&self.config);
         let addr = lst.local_addr().unwrap();
@@ -690,7 +1154,20 @@ where
             scheme: "https",
         });
 
+        if let Some(monitor) = &self.load_monitor {
+            monitor.add_listener(addr);
+        }
+
         let on_connect_fn = self.on_connect_fn.clone();
+        let on_request_head_fn = self.on_request_head_fn.clone();
+        let etag_cache = self.etag_cache.clone();
+        let on_protocol_error_fn = self.on_protocol_error_fn.clone();
+        let shutdown_tracker = self
+            .shutdown_sequence
+            .as_ref()
+            .map(ShutdownSequence::tracker);
+        let load_monitor = self.load_monitor.clone();
+        let shutdown = self.builder.graceful_shutdown_signal();
 
         self.builder =
             self.builder
@@ -699,6 +1176,16 @@ where
                     let host = c.host.clone().unwrap_or_else(|| format!("{}", addr));
 
                     let svc = HttpService::build()
+                        .shutdown_signal(shutdown_stage(
+                            &shutdown,
+                            &shutdown_tracker,
+                            ShutdownStage::Drain,
+                        ))
+                        .session_close_signal(shutdown_stage(
+                            &shutdown,
+                            &shutdown_tracker,
+                            ShutdownStage::CloseSessions,
+                        ))
                         .keep_alive(c.keep_alive)
                         .client_request_timeout(c.client_request_timeout)
                         .client_disconnect_timeout(c.client_disconnect_timeout);
@@ -709,9 +1196,33 @@ where
                         svc
                     };
 
+                    let svc = if let Some(handler) = on_request_head_fn.clone() {
+                        svc.on_request_head(move |req: &mut _| (handler)(req).map_err(Into::into))
+                    } else {
+                        svc
+                    };
+
+                    let svc = if let Some(ref etags) = etag_cache {
+                        svc.etag_cache(etags.clone())
+                    } else {
+                        svc
+                    };
+
+                    let svc = if let Some(handler) = on_protocol_error_fn.clone() {
+                        svc.observer(ProtocolErrorObserver(handler))
+                    } else {
+                        svc
+                    };
+
                     let fac = factory()
                         .into_factory()
                         .map_err(|err| err.into().error_response());
+                    let tracker = shutdown_tracker.clone();
+                    let worker = load_monitor.as_ref().map(LoadMonitor::worker);
+                    let fac = apply_fn_factory(fac, move |req: Request, srv| {
+                        let fut = load::track(worker.clone(), srv.call(req));
+                        shutdown::track::<_, _, _, B>(tracker.clone(), fut)
+                    });
 
                     let acceptor_config = match c.tls_handshake_timeout {
                         Some(dur) => TlsAcceptorConfig::default().handshake_timeout(dur),
@@ -748,6 +1259,15 @@ where
         lst: net::TcpListener,
         config: actix_tls::accept::rustls_0_22::reexports::ServerConfig,
     ) -> io::Result<Self> {
+        #[cfg(feature = "tls-key-log")]
+        let config = {
+            let mut config = config;
+            if self.config.lock().unwrap().tls_key_log {
+                config.key_log = Arc::new(tls_rustls_0_22::KeyLogFile::new());
+            }
+            config
+        };
+
         let factory = self.factory.clone();
         let cfg = Arc::clone(&self.config);
         let addr = lst.local_addr().unwrap();
@@ -756,7 +1276,20 @@ where
             scheme: "https",
         });
 
+        if let Some(monitor) = &self.load_monitor {
+            monitor.add_listener(addr);
+        }
+
         let on_connect_fn = self.on_connect_fn.clone();
+        let on_request_head_fn = self.on_request_head_fn.clone();
+        let etag_cache = self.etag_cache.clone();
+        let on_protocol_error_fn = self.on_protocol_error_fn.clone();
+        let shutdown_tracker = self
+            .shutdown_sequence
+            .as_ref()
+            .map(ShutdownSequence::tracker);
+        let load_monitor = self.load_monitor.clone();
+        let shutdown = self.builder.graceful_shutdown_signal();
 
         self.builder =
             self.builder
@@ -765,6 +1298,16 @@ where
                     let host = c.host.clone().unwrap_or_else(|| format!("{}", addr));
 
                     let svc = HttpService::build()
+                        .shutdown_signal(shutdown_stage(
+                            &shutdown,
+                            &shutdown_tracker,
+                            ShutdownStage::Drain,
+                        ))
+                        .session_close_signal(shutdown_stage(
+                            &shutdown,
+                            &shutdown_tracker,
+                            ShutdownStage::CloseSessions,
+                        ))
                         .keep_alive(c.keep_alive)
                         .client_request_timeout(c.client_request_timeout)
                         .client_disconnect_timeout(c.client_disconnect_timeout);
@@ -775,9 +1318,33 @@ where
                         svc
                     };
 
+                    let svc = if let Some(handler) = on_request_head_fn.clone() {
+                        svc.on_request_head(move |req: &mut _| (handler)(req).map_err(Into::into))
+                    } else {
+                        svc
+                    };
+
+                    let svc = if let Some(ref etags) = etag_cache {
+                        svc.etag_cache(etags.clone())
+                    } else {
+                        svc
+                    };
+
+                    let svc = if let Some(handler) = on_protocol_error_fn.clone() {
+                        svc.observer(ProtocolErrorObserver(handler))
+                    } else {
+                        svc
+                    };
+
                     let fac = factory()
                         .into_factory()
                         .map_err(|err| err.into().error_response());
+                    let tracker = shutdown_tracker.clone();
+                    let worker = load_monitor.as_ref().map(LoadMonitor::worker);
+                    let fac = apply_fn_factory(fac, move |req: Request, srv| {
+                        let fut = load::track(worker.clone(), srv.call(req));
+                        shutdown::track::<_, _, _, B>(tracker.clone(), fut)
+                    });
 
                     let acceptor_config = match c.tls_handshake_timeout {
                         Some(dur) => TlsAcceptorConfig::default().handshake_timeout(dur),
@@ -814,6 +1381,15 @@ where
         lst: net::TcpListener,
         config: actix_tls::accept::rustls_0_23::reexports::ServerConfig,
     ) -> io::Result<Self> {
+        #[cfg(feature = "tls-key-log")]
+        let config = {
+            let mut config = config;
+            if self.config.lock().unwrap().tls_key_log {
+                config.key_log = Arc::new(tls_rustls_0_23::KeyLogFile::new());
+            }
+            config
+        };
+
         let factory = self.factory.clone();
         let cfg = Arc::clone(&self.config);
         let addr = lst.local_addr().unwrap();
@@ -822,7 +1398,20 @@ where
             scheme: "https",
         });
 
+        if let Some(monitor) = &self.load_monitor {
+            monitor.add_listener(addr);
+        }
+
         let on_connect_fn = self.on_connect_fn.clone();
+        let on_request_head_fn = self.on_request_head_fn.clone();
+        let etag_cache = self.etag_cache.clone();
+        let on_protocol_error_fn = self.on_protocol_error_fn.clone();
+        let shutdown_tracker = self
+            .shutdown_sequence
+            .as_ref()
+            .map(ShutdownSequence::tracker);
+        let load_monitor = self.load_monitor.clone();
+        let shutdown = self.builder.graceful_shutdown_signal();
 
         self.builder =
             self.builder
@@ -831,6 +1420,16 @@ where
                     let host = c.host.clone().unwrap_or_else(|| format!("{}", addr));
 
                     let svc = HttpService::build()
+                        .shutdown_signal(shutdown_stage(
+                            &shutdown,
+                            &shutdown_tracker,
+                            ShutdownStage::Drain,
+                        ))
+                        .session_close_signal(shutdown_stage(
+                            &shutdown,
+                            &shutdown_tracker,
+                            ShutdownStage::CloseSessions,
+                        ))
                         .keep_alive(c.keep_alive)
                         .client_request_timeout(c.client_request_timeout)
                         .client_disconnect_timeout(c.client_disconnect_timeout);
@@ -841,9 +1440,33 @@ where
                         svc
                     };
 
+                    let svc = if let Some(handler) = on_request_head_fn.clone() {
+                        svc.on_request_head(move |req: &mut _| (handler)(req).map_err(Into::into))
+                    } else {
+                        svc
+                    };
+
+                    let svc = if let Some(ref etags) = etag_cache {
+                        svc.etag_cache(etags.clone())
+                    } else {
+                        svc
+                    };
+
+                    let svc = if let Some(handler) = on_protocol_error_fn.clone() {
+                        svc.observer(ProtocolErrorObserver(handler))
+                    } else {
+                        svc
+                    };
+
                     let fac = factory()
                         .into_factory()
                         .map_err(|err| err.into().error_response());
+                    let tracker = shutdown_tracker.clone();
+                    let worker = load_monitor.as_ref().map(LoadMonitor::worker);
+                    let fac = apply_fn_factory(fac, move |req: Request, srv| {
+                        let fut = load::track(worker.clone(), srv.call(req));
+                        shutdown::track::<_, _, _, B>(tracker.clone(), fut)
+                    });
 
                     let acceptor_config = match c.tls_handshake_timeout {
                         Some(dur) => TlsAcceptorConfig::default().handshake_timeout(dur),
@@ -870,7 +1493,23 @@ where
         lst: net::TcpListener,
         builder: SslAcceptorBuilder,
     ) -> io::Result<Self> {
-        self.listen_openssl_inner(lst, openssl_acceptor(builder)?)
+        let acceptor = self.build_openssl_acceptor(builder)?;
+        self.listen_openssl_inner(lst, acceptor)
+    }
+
+    /// Configures OpenSSL acceptor `builder` and, if enabled, key logging.
+    #[cfg(feature = "openssl")]
+    fn build_openssl_acceptor(&self, builder: SslAcceptorBuilder) -> io::Result<SslAcceptor> {
+        #[cfg(feature = "tls-key-log")]
+        let builder = {
+            let mut builder = builder;
+            if self.config.lock().unwrap().tls_key_log {
+                openssl_key_log(&mut builder);
+            }
+            builder
+        };
+
+        openssl_acceptor(builder)
     }
 
     #[cfg(feature = "openssl")]
@@ -887,7 +1526,20 @@ where
             scheme: "https",
         });
 
+        if let Some(monitor) = &self.load_monitor {
+            monitor.add_listener(addr);
+        }
+
         let on_connect_fn = self.on_connect_fn.clone();
+        let on_request_head_fn = self.on_request_head_fn.clone();
+        let etag_cache = self.etag_cache.clone();
+        let on_protocol_error_fn = self.on_protocol_error_fn.clone();
+        let shutdown_tracker = self
+            .shutdown_sequence
+            .as_ref()
+            .map(ShutdownSequence::tracker);
+        let load_monitor = self.load_monitor.clone();
+        let shutdown = self.builder.graceful_shutdown_signal();
 
         self.builder =
             self.builder
@@ -896,6 +1548,16 @@ where
                     let host = c.host.clone().unwrap_or_else(|| format!("{}", addr));
 
                     let svc = HttpService::build()
+                        .shutdown_signal(shutdown_stage(
+                            &shutdown,
+                            &shutdown_tracker,
+                            ShutdownStage::Drain,
+                        ))
+                        .session_close_signal(shutdown_stage(
+                            &shutdown,
+                            &shutdown_tracker,
+                            ShutdownStage::CloseSessions,
+                        ))
                         .keep_alive(c.keep_alive)
                         .client_request_timeout(c.client_request_timeout)
                         .client_disconnect_timeout(c.client_disconnect_timeout)
@@ -907,9 +1569,33 @@ where
                         svc
                     };
 
+                    let svc = if let Some(handler) = on_request_head_fn.clone() {
+                        svc.on_request_head(move |req: &mut _| (handler)(req).map_err(Into::into))
+                    } else {
+                        svc
+                    };
+
+                    let svc = if let Some(ref etags) = etag_cache {
+                        svc.etag_cache(etags.clone())
+                    } else {
+                        svc
+                    };
+
+                    let svc = if let Some(handler) = on_protocol_error_fn.clone() {
+                        svc.observer(ProtocolErrorObserver(handler))
+                    } else {
+                        svc
+                    };
+
                     let fac = factory()
                         .into_factory()
                         .map_err(|err| err.into().error_response());
+                    let tracker = shutdown_tracker.clone();
+                    let worker = load_monitor.as_ref().map(LoadMonitor::worker);
+                    let fac = apply_fn_factory(fac, move |req: Request, srv| {
+                        let fut = load::track(worker.clone(), srv.call(req));
+                        shutdown::track::<_, _, _, B>(tracker.clone(), fut)
+                    });
 
                     // false positive lint (?)
                     #[allow(clippy::significant_drop_in_scrutinee)]
@@ -928,60 +1614,32 @@ where
     }
 
     /// Opens Unix Domain Socket (UDS) from `uds` path and binds server to created listener.
+    ///
+    /// Any existing file at `uds_path` is removed first. See [`listen_uds()`](Self::listen_uds)
+    /// for how connections are served.
     #[cfg(unix)]
-    pub fn bind_uds<A>(mut self, uds_path: A) -> io::Result<Self>
+    pub fn bind_uds<A>(self, uds_path: A) -> io::Result<Self>
     where
         A: AsRef<std::path::Path>,
     {
-        use actix_http::Protocol;
-        use actix_rt::net::UnixStream;
-        use actix_service::{fn_service, ServiceFactoryExt as _};
-
-        let cfg = Arc::clone(&self.config);
-        let factory = self.factory.clone();
-        let socket_addr =
-            net::SocketAddr::new(net::IpAddr::V4(net::Ipv4Addr::new(127, 0, 0, 1)), 8080);
-
-        self.sockets.push(Socket {
-            scheme: "http",
-            addr: socket_addr,
-        });
-
-        self.builder = self.builder.bind_uds(
-            format!("actix-web-service-{:?}", uds_path.as_ref()),
-            uds_path,
-            move || {
-                let c = cfg.lock().unwrap();
-                let config = AppConfig::new(
-                    false,
-                    c.host.clone().unwrap_or_else(|| format!("{}", socket_addr)),
-                    socket_addr,
-                );
-
-                let fac = factory()
-                    .into_factory()
-                    .map_err(|err| err.into().error_response());
-
-                fn_service(|io: UnixStream| async { Ok((io, Protocol::Http1, None)) }).and_then(
-                    HttpService::build()
-                        .keep_alive(c.keep_alive)
-                        .client_request_timeout(c.client_request_timeout)
-                        .client_disconnect_timeout(c.client_disconnect_timeout)
-                        .finish(map_config(fac, move |_| config.clone())),
-                )
-            },
-        )?;
+        // the path must not exist when binding
+        if let Err(err) = std::fs::remove_file(uds_path.as_ref()) {
+            if err.kind() != io::ErrorKind::NotFound {
+                return Err(err);
+            }
+        }
 
-        Ok(self)
+        let lst = std::os::unix::net::UnixListener::bind(uds_path)?;
+        self.listen_uds(lst)
     }
 
     /// Binds to existing Unix Domain Socket (UDS) listener.
+    ///
+    /// Connections are served as HTTP/1.x. Their peer details are available from their requests
+    /// as [`UdsInfo`](crate::dev::UdsInfo) connection data, e.g., using
+    /// [`HttpRequest::conn_data()`](crate::HttpRequest::conn_data).
     #[cfg(unix)]
     pub fn listen_uds(mut self, lst: std::os::unix::net::UnixListener) -> io::Result<Self> {
-        use actix_http::Protocol;
-        use actix_rt::net::UnixStream;
-        use actix_service::{fn_service, ServiceFactoryExt as _};
-
         let cfg = Arc::clone(&self.config);
         let factory = self.factory.clone();
         let socket_addr =
@@ -994,6 +1652,15 @@ where
         let addr = lst.local_addr()?;
         let name = format!("actix-web-service-{:?}", addr);
         let on_connect_fn = self.on_connect_fn.clone();
+        let on_request_head_fn = self.on_request_head_fn.clone();
+        let etag_cache = self.etag_cache.clone();
+        let on_protocol_error_fn = self.on_protocol_error_fn.clone();
+        let shutdown_tracker = self
+            .shutdown_sequence
+            .as_ref()
+            .map(ShutdownSequence::tracker);
+        let load_monitor = self.load_monitor.clone();
+        let shutdown = self.builder.graceful_shutdown_signal();
 
         self.builder = self.builder.listen_uds(name, lst, move || {
             let c = cfg.lock().unwrap();
@@ -1003,22 +1670,48 @@ where
                 socket_addr,
             );
 
-            fn_service(|io: UnixStream| async { Ok((io, Protocol::Http1, None)) }).and_then({
-                let mut svc = HttpService::build()
-                    .keep_alive(c.keep_alive)
-                    .client_request_timeout(c.client_request_timeout)
-                    .client_disconnect_timeout(c.client_disconnect_timeout);
+            let mut svc = HttpService::build()
+                .shutdown_signal(shutdown_stage(
+                    &shutdown,
+                    &shutdown_tracker,
+                    ShutdownStage::Drain,
+                ))
+                .session_close_signal(shutdown_stage(
+                    &shutdown,
+                    &shutdown_tracker,
+                    ShutdownStage::CloseSessions,
+                ))
+                .keep_alive(c.keep_alive)
+                .client_request_timeout(c.client_request_timeout)
+                .client_disconnect_timeout(c.client_disconnect_timeout);
+
+            if let Some(handler) = on_connect_fn.clone() {
+                svc = svc.on_connect_ext(move |io: &_, ext: _| (handler)(io as &dyn Any, ext));
+            }
 
-                if let Some(handler) = on_connect_fn.clone() {
-                    svc = svc.on_connect_ext(move |io: &_, ext: _| (handler)(io as &dyn Any, ext));
-                }
+            if let Some(handler) = on_request_head_fn.clone() {
+                svc = svc.on_request_head(move |req: &mut _| (handler)(req).map_err(Into::into));
+            }
+
+            if let Some(ref etags) = etag_cache {
+                svc = svc.etag_cache(etags.clone());
+            }
 
-                let fac = factory()
-                    .into_factory()
-                    .map_err(|err| err.into().error_response());
+            if let Some(handler) = on_protocol_error_fn.clone() {
+                svc = svc.observer(ProtocolErrorObserver(handler));
+            }
 
-                svc.finish(map_config(fac, move |_| config.clone()))
-            })
+            let fac = factory()
+                .into_factory()
+                .map_err(|err| err.into().error_response());
+            let tracker = shutdown_tracker.clone();
+            let worker = load_monitor.as_ref().map(LoadMonitor::worker);
+            let fac = apply_fn_factory(fac, move |req: Request, srv| {
+                let fut = load::track(worker.clone(), srv.call(req));
+                shutdown::track::<_, _, _, B>(tracker.clone(), fut)
+            });
+
+            svc.finish(map_config(fac, move |_| config.clone())).uds()
         })?;
         Ok(self)
     }
@@ -1048,10 +1741,45 @@ where
     /// This methods panics if no socket addresses were successfully bound or if no Tokio runtime
     /// is set up.
     pub fn run(self) -> Server {
+        if let Some(sequence) = self.shutdown_sequence {
+            actix_rt::spawn(sequence.run(self.builder.graceful_shutdown_signal()));
+        }
+
         self.builder.run()
     }
 }
 
+type ProtocolErrorFn = Arc<dyn Fn(Option<net::SocketAddr>, &DispatchError, &[u8]) + Send + Sync>;
+
+/// Connection observer that passes protocol errors to the function set with
+/// [`HttpServer::on_protocol_error()`].
+struct ProtocolErrorObserver(ProtocolErrorFn);
+
+impl ConnectionObserver for ProtocolErrorObserver {
+    fn protocol_error(&self, conn: &ConnectionInfo, err: &DispatchError, data: &[u8]) {
+        (self.0)(conn.peer_addr(), err, data);
+    }
+}
+
+/// Returns a future that resolves once the server's graceful shutdown reaches `stage`.
+///
+/// Without a shutdown sequence, all stages are reached once the graceful shutdown starts.
+fn shutdown_stage(
+    signal: &GracefulShutdownSignal,
+    tracker: &Option<ShutdownTracker>,
+    stage: ShutdownStage,
+) -> impl Future<Output = ()> + 'static {
+    let signal = signal.clone();
+    let reached = tracker.as_ref().map(|tracker| tracker.reached(stage));
+
+    async move {
+        match reached {
+            Some(reached) => reached.await,
+            None => signal.notified().await,
+        }
+    }
+}
+
 /// Bind TCP listeners to socket addresses resolved from `addrs` with options.
 fn bind_addrs(addrs: impl net::ToSocketAddrs, backlog: u32) -> io::Result<Vec<net::TcpListener>> {
     let mut err = None;
@@ -1116,3 +1844,27 @@ fn openssl_acceptor(mut builder: SslAcceptorBuilder) -> io::Result<SslAcceptor>
 
     Ok(builder.build())
 }
+
+/// Appends the TLS session secrets of connections accepted with `builder` to the file named by the
+/// `SSLKEYLOGFILE` environment variable, if it is set.
+#[cfg(all(feature = "openssl", feature = "tls-key-log"))]
+fn openssl_key_log(builder: &mut SslAcceptorBuilder) {
+    use std::{fs::OpenOptions, io::Write as _};
+
+    let Some(path) = std::env::var_os("SSLKEYLOGFILE") else {
+        return;
+    };
+
+    let file = match OpenOptions::new().create(true).append(true).open(&path) {
+        Ok(file) => Mutex::new(file),
+        Err(err) => {
+            log::warn!("Can not open TLS key log file {path:?}: {err}");
+            return;
+        }
+    };
+
+    builder.set_keylog_callback(move |_, line| {
+        // key logging is a debugging aid, so failing to write must not fail the handshake
+        let _ = writeln!(file.lock().unwrap(), "{line}");
+    });
+}
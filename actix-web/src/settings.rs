@@ -0,0 +1,541 @@
+//! Declarative, deserializable server configuration.
+
+use std::{env, fmt, io, path::PathBuf, str::FromStr, sync::Arc, time::Duration};
+
+use actix_http::{body::MessageBody, KeepAlive, Request, Response};
+use actix_service::{IntoServiceFactory, Service, ServiceFactory};
+use serde::Deserialize;
+use tokio::sync::watch;
+
+use crate::{config::AppConfig, Error, HttpServer};
+
+/// Server configuration that can be loaded from a config file and overridden by environment
+/// variables.
+///
+/// All fields are optional; unset fields keep the [`HttpServer`] defaults. Durations are given in
+/// milliseconds, except for the shutdown timeout which, like
+/// [`HttpServer::shutdown_timeout()`], is given in seconds.
+///
+/// Any format supported by [`serde`] can be used to load settings. For example, in JSON:
+///
+/// ```json
+/// {
+///     "binds": ["0.0.0.0:8080"],
+///     "workers": 4,
+///     "keep_alive_ms": 75000,
+///     "tls": {
+///         "binds": ["0.0.0.0:8443"],
+///         "certificate": "/etc/app/cert.pem",
+///         "private_key": "/etc/app/key.pem"
+///     }
+/// }
+/// ```
+///
+/// See [`HttpServer::from_settings()`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct ServerSettings {
+    /// Plaintext socket addresses to bind to. See [`HttpServer::bind()`].
+    pub binds: Vec<String>,
+
+    /// TLS listener configuration.
+    pub tls: Option<TlsSettings>,
+
+    /// Server host name. See [`HttpServer::server_hostname()`].
+    pub hostname: Option<String>,
+
+    /// Number of workers. See [`HttpServer::workers()`].
+    pub workers: Option<usize>,
+
+    /// Maximum number of pending connections. See [`HttpServer::backlog()`].
+    pub backlog: Option<u32>,
+
+    /// Per-worker maximum number of concurrent connections. See [`HttpServer::max_connections()`].
+    pub max_connections: Option<usize>,
+
+    /// Per-worker maximum concurrent TLS connection limit.
+    /// See [`HttpServer::max_connection_rate()`].
+    pub max_connection_rate: Option<usize>,
+
+    /// Keep-alive duration, in milliseconds. Zero disables keep-alive.
+    /// See [`HttpServer::keep_alive()`].
+    pub keep_alive_ms: Option<u64>,
+
+    /// Client request head timeout, in milliseconds.
+    /// See [`HttpServer::client_request_timeout()`].
+    pub client_request_timeout_ms: Option<u64>,
+
+    /// Connection shutdown timeout, in milliseconds.
+    /// See [`HttpServer::client_disconnect_timeout()`].
+    pub client_disconnect_timeout_ms: Option<u64>,
+
+    /// TLS handshake timeout, in milliseconds. Only applies when TLS is enabled.
+    pub tls_handshake_timeout_ms: Option<u64>,
+
+    /// Graceful worker shutdown timeout, in seconds. See [`HttpServer::shutdown_timeout()`].
+    pub shutdown_timeout_secs: Option<u64>,
+}
+
+/// TLS listener configuration for [`ServerSettings`].
+///
+/// Certificates and keys are read as PEM files. Binding TLS listeners from settings requires the
+/// `openssl` crate feature.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct TlsSettings {
+    /// Socket addresses to bind TLS listeners to.
+    pub binds: Vec<String>,
+
+    /// Path to PEM-encoded certificate chain.
+    pub certificate: PathBuf,
+
+    /// Path to PEM-encoded private key.
+    pub private_key: PathBuf,
+}
+
+impl ServerSettings {
+    /// Constructs empty settings, equivalent to the [`HttpServer`] defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies overrides from environment variables named with the given `prefix`.
+    ///
+    /// For a prefix of `APP`, the following variables are recognized:
+    ///
+    /// | Variable                          | Field                          |
+    /// | --------------------------------- | ------------------------------ |
+    /// | `APP_BINDS`                       | `binds` (comma-separated)      |
+    /// | `APP_TLS_BINDS`                   | `tls.binds` (comma-separated)  |
+    /// | `APP_TLS_CERTIFICATE`             | `tls.certificate`              |
+    /// | `APP_TLS_PRIVATE_KEY`             | `tls.private_key`              |
+    /// | `APP_HOSTNAME`                    | `hostname`                     |
+    /// | `APP_WORKERS`                     | `workers`                      |
+    /// | `APP_BACKLOG`                     | `backlog`                      |
+    /// | `APP_MAX_CONNECTIONS`             | `max_connections`              |
+    /// | `APP_MAX_CONNECTION_RATE`         | `max_connection_rate`          |
+    /// | `APP_KEEP_ALIVE_MS`               | `keep_alive_ms`                |
+    /// | `APP_CLIENT_REQUEST_TIMEOUT_MS`   | `client_request_timeout_ms`    |
+    /// | `APP_CLIENT_DISCONNECT_TIMEOUT_MS`| `client_disconnect_timeout_ms` |
+    /// | `APP_TLS_HANDSHAKE_TIMEOUT_MS`    | `tls_handshake_timeout_ms`     |
+    /// | `APP_SHUTDOWN_TIMEOUT_SECS`       | `shutdown_timeout_secs`        |
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::ErrorKind::InvalidInput`] error if a numeric variable cannot be parsed.
+    pub fn with_env_overrides(self, prefix: &str) -> io::Result<Self> {
+        self.with_overrides_from(prefix, |key| env::var(key).ok())
+    }
+
+    fn with_overrides_from(
+        mut self,
+        prefix: &str,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> io::Result<Self> {
+        let var = |name: &str| lookup(&format!("{prefix}_{name}"));
+
+        fn parse<T>(name: &str, val: String) -> io::Result<T>
+        where
+            T: FromStr,
+            T::Err: fmt::Display,
+        {
+            val.trim().parse().map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid value for {name}: {err}"),
+                )
+            })
+        }
+
+        fn split_list(val: String) -> Vec<String> {
+            val.split(',')
+                .map(str::trim)
+                .filter(|addr| !addr.is_empty())
+                .map(ToOwned::to_owned)
+                .collect()
+        }
+
+        macro_rules! override_num {
+            ($field:ident, $name:literal) => {
+                if let Some(val) = var($name) {
+                    self.$field = Some(parse($name, val)?);
+                }
+            };
+        }
+
+        if let Some(val) = var("BINDS") {
+            self.binds = split_list(val);
+        }
+
+        if let Some(val) = var("TLS_BINDS") {
+            self.tls.get_or_insert_with(TlsSettings::default).binds = split_list(val);
+        }
+
+        if let Some(val) = var("TLS_CERTIFICATE") {
+            self.tls
+                .get_or_insert_with(TlsSettings::default)
+                .certificate = val.into();
+        }
+
+        if let Some(val) = var("TLS_PRIVATE_KEY") {
+            self.tls
+                .get_or_insert_with(TlsSettings::default)
+                .private_key = val.into();
+        }
+
+        if let Some(val) = var("HOSTNAME") {
+            self.hostname = Some(val);
+        }
+
+        override_num!(workers, "WORKERS");
+        override_num!(backlog, "BACKLOG");
+        override_num!(max_connections, "MAX_CONNECTIONS");
+        override_num!(max_connection_rate, "MAX_CONNECTION_RATE");
+        override_num!(keep_alive_ms, "KEEP_ALIVE_MS");
+        override_num!(client_request_timeout_ms, "CLIENT_REQUEST_TIMEOUT_MS");
+        override_num!(client_disconnect_timeout_ms, "CLIENT_DISCONNECT_TIMEOUT_MS");
+        override_num!(tls_handshake_timeout_ms, "TLS_HANDSHAKE_TIMEOUT_MS");
+        override_num!(shutdown_timeout_secs, "SHUTDOWN_TIMEOUT_SECS");
+
+        Ok(self)
+    }
+}
+
+impl<F, I, S, B> HttpServer<F, I, S, B>
+where
+    F: Fn() -> I + Send + Clone + 'static,
+    I: IntoServiceFactory<S, Request>,
+
+    S: ServiceFactory<Request, Config = AppConfig> + 'static,
+    S::Error: Into<Error> + 'static,
+    S::InitError: fmt::Debug,
+    S::Response: Into<Response<B>> + 'static,
+    <S::Service as Service<Request>>::Future: 'static,
+    S::Service: 'static,
+
+    B: MessageBody + 'static,
+{
+    /// Creates a new HTTP server from `settings`, applying all options and binding all configured
+    /// listeners.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if:
+    /// - no listeners are configured;
+    /// - any configured address fails to bind (see [`bind()`](Self::bind()));
+    /// - TLS listeners are configured but the `openssl` crate feature is not enabled;
+    /// - the TLS certificate or private key cannot be loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use actix_web::{web, App, HttpResponse, HttpServer, ServerSettings};
+    ///
+    /// #[actix_web::main]
+    /// async fn main() -> std::io::Result<()> {
+    ///     let settings: ServerSettings = serde_json::from_str(r#"{ "binds": ["127.0.0.1:8080"] }"#)?;
+    ///     let settings = settings.with_env_overrides("APP")?;
+    ///
+    ///     HttpServer::from_settings(settings, || {
+    ///         App::new().route("/", web::get().to(HttpResponse::Ok))
+    ///     })?
+    ///     .run()
+    ///     .await
+    /// }
+    /// ```
+    pub fn from_settings(settings: ServerSettings, factory: F) -> io::Result<Self> {
+        let has_tls_binds = settings
+            .tls
+            .as_ref()
+            .is_some_and(|tls| !tls.binds.is_empty());
+
+        if settings.binds.is_empty() && !has_tls_binds {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "server settings do not contain any bind addresses",
+            ));
+        }
+
+        let mut srv = HttpServer::new(factory);
+
+        if let Some(num) = settings.workers {
+            srv = srv.workers(num);
+        }
+
+        if let Some(backlog) = settings.backlog {
+            srv = srv.backlog(backlog);
+        }
+
+        if let Some(num) = settings.max_connections {
+            srv = srv.max_connections(num);
+        }
+
+        if let Some(num) = settings.max_connection_rate {
+            srv = srv.max_connection_rate(num);
+        }
+
+        if let Some(ms) = settings.keep_alive_ms {
+            srv = srv.keep_alive(KeepAlive::from(Duration::from_millis(ms)));
+        }
+
+        if let Some(ms) = settings.client_request_timeout_ms {
+            srv = srv.client_request_timeout(Duration::from_millis(ms));
+        }
+
+        if let Some(ms) = settings.client_disconnect_timeout_ms {
+            srv = srv.client_disconnect_timeout(Duration::from_millis(ms));
+        }
+
+        #[cfg(feature = "__tls")]
+        if let Some(ms) = settings.tls_handshake_timeout_ms {
+            srv = srv.tls_handshake_timeout(Duration::from_millis(ms));
+        }
+
+        if let Some(secs) = settings.shutdown_timeout_secs {
+            srv = srv.shutdown_timeout(secs);
+        }
+
+        if let Some(hostname) = &settings.hostname {
+            srv = srv.server_hostname(hostname);
+        }
+
+        for addr in &settings.binds {
+            srv = srv.bind(addr.as_str())?;
+        }
+
+        match settings.tls {
+            Some(tls) if !tls.binds.is_empty() => srv.bind_tls_settings(tls),
+            _ => Ok(srv),
+        }
+    }
+
+    #[cfg(feature = "openssl")]
+    fn bind_tls_settings(mut self, tls: TlsSettings) -> io::Result<Self> {
+        use tls_openssl::ssl::{SslAcceptor, SslFiletype, SslMethod};
+
+        for addr in &tls.binds {
+            let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
+            builder.set_private_key_file(&tls.private_key, SslFiletype::PEM)?;
+            builder.set_certificate_chain_file(&tls.certificate)?;
+
+            self = self.bind_openssl(addr.as_str(), builder)?;
+        }
+
+        Ok(self)
+    }
+
+    #[cfg(not(feature = "openssl"))]
+    fn bind_tls_settings(self, _tls: TlsSettings) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "binding TLS listeners from server settings requires the `openssl` feature",
+        ))
+    }
+}
+
+/// Limits that can be adjusted while the server is running.
+///
+/// Changes are published through a [`SettingsHandle`] and picked up by the
+/// [`Limiter`](crate::middleware::Limiter) and [`Logger`](crate::middleware::Logger) middleware on
+/// their next request.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct RuntimeLimits {
+    /// Maximum number of requests accepted per second, per worker. Unset means unlimited.
+    pub rate_limit: Option<u32>,
+
+    /// Maximum request body size, in bytes. Unset means unlimited.
+    pub body_limit: Option<usize>,
+
+    /// Fraction of requests to include in access logs, from `0.0` (none) to `1.0` (all).
+    pub log_sample_rate: f64,
+
+    /// When enabled, requests are rejected with `503 Service Unavailable`.
+    pub maintenance: bool,
+}
+
+impl Default for RuntimeLimits {
+    fn default() -> Self {
+        Self {
+            rate_limit: None,
+            body_limit: None,
+            log_sample_rate: 1.0,
+            maintenance: false,
+        }
+    }
+}
+
+impl RuntimeLimits {
+    /// Constructs default limits, which do not restrict any requests.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Handle for adjusting [`RuntimeLimits`] without restarting the server.
+///
+/// Clones of a handle share the same limits, so a handle can be moved into the `HttpServer`
+/// factory closure and kept elsewhere (e.g., in an admin endpoint or signal handler) to apply
+/// updates. Each update is published atomically; middleware never observes a partial change.
+///
+/// # Examples
+/// ```
+/// use actix_web::{middleware, App, RuntimeLimits, SettingsHandle};
+///
+/// let handle = SettingsHandle::new(RuntimeLimits::new());
+///
+/// let app = App::new()
+///     .wrap(middleware::Limiter::new(&handle))
+///     .wrap(middleware::Logger::default().sampling(&handle));
+///
+/// // later, from anywhere
+/// handle.update(|limits| {
+///     limits.maintenance = true;
+///     limits.log_sample_rate = 0.1;
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct SettingsHandle {
+    tx: Arc<watch::Sender<RuntimeLimits>>,
+}
+
+impl SettingsHandle {
+    /// Constructs a handle with initial `limits`.
+    pub fn new(limits: RuntimeLimits) -> Self {
+        let (tx, _rx) = watch::channel(limits);
+        Self { tx: Arc::new(tx) }
+    }
+
+    /// Returns a copy of the current limits.
+    pub fn current(&self) -> RuntimeLimits {
+        self.tx.borrow().clone()
+    }
+
+    /// Replaces all limits.
+    pub fn set(&self, limits: RuntimeLimits) {
+        self.tx.send_replace(limits);
+    }
+
+    /// Modifies the limits in place and publishes the result as a single update.
+    pub fn update(&self, modify: impl FnOnce(&mut RuntimeLimits)) {
+        self.tx.send_modify(modify);
+    }
+
+    pub(crate) fn subscribe(&self) -> watch::Receiver<RuntimeLimits> {
+        self.tx.subscribe()
+    }
+}
+
+impl Default for SettingsHandle {
+    fn default() -> Self {
+        Self::new(RuntimeLimits::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{web, App, HttpResponse};
+
+    #[test]
+    fn deserialize_settings() {
+        let settings: ServerSettings = serde_json::from_str(
+            r#"{
+                "binds": ["127.0.0.1:8080"],
+                "workers": 2,
+                "keep_alive_ms": 0,
+                "tls": { "binds": ["127.0.0.1:8443"], "certificate": "cert.pem", "private_key": "key.pem" }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.binds, ["127.0.0.1:8080"]);
+        assert_eq!(settings.workers, Some(2));
+        assert_eq!(settings.keep_alive_ms, Some(0));
+        assert_eq!(settings.backlog, None);
+
+        let tls = settings.tls.unwrap();
+        assert_eq!(tls.binds, ["127.0.0.1:8443"]);
+        assert_eq!(tls.certificate, PathBuf::from("cert.pem"));
+
+        serde_json::from_str::<ServerSettings>(r#"{ "bind": [] }"#).unwrap_err();
+    }
+
+    #[test]
+    fn env_overrides() {
+        let vars = HashMap::from([
+            ("APP_BINDS", "127.0.0.1:1, 127.0.0.1:2,"),
+            ("APP_WORKERS", "3"),
+            ("APP_TLS_PRIVATE_KEY", "key.pem"),
+            ("OTHER_BACKLOG", "1"),
+        ]);
+        let lookup = |key: &str| vars.get(key).map(|val| val.to_string());
+
+        let settings = ServerSettings {
+            workers: Some(8),
+            backlog: Some(64),
+            ..ServerSettings::default()
+        }
+        .with_overrides_from("APP", lookup)
+        .unwrap();
+
+        assert_eq!(settings.binds, ["127.0.0.1:1", "127.0.0.1:2"]);
+        assert_eq!(settings.workers, Some(3));
+        assert_eq!(settings.backlog, Some(64));
+        assert_eq!(settings.tls.unwrap().private_key, PathBuf::from("key.pem"));
+
+        let err = ServerSettings::new()
+            .with_overrides_from("APP", |_| Some("many".to_owned()))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[actix_rt::test]
+    async fn from_settings_binds() {
+        let factory = || App::new().route("/", web::to(HttpResponse::Ok));
+
+        let err = HttpServer::from_settings(ServerSettings::new(), factory)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let settings = ServerSettings {
+            binds: vec!["127.0.0.1:0".to_owned()],
+            workers: Some(1),
+            ..ServerSettings::default()
+        };
+        let srv = HttpServer::from_settings(settings, factory).unwrap();
+        assert_eq!(srv.addrs().len(), 1);
+    }
+
+    #[test]
+    fn settings_handle_updates() {
+        let handle = SettingsHandle::default();
+        let rx = handle.subscribe();
+        assert_eq!(*rx.borrow(), RuntimeLimits::default());
+
+        let other = handle.clone();
+        other.update(|limits| {
+            limits.maintenance = true;
+            limits.body_limit = Some(1024);
+        });
+
+        assert!(rx.has_changed().unwrap());
+        assert!(rx.borrow().maintenance);
+        assert_eq!(handle.current().body_limit, Some(1024));
+
+        handle.set(RuntimeLimits::new());
+        assert!(!rx.borrow().maintenance);
+
+        let limits: RuntimeLimits =
+            serde_json::from_str(r#"{ "rate_limit": 10, "log_sample_rate": 0.5 }"#).unwrap();
+        assert_eq!(limits.rate_limit, Some(10));
+        assert_eq!(limits.log_sample_rate, 0.5);
+        assert!(!limits.maintenance);
+    }
+}
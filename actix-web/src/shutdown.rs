@@ -0,0 +1,477 @@
+//! Ordered graceful shutdown of an [`HttpServer`](crate::HttpServer).
+
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use actix_http::{
+    body::{BodySize, MessageBody, SendFile},
+    header::HeaderMap,
+    Response,
+};
+use actix_server::GracefulShutdownSignal;
+use bytes::Bytes;
+use futures_core::future::LocalBoxFuture;
+use pin_project_lite::pin_project;
+use tokio::sync::{watch, Notify};
+
+/// Stage of a [`ShutdownSequence`], in the order they are run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ShutdownStage {
+    /// The server has stopped accepting connections.
+    ///
+    /// This stage completes immediately and has no timeout.
+    StopAccepting,
+
+    /// Drain hooks are run and HTTP/2 connections are sent GOAWAY frames.
+    Drain,
+
+    /// Requests whose handlers have not returned a response yet are awaited.
+    InFlightRequests,
+
+    /// Long-lived sessions are notified to close, through the
+    /// [`GracefulShutdown`](crate::dev::GracefulShutdown) handle in their connection data, and
+    /// responses whose bodies are still being sent, such as WebSocket sessions and event streams,
+    /// are awaited.
+    CloseSessions,
+
+    /// Stop hooks are run.
+    OnStop,
+}
+
+impl ShutdownStage {
+    const ALL: [ShutdownStage; 5] = [
+        ShutdownStage::StopAccepting,
+        ShutdownStage::Drain,
+        ShutdownStage::InFlightRequests,
+        ShutdownStage::CloseSessions,
+        ShutdownStage::OnStop,
+    ];
+}
+
+/// Outcome of a completed [`ShutdownStage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageReport {
+    stage: ShutdownStage,
+    elapsed: Duration,
+    remaining: usize,
+}
+
+impl StageReport {
+    /// Returns the stage this report is for.
+    pub fn stage(&self) -> ShutdownStage {
+        self.stage
+    }
+
+    /// Returns how long the stage took.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the number of hooks, requests, or responses the stage was still waiting for when
+    /// its timeout elapsed.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Returns true if the stage ended because its timeout elapsed.
+    pub fn timed_out(&self) -> bool {
+        self.remaining > 0
+    }
+}
+
+type Hook = Arc<dyn Fn() -> LocalBoxFuture<'static, ()> + Send + Sync>;
+
+type StageCallback = Arc<dyn Fn(&StageReport) + Send + Sync>;
+
+/// Ordered stages of a graceful shutdown, each with its own timeout.
+///
+/// By default, once a graceful shutdown of an [`HttpServer`] starts, HTTP/2 connections are
+/// drained, long-lived sessions are notified, and workers wait for connections to close, all at
+/// once, until the server's [shutdown timeout] elapses. A shutdown sequence, set with
+/// [`HttpServer::shutdown_sequence()`], runs these steps one [stage](ShutdownStage) at a time
+/// instead:
+///
+/// 1. [`StopAccepting`](ShutdownStage::StopAccepting): the server stops accepting connections.
+/// 1. [`Drain`](ShutdownStage::Drain): [drain hooks](Self::on_drain), for example deregistering
+///    from a load balancer, are run and HTTP/2 connections are sent GOAWAY frames.
+/// 1. [`InFlightRequests`](ShutdownStage::InFlightRequests): requests being handled are awaited.
+/// 1. [`CloseSessions`](ShutdownStage::CloseSessions): WebSocket sessions and other long-lived
+///    responses are notified to close, and are awaited.
+/// 1. [`OnStop`](ShutdownStage::OnStop): [stop hooks](Self::on_stop) are run.
+///
+/// Each stage ends once what it waits for is done or its [timeout](Self::timeout) elapses,
+/// after which a [`StageReport`] is passed to the [observer](Self::on_stage). Stage timeouts
+/// should add up to less than the server's shutdown timeout, after which workers are stopped
+/// regardless of the stage.
+///
+/// The sequence runs on the thread that called [`HttpServer::run()`], which may finish before
+/// it does. Await [`finished()`](Self::finished) after the server to let stop hooks complete.
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use actix_web::{web, App, HttpServer, ShutdownSequence, ShutdownStage};
+///
+/// # #[actix_web::main]
+/// # async fn main() -> std::io::Result<()> {
+/// let sequence = ShutdownSequence::new()
+///     .timeout(ShutdownStage::InFlightRequests, Duration::from_secs(15))
+///     .on_drain(|| async { /* deregister from service discovery */ })
+///     .on_stop(|| async { /* flush metrics */ })
+///     .on_stage(|report| {
+///         log::info!("{:?} took {:?}", report.stage(), report.elapsed());
+///     });
+///
+/// HttpServer::new(|| App::new().route("/", web::get().to(|| async { "hello" })))
+///     .shutdown_sequence(sequence.clone())
+///     .bind(("127.0.0.1", 8080))?
+///     .run()
+///     .await?;
+///
+/// sequence.finished().await;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`HttpServer`]: crate::HttpServer
+/// [`HttpServer::shutdown_sequence()`]: crate::HttpServer::shutdown_sequence
+/// [`HttpServer::run()`]: crate::HttpServer::run
+/// [shutdown timeout]: crate::HttpServer::shutdown_timeout
+#[derive(Clone)]
+pub struct ShutdownSequence {
+    timeouts: [Duration; 5],
+    drain_hooks: Vec<Hook>,
+    stop_hooks: Vec<Hook>,
+    on_stage: Option<StageCallback>,
+    tracker: ShutdownTracker,
+    stage: Arc<watch::Sender<Option<ShutdownStage>>>,
+    reports: Arc<watch::Sender<Option<Vec<StageReport>>>>,
+}
+
+impl ShutdownSequence {
+    /// Constructs a shutdown sequence with default timeouts and no hooks.
+    ///
+    /// The default timeouts are 5 seconds for the [`Drain`](ShutdownStage::Drain),
+    /// [`CloseSessions`](ShutdownStage::CloseSessions), and [`OnStop`](ShutdownStage::OnStop)
+    /// stages and 10 seconds for the [`InFlightRequests`](ShutdownStage::InFlightRequests) stage.
+    pub fn new() -> Self {
+        let stage = watch::channel(None).0;
+
+        Self {
+            timeouts: [
+                Duration::ZERO,
+                Duration::from_secs(5),
+                Duration::from_secs(10),
+                Duration::from_secs(5),
+                Duration::from_secs(5),
+            ],
+            drain_hooks: Vec::new(),
+            stop_hooks: Vec::new(),
+            on_stage: None,
+            tracker: ShutdownTracker {
+                stage: stage.subscribe(),
+                requests: Pending::default(),
+                responses: Pending::default(),
+            },
+            stage: Arc::new(stage),
+            reports: Arc::new(watch::channel(None).0),
+        }
+    }
+
+    /// Sets the timeout of a stage.
+    ///
+    /// The timeout of the [`StopAccepting`](ShutdownStage::StopAccepting) stage is ignored.
+    pub fn timeout(mut self, stage: ShutdownStage, timeout: Duration) -> Self {
+        self.timeouts[stage as usize] = timeout;
+        self
+    }
+
+    /// Adds a hook that is run in the [`Drain`](ShutdownStage::Drain) stage.
+    ///
+    /// Hooks of a stage run concurrently.
+    pub fn on_drain<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.drain_hooks.push(Arc::new(move || Box::pin(hook())));
+        self
+    }
+
+    /// Adds a hook that is run in the [`OnStop`](ShutdownStage::OnStop) stage.
+    ///
+    /// Hooks of a stage run concurrently.
+    pub fn on_stop<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.stop_hooks.push(Arc::new(move || Box::pin(hook())));
+        self
+    }
+
+    /// Sets a callback that is called with the report of each stage once it ends.
+    pub fn on_stage<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&StageReport) + Send + Sync + 'static,
+    {
+        self.on_stage = Some(Arc::new(f));
+        self
+    }
+
+    /// Returns a future that resolves with the reports of all stages once the sequence has run.
+    ///
+    /// The future can be obtained from any clone of the sequence.
+    pub fn finished(&self) -> impl Future<Output = Vec<StageReport>> + 'static {
+        let mut reports = self.reports.subscribe();
+
+        async move {
+            loop {
+                if let Some(reports) = reports.borrow_and_update().clone() {
+                    return reports;
+                }
+
+                if reports.changed().await.is_err() {
+                    return Vec::new();
+                }
+            }
+        }
+    }
+
+    pub(crate) fn tracker(&self) -> ShutdownTracker {
+        self.tracker.clone()
+    }
+
+    /// Runs the sequence once the server's graceful shutdown starts.
+    pub(crate) async fn run(self, signal: GracefulShutdownSignal) {
+        signal.notified().await;
+
+        let mut reports = Vec::with_capacity(ShutdownStage::ALL.len());
+
+        for stage in ShutdownStage::ALL {
+            let start = Instant::now();
+            self.stage.send_replace(Some(stage));
+
+            let timeout = self.timeouts[stage as usize];
+
+            let remaining = match stage {
+                ShutdownStage::StopAccepting => 0,
+                ShutdownStage::Drain => run_hooks(&self.drain_hooks, timeout).await,
+                ShutdownStage::InFlightRequests => self.tracker.requests.idle(timeout).await,
+                ShutdownStage::CloseSessions => self.tracker.responses.idle(timeout).await,
+                ShutdownStage::OnStop => run_hooks(&self.stop_hooks, timeout).await,
+            };
+
+            let report = StageReport {
+                stage,
+                elapsed: start.elapsed(),
+                remaining,
+            };
+
+            if report.timed_out() {
+                log::warn!(
+                    "shutdown stage {:?} timed out after {:?} with {} remaining",
+                    stage,
+                    timeout,
+                    remaining,
+                );
+            }
+
+            if let Some(on_stage) = &self.on_stage {
+                on_stage(&report);
+            }
+
+            reports.push(report);
+        }
+
+        self.reports.send_replace(Some(reports));
+    }
+}
+
+impl Default for ShutdownSequence {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ShutdownSequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShutdownSequence")
+            .field("timeouts", &self.timeouts)
+            .field("drain_hooks", &self.drain_hooks.len())
+            .field("stop_hooks", &self.stop_hooks.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Runs hooks concurrently until they complete or the timeout elapses.
+///
+/// Returns the number of hooks that did not complete, which are cancelled.
+async fn run_hooks(hooks: &[Hook], timeout: Duration) -> usize {
+    let pending = Pending::default();
+
+    let handles = hooks
+        .iter()
+        .map(|hook| {
+            let guard = pending.guard();
+            let fut = hook();
+
+            actix_rt::spawn(async move {
+                fut.await;
+                drop(guard);
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let remaining = pending.idle(timeout).await;
+
+    for handle in handles {
+        handle.abort();
+    }
+
+    remaining
+}
+
+/// Connection-side view of a [`ShutdownSequence`], shared by the services of all workers.
+#[derive(Clone)]
+pub(crate) struct ShutdownTracker {
+    stage: watch::Receiver<Option<ShutdownStage>>,
+    requests: Pending,
+    responses: Pending,
+}
+
+impl ShutdownTracker {
+    /// Returns a future that resolves once the sequence reaches `stage`.
+    pub(crate) fn reached(&self, stage: ShutdownStage) -> impl Future<Output = ()> + 'static {
+        let mut current = self.stage.clone();
+
+        async move {
+            while current
+                .borrow_and_update()
+                .map_or(true, |current| current < stage)
+            {
+                if current.changed().await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Tracks a request until its handler returns and its response until the body is dropped.
+pub(crate) async fn track<Fut, R, E, B>(
+    tracker: Option<ShutdownTracker>,
+    fut: Fut,
+) -> Result<Response<TrackedBody<B>>, E>
+where
+    Fut: Future<Output = Result<R, E>>,
+    R: Into<Response<B>>,
+{
+    let request = tracker.as_ref().map(|tracker| tracker.requests.guard());
+    let res = fut.await?.into();
+    drop(request);
+
+    let response = tracker.map(|tracker| tracker.responses.guard());
+    Ok(res.map_body(|_, body| TrackedBody { body, response }))
+}
+
+pin_project! {
+    /// Response body that is counted by the shutdown sequence until it is dropped.
+    pub(crate) struct TrackedBody<B> {
+        #[pin]
+        body: B,
+        response: Option<PendingGuard>,
+    }
+}
+
+impl<B: MessageBody> MessageBody for TrackedBody<B> {
+    type Error = B::Error;
+
+    #[inline]
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    #[inline]
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        self.project().body.poll_next(cx)
+    }
+
+    #[inline]
+    fn try_into_bytes(self) -> Result<Bytes, Self> {
+        let TrackedBody { body, response } = self;
+        body.try_into_bytes()
+            .map_err(|body| TrackedBody { body, response })
+    }
+
+    #[inline]
+    fn take_trailers(self: Pin<&mut Self>) -> Option<HeaderMap> {
+        self.project().body.take_trailers()
+    }
+
+    #[inline]
+    fn take_send_file(self: Pin<&mut Self>) -> Option<SendFile> {
+        self.project().body.take_send_file()
+    }
+}
+
+/// Number of pending requests or responses.
+#[derive(Clone, Default)]
+struct Pending(Arc<PendingInner>);
+
+#[derive(Default)]
+struct PendingInner {
+    count: AtomicUsize,
+    idle: Notify,
+}
+
+impl Pending {
+    fn guard(&self) -> PendingGuard {
+        self.0.count.fetch_add(1, Ordering::AcqRel);
+        PendingGuard(self.clone())
+    }
+
+    /// Waits until nothing is pending or the timeout elapses.
+    ///
+    /// Returns the number of requests or responses still pending.
+    async fn idle(&self, timeout: Duration) -> usize {
+        let idle = async {
+            loop {
+                // registered before the count is checked so that no notification is missed
+                let notified = self.0.idle.notified();
+
+                if self.0.count.load(Ordering::Acquire) == 0 {
+                    return;
+                }
+
+                notified.await;
+            }
+        };
+
+        let _ = actix_rt::time::timeout(timeout, idle).await;
+        self.0.count.load(Ordering::Acquire)
+    }
+}
+
+struct PendingGuard(Pending);
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        if self.0 .0.count.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.0 .0.idle.notify_waiters();
+        }
+    }
+}
@@ -0,0 +1,404 @@
+//! For signing utility documentation, see [`UrlSigner`].
+
+use std::{
+    fmt::{self, Write as _},
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use hmac::{Hmac, Mac as _};
+use sha2::Sha256;
+
+use crate::{
+    error::SignedUrlError,
+    http::{Method, Uri},
+    HttpRequest,
+};
+
+/// Query parameter holding the expiry time of a signed URL, in seconds since the Unix epoch.
+const EXPIRES: &str = "expires";
+
+/// Query parameter marking a signed URL as bound to the address of the client it was issued to.
+const BIND_IP: &str = "bind_ip";
+
+/// Query parameter holding the signature of a signed URL. Always the last parameter.
+const SIGNATURE: &str = "signature";
+
+/// Signs and verifies URLs that grant temporary access to a resource, such as download links for
+/// documents sent to patients.
+///
+/// A signed URL carries an expiry time and an HMAC-SHA256 signature of its path and query, the
+/// request method it is valid for, and optionally the IP address of the client it was issued to.
+/// Anyone holding the URL can use it until it expires, without other credentials, so keep expiry
+/// times short. The `expires`, `bind_ip`, and `signature` query parameters are appended to the
+/// URL and must not be used by the URL itself.
+///
+/// Signed URLs are checked with [`verify()`](Self::verify) in a handler, or for all requests of an
+/// app, scope, or resource with the [`VerifySignedUrl`](crate::middleware::VerifySignedUrl)
+/// middleware. They are verified against the URI of the request as received, so they must be
+/// signed with the path that the server sees, rather than one rewritten by a proxy.
+///
+/// Requires the `signed-urls` feature.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use actix_web::{
+///     get, http::Method, middleware::VerifySignedUrl, web, App, HttpRequest, HttpResponse,
+///     Responder, UrlSigner,
+/// };
+///
+/// #[get("/documents/{id}/link")]
+/// async fn share_link(
+///     signer: web::Data<UrlSigner>,
+///     id: web::Path<u32>,
+///     req: HttpRequest,
+/// ) -> impl Responder {
+///     let mut link = signer.sign(format!("/downloads/{id}"), Duration::from_secs(15 * 60));
+///
+///     // only the client that asked for the link can use it
+///     if let Some(addr) = req.peer_addr() {
+///         link = link.peer_ip(addr.ip());
+///     }
+///
+///     HttpResponse::Ok().body(link.finish())
+/// }
+///
+/// let signer = UrlSigner::new(b"a secret key of at least 32 bytes");
+///
+/// let app = App::new()
+///     .app_data(web::Data::new(signer.clone()))
+///     .service(share_link)
+///     .service(
+///         web::scope("/downloads")
+///             .wrap(VerifySignedUrl::new(signer))
+///             .route("/{id}", web::get().to(HttpResponse::Ok)),
+///     );
+/// ```
+#[derive(Clone)]
+pub struct UrlSigner {
+    key: Arc<[u8]>,
+}
+
+impl UrlSigner {
+    /// Constructs a signer using `key` to sign and verify URLs.
+    ///
+    /// Every server that verifies the URLs needs the same key. Changing the key invalidates all
+    /// URLs signed with the previous one.
+    ///
+    /// # Panics
+    /// Panics if `key` is shorter than 32 bytes.
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        let key = key.as_ref();
+
+        assert!(
+            key.len() >= 32,
+            "URL signing key must be at least 32 bytes long"
+        );
+
+        Self { key: key.into() }
+    }
+
+    /// Starts signing `url` for `GET` requests until `ttl` has elapsed.
+    ///
+    /// `url` is a path with an optional query, or an absolute URL. Only its path and query are
+    /// signed, so an absolute URL can be used with any host serving the same paths.
+    pub fn sign(&self, url: impl Into<String>, ttl: Duration) -> SignUrl<'_> {
+        SignUrl {
+            signer: self,
+            url: url.into(),
+            expires: SystemTime::now() + ttl,
+            method: Method::GET,
+            peer_ip: None,
+        }
+    }
+
+    /// Verifies that the URL of `req` is signed, has not expired, and is valid for the method and
+    /// client address of the request.
+    pub fn verify(&self, req: &HttpRequest) -> Result<(), SignedUrlError> {
+        let peer_ip = req.peer_addr().map(|addr| addr.ip());
+        self.verify_at(req.method(), req.uri(), peer_ip, SystemTime::now())
+    }
+
+    pub(crate) fn verify_at(
+        &self,
+        method: &Method,
+        uri: &Uri,
+        peer_ip: Option<IpAddr>,
+        now: SystemTime,
+    ) -> Result<(), SignedUrlError> {
+        let target = uri
+            .path_and_query()
+            .ok_or(SignedUrlError::Missing)?
+            .as_str();
+
+        // the signature covers everything before it
+        let (signed, signature) = target
+            .rsplit_once(&format!("{SIGNATURE}="))
+            .ok_or(SignedUrlError::Missing)?;
+
+        let signed = signed
+            .strip_suffix(['&', '?'])
+            .ok_or(SignedUrlError::Missing)?;
+
+        let params = signed
+            .split_once('?')
+            .map_or("", |(_, query)| query)
+            .split('&')
+            .filter_map(|param| param.split_once('='))
+            .collect::<Vec<_>>();
+
+        let expires = params
+            .iter()
+            .find(|(name, _)| *name == EXPIRES)
+            .and_then(|(_, expires)| expires.parse::<u64>().ok())
+            .ok_or(SignedUrlError::Missing)?;
+
+        let bind_ip = params.iter().any(|(name, _)| *name == BIND_IP);
+
+        let peer_ip = match (bind_ip, peer_ip) {
+            (true, None) => return Err(SignedUrlError::Invalid),
+            (true, peer_ip) => peer_ip,
+            (false, _) => None,
+        };
+
+        let signature = hex_decode(signature).ok_or(SignedUrlError::Invalid)?;
+
+        self.mac(method, signed, peer_ip)
+            .verify_slice(&signature)
+            .map_err(|_| SignedUrlError::Invalid)?;
+
+        if UNIX_EPOCH + Duration::from_secs(expires) <= now {
+            return Err(SignedUrlError::Expired);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the MAC of a signed request target, valid for `method` and `peer_ip`.
+    fn mac(&self, method: &Method, target: &str, peer_ip: Option<IpAddr>) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+
+        mac.update(method.as_str().as_bytes());
+        mac.update(b"\n");
+        mac.update(target.as_bytes());
+        mac.update(b"\n");
+
+        if let Some(peer_ip) = peer_ip {
+            mac.update(peer_ip.to_string().as_bytes());
+        }
+
+        mac
+    }
+}
+
+impl fmt::Debug for UrlSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UrlSigner").finish_non_exhaustive()
+    }
+}
+
+/// URL being signed, as returned by [`UrlSigner::sign()`].
+#[must_use = "a URL is only signed once `finish()` is called"]
+#[derive(Debug)]
+pub struct SignUrl<'a> {
+    signer: &'a UrlSigner,
+    url: String,
+    expires: SystemTime,
+    method: Method,
+    peer_ip: Option<IpAddr>,
+}
+
+impl SignUrl<'_> {
+    /// Sets the request method that the URL is valid for, instead of `GET`.
+    pub fn method(mut self, method: Method) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Binds the URL to the IP address of the client it is issued to.
+    ///
+    /// Requests are checked against their peer address, so behind a reverse proxy, binding works
+    /// only if the proxy preserves client addresses, such as with the PROXY protocol.
+    pub fn peer_ip(mut self, ip: IpAddr) -> Self {
+        self.peer_ip = Some(ip);
+        self
+    }
+
+    /// Returns the signed URL.
+    pub fn finish(self) -> String {
+        let expires = self
+            .expires
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |expires| expires.as_secs());
+
+        let mut url = self.url;
+        url.push(if url.contains('?') { '&' } else { '?' });
+        let _ = write!(url, "{EXPIRES}={expires}");
+
+        if self.peer_ip.is_some() {
+            let _ = write!(url, "&{BIND_IP}=1");
+        }
+
+        // only the path and query are signed
+        let target = match url.find("://") {
+            Some(idx) => {
+                let rest = &url[idx + 3..];
+                rest.find('/').map_or("/", |start| &rest[start..])
+            }
+            None => url.as_str(),
+        };
+
+        let signature = self
+            .signer
+            .mac(&self.method, target, self.peer_ip)
+            .finalize()
+            .into_bytes();
+
+        let _ = write!(url, "&{SIGNATURE}=");
+
+        for byte in signature {
+            let _ = write!(url, "{byte:02x}");
+        }
+
+        url
+    }
+}
+
+fn hex_decode(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(&hex[idx..idx + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    const KEY: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    fn verify(
+        signer: &UrlSigner,
+        method: Method,
+        url: &str,
+        peer_ip: Option<IpAddr>,
+    ) -> Result<(), SignedUrlError> {
+        signer.verify_at(&method, &url.parse().unwrap(), peer_ip, SystemTime::now())
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let signer = UrlSigner::new(KEY);
+
+        let url = signer
+            .sign("/downloads/7?inline=1", Duration::from_secs(60))
+            .finish();
+        assert!(url.starts_with("/downloads/7?inline=1&expires="));
+        assert!(verify(&signer, Method::GET, &url, None).is_ok());
+
+        assert!(matches!(
+            verify(&signer, Method::DELETE, &url, None),
+            Err(SignedUrlError::Invalid)
+        ));
+        assert!(matches!(
+            verify(&signer, Method::GET, &url.replace("/7?", "/8?"), None),
+            Err(SignedUrlError::Invalid)
+        ));
+        assert!(matches!(
+            verify(&signer, Method::GET, &format!("{url}&inline=0"), None),
+            Err(SignedUrlError::Invalid)
+        ));
+        assert!(matches!(
+            verify(&signer, Method::GET, "/downloads/7", None),
+            Err(SignedUrlError::Missing)
+        ));
+
+        let other = UrlSigner::new([7; 32]);
+        assert!(matches!(
+            verify(&other, Method::GET, &url, None),
+            Err(SignedUrlError::Invalid)
+        ));
+    }
+
+    #[test]
+    fn absolute_url() {
+        let signer = UrlSigner::new(KEY);
+
+        let url = signer
+            .sign(
+                "https://portal.example.com/downloads/7",
+                Duration::from_secs(60),
+            )
+            .method(Method::POST)
+            .finish();
+        assert!(url.starts_with("https://portal.example.com/downloads/7?expires="));
+
+        let path = url.trim_start_matches("https://portal.example.com");
+        assert!(verify(&signer, Method::POST, path, None).is_ok());
+    }
+
+    #[test]
+    fn expiry() {
+        let signer = UrlSigner::new(KEY);
+
+        let url = signer.sign("/downloads/7", Duration::ZERO).finish();
+        assert!(matches!(
+            verify(&signer, Method::GET, &url, None),
+            Err(SignedUrlError::Expired)
+        ));
+
+        let url = signer
+            .sign("/downloads/7", Duration::from_secs(60))
+            .finish();
+        let later = SystemTime::now() + Duration::from_secs(61);
+        assert!(matches!(
+            signer.verify_at(&Method::GET, &url.parse().unwrap(), None, later),
+            Err(SignedUrlError::Expired)
+        ));
+    }
+
+    #[test]
+    fn peer_ip_binding() {
+        let signer = UrlSigner::new(KEY);
+        let ip = IpAddr::from(Ipv4Addr::new(203, 0, 113, 7));
+
+        let url = signer
+            .sign("/downloads/7", Duration::from_secs(60))
+            .peer_ip(ip)
+            .finish();
+        assert!(url.contains("&bind_ip=1&"));
+
+        assert!(verify(&signer, Method::GET, &url, Some(ip)).is_ok());
+        assert!(matches!(
+            verify(&signer, Method::GET, &url, Some(Ipv4Addr::LOCALHOST.into())),
+            Err(SignedUrlError::Invalid)
+        ));
+        assert!(matches!(
+            verify(&signer, Method::GET, &url, None),
+            Err(SignedUrlError::Invalid)
+        ));
+
+        // removing the binding invalidates the signature
+        let unbound = url.replace("&bind_ip=1", "");
+        assert!(matches!(
+            verify(&signer, Method::GET, &unbound, Some(ip)),
+            Err(SignedUrlError::Invalid)
+        ));
+    }
+
+    #[test]
+    #[should_panic]
+    fn short_key() {
+        UrlSigner::new(b"too short");
+    }
+}
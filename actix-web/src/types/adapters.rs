@@ -0,0 +1,348 @@
+//! Adapters for deserializing URL encoded fields in custom formats.
+//!
+//! Adapters are registered for individual fields on [`QueryConfig`](super::QueryConfig) and
+//! [`FormConfig`](super::FormConfig). They rewrite a field's raw value before it is deserialized,
+//! so that handlers can declare fields with their natural types instead of strings that they
+//! parse themselves.
+//!
+//! The date-time adapters in this module produce [RFC 3339] date-times, which `chrono`'s
+//! `DateTime` types deserialize from by default and `time`'s `OffsetDateTime` deserializes from
+//! with `#[serde(with = "time::serde::rfc3339")]`.
+//!
+//! # Examples
+//! ```
+//! use actix_web::{get, web, App};
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Search {
+//!     // `?since=1700000000000`
+//!     since: String,
+//!     // `?ids=1,2,3`
+//!     ids: Vec<u32>,
+//! }
+//!
+//! #[get("/appointments")]
+//! async fn appointments(search: web::Query<Search>) -> String {
+//!     format!("{} appointments since {}", search.ids.len(), search.since)
+//! }
+//!
+//! let query_cfg = web::QueryConfig::default()
+//!     .adapter("since", web::adapters::epoch_millis)
+//!     .list("ids", ',');
+//!
+//! App::new().app_data(query_cfg).service(appointments);
+//! ```
+//!
+//! [RFC 3339]: https://www.rfc-editor.org/rfc/rfc3339
+
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
+
+use serde::de::{
+    self,
+    value::{Error, MapDeserializer, SeqDeserializer},
+    DeserializeOwned, Error as _, IntoDeserializer, Visitor,
+};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+/// Converts a Unix timestamp in milliseconds to an RFC 3339 date-time in UTC.
+///
+/// ```
+/// use actix_web::web::adapters::epoch_millis;
+///
+/// assert_eq!(epoch_millis("1700000000123").unwrap(), "2023-11-14T22:13:20.123Z");
+/// assert!(epoch_millis("yesterday").is_err());
+/// ```
+pub fn epoch_millis(value: &str) -> Result<String, String> {
+    let millis = value
+        .parse::<i64>()
+        .map_err(|_| "expected a Unix timestamp in milliseconds".to_owned())?;
+
+    format_rfc3339(i128::from(millis) * 1_000_000)
+}
+
+/// Converts a Unix timestamp in seconds to an RFC 3339 date-time in UTC.
+///
+/// ```
+/// use actix_web::web::adapters::epoch_seconds;
+///
+/// assert_eq!(epoch_seconds("1700000000").unwrap(), "2023-11-14T22:13:20Z");
+/// ```
+pub fn epoch_seconds(value: &str) -> Result<String, String> {
+    let secs = value
+        .parse::<i64>()
+        .map_err(|_| "expected a Unix timestamp in seconds".to_owned())?;
+
+    format_rfc3339(i128::from(secs) * 1_000_000_000)
+}
+
+fn format_rfc3339(nanos: i128) -> Result<String, String> {
+    OffsetDateTime::from_unix_timestamp_nanos(nanos)
+        .map_err(|err| err.to_string())?
+        .format(&Rfc3339)
+        .map_err(|err| err.to_string())
+}
+
+type AdapterFn = dyn Fn(&str) -> Result<String, String> + Send + Sync;
+
+/// Adapters and list separators registered for the fields of a URL encoded payload.
+#[derive(Clone, Default)]
+pub(crate) struct FieldAdapters(Option<Arc<AdaptersInner>>);
+
+#[derive(Clone, Default)]
+struct AdaptersInner {
+    adapters: HashMap<String, Arc<AdapterFn>>,
+    separators: HashMap<String, char>,
+}
+
+impl FieldAdapters {
+    /// No adapters, usable where a constant is promoted to `'static`.
+    pub(crate) const EMPTY: Self = Self(None);
+
+    pub(crate) const fn new() -> Self {
+        Self::EMPTY
+    }
+
+    pub(crate) fn adapter<F>(&mut self, field: String, adapter: F)
+    where
+        F: Fn(&str) -> Result<String, String> + Send + Sync + 'static,
+    {
+        self.inner_mut().adapters.insert(field, Arc::new(adapter));
+    }
+
+    pub(crate) fn list(&mut self, field: String, separator: char) {
+        self.inner_mut().separators.insert(field, separator);
+    }
+
+    fn inner_mut(&mut self) -> &mut AdaptersInner {
+        Arc::make_mut(self.0.get_or_insert_with(Default::default))
+    }
+
+    /// Deserializes a `T` from URL encoded `input`, applying the registered adapters.
+    ///
+    /// Without any adapters, this is equivalent to [`serde_urlencoded::from_bytes()`].
+    pub(crate) fn deserialize<T: DeserializeOwned>(&self, input: &[u8]) -> Result<T, Error> {
+        let Some(ref inner) = self.0 else {
+            return serde_urlencoded::from_bytes(input);
+        };
+
+        let mut fields = Vec::<(Cow<'_, str>, FieldValue<'_>)>::new();
+
+        for (name, value) in url::form_urlencoded::parse(input) {
+            let adapter = inner.adapters.get(name.as_ref());
+
+            let adapt = |value: &str| match adapter {
+                Some(adapter) => adapter(value).map(Cow::Owned).map_err(|msg| {
+                    Error::custom(format_args!("invalid value for field `{name}`: {msg}"))
+                }),
+                None => Ok(Cow::Owned(value.to_owned())),
+            };
+
+            match inner.separators.get(name.as_ref()) {
+                Some(&separator) => {
+                    let items = value
+                        .split(separator)
+                        .filter(|item| !item.is_empty())
+                        .map(adapt)
+                        .collect::<Result<Vec<_>, _>>()?;
+
+                    // repeated list fields are joined into one list
+                    match fields.iter_mut().find(|(field, _)| *field == name) {
+                        Some((_, FieldValue::Many(list))) => list.extend(items),
+                        _ => fields.push((name, FieldValue::Many(items))),
+                    }
+                }
+
+                None if adapter.is_some() => {
+                    let value = adapt(&value)?;
+                    fields.push((name, FieldValue::One(value)));
+                }
+
+                None => fields.push((name, FieldValue::One(value))),
+            }
+        }
+
+        T::deserialize(MapDeserializer::new(fields.into_iter()))
+    }
+}
+
+/// Value of a URL encoded field, deserialized like `serde_urlencoded` deserializes values.
+enum FieldValue<'a> {
+    One(Cow<'a, str>),
+    Many(Vec<Cow<'a, str>>),
+}
+
+impl<'de, 'a> IntoDeserializer<'de, Error> for FieldValue<'a> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+macro_rules! forward_parsed_value {
+    ($($ty:ident => $method:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                match self {
+                    FieldValue::One(value) => match value.parse::<$ty>() {
+                        Ok(val) => IntoDeserializer::<'de, Error>::into_deserializer(val)
+                            .$method(visitor),
+                        Err(err) => Err(Error::custom(err)),
+                    },
+                    FieldValue::Many(_) => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for FieldValue<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            FieldValue::One(value) => visitor.visit_str(&value),
+            FieldValue::Many(items) => {
+                visitor.visit_seq(SeqDeserializer::new(items.into_iter().map(FieldValue::One)))
+            }
+        }
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            FieldValue::One(value) => visitor.visit_seq(SeqDeserializer::new(std::iter::once(
+                FieldValue::One(value),
+            ))),
+            FieldValue::Many(_) => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            FieldValue::One(value) => visitor.visit_enum(
+                IntoDeserializer::<'de, Error>::into_deserializer(value.into_owned()),
+            ),
+            FieldValue::Many(_) => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_parsed_value! {
+        bool => deserialize_bool,
+        u8 => deserialize_u8,
+        u16 => deserialize_u16,
+        u32 => deserialize_u32,
+        u64 => deserialize_u64,
+        i8 => deserialize_i8,
+        i16 => deserialize_i16,
+        i32 => deserialize_i32,
+        i64 => deserialize_i64,
+        f32 => deserialize_f32,
+        f64 => deserialize_f64,
+    }
+
+    serde::forward_to_deserialize_any! {
+        char str string unit bytes byte_buf unit_struct tuple_struct tuple map struct identifier
+        ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Kind {
+        Visit,
+        Call,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Search {
+        since: String,
+        ids: Vec<u32>,
+        kinds: Option<Vec<Kind>>,
+        limit: Option<u8>,
+        kind: Kind,
+    }
+
+    #[test]
+    fn deserialize() {
+        let mut adapters = FieldAdapters::new();
+        adapters.adapter("since".to_owned(), epoch_seconds);
+        adapters.list("ids".to_owned(), ',');
+        adapters.list("kinds".to_owned(), ' ');
+
+        let search = adapters
+            .deserialize::<Search>(b"since=0&ids=1,2&ids=3&kinds=Visit+Call&limit=10&kind=Call")
+            .unwrap();
+
+        assert_eq!(
+            search,
+            Search {
+                since: "1970-01-01T00:00:00Z".to_owned(),
+                ids: vec![1, 2, 3],
+                kinds: Some(vec![Kind::Visit, Kind::Call]),
+                limit: Some(10),
+                kind: Kind::Call,
+            }
+        );
+
+        let err = adapters
+            .deserialize::<Search>(b"since=now&ids=1&kind=Call")
+            .unwrap_err();
+        assert!(err.to_string().contains("`since`"));
+
+        assert!(adapters
+            .deserialize::<Search>(b"since=0&ids=1,x&kind=Call")
+            .is_err());
+    }
+
+    #[test]
+    fn no_adapters() {
+        #[derive(Deserialize)]
+        struct Id {
+            id: u32,
+        }
+
+        let id = FieldAdapters::new().deserialize::<Id>(b"id=7").unwrap();
+        assert_eq!(id.id, 7);
+    }
+}
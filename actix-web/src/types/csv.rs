@@ -0,0 +1,333 @@
+//! Streaming CSV responder. See [`Csv`].
+
+use std::{
+    cell::RefCell,
+    io,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use bytes::{Bytes, BytesMut};
+use futures_core::Stream;
+use pin_project_lite::pin_project;
+use serde::Serialize;
+
+use crate::{
+    body::{BodySize, BoxBody, MessageBody},
+    http::header::ContentDisposition,
+    BoxError, HttpRequest, HttpResponse, Responder,
+};
+
+/// Number of buffered bytes after which a chunk is sent without waiting for more rows.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Byte order mark, which some spreadsheet applications need to detect UTF-8 CSV files.
+const BOM: &[u8] = "\u{FEFF}".as_bytes();
+
+/// Streaming CSV responder.
+///
+/// `Csv` serializes the rows of a stream with [`serde`] as they arrive and sends them in chunks,
+/// so that large exports are not built in memory. The first row is preceded by a header row
+/// holding the field names of the serialized structs, unless turned off with
+/// [`has_headers()`](Self::has_headers).
+///
+/// The response is `200 OK` with a `text/csv; charset=utf-8` content type. If the row stream or
+/// serialization of a row fails, the response body fails with that error, which aborts the
+/// response so that clients can not mistake it for a complete file.
+///
+/// # Examples
+/// ```
+/// use actix_web::{get, web, Responder};
+/// use futures_util::stream;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Appointment {
+///     id: u32,
+///     patient: String,
+///     starts_at: String,
+/// }
+///
+/// #[get("/schedule.csv")]
+/// async fn schedule() -> impl Responder {
+///     let rows = stream::iter(vec![Ok::<_, std::io::Error>(Appointment {
+///         id: 1,
+///         patient: "Jane Doe".to_owned(),
+///         starts_at: "2024-05-01T09:00:00Z".to_owned(),
+///     })]);
+///
+///     web::Csv::new(rows).delimiter(b';').bom(true).attachment("schedule.csv")
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Csv<S> {
+    rows: S,
+    delimiter: u8,
+    has_headers: bool,
+    bom: bool,
+    filename: Option<String>,
+}
+
+impl<S, T, E> Csv<S>
+where
+    S: Stream<Item = Result<T, E>> + 'static,
+    T: Serialize,
+    E: Into<BoxError> + 'static,
+{
+    /// Constructs a responder that serializes the rows of `rows`.
+    pub fn new(rows: S) -> Self {
+        Self {
+            rows,
+            delimiter: b',',
+            has_headers: true,
+            bom: false,
+            filename: None,
+        }
+    }
+
+    /// Sets the field delimiter.
+    ///
+    /// Defaults to `,`.
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Sets whether a header row is written before the first row.
+    ///
+    /// Defaults to true.
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+
+    /// Sets whether the body starts with a UTF-8 byte order mark.
+    ///
+    /// Some spreadsheet applications only detect the encoding of CSV files that start with one.
+    /// Defaults to false.
+    pub fn bom(mut self, bom: bool) -> Self {
+        self.bom = bom;
+        self
+    }
+
+    /// Sets a `Content-Disposition` header that asks clients to save the response as a file with
+    /// the given name.
+    pub fn attachment(mut self, filename: impl Into<String>) -> Self {
+        self.filename = Some(filename.into());
+        self
+    }
+}
+
+impl<S, T, E> Responder for Csv<S>
+where
+    S: Stream<Item = Result<T, E>> + 'static,
+    T: Serialize,
+    E: Into<BoxError> + 'static,
+{
+    type Body = BoxBody;
+
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        let buf = SharedBuf::default();
+
+        if self.bom {
+            buf.0.borrow_mut().extend_from_slice(BOM);
+        }
+
+        let writer = ::csv::WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .has_headers(self.has_headers)
+            .from_writer(buf.clone());
+
+        let mut res = HttpResponse::Ok();
+        res.content_type(mime::TEXT_CSV_UTF_8);
+
+        if let Some(filename) = self.filename {
+            res.insert_header(ContentDisposition::attachment(filename));
+        }
+
+        res.body(CsvBody {
+            rows: self.rows,
+            writer: Some(writer),
+            buf,
+        })
+    }
+}
+
+pin_project! {
+    struct CsvBody<S> {
+        #[pin]
+        rows: S,
+        writer: Option<::csv::Writer<SharedBuf>>,
+        buf: SharedBuf,
+    }
+}
+
+impl<S, T, E> MessageBody for CsvBody<S>
+where
+    S: Stream<Item = Result<T, E>>,
+    T: Serialize,
+    E: Into<BoxError>,
+{
+    type Error = BoxError;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let mut this = self.project();
+
+        let Some(writer) = this.writer else {
+            return Poll::Ready(None);
+        };
+
+        loop {
+            if this.buf.len() >= CHUNK_SIZE {
+                return Poll::Ready(Some(Ok(this.buf.take())));
+            }
+
+            match this.rows.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(row))) => {
+                    if let Err(err) = writer.serialize(row) {
+                        *this.writer = None;
+                        return Poll::Ready(Some(Err(err.into())));
+                    }
+                }
+
+                Poll::Ready(Some(Err(err))) => {
+                    *this.writer = None;
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+
+                Poll::Ready(None) => {
+                    let res = writer.flush();
+                    *this.writer = None;
+
+                    return match res {
+                        Ok(()) if this.buf.is_empty() => Poll::Ready(None),
+                        Ok(()) => Poll::Ready(Some(Ok(this.buf.take()))),
+                        Err(err) => Poll::Ready(Some(Err(err.into()))),
+                    };
+                }
+
+                Poll::Pending => {
+                    // send what has been serialized so far while waiting for more rows
+                    if let Err(err) = writer.flush() {
+                        *this.writer = None;
+                        return Poll::Ready(Some(Err(err.into())));
+                    }
+
+                    if this.buf.is_empty() {
+                        return Poll::Pending;
+                    }
+
+                    return Poll::Ready(Some(Ok(this.buf.take())));
+                }
+            }
+        }
+    }
+}
+
+/// Buffer that the CSV writer flushes into, shared with the body so it can take the output.
+#[derive(Clone, Default)]
+struct SharedBuf(Rc<RefCell<BytesMut>>);
+
+impl SharedBuf {
+    fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+
+    fn take(&self) -> Bytes {
+        self.0.borrow_mut().split().freeze()
+    }
+}
+
+impl io::Write for SharedBuf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use futures_util::stream;
+
+    use super::*;
+    use crate::{body::to_bytes, http::header, test::TestRequest};
+
+    #[derive(Serialize)]
+    struct Row {
+        id: u32,
+        name: &'static str,
+    }
+
+    #[actix_rt::test]
+    async fn responder() {
+        let req = TestRequest::default().to_http_request();
+
+        let rows = stream::iter([
+            Ok::<_, Infallible>(Row { id: 1, name: "a" }),
+            Ok(Row { id: 2, name: "b;c" }),
+        ]);
+        let res = Csv::new(rows)
+            .delimiter(b';')
+            .bom(true)
+            .attachment("rows.csv")
+            .respond_to(&req);
+
+        assert!(res.status().is_success());
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/csv; charset=utf-8"
+        );
+        assert_eq!(
+            res.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+            "attachment; filename=\"rows.csv\""
+        );
+
+        let body = to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, "\u{FEFF}id;name\n1;a\n2;\"b;c\"\n");
+    }
+
+    #[actix_rt::test]
+    async fn without_headers() {
+        let req = TestRequest::default().to_http_request();
+
+        let rows = stream::iter([Ok::<_, Infallible>(Row { id: 1, name: "a" })]);
+        let res = Csv::new(rows).has_headers(false).respond_to(&req);
+
+        assert!(res.headers().get(header::CONTENT_DISPOSITION).is_none());
+
+        let body = to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, "1,a\n");
+    }
+
+    #[actix_rt::test]
+    async fn row_error() {
+        let req = TestRequest::default().to_http_request();
+
+        let rows = stream::iter([
+            Ok(Row { id: 1, name: "a" }),
+            Err(io::Error::new(io::ErrorKind::Other, "database gone")),
+        ]);
+        let res = Csv::new(rows).respond_to(&req);
+
+        let err = to_bytes(res.into_body()).await.unwrap_err();
+        assert_eq!(err.to_string(), "database gone");
+    }
+}
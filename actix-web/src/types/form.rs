@@ -17,11 +17,12 @@ use futures_core::{future::LocalBoxFuture, ready};
 use futures_util::{FutureExt as _, StreamExt as _};
 use serde::{de::DeserializeOwned, Serialize};
 
+use super::adapters::FieldAdapters;
 #[cfg(feature = "__compress")]
 use crate::dev::Decompress;
 use crate::{
     body::EitherBody, error::UrlencodedError, extract::FromRequest, http::header::CONTENT_LENGTH,
-    web, Error, HttpMessage, HttpRequest, HttpResponse, Responder,
+    middleware::MemoryBudget, web, Error, HttpMessage, HttpRequest, HttpResponse, Responder,
 };
 
 /// URL encoded payload extractor and responder.
@@ -130,10 +131,16 @@ where
 
     #[inline]
     fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
-        let FormConfig { limit, err_handler } = FormConfig::from_req(req).clone();
+        let FormConfig {
+            limit,
+            err_handler,
+            adapters,
+        } = FormConfig::from_req(req).clone();
 
         FormExtractFut {
-            fut: UrlEncoded::new(req, payload).limit(limit),
+            fut: UrlEncoded::new(req, payload)
+                .limit(limit)
+                .adapters(adapters),
             req: req.clone(),
             err_handler,
         }
@@ -224,6 +231,7 @@ impl<T: Serialize> Responder for Form<T> {
 pub struct FormConfig {
     limit: usize,
     err_handler: FormErrHandler,
+    adapters: FieldAdapters,
 }
 
 impl FormConfig {
@@ -242,6 +250,29 @@ impl FormConfig {
         self
     }
 
+    /// Registers an adapter that rewrites the raw value of `field` before it is deserialized.
+    ///
+    /// Adapters return the rewritten value, or a message describing why the value is invalid,
+    /// which fails extraction. See the [`adapters`](super::adapters) module for adapters for common
+    /// formats, such as Unix timestamps.
+    pub fn adapter<F>(mut self, field: impl Into<String>, adapter: F) -> Self
+    where
+        F: Fn(&str) -> Result<String, String> + Send + Sync + 'static,
+    {
+        self.adapters.adapter(field.into(), adapter);
+        self
+    }
+
+    /// Splits the value of `field` into a list at each `separator`, e.g., to deserialize
+    /// `tags=a,b,c` into a `Vec<String>`.
+    ///
+    /// Empty items are skipped, repeated fields are joined into one list, and an
+    /// [adapter](Self::adapter) registered for the field is applied to each item.
+    pub fn list(mut self, field: impl Into<String>, separator: char) -> Self {
+        self.adapters.list(field.into(), separator);
+        self
+    }
+
     /// Extract payload config from app data.
     ///
     /// Checks both `T` and `Data<T>`, in that order, and falls back to the default payload config.
@@ -256,6 +287,7 @@ impl FormConfig {
 const DEFAULT_CONFIG: FormConfig = FormConfig {
     limit: 16_384, // 2^14 bytes (~16kB)
     err_handler: None,
+    adapters: FieldAdapters::EMPTY,
 };
 
 impl Default for FormConfig {
@@ -280,6 +312,8 @@ pub struct UrlEncoded<T> {
     limit: usize,
     length: Option<usize>,
     encoding: &'static Encoding,
+    budget: Option<MemoryBudget>,
+    adapters: FieldAdapters,
     err: Option<UrlencodedError>,
     fut: Option<LocalBoxFuture<'static, Result<T, UrlencodedError>>>,
 }
@@ -325,6 +359,8 @@ impl<T> UrlEncoded<T> {
             stream: Some(payload),
             limit: 32_768,
             length: len,
+            budget: MemoryBudget::of(req),
+            adapters: FieldAdapters::new(),
             fut: None,
             err: None,
         }
@@ -338,6 +374,8 @@ impl<T> UrlEncoded<T> {
             err: Some(err),
             length: None,
             encoding: UTF_8,
+            budget: None,
+            adapters: FieldAdapters::new(),
         }
     }
 
@@ -346,6 +384,12 @@ impl<T> UrlEncoded<T> {
         self.limit = limit;
         self
     }
+
+    /// Set adapters applied to fields before they are deserialized.
+    pub(crate) fn adapters(mut self, adapters: FieldAdapters) -> Self {
+        self.adapters = adapters;
+        self
+    }
 }
 
 impl<T> Future for UrlEncoded<T>
@@ -373,6 +417,8 @@ where
 
         // future
         let encoding = self.encoding;
+        let budget = self.budget.take();
+        let adapters = std::mem::take(&mut self.adapters);
         let mut stream = self.stream.take().unwrap();
 
         self.fut = Some(
@@ -387,20 +433,32 @@ where
                             size: body.len() + chunk.len(),
                             limit,
                         });
+                    } else if let Some(Err(err)) = budget
+                        .as_ref()
+                        .map(|budget| budget.try_reserve(chunk.len()))
+                    {
+                        return Err(UrlencodedError::Overflow {
+                            size: body.len() + chunk.len(),
+                            limit: body.len() + err.remaining,
+                        });
                     } else {
                         body.extend_from_slice(&chunk);
                     }
                 }
 
                 if encoding == UTF_8 {
-                    serde_urlencoded::from_bytes::<T>(&body).map_err(UrlencodedError::Parse)
+                    adapters
+                        .deserialize::<T>(&body)
+                        .map_err(UrlencodedError::Parse)
                 } else {
                     let body = encoding
                         .decode_without_bom_handling_and_without_replacement(&body)
                         .map(Cow::into_owned)
                         .ok_or(UrlencodedError::Encoding)?;
 
-                    serde_urlencoded::from_str::<T>(&body).map_err(UrlencodedError::Parse)
+                    adapters
+                        .deserialize::<T>(body.as_bytes())
+                        .map_err(UrlencodedError::Parse)
                 }
             }
             .boxed_local(),
@@ -5,7 +5,7 @@ use std::{fmt, ops};
 use actix_utils::future::{ready, Ready};
 
 use crate::{
-    dev::Payload, error::ParseError, extract::FromRequest, http::header::Header as ParseHeader,
+    dev::Payload, error::HeaderError, extract::FromRequest, http::header::Header as ParseHeader,
     HttpRequest,
 };
 
@@ -14,6 +14,10 @@ use crate::{
 /// To extract a header, the inner type `T` must implement the
 /// [`Header`](crate::http::header::Header) trait.
 ///
+/// If the header is missing or can not be parsed, extraction fails with a [`HeaderError`] naming
+/// the header, which results in a `400 Bad Request` response. Use `Option<Header<T>>` for headers
+/// that may be absent.
+///
 /// # Examples
 /// ```
 /// use actix_web::{get, web, http::header};
@@ -60,14 +64,17 @@ impl<T> FromRequest for Header<T>
 where
     T: ParseHeader,
 {
-    type Error = ParseError;
+    type Error = HeaderError;
     type Future = Ready<Result<Self, Self::Error>>;
 
     #[inline]
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
         match ParseHeader::parse(req) {
             Ok(header) => ready(Ok(Header(header))),
-            Err(err) => ready(Err(err)),
+            Err(_) if !req.headers().contains_key(T::name()) => {
+                ready(Err(HeaderError::Missing { name: T::name() }))
+            }
+            Err(_) => ready(Err(HeaderError::Invalid { name: T::name() })),
         }
     }
 }
@@ -97,8 +104,26 @@ mod tests {
             .unwrap();
         assert_eq!(s.into_inner().0, vec![Method::GET]);
 
-        assert!(Header::<header::Date>::from_request(&req, &mut pl)
+        let err = Header::<header::Date>::from_request(&req, &mut pl)
             .await
-            .is_err());
+            .unwrap_err();
+        assert!(matches!(err, HeaderError::Missing { .. }));
+        assert_eq!(err.to_string(), "Missing `date` header");
+    }
+
+    #[actix_rt::test]
+    async fn test_header_extract_invalid() {
+        let (req, mut pl) = TestRequest::default()
+            .insert_header((header::IF_MODIFIED_SINCE, "yesterday"))
+            .to_http_parts();
+
+        let err = Header::<header::IfModifiedSince>::from_request(&req, &mut pl)
+            .await
+            .unwrap_err();
+        assert_eq!(err.name(), &header::IF_MODIFIED_SINCE);
+        assert_eq!(err.to_string(), "Invalid `if-modified-since` header");
+
+        let res = crate::ResponseError::error_response(&err);
+        assert_eq!(res.status(), crate::http::StatusCode::BAD_REQUEST);
     }
 }
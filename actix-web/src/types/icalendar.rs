@@ -0,0 +1,421 @@
+//! Streaming iCalendar responder. See [`ICalendar`].
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::SystemTime,
+};
+
+use bytes::{Bytes, BytesMut};
+use futures_core::Stream;
+use pin_project_lite::pin_project;
+use time::OffsetDateTime;
+
+use crate::{
+    body::{BodySize, BoxBody, MessageBody},
+    http::header::ContentDisposition,
+    BoxError, HttpRequest, HttpResponse, Responder,
+};
+
+/// Number of buffered bytes after which a chunk is sent without waiting for more events.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Maximum length of a content line in octets, not counting the line break.
+const MAX_LINE_LEN: usize = 75;
+
+const DEFAULT_PRODUCT_ID: &str = "-//Actix Web//NONSGML Calendar//EN";
+
+/// An event in an [`ICalendar`] response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarEvent {
+    uid: String,
+    start: SystemTime,
+    end: Option<SystemTime>,
+    summary: Option<String>,
+    description: Option<String>,
+    location: Option<String>,
+}
+
+impl CalendarEvent {
+    /// Constructs an event that starts at `start`.
+    ///
+    /// The `uid` identifies the event across exports, so that calendar applications update
+    /// events they have imported before instead of duplicating them. It should be globally
+    /// unique, for example by including the domain name of the application.
+    pub fn new(uid: impl Into<String>, start: SystemTime) -> Self {
+        Self {
+            uid: uid.into(),
+            start,
+            end: None,
+            summary: None,
+            description: None,
+            location: None,
+        }
+    }
+
+    /// Sets the time at which the event ends.
+    pub fn end(mut self, end: SystemTime) -> Self {
+        self.end = Some(end);
+        self
+    }
+
+    /// Sets the title of the event.
+    pub fn summary(mut self, summary: impl Into<String>) -> Self {
+        self.summary = Some(summary.into());
+        self
+    }
+
+    /// Sets the description of the event.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Sets the location of the event.
+    pub fn location(mut self, location: impl Into<String>) -> Self {
+        self.location = Some(location.into());
+        self
+    }
+
+    fn write_to(&self, buf: &mut BytesMut, dtstamp: &str) {
+        write_line(buf, "BEGIN:VEVENT");
+        write_property(buf, "UID", &self.uid);
+        write_line(buf, &format!("DTSTAMP:{dtstamp}"));
+        write_line(buf, &format!("DTSTART:{}", format_date_time(self.start)));
+
+        if let Some(end) = self.end {
+            write_line(buf, &format!("DTEND:{}", format_date_time(end)));
+        }
+
+        for (name, value) in [
+            ("SUMMARY", &self.summary),
+            ("DESCRIPTION", &self.description),
+            ("LOCATION", &self.location),
+        ] {
+            if let Some(value) = value {
+                write_property(buf, name, value);
+            }
+        }
+
+        write_line(buf, "END:VEVENT");
+    }
+}
+
+/// Streaming iCalendar ([RFC 5545]) responder.
+///
+/// `ICalendar` writes the events of a stream as they arrive and sends them in chunks, so that
+/// large calendars are not built in memory. Calendar applications can import the response or
+/// subscribe to its URL.
+///
+/// The response is `200 OK` with a `text/calendar; charset=utf-8` content type. Times are written
+/// in UTC. If the event stream fails, the response body fails with that error, which aborts the
+/// response so that clients can not mistake it for a complete calendar.
+///
+/// # Examples
+/// ```
+/// use std::time::{Duration, SystemTime};
+///
+/// use actix_web::{get, web, Responder};
+/// use futures_util::stream;
+///
+/// #[get("/schedule.ics")]
+/// async fn schedule() -> impl Responder {
+///     let start = SystemTime::now() + Duration::from_secs(24 * 60 * 60);
+///
+///     let events = stream::iter(vec![Ok::<_, std::io::Error>(
+///         web::CalendarEvent::new("appointment-1@clinic.example", start)
+///             .end(start + Duration::from_secs(30 * 60))
+///             .summary("Follow-up consultation"),
+///     )]);
+///
+///     web::ICalendar::new(events)
+///         .name("Appointments")
+///         .attachment("schedule.ics")
+/// }
+/// ```
+///
+/// [RFC 5545]: https://www.rfc-editor.org/rfc/rfc5545
+#[derive(Debug)]
+pub struct ICalendar<S> {
+    events: S,
+    product_id: String,
+    name: Option<String>,
+    filename: Option<String>,
+}
+
+impl<S, E> ICalendar<S>
+where
+    S: Stream<Item = Result<CalendarEvent, E>> + 'static,
+    E: Into<BoxError> + 'static,
+{
+    /// Constructs a responder that writes the events of `events`.
+    pub fn new(events: S) -> Self {
+        Self {
+            events,
+            product_id: DEFAULT_PRODUCT_ID.to_owned(),
+            name: None,
+            filename: None,
+        }
+    }
+
+    /// Sets the identifier of the product that created the calendar (the `PRODID` property).
+    ///
+    /// Defaults to `-//Actix Web//NONSGML Calendar//EN`.
+    pub fn product_id(mut self, product_id: impl Into<String>) -> Self {
+        self.product_id = product_id.into();
+        self
+    }
+
+    /// Sets the name that calendar applications show for the calendar.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets a `Content-Disposition` header that asks clients to save the response as a file with
+    /// the given name.
+    pub fn attachment(mut self, filename: impl Into<String>) -> Self {
+        self.filename = Some(filename.into());
+        self
+    }
+}
+
+impl<S, E> Responder for ICalendar<S>
+where
+    S: Stream<Item = Result<CalendarEvent, E>> + 'static,
+    E: Into<BoxError> + 'static,
+{
+    type Body = BoxBody;
+
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        let mut buf = BytesMut::new();
+
+        write_line(&mut buf, "BEGIN:VCALENDAR");
+        write_line(&mut buf, "VERSION:2.0");
+        write_property(&mut buf, "PRODID", &self.product_id);
+
+        if let Some(ref name) = self.name {
+            write_property(&mut buf, "X-WR-CALNAME", name);
+        }
+
+        let mut res = HttpResponse::Ok();
+        res.content_type("text/calendar; charset=utf-8");
+
+        if let Some(filename) = self.filename {
+            res.insert_header(ContentDisposition::attachment(filename));
+        }
+
+        res.body(ICalendarBody {
+            events: self.events,
+            dtstamp: format_date_time(SystemTime::now()),
+            buf,
+            done: false,
+        })
+    }
+}
+
+pin_project! {
+    struct ICalendarBody<S> {
+        #[pin]
+        events: S,
+        dtstamp: String,
+        buf: BytesMut,
+        done: bool,
+    }
+}
+
+impl<S, E> MessageBody for ICalendarBody<S>
+where
+    S: Stream<Item = Result<CalendarEvent, E>>,
+    E: Into<BoxError>,
+{
+    type Error = BoxError;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let mut this = self.project();
+
+        while !*this.done && this.buf.len() < CHUNK_SIZE {
+            match this.events.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(event))) => event.write_to(this.buf, this.dtstamp),
+
+                Poll::Ready(Some(Err(err))) => {
+                    *this.done = true;
+                    this.buf.clear();
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+
+                Poll::Ready(None) => {
+                    *this.done = true;
+                    write_line(this.buf, "END:VCALENDAR");
+                }
+
+                // send what has been written so far while waiting for more events
+                Poll::Pending if this.buf.is_empty() => return Poll::Pending,
+                Poll::Pending => break,
+            }
+        }
+
+        if this.buf.is_empty() {
+            return Poll::Ready(None);
+        }
+
+        Poll::Ready(Some(Ok(this.buf.split().freeze())))
+    }
+}
+
+/// Formats `time` as a UTC date-time, like `20240501T090000Z`.
+fn format_date_time(time: SystemTime) -> String {
+    let dt = OffsetDateTime::from(time);
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        dt.year(),
+        u8::from(dt.month()),
+        dt.day(),
+        dt.hour(),
+        dt.minute(),
+        dt.second(),
+    )
+}
+
+/// Writes a property with a text value, escaping the value.
+fn write_property(buf: &mut BytesMut, name: &str, value: &str) {
+    let mut line = String::with_capacity(name.len() + 1 + value.len());
+    line.push_str(name);
+    line.push(':');
+
+    for ch in value.chars() {
+        match ch {
+            '\\' | ';' | ',' => {
+                line.push('\\');
+                line.push(ch);
+            }
+            '\n' => line.push_str("\\n"),
+            '\r' => {}
+            _ => line.push(ch),
+        }
+    }
+
+    write_line(buf, &line);
+}
+
+/// Writes a content line, folding it into lines of at most 75 octets.
+fn write_line(buf: &mut BytesMut, mut line: &str) {
+    // continuation lines start with a space, which counts towards their length
+    let mut max_len = MAX_LINE_LEN;
+
+    while line.len() > max_len {
+        let mut at = max_len;
+        while !line.is_char_boundary(at) {
+            at -= 1;
+        }
+
+        let (head, tail) = line.split_at(at);
+        buf.extend_from_slice(head.as_bytes());
+        buf.extend_from_slice(b"\r\n ");
+
+        line = tail;
+        max_len = MAX_LINE_LEN - 1;
+    }
+
+    buf.extend_from_slice(line.as_bytes());
+    buf.extend_from_slice(b"\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, io, time::Duration};
+
+    use futures_util::stream;
+
+    use super::*;
+    use crate::{body::to_bytes, http::header, test::TestRequest};
+
+    #[actix_rt::test]
+    async fn responder() {
+        let req = TestRequest::default().to_http_request();
+
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_714_554_000);
+        let events = stream::iter([Ok::<_, Infallible>(
+            CalendarEvent::new("1@example.com", start)
+                .end(start + Duration::from_secs(1800))
+                .summary("Check-up; Dr. Smith, room 2")
+                .description("Bring\nprevious results"),
+        )]);
+        let res = ICalendar::new(events)
+            .name("Schedule")
+            .attachment("schedule.ics")
+            .respond_to(&req);
+
+        assert!(res.status().is_success());
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/calendar; charset=utf-8"
+        );
+        assert_eq!(
+            res.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+            "attachment; filename=\"schedule.ics\""
+        );
+
+        let body = to_bytes(res.into_body()).await.unwrap();
+        let body = std::str::from_utf8(&body).unwrap();
+        let lines = body.split("\r\n").collect::<Vec<_>>();
+
+        assert_eq!(
+            lines[..5],
+            [
+                "BEGIN:VCALENDAR",
+                "VERSION:2.0",
+                "PRODID:-//Actix Web//NONSGML Calendar//EN",
+                "X-WR-CALNAME:Schedule",
+                "BEGIN:VEVENT",
+            ]
+        );
+        assert_eq!(lines[5], "UID:1@example.com");
+        assert!(lines[6].starts_with("DTSTAMP:"));
+        assert_eq!(
+            lines[7..],
+            [
+                "DTSTART:20240501T090000Z",
+                "DTEND:20240501T093000Z",
+                "SUMMARY:Check-up\\; Dr. Smith\\, room 2",
+                "DESCRIPTION:Bring\\nprevious results",
+                "END:VEVENT",
+                "END:VCALENDAR",
+                "",
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn event_error() {
+        let req = TestRequest::default().to_http_request();
+
+        let events = stream::iter([Err(io::Error::other("database gone"))]);
+        let res = ICalendar::new(events).respond_to(&req);
+
+        let err = to_bytes(res.into_body()).await.unwrap_err();
+        assert_eq!(err.to_string(), "database gone");
+    }
+
+    #[test]
+    fn line_folding() {
+        let mut buf = BytesMut::new();
+        write_property(&mut buf, "SUMMARY", &"é".repeat(60));
+
+        let lines = buf[..].split(|&b| b == b'\n').collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|line| line.len() <= MAX_LINE_LEN + 1));
+        assert_eq!(lines[1][0], b' ');
+
+        let unfolded = std::str::from_utf8(&buf).unwrap().replace("\r\n ", "");
+        assert_eq!(unfolded, format!("SUMMARY:{}\r\n", "é".repeat(60)));
+    }
+}
@@ -22,6 +22,7 @@ use crate::{
     error::{Error, JsonPayloadError},
     extract::FromRequest,
     http::header::{ContentLength, Header as _},
+    middleware::MemoryBudget,
     request::HttpRequest,
     web, HttpMessage, HttpResponse, Responder,
 };
@@ -312,6 +313,7 @@ pub enum JsonBody<T> {
         #[cfg(not(feature = "__compress"))]
         payload: Payload,
         buf: BytesMut,
+        budget: Option<MemoryBudget>,
         _res: PhantomData<T>,
     },
 }
@@ -368,6 +370,7 @@ impl<T: DeserializeOwned> JsonBody<T> {
             length,
             payload,
             buf: BytesMut::with_capacity(8192),
+            budget: MemoryBudget::of(req),
             _res: PhantomData,
         }
     }
@@ -379,6 +382,7 @@ impl<T: DeserializeOwned> JsonBody<T> {
                 length,
                 payload,
                 buf,
+                budget,
                 ..
             } => {
                 if let Some(len) = length {
@@ -395,6 +399,7 @@ impl<T: DeserializeOwned> JsonBody<T> {
                     length,
                     payload,
                     buf,
+                    budget,
                     _res: PhantomData,
                 }
             }
@@ -414,6 +419,7 @@ impl<T: DeserializeOwned> Future for JsonBody<T> {
                 limit,
                 buf,
                 payload,
+                budget,
                 ..
             } => loop {
                 let res = ready!(Pin::new(&mut *payload).poll_next(cx));
@@ -423,6 +429,13 @@ impl<T: DeserializeOwned> Future for JsonBody<T> {
                         let buf_len = buf.len() + chunk.len();
                         if buf_len > *limit {
                             return Poll::Ready(Err(JsonPayloadError::Overflow { limit: *limit }));
+                        } else if let Some(Err(err)) = budget
+                            .as_ref()
+                            .map(|budget| budget.try_reserve(chunk.len()))
+                        {
+                            return Poll::Ready(Err(JsonPayloadError::Overflow {
+                                limit: buf.len() + err.remaining,
+                            }));
                         } else {
                             buf.extend_from_slice(&chunk);
                         }
@@ -1,20 +1,27 @@
 //! Common extractors and responders.
 
+pub mod adapters;
+#[cfg(feature = "csv")]
+mod csv;
 mod either;
 mod form;
 mod header;
 mod html;
+mod icalendar;
 mod json;
 mod path;
 mod payload;
 mod query;
 mod readlines;
 
+#[cfg(feature = "csv")]
+pub use self::csv::Csv;
 pub use self::{
     either::Either,
     form::{Form, FormConfig, UrlEncoded},
     header::Header,
     html::Html,
+    icalendar::{CalendarEvent, ICalendar},
     json::{Json, JsonBody, JsonConfig},
     path::{Path, PathConfig},
     payload::{Payload, PayloadConfig},
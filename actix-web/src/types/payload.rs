@@ -16,8 +16,8 @@ use futures_core::{ready, stream::Stream};
 use mime::Mime;
 
 use crate::{
-    body, dev, error::ErrorBadRequest, http::header, web, Error, FromRequest, HttpMessage,
-    HttpRequest,
+    body, dev, error::ErrorBadRequest, http::header, middleware::MemoryBudget, web, Error,
+    FromRequest, HttpMessage, HttpRequest,
 };
 
 /// Extract a request's raw payload stream.
@@ -353,6 +353,7 @@ pub struct HttpMessageBody {
     #[cfg(not(feature = "__compress"))]
     stream: dev::Payload,
     buf: BytesMut,
+    budget: Option<MemoryBudget>,
     err: Option<PayloadError>,
 }
 
@@ -393,6 +394,7 @@ impl HttpMessageBody {
             limit: DEFAULT_CONFIG_LIMIT,
             length,
             buf: BytesMut::with_capacity(8192),
+            budget: MemoryBudget::of(req),
             err,
         }
     }
@@ -426,7 +428,12 @@ impl Future for HttpMessageBody {
             match res {
                 Some(chunk) => {
                     let chunk = chunk?;
-                    if this.buf.len() + chunk.len() > this.limit {
+                    if this.buf.len() + chunk.len() > this.limit
+                        || this
+                            .budget
+                            .as_ref()
+                            .is_some_and(|budget| budget.try_reserve(chunk.len()).is_err())
+                    {
                         return Poll::Ready(Err(PayloadError::Overflow));
                     } else {
                         this.buf.extend_from_slice(&chunk);
@@ -5,6 +5,7 @@ use std::{fmt, ops, sync::Arc};
 use actix_utils::future::{ok, ready, Ready};
 use serde::de::DeserializeOwned;
 
+use super::adapters::FieldAdapters;
 use crate::{dev::Payload, error::QueryPayloadError, Error, FromRequest, HttpRequest};
 
 /// Extract typed information from the request's query.
@@ -112,11 +113,12 @@ impl<T: DeserializeOwned> FromRequest for Query<T> {
 
     #[inline]
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        let error_handler = req
-            .app_data::<QueryConfig>()
-            .and_then(|c| c.err_handler.clone());
+        let config = req.app_data::<QueryConfig>();
+        let error_handler = config.and_then(|c| c.err_handler.clone());
+        let adapters = config.map(|c| c.adapters.clone()).unwrap_or_default();
 
-        serde_urlencoded::from_str::<T>(req.query_string())
+        adapters
+            .deserialize::<T>(req.query_string().as_bytes())
             .map(|val| ok(Query(val)))
             .unwrap_or_else(move |err| {
                 let err = QueryPayloadError::Deserialize(err);
@@ -171,6 +173,7 @@ impl<T: DeserializeOwned> FromRequest for Query<T> {
 pub struct QueryConfig {
     #[allow(clippy::type_complexity)]
     err_handler: Option<Arc<dyn Fn(QueryPayloadError, &HttpRequest) -> Error + Send + Sync>>,
+    adapters: FieldAdapters,
 }
 
 impl QueryConfig {
@@ -182,6 +185,30 @@ impl QueryConfig {
         self.err_handler = Some(Arc::new(f));
         self
     }
+
+    /// Registers an adapter that rewrites the raw value of the `field` query parameter before it
+    /// is deserialized.
+    ///
+    /// Adapters return the rewritten value, or a message describing why the value is invalid,
+    /// which fails extraction. See the [`adapters`](super::adapters) module for adapters for common
+    /// formats, such as Unix timestamps.
+    pub fn adapter<F>(mut self, field: impl Into<String>, adapter: F) -> Self
+    where
+        F: Fn(&str) -> Result<String, String> + Send + Sync + 'static,
+    {
+        self.adapters.adapter(field.into(), adapter);
+        self
+    }
+
+    /// Splits the value of the `field` query parameter into a list at each `separator`, e.g., to
+    /// deserialize `?ids=1,2,3` into a `Vec<u32>`.
+    ///
+    /// Empty items are skipped, repeated parameters are joined into one list, and an
+    /// [adapter](Self::adapter) registered for the field is applied to each item.
+    pub fn list(mut self, field: impl Into<String>, separator: char) -> Self {
+        self.adapters.list(field.into(), separator);
+        self
+    }
 }
 
 #[cfg(test)]
@@ -271,4 +298,31 @@ mod tests {
             StatusCode::UNPROCESSABLE_ENTITY
         );
     }
+
+    #[actix_rt::test]
+    async fn test_adapters() {
+        #[derive(Deserialize)]
+        struct Search {
+            since: String,
+            ids: Vec<u32>,
+        }
+
+        let config = QueryConfig::default()
+            .adapter("since", crate::web::adapters::epoch_millis)
+            .list("ids", ',');
+
+        let req = TestRequest::with_uri("/?since=1000&ids=1,2,3")
+            .app_data(config.clone())
+            .to_srv_request();
+        let (req, mut pl) = req.into_parts();
+        let search = Query::<Search>::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(search.since, "1970-01-01T00:00:01Z");
+        assert_eq!(search.ids, [1, 2, 3]);
+
+        let req = TestRequest::with_uri("/?since=today&ids=1")
+            .app_data(config)
+            .to_srv_request();
+        let (req, mut pl) = req.into_parts();
+        assert!(Query::<Search>::from_request(&req, &mut pl).await.is_err());
+    }
 }
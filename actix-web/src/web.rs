@@ -16,15 +16,20 @@
 //! - [`Form`]: URL-encoded response
 //! - [`Bytes`]: Raw bytes response
 //! - [`Redirect`](Redirect::to): Convenient redirect responses
+//! - [`LongPoll`]: Long-polling responses
+//! - `Csv`: Streaming CSV response (requires the `csv` feature)
+//! - [`ICalendar`]: Streaming iCalendar response
 
 use std::{borrow::Cow, future::Future};
 
 use actix_router::IntoPatterns;
 pub use bytes::{Buf, BufMut, Bytes, BytesMut};
 
+#[cfg(feature = "process")]
+pub use crate::process::Process;
 pub use crate::{
-    config::ServiceConfig, data::Data, redirect::Redirect, request_data::ReqData,
-    thin_data::ThinData, types::*,
+    config::ServiceConfig, data::Data, long_poll::LongPoll, redirect::Redirect,
+    request_data::ReqData, thin_data::ThinData, types::*,
 };
 use crate::{
     error::BlockingError, http::Method, service::WebService, FromRequest, Handler, Resource,
@@ -3,7 +3,10 @@ extern crate tls_openssl as openssl;
 
 use std::{sync::mpsc, thread, time::Duration};
 
-use actix_web::{web, App, HttpResponse, HttpServer};
+use actix_web::{
+    dev::Request, web, App, HttpMessage as _, HttpRequest, HttpResponse, HttpServer, LoadMonitor,
+    ShutdownSequence, ShutdownStage, StageReport,
+};
 
 #[actix_rt::test]
 async fn test_start() {
@@ -74,6 +77,320 @@ async fn test_start() {
     srv.stop(false).await;
 }
 
+#[actix_rt::test]
+async fn test_on_request_head() {
+    let addr = actix_test::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        actix_rt::System::new()
+            .block_on(async {
+                let srv = HttpServer::new(|| {
+                    App::new().default_service(web::to(|req: HttpRequest| async move {
+                        let tag = req.extensions().get::<&str>().copied().unwrap_or("none");
+                        format!("{} {}", req.path(), tag)
+                    }))
+                })
+                .on_request_head(|req: &mut Request| {
+                    if req.path() == "/reject" {
+                        return Err(HttpResponse::Forbidden().body("denied"));
+                    }
+
+                    if req.path() == "/upload" {
+                        req.head_mut().uri = "/uploads/large".parse().unwrap();
+                        req.extensions_mut().insert("large");
+                    }
+
+                    Ok(())
+                })
+                .workers(1)
+                .disable_signals()
+                .bind(format!("{}", addr))
+                .unwrap()
+                .run();
+
+                tx.send(srv.handle()).unwrap();
+
+                srv.await
+            })
+            .unwrap();
+    });
+
+    let srv = rx.recv().unwrap();
+    let client = awc::Client::default();
+
+    let mut res = client
+        .post(format!("http://{}/reject", addr))
+        .send_body("payload")
+        .await
+        .unwrap();
+    assert_eq!(res.status().as_u16(), 403);
+    assert_eq!(res.body().await.unwrap(), "denied");
+
+    let mut res = client
+        .post(format!("http://{}/upload", addr))
+        .send_body("payload")
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.body().await.unwrap(), "/uploads/large large");
+
+    srv.stop(false).await;
+}
+
+#[actix_rt::test]
+async fn test_shutdown_sequence() {
+    let addr = actix_test::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let (stage_tx, stage_rx) = mpsc::channel();
+    let sequence = ShutdownSequence::new()
+        .timeout(ShutdownStage::InFlightRequests, Duration::from_secs(5))
+        .on_drain(|| async {})
+        .on_stop(|| actix_rt::time::sleep(Duration::from_secs(60)))
+        .timeout(ShutdownStage::OnStop, Duration::from_millis(10))
+        .on_stage(move |report| stage_tx.send(report.stage()).unwrap());
+
+    let server_sequence = sequence.clone();
+
+    thread::spawn(move || {
+        actix_rt::System::new()
+            .block_on(async {
+                let srv = HttpServer::new(|| {
+                    App::new().default_service(web::to(|| async {
+                        actix_rt::time::sleep(Duration::from_millis(300)).await;
+                        "done"
+                    }))
+                })
+                .shutdown_sequence(server_sequence)
+                .workers(1)
+                .disable_signals()
+                .bind(format!("{}", addr))
+                .unwrap()
+                .run();
+
+                tx.send(srv.handle()).unwrap();
+
+                srv.await
+            })
+            .unwrap();
+    });
+
+    let srv = rx.recv().unwrap();
+    let client = awc::Client::default();
+
+    let req = actix_rt::spawn(client.get(format!("http://{}/", addr)).send());
+    actix_rt::time::sleep(Duration::from_millis(100)).await;
+
+    let stop = actix_rt::spawn(srv.stop(true));
+
+    // the in-flight request completes during the shutdown
+    let mut res = req.await.unwrap().unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.body().await.unwrap(), "done");
+
+    let reports = sequence.finished().await;
+    stop.await.unwrap();
+
+    let stages = reports.iter().map(StageReport::stage).collect::<Vec<_>>();
+    assert_eq!(
+        stages,
+        [
+            ShutdownStage::StopAccepting,
+            ShutdownStage::Drain,
+            ShutdownStage::InFlightRequests,
+            ShutdownStage::CloseSessions,
+            ShutdownStage::OnStop,
+        ]
+    );
+    assert_eq!(stage_rx.try_iter().collect::<Vec<_>>(), stages);
+
+    assert!(!reports[2].timed_out());
+    assert!(reports[2].elapsed() >= Duration::from_millis(100));
+    assert_eq!(reports[4].remaining(), 1);
+}
+
+#[actix_rt::test]
+async fn test_load_monitor() {
+    let addr = actix_test::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let monitor = LoadMonitor::new();
+    let server_monitor = monitor.clone();
+
+    thread::spawn(move || {
+        actix_rt::System::new()
+            .block_on(async {
+                let app_monitor = server_monitor.clone();
+
+                let srv = HttpServer::new(move || {
+                    let monitor = app_monitor.clone();
+
+                    App::new()
+                        .route(
+                            "/load",
+                            web::get().to(move || {
+                                let report = monitor.report();
+                                async move { report }
+                            }),
+                        )
+                        .route(
+                            "/slow",
+                            web::get().to(|| async {
+                                actix_rt::time::sleep(Duration::from_millis(300)).await;
+                                "done"
+                            }),
+                        )
+                })
+                .load_monitor(server_monitor)
+                .workers(1)
+                .disable_signals()
+                .bind(format!("{}", addr))
+                .unwrap()
+                .run();
+
+                tx.send(srv.handle()).unwrap();
+
+                srv.await
+            })
+            .unwrap();
+    });
+
+    let srv = rx.recv().unwrap();
+    let client = awc::Client::default();
+
+    let req = actix_rt::spawn(client.get(format!("http://{}/slow", addr)).send());
+    actix_rt::time::sleep(Duration::from_millis(100)).await;
+
+    let report = monitor.report();
+    assert_eq!(report.in_flight(), 1);
+    assert_eq!(report.workers().len(), 1);
+    assert_eq!(report.listeners()[0].addr(), addr);
+
+    assert!(req.await.unwrap().unwrap().status().is_success());
+    assert_eq!(monitor.report().in_flight(), 0);
+
+    // the report request itself is in flight while the report is taken
+    let mut res = client
+        .get(format!("http://{}/load", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.headers().get("cache-control").unwrap(), "no-store");
+
+    let report = res.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(report["in_flight"], 1);
+    assert_eq!(report["workers"][0]["id"], 0);
+
+    srv.stop(false).await;
+}
+
+#[cfg(unix)]
+#[actix_rt::test]
+async fn test_start_uds() {
+    use actix_rt::net::UnixStream;
+    use actix_web::dev::UdsInfo;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    let path = std::env::temp_dir().join(format!("actix-web-test-{}.sock", std::process::id()));
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn({
+        let path = path.clone();
+
+        move || {
+            actix_rt::System::new()
+                .block_on(async {
+                    let srv = HttpServer::new(|| {
+                        App::new().default_service(web::to(|req: HttpRequest| async move {
+                            let pid = req.conn_data::<UdsInfo>().and_then(UdsInfo::pid);
+                            let tag = req.conn_data::<&str>().copied().unwrap_or("none");
+                            format!("{pid:?} {tag}")
+                        }))
+                    })
+                    .on_connect(|io, ext| {
+                        if io.downcast_ref::<UnixStream>().is_some() {
+                            ext.insert("uds");
+                        }
+                    })
+                    .workers(1)
+                    .disable_signals()
+                    .bind_uds(&path)
+                    .unwrap()
+                    .run();
+
+                    tx.send(srv.handle()).unwrap();
+
+                    srv.await
+                })
+                .unwrap();
+        }
+    });
+
+    let srv = rx.recv().unwrap();
+
+    let mut stream = UnixStream::connect(&path).await.unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+        .await
+        .unwrap();
+
+    let mut res = String::new();
+    stream.read_to_string(&mut res).await.unwrap();
+    assert!(res.starts_with("HTTP/1.1 200 OK"));
+    assert!(res.ends_with(&format!("Some({}) uds", std::process::id())));
+
+    srv.stop(false).await;
+    let _ = std::fs::remove_file(&path);
+}
+
+#[cfg(feature = "experimental-io-uring")]
+#[actix_rt::test]
+async fn test_start_uring() {
+    use actix_http::UringStream;
+
+    let addr = actix_test::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        actix_rt::System::new()
+            .block_on(async {
+                let srv = HttpServer::new(|| {
+                    App::new().default_service(web::to(|req: HttpRequest| async move {
+                        req.conn_data::<&str>().copied().unwrap_or("none")
+                    }))
+                })
+                .on_connect(|io, ext| {
+                    if io.downcast_ref::<UringStream>().is_some() {
+                        ext.insert("uring");
+                    }
+                })
+                .workers(1)
+                .disable_signals()
+                .bind_uring(addr)
+                .unwrap()
+                .run();
+
+                tx.send(srv.handle()).unwrap();
+
+                srv.await
+            })
+            .unwrap();
+    });
+
+    let srv = rx.recv().unwrap();
+
+    let mut res = awc::Client::new()
+        .get(format!("http://{addr}"))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.body().await.unwrap(), "uring");
+
+    srv.stop(false).await;
+}
+
 #[cfg(feature = "openssl")]
 fn ssl_acceptor() -> openssl::ssl::SslAcceptorBuilder {
     use openssl::{
@@ -1,7 +1,7 @@
 #[cfg(feature = "openssl")]
 extern crate tls_openssl as openssl;
 #[cfg(feature = "rustls-0_23")]
-extern crate tls_rustls as rustls;
+extern crate tls_rustls_0_23 as rustls;
 
 use std::{
     future::Future,
@@ -940,3 +940,38 @@ async fn test_accept_encoding_no_match() {
 
     srv.stop().await;
 }
+
+#[cfg(feature = "fault-injection")]
+#[actix_rt::test]
+async fn test_fault_injection() {
+    use std::net;
+
+    use actix_web::middleware::{Fault, FaultInjection};
+
+    let srv = actix_test::start_with(actix_test::config().h1(), || {
+        App::new()
+            .wrap(
+                FaultInjection::new()
+                    .fault(Fault::truncate_body(5).header(("x-fault", "truncate")))
+                    .fault(Fault::drop_connection().header(("x-fault", "drop"))),
+            )
+            .default_service(web::to(|| async { "hello world" }))
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\nx-fault: truncate\r\n\r\n");
+    let mut data = Vec::new();
+    let _ = stream.read_to_end(&mut data);
+    let data = String::from_utf8(data).unwrap();
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(data.contains("content-length: 11\r\n"));
+    assert!(data.ends_with("\r\n\r\nhello"));
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET / HTTP/1.1\r\nx-fault: drop\r\n\r\n");
+    let mut data = Vec::new();
+    let _ = stream.read_to_end(&mut data);
+    assert!(data.is_empty());
+
+    srv.stop().await;
+}
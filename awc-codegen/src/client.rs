@@ -0,0 +1,399 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    parse::Parser as _, FnArg, Ident, ItemTrait, LitStr, Pat, ReturnType, TraitItem, TraitItemFn,
+    Type,
+};
+
+/// Method attributes and the HTTP methods they stand for.
+const METHODS: &[(&str, &str)] = &[
+    ("get", "GET"),
+    ("post", "POST"),
+    ("put", "PUT"),
+    ("patch", "PATCH"),
+    ("delete", "DELETE"),
+    ("head", "HEAD"),
+    ("options", "OPTIONS"),
+];
+
+/// How a method argument is sent.
+enum Arg {
+    Path,
+    Query,
+    Json,
+    Form,
+    Body,
+    Header(LitStr),
+}
+
+pub(crate) fn expand(args: TokenStream, input: TokenStream) -> syn::Result<TokenStream> {
+    let mut name = None;
+
+    let args_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            let lit: LitStr = meta.value()?.parse()?;
+            name = Some(lit.parse::<Ident>()?);
+            Ok(())
+        } else {
+            Err(meta.error("unsupported argument, expected `name = \"...\"`"))
+        }
+    });
+    args_parser.parse2(args)?;
+
+    let mut item: ItemTrait = syn::parse2(input)?;
+
+    if !item.generics.params.is_empty() || item.generics.where_clause.is_some() {
+        return Err(syn::Error::new_spanned(
+            &item.generics,
+            "API client traits can not be generic",
+        ));
+    }
+
+    let mut methods = Vec::with_capacity(item.items.len());
+
+    for trait_item in &mut item.items {
+        let TraitItem::Fn(func) = trait_item else {
+            return Err(syn::Error::new_spanned(
+                trait_item,
+                "API client traits can only contain methods",
+            ));
+        };
+
+        methods.push(expand_method(func)?);
+    }
+
+    let vis = &item.vis;
+    let trait_name = &item.ident;
+    let client = name.unwrap_or_else(|| format_ident!("{}Client", trait_name));
+    let doc = format!("Client for the [`{trait_name}`] API.");
+
+    Ok(quote! {
+        // the generated futures are not `Send`, just like those of `awc`
+        #[allow(async_fn_in_trait)]
+        #item
+
+        #[doc = #doc]
+        #[derive(Clone)]
+        #vis struct #client {
+            client: ::awc::Client,
+            base_url: ::std::string::String,
+        }
+
+        impl #client {
+            /// Constructs a client that sends requests to the API at `base_url` using `client`.
+            #vis fn new(
+                client: ::awc::Client,
+                base_url: impl ::std::convert::Into<::std::string::String>,
+            ) -> Self {
+                let mut base_url = base_url.into();
+
+                // path templates start with a slash
+                while base_url.ends_with('/') {
+                    base_url.pop();
+                }
+
+                Self { client, base_url }
+            }
+        }
+
+        impl #trait_name for #client {
+            #(#methods)*
+        }
+    })
+}
+
+/// Removes the macro's attributes from `func` and returns its implementation.
+fn expand_method(func: &mut TraitItemFn) -> syn::Result<TokenStream> {
+    let sig = &func.sig;
+
+    if let Some(default) = &func.default {
+        return Err(syn::Error::new_spanned(
+            default,
+            "API client methods can not have a default implementation",
+        ));
+    }
+
+    if sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            sig.fn_token,
+            "API client methods must be `async`",
+        ));
+    }
+
+    if !sig.generics.params.is_empty() || sig.generics.where_clause.is_some() {
+        return Err(syn::Error::new_spanned(
+            &sig.generics,
+            "API client methods can not be generic",
+        ));
+    }
+
+    let by_ref = matches!(
+        sig.inputs.first(),
+        Some(FnArg::Receiver(recv)) if recv.reference.is_some() && recv.mutability.is_none()
+    );
+    if !by_ref {
+        return Err(syn::Error::new_spanned(
+            &sig.ident,
+            "API client methods must take `&self`",
+        ));
+    }
+
+    let decode = decode_fn(&sig.output)?;
+
+    let mut route = None;
+    let mut attrs = Vec::with_capacity(func.attrs.len());
+
+    for attr in func.attrs.drain(..) {
+        let Some((_, method)) = METHODS.iter().find(|(name, _)| attr.path().is_ident(name)) else {
+            attrs.push(attr);
+            continue;
+        };
+
+        if route.is_some() {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "API client methods can only have one HTTP method",
+            ));
+        }
+
+        let path: LitStr = attr.parse_args()?;
+        route = Some((Ident::new(method, Span::call_site()), path));
+    }
+
+    func.attrs = attrs;
+
+    let Some((method, path)) = route else {
+        return Err(syn::Error::new_spanned(
+            &func.sig.ident,
+            "missing HTTP method attribute, e.g. `#[get(\"/path\")]`",
+        ));
+    };
+
+    let mut path_args = Vec::new();
+    let mut query = None;
+    let mut body = None;
+    let mut headers = Vec::new();
+
+    for arg in func.sig.inputs.iter_mut().skip(1) {
+        let FnArg::Typed(arg) = arg else {
+            continue;
+        };
+
+        let Pat::Ident(pat) = &*arg.pat else {
+            return Err(syn::Error::new_spanned(
+                &arg.pat,
+                "API client method arguments must be identifiers",
+            ));
+        };
+        let ident = pat.ident.clone();
+
+        let mut kind = None;
+        let mut attrs = Vec::with_capacity(arg.attrs.len());
+
+        for attr in arg.attrs.drain(..) {
+            let path = attr.path();
+
+            let arg_kind = if path.is_ident("query") {
+                Arg::Query
+            } else if path.is_ident("json") {
+                Arg::Json
+            } else if path.is_ident("form") {
+                Arg::Form
+            } else if path.is_ident("body") {
+                Arg::Body
+            } else if path.is_ident("header") {
+                let name: LitStr = attr.parse_args()?;
+
+                if !is_header_name(&name.value()) {
+                    return Err(syn::Error::new_spanned(name, "invalid header name"));
+                }
+
+                Arg::Header(name)
+            } else {
+                attrs.push(attr);
+                continue;
+            };
+
+            if !matches!(arg_kind, Arg::Header(_)) {
+                attr.meta.require_path_only()?;
+            }
+
+            if kind.replace(arg_kind).is_some() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "arguments can only be sent in one way",
+                ));
+            }
+        }
+
+        arg.attrs = attrs;
+
+        match kind.unwrap_or(Arg::Path) {
+            Arg::Path => path_args.push(ident),
+
+            Arg::Query if query.is_some() => {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "only one argument can be sent as the query string",
+                ));
+            }
+            Arg::Query => query = Some(ident),
+
+            Arg::Header(name) => headers.push((name, ident)),
+
+            _ if body.is_some() => {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "only one argument can be sent as the request body",
+                ));
+            }
+            Arg::Json => body = Some(quote! { send_json(&#ident) }),
+            Arg::Form => body = Some(quote! { send_form(&#ident) }),
+            Arg::Body => body = Some(quote! { send_body(#ident) }),
+        }
+    }
+
+    let (format, segments) = parse_path(&path, &path_args)?;
+    let query = query.into_iter();
+    let (header_names, header_values): (Vec<_>, Vec<_>) = headers.into_iter().unzip();
+    let send = body.unwrap_or_else(|| quote! { send() });
+    let sig = &func.sig;
+
+    Ok(quote! {
+        #sig {
+            let __awc_url = ::std::format!(
+                #format,
+                self.base_url,
+                #(::awc::__private::path_segment(&#segments)),*
+            );
+
+            let __awc_req = self.client.request(::awc::http::Method::#method, __awc_url);
+            #(
+                let __awc_req = __awc_req
+                    .query(&#query)
+                    .map_err(::awc::error::ApiError::from)?;
+            )*
+            #(
+                let __awc_req = __awc_req.insert_header((#header_names, #header_values));
+            )*
+
+            let __awc_res = __awc_req.#send.await;
+
+            ::awc::__private::#decode(__awc_res)
+                .await
+                .map_err(::std::convert::From::from)
+        }
+    })
+}
+
+/// Returns the name of the helper that decodes response bodies for a method's return type.
+fn decode_fn(output: &ReturnType) -> syn::Result<Ident> {
+    let ok = match output {
+        ReturnType::Type(_, ty) => result_ok_type(ty),
+        ReturnType::Default => None,
+    };
+
+    let Some(ok) = ok else {
+        return Err(syn::Error::new_spanned(
+            output,
+            "API client methods must return a `Result`",
+        ));
+    };
+
+    let name = match ok {
+        Type::Tuple(tuple) if tuple.elems.is_empty() => "empty",
+        Type::Path(path)
+            if path
+                .path
+                .segments
+                .last()
+                .is_some_and(|seg| seg.ident == "Bytes") =>
+        {
+            "bytes"
+        }
+        _ => "json",
+    };
+
+    Ok(Ident::new(name, Span::call_site()))
+}
+
+/// Returns the success type of a `Result` type.
+fn result_ok_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+
+    let seg = path.path.segments.last()?;
+    if seg.ident != "Result" {
+        return None;
+    }
+
+    let syn::PathArguments::AngleBracketed(args) = &seg.arguments else {
+        return None;
+    };
+
+    match args.args.first()? {
+        syn::GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+/// Converts a path template into a format string, taking the base URL and then each segment,
+/// and returns the arguments filling the segments in order.
+fn parse_path(path: &LitStr, args: &[Ident]) -> syn::Result<(String, Vec<Ident>)> {
+    let template = path.value();
+
+    if !template.starts_with('/') {
+        return Err(syn::Error::new_spanned(path, "paths must start with `/`"));
+    }
+
+    let mut format = String::from("{}");
+    let mut segments = Vec::new();
+    let mut rest = template.as_str();
+
+    while let Some(start) = rest.find(['{', '}']) {
+        if rest[start..].starts_with('}') {
+            return Err(syn::Error::new_spanned(path, "unmatched `}` in path"));
+        }
+
+        let Some(len) = rest[start..].find('}') else {
+            return Err(syn::Error::new_spanned(path, "unclosed `{` in path"));
+        };
+
+        let name = &rest[start + 1..start + len];
+        let Some(arg) = args.iter().find(|arg| *arg == name) else {
+            return Err(syn::Error::new_spanned(
+                path,
+                format!("no argument named `{name}` for path segment"),
+            ));
+        };
+
+        format.push_str(&rest[..start]);
+        format.push_str("{}");
+        segments.push(arg.clone());
+
+        rest = &rest[start + len + 1..];
+    }
+
+    format.push_str(rest);
+
+    if let Some(unused) = args.iter().find(|arg| !segments.contains(arg)) {
+        return Err(syn::Error::new_spanned(
+            unused,
+            format!(
+                "argument `{unused}` is not used in the path; annotate it with `#[query]`, \
+                `#[json]`, `#[form]`, `#[body]` or `#[header(\"...\")]` to send it otherwise"
+            ),
+        ));
+    }
+
+    Ok((format, segments))
+}
+
+/// Returns true if `name` is a valid header name token.
+fn is_header_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
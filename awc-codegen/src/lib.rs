@@ -0,0 +1,101 @@
+//! API client macro for awc.
+//!
+//! # Awc Re-exports
+//! Awc re-exports this crate's macro when its `macros` feature is enabled, so you usually don't
+//! have to specify a dependency on this crate explicitly. Generated code refers to items in
+//! `awc`, which must be a dependency of the crate using the macro.
+
+#![doc(html_logo_url = "https://actix.rs/img/logo.png")]
+#![doc(html_favicon_url = "https://actix.rs/favicon.ico")]
+#![cfg_attr(docsrs, feature(doc_auto_cfg))]
+
+use proc_macro::TokenStream;
+
+mod client;
+
+/// Generates a typed client for an HTTP API from an annotated trait.
+///
+/// Each method of the trait describes one API endpoint. The macro emits the trait together with a
+/// client struct, named after the trait with a `Client` suffix, that implements it by sending
+/// requests with an `awc::Client`. A different name can be chosen with
+/// `#[api_client(name = "...")]`.
+///
+/// The client is constructed with `new(client, base_url)`, where the base URL is prepended to the
+/// path of every request.
+///
+/// # Methods
+/// Methods must be `async`, take `&self` and be annotated with the HTTP method and path template
+/// of their endpoint: `#[get("/path")]`, `#[post(..)]`, `#[put(..)]`, `#[patch(..)]`,
+/// `#[delete(..)]`, `#[head(..)]` or `#[options(..)]`.
+///
+/// Arguments without attributes fill the `{name}` segments of the path template with their
+/// [`Display`](std::fmt::Display) output, percent-encoded. Every such argument must appear in the
+/// template, and every segment must have an argument. Other arguments are annotated with how they
+/// are sent:
+///
+/// - `#[query]`: serialized as the query string with `serde_urlencoded`.
+/// - `#[json]`: serialized as a JSON request body.
+/// - `#[form]`: serialized as a URL-encoded form request body.
+/// - `#[body]`: sent as the request body as is; any `MessageBody` can be used.
+/// - `#[header("Name")]`: sent as the value of a request header.
+///
+/// At most one argument can be sent as the query string and one as the request body.
+///
+/// # Responses
+/// Methods must return a `Result<T, E>`, where `E` implements `From<awc::error::ApiError>`.
+/// Responses with a status other than success are returned as `ApiError::Status`, carrying their
+/// body. The body of successful responses is deserialized as JSON into `T`, unless `T` is `()`,
+/// in which case the body is discarded, or `Bytes`, in which case it is returned as is.
+///
+/// # Examples
+/// ```
+/// use awc::{error::ApiError, http::header::HeaderValue};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize)]
+/// struct Patient {
+///     id: u64,
+///     name: String,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Search<'a> {
+///     name: &'a str,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct NewNote<'a> {
+///     text: &'a str,
+/// }
+///
+/// #[awc_codegen::api_client] // or `#[awc::api_client]` with awc's `macros` feature
+/// trait Registry {
+///     #[get("/patients/{id}")]
+///     async fn patient(&self, id: u64) -> Result<Patient, ApiError>;
+///
+///     #[get("/patients")]
+///     async fn search(&self, #[query] search: &Search<'_>) -> Result<Vec<Patient>, ApiError>;
+///
+///     #[post("/patients/{id}/notes")]
+///     async fn add_note(
+///         &self,
+///         id: u64,
+///         #[json] note: &NewNote<'_>,
+///         #[header("X-Request-Id")] request_id: HeaderValue,
+///     ) -> Result<(), ApiError>;
+/// }
+///
+/// # async fn run() -> Result<(), ApiError> {
+/// let registry = RegistryClient::new(awc::Client::new(), "https://registry.example.com/v2");
+///
+/// let patient = registry.patient(42).await?;
+/// let found = registry.search(&Search { name: &patient.name }).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[proc_macro_attribute]
+pub fn api_client(args: TokenStream, input: TokenStream) -> TokenStream {
+    client::expand(args.into(), input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
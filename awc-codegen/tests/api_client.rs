@@ -0,0 +1,118 @@
+use actix_web::{
+    http::{header, StatusCode},
+    web::{self, Bytes},
+    App, HttpRequest, HttpResponse,
+};
+use awc::error::ApiError;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Patient {
+    id: u64,
+    name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Search {
+    name: String,
+}
+
+#[derive(Debug)]
+enum RegistryError {
+    Api(ApiError),
+}
+
+impl From<ApiError> for RegistryError {
+    fn from(err: ApiError) -> Self {
+        RegistryError::Api(err)
+    }
+}
+
+#[awc_codegen::api_client(name = "Registry")]
+trait RegistryApi {
+    #[get("/patients/{id}")]
+    async fn patient(&self, id: u64) -> Result<Patient, RegistryError>;
+
+    #[get("/patients")]
+    async fn search(&self, #[query] search: &Search) -> Result<Vec<Patient>, ApiError>;
+
+    #[put("/patients/{id}")]
+    async fn update(
+        &self,
+        id: u64,
+        #[json] patient: &Patient,
+        #[header("If-Match")] etag: &str,
+    ) -> Result<(), ApiError>;
+
+    #[get("/files/{name}")]
+    async fn file(&self, name: &str) -> Result<Bytes, ApiError>;
+}
+
+fn jane() -> Patient {
+    Patient {
+        id: 1,
+        name: "Jane".to_owned(),
+    }
+}
+
+async fn patient(id: web::Path<u64>) -> HttpResponse {
+    match id.into_inner() {
+        1 => HttpResponse::Ok().json(jane()),
+        _ => HttpResponse::NotFound().body("no such patient"),
+    }
+}
+
+async fn search(search: web::Query<Search>) -> HttpResponse {
+    let found = if search.name == "Jane" {
+        vec![jane()]
+    } else {
+        vec![]
+    };
+
+    HttpResponse::Ok().json(found)
+}
+
+async fn update(req: HttpRequest, id: web::Path<u64>, patient: web::Json<Patient>) -> HttpResponse {
+    assert_eq!(req.headers().get(header::IF_MATCH).unwrap(), "\"v1\"");
+    assert_eq!(*id, patient.id);
+    HttpResponse::NoContent().finish()
+}
+
+async fn file(req: HttpRequest) -> String {
+    req.uri().path().to_owned()
+}
+
+#[actix_rt::test]
+async fn api_client() {
+    let srv = actix_test::start(|| {
+        App::new()
+            .route("/patients", web::get().to(search))
+            .route("/patients/{id}", web::get().to(patient))
+            .route("/patients/{id}", web::put().to(update))
+            .route("/files/{name}", web::get().to(file))
+    });
+
+    // trailing slashes of the base URL are removed
+    let registry = Registry::new(awc::Client::new(), srv.url("/"));
+
+    assert_eq!(registry.patient(1).await.unwrap(), jane());
+
+    match registry.patient(2).await {
+        Err(RegistryError::Api(ApiError::Status(status, body))) => {
+            assert_eq!(status, StatusCode::NOT_FOUND);
+            assert_eq!(body, "no such patient");
+        }
+        res => panic!("unexpected result: {res:?}"),
+    }
+
+    let search = Search {
+        name: "Jane".to_owned(),
+    };
+    assert_eq!(registry.search(&search).await.unwrap(), vec![jane()]);
+
+    registry.update(1, &jane(), "\"v1\"").await.unwrap();
+
+    // path segments are percent-encoded
+    let path = registry.file("a b/c").await.unwrap();
+    assert_eq!(path, "/files/a%20b%2Fc");
+}
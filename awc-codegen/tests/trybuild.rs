@@ -0,0 +1,12 @@
+#[rustversion::stable(1.75)] // MSRV
+#[test]
+fn compile_macros() {
+    let t = trybuild::TestCases::new();
+
+    t.pass("tests/trybuild/client-ok.rs");
+    t.compile_fail("tests/trybuild/missing-method-fail.rs");
+    t.compile_fail("tests/trybuild/unknown-segment-fail.rs");
+    t.compile_fail("tests/trybuild/unused-arg-fail.rs");
+    t.compile_fail("tests/trybuild/two-bodies-fail.rs");
+    t.compile_fail("tests/trybuild/not-result-fail.rs");
+}
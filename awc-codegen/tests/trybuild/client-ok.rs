@@ -0,0 +1,43 @@
+use awc::error::ApiError;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+struct Patient {
+    name: String,
+}
+
+#[derive(Serialize)]
+struct Page {
+    offset: u32,
+}
+
+#[awc_codegen::api_client(name = "Registry")]
+pub trait RegistryApi {
+    /// Fetches a patient.
+    #[get("/patients/{id}")]
+    async fn patient(&self, id: u64) -> Result<Patient, ApiError>;
+
+    #[get("/clinics/{clinic}/patients")]
+    async fn patients(&self, clinic: &str, #[query] page: Page) -> Result<Vec<Patient>, ApiError>;
+
+    #[post("/patients/{id}/photo")]
+    async fn upload_photo(
+        &self,
+        id: u64,
+        #[body] photo: Vec<u8>,
+        #[header("Content-Type")] content_type: &str,
+    ) -> Result<(), ApiError>;
+
+    #[delete("/patients/{id}")]
+    async fn remove(&self, id: u64) -> Result<awc::body::Bytes, ApiError>;
+}
+
+async fn names(registry: &Registry) -> Result<Vec<String>, ApiError> {
+    let patients = registry.patients("north", Page { offset: 0 }).await?;
+    Ok(patients.into_iter().map(|patient| patient.name).collect())
+}
+
+fn main() {
+    let registry = Registry::new(awc::Client::new(), "http://localhost:8080/");
+    let _ = names(&registry);
+}
@@ -0,0 +1,6 @@
+#[awc_codegen::api_client]
+trait Api {
+    async fn ping(&self) -> Result<(), awc::error::ApiError>;
+}
+
+fn main() {}
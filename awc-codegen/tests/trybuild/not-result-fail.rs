@@ -0,0 +1,7 @@
+#[awc_codegen::api_client]
+trait Api {
+    #[get("/ping")]
+    async fn ping(&self) -> u16;
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[awc_codegen::api_client]
+trait Api {
+    #[post("/notes")]
+    async fn add(&self, #[json] a: String, #[form] b: String) -> Result<(), awc::error::ApiError>;
+}
+
+fn main() {}
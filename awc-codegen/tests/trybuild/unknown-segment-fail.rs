@@ -0,0 +1,7 @@
+#[awc_codegen::api_client]
+trait Api {
+    #[get("/patients/{id}")]
+    async fn patient(&self, patient_id: u64) -> Result<(), awc::error::ApiError>;
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[awc_codegen::api_client]
+trait Api {
+    #[get("/patients")]
+    async fn patients(&self, clinic: u64) -> Result<(), awc::error::ApiError>;
+}
+
+fn main() {}
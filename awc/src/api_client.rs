@@ -0,0 +1,83 @@
+//! Helpers used by code generated by the `api_client` macro.
+
+use std::fmt;
+
+use bytes::Bytes;
+use futures_core::Stream;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    error::{ApiError, PayloadError, SendRequestError},
+    ClientResponse,
+};
+
+/// Characters that are left unencoded in path segments.
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Formats `value` as a single percent-encoded path segment.
+pub fn path_segment(value: &impl fmt::Display) -> String {
+    utf8_percent_encode(&value.to_string(), SEGMENT).to_string()
+}
+
+/// Deserializes the JSON body of a successful response.
+pub async fn json<S, T>(res: Result<ClientResponse<S>, SendRequestError>) -> Result<T, ApiError>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>>,
+    T: DeserializeOwned,
+{
+    let mut res = success(res).await?;
+    Ok(res.json().await?)
+}
+
+/// Returns the body of a successful response.
+pub async fn bytes<S>(res: Result<ClientResponse<S>, SendRequestError>) -> Result<Bytes, ApiError>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>>,
+{
+    let mut res = success(res).await?;
+    Ok(res.body().await?)
+}
+
+/// Checks that a response is successful, discarding its body.
+pub async fn empty<S>(res: Result<ClientResponse<S>, SendRequestError>) -> Result<(), ApiError>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>>,
+{
+    success(res).await.map(|_| ())
+}
+
+/// Returns the response if its status is successful, or its status and body otherwise.
+async fn success<S>(
+    res: Result<ClientResponse<S>, SendRequestError>,
+) -> Result<ClientResponse<S>, ApiError>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>>,
+{
+    let mut res = res?;
+
+    if res.status().is_success() {
+        return Ok(res);
+    }
+
+    // the body is only informational, so reading it is allowed to fail
+    let body = res.body().await.unwrap_or_default();
+    Err(ApiError::Status(res.status(), body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_segments() {
+        assert_eq!(path_segment(&42), "42");
+        assert_eq!(path_segment(&"a-b_c.d~e"), "a-b_c.d~e");
+        assert_eq!(path_segment(&"a/b c?d"), "a%2Fb%20c%3Fd");
+        assert_eq!(path_segment(&"é"), "%C3%A9");
+    }
+}
@@ -1,9 +1,9 @@
-use std::{fmt, net::IpAddr, rc::Rc, time::Duration};
+use std::{fmt, future::Future, mem, net::IpAddr, rc::Rc, time::Duration};
 
 use actix_http::{
     error::HttpError,
     header::{self, HeaderMap, HeaderName, TryIntoHeaderPair},
-    Uri,
+    ClientDisconnect, Uri,
 };
 use actix_rt::net::{ActixStream, TcpStream};
 use actix_service::{boxed, Service};
@@ -11,20 +11,26 @@ use base64::prelude::*;
 
 use crate::{
     client::{
-        ClientConfig, ConnectInfo, Connector, ConnectorService, TcpConnectError, TcpConnection,
+        ClientConfig, ClientIdentity, ConnectInfo, Connector, ConnectorService, EgressPolicy,
+        Proxy, TcpConnectError, TcpConnection,
     },
     connect::DefaultConnector,
     error::SendRequestError,
-    middleware::{NestTransform, Redirect, Transform},
-    Client, ConnectRequest, ConnectResponse,
+    middleware::{DigestAuth, NestTransform, Redirect, Transform, WrapFn},
+    timings::TraceConfig,
+    BoxConnectorService, Client, ConnectRequest, ConnectResponse,
 };
 
+#[cfg(feature = "cookies")]
+use crate::middleware::CookieStore;
+
 /// An HTTP Client builder
 ///
 /// This type can be used to construct an instance of `Client` through a
 /// builder-like pattern.
 pub struct ClientBuilder<S = (), M = ()> {
     max_http_version: Option<http::Version>,
+    http2_prior_knowledge: bool,
     stream_window_size: Option<u32>,
     conn_window_size: Option<u32>,
     fundamental_headers: bool,
@@ -33,7 +39,12 @@ pub struct ClientBuilder<S = (), M = ()> {
     connector: Connector<S>,
     middleware: M,
     local_address: Option<IpAddr>,
-    max_redirects: u8,
+    proxy: Option<Proxy>,
+    egress_policy: Option<EgressPolicy>,
+    redirect: Redirect,
+    digest_auth: Option<DigestAuth>,
+    max_response_body_size: Option<usize>,
+    trace: TraceConfig,
 }
 
 impl ClientBuilder {
@@ -54,6 +65,7 @@ impl ClientBuilder {
     > {
         ClientBuilder {
             max_http_version: None,
+            http2_prior_knowledge: false,
             stream_window_size: None,
             conn_window_size: None,
             fundamental_headers: true,
@@ -62,7 +74,12 @@ impl ClientBuilder {
             connector: Connector::new(),
             middleware: (),
             local_address: None,
-            max_redirects: 10,
+            proxy: None,
+            egress_policy: None,
+            redirect: Redirect::new(),
+            digest_auth: None,
+            max_response_body_size: None,
+            trace: TraceConfig::default(),
         }
     }
 }
@@ -88,11 +105,17 @@ where
             default_headers: self.default_headers,
             timeout: self.timeout,
             local_address: self.local_address,
+            proxy: self.proxy,
+            egress_policy: self.egress_policy,
             connector,
             max_http_version: self.max_http_version,
+            http2_prior_knowledge: self.http2_prior_knowledge,
             stream_window_size: self.stream_window_size,
             conn_window_size: self.conn_window_size,
-            max_redirects: self.max_redirects,
+            redirect: self.redirect,
+            digest_auth: self.digest_auth,
+            max_response_body_size: self.max_response_body_size,
+            trace: self.trace,
         }
     }
 
@@ -111,12 +134,63 @@ where
         self
     }
 
+    /// Sets the maximum size of response bodies, in bytes.
+    ///
+    /// Response payloads fail with [`PayloadError::Overflow`] once more bytes than the limit have
+    /// been read from them, whether they are read with [`ClientResponse::body()`],
+    /// [`ClientResponse::json()`], or as a stream. The limit applies to decompressed bodies.
+    /// Unlike the limits of `body()` and `json()`, it can not be raised for a single response.
+    ///
+    /// By default, there is no limit.
+    ///
+    /// [`PayloadError::Overflow`]: crate::error::PayloadError::Overflow
+    /// [`ClientResponse::body()`]: crate::ClientResponse::body
+    /// [`ClientResponse::json()`]: crate::ClientResponse::json
+    pub fn max_response_body_size(mut self, limit: usize) -> Self {
+        self.max_response_body_size = Some(limit);
+        self
+    }
+
     /// Set local IP Address the connector would use for establishing connection.
     pub fn local_address(mut self, addr: IpAddr) -> Self {
         self.local_address = Some(addr);
         self
     }
 
+    /// Sends requests through an HTTP proxy.
+    ///
+    /// Plain `http` requests are sent to the proxy, which forwards them to their target.
+    /// Connections to `https` targets, and WebSocket connections, are tunneled through the proxy
+    /// with `CONNECT` requests, so TLS is still negotiated with the target. Hosts can be excluded
+    /// from being proxied with [`Proxy::no_proxy()`]. See [`Proxy`] for details.
+    ///
+    /// Replaces a proxy set on the [connector](Self::connector).
+    ///
+    /// # Examples
+    /// ```
+    /// use awc::{http::Uri, Client};
+    ///
+    /// let client = Client::builder()
+    ///     .proxy(Uri::from_static("http://proxy.internal:3128"))
+    ///     .finish();
+    /// ```
+    pub fn proxy(mut self, proxy: impl Into<Proxy>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    /// Restricts the destinations that requests can be sent to.
+    ///
+    /// Requests to destinations that the policy does not allow, including redirects to them, fail
+    /// with [`ConnectError::Denied`](crate::error::ConnectError::Denied). See [`EgressPolicy`]
+    /// for details.
+    ///
+    /// Replaces a policy set on the [connector](Self::connector).
+    pub fn egress_policy(mut self, policy: EgressPolicy) -> Self {
+        self.egress_policy = Some(policy);
+        self
+    }
+
     /// Maximum supported HTTP major version.
     ///
     /// Supported versions are HTTP/1.1 and HTTP/2.
@@ -125,11 +199,22 @@ where
         self
     }
 
+    /// Sends requests to `http` URLs over HTTP/2 with prior knowledge.
+    ///
+    /// Cleartext connections start speaking HTTP/2 right away, without an upgrade from HTTP/1.1,
+    /// which servers such as gRPC services expect. Requests whose version is set to HTTP/1.x
+    /// with [`ClientRequest::version()`](crate::ClientRequest::version) still use HTTP/1.1.
+    /// Requests to `https` URLs are not affected; their protocol is negotiated with ALPN.
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.http2_prior_knowledge = true;
+        self
+    }
+
     /// Do not follow redirects.
     ///
     /// Redirects are allowed by default.
     pub fn disable_redirects(mut self) -> Self {
-        self.max_redirects = 0;
+        self.redirect = self.redirect.max_redirect_times(0);
         self
     }
 
@@ -137,7 +222,40 @@ where
     ///
     /// Max redirects is set to 10 by default.
     pub fn max_redirects(mut self, num: u8) -> Self {
-        self.max_redirects = num;
+        self.redirect = self.redirect.max_redirect_times(num);
+        self
+    }
+
+    /// Set policy for following redirects.
+    ///
+    /// Replaces the policy, including the max number of redirects, set by earlier calls. See
+    /// [`Redirect`] for the available options.
+    pub fn redirect_policy(mut self, redirect: Redirect) -> Self {
+        self.redirect = redirect;
+        self
+    }
+
+    /// Sets the headers whose values are redacted in the `awc.request` tracing spans.
+    ///
+    /// Replaces the default list, which is `Authorization`, `Proxy-Authorization`, `Cookie`, and
+    /// `Set-Cookie`. Redacted headers are recorded with a `[redacted]` placeholder value.
+    ///
+    /// # Examples
+    /// ```
+    /// use awc::{http::header, Client};
+    ///
+    /// let client = Client::builder()
+    ///     .trace_redacted_headers([
+    ///         header::AUTHORIZATION,
+    ///         header::COOKIE,
+    ///         header::SET_COOKIE,
+    ///         header::HeaderName::from_static("x-api-key"),
+    ///     ])
+    ///     .finish();
+    /// ```
+    #[cfg(feature = "tracing")]
+    pub fn trace_redacted_headers(mut self, names: impl IntoIterator<Item = HeaderName>) -> Self {
+        self.trace.redacted_headers(names);
         self
     }
 
@@ -226,6 +344,20 @@ where
         self.add_default_header((header::AUTHORIZATION, format!("Bearer {}", token)))
     }
 
+    /// Set client wide HTTP digest authentication credentials.
+    ///
+    /// Requests are sent again with an `Authorization` header when the server challenges them. The
+    /// challenges of each origin are remembered, so that later requests to it are authenticated
+    /// without a round trip. Challenges of hosts that requests are redirected to are not answered.
+    /// See [`DigestAuth`] for details.
+    pub fn digest_auth<N>(mut self, username: N, password: Option<&str>) -> Self
+    where
+        N: fmt::Display,
+    {
+        self.digest_auth = Some(DigestAuth::new(username, password.unwrap_or_default()));
+        self
+    }
+
     /// Registers middleware, in the form of a middleware component (type), that runs during inbound
     /// and/or outbound processing in the request life-cycle (request -> response),
     /// modifying request/response as necessary, across all requests managed by the `Client`.
@@ -238,27 +370,78 @@ where
             middleware: NestTransform::new(self.middleware, mw),
             fundamental_headers: self.fundamental_headers,
             max_http_version: self.max_http_version,
+            http2_prior_knowledge: self.http2_prior_knowledge,
             stream_window_size: self.stream_window_size,
             conn_window_size: self.conn_window_size,
             default_headers: self.default_headers,
             timeout: self.timeout,
             connector: self.connector,
             local_address: self.local_address,
-            max_redirects: self.max_redirects,
+            proxy: self.proxy,
+            egress_policy: self.egress_policy,
+            redirect: self.redirect,
+            digest_auth: self.digest_auth,
+            max_response_body_size: self.max_response_body_size,
+            trace: self.trace,
         }
     }
 
+    /// Registers middleware, in the form of an async function, that runs across all requests
+    /// managed by the `Client`.
+    ///
+    /// The function is called with each request and the service it wraps, which it can call to
+    /// send the request on, change the request before sending it or the response after receiving
+    /// it, or send it more than once. Middleware registered later wraps middleware registered
+    /// earlier, so runs first for requests.
+    ///
+    /// The wrapped service is type-erased so that the function's argument types are known;
+    /// [`wrap()`](Self::wrap) avoids this indirection.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_service::Service as _;
+    /// use awc::{http::header, ConnectRequest};
+    ///
+    /// let client = awc::ClientBuilder::new()
+    ///     .wrap_fn(|mut req, srv| {
+    ///         if let ConnectRequest::Client(head, ..) = &mut req {
+    ///             log::debug!("sending {} {}", head.as_ref().method, head.as_ref().uri);
+    ///         }
+    ///
+    ///         let fut = srv.call(req);
+    ///
+    ///         async move {
+    ///             let res = fut.await;
+    ///             log::debug!("received response: {}", res.is_ok());
+    ///             res
+    ///         }
+    ///     })
+    ///     .finish();
+    /// ```
+    pub fn wrap_fn<S1, F, Fut>(
+        self,
+        f: F,
+    ) -> ClientBuilder<S, NestTransform<M, WrapFn<F>, S1, ConnectRequest>>
+    where
+        M: Transform<S1, ConnectRequest>,
+        M::Transform:
+            Service<ConnectRequest, Response = ConnectResponse, Error = SendRequestError> + 'static,
+        <M::Transform as Service<ConnectRequest>>::Future: 'static,
+        F: Fn(ConnectRequest, &BoxConnectorService) -> Fut,
+        Fut: Future<Output = Result<ConnectResponse, SendRequestError>>,
+    {
+        self.wrap(WrapFn::new(f))
+    }
+
     /// Finish build process and create `Client` instance.
-    pub fn finish(self) -> Client
+    pub fn finish(mut self) -> Client
     where
         M: Transform<DefaultConnector<ConnectorService<S, Io>>, ConnectRequest> + 'static,
         M::Transform: Service<ConnectRequest, Response = ConnectResponse, Error = SendRequestError>,
     {
-        let max_redirects = self.max_redirects;
-
-        if max_redirects > 0 {
-            self.wrap(Redirect::new().max_redirect_times(max_redirects))
-                ._finish()
+        if self.redirect.max_times() > 0 {
+            let redirect = mem::take(&mut self.redirect);
+            self.wrap(redirect)._finish()
         } else {
             self._finish()
         }
@@ -283,18 +466,226 @@ where
         if let Some(val) = self.local_address {
             connector = connector.local_address(val);
         }
+        if let Some(ref proxy) = self.proxy {
+            connector = connector.with_proxy(proxy.clone());
+        }
+        if let Some(policy) = self.egress_policy {
+            connector = connector.egress_policy(policy);
+        }
+
+        let connector = connector.finish();
+        let pool = Rc::clone(connector.pool_monitor());
+
+        let egress = connector.egress_policy().cloned();
+
+        let connector = DefaultConnector::new(connector)
+            .proxy(self.proxy)
+            .egress_policy(egress)
+            .trace(self.trace);
+        let mut connector = boxed::rc_service(self.middleware.new_transform(connector));
 
-        let connector = DefaultConnector::new(connector.finish());
-        let connector = boxed::rc_service(self.middleware.new_transform(connector));
+        // answers challenges outside of redirects, so that only the requested origins are answered
+        if let Some(auth) = self.digest_auth {
+            connector = boxed::rc_service(auth.new_transform(connector));
+        }
 
         Client(ClientConfig {
             default_headers: Rc::new(self.default_headers),
             timeout: self.timeout,
+            http2_prior_knowledge: self.http2_prior_knowledge,
             connector,
+            pool,
+            identity: None,
+            server_name: None,
+            cancel_on: None,
+            max_response_body_size: self.max_response_body_size,
         })
     }
 }
 
+/// A builder for a child client, which shares the connector of the client it is created from.
+///
+/// See [`Client::child()`].
+pub struct ChildClientBuilder {
+    parent: ClientConfig,
+    inherited_headers: HeaderMap,
+    default_headers: HeaderMap,
+    #[cfg(feature = "cookies")]
+    cookie_store: Option<CookieStore>,
+    digest_auth: Option<DigestAuth>,
+}
+
+impl ChildClientBuilder {
+    pub(crate) fn new(parent: ClientConfig) -> Self {
+        Self {
+            inherited_headers: (*parent.default_headers).clone(),
+            default_headers: HeaderMap::new(),
+            parent,
+            #[cfg(feature = "cookies")]
+            cookie_store: None,
+            digest_auth: None,
+        }
+    }
+
+    /// Set request timeout.
+    ///
+    /// Defaults to the timeout of the parent client.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.parent.timeout = Some(timeout);
+        self
+    }
+
+    /// Disable request timeout.
+    pub fn disable_timeout(mut self) -> Self {
+        self.parent.timeout = None;
+        self
+    }
+
+    /// Sets the maximum size of response bodies, in bytes.
+    ///
+    /// Defaults to the limit of the parent client. See [`ClientBuilder::max_response_body_size()`]
+    /// for details.
+    pub fn max_response_body_size(mut self, limit: usize) -> Self {
+        self.parent.max_response_body_size = Some(limit);
+        self
+    }
+
+    /// Presents a client identity on the TLS connections of the child client's requests.
+    ///
+    /// Overrides the identities of the shared connector. See [`ClientIdentity`] for details.
+    pub fn client_identity(mut self, identity: ClientIdentity) -> Self {
+        self.parent.identity = Some(identity);
+        self
+    }
+
+    /// Cancels the child client's requests once `disconnect` is notified.
+    ///
+    /// Ties the outbound requests made while handling an inbound request to the lifetime of its
+    /// connection, so that requests the inbound client will never see the result of stop
+    /// consuming upstream resources. See [`ClientRequest::cancel_on()`] for details.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::{http::ClientDisconnect, web, HttpResponse, Responder};
+    ///
+    /// async fn search(
+    ///     client: web::Data<awc::Client>,
+    ///     disconnect: ClientDisconnect,
+    /// ) -> impl Responder {
+    ///     let client = client.child().cancel_on(disconnect).finish();
+    ///
+    ///     // stops waiting for the index once the inbound client is gone
+    ///     match client.get("http://index.internal/search").send().await {
+    ///         Ok(res) => HttpResponse::build(res.status()).streaming(res),
+    ///         Err(_) => HttpResponse::BadGateway().finish(),
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// [`ClientRequest::cancel_on()`]: crate::ClientRequest::cancel_on
+    pub fn cancel_on(mut self, disconnect: ClientDisconnect) -> Self {
+        self.parent.cancel_on = Some(disconnect);
+        self
+    }
+
+    /// Do not inherit the default headers of the parent client.
+    pub fn no_default_headers(mut self) -> Self {
+        self.inherited_headers.clear();
+        self
+    }
+
+    /// Add default header.
+    ///
+    /// Headers added by this method get added to every request of the child client unless
+    /// overridden by other methods, and replace default headers with the same name that are
+    /// inherited from the parent client.
+    ///
+    /// # Panics
+    /// Panics if header name or value is invalid.
+    pub fn add_default_header(mut self, header: impl TryIntoHeaderPair) -> Self {
+        match header.try_into_pair() {
+            Ok((key, value)) => self.default_headers.append(key, value),
+            Err(err) => panic!("Header error: {:?}", err.into()),
+        }
+
+        self
+    }
+
+    /// Set HTTP basic authorization header of the child client.
+    pub fn basic_auth<N>(mut self, username: N, password: Option<&str>) -> Self
+    where
+        N: fmt::Display,
+    {
+        let auth = match password {
+            Some(password) => format!("{}:{}", username, password),
+            None => format!("{}:", username),
+        };
+
+        self.default_headers.remove(header::AUTHORIZATION);
+        self.add_default_header((
+            header::AUTHORIZATION,
+            format!("Basic {}", BASE64_STANDARD.encode(auth)),
+        ))
+    }
+
+    /// Set HTTP bearer authentication header of the child client.
+    pub fn bearer_auth<T>(mut self, token: T) -> Self
+    where
+        T: fmt::Display,
+    {
+        self.default_headers.remove(header::AUTHORIZATION);
+        self.add_default_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+    }
+
+    /// Set HTTP digest authentication credentials of the child client.
+    ///
+    /// Overrides the digest authentication of the parent client. The challenges answered by the
+    /// child client are only remembered by it. See [`ClientBuilder::digest_auth()`] for details.
+    pub fn digest_auth<N>(mut self, username: N, password: Option<&str>) -> Self
+    where
+        N: fmt::Display,
+    {
+        self.digest_auth = Some(DigestAuth::new(username, password.unwrap_or_default()));
+        self
+    }
+
+    /// Stores the cookies set by responses to the child client's requests in `store`, and sends
+    /// them with its later requests.
+    ///
+    /// The store is only used by the child client, and by other clients it is given to. Cookies
+    /// set by responses to redirects that are followed by the parent client are not stored.
+    #[cfg(feature = "cookies")]
+    pub fn cookie_store(mut self, store: CookieStore) -> Self {
+        self.cookie_store = Some(store);
+        self
+    }
+
+    /// Finish build process and create the child `Client`.
+    pub fn finish(self) -> Client {
+        let mut config = self.parent;
+
+        let mut default_headers = self.inherited_headers;
+        for name in self.default_headers.keys() {
+            default_headers.remove(name);
+        }
+        for (name, value) in self.default_headers.iter() {
+            default_headers.append(name.clone(), value.clone());
+        }
+        config.default_headers = Rc::new(default_headers);
+
+        #[cfg(feature = "cookies")]
+        if let Some(store) = self.cookie_store {
+            config.connector = boxed::rc_service(store.new_transform(config.connector));
+        }
+
+        if let Some(auth) = self.digest_auth {
+            config.connector = boxed::rc_service(auth.new_transform(config.connector));
+        }
+
+        Client(config)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -337,4 +728,36 @@ mod tests {
             "Bearer someS3cr3tAutht0k3n"
         );
     }
+
+    #[test]
+    fn child_client_headers() {
+        let parent = ClientBuilder::new()
+            .add_default_header(("x-app", "scheduler"))
+            .bearer_auth("parent")
+            .finish();
+
+        let child = parent
+            .child()
+            .add_default_header(("x-tenant", "a"))
+            .bearer_auth("tenant-a")
+            .finish();
+
+        let headers = &child.0.default_headers;
+        assert_eq!(headers.get("x-app").unwrap(), "scheduler");
+        assert_eq!(headers.get("x-tenant").unwrap(), "a");
+        assert_eq!(
+            headers.get_all(header::AUTHORIZATION).collect::<Vec<_>>(),
+            ["Bearer tenant-a"]
+        );
+
+        // the parent is not affected
+        assert!(parent.0.default_headers.get("x-tenant").is_none());
+        assert_eq!(
+            parent.0.default_headers.get(header::AUTHORIZATION).unwrap(),
+            "Bearer parent"
+        );
+
+        let child = parent.child().no_default_headers().finish();
+        assert!(child.0.default_headers.is_empty());
+    }
 }
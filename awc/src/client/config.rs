@@ -1,4 +1,15 @@
-use std::{net::IpAddr, time::Duration};
+use std::{net::IpAddr, rc::Rc, sync::Arc, time::Duration};
+
+use actix_tls::connect::Resolve;
+use tokio::sync::Semaphore;
+
+use super::{
+    identity::ClientIdentity,
+    pinning::CertificatePins,
+    pool_monitor::{PoolEventCallback, PoolMonitor},
+    proxy::HostRule,
+    EgressPolicy, Proxy,
+};
 
 const DEFAULT_H2_CONN_WINDOW: u32 = 1024 * 1024 * 2; // 2MB
 const DEFAULT_H2_STREAM_WINDOW: u32 = 1024 * 1024; // 1MB
@@ -7,28 +18,55 @@ const DEFAULT_H2_STREAM_WINDOW: u32 = 1024 * 1024; // 1MB
 #[derive(Clone)]
 pub(crate) struct ConnectorConfig {
     pub(crate) timeout: Duration,
+    pub(crate) connection_attempt_delay: Duration,
     pub(crate) handshake_timeout: Duration,
     pub(crate) conn_lifetime: Duration,
     pub(crate) conn_keep_alive: Duration,
     pub(crate) disconnect_timeout: Option<Duration>,
     pub(crate) limit: usize,
+    pub(crate) limit_per_host: Option<usize>,
+    pub(crate) total_limit: Option<usize>,
+    pub(crate) total_permits: Option<Arc<Semaphore>>,
+    pub(crate) pool_timeout: Option<Duration>,
     pub(crate) conn_window_size: u32,
     pub(crate) stream_window_size: u32,
     pub(crate) local_address: Option<IpAddr>,
+    pub(crate) proxy: Option<Proxy>,
+    pub(crate) resolver: Option<Rc<dyn Resolve>>,
+    pub(crate) egress: Option<Rc<EgressPolicy>>,
+    pub(crate) identities: Vec<(HostRule, ClientIdentity)>,
+    pub(crate) pins: Vec<(HostRule, CertificatePins)>,
+    #[allow(dead_code)] // only dead when the tls-key-log feature is disabled
+    pub(crate) tls_key_log: bool,
+    pub(crate) on_pool_event: Option<Rc<PoolEventCallback>>,
+    pub(crate) pool_monitor: Rc<PoolMonitor>,
 }
 
 impl Default for ConnectorConfig {
     fn default() -> Self {
         Self {
             timeout: Duration::from_secs(5),
+            connection_attempt_delay: Duration::from_millis(250),
             handshake_timeout: Duration::from_secs(5),
             conn_lifetime: Duration::from_secs(75),
             conn_keep_alive: Duration::from_secs(15),
             disconnect_timeout: Some(Duration::from_millis(3000)),
             limit: 100,
+            limit_per_host: None,
+            total_limit: None,
+            total_permits: None,
+            pool_timeout: None,
             conn_window_size: DEFAULT_H2_CONN_WINDOW,
             stream_window_size: DEFAULT_H2_STREAM_WINDOW,
             local_address: None,
+            proxy: None,
+            resolver: None,
+            egress: None,
+            identities: Vec::new(),
+            pins: Vec::new(),
+            tls_key_log: cfg!(debug_assertions),
+            on_pool_event: None,
+            pool_monitor: Rc::default(),
         }
     }
 }
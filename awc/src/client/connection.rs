@@ -257,11 +257,11 @@ where
                 Connection::Tls(ConnectionType::H1(conn)) => {
                     h1proto::send_request(conn, head.into(), body).await
                 }
-                Connection::Tls(ConnectionType::H2(conn)) => {
+                Connection::Tcp(ConnectionType::H2(conn)) => {
                     h2proto::send_request(conn, head.into(), body).await
                 }
-                _ => {
-                    unreachable!("Plain TCP connection can be used only with HTTP/1.1 protocol")
+                Connection::Tls(ConnectionType::H2(conn)) => {
+                    h2proto::send_request(conn, head.into(), body).await
                 }
             }
         })
@@ -285,12 +285,13 @@ where
                     let (head, framed) = h1proto::open_tunnel(self, head.into()).await?;
                     Ok((head, framed))
                 }
-                Connection::Tls(ConnectionType::H2(mut conn)) => {
+                Connection::Tcp(ConnectionType::H2(mut conn)) => {
                     conn.release();
                     Err(SendRequestError::TunnelNotSupported)
                 }
-                Connection::Tcp(ConnectionType::H2(_)) => {
-                    unreachable!("Plain Tcp connection can be used only in Http1 protocol")
+                Connection::Tls(ConnectionType::H2(mut conn)) => {
+                    conn.release();
+                    Err(SendRequestError::TunnelNotSupported)
                 }
             }
         })
@@ -1,34 +1,43 @@
 use std::{
     fmt,
-    future::Future,
-    net::IpAddr,
-    pin::Pin,
+    future::{poll_fn, Future},
+    io,
+    net::{IpAddr, SocketAddr},
+    pin::{pin, Pin},
     rc::Rc,
+    sync::Arc,
     task::{Context, Poll},
     time::Duration,
 };
 
-use actix_http::Protocol;
+use actix_http::{header::HeaderValue, Protocol};
 use actix_rt::{
     net::{ActixStream, TcpStream},
-    time::{sleep, Sleep},
+    time::{sleep, Instant, Sleep},
 };
 use actix_service::Service;
 use actix_tls::connect::{
     ConnectError as TcpConnectError, ConnectInfo, Connection as TcpConnection,
-    Connector as TcpConnector, Resolver,
+    Connector as TcpConnector, Resolve, Resolver,
 };
 use futures_core::{future::LocalBoxFuture, ready};
 use http::Uri;
 use pin_project_lite::pin_project;
+use tokio::sync::Semaphore;
 
 use super::{
     config::ConnectorConfig,
     connection::{Connection, ConnectionIo},
+    egress::{host_rules, EgressPolicy},
     error::ConnectError,
+    identity::ClientIdentity,
+    pinning::CertificatePins,
     pool::ConnectionPool,
+    pool_monitor::{PoolEvent, PoolMonitor},
+    proxy::{HostRule, Proxy},
     Connect,
 };
+use crate::timings::{Phase, PhaseTimer};
 
 enum OurTlsConnector {
     #[allow(dead_code)] // only dead when no TLS feature is enabled
@@ -63,6 +72,79 @@ enum OurTlsConnector {
     Rustls023(std::sync::Arc<actix_tls::connect::rustls_0_23::reexports::ClientConfig>),
 }
 
+#[cfg(feature = "tls-key-log")]
+impl OurTlsConnector {
+    /// Makes connections write their TLS session secrets to the file named by `SSLKEYLOGFILE`.
+    #[allow(clippy::match_single_binding)] // only a single binding when no TLS feature is enabled
+    fn with_key_log(self) -> Self {
+        match self {
+            #[cfg(feature = "openssl")]
+            OurTlsConnector::OpensslBuilder(mut builder) => {
+                openssl_key_log(&mut builder);
+                OurTlsConnector::OpensslBuilder(builder)
+            }
+
+            #[cfg(feature = "rustls-0_20")]
+            OurTlsConnector::Rustls020(config) => {
+                let mut config = (*config).clone();
+                config.key_log = std::sync::Arc::new(tls_rustls_0_20::KeyLogFile::new());
+                OurTlsConnector::Rustls020(std::sync::Arc::new(config))
+            }
+
+            #[cfg(feature = "rustls-0_21")]
+            OurTlsConnector::Rustls021(config) => {
+                let mut config = (*config).clone();
+                config.key_log = std::sync::Arc::new(tls_rustls_0_21::KeyLogFile::new());
+                OurTlsConnector::Rustls021(std::sync::Arc::new(config))
+            }
+
+            #[cfg(any(
+                feature = "rustls-0_22-webpki-roots",
+                feature = "rustls-0_22-native-roots",
+            ))]
+            OurTlsConnector::Rustls022(config) => {
+                let mut config = (*config).clone();
+                config.key_log = std::sync::Arc::new(tls_rustls_0_22::KeyLogFile::new());
+                OurTlsConnector::Rustls022(std::sync::Arc::new(config))
+            }
+
+            #[cfg(feature = "rustls-0_23")]
+            OurTlsConnector::Rustls023(config) => {
+                let mut config = (*config).clone();
+                config.key_log = std::sync::Arc::new(tls_rustls_0_23::KeyLogFile::new());
+                OurTlsConnector::Rustls023(std::sync::Arc::new(config))
+            }
+
+            // built OpenSSL connectors can not be changed
+            tls => tls,
+        }
+    }
+}
+
+/// Appends the TLS session secrets of connections made with `builder` to the file named by the
+/// `SSLKEYLOGFILE` environment variable, if it is set.
+#[cfg(all(feature = "openssl", feature = "tls-key-log"))]
+fn openssl_key_log(builder: &mut actix_tls::connect::openssl::reexports::SslConnectorBuilder) {
+    use std::{fs::OpenOptions, io::Write as _, sync::Mutex};
+
+    let Some(path) = std::env::var_os("SSLKEYLOGFILE") else {
+        return;
+    };
+
+    let file = match OpenOptions::new().create(true).append(true).open(&path) {
+        Ok(file) => Mutex::new(file),
+        Err(err) => {
+            log::warn!("Can not open TLS key log file {path:?}: {err}");
+            return;
+        }
+    };
+
+    builder.set_keylog_callback(move |_, line| {
+        // key logging is a debugging aid, so failing to write must not fail the handshake
+        let _ = writeln!(file.lock().unwrap(), "{line}");
+    });
+}
+
 /// Manages HTTP client network connectivity.
 ///
 /// The `Connector` type uses a builder-like combinator pattern for service construction that
@@ -100,9 +182,14 @@ impl Connector<()> {
                 Error = actix_tls::connect::ConnectError,
             > + Clone,
     > {
+        let resolver = resolver::resolver();
+
         Connector {
-            connector: TcpConnector::new(resolver::resolver()).service(),
-            config: ConnectorConfig::default(),
+            connector: resolver::connector(&resolver),
+            config: ConnectorConfig {
+                resolver: Some(resolver),
+                ..ConnectorConfig::default()
+            },
             tls: Self::build_tls(vec![b"h2".to_vec(), b"http/1.1".to_vec()]),
         }
     }
@@ -211,18 +298,49 @@ impl Connector<()> {
 
 impl<S> Connector<S> {
     /// Sets custom connector.
-    pub fn connector<S1, Io1>(self, connector: S1) -> Connector<S1>
+    ///
+    /// Custom connectors look up host names themselves, so connections made with them are not
+    /// raced as described in [`connection_attempt_delay`](Self::connection_attempt_delay).
+    pub fn connector<S1, Io1>(mut self, connector: S1) -> Connector<S1>
     where
         Io1: ActixStream + fmt::Debug + 'static,
         S1: Service<ConnectInfo<Uri>, Response = TcpConnection<Uri, Io1>, Error = TcpConnectError>
             + Clone,
     {
+        self.config.resolver = None;
+
         Connector {
             connector,
             config: self.config,
             tls: self.tls,
         }
     }
+
+    /// Sets the resolver used to look up host names.
+    ///
+    /// Replaces any [custom connector](Self::connector) with a TCP connector that uses `resolver`.
+    /// See [`DohResolver`](crate::DohResolver) for looking up host names over HTTPS, or implement
+    /// [`Resolve`] to use another resolver, such as `hickory-resolver`.
+    pub fn resolver<R>(self, resolver: R) -> Connector<actix_tls::connect::ConnectorService>
+    where
+        R: Resolve + 'static,
+    {
+        let resolver = resolver::custom(resolver);
+
+        let mut connector = self.connector(resolver::connector(&resolver));
+        connector.config.resolver = Some(resolver);
+        connector
+    }
+
+    /// Sends all requests over the Unix domain socket at `path`.
+    ///
+    /// Replaces any [custom connector](Self::connector) or [resolver](Self::resolver). The
+    /// authority of request URIs is only used for the `Host` header, so requests to a local daemon
+    /// can use any host name, e.g., `http://localhost/info`.
+    #[cfg(unix)]
+    pub fn unix(self, path: impl Into<std::path::PathBuf>) -> Connector<super::UnixConnector> {
+        self.connector(super::UnixConnector::new(path))
+    }
 }
 
 impl<S, IO> Connector<S>
@@ -260,6 +378,23 @@ where
         self
     }
 
+    /// Sets whether connections write their TLS session secrets to a key log file.
+    ///
+    /// When enabled and the `SSLKEYLOGFILE` environment variable is set, the secrets of each TLS
+    /// connection are appended to the file it names, in the NSS key log format. Tools such as
+    /// Wireshark can then decrypt captured traffic, which helps with debugging interoperability
+    /// issues. Anyone who can read the file can decrypt those connections.
+    ///
+    /// Does not apply to connectors set with [`openssl()`](Self::openssl), which can not be
+    /// changed once built; set a key log callback on their builder instead.
+    ///
+    /// By default, key logging is enabled in debug builds and disabled in release builds.
+    #[cfg(feature = "tls-key-log")]
+    pub fn tls_key_log(mut self, enabled: bool) -> Self {
+        self.config.tls_key_log = enabled;
+        self
+    }
+
     /// Sets custom OpenSSL `SslConnector` instance.
     #[cfg(feature = "openssl")]
     pub fn openssl(
@@ -363,6 +498,25 @@ where
         self
     }
 
+    /// Set delay between attempts to connect to the addresses of a host.
+    ///
+    /// When a host name resolves to multiple addresses, connections are attempted as described by
+    /// [RFC 8305] ("Happy Eyeballs"): IPv6 and IPv4 addresses are interleaved, starting with the
+    /// family of the first address returned by the resolver, and the next address is tried when
+    /// this delay elapses without a connection being established, or as soon as the previous
+    /// attempt fails. The first connection established is used and the other attempts are
+    /// canceled, so that an unreachable address family does not hold up connecting.
+    ///
+    /// A delay as long as the [connection timeout](Self::timeout) tries addresses one at a time.
+    ///
+    /// The default delay is 250 milliseconds.
+    ///
+    /// [RFC 8305]: https://www.rfc-editor.org/rfc/rfc8305
+    pub fn connection_attempt_delay(mut self, dur: Duration) -> Self {
+        self.config.connection_attempt_delay = dur;
+        self
+    }
+
     /// Set total number of simultaneous connections per type of scheme.
     ///
     /// If limit is 0, the connector has no limit.
@@ -378,6 +532,69 @@ where
         self
     }
 
+    /// Set number of simultaneous connections to a single host per type of scheme.
+    ///
+    /// Requests to a host that has reached this limit wait for one of its connections to be
+    /// released, without holding up requests to other hosts, so that a slow host can not take up
+    /// all the connections allowed by [`limit`](Self::limit).
+    ///
+    /// If limit is 0, the connector has no per-host limit. There is no per-host limit by default.
+    pub fn limit_per_host(mut self, limit: usize) -> Self {
+        self.config.limit_per_host = (limit != 0).then_some(limit);
+        self
+    }
+
+    /// Set total number of simultaneous connections, shared by all types of scheme.
+    ///
+    /// Unlike [`limit`](Self::limit), which applies to `http` and `https` connections separately,
+    /// this limit caps the connections of both together.
+    ///
+    /// If limit is 0, the connector has no total limit. There is no total limit by default.
+    pub fn total_limit(mut self, limit: usize) -> Self {
+        self.config.total_limit = (limit != 0).then_some(limit);
+        self
+    }
+
+    /// Set maximum time a request waits for a connection when a connection limit has been
+    /// reached.
+    ///
+    /// Requests that can not get a connection within this time fail with
+    /// [`ConnectError::PoolTimeout`]. The timeout does not include the time taken to open a new
+    /// connection, which is bounded by [`timeout`](Self::timeout).
+    ///
+    /// By default, requests wait for a connection indefinitely.
+    pub fn pool_timeout(mut self, dur: Duration) -> Self {
+        self.config.pool_timeout = Some(dur);
+        self
+    }
+
+    /// Set callback to run on connection pool events.
+    ///
+    /// The callback is called when a connection is acquired for a request, with the time the
+    /// request waited for it, and when a connection is returned to the pool or closed. Use it to
+    /// export pool metrics; the current state of the pool can be queried with
+    /// [`Client::pool_state()`](crate::Client::pool_state).
+    ///
+    /// # Examples
+    /// ```
+    /// use awc::{Client, Connector, PoolEvent};
+    ///
+    /// let connector = Connector::new().on_pool_event(|event| {
+    ///     if let PoolEvent::Acquired { authority, wait, .. } = event {
+    ///         println!("waited {wait:?} for a connection to {authority}");
+    ///     }
+    /// });
+    ///
+    /// let client = Client::builder().connector(connector).finish();
+    /// ```
+    pub fn on_pool_event<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&PoolEvent) + 'static,
+    {
+        self.config.on_pool_event = Some(Rc::new(f));
+        self
+    }
+
     /// Set keep-alive period for opened connection.
     ///
     /// Keep-alive period is the period between connection usage. If
@@ -418,21 +635,119 @@ where
         self
     }
 
+    /// Routes all connections through an HTTP proxy using `CONNECT` tunnels.
+    ///
+    /// Each new connection asks the proxy to open a tunnel to the request's target authority;
+    /// TLS and HTTP/2 are then negotiated with the target over that tunnel. Established tunnels are
+    /// pooled per target authority like direct connections, so keep-alive and HTTP/2
+    /// multiplexing reuse a tunnel instead of opening a new one for every request.
+    ///
+    /// Credentials in the proxy URI's user info are sent to the proxy with each `CONNECT` request
+    /// using HTTP basic authentication.
+    ///
+    /// The connection timeout covers both connecting to the proxy and the `CONNECT` exchange.
+    /// A proxy response other than `2xx` fails the connection with [`ConnectError::Tunnel`].
+    ///
+    /// To send plain `http` requests to the proxy without a tunnel, or to exclude hosts from
+    /// being proxied, use [`ClientBuilder::proxy()`](crate::ClientBuilder::proxy) instead.
+    ///
+    /// [`ConnectError::Tunnel`]: crate::error::ConnectError::Tunnel
+    pub fn proxy(self, proxy: Uri) -> Self {
+        self.with_proxy(Proxy::new(proxy))
+    }
+
+    pub(crate) fn with_proxy(mut self, proxy: Proxy) -> Self {
+        self.config.proxy = Some(proxy);
+        self
+    }
+
+    /// Restricts the destinations that connections can be made to.
+    ///
+    /// The addresses that host names resolve to are checked against the policy before connecting.
+    /// Clients built with this connector also check the URI of each request. See [`EgressPolicy`]
+    /// for details.
+    pub fn egress_policy(mut self, policy: EgressPolicy) -> Self {
+        self.config.egress = Some(Rc::new(policy));
+        self
+    }
+
+    /// Presents a client identity on TLS connections to the listed hosts, using the syntax of
+    /// [`Proxy::no_proxy()`].
+    ///
+    /// Domain names match the domain and its subdomains, and IP addresses and networks match URIs
+    /// that contain an IP address within them. Entries can be restricted to a port. Other hosts
+    /// are connected to with the connector's own TLS configuration.
+    ///
+    /// Can be called multiple times to set the identities of more hosts. When several entries
+    /// match a host, the identity that was set first is used. See [`ClientIdentity`] for details.
+    ///
+    /// # Panics
+    /// Panics if an entry can not be parsed.
+    ///
+    /// [`Proxy::no_proxy()`]: super::Proxy::no_proxy
+    pub fn client_identity(mut self, hosts: &str, identity: ClientIdentity) -> Self {
+        self.config
+            .identities
+            .extend(host_rules(hosts).map(|rule| (rule, identity.clone())));
+        self
+    }
+
+    /// Pins the public keys that the certificates of the listed hosts are expected to have, using
+    /// the syntax of [`Proxy::no_proxy()`].
+    ///
+    /// Domain names match the domain and its subdomains, and IP addresses and networks match URIs
+    /// that contain an IP address within them. Entries can be restricted to a port. The
+    /// certificates of other hosts are only verified by the connector's TLS configuration.
+    ///
+    /// Can be called multiple times to pin the keys of more hosts. When several entries match a
+    /// host, the pins that were set first are used. See [`CertificatePins`] for details.
+    ///
+    /// # Panics
+    /// Panics if an entry can not be parsed.
+    ///
+    /// [`Proxy::no_proxy()`]: super::Proxy::no_proxy
+    pub fn pin_certificates(mut self, hosts: &str, pins: CertificatePins) -> Self {
+        self.config
+            .pins
+            .extend(host_rules(hosts).map(|rule| (rule, pins.clone())));
+        self
+    }
+
     /// Finish configuration process and create connector service.
     ///
     /// The `Connector` builder always concludes by calling `finish()` last in its combinator chain.
-    pub fn finish(self) -> ConnectorService<S, IO> {
+    pub fn finish(mut self) -> ConnectorService<S, IO> {
         let local_address = self.config.local_address;
         let timeout = self.config.timeout;
-
-        let tcp_service_inner =
-            TcpConnectorInnerService::new(self.connector, timeout, local_address);
+        let proxy = self.config.proxy.clone();
+        let egress = self.config.egress.clone();
+        let identities = std::mem::take(&mut self.config.identities);
+        #[allow(unused_variables)] // only unused when no TLS feature is enabled
+        let pins: Rc<[_]> = std::mem::take(&mut self.config.pins).into();
+        let happy_eyeballs = self.config.resolver.clone().map(|resolver| HappyEyeballs {
+            resolver,
+            delay: self.config.connection_attempt_delay,
+        });
+
+        let tcp_service_inner = TcpConnectorInnerService::new(
+            self.connector,
+            timeout,
+            local_address,
+            proxy,
+            egress.clone(),
+            happy_eyeballs,
+        );
 
         #[allow(clippy::redundant_clone)]
         let tcp_service = TcpConnectorService {
             service: tcp_service_inner.clone(),
         };
 
+        #[cfg(feature = "tls-key-log")]
+        if self.config.tls_key_log {
+            self.tls = self.tls.with_key_log();
+        }
+
         let tls = match self.tls {
             #[cfg(feature = "openssl")]
             OurTlsConnector::OpensslBuilder(builder) => OurTlsConnector::Openssl(builder.build()),
@@ -448,8 +763,6 @@ where
 
                 #[cfg(feature = "dangerous-h2c")]
                 {
-                    use std::io;
-
                     use actix_tls::connect::Connection;
                     use actix_utils::future::{ready, Ready};
 
@@ -459,6 +772,10 @@ where
                             let io = self.into_parts().0;
                             (io, Protocol::Http2)
                         }
+
+                        fn peer_public_key(&self) -> Option<Vec<u8>> {
+                            None
+                        }
                     }
 
                     /// With the `dangerous-h2c` feature enabled, this connector uses a no-op TLS
@@ -491,6 +808,8 @@ where
                     let tls_service = TlsConnectorService {
                         tcp_service: tcp_service_inner,
                         tls_service: NoOpTlsConnectorService,
+                        identity_service: |_| Some(NoOpTlsConnectorService),
+                        pins,
                         timeout: handshake_timeout,
                     };
 
@@ -518,6 +837,11 @@ where
                             (Box::new(sock), Protocol::Http1)
                         }
                     }
+
+                    fn peer_public_key(&self) -> Option<Vec<u8>> {
+                        let cert = self.io_ref().ssl().peer_certificate()?;
+                        cert.public_key().ok()?.public_key_to_der().ok()
+                    }
                 }
 
                 let handshake_timeout = self.config.handshake_timeout;
@@ -525,6 +849,13 @@ where
                 let tls_service = TlsConnectorService {
                     tcp_service: tcp_service_inner,
                     tls_service: TlsConnector::service(tls),
+                    identity_service: |identity| {
+                        identity
+                            .openssl_connector()
+                            .cloned()
+                            .map(TlsConnector::service)
+                    },
+                    pins,
                     timeout: handshake_timeout,
                 };
 
@@ -557,6 +888,12 @@ where
                             (Box::new(sock), Protocol::Http1)
                         }
                     }
+
+                    fn peer_public_key(&self) -> Option<Vec<u8>> {
+                        let cert = self.io_ref().get_ref().1.peer_certificates()?.first()?;
+                        super::pinning::certificate_public_key(cert.0.as_slice())
+                            .map(<[u8]>::to_vec)
+                    }
                 }
 
                 let handshake_timeout = self.config.handshake_timeout;
@@ -564,6 +901,13 @@ where
                 let tls_service = TlsConnectorService {
                     tcp_service: tcp_service_inner,
                     tls_service: TlsConnector::service(tls),
+                    identity_service: |identity| {
+                        identity
+                            .rustls_0_20_config()
+                            .cloned()
+                            .map(TlsConnector::service)
+                    },
+                    pins,
                     timeout: handshake_timeout,
                 };
 
@@ -591,6 +935,12 @@ where
                             (Box::new(sock), Protocol::Http1)
                         }
                     }
+
+                    fn peer_public_key(&self) -> Option<Vec<u8>> {
+                        let cert = self.io_ref().get_ref().1.peer_certificates()?.first()?;
+                        super::pinning::certificate_public_key(cert.0.as_slice())
+                            .map(<[u8]>::to_vec)
+                    }
                 }
 
                 let handshake_timeout = self.config.handshake_timeout;
@@ -598,6 +948,13 @@ where
                 let tls_service = TlsConnectorService {
                     tcp_service: tcp_service_inner,
                     tls_service: TlsConnector::service(tls),
+                    identity_service: |identity| {
+                        identity
+                            .rustls_0_21_config()
+                            .cloned()
+                            .map(TlsConnector::service)
+                    },
+                    pins,
                     timeout: handshake_timeout,
                 };
 
@@ -628,6 +985,11 @@ where
                             (Box::new(sock), Protocol::Http1)
                         }
                     }
+
+                    fn peer_public_key(&self) -> Option<Vec<u8>> {
+                        let cert = self.io_ref().get_ref().1.peer_certificates()?.first()?;
+                        super::pinning::certificate_public_key(cert).map(<[u8]>::to_vec)
+                    }
                 }
 
                 let handshake_timeout = self.config.handshake_timeout;
@@ -635,6 +997,13 @@ where
                 let tls_service = TlsConnectorService {
                     tcp_service: tcp_service_inner,
                     tls_service: TlsConnector::service(tls),
+                    identity_service: |identity| {
+                        identity
+                            .rustls_0_22_config()
+                            .cloned()
+                            .map(TlsConnector::service)
+                    },
+                    pins,
                     timeout: handshake_timeout,
                 };
 
@@ -662,6 +1031,11 @@ where
                             (Box::new(sock), Protocol::Http1)
                         }
                     }
+
+                    fn peer_public_key(&self) -> Option<Vec<u8>> {
+                        let cert = self.io_ref().get_ref().1.peer_certificates()?.first()?;
+                        super::pinning::certificate_public_key(cert).map(<[u8]>::to_vec)
+                    }
                 }
 
                 let handshake_timeout = self.config.handshake_timeout;
@@ -669,6 +1043,13 @@ where
                 let tls_service = TlsConnectorService {
                     tcp_service: tcp_service_inner,
                     tls_service: TlsConnector::service(tls),
+                    identity_service: |identity| {
+                        identity
+                            .rustls_0_23_config()
+                            .cloned()
+                            .map(TlsConnector::service)
+                    },
+                    pins,
                     timeout: handshake_timeout,
                 };
 
@@ -676,6 +1057,17 @@ where
             }
         };
 
+        // shared by both pools so that their state is reported together
+        self.config.pool_monitor = Rc::new(PoolMonitor::new(
+            self.config.limit,
+            self.config.on_pool_event.clone(),
+        ));
+
+        self.config.total_permits = self
+            .config
+            .total_limit
+            .map(|limit| Arc::new(Semaphore::new(limit)));
+
         let tcp_config = self.config.no_disconnect_timeout();
 
         let tcp_pool = ConnectionPool::new(tcp_service, tcp_config);
@@ -684,7 +1076,12 @@ where
         let tls_pool =
             tls_service.map(move |tls_service| ConnectionPool::new(tls_service, tls_config));
 
-        ConnectorServicePriv { tcp_pool, tls_pool }
+        ConnectorServicePriv {
+            tcp_pool,
+            tls_pool,
+            egress,
+            identities,
+        }
     }
 }
 
@@ -706,6 +1103,7 @@ where
 
     fn call(&self, req: Connect) -> Self::Future {
         TcpConnectorFuture {
+            protocol: req.protocol.unwrap_or(Protocol::Http1),
             fut: self.service.call(req),
         }
     }
@@ -716,6 +1114,7 @@ pin_project! {
     pub struct TcpConnectorFuture<Fut> {
         #[pin]
         fut: Fut,
+        protocol: Protocol,
     }
 }
 
@@ -726,10 +1125,12 @@ where
     type Output = Result<(Io, Protocol), ConnectError>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.project()
-            .fut
+        let this = self.project();
+        let protocol = *this.protocol;
+
+        this.fut
             .poll(cx)
-            .map_ok(|res| (res.into_parts().0, Protocol::Http1))
+            .map_ok(|res| (res.into_parts().0, protocol))
     }
 }
 
@@ -753,6 +1154,13 @@ struct TlsConnectorService<Tcp, Tls> {
     /// TLS connection is canceled on `TlsConnectorService`'s timeout setting.
     tls_service: Tls,
 
+    /// Constructs the TLS service for a client identity, or returns `None` if the identity is for
+    /// another TLS library.
+    identity_service: fn(&ClientIdentity) -> Option<Tls>,
+
+    /// Pins of the certificates of hosts.
+    pins: Rc<[(HostRule, CertificatePins)]>,
+
     timeout: Duration,
 }
 
@@ -784,13 +1192,36 @@ where
     }
 
     fn call(&self, req: Connect) -> Self::Future {
+        let tls_service = match req.identity {
+            None => self.tls_service.clone(),
+            Some(ref identity) => match (self.identity_service)(identity) {
+                Some(tls_service) => tls_service,
+                None => return TlsConnectorFuture::IdentityNotSupported,
+            },
+        };
+
+        let pins = host_match(&self.pins, &req.uri).map(|pins| {
+            let host = req.uri.host().unwrap_or_default().to_owned();
+            (pins.clone(), host)
+        });
+
+        let server_name = req.server_name.clone().map(|name| {
+            Uri::builder()
+                .scheme("https")
+                .authority(name)
+                .path_and_query("/")
+                .build()
+                .expect("server name is a valid authority")
+        });
+
         let fut = self.tcp_service.call(req);
-        let tls_service = self.tls_service.clone();
         let timeout = self.timeout;
 
         TlsConnectorFuture::TcpConnect {
             fut,
             tls_service: Some(tls_service),
+            server_name,
+            pins,
             timeout,
         }
     }
@@ -804,20 +1235,28 @@ pin_project! {
             #[pin]
             fut: Fut1,
             tls_service: Option<S>,
+            server_name: Option<Uri>,
+            pins: Option<(CertificatePins, String)>,
             timeout: Duration,
         },
         TlsConnect {
             #[pin]
             fut: Fut2,
+            pins: Option<(CertificatePins, String)>,
             #[pin]
             timeout: Sleep,
+            timer: Option<PhaseTimer>,
         },
+        IdentityNotSupported,
     }
 
 }
 /// helper trait for generic over different TlsStream types between tls crates.
 trait IntoConnectionIo {
     fn into_connection_io(self) -> (Box<dyn ConnectionIo>, Protocol);
+
+    /// Returns the DER encoded public key of the server's certificate, if it presented one.
+    fn peer_public_key(&self) -> Option<Vec<u8>>;
 }
 
 impl<S, Io, Fut1, Fut2, Res> Future for TlsConnectorFuture<S, Fut1, Fut2>
@@ -835,21 +1274,55 @@ where
             TlsConnectorProj::TcpConnect {
                 fut,
                 tls_service,
+                server_name,
+                pins,
                 timeout,
             } => {
-                let res = ready!(fut.poll(cx))?;
+                let mut res = ready!(fut.poll(cx))?;
+
+                // the TLS service takes the server name from the host of the connection's URI
+                if let Some(server_name) = server_name.take() {
+                    let (io, _) = res.into_parts();
+                    res = TcpConnection::new(server_name, io);
+                }
+
                 let fut = tls_service
                     .take()
                     .expect("TlsConnectorFuture polled after complete")
                     .call(res);
+                let pins = pins.take();
                 let timeout = sleep(*timeout);
-                self.set(TlsConnectorFuture::TlsConnect { fut, timeout });
+                let timer = Some(PhaseTimer::start(Phase::Tls));
+                self.set(TlsConnectorFuture::TlsConnect {
+                    fut,
+                    pins,
+                    timeout,
+                    timer,
+                });
                 self.poll(cx)
             }
-            TlsConnectorProj::TlsConnect { fut, timeout } => match fut.poll(cx)? {
-                Poll::Ready(res) => Poll::Ready(Ok(res.into_connection_io())),
+            TlsConnectorProj::TlsConnect {
+                fut,
+                pins,
+                timeout,
+                timer,
+            } => match fut.poll(cx)? {
+                Poll::Ready(res) => {
+                    if let Some(timer) = timer.take() {
+                        timer.finish();
+                    }
+
+                    if let Some((pins, host)) = pins.take() {
+                        pins.verify(&host, res.peer_public_key().as_deref())?;
+                    }
+
+                    Poll::Ready(Ok(res.into_connection_io()))
+                }
                 Poll::Pending => timeout.poll(cx).map(|_| Err(ConnectError::Timeout)),
             },
+            TlsConnectorProj::IdentityNotSupported => {
+                Poll::Ready(Err(ConnectError::IdentityNotSupported))
+            }
         }
     }
 }
@@ -861,14 +1334,27 @@ pub struct TcpConnectorInnerService<S: Clone> {
     service: S,
     timeout: Duration,
     local_address: Option<std::net::IpAddr>,
+    proxy: Option<Proxy>,
+    egress: Option<Rc<EgressPolicy>>,
+    happy_eyeballs: Option<HappyEyeballs>,
 }
 
 impl<S: Clone> TcpConnectorInnerService<S> {
-    fn new(service: S, timeout: Duration, local_address: Option<std::net::IpAddr>) -> Self {
+    fn new(
+        service: S,
+        timeout: Duration,
+        local_address: Option<std::net::IpAddr>,
+        proxy: Option<Proxy>,
+        egress: Option<Rc<EgressPolicy>>,
+        happy_eyeballs: Option<HappyEyeballs>,
+    ) -> Self {
         Self {
             service,
             timeout,
             local_address,
+            proxy,
+            egress,
+            happy_eyeballs,
         }
     }
 }
@@ -878,50 +1364,343 @@ where
     S: Service<ConnectInfo<Uri>, Response = TcpConnection<Uri, Io>, Error = TcpConnectError>
         + Clone
         + 'static,
+    Io: ActixStream + 'static,
 {
     type Response = S::Response;
     type Error = ConnectError;
-    type Future = TcpConnectorInnerFuture<S::Future>;
+    type Future = TcpConnectorInnerFuture<
+        LocalBoxFuture<'static, Result<TcpConnection<Uri, Io>, ConnectError>>,
+        Io,
+    >;
 
     actix_service::forward_ready!(service);
 
     fn call(&self, req: Connect) -> Self::Future {
-        let mut req = ConnectInfo::new(req.uri).set_addr(req.addr);
+        // with a proxy, connect to the proxy and tunnel to the requested uri afterwards
+        let (uri, addr, tunnel) = match self.proxy {
+            Some(ref proxy) if proxy.tunnels(&req.uri) => (
+                proxy.uri().clone(),
+                None,
+                Some((req.uri, proxy.authorization().cloned())),
+            ),
+            _ => (req.uri, req.addr, None),
+        };
 
-        if let Some(local_addr) = self.local_address {
-            req = req.set_local_addr(local_addr);
-        }
+        // the proxy is trusted, and looks up the host names of tunnel targets itself
+        let egress = match self.proxy {
+            Some(ref proxy) if proxy.is_proxy(&uri) => None,
+            _ => self.egress.clone(),
+        };
+
+        let ip = addr.map(|addr| addr.ip()).or_else(|| ip_literal(&uri));
+
+        let denied = match (ip, &egress) {
+            (Some(ip), Some(egress)) => egress.check_addr(ip).err(),
+            _ => None,
+        };
+
+        let fut: LocalBoxFuture<'static, _> = match (denied, &self.happy_eyeballs) {
+            (Some(err), _) => Box::pin(std::future::ready(Err(err))),
+
+            (None, Some(happy_eyeballs)) if ip.is_none() => {
+                Box::pin(happy_eyeballs.clone().connect(
+                    self.service.clone(),
+                    uri,
+                    self.local_address,
+                    egress,
+                ))
+            }
+
+            (None, _) => {
+                let mut req = ConnectInfo::new(uri).set_addr(addr);
+
+                if let Some(local_addr) = self.local_address {
+                    req = req.set_local_addr(local_addr);
+                }
+
+                let fut = self.service.call(req);
+                Box::pin(async move { fut.await.map_err(ConnectError::from) })
+            }
+        };
 
         TcpConnectorInnerFuture {
-            fut: self.service.call(req),
+            fut,
+            tunnel,
+            handshake: None,
             timeout: sleep(self.timeout),
+            timer: Some(PhaseTimer::start(Phase::Connect)),
         }
     }
 }
 
 pin_project! {
     #[project = TcpConnectorInnerFutureProj]
-    pub struct TcpConnectorInnerFuture<Fut> {
+    pub struct TcpConnectorInnerFuture<Fut, Io> {
         #[pin]
         fut: Fut,
+        tunnel: Option<(Uri, Option<HeaderValue>)>,
+        handshake: Option<LocalBoxFuture<'static, Result<TcpConnection<Uri, Io>, ConnectError>>>,
         #[pin]
         timeout: Sleep,
+        timer: Option<PhaseTimer>,
     }
 }
 
-impl<Fut, Io> Future for TcpConnectorInnerFuture<Fut>
+impl<Fut, Io> Future for TcpConnectorInnerFuture<Fut, Io>
 where
-    Fut: Future<Output = Result<TcpConnection<Uri, Io>, TcpConnectError>>,
+    Fut: Future<Output = Result<TcpConnection<Uri, Io>, ConnectError>>,
+    Io: ActixStream + 'static,
 {
     type Output = Result<TcpConnection<Uri, Io>, ConnectError>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.project();
-        match this.fut.poll(cx) {
-            Poll::Ready(res) => Poll::Ready(res.map_err(ConnectError::from)),
-            Poll::Pending => this.timeout.poll(cx).map(|_| Err(ConnectError::Timeout)),
+
+        if this.handshake.is_none() {
+            match this.fut.poll(cx) {
+                Poll::Ready(Ok(conn)) => match this.tunnel.take() {
+                    Some((target, authorization)) => {
+                        let (io, _) = conn.into_parts();
+                        *this.handshake = Some(Box::pin(connect_tunnel(io, target, authorization)));
+                    }
+                    None => {
+                        if let Some(timer) = this.timer.take() {
+                            timer.finish();
+                        }
+                        return Poll::Ready(Ok(conn));
+                    }
+                },
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => {}
+            }
+        }
+
+        if let Some(handshake) = this.handshake {
+            if let Poll::Ready(res) = handshake.as_mut().poll(cx) {
+                if let Some(timer) = this.timer.take() {
+                    timer.finish();
+                }
+                return Poll::Ready(res);
+            }
+        }
+
+        this.timeout.poll(cx).map(|_| Err(ConnectError::Timeout))
+    }
+}
+
+/// Returns the IP address that is the host of `uri`, which needs no lookup.
+fn ip_literal(uri: &Uri) -> Option<IpAddr> {
+    uri.host()?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .ok()
+}
+
+/// Races connections to the addresses of a host as described by RFC 8305 ("Happy Eyeballs").
+#[derive(Clone)]
+struct HappyEyeballs {
+    resolver: Rc<dyn Resolve>,
+    delay: Duration,
+}
+
+impl HappyEyeballs {
+    async fn connect<S, Io>(
+        self,
+        service: S,
+        uri: Uri,
+        local_address: Option<IpAddr>,
+        egress: Option<Rc<EgressPolicy>>,
+    ) -> Result<TcpConnection<Uri, Io>, ConnectError>
+    where
+        S: Service<ConnectInfo<Uri>, Response = TcpConnection<Uri, Io>, Error = TcpConnectError>,
+    {
+        let host = uri.host().unwrap_or_default();
+        let port = ConnectInfo::new(uri.clone()).port();
+
+        let mut addrs = self
+            .resolver
+            .lookup(host, port)
+            .await
+            .map_err(ConnectError::Resolver)?;
+
+        // only the addresses checked here are connected to, so a host name can not be rebound
+        if let Some(egress) = egress {
+            let mut denied = None;
+
+            addrs.retain(|addr| match egress.check_addr(addr.ip()) {
+                Ok(()) => true,
+                Err(err) => {
+                    denied.get_or_insert(err);
+                    false
+                }
+            });
+
+            if addrs.is_empty() {
+                if let Some(err) = denied {
+                    return Err(err);
+                }
+            }
+        }
+
+        let mut addrs = interleave_families(addrs).into_iter();
+        let mut attempts = Vec::<Pin<Box<S::Future>>>::new();
+        let mut last_err = None;
+        let mut delay = pin!(sleep(self.delay));
+
+        poll_fn(|cx| loop {
+            let mut started = false;
+
+            // start the next attempt once the delay elapses or all attempts so far have failed
+            if attempts.is_empty() || delay.as_mut().poll(cx).is_ready() {
+                match addrs.next() {
+                    Some(addr) => {
+                        let mut req = ConnectInfo::new(uri.clone()).set_addr(Some(addr));
+
+                        if let Some(local_addr) = local_address {
+                            req = req.set_local_addr(local_addr);
+                        }
+
+                        attempts.push(Box::pin(service.call(req)));
+                        delay.as_mut().reset(Instant::now() + self.delay);
+                        started = true;
+                    }
+
+                    None if attempts.is_empty() => {
+                        return Poll::Ready(Err(last_err
+                            .take()
+                            .map_or(ConnectError::NoRecords, ConnectError::from)));
+                    }
+
+                    None => {}
+                }
+            }
+
+            let mut failed = false;
+            let mut idx = 0;
+
+            while idx < attempts.len() {
+                match attempts[idx].as_mut().poll(cx) {
+                    Poll::Ready(Ok(conn)) => return Poll::Ready(Ok(conn)),
+
+                    Poll::Ready(Err(err)) => {
+                        log::trace!("connection attempt failed: {err}");
+                        drop(attempts.swap_remove(idx));
+                        last_err = Some(err);
+                        failed = true;
+                    }
+
+                    Poll::Pending => idx += 1,
+                }
+            }
+
+            if !started && !failed {
+                return Poll::Pending;
+            }
+        })
+        .await
+    }
+}
+
+/// Orders addresses so that IPv6 and IPv4 addresses alternate, starting with the family of the
+/// first address, as described in RFC 8305 §4.
+fn interleave_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let Some(first) = addrs.first() else {
+        return addrs;
+    };
+
+    let first_is_ipv6 = first.is_ipv6();
+    let (preferred, other): (Vec<_>, Vec<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first_is_ipv6);
+
+    let mut interleaved = Vec::with_capacity(preferred.len() + other.len());
+    let mut other = other.into_iter();
+
+    for addr in preferred {
+        interleaved.push(addr);
+        interleaved.extend(other.next());
+    }
+
+    interleaved.extend(other);
+    interleaved
+}
+
+/// Max size of the proxy's response to a `CONNECT` request.
+const MAX_TUNNEL_RESPONSE_SIZE: usize = 8 * 1024;
+
+/// Asks the proxy on the other end of `io` to open a tunnel to `target`.
+async fn connect_tunnel<Io>(
+    mut io: Io,
+    target: Uri,
+    authorization: Option<HeaderValue>,
+) -> Result<TcpConnection<Uri, Io>, ConnectError>
+where
+    Io: ActixStream,
+{
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    let host = target.host().ok_or(ConnectError::Unresolved)?;
+    let port = target.port_u16().unwrap_or(match target.scheme_str() {
+        Some("https") | Some("wss") => 443,
+        _ => 80,
+    });
+
+    let mut req = format!("CONNECT {host}:{port} HTTP/1.1\r\nhost: {host}:{port}\r\n").into_bytes();
+    if let Some(authorization) = authorization {
+        req.extend_from_slice(b"proxy-authorization: ");
+        req.extend_from_slice(authorization.as_bytes());
+        req.extend_from_slice(b"\r\n");
+    }
+    req.extend_from_slice(b"\r\n");
+    io.write_all(&req).await?;
+
+    let mut buf = bytes::BytesMut::with_capacity(1024);
+
+    let head_len = loop {
+        if let Some(pos) = buf.windows(4).position(|window| window == b"\r\n\r\n") {
+            break pos + 4;
         }
+
+        if buf.len() >= MAX_TUNNEL_RESPONSE_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "proxy response to CONNECT request is too large",
+            )
+            .into());
+        }
+
+        if io.read_buf(&mut buf).await? == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+    };
+
+    // the target speaks only after the client does, so nothing may follow the response head
+    if buf.len() > head_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unexpected data after proxy response to CONNECT request",
+        )
+        .into());
+    }
+
+    let status = buf[..head_len]
+        .split(|&byte| byte == b' ')
+        .nth(1)
+        .filter(|_| buf.starts_with(b"HTTP/1."))
+        .and_then(|code| http::StatusCode::from_bytes(code).ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "malformed proxy response to CONNECT request",
+            )
+        })?;
+
+    if !status.is_success() {
+        return Err(ConnectError::Tunnel(status));
     }
+
+    Ok(TcpConnection::new(target, io))
 }
 
 /// Connector service for pooled Plain/Tls Tcp connections.
@@ -951,6 +1730,31 @@ where
 {
     tcp_pool: ConnectionPool<S1, Io1>,
     tls_pool: Option<ConnectionPool<S2, Io2>>,
+    egress: Option<Rc<EgressPolicy>>,
+    identities: Vec<(HostRule, ClientIdentity)>,
+}
+
+impl<S1, S2, Io1, Io2> ConnectorServicePriv<S1, S2, Io1, Io2>
+where
+    S1: Service<Connect, Response = (Io1, Protocol), Error = ConnectError>,
+    S2: Service<Connect, Response = (Io2, Protocol), Error = ConnectError>,
+    Io1: ConnectionIo,
+    Io2: ConnectionIo,
+{
+    /// Returns the monitor of the connector's connection pools.
+    pub(crate) fn pool_monitor(&self) -> &Rc<PoolMonitor> {
+        self.tcp_pool.monitor()
+    }
+
+    /// Returns the egress policy of the connector, if one is set.
+    pub(crate) fn egress_policy(&self) -> Option<&Rc<EgressPolicy>> {
+        self.egress.as_ref()
+    }
+
+    /// Returns the client identity set for the host of `uri`, if any.
+    fn host_identity(&self, uri: &Uri) -> Option<&ClientIdentity> {
+        host_match(&self.identities, uri)
+    }
 }
 
 impl<S1, S2, Io1, Io2> Service<Connect> for ConnectorServicePriv<S1, S2, Io1, Io2>
@@ -972,17 +1776,32 @@ where
         Poll::Ready(Ok(()))
     }
 
-    fn call(&self, req: Connect) -> Self::Future {
+    fn call(&self, mut req: Connect) -> Self::Future {
         match req.uri.scheme_str() {
             Some("https") | Some("wss") => match self.tls_pool {
                 None => ConnectorServiceFuture::SslIsNotSupported,
-                Some(ref pool) => ConnectorServiceFuture::Tls {
-                    fut: pool.call(req),
-                },
-            },
-            _ => ConnectorServiceFuture::Tcp {
-                fut: self.tcp_pool.call(req),
+                Some(ref pool) => {
+                    // the protocol of TLS connections is negotiated with ALPN
+                    req.protocol = None;
+
+                    if req.identity.is_none() {
+                        req.identity = self.host_identity(&req.uri).cloned();
+                    }
+
+                    ConnectorServiceFuture::Tls {
+                        fut: pool.call(req),
+                    }
+                }
             },
+            _ => {
+                // cleartext connections are pooled regardless of identity and server name
+                req.identity = None;
+                req.server_name = None;
+
+                ConnectorServiceFuture::Tcp {
+                    fut: self.tcp_pool.call(req),
+                }
+            }
         }
     }
 }
@@ -1032,29 +1851,100 @@ where
     }
 }
 
-#[cfg(not(feature = "trust-dns"))]
+/// Returns the value of the first rule that matches the host of the TLS connection to `uri`.
+fn host_match<'a, V>(rules: &'a [(HostRule, V)], uri: &Uri) -> Option<&'a V> {
+    let host = uri.host()?.trim_start_matches('[').trim_end_matches(']');
+    let port = uri.port_u16().unwrap_or(443);
+
+    rules
+        .iter()
+        .find(|(rule, _)| rule.matches(host, port))
+        .map(|(_, value)| value)
+}
+
 mod resolver {
     use super::*;
 
-    pub(super) fn resolver() -> Resolver {
-        Resolver::default()
+    /// Wraps a resolver to time its lookups as the DNS phase of the current request.
+    struct TimedResolver<R>(R);
+
+    /// Resolver shared by the TCP connector and Happy Eyeballs connection racing.
+    struct SharedResolver(Rc<dyn Resolve>);
+
+    pub(super) fn custom(resolver: impl Resolve + 'static) -> Rc<dyn Resolve> {
+        Rc::new(TimedResolver(resolver))
     }
-}
 
-#[cfg(feature = "trust-dns")]
-mod resolver {
-    use std::{cell::RefCell, net::SocketAddr};
+    /// Constructs a TCP connector that looks up host names with `resolver`.
+    pub(super) fn connector(resolver: &Rc<dyn Resolve>) -> actix_tls::connect::ConnectorService {
+        TcpConnector::new(Resolver::custom(SharedResolver(Rc::clone(resolver)))).service()
+    }
 
-    use actix_tls::connect::Resolve;
-    use trust_dns_resolver::{
-        config::{ResolverConfig, ResolverOpts},
-        system_conf::read_system_conf,
-        TokioAsyncResolver,
-    };
+    impl Resolve for SharedResolver {
+        fn lookup<'a>(
+            &'a self,
+            host: &'a str,
+            port: u16,
+        ) -> LocalBoxFuture<'a, Result<Vec<SocketAddr>, Box<dyn std::error::Error>>> {
+            self.0.lookup(host, port)
+        }
+    }
 
-    use super::*;
+    impl<R: Resolve> Resolve for TimedResolver<R> {
+        fn lookup<'a>(
+            &'a self,
+            host: &'a str,
+            port: u16,
+        ) -> LocalBoxFuture<'a, Result<Vec<SocketAddr>, Box<dyn std::error::Error>>> {
+            Box::pin(async move {
+                let timer = PhaseTimer::start(Phase::Dns);
+                let res = self.0.lookup(host, port).await;
+                timer.finish();
+                res
+            })
+        }
+    }
+
+    #[cfg(not(feature = "trust-dns"))]
+    pub(super) fn resolver() -> Rc<dyn Resolve> {
+        use std::net::ToSocketAddrs as _;
+
+        /// Resolves using the system's resolver on the blocking thread pool.
+        struct SystemResolver;
+
+        impl Resolve for SystemResolver {
+            fn lookup<'a>(
+                &'a self,
+                host: &'a str,
+                port: u16,
+            ) -> LocalBoxFuture<'a, Result<Vec<SocketAddr>, Box<dyn std::error::Error>>>
+            {
+                let host = host.to_owned();
+
+                Box::pin(async move {
+                    let addrs = actix_rt::task::spawn_blocking(move || {
+                        (host.as_str(), port).to_socket_addrs()
+                    })
+                    .await??;
+
+                    Ok(addrs.collect())
+                })
+            }
+        }
+
+        custom(SystemResolver)
+    }
+
+    #[cfg(feature = "trust-dns")]
+    pub(super) fn resolver() -> Rc<dyn Resolve> {
+        use std::cell::RefCell;
+
+        use trust_dns_resolver::{
+            config::{ResolverConfig, ResolverOpts},
+            system_conf::read_system_conf,
+            TokioAsyncResolver,
+        };
 
-    pub(super) fn resolver() -> Resolver {
         // new type for impl Resolve trait for TokioAsyncResolver.
         struct TrustDnsResolver(TokioAsyncResolver);
 
@@ -1080,12 +1970,13 @@ mod resolver {
 
         // resolver struct is cached in thread local so new clients can reuse the existing instance
         thread_local! {
-            static TRUST_DNS_RESOLVER: RefCell<Option<Resolver>> = const { RefCell::new(None) };
+            static TRUST_DNS_RESOLVER: RefCell<Option<Rc<dyn Resolve>>> =
+                const { RefCell::new(None) };
         }
 
         // get from thread local or construct a new trust-dns resolver.
         TRUST_DNS_RESOLVER.with(|local| {
-            let resolver = local.borrow().as_ref().map(Clone::clone);
+            let resolver = local.borrow().as_ref().map(Rc::clone);
 
             match resolver {
                 Some(resolver) => resolver,
@@ -1102,8 +1993,8 @@ mod resolver {
                     let resolver = TokioAsyncResolver::tokio(cfg, opts);
 
                     // box trust dns resolver and put it in thread local.
-                    let resolver = Resolver::custom(TrustDnsResolver(resolver));
-                    *local.borrow_mut() = Some(resolver.clone());
+                    let resolver = custom(TrustDnsResolver(resolver));
+                    *local.borrow_mut() = Some(Rc::clone(&resolver));
 
                     resolver
                 }
@@ -1135,7 +2026,7 @@ mod tests {
         .await;
 
         let connector = Connector {
-            connector: TcpConnector::new(resolver::resolver()).service(),
+            connector: resolver::connector(&resolver::resolver()),
             config: ConnectorConfig::default(),
             tls: OurTlsConnector::None,
         };
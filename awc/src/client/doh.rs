@@ -0,0 +1,412 @@
+//! DNS-over-HTTPS resolver.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use actix_http::{header, Uri};
+use actix_tls::connect::Resolve;
+use bytes::{BufMut as _, Bytes, BytesMut};
+use futures_core::future::LocalBoxFuture;
+use futures_util::future::try_join;
+
+use crate::Client;
+
+const DNS_MESSAGE: &str = "application/dns-message";
+
+/// Max size of a DNS message.
+const MAX_MESSAGE_SIZE: usize = 65_535;
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+
+/// Resolver that looks up host names using DNS-over-HTTPS ([RFC 8484]).
+///
+/// `A` and `AAAA` queries are sent in DNS wire format as `POST` requests to the endpoint, and their
+/// answers are cached for as long as their TTL allows. Select it with [`Connector::resolver()`].
+///
+/// Requests to the endpoint are sent to its bootstrap addresses, so no cleartext DNS query is ever
+/// needed to reach it. Endpoints whose host is an IP address need no bootstrap addresses; the host
+/// of any other endpoint without them is resolved by the client sending the queries.
+///
+/// # Examples
+/// ```no_run
+/// use awc::{Client, Connector, DohResolver};
+///
+/// # #[actix_rt::main]
+/// # async fn main() {
+/// let client = Client::builder()
+///     .connector(Connector::new().resolver(DohResolver::cloudflare()))
+///     .finish();
+/// # }
+/// ```
+///
+/// [RFC 8484]: https://www.rfc-editor.org/rfc/rfc8484
+/// [`Connector::resolver()`]: crate::Connector::resolver
+pub struct DohResolver {
+    client: Client,
+    endpoint: Uri,
+    bootstrap: Vec<SocketAddr>,
+    cache: RefCell<HashMap<String, CachedAnswer>>,
+}
+
+struct CachedAnswer {
+    ips: Vec<IpAddr>,
+    expires: Instant,
+}
+
+impl DohResolver {
+    /// Constructs a resolver that queries `endpoint`, e.g., `https://dns.example/dns-query`.
+    ///
+    /// Queries are sent by a client with default settings.
+    pub fn new(endpoint: Uri) -> Self {
+        Self {
+            client: Client::default(),
+            endpoint,
+            bootstrap: Vec::new(),
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Constructs a resolver that queries Cloudflare's public DNS-over-HTTPS endpoint.
+    pub fn cloudflare() -> Self {
+        Self::new(Uri::from_static("https://cloudflare-dns.com/dns-query")).bootstrap([
+            IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+            IpAddr::V4(Ipv4Addr::new(1, 0, 0, 1)),
+        ])
+    }
+
+    /// Constructs a resolver that queries Google's public DNS-over-HTTPS endpoint.
+    pub fn google() -> Self {
+        Self::new(Uri::from_static("https://dns.google/dns-query")).bootstrap([
+            IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+            IpAddr::V4(Ipv4Addr::new(8, 8, 4, 4)),
+        ])
+    }
+
+    /// Sets the addresses that requests to the endpoint are sent to, tried in order.
+    pub fn bootstrap(mut self, addrs: impl IntoIterator<Item = IpAddr>) -> Self {
+        let port = self.endpoint.port_u16().unwrap_or(443);
+        self.bootstrap = addrs
+            .into_iter()
+            .map(|ip| SocketAddr::new(ip, port))
+            .collect();
+        self
+    }
+
+    /// Sets the client that sends queries, e.g., to configure TLS or timeouts.
+    ///
+    /// The client must not itself use this resolver.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    async fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, Box<dyn std::error::Error>> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+
+        if let Some(answer) = self.cache.borrow().get(&host) {
+            if answer.expires > Instant::now() {
+                return Ok(answer.ips.clone());
+            }
+        }
+
+        let (v4, v6) = try_join(self.query(&host, TYPE_A), self.query(&host, TYPE_AAAA)).await?;
+
+        let ttl = v4.ttl.min(v6.ttl);
+        let ips = v4.ips.into_iter().chain(v6.ips).collect::<Vec<_>>();
+
+        self.cache.borrow_mut().insert(
+            host,
+            CachedAnswer {
+                ips: ips.clone(),
+                expires: Instant::now() + ttl,
+            },
+        );
+
+        Ok(ips)
+    }
+
+    async fn query(&self, host: &str, qtype: u16) -> Result<Answer, Box<dyn std::error::Error>> {
+        let query = encode_query(host, qtype)?;
+
+        // without bootstrap addresses, the endpoint host is resolved by the client
+        let addrs = self.bootstrap.iter().copied().map(Some);
+        let addrs = addrs.chain(self.bootstrap.is_empty().then_some(None));
+
+        let mut last_err = None;
+
+        for addr in addrs {
+            let mut req = self
+                .client
+                .post(self.endpoint.clone())
+                .insert_header((header::CONTENT_TYPE, DNS_MESSAGE))
+                .insert_header((header::ACCEPT, DNS_MESSAGE));
+
+            if let Some(addr) = addr {
+                req = req.address(addr);
+            }
+
+            let mut res = match req.send_body(query.clone()).await {
+                Ok(res) => res,
+                Err(err) => {
+                    last_err = Some(err.to_string());
+                    continue;
+                }
+            };
+
+            if !res.status().is_success() {
+                return Err(
+                    format!("DNS-over-HTTPS endpoint responded with {}", res.status()).into(),
+                );
+            }
+
+            let body = res.body().limit(MAX_MESSAGE_SIZE).await?;
+            return Ok(decode_answer(&body, qtype)?);
+        }
+
+        Err(format!(
+            "could not reach DNS-over-HTTPS endpoint: {}",
+            last_err.unwrap_or_default()
+        )
+        .into())
+    }
+}
+
+impl Resolve for DohResolver {
+    fn lookup<'a>(
+        &'a self,
+        host: &'a str,
+        port: u16,
+    ) -> LocalBoxFuture<'a, Result<Vec<SocketAddr>, Box<dyn std::error::Error>>> {
+        Box::pin(async move {
+            let ips = self.resolve(host).await?;
+            Ok(ips
+                .into_iter()
+                .map(|ip| SocketAddr::new(ip, port))
+                .collect())
+        })
+    }
+}
+
+/// Addresses found in a DNS response.
+#[derive(Debug, PartialEq, Eq)]
+struct Answer {
+    ips: Vec<IpAddr>,
+    /// Shortest TTL of the address records.
+    ttl: Duration,
+}
+
+/// Encodes a recursive query for records of `qtype` for `host`.
+fn encode_query(host: &str, qtype: u16) -> io::Result<Bytes> {
+    let mut buf = BytesMut::with_capacity(18 + host.len());
+
+    // ID is zero for HTTP cache friendliness, as recommended by RFC 8484 §4.1
+    buf.put_u16(0);
+    // standard query with recursion desired
+    buf.put_u16(0x0100);
+    // one question; no answer, authority, or additional records
+    buf.put_u16(1);
+    buf.put_u16(0);
+    buf.put_u16(0);
+    buf.put_u16(0);
+
+    if host.is_empty() || host.len() > 253 {
+        return Err(invalid_input("invalid host name length"));
+    }
+
+    for label in host.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(invalid_input("invalid host name label length"));
+        }
+
+        buf.put_u8(label.len() as u8);
+        buf.put_slice(label.as_bytes());
+    }
+
+    buf.put_u8(0);
+    buf.put_u16(qtype);
+    buf.put_u16(CLASS_IN);
+
+    Ok(buf.freeze())
+}
+
+/// Decodes the address records of `qtype` from a DNS response.
+fn decode_answer(msg: &[u8], qtype: u16) -> io::Result<Answer> {
+    let mut reader = Reader { msg, pos: 0 };
+
+    reader.skip(2)?;
+    let flags = reader.u16()?;
+    let questions = reader.u16()?;
+    let answers = reader.u16()?;
+    reader.skip(4)?;
+
+    if flags & 0x8000 == 0 {
+        return Err(invalid_data("DNS message is not a response"));
+    }
+
+    match flags & 0x000F {
+        0 => {}
+        // NXDOMAIN; the host has no records
+        3 => {
+            return Ok(Answer {
+                ips: Vec::new(),
+                ttl: Duration::ZERO,
+            })
+        }
+        rcode => {
+            return Err(invalid_data(&format!(
+                "DNS query failed with RCODE {rcode}"
+            )))
+        }
+    }
+
+    for _ in 0..questions {
+        reader.skip_name()?;
+        reader.skip(4)?;
+    }
+
+    let mut ips = Vec::new();
+    let mut ttl = u32::MAX;
+
+    for _ in 0..answers {
+        reader.skip_name()?;
+        let rtype = reader.u16()?;
+        let class = reader.u16()?;
+        let record_ttl = reader.u32()?;
+        let len = reader.u16()? as usize;
+        let data = reader.take(len)?;
+
+        // other records, e.g. CNAMEs leading to the addresses, are skipped
+        if rtype != qtype || class != CLASS_IN {
+            continue;
+        }
+
+        let ip = match (rtype, data.len()) {
+            (TYPE_A, 4) => IpAddr::V4(Ipv4Addr::new(data[0], data[1], data[2], data[3])),
+            (TYPE_AAAA, 16) => {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(data);
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+            _ => return Err(invalid_data("invalid address record length")),
+        };
+
+        ips.push(ip);
+        ttl = ttl.min(record_ttl);
+    }
+
+    let ttl = if ips.is_empty() { 0 } else { ttl };
+
+    Ok(Answer {
+        ips,
+        ttl: Duration::from_secs(u64::from(ttl)),
+    })
+}
+
+struct Reader<'a> {
+    msg: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let data = self
+            .msg
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| invalid_data("DNS message is truncated"))?;
+
+        self.pos += len;
+        Ok(data)
+    }
+
+    fn skip(&mut self, len: usize) -> io::Result<()> {
+        self.take(len).map(|_| ())
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let data = self.take(2)?;
+        Ok(u16::from_be_bytes([data[0], data[1]]))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let data = self.take(4)?;
+        Ok(u32::from_be_bytes([data[0], data[1], data[2], data[3]]))
+    }
+
+    /// Skips a possibly compressed domain name.
+    fn skip_name(&mut self) -> io::Result<()> {
+        loop {
+            let len = self.take(1)?[0];
+
+            match len {
+                0 => return Ok(()),
+                // pointer to a name elsewhere in the message ends this one
+                len if len & 0xC0 == 0xC0 => return self.skip(1),
+                len if len & 0xC0 == 0 => self.skip(len as usize)?,
+                _ => return Err(invalid_data("invalid DNS name label")),
+            }
+        }
+    }
+}
+
+fn invalid_input(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_encoding() {
+        let query = encode_query("example.com", TYPE_AAAA).unwrap();
+
+        assert_eq!(
+            query.as_ref(),
+            b"\x00\x00\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00\
+              \x07example\x03com\x00\x00\x1c\x00\x01"
+        );
+
+        assert!(encode_query("", TYPE_A).is_err());
+        assert!(encode_query("example..com", TYPE_A).is_err());
+        assert!(encode_query(&"a".repeat(64), TYPE_A).is_err());
+    }
+
+    #[test]
+    fn answer_decoding() {
+        let mut msg = b"\x00\x00\x81\x80\x00\x01\x00\x02\x00\x00\x00\x00".to_vec();
+        // question
+        msg.extend_from_slice(b"\x03www\x07example\x03com\x00\x00\x01\x00\x01");
+        // CNAME pointing to the question name, then an A record for it
+        msg.extend_from_slice(b"\xc0\x0c\x00\x05\x00\x01\x00\x00\x0e\x10\x00\x02\xc0\x10");
+        msg.extend_from_slice(b"\xc0\x10\x00\x01\x00\x01\x00\x00\x00\x3c\x00\x04\x5d\xb8\xd8\x22");
+
+        assert_eq!(
+            decode_answer(&msg, TYPE_A).unwrap(),
+            Answer {
+                ips: vec![IpAddr::V4(Ipv4Addr::new(93, 184, 216, 34))],
+                ttl: Duration::from_secs(60),
+            }
+        );
+
+        // NXDOMAIN
+        msg[3] = 0x83;
+        assert!(decode_answer(&msg, TYPE_A).unwrap().ips.is_empty());
+
+        // SERVFAIL
+        msg[3] = 0x82;
+        assert!(decode_answer(&msg, TYPE_A).is_err());
+
+        assert!(decode_answer(&msg[..20], TYPE_A).is_err());
+    }
+}
@@ -0,0 +1,412 @@
+//! Egress policy for outbound connections.
+
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    ops::RangeInclusive,
+};
+
+use actix_http::Uri;
+
+use super::{
+    error::ConnectError,
+    proxy::{in_network, parse_network, HostRule},
+};
+
+/// Networks whose addresses are not reachable on the public internet.
+const NON_PUBLIC_V4: &[(Ipv4Addr, u8)] = &[
+    (Ipv4Addr::new(0, 0, 0, 0), 8),       // "this" network
+    (Ipv4Addr::new(10, 0, 0, 0), 8),      // private use
+    (Ipv4Addr::new(100, 64, 0, 0), 10),   // shared address space
+    (Ipv4Addr::new(127, 0, 0, 0), 8),     // loopback
+    (Ipv4Addr::new(169, 254, 0, 0), 16),  // link-local
+    (Ipv4Addr::new(172, 16, 0, 0), 12),   // private use
+    (Ipv4Addr::new(192, 0, 0, 0), 24),    // IETF protocol assignments
+    (Ipv4Addr::new(192, 0, 2, 0), 24),    // documentation
+    (Ipv4Addr::new(192, 168, 0, 0), 16),  // private use
+    (Ipv4Addr::new(198, 18, 0, 0), 15),   // benchmarking
+    (Ipv4Addr::new(198, 51, 100, 0), 24), // documentation
+    (Ipv4Addr::new(203, 0, 113, 0), 24),  // documentation
+    (Ipv4Addr::new(224, 0, 0, 0), 4),     // multicast
+    (Ipv4Addr::new(240, 0, 0, 0), 4),     // reserved and broadcast
+];
+
+/// Networks whose addresses are not reachable on the public internet.
+const NON_PUBLIC_V6: &[(Ipv6Addr, u8)] = &[
+    (Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0), 96), // unspecified, loopback and IPv4-compatible
+    (Ipv6Addr::new(0x100, 0, 0, 0, 0, 0, 0, 0), 64), // discard-only
+    (Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0), 32), // documentation
+    (Ipv6Addr::new(0xfc00, 0, 0, 0, 0, 0, 0, 0), 7), // unique local
+    (Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0), 10), // link-local
+    (Ipv6Addr::new(0xfec0, 0, 0, 0, 0, 0, 0, 0), 10), // site-local
+    (Ipv6Addr::new(0xff00, 0, 0, 0, 0, 0, 0, 0), 8), // multicast
+];
+
+/// Restricts the destinations that a client may connect to.
+///
+/// An egress policy makes it safe to send requests to URLs supplied by users, or read from
+/// untrusted documents, without the requests reaching internal services (server-side request
+/// forgery). Requests to a destination that the policy does not allow fail with
+/// [`ConnectError::Denied`] before a connection is made.
+///
+/// The URI of every request, including the requests that follow redirects, is checked for:
+/// - its scheme, which must be one of the [allowed schemes](Self::schemes);
+/// - its host, which must not be [denied](Self::deny_hosts) and, if any hosts are
+///   [allowed](Self::allow_hosts), must be one of them;
+/// - its port, which must be one of the [allowed ports](Self::ports), if any are set.
+///
+/// The IP addresses that a host name resolves to are then checked, as are IP addresses in URIs
+/// and addresses set with [`ClientRequest::address()`](crate::ClientRequest::address). By
+/// default, only addresses reachable on the public internet are allowed, which excludes the
+/// loopback, private-use (RFC 1918), shared, link-local, unique local, multicast and reserved
+/// networks. IPv4 addresses embedded in IPv6 addresses are checked as IPv4 addresses. Non-public
+/// networks can be [allowed](Self::allow_networks), and any network can be
+/// [denied](Self::deny_networks). Resolved addresses that are not allowed are skipped, and the
+/// addresses that are checked are the ones connected to, so a host name can not resolve to an
+/// allowed address when checked and to another one when connecting (DNS rebinding).
+///
+/// Resolved addresses are only checked when host names are looked up by the connector's
+/// resolver, which is the case for the default connector and one set with
+/// [`Connector::resolver()`](super::Connector::resolver). Custom connectors set with
+/// [`Connector::connector()`](super::Connector::connector) look up host names themselves. The
+/// addresses of host names are also not checked when connections are tunneled through a
+/// [proxy](super::Proxy), which looks them up instead; the proxy itself is always allowed.
+///
+/// # Examples
+/// ```
+/// use awc::{Client, EgressPolicy};
+///
+/// let policy = EgressPolicy::new()
+///     .schemes("https")
+///     .deny_hosts("metadata.google.internal")
+///     .allow_networks("10.20.0.0/16");
+///
+/// let client = Client::builder().egress_policy(policy).finish();
+/// ```
+#[derive(Debug, Clone)]
+pub struct EgressPolicy {
+    schemes: Vec<String>,
+    allowed_hosts: Option<Vec<HostRule>>,
+    denied_hosts: Vec<HostRule>,
+    ports: Option<Vec<RangeInclusive<u16>>>,
+    allowed_networks: Vec<(IpAddr, u8)>,
+    denied_networks: Vec<(IpAddr, u8)>,
+}
+
+impl EgressPolicy {
+    /// Constructs a policy that allows the `http`, `https`, `ws` and `wss` schemes, any host and
+    /// port, and addresses reachable on the public internet.
+    pub fn new() -> Self {
+        Self {
+            schemes: ["http", "https", "ws", "wss"].map(str::to_owned).to_vec(),
+            allowed_hosts: None,
+            denied_hosts: Vec::new(),
+            ports: None,
+            allowed_networks: Vec::new(),
+            denied_networks: Vec::new(),
+        }
+    }
+
+    /// Sets the allowed URI schemes, separated by commas or whitespace.
+    ///
+    /// Replaces the schemes allowed by default, or by a previous call.
+    pub fn schemes(mut self, list: &str) -> Self {
+        self.schemes = entries(list).map(str::to_ascii_lowercase).collect();
+        self
+    }
+
+    /// Only allows the listed hosts, using the syntax of [`Proxy::no_proxy()`].
+    ///
+    /// Domain names match the domain and its subdomains, and IP addresses and networks match URIs
+    /// that contain an IP address within them. Entries can be restricted to a port.
+    ///
+    /// Can be called multiple times to allow more hosts.
+    ///
+    /// # Panics
+    /// Panics if an entry can not be parsed.
+    ///
+    /// [`Proxy::no_proxy()`]: super::Proxy::no_proxy
+    pub fn allow_hosts(mut self, list: &str) -> Self {
+        self.allowed_hosts
+            .get_or_insert_with(Vec::new)
+            .extend(host_rules(list));
+        self
+    }
+
+    /// Denies the listed hosts, using the syntax of [`Proxy::no_proxy()`].
+    ///
+    /// Denied hosts take precedence over [allowed hosts](Self::allow_hosts). Can be called
+    /// multiple times to deny more hosts.
+    ///
+    /// # Panics
+    /// Panics if an entry can not be parsed.
+    ///
+    /// [`Proxy::no_proxy()`]: super::Proxy::no_proxy
+    pub fn deny_hosts(mut self, list: &str) -> Self {
+        self.denied_hosts.extend(host_rules(list));
+        self
+    }
+
+    /// Only allows the listed ports, separated by commas or whitespace.
+    ///
+    /// Each entry is a port, such as `443`, or an inclusive range of ports, such as `8000-8999`.
+    /// URIs without a port use the default port of their scheme. Can be called multiple times to
+    /// allow more ports.
+    ///
+    /// # Panics
+    /// Panics if an entry can not be parsed.
+    pub fn ports(mut self, list: &str) -> Self {
+        let ports = entries(list).map(|entry| {
+            let range = match entry.split_once('-') {
+                Some((start, end)) => start.parse().ok().zip(end.parse().ok()),
+                None => entry.parse().ok().map(|port| (port, port)),
+            };
+
+            let (start, end) = range.unwrap_or_else(|| panic!("invalid port range `{entry}`"));
+            start..=end
+        });
+
+        self.ports.get_or_insert_with(Vec::new).extend(ports);
+        self
+    }
+
+    /// Allows addresses within the listed networks, even if they are not reachable on the public
+    /// internet.
+    ///
+    /// Each entry is an IP address, such as `10.0.0.5`, or an IP network in CIDR notation, such
+    /// as `10.20.0.0/16`, separated by commas or whitespace. Can be called multiple times to allow
+    /// more networks.
+    ///
+    /// # Panics
+    /// Panics if an entry can not be parsed.
+    pub fn allow_networks(mut self, list: &str) -> Self {
+        self.allowed_networks.extend(networks(list));
+        self
+    }
+
+    /// Denies addresses within the listed networks, using the syntax of
+    /// [`allow_networks()`](Self::allow_networks).
+    ///
+    /// Denied networks take precedence over allowed networks. Can be called multiple times to
+    /// deny more networks.
+    ///
+    /// # Panics
+    /// Panics if an entry can not be parsed.
+    pub fn deny_networks(mut self, list: &str) -> Self {
+        self.denied_networks.extend(networks(list));
+        self
+    }
+
+    /// Checks the scheme, host and port of `uri`, and its IP address if it has one.
+    pub(crate) fn check_uri(&self, uri: &Uri) -> Result<(), ConnectError> {
+        let scheme = uri.scheme_str().unwrap_or_default();
+
+        if !self
+            .schemes
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+        {
+            return Err(denied(format!("scheme `{scheme}` is not allowed")));
+        }
+
+        let Some(host) = uri.host() else {
+            return Err(denied("URI has no host".to_owned()));
+        };
+
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let port = uri.port_u16().unwrap_or(match scheme {
+            "https" | "wss" => 443,
+            _ => 80,
+        });
+
+        let allowed = !self
+            .denied_hosts
+            .iter()
+            .any(|rule| rule.matches(host, port))
+            && match &self.allowed_hosts {
+                Some(rules) => rules.iter().any(|rule| rule.matches(host, port)),
+                None => true,
+            };
+
+        if !allowed {
+            return Err(denied(format!("host `{host}` is not allowed")));
+        }
+
+        if let Some(ref ports) = self.ports {
+            if !ports.iter().any(|range| range.contains(&port)) {
+                return Err(denied(format!("port {port} is not allowed")));
+            }
+        }
+
+        match host.parse() {
+            Ok(addr) => self.check_addr(addr),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Checks an IP address that is about to be connected to.
+    pub(crate) fn check_addr(&self, addr: IpAddr) -> Result<(), ConnectError> {
+        if self.allows_addr(addr.to_canonical()) {
+            Ok(())
+        } else {
+            Err(denied(format!("address {addr} is not allowed")))
+        }
+    }
+
+    fn allows_addr(&self, addr: IpAddr) -> bool {
+        let within = |networks: &[(IpAddr, u8)]| {
+            networks
+                .iter()
+                .any(|&(network, prefix)| in_network(addr, network, prefix))
+        };
+
+        if within(&self.denied_networks) {
+            return false;
+        }
+
+        if within(&self.allowed_networks) {
+            return true;
+        }
+
+        let public = match addr {
+            IpAddr::V4(addr) => !NON_PUBLIC_V4
+                .iter()
+                .any(|&(network, prefix)| in_network(addr.into(), network.into(), prefix)),
+            IpAddr::V6(addr) => !NON_PUBLIC_V6
+                .iter()
+                .any(|&(network, prefix)| in_network(addr.into(), network.into(), prefix)),
+        };
+
+        public
+            && match embedded_ipv4(addr) {
+                Some(addr) => self.allows_addr(addr.into()),
+                None => true,
+            }
+    }
+}
+
+impl Default for EgressPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the IPv4 address embedded in a NAT64 (`64:ff9b::/96`) or 6to4 (`2002::/16`) address.
+fn embedded_ipv4(addr: IpAddr) -> Option<Ipv4Addr> {
+    let IpAddr::V6(addr) = addr else {
+        return None;
+    };
+
+    let bits = u128::from(addr);
+
+    match addr.segments() {
+        [0x64, 0xff9b, 0, 0, 0, 0, ..] => Some(Ipv4Addr::from(bits as u32)),
+        [0x2002, ..] => Some(Ipv4Addr::from((bits >> 80) as u32)),
+        _ => None,
+    }
+}
+
+fn denied(reason: String) -> ConnectError {
+    ConnectError::Denied(reason)
+}
+
+pub(super) fn entries(list: &str) -> impl Iterator<Item = &str> {
+    list.split(|c: char| c == ',' || c.is_ascii_whitespace())
+        .filter(|entry| !entry.is_empty())
+}
+
+pub(super) fn host_rules(list: &str) -> impl Iterator<Item = HostRule> + '_ {
+    entries(list).map(|entry| {
+        HostRule::parse(entry).unwrap_or_else(|| panic!("invalid host entry `{entry}`"))
+    })
+}
+
+fn networks(list: &str) -> impl Iterator<Item = (IpAddr, u8)> + '_ {
+    entries(list)
+        .map(|entry| parse_network(entry).unwrap_or_else(|| panic!("invalid network `{entry}`")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(policy: &EgressPolicy, uri: &str) -> bool {
+        policy.check_uri(&uri.parse().unwrap()).is_ok()
+    }
+
+    fn check_addr(policy: &EgressPolicy, addr: &str) -> bool {
+        policy.check_addr(addr.parse().unwrap()).is_ok()
+    }
+
+    #[test]
+    fn default_policy() {
+        let policy = EgressPolicy::new();
+
+        assert!(check(&policy, "https://www.rust-lang.org/"));
+        assert!(check(&policy, "ws://www.rust-lang.org:8080/"));
+        assert!(check(&policy, "http://93.184.216.34/"));
+        assert!(!check(&policy, "ftp://www.rust-lang.org/"));
+        assert!(!check(&policy, "http://127.0.0.1:8080/"));
+        assert!(!check(&policy, "http://[::1]/"));
+
+        for addr in [
+            "10.1.2.3",
+            "172.20.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:192.168.1.1",
+            "64:ff9b::a01:203",
+            "2002:a01:203::1",
+        ] {
+            assert!(!check_addr(&policy, addr), "{addr} should be denied");
+        }
+
+        for addr in ["93.184.216.34", "2606:2800:220:1::1", "64:ff9b::5db8:d822"] {
+            assert!(check_addr(&policy, addr), "{addr} should be allowed");
+        }
+    }
+
+    #[test]
+    fn hosts_and_ports() {
+        let policy = EgressPolicy::new()
+            .schemes("HTTPS")
+            .allow_hosts("example.com, api.example.org:8443")
+            .deny_hosts("internal.example.com")
+            .ports("443 8000-9000");
+
+        assert!(check(&policy, "https://example.com/"));
+        assert!(check(&policy, "https://www.Example.com:8080/"));
+        assert!(check(&policy, "https://api.example.org:8443/"));
+        assert!(!check(&policy, "http://example.com/"));
+        assert!(!check(&policy, "https://api.example.org/"));
+        assert!(!check(&policy, "https://internal.example.com/"));
+        assert!(!check(&policy, "https://db.internal.example.com/"));
+        assert!(!check(&policy, "https://example.net/"));
+        assert!(!check(&policy, "https://example.com:22/"));
+    }
+
+    #[test]
+    fn networks() {
+        let policy = EgressPolicy::new()
+            .allow_networks("10.20.0.0/16 fd00::1")
+            .deny_networks("10.20.30.0/24, 93.184.216.0/24");
+
+        assert!(check_addr(&policy, "10.20.1.1"));
+        assert!(check_addr(&policy, "::ffff:10.20.1.1"));
+        assert!(check_addr(&policy, "fd00::1"));
+        assert!(!check_addr(&policy, "10.21.1.1"));
+        assert!(!check_addr(&policy, "10.20.30.1"));
+        assert!(!check_addr(&policy, "93.184.216.34"));
+        assert!(check(&policy, "http://10.20.1.1/"));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid network")]
+    fn invalid_network() {
+        EgressPolicy::new().allow_networks("10.0.0.0/33");
+    }
+}
@@ -36,6 +36,10 @@ pub enum ConnectError {
     #[display("Timeout while establishing connection")]
     Timeout,
 
+    /// Waiting for a connection from the pool took too long
+    #[display("Timeout while waiting for a connection from the pool")]
+    PoolTimeout,
+
     /// Connector has been disconnected
     #[display("Internal error: connector has been disconnected")]
     Disconnected,
@@ -44,6 +48,24 @@ pub enum ConnectError {
     #[display("Connector received `Connect` method with unresolved host")]
     Unresolved,
 
+    /// Proxy refused to open a tunnel to the target host
+    #[display("Proxy responded to CONNECT request with status {}", _0)]
+    #[from(skip)]
+    Tunnel(http::StatusCode),
+
+    /// Destination is not allowed by the client's [egress policy](crate::EgressPolicy)
+    #[display("Connection denied by egress policy: {}", _0)]
+    #[from(skip)]
+    Denied(String),
+
+    /// [Client identity](crate::ClientIdentity) is for another TLS library than the connector's
+    #[display("Client identity is not supported by the connector's TLS library")]
+    IdentityNotSupported,
+
+    /// Server certificate does not match the [pins](crate::CertificatePins) of its host
+    #[display("Server certificate does not match the pinned public keys")]
+    CertificatePinMismatch,
+
     /// Connection io error
     #[display("{}", _0)]
     Io(io::Error),
@@ -115,9 +137,31 @@ pub enum SendRequestError {
     #[display("Tunnels are not supported for http2 connection")]
     TunnelNotSupported,
 
+    /// Tunnel was returned for a standard HTTP request
+    #[display("Connector returned a tunnel for an HTTP request")]
+    UnexpectedTunnel,
+
+    /// Standard HTTP response was returned for a tunnel request
+    #[display("Connector returned an HTTP response for a tunnel request")]
+    UnexpectedClientResponse,
+
     /// Error sending request body
     Body(BoxError),
 
+    /// Request would have waited too long for the rate limit of its host
+    #[display("Rate limit exceeded for {}", _0)]
+    #[from(skip)]
+    RateLimited(String),
+
+    /// Circuit breaker of the request's host is open
+    #[display("Circuit breaker is open for {}", _0)]
+    #[from(skip)]
+    CircuitOpen(String),
+
+    /// Inbound client that the request was made for disconnected
+    #[display("Request cancelled because the inbound client disconnected")]
+    Cancelled,
+
     /// Other errors that can occur after submitting a request.
     #[display("{:?}: {}", _1, _0)]
     Custom(BoxError, Box<dyn fmt::Debug>),
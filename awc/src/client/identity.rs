@@ -0,0 +1,211 @@
+//! TLS client identities for mutual TLS.
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// TLS configuration that a client presents its certificate with, for servers that require mutual
+/// TLS (mTLS).
+///
+/// A connector presents the client certificate of its own TLS configuration, if any, to every
+/// host. A client identity replaces that configuration for:
+/// - the hosts it is set for with [`Connector::client_identity()`];
+/// - the requests of a child client it is set for with [`ChildClientBuilder::client_identity()`];
+/// - a single request it is set for with [`ClientRequest::client_identity()`], including the
+///   requests that follow its redirects.
+///
+/// The identity of a request takes precedence over the identity of its host. Connections made
+/// with an identity are pooled separately from connections made with other identities, or with
+/// none, so they are never reused by requests that should present another certificate.
+///
+/// An identity wraps a complete TLS configuration of the TLS library used by the connector, which
+/// also decides which servers are trusted. Requests with an identity of another TLS library fail
+/// with [`ConnectError::IdentityNotSupported`]. Connections only use HTTP/2 if the configuration
+/// offers it with ALPN, and their secrets are only logged if the configuration has a key log.
+///
+/// Clones of an identity are the same identity. Construct each identity once and clone it, since
+/// identities constructed separately from the same configuration do not share connections.
+///
+/// # Examples
+/// ```no_run
+/// # #[cfg(feature = "rustls-0_23")]
+/// # fn run(
+/// #     pharmacy_config: tls_rustls_0_23::ClientConfig,
+/// #     lab_config: tls_rustls_0_23::ClientConfig,
+/// # ) {
+/// use std::sync::Arc;
+///
+/// use awc::{Client, ClientIdentity, Connector};
+///
+/// // configurations with the client certificates that each partner requires
+/// let pharmacy = ClientIdentity::rustls_0_23(Arc::new(pharmacy_config));
+/// let lab = ClientIdentity::rustls_0_23(Arc::new(lab_config));
+///
+/// let client = Client::builder()
+///     .connector(Connector::new().client_identity("pharmacy.example.com", pharmacy))
+///     .finish();
+///
+/// // a single request can present another identity
+/// let res = client
+///     .get("https://lab.example.com/results")
+///     .client_identity(lab)
+///     .send();
+/// # }
+/// ```
+///
+/// [`Connector::client_identity()`]: super::Connector::client_identity
+/// [`ChildClientBuilder::client_identity()`]: crate::ChildClientBuilder::client_identity
+/// [`ClientRequest::client_identity()`]: crate::ClientRequest::client_identity
+/// [`ConnectError::IdentityNotSupported`]: super::ConnectError::IdentityNotSupported
+#[derive(Clone)]
+pub struct ClientIdentity {
+    id: u64,
+    #[allow(dead_code)] // only dead when no TLS feature is enabled
+    tls: IdentityTls,
+}
+
+#[derive(Clone)]
+enum IdentityTls {
+    #[cfg(feature = "openssl")]
+    Openssl(actix_tls::connect::openssl::reexports::SslConnector),
+
+    #[cfg(feature = "rustls-0_20")]
+    Rustls020(std::sync::Arc<actix_tls::connect::rustls_0_20::reexports::ClientConfig>),
+
+    #[cfg(feature = "rustls-0_21")]
+    Rustls021(std::sync::Arc<actix_tls::connect::rustls_0_21::reexports::ClientConfig>),
+
+    #[cfg(any(
+        feature = "rustls-0_22-webpki-roots",
+        feature = "rustls-0_22-native-roots",
+    ))]
+    Rustls022(std::sync::Arc<actix_tls::connect::rustls_0_22::reexports::ClientConfig>),
+
+    #[cfg(feature = "rustls-0_23")]
+    Rustls023(std::sync::Arc<actix_tls::connect::rustls_0_23::reexports::ClientConfig>),
+}
+
+impl ClientIdentity {
+    #[allow(dead_code)] // only dead when no TLS feature is enabled
+    fn new(tls: IdentityTls) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            tls,
+        }
+    }
+
+    /// Constructs an identity from an OpenSSL connector.
+    #[cfg(feature = "openssl")]
+    pub fn openssl(connector: actix_tls::connect::openssl::reexports::SslConnector) -> Self {
+        Self::new(IdentityTls::Openssl(connector))
+    }
+
+    /// Constructs an identity from a Rustls v0.20 configuration.
+    #[cfg(feature = "rustls-0_20")]
+    pub fn rustls_0_20(
+        config: std::sync::Arc<actix_tls::connect::rustls_0_20::reexports::ClientConfig>,
+    ) -> Self {
+        Self::new(IdentityTls::Rustls020(config))
+    }
+
+    /// Constructs an identity from a Rustls v0.21 configuration.
+    #[cfg(feature = "rustls-0_21")]
+    pub fn rustls_0_21(
+        config: std::sync::Arc<actix_tls::connect::rustls_0_21::reexports::ClientConfig>,
+    ) -> Self {
+        Self::new(IdentityTls::Rustls021(config))
+    }
+
+    /// Constructs an identity from a Rustls v0.22 configuration.
+    #[cfg(any(
+        feature = "rustls-0_22-webpki-roots",
+        feature = "rustls-0_22-native-roots",
+    ))]
+    pub fn rustls_0_22(
+        config: std::sync::Arc<actix_tls::connect::rustls_0_22::reexports::ClientConfig>,
+    ) -> Self {
+        Self::new(IdentityTls::Rustls022(config))
+    }
+
+    /// Constructs an identity from a Rustls v0.23 configuration.
+    #[cfg(feature = "rustls-0_23")]
+    pub fn rustls_0_23(
+        config: std::sync::Arc<actix_tls::connect::rustls_0_23::reexports::ClientConfig>,
+    ) -> Self {
+        Self::new(IdentityTls::Rustls023(config))
+    }
+
+    /// Returns the ID that connections made with this identity are pooled by.
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    #[cfg(feature = "openssl")]
+    pub(crate) fn openssl_connector(
+        &self,
+    ) -> Option<&actix_tls::connect::openssl::reexports::SslConnector> {
+        match self.tls {
+            IdentityTls::Openssl(ref connector) => Some(connector),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "rustls-0_20")]
+    pub(crate) fn rustls_0_20_config(
+        &self,
+    ) -> Option<&std::sync::Arc<actix_tls::connect::rustls_0_20::reexports::ClientConfig>> {
+        match self.tls {
+            IdentityTls::Rustls020(ref config) => Some(config),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "rustls-0_21")]
+    pub(crate) fn rustls_0_21_config(
+        &self,
+    ) -> Option<&std::sync::Arc<actix_tls::connect::rustls_0_21::reexports::ClientConfig>> {
+        match self.tls {
+            IdentityTls::Rustls021(ref config) => Some(config),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    #[cfg(any(
+        feature = "rustls-0_22-webpki-roots",
+        feature = "rustls-0_22-native-roots",
+    ))]
+    pub(crate) fn rustls_0_22_config(
+        &self,
+    ) -> Option<&std::sync::Arc<actix_tls::connect::rustls_0_22::reexports::ClientConfig>> {
+        match self.tls {
+            IdentityTls::Rustls022(ref config) => Some(config),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "rustls-0_23")]
+    pub(crate) fn rustls_0_23_config(
+        &self,
+    ) -> Option<&std::sync::Arc<actix_tls::connect::rustls_0_23::reexports::ClientConfig>> {
+        match self.tls {
+            IdentityTls::Rustls023(ref config) => Some(config),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Debug for ClientIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientIdentity")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
@@ -1,34 +1,82 @@
 //! HTTP client.
 
-use std::{rc::Rc, time::Duration};
+use std::{
+    future::{poll_fn, Future as _},
+    pin::Pin,
+    rc::Rc,
+    task::Poll,
+    time::Duration,
+};
 
-use actix_http::{error::HttpError, header::HeaderMap, Method, RequestHead, Uri};
+use actix_http::{
+    error::HttpError, header::HeaderMap, uri::Authority, ClientDisconnect, Method, Protocol,
+    RequestHead, Uri,
+};
 use actix_rt::net::TcpStream;
 use actix_service::Service;
 pub use actix_tls::connect::{
     ConnectError as TcpConnectError, ConnectInfo, Connection as TcpConnection,
 };
+use futures_core::future::LocalBoxFuture;
 
-use crate::{ws, BoxConnectorService, ClientBuilder, ClientRequest};
+use self::{pool_monitor::PoolMonitor, scope::ConnectScope};
+use crate::{
+    ws, BoxConnectorService, ChildClientBuilder, ClientBuilder, ClientRequest, ConnectRequest,
+    ConnectResponse,
+};
 
 mod config;
 mod connection;
 mod connector;
+mod doh;
+mod egress;
 mod error;
 mod h1proto;
 mod h2proto;
+mod identity;
+mod pinning;
 mod pool;
+mod pool_monitor;
+mod proxy;
+pub(crate) mod scope;
+#[cfg(unix)]
+mod unix;
 
 pub use self::{
     connection::{Connection, ConnectionIo},
     connector::{Connector, ConnectorService},
+    doh::DohResolver,
+    egress::EgressPolicy,
     error::{ConnectError, FreezeRequestError, InvalidUrl, SendRequestError},
+    identity::ClientIdentity,
+    pinning::CertificatePins,
+    pool_monitor::{HostPoolState, PoolEvent, PoolState},
+    proxy::Proxy,
 };
 
+#[cfg(unix)]
+pub use self::unix::UnixConnector;
+
 #[derive(Clone)]
 pub struct Connect {
     pub uri: Uri,
     pub addr: Option<std::net::SocketAddr>,
+
+    /// Protocol to use on a cleartext connection, or `None` for HTTP/1.1.
+    ///
+    /// Ignored for TLS connections, whose protocol is negotiated with ALPN.
+    pub protocol: Option<Protocol>,
+
+    /// Client identity to present on a TLS connection, or `None` for the connector's default.
+    ///
+    /// Ignored for cleartext connections.
+    pub identity: Option<ClientIdentity>,
+
+    /// Name to send as the TLS server name, and to verify the server's certificate against,
+    /// instead of the host of `uri`.
+    ///
+    /// Ignored for cleartext connections.
+    pub server_name: Option<Authority>,
 }
 
 /// An asynchronous HTTP and WebSocket client.
@@ -60,6 +108,56 @@ pub(crate) struct ClientConfig {
     pub(crate) connector: BoxConnectorService,
     pub(crate) default_headers: Rc<HeaderMap>,
     pub(crate) timeout: Option<Duration>,
+    pub(crate) http2_prior_knowledge: bool,
+    pub(crate) pool: Rc<PoolMonitor>,
+    pub(crate) identity: Option<ClientIdentity>,
+    pub(crate) server_name: Option<Authority>,
+    pub(crate) cancel_on: Option<ClientDisconnect>,
+    pub(crate) max_response_body_size: Option<usize>,
+}
+
+impl ClientConfig {
+    /// Calls the connector with a request, making its connections with the client identity and
+    /// TLS server name of the config, if any.
+    ///
+    /// The call fails with [`SendRequestError::Cancelled`] once the config's inbound client
+    /// disconnects, if it has one.
+    pub(crate) fn connect(
+        &self,
+        req: ConnectRequest,
+    ) -> LocalBoxFuture<'static, Result<ConnectResponse, SendRequestError>> {
+        let uri = match req {
+            ConnectRequest::Client(ref head, ..) => &head.as_ref().uri,
+            ConnectRequest::Tunnel(ref head, ..) => &head.uri,
+        };
+
+        let scope = ConnectScope {
+            identity: self.identity.clone(),
+            server_name: self
+                .server_name
+                .clone()
+                .and_then(|name| Some((uri.host()?.to_owned(), name))),
+        };
+
+        let mut fut = if scope.is_empty() {
+            self.connector.call(req)
+        } else {
+            let fut = scope::scope(&scope, || self.connector.call(req));
+            Box::pin(scope::Scoped::new(scope, fut))
+        };
+
+        let Some(mut disconnect) = self.cancel_on.clone() else {
+            return fut;
+        };
+
+        Box::pin(poll_fn(move |cx| {
+            if Pin::new(&mut disconnect).poll(cx).is_ready() {
+                return Poll::Ready(Err(SendRequestError::Cancelled));
+            }
+
+            fut.as_mut().poll(cx)
+        }))
+    }
 }
 
 impl Default for Client {
@@ -87,6 +185,56 @@ impl Client {
         ClientBuilder::new()
     }
 
+    /// Constructs a client with default settings that sends all requests over the Unix domain
+    /// socket at `path`.
+    ///
+    /// The authority of request URIs is only used for the `Host` header. Use
+    /// [`Connector::unix()`](crate::Connector::unix) to configure the connector further.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # #[actix_rt::main]
+    /// # async fn main() {
+    /// let client = awc::Client::unix("/var/run/docker.sock");
+    /// let res = client.get("http://localhost/version").send().await;
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn unix(path: impl Into<std::path::PathBuf>) -> Client {
+        ClientBuilder::new()
+            .connector(Connector::new().unix(path))
+            .finish()
+    }
+
+    /// Constructs a builder for a child client, which shares this client's connector and
+    /// connection pool.
+    ///
+    /// A child client starts with the default headers and timeout of this client, and has its own
+    /// default headers, authentication and [cookie store](ChildClientBuilder::cookie_store) that
+    /// are not shared with this client or its other children. Child clients are cheap to create,
+    /// so a worker that acts on behalf of many tenants can create one per tenant, or per job,
+    /// without opening new connections or leaking credentials and cookies between tenants.
+    ///
+    /// Child clients send their requests through this client's middleware, including
+    /// redirect handling.
+    ///
+    /// # Examples
+    /// ```
+    /// use awc::{middleware::CookieStore, Client};
+    ///
+    /// let client = Client::new();
+    ///
+    /// let tenant = client
+    ///     .child()
+    ///     .bearer_auth("tenant-token")
+    ///     .add_default_header(("X-Tenant-Id", "clinic-3"))
+    ///     .cookie_store(CookieStore::new())
+    ///     .finish();
+    /// ```
+    pub fn child(&self) -> ChildClientBuilder {
+        ChildClientBuilder::new(self.0.clone())
+    }
+
     /// Construct HTTP request.
     pub fn request<U>(&self, method: Method, url: U) -> ClientRequest
     where
@@ -202,4 +350,39 @@ impl Client {
     pub fn headers(&mut self) -> Option<&mut HeaderMap> {
         Rc::get_mut(&mut self.0.default_headers)
     }
+
+    /// Returns a snapshot of the state of the client's connection pools.
+    ///
+    /// Reports the idle and in-use connections to each host, the age of idle connections, the
+    /// time requests waited to acquire connections, and how many requests are waiting for a
+    /// connection because the pool limit has been reached. To be notified of pool activity as it
+    /// happens, set a callback with [`Connector::on_pool_event()`].
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # #[actix_rt::main]
+    /// # async fn main() {
+    /// let client = awc::Client::default();
+    /// let _res = client.get("http://www.rust-lang.org").send().await;
+    ///
+    /// let state = client.pool_state();
+    ///
+    /// for host in state.hosts() {
+    ///     println!(
+    ///         "{}: {} idle, {} active, {:?} mean wait",
+    ///         host.authority(),
+    ///         host.idle(),
+    ///         host.active(),
+    ///         host.mean_wait(),
+    ///     );
+    /// }
+    ///
+    /// if state.waiting() > 0 {
+    ///     println!("connection pool exhausted");
+    /// }
+    /// # }
+    /// ```
+    pub fn pool_state(&self) -> PoolState {
+        self.0.pool.state()
+    }
 }
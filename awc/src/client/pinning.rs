@@ -0,0 +1,224 @@
+//! Certificate pinning by the public keys of servers.
+
+use std::{fmt, rc::Rc};
+
+use base64::prelude::*;
+use sha2::{Digest as _, Sha256};
+
+use super::ConnectError;
+
+/// Public keys that a server's certificate is expected to have, in addition to being trusted by
+/// the connector's TLS configuration.
+///
+/// Pins are base64 encoded SHA-256 hashes of DER encoded `SubjectPublicKeyInfo` structures, the
+/// format of `pin-sha256` values in HPKP headers. The pin of a server's key can be computed with:
+///
+/// ```sh
+/// openssl x509 -in cert.pem -pubkey -noout \
+///     | openssl pkey -pubin -outform der \
+///     | openssl dgst -sha256 -binary \
+///     | openssl enc -base64
+/// ```
+///
+/// Pins are checked against the certificate that the server identifies itself with, once the TLS
+/// handshake has verified its chain. Connections to servers whose key matches none of the pins fail
+/// with [`ConnectError::CertificatePinMismatch`], unless the pins are
+/// [report-only](Self::report_only). Pinning keys, rather than certificates, keeps pins valid
+/// across certificate renewals that reuse the key. Include the pin of the next key before
+/// rotating keys, so that connections keep working during the rotation.
+///
+/// # Examples
+/// ```
+/// use awc::{CertificatePins, Connector};
+///
+/// let pins = CertificatePins::sha256([
+///     // current key
+///     "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=",
+///     // backup key
+///     "LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=",
+/// ]);
+///
+/// let connector = Connector::new().pin_certificates("pharmacy.example.com", pins);
+/// ```
+///
+/// [`ConnectError::CertificatePinMismatch`]: super::ConnectError::CertificatePinMismatch
+#[derive(Clone)]
+pub struct CertificatePins {
+    hashes: Rc<[[u8; 32]]>,
+    report_only: bool,
+}
+
+impl CertificatePins {
+    /// Constructs pins from base64 encoded SHA-256 hashes of public keys.
+    ///
+    /// # Panics
+    /// Panics if a pin is not a base64 encoded SHA-256 hash.
+    pub fn sha256<I>(pins: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let hashes = pins
+            .into_iter()
+            .map(|pin| {
+                let pin = pin.as_ref();
+
+                BASE64_STANDARD
+                    .decode(pin)
+                    .ok()
+                    .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+                    .unwrap_or_else(|| panic!("invalid SHA-256 certificate pin `{pin}`"))
+            })
+            .collect();
+
+        Self {
+            hashes,
+            report_only: false,
+        }
+    }
+
+    /// Sets whether mismatched keys are only logged, instead of failing the connection.
+    ///
+    /// Report-only pins can be deployed ahead of enforcing them, to find servers whose keys would
+    /// be rejected. Mismatches are logged as warnings with the pin of the server's key.
+    ///
+    /// Disabled by default.
+    pub fn report_only(mut self, report_only: bool) -> Self {
+        self.report_only = report_only;
+        self
+    }
+
+    /// Checks the DER encoded public key of the certificate presented by `host` against the pins.
+    pub(crate) fn verify(&self, host: &str, public_key: Option<&[u8]>) -> Result<(), ConnectError> {
+        let hash = public_key.map(|public_key| <[u8; 32]>::from(Sha256::digest(public_key)));
+
+        if hash.is_some_and(|hash| self.hashes.contains(&hash)) {
+            return Ok(());
+        }
+
+        let pin = hash.map_or_else(|| "none".to_owned(), |hash| BASE64_STANDARD.encode(hash));
+
+        if self.report_only {
+            log::warn!("Certificate of {host} does not match its pins; key pin: {pin}");
+            Ok(())
+        } else {
+            log::debug!("Rejected certificate of {host}; key pin: {pin}");
+            Err(ConnectError::CertificatePinMismatch)
+        }
+    }
+}
+
+impl fmt::Debug for CertificatePins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CertificatePins")
+            .field("pins", &self.hashes.len())
+            .field("report_only", &self.report_only)
+            .finish()
+    }
+}
+
+/// Returns the DER encoded `SubjectPublicKeyInfo` of a DER encoded X.509 certificate.
+#[allow(dead_code)] // only dead when no Rustls feature is enabled
+pub(crate) fn certificate_public_key(cert: &[u8]) -> Option<&[u8]> {
+    const SEQUENCE: u8 = 0x30;
+    const VERSION: u8 = 0xa0;
+
+    let cert = der_contents(cert, SEQUENCE)?;
+    let tbs_cert = der_contents(cert, SEQUENCE)?;
+
+    // version is optional
+    let mut fields = tbs_cert;
+    if fields.first() == Some(&VERSION) {
+        fields = &fields[der_len(fields)?..];
+    }
+
+    // serial number, signature algorithm, issuer, validity, and subject
+    for _ in 0..5 {
+        fields = &fields[der_len(fields)?..];
+    }
+
+    if fields.first() != Some(&SEQUENCE) {
+        return None;
+    }
+
+    Some(&fields[..der_len(fields)?])
+}
+
+/// Returns the contents of the DER element at the start of `buf`, if it has the given `tag`.
+fn der_contents(buf: &[u8], tag: u8) -> Option<&[u8]> {
+    if buf.first() != Some(&tag) {
+        return None;
+    }
+
+    let len = der_len(buf)?;
+    let header_len = der_header_len(buf)?;
+    Some(&buf[header_len..len])
+}
+
+/// Returns the length of the DER element at the start of `buf`, including its header.
+fn der_len(buf: &[u8]) -> Option<usize> {
+    let header_len = der_header_len(buf)?;
+
+    let contents_len = match buf[1] {
+        len @ 0..=0x7f => usize::from(len),
+        _ => buf[2..header_len]
+            .iter()
+            .fold(0, |len, &byte| (len << 8) | usize::from(byte)),
+    };
+
+    let len = header_len.checked_add(contents_len)?;
+    (len <= buf.len()).then_some(len)
+}
+
+/// Returns the length of the header of the DER element at the start of `buf`.
+fn der_header_len(buf: &[u8]) -> Option<usize> {
+    let header_len = match *buf.get(1)? {
+        0..=0x7f => 2,
+        // long form lengths of up to 4 bytes
+        len @ 0x81..=0x84 => 2 + usize::from(len & 0x7f),
+        _ => return None,
+    };
+
+    (header_len <= buf.len()).then_some(header_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_key_of_certificate() {
+        let cert = rcgen::generate_simple_self_signed(["localhost".to_owned()]).unwrap();
+
+        let public_key = certificate_public_key(cert.cert.der()).unwrap();
+        assert_eq!(public_key, cert.key_pair.public_key_der());
+
+        assert!(certificate_public_key(b"").is_none());
+        assert!(certificate_public_key(&cert.cert.der()[..100]).is_none());
+    }
+
+    #[test]
+    fn verify() {
+        let public_key = b"public key".as_slice();
+        let pin = BASE64_STANDARD.encode(Sha256::digest(public_key));
+
+        let pins = CertificatePins::sha256([pin.as_str()]);
+        assert!(pins.verify("localhost", Some(public_key)).is_ok());
+        assert!(matches!(
+            pins.verify("localhost", Some(b"other key".as_slice())),
+            Err(ConnectError::CertificatePinMismatch)
+        ));
+        assert!(pins.verify("localhost", None).is_err());
+
+        let pins = pins.report_only(true);
+        assert!(pins
+            .verify("localhost", Some(b"other key".as_slice()))
+            .is_ok());
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_pin() {
+        CertificatePins::sha256(["not a pin"]);
+    }
+}
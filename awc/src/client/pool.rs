@@ -15,7 +15,7 @@ use std::{
 
 use actix_codec::{AsyncRead, AsyncWrite, ReadBuf};
 use actix_http::Protocol;
-use actix_rt::time::{sleep, Sleep};
+use actix_rt::time::{sleep, timeout, Sleep};
 use actix_service::Service;
 use futures_core::future::LocalBoxFuture;
 use futures_util::FutureExt as _;
@@ -28,18 +28,24 @@ use super::{
     connection::{ConnectionInnerType, ConnectionIo, ConnectionType, H2ConnectionInner},
     error::ConnectError,
     h2proto::handshake,
+    identity::ClientIdentity,
+    pool_monitor::PoolMonitor,
     Connect,
 };
+use crate::timings;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Key {
     authority: Authority,
-}
 
-impl From<Authority> for Key {
-    fn from(authority: Authority) -> Key {
-        Key { authority }
-    }
+    /// Protocol requested for cleartext connections, which are pooled separately per protocol.
+    protocol: Option<Protocol>,
+
+    /// Client identity presented on TLS connections, which are pooled separately per identity.
+    identity: Option<u64>,
+
+    /// TLS server name sent instead of the host, which connections are pooled separately per.
+    server_name: Option<Authority>,
 }
 
 /// Connections pool to reuse I/O per [`Authority`].
@@ -64,14 +70,48 @@ where
     fn new(config: ConnectorConfig) -> Self {
         let permits = Arc::new(Semaphore::new(config.limit));
         let available = RefCell::new(HashMap::new());
+        let host_permits = RefCell::new(HashMap::new());
 
         Self(Rc::new(ConnectionPoolInnerPriv {
             config,
             available,
             permits,
+            host_permits,
         }))
     }
 
+    /// Acquires the permits needed to use a connection to `key`.
+    ///
+    /// The host's permit is acquired first so that requests waiting on a host that has reached
+    /// its limit do not hold permits needed by requests to other hosts.
+    async fn acquire_permits(&self, key: &Key) -> Result<Permits, ConnectError> {
+        let host = match self.host_semaphore(key) {
+            Some(semaphore) => Some(acquire_permit(semaphore).await?),
+            None => None,
+        };
+
+        let pool = acquire_permit(Arc::clone(&self.permits)).await?;
+
+        let total = match self.config.total_permits {
+            Some(ref semaphore) => Some(acquire_permit(Arc::clone(semaphore)).await?),
+            None => None,
+        };
+
+        Ok(Permits { host, pool, total })
+    }
+
+    /// Returns the semaphore limiting the connections to `key`, if there is a per-host limit.
+    fn host_semaphore(&self, key: &Key) -> Option<Arc<Semaphore>> {
+        let limit = self.config.limit_per_host?;
+
+        let mut hosts = self.host_permits.borrow_mut();
+        let semaphore = hosts
+            .entry(key.authority.clone())
+            .or_insert_with(|| Arc::new(Semaphore::new(limit)));
+
+        Some(Arc::clone(semaphore))
+    }
+
     /// Spawns a graceful shutdown task for the underlying I/O with a timeout.
     fn close(&self, conn: ConnectionInnerType<Io>) {
         if let Some(timeout) = self.config.disconnect_timeout {
@@ -115,8 +155,13 @@ where
             self.permits.close();
             std::mem::take(&mut *self.available.borrow_mut())
                 .into_iter()
-                .for_each(|(_, conns)| {
-                    conns.into_iter().for_each(|pooled| self.close(pooled.conn))
+                .for_each(|(key, conns)| {
+                    conns.into_iter().for_each(|pooled| {
+                        self.config
+                            .pool_monitor
+                            .unpooled(&key.authority, pooled.created);
+                        self.close(pooled.conn);
+                    })
                 });
         }
     }
@@ -129,6 +174,7 @@ where
     config: ConnectorConfig,
     available: RefCell<HashMap<Key, VecDeque<PooledConnection<Io>>>>,
     permits: Arc<Semaphore>,
+    host_permits: RefCell<HashMap<Authority, Arc<Semaphore>>>,
 }
 
 impl<S, Io> ConnectionPool<S, Io>
@@ -150,6 +196,11 @@ where
 
         Self { connector, inner }
     }
+
+    /// Returns the monitor of the pool's connections.
+    pub(crate) fn monitor(&self) -> &Rc<PoolMonitor> {
+        &self.inner.config.pool_monitor
+    }
 }
 
 impl<S, Io> Service<Connect> for ConnectionPool<S, Io>
@@ -169,21 +220,31 @@ where
 
         Box::pin(async move {
             let key = if let Some(authority) = req.uri.authority() {
-                authority.clone().into()
+                Key {
+                    authority: authority.clone(),
+                    protocol: req.protocol,
+                    identity: req.identity.as_ref().map(ClientIdentity::id),
+                    server_name: req.server_name.clone(),
+                }
             } else {
                 return Err(ConnectError::Unresolved);
             };
 
-            // acquire an owned permit and carry it with connection
-            let permit = Arc::clone(&inner.permits)
-                .acquire_owned()
-                .await
-                .map_err(|_| {
-                    ConnectError::Io(io::Error::new(
-                        io::ErrorKind::Other,
-                        "failed to acquire semaphore on client connection pool",
-                    ))
-                })?;
+            let started = Instant::now();
+            let monitor = Rc::clone(&inner.config.pool_monitor);
+
+            // acquire owned permits and carry them with connection
+            let permits = {
+                let _waiting = WaitGuard::new(&monitor);
+                let permits = inner.acquire_permits(&key);
+
+                match inner.config.pool_timeout {
+                    Some(dur) => timeout(dur, permits)
+                        .await
+                        .map_err(|_| ConnectError::PoolTimeout)??,
+                    None => permits.await?,
+                }
+            };
 
             let conn = {
                 let mut conn = None;
@@ -195,6 +256,8 @@ where
                     let now = Instant::now();
 
                     while let Some(mut c) = conns.pop_front() {
+                        monitor.unpooled(&key.authority, c.created);
+
                         let config = &inner.config;
                         let idle_dur = now - c.used;
                         let age = now - c.created;
@@ -203,6 +266,7 @@ where
 
                         if conn_ineligible {
                             // drop connections that are too old
+                            monitor.closed(&key.authority);
                             inner.close(c.conn);
                         } else {
                             // check if the connection is still usable
@@ -212,6 +276,7 @@ where
                                     "ConnectionCheckFuture must never yield with Poll::Pending.",
                                 ) {
                                     ConnectionState::Tainted => {
+                                        monitor.closed(&key.authority);
                                         inner.close(c.conn);
                                         continue;
                                     }
@@ -230,13 +295,17 @@ where
                 conn
             };
 
-            // construct acquired. It's used to put Io type back to pool/ close the Io type.
-            // permit is carried with the whole lifecycle of Acquired.
-            let acquired = Acquired { key, inner, permit };
-
             // match the connection and spawn new one if did not get anything.
+            //
+            // acquired is constructed once a connection is available. It's used to put Io type
+            // back to pool/ close the Io type. permit is carried with the whole lifecycle of
+            // Acquired.
             match conn {
-                Some(conn) => Ok(ConnectionType::from_pool(conn.conn, conn.created, acquired)),
+                Some(conn) => {
+                    timings::record_reused();
+                    let acquired = Acquired::new(key, inner, permits, started, true);
+                    Ok(ConnectionType::from_pool(conn.conn, conn.created, acquired))
+                }
                 None => {
                     let (io, proto) = connector.call(req).await?;
 
@@ -244,12 +313,13 @@ where
                     assert!(proto != Protocol::Http3);
 
                     if proto == Protocol::Http1 {
+                        let acquired = Acquired::new(key, inner, permits, started, false);
                         Ok(ConnectionType::from_h1(io, Instant::now(), acquired))
                     } else {
-                        let config = &acquired.inner.config;
-                        let (sender, connection) = handshake(io, config).await?;
-                        let inner = H2ConnectionInner::new(sender, connection);
-                        Ok(ConnectionType::from_h2(inner, Instant::now(), acquired))
+                        let (sender, connection) = handshake(io, &inner.config).await?;
+                        let h2 = H2ConnectionInner::new(sender, connection);
+                        let acquired = Acquired::new(key, inner, permits, started, false);
+                        Ok(ConnectionType::from_h2(h2, Instant::now(), acquired))
                     }
                 }
             }
@@ -257,6 +327,39 @@ where
     }
 }
 
+async fn acquire_permit(semaphore: Arc<Semaphore>) -> Result<OwnedSemaphorePermit, ConnectError> {
+    semaphore.acquire_owned().await.map_err(|_| {
+        ConnectError::Io(io::Error::other(
+            "failed to acquire semaphore on client connection pool",
+        ))
+    })
+}
+
+/// Permits for limiting concurrent in-flight connections.
+struct Permits {
+    host: Option<OwnedSemaphorePermit>,
+    #[allow(dead_code)] // held for the lifetime of the connection
+    pool: OwnedSemaphorePermit,
+    #[allow(dead_code)] // held for the lifetime of the connection
+    total: Option<OwnedSemaphorePermit>,
+}
+
+/// Counts a request as waiting for a pool permit until dropped.
+struct WaitGuard<'a>(&'a PoolMonitor);
+
+impl<'a> WaitGuard<'a> {
+    fn new(monitor: &'a PoolMonitor) -> Self {
+        monitor.wait_started();
+        Self(monitor)
+    }
+}
+
+impl Drop for WaitGuard<'_> {
+    fn drop(&mut self) {
+        self.0.wait_finished();
+    }
+}
+
 /// Type for check the connection and determine if it's usable.
 struct ConnectionCheckFuture<'a, Io> {
     io: &'a mut Io,
@@ -348,13 +451,38 @@ where
     key: Key,
     /// handle to connection pool.
     inner: ConnectionPoolInner<Io>,
-    /// permit for limit concurrent in-flight connection for a Client object.
-    permit: OwnedSemaphorePermit,
+    /// permits for limit concurrent in-flight connection for a Client object.
+    permits: Permits,
+}
+
+impl<Io> Acquired<Io>
+where
+    Io: AsyncWrite + Unpin + 'static,
+{
+    fn new(
+        key: Key,
+        inner: ConnectionPoolInner<Io>,
+        permits: Permits,
+        started: Instant,
+        reused: bool,
+    ) -> Self {
+        inner
+            .config
+            .pool_monitor
+            .acquired(&key.authority, started.elapsed(), reused);
+
+        Acquired {
+            key,
+            inner,
+            permits,
+        }
+    }
 }
 
 impl<Io: ConnectionIo> Acquired<Io> {
     /// Close the IO.
     pub(super) fn close(&self, conn: ConnectionInnerType<Io>) {
+        self.inner.config.pool_monitor.closed(&self.key.authority);
         self.inner.close(conn);
     }
 
@@ -362,6 +490,8 @@ impl<Io: ConnectionIo> Acquired<Io> {
     pub(super) fn release(&self, conn: ConnectionInnerType<Io>, created: Instant) {
         let Acquired { key, inner, .. } = self;
 
+        inner.config.pool_monitor.released(&key.authority, created);
+
         inner
             .available
             .borrow_mut()
@@ -373,7 +503,30 @@ impl<Io: ConnectionIo> Acquired<Io> {
                 used: Instant::now(),
             });
 
-        let _ = &self.permit;
+        let _ = &self.permits;
+    }
+}
+
+impl<Io> Drop for Acquired<Io>
+where
+    Io: AsyncWrite + Unpin + 'static,
+{
+    fn drop(&mut self) {
+        self.inner.config.pool_monitor.finished(&self.key.authority);
+
+        // forget the host's semaphore once no request holds or waits for its permits
+        if let Some(permit) = self.permits.host.take() {
+            drop(permit);
+
+            let mut hosts = self.inner.host_permits.borrow_mut();
+
+            if hosts
+                .get(&self.key.authority)
+                .is_some_and(|semaphore| Arc::strong_count(semaphore) == 1)
+            {
+                hosts.remove(&self.key.authority);
+            }
+        }
     }
 }
 
@@ -384,6 +537,7 @@ mod test {
     use http::Uri;
 
     use super::*;
+    use crate::PoolEvent;
 
     /// A stream type that always returns pending on async read.
     ///
@@ -469,6 +623,9 @@ mod test {
         let req = Connect {
             uri: Uri::from_static("http://localhost"),
             addr: None,
+            protocol: None,
+            identity: None,
+            server_name: None,
         };
 
         let conn = pool.call(req.clone()).await.unwrap();
@@ -492,6 +649,46 @@ mod test {
         assert!(now.elapsed() >= Duration::from_millis(100));
     }
 
+    #[actix_rt::test]
+    async fn test_pool_limit_per_host() {
+        let connector = TestPoolConnector {
+            generated: Rc::new(Cell::new(0)),
+        };
+
+        let config = ConnectorConfig {
+            limit: 2,
+            limit_per_host: Some(1),
+            pool_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+
+        let pool = super::ConnectionPool::new(connector, config);
+
+        let req = |uri| Connect {
+            uri: Uri::from_static(uri),
+            addr: None,
+            protocol: None,
+            identity: None,
+            server_name: None,
+        };
+
+        let conn = pool.call(req("http://slow.example")).await.unwrap();
+
+        // the slow host is at its limit
+        let err = pool.call(req("http://slow.example")).await.err().unwrap();
+        assert!(matches!(err, ConnectError::PoolTimeout));
+
+        // other hosts are not held up by it
+        let other = pool.call(req("http://fast.example")).await.unwrap();
+        release(other);
+
+        drop(conn);
+        assert!(pool.inner.host_permits.borrow().is_empty());
+
+        let conn = pool.call(req("http://slow.example")).await.unwrap();
+        release(conn);
+    }
+
     #[actix_rt::test]
     async fn test_pool_keep_alive() {
         let generated = Rc::new(Cell::new(0));
@@ -509,6 +706,9 @@ mod test {
         let req = Connect {
             uri: Uri::from_static("http://localhost"),
             addr: None,
+            protocol: None,
+            identity: None,
+            server_name: None,
         };
 
         let conn = pool.call(req.clone()).await.unwrap();
@@ -534,6 +734,77 @@ mod test {
         release(conn);
     }
 
+    #[actix_rt::test]
+    async fn test_pool_monitor() {
+        let connector = TestPoolConnector {
+            generated: Rc::new(Cell::new(0)),
+        };
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+
+        let config = ConnectorConfig {
+            limit: 2,
+            pool_monitor: Rc::new(PoolMonitor::new(2, {
+                let events = Rc::clone(&events);
+                Some(Rc::new(move |event: &PoolEvent| {
+                    events.borrow_mut().push(event.clone())
+                }))
+            })),
+            ..Default::default()
+        };
+
+        let pool = super::ConnectionPool::new(connector, config);
+
+        let req = Connect {
+            uri: Uri::from_static("http://localhost"),
+            addr: None,
+            protocol: None,
+            identity: None,
+            server_name: None,
+        };
+
+        let conn1 = pool.call(req.clone()).await.unwrap();
+        let conn2 = pool.call(req.clone()).await.unwrap();
+
+        let state = pool.monitor().state();
+        assert_eq!(state.limit(), 2);
+        assert_eq!(state.active(), 2);
+        assert_eq!(state.idle(), 0);
+
+        // the third request waits for a connection
+        let mut waiting = Box::pin(pool.call(req.clone()));
+        assert!(waiting.as_mut().now_or_never().is_none());
+        assert_eq!(pool.monitor().state().waiting(), 1);
+
+        release(conn1);
+        drop(conn2);
+
+        let conn3 = waiting.await.unwrap();
+
+        let state = pool.monitor().state();
+        assert_eq!(state.waiting(), 0);
+        assert_eq!(state.active(), 1);
+        assert_eq!(state.idle(), 0);
+
+        release(conn3);
+
+        let state = pool.monitor().state();
+        let host = &state.hosts()[0];
+        assert_eq!(host.authority(), "localhost");
+        assert_eq!(host.active(), 0);
+        assert_eq!(host.idle(), 1);
+        assert!(host.oldest_idle().is_some());
+        assert_eq!(host.acquired(), 3);
+
+        let events = events.borrow();
+        assert_eq!(events.len(), 5);
+        assert!(matches!(events[2], PoolEvent::Released { .. }));
+        assert!(matches!(
+            events[3],
+            PoolEvent::Acquired { reused: true, .. }
+        ));
+    }
+
     #[actix_rt::test]
     async fn test_pool_lifetime() {
         let generated = Rc::new(Cell::new(0));
@@ -551,6 +822,9 @@ mod test {
         let req = Connect {
             uri: Uri::from_static("http://localhost"),
             addr: None,
+            protocol: None,
+            identity: None,
+            server_name: None,
         };
 
         let conn = pool.call(req.clone()).await.unwrap();
@@ -590,6 +864,9 @@ mod test {
         let req = Connect {
             uri: Uri::from_static("https://crates.io"),
             addr: None,
+            protocol: None,
+            identity: None,
+            server_name: None,
         };
 
         let conn = pool.call(req.clone()).await.unwrap();
@@ -603,6 +880,9 @@ mod test {
         let req = Connect {
             uri: Uri::from_static("https://google.com"),
             addr: None,
+            protocol: None,
+            identity: None,
+            server_name: None,
         };
 
         let conn = pool.call(req.clone()).await.unwrap();
@@ -627,6 +907,9 @@ mod test {
         let req = Connect {
             uri: Uri::from_static("https://crates.io"),
             addr: None,
+            protocol: None,
+            identity: None,
+            server_name: None,
         };
 
         let conn = pool.call(req.clone()).await.unwrap();
@@ -636,6 +919,9 @@ mod test {
         let req = Connect {
             uri: Uri::from_static("https://google.com"),
             addr: None,
+            protocol: None,
+            identity: None,
+            server_name: None,
         };
         let conn = pool.call(req.clone()).await.unwrap();
         assert_eq!(2, generated_clone.get());
@@ -0,0 +1,282 @@
+//! Connection pool introspection and metrics.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fmt,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use http::uri::Authority;
+
+pub(crate) type PoolEventCallback = dyn Fn(&PoolEvent);
+
+/// Snapshot of the state of a client's connection pools.
+///
+/// Returned by [`Client::pool_state()`](crate::Client::pool_state). Connections to `http` and
+/// `https` targets are pooled separately, and each of the two pools allows up to
+/// [`limit`](Self::limit) connections to be in use at once; requests beyond that wait for a
+/// connection to be released.
+#[derive(Debug, Clone)]
+pub struct PoolState {
+    limit: usize,
+    waiting: usize,
+    hosts: Vec<HostPoolState>,
+}
+
+impl PoolState {
+    /// Returns the maximum number of connections each pool allows to be in use at once.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Returns the number of requests waiting for a connection because the pool limit has been
+    /// reached.
+    ///
+    /// A non-zero value for a sustained period means the pool is exhausted.
+    pub fn waiting(&self) -> usize {
+        self.waiting
+    }
+
+    /// Returns the total number of idle connections.
+    pub fn idle(&self) -> usize {
+        self.hosts.iter().map(HostPoolState::idle).sum()
+    }
+
+    /// Returns the total number of connections in use.
+    pub fn active(&self) -> usize {
+        self.hosts.iter().map(HostPoolState::active).sum()
+    }
+
+    /// Returns the state of the connections to each host, ordered by authority.
+    pub fn hosts(&self) -> &[HostPoolState] {
+        &self.hosts
+    }
+}
+
+/// State of the pooled connections to a host.
+#[derive(Debug, Clone)]
+pub struct HostPoolState {
+    authority: Authority,
+    idle: usize,
+    active: usize,
+    oldest_idle: Option<Duration>,
+    acquired: u64,
+    total_wait: Duration,
+    max_wait: Duration,
+}
+
+impl HostPoolState {
+    /// Returns the authority (host and port) of the connections.
+    pub fn authority(&self) -> &Authority {
+        &self.authority
+    }
+
+    /// Returns the number of idle connections kept for reuse.
+    ///
+    /// Idle connections past their keep-alive or lifetime are counted until the next request to
+    /// the host closes them.
+    pub fn idle(&self) -> usize {
+        self.idle
+    }
+
+    /// Returns the number of connections in use by requests.
+    ///
+    /// Each request multiplexed on an HTTP/2 connection is counted separately.
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// Returns the age of the oldest idle connection, if there are any.
+    pub fn oldest_idle(&self) -> Option<Duration> {
+        self.oldest_idle
+    }
+
+    /// Returns the number of connections acquired for requests so far, new or reused.
+    pub fn acquired(&self) -> u64 {
+        self.acquired
+    }
+
+    /// Returns the mean time requests waited to acquire a connection, including the time taken
+    /// to open new connections.
+    pub fn mean_wait(&self) -> Duration {
+        if self.acquired == 0 {
+            return Duration::ZERO;
+        }
+
+        Duration::from_secs_f64(self.total_wait.as_secs_f64() / self.acquired as f64)
+    }
+
+    /// Returns the longest time a request waited to acquire a connection.
+    pub fn max_wait(&self) -> Duration {
+        self.max_wait
+    }
+}
+
+/// Connection pool event, passed to the callback set with
+/// [`Connector::on_pool_event()`](crate::Connector::on_pool_event).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum PoolEvent {
+    /// A connection was acquired for a request.
+    Acquired {
+        /// Authority of the connection.
+        authority: Authority,
+
+        /// Time the request waited for the connection, including the time taken to open a new
+        /// connection.
+        wait: Duration,
+
+        /// True if an idle connection was reused.
+        reused: bool,
+    },
+
+    /// A connection was returned to the pool to be reused.
+    Released {
+        /// Authority of the connection.
+        authority: Authority,
+    },
+
+    /// A pooled connection was closed, because the server or the request did not allow it to
+    /// be reused, or because it exceeded its keep-alive or lifetime.
+    Closed {
+        /// Authority of the connection.
+        authority: Authority,
+    },
+}
+
+/// Tracks the state of the connection pools of a connector and reports their events.
+#[derive(Default)]
+pub(crate) struct PoolMonitor {
+    limit: usize,
+    hosts: RefCell<HashMap<Authority, HostCounters>>,
+    waiting: Cell<usize>,
+    callback: Option<Rc<PoolEventCallback>>,
+}
+
+#[derive(Debug, Default)]
+struct HostCounters {
+    /// Creation times of the idle connections.
+    idle: Vec<Instant>,
+    active: usize,
+    acquired: u64,
+    total_wait: Duration,
+    max_wait: Duration,
+}
+
+impl PoolMonitor {
+    pub(crate) fn new(limit: usize, callback: Option<Rc<PoolEventCallback>>) -> Self {
+        Self {
+            limit,
+            callback,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn wait_started(&self) {
+        self.waiting.set(self.waiting.get() + 1);
+    }
+
+    pub(crate) fn wait_finished(&self) {
+        self.waiting.set(self.waiting.get().saturating_sub(1));
+    }
+
+    pub(crate) fn acquired(&self, authority: &Authority, wait: Duration, reused: bool) {
+        {
+            let mut hosts = self.hosts.borrow_mut();
+            let host = hosts.entry(authority.clone()).or_default();
+
+            host.active += 1;
+            host.acquired += 1;
+            host.total_wait += wait;
+            host.max_wait = host.max_wait.max(wait);
+        }
+
+        self.emit(|| PoolEvent::Acquired {
+            authority: authority.clone(),
+            wait,
+            reused,
+        });
+    }
+
+    /// Records that a connection that was in use has been let go of, whether it was released,
+    /// closed, or dropped.
+    pub(crate) fn finished(&self, authority: &Authority) {
+        if let Some(host) = self.hosts.borrow_mut().get_mut(authority) {
+            host.active = host.active.saturating_sub(1);
+        }
+    }
+
+    pub(crate) fn released(&self, authority: &Authority, created: Instant) {
+        self.hosts
+            .borrow_mut()
+            .entry(authority.clone())
+            .or_default()
+            .idle
+            .push(created);
+
+        self.emit(|| PoolEvent::Released {
+            authority: authority.clone(),
+        });
+    }
+
+    /// Records that an idle connection has been taken out of the pool.
+    pub(crate) fn unpooled(&self, authority: &Authority, created: Instant) {
+        if let Some(host) = self.hosts.borrow_mut().get_mut(authority) {
+            if let Some(idx) = host.idle.iter().position(|idle| *idle == created) {
+                host.idle.swap_remove(idx);
+            }
+        }
+    }
+
+    pub(crate) fn closed(&self, authority: &Authority) {
+        self.emit(|| PoolEvent::Closed {
+            authority: authority.clone(),
+        });
+    }
+
+    pub(crate) fn state(&self) -> PoolState {
+        let now = Instant::now();
+
+        let mut hosts = self
+            .hosts
+            .borrow()
+            .iter()
+            .map(|(authority, host)| HostPoolState {
+                authority: authority.clone(),
+                idle: host.idle.len(),
+                active: host.active,
+                oldest_idle: host.idle.iter().min().map(|created| now - *created),
+                acquired: host.acquired,
+                total_wait: host.total_wait,
+                max_wait: host.max_wait,
+            })
+            .collect::<Vec<_>>();
+
+        hosts.sort_by(|a, b| a.authority.as_str().cmp(b.authority.as_str()));
+
+        PoolState {
+            limit: self.limit,
+            waiting: self.waiting.get(),
+            hosts,
+        }
+    }
+
+    fn emit(&self, event: impl FnOnce() -> PoolEvent) {
+        if let Some(ref callback) = self.callback {
+            callback(&event());
+        }
+    }
+}
+
+impl fmt::Debug for PoolMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoolMonitor")
+            .field("limit", &self.limit)
+            .field("hosts", &self.hosts)
+            .field("waiting", &self.waiting)
+            .field("callback", &self.callback.is_some())
+            .finish()
+    }
+}
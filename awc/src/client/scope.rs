@@ -0,0 +1,89 @@
+//! Connection settings of requests, passed to the connector while their connections are made.
+
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use http::uri::{Authority, Uri};
+use pin_project_lite::pin_project;
+
+use super::identity::ClientIdentity;
+
+/// Connection settings of a request that override those of its connector.
+#[derive(Clone, Default)]
+pub(crate) struct ConnectScope {
+    /// Client identity to present on TLS connections.
+    pub(crate) identity: Option<ClientIdentity>,
+
+    /// Host of the request, and the name sent to it as the TLS server name.
+    pub(crate) server_name: Option<(String, Authority)>,
+}
+
+impl ConnectScope {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.identity.is_none() && self.server_name.is_none()
+    }
+
+    /// Returns the TLS server name to send to the host of `uri`, if it is overridden.
+    pub(crate) fn server_name(&self, uri: &Uri) -> Option<Authority> {
+        let (host, name) = self.server_name.as_ref()?;
+        (uri.host() == Some(host.as_str())).then(|| name.clone())
+    }
+}
+
+thread_local! {
+    /// Connection settings of the request whose connection is currently being made.
+    static CURRENT: RefCell<ConnectScope> = const {
+        RefCell::new(ConnectScope {
+            identity: None,
+            server_name: None,
+        })
+    };
+}
+
+/// Returns the connection settings of the request whose connection is currently being made.
+pub(crate) fn current() -> ConnectScope {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Runs `f` with `scope` as the connection settings of the connections it makes.
+pub(crate) fn scope<R>(scope: &ConnectScope, f: impl FnOnce() -> R) -> R {
+    struct Restore(ConnectScope);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CURRENT.with(|current| *current.borrow_mut() = std::mem::take(&mut self.0));
+        }
+    }
+
+    let _restore = Restore(CURRENT.with(|current| current.replace(scope.clone())));
+    f()
+}
+
+pin_project! {
+    /// Future that makes the connections of a request, and of the requests following its
+    /// redirects, with its connection settings.
+    pub(crate) struct Scoped<Fut> {
+        scope: ConnectScope,
+        #[pin]
+        fut: Fut,
+    }
+}
+
+impl<Fut> Scoped<Fut> {
+    pub(crate) fn new(scope: ConnectScope, fut: Fut) -> Self {
+        Self { scope, fut }
+    }
+}
+
+impl<Fut: Future> Future for Scoped<Fut> {
+    type Output = Fut::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        scope(this.scope, || this.fut.poll(cx))
+    }
+}
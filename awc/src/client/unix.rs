@@ -0,0 +1,54 @@
+//! Unix domain socket connector.
+
+use std::{path::PathBuf, rc::Rc};
+
+use actix_rt::net::UnixStream;
+use actix_service::Service;
+use actix_tls::connect::{
+    ConnectError as TcpConnectError, ConnectInfo, Connection as TcpConnection,
+};
+use futures_core::future::LocalBoxFuture;
+use http::Uri;
+
+/// Connector service that opens every connection to the same Unix domain socket.
+///
+/// The authority of request URIs is not used to connect; it is only sent in the `Host` header and
+/// used to key pooled connections. Select it with [`Connector::unix()`] or construct a client that
+/// uses it with [`Client::unix()`].
+///
+/// [`Connector::unix()`]: crate::Connector::unix
+/// [`Client::unix()`]: crate::Client::unix
+#[derive(Debug, Clone)]
+pub struct UnixConnector {
+    path: Rc<PathBuf>,
+}
+
+impl UnixConnector {
+    /// Constructs a connector for the socket at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Rc::new(path.into()),
+        }
+    }
+}
+
+impl Service<ConnectInfo<Uri>> for UnixConnector {
+    type Response = TcpConnection<Uri, UnixStream>;
+    type Error = TcpConnectError;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::always_ready!();
+
+    fn call(&self, req: ConnectInfo<Uri>) -> Self::Future {
+        let path = Rc::clone(&self.path);
+        let uri = req.request().clone();
+
+        Box::pin(async move {
+            let io = UnixStream::connect(&*path)
+                .await
+                .map_err(TcpConnectError::Io)?;
+
+            Ok(TcpConnection::new(uri, io))
+        })
+    }
+}
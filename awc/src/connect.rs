@@ -7,13 +7,21 @@ use std::{
 };
 
 use actix_codec::Framed;
-use actix_http::{h1::ClientCodec, Payload, RequestHead, RequestHeadType, ResponseHead};
+use actix_http::{
+    h1::ClientCodec,
+    header::{HeaderMap, PROXY_AUTHORIZATION},
+    Payload, Protocol, RequestHead, RequestHeadType, ResponseHead, Version,
+};
 use actix_service::Service;
 use futures_core::{future::LocalBoxFuture, ready};
 
 use crate::{
     any_body::AnyBody,
-    client::{Connect as ClientConnect, ConnectError, Connection, ConnectionIo, SendRequestError},
+    client::{
+        scope, Connect as ClientConnect, ConnectError, Connection, ConnectionIo, EgressPolicy,
+        Proxy, SendRequestError,
+    },
+    timings::{Recorder, RequestTimer, TraceConfig},
     ClientResponse,
 };
 
@@ -53,10 +61,35 @@ pub enum ConnectResponse {
 }
 
 impl ConnectResponse {
+    /// Unwraps type into HTTP response.
+    ///
+    /// # Errors
+    /// Returns [`SendRequestError::UnexpectedTunnel`] if enum variant is not `Client`.
+    pub fn try_into_client_response(self) -> Result<ClientResponse, SendRequestError> {
+        match self {
+            ConnectResponse::Client(res) => Ok(res),
+            ConnectResponse::Tunnel(..) => Err(SendRequestError::UnexpectedTunnel),
+        }
+    }
+
+    /// Unwraps type into WebSocket tunnel response.
+    ///
+    /// # Errors
+    /// Returns [`SendRequestError::UnexpectedClientResponse`] if enum variant is not `Tunnel`.
+    pub fn try_into_tunnel_response(
+        self,
+    ) -> Result<(ResponseHead, Framed<BoxedSocket, ClientCodec>), SendRequestError> {
+        match self {
+            ConnectResponse::Tunnel(head, framed) => Ok((head, framed)),
+            ConnectResponse::Client(_) => Err(SendRequestError::UnexpectedClientResponse),
+        }
+    }
+
     /// Unwraps type into HTTP response.
     ///
     /// # Panics
     /// Panics if enum variant is not `Client`.
+    #[deprecated(since = "3.6.0", note = "Prefer `try_into_client_response()`.")]
     pub fn into_client_response(self) -> ClientResponse {
         match self {
             ConnectResponse::Client(res) => res,
@@ -70,6 +103,7 @@ impl ConnectResponse {
     ///
     /// # Panics
     /// Panics if enum variant is not `Tunnel`.
+    #[deprecated(since = "3.6.0", note = "Prefer `try_into_tunnel_response()`.")]
     pub fn into_tunnel_response(self) -> (ResponseHead, Framed<BoxedSocket, ClientCodec>) {
         match self {
             ConnectResponse::Tunnel(head, framed) => (head, framed),
@@ -82,11 +116,34 @@ impl ConnectResponse {
 
 pub struct DefaultConnector<S> {
     connector: S,
+    proxy: Option<Proxy>,
+    egress: Option<Rc<EgressPolicy>>,
+    trace: TraceConfig,
 }
 
 impl<S> DefaultConnector<S> {
     pub(crate) fn new(connector: S) -> Self {
-        Self { connector }
+        Self {
+            connector,
+            proxy: None,
+            egress: None,
+            trace: TraceConfig::default(),
+        }
+    }
+
+    pub(crate) fn proxy(mut self, proxy: Option<Proxy>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    pub(crate) fn egress_policy(mut self, egress: Option<Rc<EgressPolicy>>) -> Self {
+        self.egress = egress;
+        self
+    }
+
+    pub(crate) fn trace(mut self, trace: TraceConfig) -> Self {
+        self.trace = trace;
+        self
     }
 }
 
@@ -101,22 +158,92 @@ where
 
     actix_service::forward_ready!(connector);
 
-    fn call(&self, req: ConnectRequest) -> Self::Future {
-        // connect to the host
-        let fut = match req {
-            ConnectRequest::Client(ref head, .., addr) => self.connector.call(ClientConnect {
-                uri: head.as_ref().uri.clone(),
-                addr,
-            }),
-            ConnectRequest::Tunnel(ref head, addr) => self.connector.call(ClientConnect {
-                uri: head.uri.clone(),
-                addr,
-            }),
+    fn call(&self, mut req: ConnectRequest) -> Self::Future {
+        // the target is checked here, as requests to a proxy are only connected to the proxy
+        if let Some(ref egress) = self.egress {
+            let uri = match req {
+                ConnectRequest::Client(ref head, ..) => &head.as_ref().uri,
+                ConnectRequest::Tunnel(ref head, _) => &head.uri,
+            };
+
+            if let Err(err) = egress.check_uri(uri) {
+                return ConnectRequestFuture::Denied { err: Some(err) };
+            }
+        }
+
+        let mut proxy_uri = None;
+
+        // plain requests are sent to the proxy, which is told the target in the request line;
+        // everything else is tunneled to the target by the connector
+        if let (ConnectRequest::Client(head, ..), Some(proxy)) = (&mut req, &self.proxy) {
+            let uri = &head.as_ref().uri;
+
+            if uri.scheme_str() == Some("http") && proxy.tunnels(uri) {
+                forward_to_proxy(head, proxy);
+                proxy_uri = Some(proxy.uri().clone());
+            }
+        }
+
+        let (head, addr) = match req {
+            ConnectRequest::Client(ref head, .., addr) => (head.as_ref(), addr),
+            ConnectRequest::Tunnel(ref head, addr) => (head, addr),
         };
 
+        let recorder = Recorder::new(head, &self.trace);
+
+        let connect = match proxy_uri {
+            Some(uri) => ClientConnect {
+                uri,
+                addr: None,
+                protocol: None,
+                identity: None,
+                server_name: None,
+            },
+            None => {
+                let scope = scope::current();
+
+                ClientConnect {
+                    uri: head.uri.clone(),
+                    addr,
+                    protocol: (head.version == Version::HTTP_2).then_some(Protocol::Http2),
+                    server_name: scope.server_name(&head.uri),
+                    identity: scope.identity,
+                }
+            }
+        };
+
+        // connect to the host
+        let fut = recorder.scope(|| self.connector.call(connect));
+
         ConnectRequestFuture::Connection {
             fut,
             req: Some(req),
+            recorder,
+        }
+    }
+}
+
+/// Prepares a plain request head to be sent to a forward proxy.
+fn forward_to_proxy(head: &mut RequestHeadType, proxy: &Proxy) {
+    let authorization = proxy.authorization().cloned();
+
+    match head {
+        RequestHeadType::Owned(head) => {
+            head.set_absolute_form(true);
+
+            if let Some(authorization) = authorization {
+                head.headers.insert(PROXY_AUTHORIZATION, authorization);
+            }
+        }
+
+        RequestHeadType::Rc(head, extra_headers) => {
+            Rc::make_mut(head).set_absolute_form(true);
+
+            if let Some(authorization) = authorization {
+                extra_headers
+                    .get_or_insert_with(HeaderMap::new)
+                    .insert(PROXY_AUTHORIZATION, authorization);
+            }
         }
     }
 }
@@ -130,16 +257,21 @@ pin_project_lite::pin_project! {
         Connection {
             #[pin]
             fut: Fut,
-            req: Option<ConnectRequest>
+            req: Option<ConnectRequest>,
+            recorder: Rc<Recorder>,
         },
         Client {
-            fut: LocalBoxFuture<'static, Result<(ResponseHead, Payload), SendRequestError>>
+            fut: LocalBoxFuture<'static, Result<(ResponseHead, Payload), SendRequestError>>,
+            timer: Option<RequestTimer>,
         },
         Tunnel {
             fut: LocalBoxFuture<
                 'static,
                 Result<(ResponseHead, Framed<Connection<Io>, ClientCodec>), SendRequestError>,
             >,
+        },
+        Denied {
+            err: Option<ConnectError>,
         }
     }
 }
@@ -153,8 +285,10 @@ where
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         match self.as_mut().project() {
-            ConnectRequestProj::Connection { fut, req } => {
-                let connection = ready!(fut.poll(cx))?;
+            ConnectRequestProj::Connection { fut, req, recorder } => {
+                // connection phases are timed by the connector services polled in this scope
+                let connection = ready!(recorder.scope(|| fut.poll(cx)))?;
+                let timer = recorder.start_request();
                 let req = req.take().unwrap();
 
                 match req {
@@ -162,6 +296,7 @@ where
                         // send request
                         let fut = ConnectRequestFuture::Client {
                             fut: connection.send_request(head, body),
+                            timer: Some(timer),
                         };
 
                         self.set(fut);
@@ -180,11 +315,17 @@ where
                 self.poll(cx)
             }
 
-            ConnectRequestProj::Client { fut } => {
+            ConnectRequestProj::Client { fut, timer } => {
                 let (head, payload) = ready!(fut.as_mut().poll(cx))?;
-                Poll::Ready(Ok(ConnectResponse::Client(ClientResponse::new(
-                    head, payload,
-                ))))
+                let timer = timer
+                    .take()
+                    .expect("ConnectRequestFuture polled after complete");
+                let payload = timer.trace_response(&head, payload);
+
+                let mut res = ClientResponse::new(head, payload);
+                res.timings = Some(timer.finish());
+
+                Poll::Ready(Ok(ConnectResponse::Client(res)))
             }
 
             ConnectRequestProj::Tunnel { fut } => {
@@ -192,6 +333,11 @@ where
                 let framed = framed.into_map_io(|io| Box::new(io) as _);
                 Poll::Ready(Ok(ConnectResponse::Tunnel(head, framed)))
             }
+
+            ConnectRequestProj::Denied { err } => Poll::Ready(Err(err
+                .take()
+                .expect("ConnectRequestFuture polled after complete")
+                .into())),
         }
     }
 }
@@ -1,12 +1,15 @@
 //! HTTP client errors
 
 // TODO: figure out how best to expose http::Error vs actix_http::Error
+use std::io;
+
 pub use actix_http::{
     error::{HttpError, PayloadError},
     header::HeaderValue,
     ws::{HandshakeError as WsHandshakeError, ProtocolError as WsProtocolError},
     StatusCode,
 };
+use bytes::Bytes;
 use derive_more::derive::{Display, From};
 use serde_json::error::Error as JsonError;
 
@@ -79,3 +82,84 @@ pub enum JsonPayloadError {
 }
 
 impl std::error::Error for JsonPayloadError {}
+
+/// A set of errors that can occur while saving a response body
+#[derive(Debug, Display, From)]
+pub enum SaveError {
+    /// Payload error
+    #[display("Error that occur during reading payload: {}", _0)]
+    Payload(PayloadError),
+
+    /// Error writing to the destination
+    #[display("Error writing response body: {}", _0)]
+    Io(io::Error),
+
+    /// Body does not match the expected digest
+    #[display("Response body does not match the expected digest")]
+    Integrity,
+
+    /// Range request for resuming the download could not be sent
+    #[display("Failed to resume download: {}", _0)]
+    Resume(SendRequestError),
+
+    /// Server did not respond to the range request with the rest of the body
+    #[display("Server responded to range request with status {}", _0)]
+    #[from(skip)]
+    ResumeRejected(StatusCode),
+}
+
+impl std::error::Error for SaveError {}
+
+/// A set of errors that can occur while sending a chunk of an upload
+#[derive(Debug, Display, From)]
+pub enum UploadError {
+    /// Error reading the file
+    #[display("Error reading upload file: {}", _0)]
+    Io(io::Error),
+
+    /// File length differs from when the upload started
+    #[display("Upload file has changed since the upload started")]
+    FileChanged,
+
+    /// Request for the chunk could not be sent
+    #[display("Failed to send upload chunk: {}", _0)]
+    Send(SendRequestError),
+
+    /// Server did not accept the chunk
+    #[display("Server responded to upload chunk with status {}", _0)]
+    #[from(skip)]
+    Rejected(StatusCode),
+}
+
+impl std::error::Error for UploadError {}
+
+/// A set of errors that can occur while calling a method of a generated API client
+///
+/// Error types of the methods of clients generated by the `api_client` macro must implement
+/// `From<ApiError>`.
+#[derive(Debug, Display, From)]
+#[non_exhaustive]
+pub enum ApiError {
+    /// Query string could not be serialized
+    #[display("Failed to serialize query string: {}", _0)]
+    Query(serde_urlencoded::ser::Error),
+
+    /// Request could not be sent
+    #[display("{}", _0)]
+    Send(SendRequestError),
+
+    /// Server responded with a status other than success, with the response body
+    #[display("Server responded with status {}", _0)]
+    #[from(skip)]
+    Status(StatusCode, Bytes),
+
+    /// Payload error
+    #[display("Error that occur during reading payload: {}", _0)]
+    Payload(PayloadError),
+
+    /// Response body could not be parsed as JSON
+    #[display("{}", _0)]
+    Json(JsonPayloadError),
+}
+
+impl std::error::Error for ApiError {}
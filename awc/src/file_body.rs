@@ -0,0 +1,273 @@
+use std::{
+    fs::File,
+    future::Future as _,
+    io,
+    ops::{Bound, RangeBounds},
+    path::Path,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+
+use actix_http::body::{BodySize, MessageBody, SendFile};
+use actix_rt::time::{sleep_until, Sleep};
+use bytes::Bytes;
+
+/// Request body that streams a region of a file from disk.
+///
+/// The file is read in chunks on the blocking thread pool as the connection accepts them, so
+/// large files are uploaded without being read into memory. The body has a known size, so requests
+/// are sent with a `Content-Length` header.
+///
+/// [`ClientRequest::send_file()`](crate::ClientRequest::send_file) covers the common case. Use this
+/// type directly with [`send_body()`](crate::ClientRequest::send_body) to limit the upload rate.
+///
+/// # Examples
+/// ```no_run
+/// # use awc::{Client, FileBody};
+/// # #[actix_rt::main]
+/// # async fn async_ctx() -> Result<(), Box<dyn std::error::Error>> {
+/// let body = FileBody::open("study.dcm", ..)?.rate_limit(1024 * 1024);
+///
+/// let res = Client::default()
+///     .put("https://example.com/studies/1")
+///     .content_type(body.content_type().as_ref())
+///     .send_body(body)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FileBody {
+    file: SendFile,
+    content_type: mime::Mime,
+    throttle: Option<Throttle>,
+}
+
+impl FileBody {
+    /// Opens the file at `path` and constructs a body that sends the given byte `range` of it.
+    ///
+    /// Pass `..` to send the whole file. The `Content-Type` of the body is guessed from the file
+    /// extension; see [`content_type()`](Self::content_type).
+    ///
+    /// # Errors
+    /// Returns an error if the file could not be opened, or if `range` does not lie within it.
+    pub fn open(path: impl AsRef<Path>, range: impl RangeBounds<u64>) -> io::Result<Self> {
+        let path = path.as_ref();
+
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.saturating_add(1),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => len,
+        };
+
+        if start > end || end > len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("range {start}..{end} is out of bounds for file of length {len}"),
+            ));
+        }
+
+        Ok(Self {
+            file: SendFile::new(file, start, end - start),
+            content_type: mime_guess::from_path(path).first_or_octet_stream(),
+            throttle: None,
+        })
+    }
+
+    /// Limits the rate at which the body is sent to `bytes_per_sec`.
+    ///
+    /// # Panics
+    /// Panics if `bytes_per_sec` is zero.
+    pub fn rate_limit(mut self, bytes_per_sec: u64) -> Self {
+        assert!(bytes_per_sec > 0, "upload rate limit must be non-zero");
+        self.throttle = Some(Throttle::new(bytes_per_sec));
+        self
+    }
+
+    /// Returns the media type guessed from the file extension.
+    ///
+    /// Files with unknown extensions are `application/octet-stream`.
+    pub fn content_type(&self) -> &mime::Mime {
+        &self.content_type
+    }
+}
+
+impl MessageBody for FileBody {
+    type Error = io::Error;
+
+    fn size(&self) -> BodySize {
+        let pending = self
+            .throttle
+            .as_ref()
+            .map_or(0, |throttle| throttle.pending.len());
+        BodySize::Sized(self.file.len() + pending as u64)
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.get_mut();
+
+        let Some(throttle) = &mut this.throttle else {
+            return Pin::new(&mut this.file).poll_next(cx);
+        };
+
+        ready!(throttle.poll_ready(cx));
+
+        if throttle.pending.is_empty() {
+            match ready!(Pin::new(&mut this.file).poll_next(cx)) {
+                Some(Ok(chunk)) => throttle.pending = chunk,
+                res => return Poll::Ready(res),
+            }
+        }
+
+        Poll::Ready(Some(Ok(throttle.take())))
+    }
+}
+
+/// Paces chunks so the average rate since the first chunk stays within the limit.
+#[derive(Debug)]
+struct Throttle {
+    bytes_per_sec: u64,
+    started: Option<Instant>,
+    sent: u64,
+    /// Rest of the last chunk read from the file.
+    pending: Bytes,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl Throttle {
+    fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec,
+            started: None,
+            sent: 0,
+            pending: Bytes::new(),
+            sleep: None,
+        }
+    }
+
+    /// Resolves once the bytes sent so far are within the rate limit.
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let started = *self.started.get_or_insert_with(Instant::now);
+        let deadline =
+            started + Duration::from_secs_f64(self.sent as f64 / self.bytes_per_sec as f64);
+
+        if deadline <= Instant::now() {
+            self.sleep = None;
+            return Poll::Ready(());
+        }
+
+        match &mut self.sleep {
+            Some(sleep) => sleep.as_mut().reset(deadline.into()),
+            None => self.sleep = Some(Box::pin(sleep_until(deadline.into()))),
+        }
+
+        ready!(self.sleep.as_mut().unwrap().as_mut().poll(cx));
+        self.sleep = None;
+        Poll::Ready(())
+    }
+
+    /// Splits off the next chunk to send, sized to about a tenth of a second's worth of bytes.
+    fn take(&mut self) -> Bytes {
+        let max = (self.bytes_per_sec / 10).clamp(1, usize::MAX as u64) as usize;
+        let chunk = self.pending.split_to(self.pending.len().min(max));
+        self.sent += chunk.len() as u64;
+        chunk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write as _,
+        path::PathBuf,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use actix_http::body::to_bytes;
+
+    use super::*;
+
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(ext: &str, contents: &[u8]) -> Self {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+            let path = std::env::temp_dir().join(format!(
+                "awc-file-body-{}-{}.{ext}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+
+            File::create(&path).unwrap().write_all(contents).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn ranges() {
+        let file = TempFile::new("txt", b"hello world");
+
+        let body = FileBody::open(&file.0, ..).unwrap();
+        assert_eq!(body.content_type(), &mime::TEXT_PLAIN);
+        assert_eq!(body.size(), BodySize::Sized(11));
+        assert_eq!(to_bytes(body).await.unwrap(), "hello world");
+
+        let body = FileBody::open(&file.0, 6..).unwrap();
+        assert_eq!(to_bytes(body).await.unwrap(), "world");
+
+        let body = FileBody::open(&file.0, 2..=4).unwrap();
+        assert_eq!(to_bytes(body).await.unwrap(), "llo");
+
+        let body = FileBody::open(&file.0, 11..).unwrap();
+        assert_eq!(to_bytes(body).await.unwrap(), "");
+
+        let err = FileBody::open(&file.0, 5..12).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        #[allow(clippy::reversed_empty_ranges)]
+        let err = FileBody::open(&file.0, 5..4).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[actix_rt::test]
+    async fn unknown_extension() {
+        let file = TempFile::new("unknown-ext", b"");
+        let body = FileBody::open(&file.0, ..).unwrap();
+        assert_eq!(body.content_type(), &mime::APPLICATION_OCTET_STREAM);
+    }
+
+    #[actix_rt::test]
+    async fn rate_limit() {
+        let contents = vec![b'a'; 3000];
+        let file = TempFile::new("bin", &contents);
+
+        let body = FileBody::open(&file.0, ..).unwrap().rate_limit(10_000);
+        assert_eq!(body.size(), BodySize::Sized(3000));
+
+        let start = Instant::now();
+        assert_eq!(to_bytes(body).await.unwrap(), contents);
+
+        // the last 1000-byte chunk is sent 200ms after the first
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+}
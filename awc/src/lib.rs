@@ -111,22 +111,34 @@
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 
 pub use actix_http::body;
+pub use actix_tls::connect::Resolve;
 #[cfg(feature = "cookies")]
 pub use cookie;
 
 mod any_body;
+mod api_client;
 mod builder;
 mod client;
 mod connect;
 pub mod error;
+mod file_body;
 mod frozen;
 pub mod middleware;
 mod request;
 mod responses;
+mod resume;
 mod sender;
 pub mod test;
+mod timings;
 pub mod ws;
 
+#[doc(hidden)]
+pub mod __private {
+    //! Helpers used by code generated by the `api_client` macro. Not public API.
+
+    pub use crate::api_client::{bytes, empty, json, path_segment};
+}
+
 pub mod http {
     //! Various HTTP related types.
 
@@ -135,14 +147,26 @@ pub mod http {
 }
 
 #[allow(deprecated)]
-pub use self::responses::{ClientResponse, JsonBody, MessageBody, ResponseBody};
+pub use self::responses::{ClientResponse, JsonBody, MessageBody, ResponseBody, SaveBody};
 pub use self::{
-    builder::ClientBuilder,
-    client::{Client, Connect, Connector},
+    builder::{ChildClientBuilder, ClientBuilder},
+    client::{
+        CertificatePins, Client, ClientIdentity, Connect, Connector, DohResolver, EgressPolicy,
+        HostPoolState, PoolEvent, PoolState, Proxy,
+    },
     connect::{BoxConnectorService, BoxedSocket, ConnectRequest, ConnectResponse},
+    file_body::FileBody,
     frozen::{FrozenClientRequest, FrozenSendBuilder},
     request::ClientRequest,
+    resume::{DownloadToken, UploadToken},
     sender::SendClientRequest,
+    timings::Timings,
 };
 
+#[cfg(unix)]
+pub use self::client::UnixConnector;
+
+#[cfg(feature = "macros")]
+pub use awc_codegen::api_client;
+
 pub(crate) type BoxError = Box<dyn std::error::Error>;
@@ -0,0 +1,811 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+    time::{Duration, SystemTime},
+};
+
+use actix_http::{
+    error::PayloadError,
+    h1,
+    header::{self, HeaderMap, HeaderName, HeaderValue, HttpDate},
+    BoxedPayloadStream, Method, Payload, RequestHeadType, ResponseHead, StatusCode, Uri, Version,
+};
+use actix_service::Service;
+use bytes::{Bytes, BytesMut};
+use futures_core::{future::LocalBoxFuture, ready, Stream};
+use pin_project_lite::pin_project;
+
+use super::Transform;
+use crate::{
+    client::SendRequestError,
+    connect::{ConnectRequest, ConnectResponse},
+    ClientResponse,
+};
+
+/// Storage of the responses cached by the [`Cache`] middleware.
+///
+/// Responses are stored under a key derived from the request method and URI. Only the most
+/// recently stored variant of a resource is kept for each key; the [`Vary`](header::VARY) request
+/// headers it was selected with are stored along with it.
+///
+/// Storage failures should not fail requests, so implementations backed by an external database
+/// should log their errors and behave as if the key was not stored.
+pub trait CacheStorage: 'static {
+    /// Returns the response stored under `key`, if any.
+    fn get(&self, key: &str) -> LocalBoxFuture<'static, Option<CachedResponse>>;
+
+    /// Stores `response` under `key`, replacing any response stored before.
+    fn put(&self, key: &str, response: CachedResponse) -> LocalBoxFuture<'static, ()>;
+
+    /// Removes the response stored under `key`, if any.
+    fn remove(&self, key: &str) -> LocalBoxFuture<'static, ()>;
+}
+
+/// Response stored by a [`CacheStorage`].
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    /// Status code of the response.
+    pub status: StatusCode,
+
+    /// HTTP version of the response.
+    pub version: Version,
+
+    /// Response headers.
+    pub headers: HeaderMap,
+
+    /// Response body.
+    pub body: Bytes,
+
+    /// Values of the request headers named by the response's `Vary` header.
+    pub vary: Vec<(HeaderName, Option<HeaderValue>)>,
+
+    /// Time at which the response was received or last revalidated.
+    pub response_time: SystemTime,
+}
+
+impl CachedResponse {
+    /// Returns true if the request headers select this variant of the resource.
+    fn matches(&self, req_headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| req_headers.get(name) == value.as_ref())
+    }
+
+    /// Returns the age of the response at `now`, per RFC 7234 §4.2.3.
+    fn age(&self, now: SystemTime) -> Duration {
+        let age_value = self
+            .headers
+            .get(header::AGE)
+            .and_then(|age| age.to_str().ok()?.parse().ok())
+            .map_or(Duration::ZERO, Duration::from_secs);
+
+        let apparent_age = header_date(&self.headers, header::DATE)
+            .and_then(|date| self.response_time.duration_since(date).ok())
+            .unwrap_or_default();
+
+        let resident_time = now.duration_since(self.response_time).unwrap_or_default();
+
+        apparent_age.max(age_value) + resident_time
+    }
+
+    /// Returns how long the response is fresh for after it was generated, per RFC 7234 §4.2.1.
+    fn freshness_lifetime(&self) -> Duration {
+        let cache_control = CacheControl::parse(&self.headers);
+
+        if let Some(max_age) = cache_control.max_age {
+            return max_age;
+        }
+
+        let date = header_date(&self.headers, header::DATE).unwrap_or(self.response_time);
+
+        if self.headers.contains_key(header::EXPIRES) {
+            // invalid dates, such as "0", mean the response is already expired
+            return header_date(&self.headers, header::EXPIRES)
+                .and_then(|expires| expires.duration_since(date).ok())
+                .unwrap_or_default();
+        }
+
+        // heuristic freshness, per RFC 7234 §4.2.2
+        match header_date(&self.headers, header::LAST_MODIFIED) {
+            Some(last_modified) if is_cacheable_by_default(self.status) => date
+                .duration_since(last_modified)
+                .map_or(Duration::ZERO, |age| age / 10),
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Returns true if the response can be used for a request with `req_cache_control` at `now`
+    /// without revalidating it.
+    fn is_fresh(&self, req_cache_control: &CacheControl, now: SystemTime) -> bool {
+        let cache_control = CacheControl::parse(&self.headers);
+
+        if cache_control.no_cache || req_cache_control.no_cache {
+            return false;
+        }
+
+        let mut lifetime = self.freshness_lifetime();
+        if let Some(max_age) = req_cache_control.max_age {
+            lifetime = lifetime.min(max_age);
+        }
+
+        self.age(now) < lifetime
+    }
+
+    /// Constructs a response to serve from the cache at `now`.
+    fn to_response(&self, now: SystemTime) -> ClientResponse {
+        let mut head = ResponseHead::new(self.status);
+        head.version = self.version;
+        head.headers = self.headers.clone();
+        head.headers
+            .insert(header::AGE, HeaderValue::from(self.age(now).as_secs()));
+
+        let (_, mut payload) = h1::Payload::create(true);
+        payload.unread_data(self.body.clone());
+
+        ClientResponse::new(head, payload.into())
+    }
+}
+
+/// Middleware for caching responses, following the rules of a private cache in RFC 7234.
+///
+/// Responses to `GET` requests are stored when their status and `Cache-Control`, `Expires`, and
+/// `Last-Modified` headers allow it, and reused while they are fresh. Stale responses with an
+/// `ETag` or `Last-Modified` validator are revalidated with a conditional request, and reused if
+/// the server responds with `304 Not Modified`. Successful `POST`, `PUT`, `PATCH`, and `DELETE`
+/// requests remove the cached response for their URI.
+///
+/// Responses are stored once their body has been read completely, so responses whose body is
+/// never read, or is larger than the [maximum body size](Self::max_body_size), are not cached.
+/// Requests with their own conditional headers, and requests and responses with the `no-store`
+/// directive, bypass the cache.
+///
+/// # Examples
+/// ```
+/// use awc::{
+///     middleware::{Cache, MemoryCacheStorage},
+///     ClientBuilder,
+/// };
+///
+/// let client = ClientBuilder::new()
+///     .wrap(Cache::new(MemoryCacheStorage::new(1024)))
+///     .finish();
+/// ```
+pub struct Cache {
+    storage: Rc<dyn CacheStorage>,
+    max_body_size: usize,
+}
+
+impl Cache {
+    /// Constructs a cache middleware that stores responses in `storage`.
+    pub fn new(storage: impl CacheStorage) -> Self {
+        Self {
+            storage: Rc::new(storage),
+            max_body_size: 1024 * 1024,
+        }
+    }
+
+    /// Sets the maximum size of the response bodies that are cached.
+    ///
+    /// Defaults to 1MiB.
+    pub fn max_body_size(mut self, size: usize) -> Self {
+        self.max_body_size = size;
+        self
+    }
+}
+
+impl fmt::Debug for Cache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cache")
+            .field("max_body_size", &self.max_body_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> Transform<S, ConnectRequest> for Cache
+where
+    S: Service<ConnectRequest, Response = ConnectResponse, Error = SendRequestError> + 'static,
+{
+    type Transform = CacheService<S>;
+
+    fn new_transform(self, service: S) -> Self::Transform {
+        CacheService {
+            storage: self.storage,
+            max_body_size: self.max_body_size,
+            connector: Rc::new(service),
+        }
+    }
+}
+
+/// Service of a [`Cache`] middleware.
+pub struct CacheService<S> {
+    storage: Rc<dyn CacheStorage>,
+    max_body_size: usize,
+    connector: Rc<S>,
+}
+
+impl<S> Service<ConnectRequest> for CacheService<S>
+where
+    S: Service<ConnectRequest, Response = ConnectResponse, Error = SendRequestError> + 'static,
+{
+    type Response = ConnectResponse;
+    type Error = SendRequestError;
+    type Future = LocalBoxFuture<'static, Result<ConnectResponse, SendRequestError>>;
+
+    actix_service::forward_ready!(connector);
+
+    fn call(&self, req: ConnectRequest) -> Self::Future {
+        let (method, uri, req_headers) = match req {
+            ConnectRequest::Client(ref head, ..) => {
+                let head = head.as_ref();
+                (head.method.clone(), head.uri.clone(), head.headers.clone())
+            }
+            ConnectRequest::Tunnel(..) => return Box::pin(self.connector.call(req)),
+        };
+
+        let connector = Rc::clone(&self.connector);
+        let storage = Rc::clone(&self.storage);
+        let key = cache_key(&uri);
+
+        if method != Method::GET {
+            let fut = connector.call(req);
+
+            return Box::pin(async move {
+                let res = fut.await?;
+
+                // unsafe methods invalidate the cached response of their target
+                if let ConnectResponse::Client(ref res) = res {
+                    if !is_safe(&method)
+                        && (res.status().is_success() || res.status().is_redirection())
+                    {
+                        storage.remove(&key).await;
+                    }
+                }
+
+                Ok(res)
+            });
+        }
+
+        let req_cache_control = CacheControl::parse(&req_headers);
+
+        if req_cache_control.no_store
+            || req_headers.contains_key(header::IF_NONE_MATCH)
+            || req_headers.contains_key(header::IF_MODIFIED_SINCE)
+        {
+            return Box::pin(connector.call(req));
+        }
+
+        let max_body_size = self.max_body_size;
+
+        Box::pin(async move {
+            let mut req = req;
+
+            let cached = storage
+                .get(&key)
+                .await
+                .filter(|cached| cached.matches(&req_headers));
+
+            if let Some(ref cached) = cached {
+                if cached.is_fresh(&req_cache_control, SystemTime::now()) {
+                    let res = cached.to_response(SystemTime::now());
+                    return Ok(ConnectResponse::Client(res));
+                }
+
+                if let ConnectRequest::Client(ref mut head, ..) = req {
+                    add_validators(head, cached);
+                }
+            }
+
+            let res = connector.call(req).await?.try_into_client_response()?;
+
+            let now = SystemTime::now();
+
+            if let (StatusCode::NOT_MODIFIED, Some(mut cached)) = (res.status(), cached) {
+                // update the stored response with the headers of the 304 response
+                for (name, value) in res.headers() {
+                    if name != header::CONTENT_LENGTH {
+                        cached.headers.insert(name.clone(), value.clone());
+                    }
+                }
+                cached.response_time = now;
+
+                let res = cached.to_response(now);
+                storage.put(&key, cached).await;
+                return Ok(ConnectResponse::Client(res));
+            }
+
+            if !is_storable(&req_headers, &res) {
+                return Ok(ConnectResponse::Client(res));
+            }
+
+            let vary = res
+                .headers()
+                .get_all(header::VARY)
+                .filter_map(|vary| vary.to_str().ok())
+                .flat_map(|vary| vary.split(','))
+                .filter_map(|name| HeaderName::try_from(name.trim()).ok())
+                .map(|name| {
+                    let value = req_headers.get(&name).cloned();
+                    (name, value)
+                })
+                .collect();
+
+            let entry = CachedResponse {
+                status: res.status(),
+                version: res.version(),
+                headers: res.headers().clone(),
+                body: Bytes::new(),
+                vary,
+                response_time: now,
+            };
+
+            let res = res.map_body(|_, payload| {
+                Payload::from(Box::pin(CachingPayload {
+                    payload,
+                    buf: BytesMut::new(),
+                    pending: Some((storage, key, entry)),
+                    max_body_size,
+                }) as BoxedPayloadStream)
+            });
+
+            Ok(ConnectResponse::Client(res))
+        })
+    }
+}
+
+pin_project! {
+    /// Response payload that stores the response in the cache once it has been read completely.
+    struct CachingPayload {
+        #[pin]
+        payload: Payload,
+        buf: BytesMut,
+        pending: Option<(Rc<dyn CacheStorage>, String, CachedResponse)>,
+        max_body_size: usize,
+    }
+}
+
+impl Stream for CachingPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+
+        match ready!(this.payload.poll_next(cx)) {
+            Some(Ok(chunk)) => {
+                if this.pending.is_some() {
+                    if this.buf.len() + chunk.len() > *this.max_body_size {
+                        *this.pending = None;
+                        *this.buf = BytesMut::new();
+                    } else {
+                        this.buf.extend_from_slice(&chunk);
+                    }
+                }
+
+                Poll::Ready(Some(Ok(chunk)))
+            }
+
+            Some(Err(err)) => {
+                *this.pending = None;
+                Poll::Ready(Some(Err(err)))
+            }
+
+            None => {
+                if let Some((storage, key, mut entry)) = this.pending.take() {
+                    entry.body = this.buf.split().freeze();
+
+                    // storages that do not need to wait for I/O store the response right away
+                    actix_rt::spawn(storage.put(&key, entry));
+                }
+
+                Poll::Ready(None)
+            }
+        }
+    }
+}
+
+/// Directives of `Cache-Control` headers that are relevant to a private cache.
+#[derive(Debug, Default)]
+struct CacheControl {
+    no_store: bool,
+    no_cache: bool,
+    public: bool,
+    must_revalidate: bool,
+    max_age: Option<Duration>,
+}
+
+impl CacheControl {
+    fn parse(headers: &HeaderMap) -> Self {
+        let mut cache_control = CacheControl::default();
+
+        let directives = headers
+            .get_all(header::CACHE_CONTROL)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','));
+
+        for directive in directives {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (directive.trim(), None),
+            };
+
+            match name.to_ascii_lowercase().as_str() {
+                "no-store" => cache_control.no_store = true,
+                "no-cache" => cache_control.no_cache = true,
+                "public" => cache_control.public = true,
+                "must-revalidate" => cache_control.must_revalidate = true,
+                "max-age" => {
+                    cache_control.max_age = value
+                        .and_then(|secs| secs.parse().ok())
+                        .map(Duration::from_secs)
+                }
+                _ => {}
+            }
+        }
+
+        cache_control
+    }
+}
+
+/// Returns the cache key of `GET` requests for `uri`.
+fn cache_key(uri: &Uri) -> String {
+    format!("GET {uri}")
+}
+
+/// Returns true if requests with `method` do not change the state of the server.
+fn is_safe(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    )
+}
+
+/// Returns true if responses with `status` can be cached without explicit freshness information,
+/// per RFC 7231 §6.1.
+fn is_cacheable_by_default(status: StatusCode) -> bool {
+    matches!(
+        status.as_u16(),
+        200 | 203 | 204 | 300 | 301 | 308 | 404 | 405 | 410 | 414 | 501
+    )
+}
+
+/// Returns true if the response to a `GET` request with `req_headers` may be stored, per
+/// RFC 7234 §3.
+fn is_storable(req_headers: &HeaderMap, res: &ClientResponse) -> bool {
+    let cache_control = CacheControl::parse(res.headers());
+
+    if cache_control.no_store
+        || res.headers().contains_key(header::SET_COOKIE)
+        || res
+            .headers()
+            .get_all(header::VARY)
+            .any(|vary| vary.to_str().is_ok_and(|vary| vary.trim() == "*"))
+    {
+        return false;
+    }
+
+    // responses to authenticated requests may be personalized
+    if req_headers.contains_key(header::AUTHORIZATION)
+        && !(cache_control.public || cache_control.must_revalidate)
+    {
+        return false;
+    }
+
+    let has_validator = res.headers().contains_key(header::ETAG)
+        || res.headers().contains_key(header::LAST_MODIFIED);
+    let has_freshness =
+        cache_control.max_age.is_some() || res.headers().contains_key(header::EXPIRES);
+
+    (is_cacheable_by_default(res.status()) || has_freshness || cache_control.public)
+        && res.status() != StatusCode::PARTIAL_CONTENT
+        && (has_freshness || has_validator)
+}
+
+/// Adds conditional headers for revalidating `cached` to a request.
+fn add_validators(head: &mut RequestHeadType, cached: &CachedResponse) {
+    let validators = [
+        (header::IF_NONE_MATCH, cached.headers.get(header::ETAG)),
+        (
+            header::IF_MODIFIED_SINCE,
+            cached.headers.get(header::LAST_MODIFIED),
+        ),
+    ];
+
+    for (name, value) in validators {
+        let Some(value) = value else {
+            continue;
+        };
+
+        match head {
+            RequestHeadType::Owned(head) => {
+                head.headers.insert(name, value.clone());
+            }
+            RequestHeadType::Rc(_, extra_headers) => {
+                extra_headers
+                    .get_or_insert_with(HeaderMap::new)
+                    .insert(name, value.clone());
+            }
+        }
+    }
+}
+
+fn header_date(headers: &HeaderMap, name: HeaderName) -> Option<SystemTime> {
+    headers
+        .get(name)?
+        .to_str()
+        .ok()?
+        .parse::<HttpDate>()
+        .ok()
+        .map(SystemTime::from)
+}
+
+/// In-memory [`CacheStorage`] that evicts the least recently used responses.
+///
+/// Clones share the same storage.
+#[derive(Debug, Clone)]
+pub struct MemoryCacheStorage {
+    inner: Rc<RefCell<Lru>>,
+}
+
+#[derive(Debug)]
+struct Lru {
+    capacity: usize,
+    entries: HashMap<String, (CachedResponse, u64)>,
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl Lru {
+    fn touch(&mut self, key: &str) -> Option<&CachedResponse> {
+        let tick = self.tick;
+        let (entry, last_used) = self.entries.get_mut(key)?;
+
+        self.tick += 1;
+        let key = self.recency.remove(last_used).unwrap();
+        self.recency.insert(tick, key);
+        *last_used = tick;
+
+        Some(entry)
+    }
+}
+
+impl MemoryCacheStorage {
+    /// Constructs a storage that holds up to `capacity` responses.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(Lru {
+                capacity,
+                entries: HashMap::new(),
+                recency: BTreeMap::new(),
+                tick: 0,
+            })),
+        }
+    }
+
+    /// Returns the number of stored responses.
+    pub fn len(&self) -> usize {
+        self.inner.borrow().entries.len()
+    }
+
+    /// Returns true if no responses are stored.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl CacheStorage for MemoryCacheStorage {
+    fn get(&self, key: &str) -> LocalBoxFuture<'static, Option<CachedResponse>> {
+        let entry = self.inner.borrow_mut().touch(key).cloned();
+        Box::pin(async move { entry })
+    }
+
+    fn put(&self, key: &str, response: CachedResponse) -> LocalBoxFuture<'static, ()> {
+        let mut lru = self.inner.borrow_mut();
+
+        if lru.capacity > 0 {
+            let tick = lru.tick;
+            lru.tick += 1;
+
+            if let Some((_, last_used)) = lru.entries.insert(key.to_owned(), (response, tick)) {
+                lru.recency.remove(&last_used);
+            }
+            lru.recency.insert(tick, key.to_owned());
+
+            while lru.entries.len() > lru.capacity {
+                let (_, key) = lru.recency.pop_first().unwrap();
+                lru.entries.remove(&key);
+            }
+        }
+
+        Box::pin(async {})
+    }
+
+    fn remove(&self, key: &str) -> LocalBoxFuture<'static, ()> {
+        let mut lru = self.inner.borrow_mut();
+
+        if let Some((_, last_used)) = lru.entries.remove(key) {
+            lru.recency.remove(&last_used);
+        }
+
+        Box::pin(async {})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use actix_web::{
+        http::header::{ETag, EntityTag},
+        web, App, HttpRequest, HttpResponse,
+    };
+
+    use super::*;
+    use crate::ClientBuilder;
+
+    fn cached(headers: &[(HeaderName, &'static str)], age: Duration) -> CachedResponse {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.append(name.clone(), HeaderValue::from_static(value));
+        }
+
+        CachedResponse {
+            status: StatusCode::OK,
+            version: Version::HTTP_11,
+            headers: map,
+            body: Bytes::new(),
+            vary: Vec::new(),
+            response_time: SystemTime::now() - age,
+        }
+    }
+
+    #[test]
+    fn freshness() {
+        let no_directives = CacheControl::default();
+        let now = SystemTime::now();
+
+        let res = cached(
+            &[(header::CACHE_CONTROL, "public, max-age=60")],
+            Duration::ZERO,
+        );
+        assert!(res.is_fresh(&no_directives, now));
+
+        let res = cached(
+            &[(header::CACHE_CONTROL, "max-age=60")],
+            Duration::from_secs(61),
+        );
+        assert!(!res.is_fresh(&no_directives, now));
+
+        let res = cached(
+            &[(header::CACHE_CONTROL, "max-age=60"), (header::AGE, "59")],
+            Duration::from_secs(2),
+        );
+        assert!(!res.is_fresh(&no_directives, now));
+
+        let res = cached(
+            &[(header::CACHE_CONTROL, "max-age=60, no-cache")],
+            Duration::ZERO,
+        );
+        assert!(!res.is_fresh(&no_directives, now));
+
+        let res = cached(&[(header::EXPIRES, "0")], Duration::ZERO);
+        assert!(!res.is_fresh(&no_directives, now));
+
+        // heuristic freshness of a week old resource is 16.8 hours
+        let last_modified = HttpDate::from(now - Duration::from_secs(7 * 24 * 3600)).to_string();
+        let mut res = cached(&[], Duration::from_secs(3600));
+        res.headers.insert(
+            header::LAST_MODIFIED,
+            HeaderValue::try_from(last_modified).unwrap(),
+        );
+        assert!(res.is_fresh(&no_directives, now));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("max-age=0"));
+        assert!(!res.is_fresh(&CacheControl::parse(&headers), now));
+    }
+
+    #[actix_rt::test]
+    async fn lru_eviction() {
+        let storage = MemoryCacheStorage::new(2);
+        let res = cached(&[], Duration::ZERO);
+
+        storage.put("a", res.clone()).await;
+        storage.put("b", res.clone()).await;
+        storage.get("a").await;
+        storage.put("c", res.clone()).await;
+
+        assert_eq!(storage.len(), 2);
+        assert!(storage.inner.borrow().entries.contains_key("a"));
+        assert!(!storage.inner.borrow().entries.contains_key("b"));
+
+        storage.remove("a").await;
+        assert_eq!(storage.len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn cache_and_revalidate() {
+        let hits = Arc::new(AtomicUsize::new(0));
+
+        let srv = actix_test::start({
+            let hits = Arc::clone(&hits);
+
+            move || {
+                let hits = Arc::clone(&hits);
+
+                App::new()
+                    .route(
+                        "/fresh",
+                        web::get().to({
+                            let hits = Arc::clone(&hits);
+                            move || {
+                                hits.fetch_add(1, Ordering::SeqCst);
+                                async {
+                                    HttpResponse::Ok()
+                                        .insert_header((header::CACHE_CONTROL, "max-age=60"))
+                                        .body("fresh")
+                                }
+                            }
+                        }),
+                    )
+                    .route("/fresh", web::post().to(HttpResponse::Ok))
+                    .route(
+                        "/etag",
+                        web::get().to(move |req: HttpRequest| {
+                            hits.fetch_add(1, Ordering::SeqCst);
+                            let etag = EntityTag::new_strong("v1".to_owned());
+
+                            async move {
+                                match req.headers().get(header::IF_NONE_MATCH) {
+                                    Some(tag) if tag == "\"v1\"" => HttpResponse::NotModified()
+                                        .insert_header(ETag(etag))
+                                        .finish(),
+                                    _ => HttpResponse::Ok()
+                                        .insert_header(ETag(etag))
+                                        .insert_header((header::CACHE_CONTROL, "no-cache"))
+                                        .body("etag"),
+                                }
+                            }
+                        }),
+                    )
+            }
+        });
+
+        let storage = MemoryCacheStorage::new(16);
+        let client = ClientBuilder::new()
+            .wrap(Cache::new(storage.clone()))
+            .finish();
+
+        for _ in 0..3 {
+            let mut res = client.get(srv.url("/fresh")).send().await.unwrap();
+            assert_eq!(res.body().await.unwrap(), "fresh");
+        }
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        // a successful unsafe request invalidates the response
+        let _ = client.post(srv.url("/fresh")).send().await.unwrap();
+        let mut res = client.get(srv.url("/fresh")).send().await.unwrap();
+        assert_eq!(res.body().await.unwrap(), "fresh");
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        hits.store(0, Ordering::SeqCst);
+
+        for _ in 0..2 {
+            let mut res = client.get(srv.url("/etag")).send().await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(res.body().await.unwrap(), "etag");
+
+            // let the spawned store complete
+            actix_rt::task::yield_now().await;
+        }
+
+        // the second request was revalidated
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        assert_eq!(storage.len(), 2);
+    }
+}
@@ -0,0 +1,543 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use actix_service::Service;
+use futures_core::future::LocalBoxFuture;
+
+use super::{host_key, Transform};
+use crate::{
+    client::{ConnectError, SendRequestError},
+    connect::{ConnectRequest, ConnectResponse},
+};
+
+/// State of the circuit of a host, see [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests are sent, and their failure rate is tracked.
+    Closed,
+
+    /// Requests fail immediately without being sent.
+    Open,
+
+    /// A limited number of probe requests are sent to check whether the host has recovered.
+    HalfOpen,
+}
+
+/// Middleware that stops sending requests to hosts that keep failing.
+///
+/// Every host and port that requests are sent to has its own circuit. While the circuit of a host
+/// is closed, the middleware counts the requests sent to it and how many of them failed. A request
+/// fails when the connection can not be established, when reading its response fails or times
+/// out, or when its response has a `5xx` status. Requests abandoned before their response
+/// arrives, such as by the request timeout, count as failures too.
+///
+/// Once at least the [minimum number of requests](Self::min_requests) have been sent within a
+/// [window](Self::window) and the share of failures reaches the
+/// [failure rate](Self::failure_rate), the circuit opens. Requests to the host then fail
+/// immediately with [`SendRequestError::CircuitOpen`], which spares both the host and the callers
+/// waiting on it. After the [open duration](Self::open_duration), the circuit is half-open and
+/// lets a number of [probe requests](Self::probes) through. It closes again once all of them
+/// succeed, and opens again as soon as one of them fails.
+///
+/// Clones of a `CircuitBreaker` share their circuits, so a clone kept after building the client
+/// can be used to inspect and reset them.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use awc::{
+///     middleware::{CircuitBreaker, CircuitState},
+///     ClientBuilder,
+/// };
+///
+/// let breaker = CircuitBreaker::new()
+///     .failure_rate(0.25)
+///     .open_duration(Duration::from_secs(10));
+///
+/// let client = ClientBuilder::new().wrap(breaker.clone()).finish();
+///
+/// assert_eq!(breaker.state("pharmacy.example.com:443"), CircuitState::Closed);
+/// ```
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    failure_rate: f64,
+    min_requests: u32,
+    window: Duration,
+    open_duration: Duration,
+    probes: u32,
+    circuits: Rc<RefCell<HashMap<String, Circuit>>>,
+}
+
+impl CircuitBreaker {
+    /// Constructs a circuit breaker with default settings.
+    pub fn new() -> Self {
+        Self {
+            failure_rate: 0.5,
+            min_requests: 10,
+            window: Duration::from_secs(60),
+            open_duration: Duration::from_secs(30),
+            probes: 1,
+            circuits: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+
+    /// Sets the share of failed requests, between 0 and 1, at which the circuit of a host opens.
+    ///
+    /// Defaults to 0.5.
+    ///
+    /// # Panics
+    /// Panics if `rate` is not greater than 0 and at most 1.
+    pub fn failure_rate(mut self, rate: f64) -> Self {
+        assert!(
+            rate > 0.0 && rate <= 1.0,
+            "failure rate must be greater than 0 and at most 1"
+        );
+        self.failure_rate = rate;
+        self
+    }
+
+    /// Sets the number of requests that have to be sent to a host within a window before its
+    /// failure rate can open the circuit.
+    ///
+    /// Defaults to 10.
+    pub fn min_requests(mut self, requests: u32) -> Self {
+        self.min_requests = requests;
+        self
+    }
+
+    /// Sets the length of the windows over which requests and failures are counted.
+    ///
+    /// Counts are reset at the end of each window. Defaults to 60 seconds.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Sets how long the circuit of a host stays open before probe requests are sent.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn open_duration(mut self, duration: Duration) -> Self {
+        self.open_duration = duration;
+        self
+    }
+
+    /// Sets the number of probe requests that have to succeed for a half-open circuit to close.
+    ///
+    /// Up to this many requests are sent at once while the circuit is half-open. Defaults to 1.
+    ///
+    /// # Panics
+    /// Panics if `probes` is zero.
+    pub fn probes(mut self, probes: u32) -> Self {
+        assert!(
+            probes > 0,
+            "circuit breaker needs at least one probe request"
+        );
+        self.probes = probes;
+        self
+    }
+
+    /// Returns the state of the circuit of `host`, given as a host name or IP address and port,
+    /// e.g., `api.example.com:443`.
+    ///
+    /// Hosts that no request has been sent to have a closed circuit.
+    pub fn state(&self, host: &str) -> CircuitState {
+        self.circuits
+            .borrow()
+            .get(&host.to_ascii_lowercase())
+            .map_or(CircuitState::Closed, |circuit| {
+                circuit.state(Instant::now())
+            })
+    }
+
+    /// Returns the hosts that requests have been sent to recently, with the states of their
+    /// circuits.
+    pub fn hosts(&self) -> Vec<(String, CircuitState)> {
+        let now = Instant::now();
+
+        self.circuits
+            .borrow()
+            .iter()
+            .map(|(host, circuit)| (host.clone(), circuit.state(now)))
+            .collect()
+    }
+
+    /// Closes the circuit of `host` and resets its counts.
+    pub fn reset(&self, host: &str) {
+        self.circuits
+            .borrow_mut()
+            .remove(&host.to_ascii_lowercase());
+    }
+
+    /// Checks whether a request can be sent to `key`, returning whether it is a probe request.
+    fn admit(&self, key: &str, now: Instant) -> Result<bool, SendRequestError> {
+        let mut circuits = self.circuits.borrow_mut();
+
+        if !circuits.contains_key(key) {
+            // circuits of hosts that have not been used for a window have nothing worth keeping
+            circuits.retain(|_, circuit| !circuit.is_idle(now, self.window));
+        }
+
+        let circuit = circuits
+            .entry(key.to_owned())
+            .or_insert_with(|| Circuit::new(now));
+
+        if let State::Open { until } = circuit.state {
+            if now >= until {
+                circuit.state = State::HalfOpen {
+                    in_flight: 0,
+                    successes: 0,
+                };
+            }
+        }
+
+        match &mut circuit.state {
+            State::Closed => Ok(false),
+
+            State::HalfOpen {
+                in_flight,
+                successes,
+            } if *in_flight + *successes < self.probes => {
+                *in_flight += 1;
+                Ok(true)
+            }
+
+            State::Open { .. } | State::HalfOpen { .. } => {
+                Err(SendRequestError::CircuitOpen(key.to_owned()))
+            }
+        }
+    }
+
+    /// Records the outcome of a request to `key`.
+    fn record(&self, key: &str, probe: bool, failed: bool, now: Instant) {
+        let mut circuits = self.circuits.borrow_mut();
+
+        let Some(circuit) = circuits.get_mut(key) else {
+            return;
+        };
+
+        match circuit.state {
+            State::Closed if !probe => {
+                if now.duration_since(circuit.window_start) >= self.window {
+                    circuit.reset_counts(now);
+                }
+
+                circuit.requests += 1;
+                circuit.failures += u32::from(failed);
+
+                if circuit.requests >= self.min_requests
+                    && f64::from(circuit.failures)
+                        >= self.failure_rate * f64::from(circuit.requests)
+                {
+                    circuit.state = State::Open {
+                        until: now + self.open_duration,
+                    };
+                }
+            }
+
+            State::HalfOpen { .. } if probe && failed => {
+                circuit.state = State::Open {
+                    until: now + self.open_duration,
+                };
+            }
+
+            State::HalfOpen {
+                ref mut in_flight,
+                ref mut successes,
+            } if probe => {
+                *in_flight -= 1;
+                *successes += 1;
+
+                if *successes >= self.probes {
+                    circuit.state = State::Closed;
+                    circuit.reset_counts(now);
+                }
+            }
+
+            // outcome of a request sent before the circuit changed state
+            _ => {}
+        }
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CircuitBreaker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CircuitBreaker")
+            .field("failure_rate", &self.failure_rate)
+            .field("min_requests", &self.min_requests)
+            .field("window", &self.window)
+            .field("open_duration", &self.open_duration)
+            .field("probes", &self.probes)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Circuit of a single host.
+struct Circuit {
+    state: State,
+    window_start: Instant,
+    requests: u32,
+    failures: u32,
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    Closed,
+    Open { until: Instant },
+    HalfOpen { in_flight: u32, successes: u32 },
+}
+
+impl Circuit {
+    fn new(now: Instant) -> Self {
+        Self {
+            state: State::Closed,
+            window_start: now,
+            requests: 0,
+            failures: 0,
+        }
+    }
+
+    fn state(&self, now: Instant) -> CircuitState {
+        match self.state {
+            State::Closed => CircuitState::Closed,
+            State::Open { until } if now < until => CircuitState::Open,
+            State::Open { .. } | State::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+
+    fn reset_counts(&mut self, now: Instant) {
+        self.window_start = now;
+        self.requests = 0;
+        self.failures = 0;
+    }
+
+    /// Returns true if the circuit is closed and its window has ended.
+    fn is_idle(&self, now: Instant, window: Duration) -> bool {
+        matches!(self.state, State::Closed) && now.duration_since(self.window_start) >= window
+    }
+}
+
+impl<S> Transform<S, ConnectRequest> for CircuitBreaker
+where
+    S: Service<ConnectRequest, Response = ConnectResponse, Error = SendRequestError> + 'static,
+{
+    type Transform = CircuitBreakerService<S>;
+
+    fn new_transform(self, service: S) -> Self::Transform {
+        CircuitBreakerService {
+            breaker: self,
+            connector: Rc::new(service),
+        }
+    }
+}
+
+/// Service of a [`CircuitBreaker`] middleware.
+pub struct CircuitBreakerService<S> {
+    breaker: CircuitBreaker,
+    connector: Rc<S>,
+}
+
+impl<S> Service<ConnectRequest> for CircuitBreakerService<S>
+where
+    S: Service<ConnectRequest, Response = ConnectResponse, Error = SendRequestError> + 'static,
+{
+    type Response = ConnectResponse;
+    type Error = SendRequestError;
+    type Future = LocalBoxFuture<'static, Result<ConnectResponse, SendRequestError>>;
+
+    actix_service::forward_ready!(connector);
+
+    fn call(&self, req: ConnectRequest) -> Self::Future {
+        let uri = match req {
+            ConnectRequest::Client(ref head, ..) => &head.as_ref().uri,
+            ConnectRequest::Tunnel(ref head, ..) => &head.uri,
+        };
+
+        // requests without a host fail in the connector
+        let Some(key) = host_key(uri) else {
+            return Box::pin(self.connector.call(req));
+        };
+
+        let probe = match self.breaker.admit(&key, Instant::now()) {
+            Ok(probe) => probe,
+            Err(err) => return Box::pin(async move { Err(err) }),
+        };
+
+        let mut outcome = Outcome {
+            breaker: self.breaker.clone(),
+            key,
+            probe,
+            recorded: false,
+        };
+
+        let fut = self.connector.call(req);
+
+        Box::pin(async move {
+            let res = fut.await;
+
+            let failed = match res {
+                Ok(ConnectResponse::Client(ref res)) => res.status().is_server_error(),
+                Ok(ConnectResponse::Tunnel(ref head, _)) => head.status.is_server_error(),
+                Err(ref err) => is_failure(err),
+            };
+
+            outcome.record(failed);
+            res
+        })
+    }
+}
+
+/// Records the outcome of a request, or a failure if it is dropped before it completes.
+struct Outcome {
+    breaker: CircuitBreaker,
+    key: String,
+    probe: bool,
+    recorded: bool,
+}
+
+impl Outcome {
+    fn record(&mut self, failed: bool) {
+        self.recorded = true;
+        self.breaker
+            .record(&self.key, self.probe, failed, Instant::now());
+    }
+}
+
+impl Drop for Outcome {
+    fn drop(&mut self) {
+        if !self.recorded {
+            self.record(true);
+        }
+    }
+}
+
+/// Returns true if `err` indicates a problem with the host.
+fn is_failure(err: &SendRequestError) -> bool {
+    match err {
+        SendRequestError::Connect(ConnectError::Denied(_)) => false,
+        SendRequestError::Connect(_)
+        | SendRequestError::Send(_)
+        | SendRequestError::Response(_)
+        | SendRequestError::H2(_)
+        | SendRequestError::Timeout => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{web, App, HttpResponse};
+
+    use super::*;
+    use crate::ClientBuilder;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn transitions() {
+        let breaker = CircuitBreaker::new()
+            .min_requests(4)
+            .failure_rate(0.5)
+            .open_duration(SECOND)
+            .probes(2);
+        let now = Instant::now();
+        let send = |now, failed| {
+            let probe = breaker.admit("a:80", now)?;
+            breaker.record("a:80", probe, failed, now);
+            Ok::<_, SendRequestError>(probe)
+        };
+
+        // too few requests to open
+        send(now, true).unwrap();
+        send(now, true).unwrap();
+        send(now, false).unwrap();
+        assert_eq!(breaker.state("a:80"), CircuitState::Closed);
+
+        send(now, false).unwrap();
+        assert!(matches!(
+            breaker.circuits.borrow()["a:80"].state,
+            State::Open { .. }
+        ));
+        assert!(matches!(
+            send(now, false),
+            Err(SendRequestError::CircuitOpen(ref host)) if host == "a:80"
+        ));
+
+        // half-open circuit lets the configured number of probes through at once
+        let later = now + SECOND;
+        assert!(breaker.admit("a:80", later).unwrap());
+        assert!(breaker.admit("a:80", later).unwrap());
+        assert!(breaker.admit("a:80", later).is_err());
+
+        // failed probe opens the circuit again
+        breaker.record("a:80", true, false, later);
+        breaker.record("a:80", true, true, later);
+        assert!(breaker.admit("a:80", later).is_err());
+
+        // successful probes close it
+        let later = later + SECOND;
+        assert!(send(later, false).unwrap());
+        assert!(send(later, false).unwrap());
+        assert!(!send(later, false).unwrap());
+
+        breaker.reset("A:80");
+        assert!(breaker.hosts().is_empty());
+    }
+
+    #[test]
+    fn windows() {
+        let breaker = CircuitBreaker::new().min_requests(2).window(SECOND);
+        let now = Instant::now();
+        let send =
+            |now, failed| breaker.record("a:80", breaker.admit("a:80", now).unwrap(), failed, now);
+
+        send(now, true);
+
+        // counts are reset after a window
+        send(now + SECOND, false);
+        send(now + SECOND, false);
+        assert_eq!(breaker.state("a:80"), CircuitState::Closed);
+
+        // idle circuits are dropped when another host is added
+        breaker.admit("b:80", now + SECOND * 3).unwrap();
+        assert_eq!(
+            breaker.hosts(),
+            vec![("b:80".to_owned(), CircuitState::Closed)]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn opens_on_server_errors() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/ok", web::to(|| async { HttpResponse::Ok().finish() }))
+                .default_service(web::to(|| async {
+                    HttpResponse::ServiceUnavailable().finish()
+                }))
+        });
+
+        let breaker = CircuitBreaker::new().min_requests(2);
+        let client = ClientBuilder::new().wrap(breaker.clone()).finish();
+
+        for _ in 0..2 {
+            let res = client.get(srv.url("/down")).send().await.unwrap();
+            assert_eq!(res.status().as_u16(), 503);
+        }
+
+        assert_eq!(breaker.state(&srv.addr().to_string()), CircuitState::Open);
+
+        let err = client.get(srv.url("/ok")).send().await.unwrap_err();
+        assert!(matches!(err, SendRequestError::CircuitOpen(_)));
+    }
+}
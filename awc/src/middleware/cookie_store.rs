@@ -0,0 +1,436 @@
+use std::{cell::RefCell, cmp::Reverse, fmt, rc::Rc};
+
+use actix_http::{
+    header::{HeaderMap, HeaderValue, COOKIE, SET_COOKIE},
+    RequestHeadType, Uri,
+};
+use actix_service::Service;
+use futures_core::future::LocalBoxFuture;
+
+use super::Transform;
+use crate::{
+    client::SendRequestError,
+    connect::{ConnectRequest, ConnectResponse},
+    cookie::{time::OffsetDateTime, Cookie},
+};
+
+/// Middleware that stores the cookies set by responses and sends them with later requests.
+///
+/// Cookies are matched to requests by domain, path and scheme as described by [RFC 6265], and
+/// expire according to their `Max-Age` or `Expires` attributes. Cookies set on a request with
+/// [`ClientRequest::cookie()`](crate::ClientRequest::cookie) are sent alongside the stored ones.
+///
+/// Clones of a store share its cookies, so a store can be inspected or cleared while it is used
+/// by a client. Give each [child client](crate::Client::child) its own store to keep the sessions
+/// of different tenants apart.
+///
+/// # Examples
+/// ```
+/// use awc::{middleware::CookieStore, ClientBuilder};
+///
+/// let cookies = CookieStore::new();
+/// let client = ClientBuilder::new().wrap(cookies.clone()).finish();
+/// ```
+///
+/// [RFC 6265]: https://www.rfc-editor.org/rfc/rfc6265
+#[derive(Clone)]
+pub struct CookieStore {
+    cookies: Rc<RefCell<Vec<StoredCookie>>>,
+}
+
+struct StoredCookie {
+    cookie: Cookie<'static>,
+    domain: String,
+    host_only: bool,
+    path: String,
+    expires: Option<OffsetDateTime>,
+}
+
+impl CookieStore {
+    /// Constructs an empty cookie store.
+    pub fn new() -> Self {
+        Self {
+            cookies: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// Returns the cookies that would be sent with a request to `uri`.
+    ///
+    /// Cookies with longer paths are listed first.
+    pub fn cookies(&self, uri: &Uri) -> Vec<Cookie<'static>> {
+        let Some(host) = host(uri) else {
+            return Vec::new();
+        };
+
+        let secure = matches!(uri.scheme_str(), Some("https") | Some("wss"));
+        let now = OffsetDateTime::now_utc();
+
+        let mut cookies = self.cookies.borrow_mut();
+        cookies.retain(|stored| !matches!(stored.expires, Some(expires) if expires <= now));
+
+        let mut matching = cookies
+            .iter()
+            .filter(|stored| {
+                domain_matches(&host, &stored.domain, stored.host_only)
+                    && path_matches(uri.path(), &stored.path)
+                    && (secure || !stored.cookie.secure().unwrap_or(false))
+            })
+            .collect::<Vec<_>>();
+
+        matching.sort_by_key(|stored| Reverse(stored.path.len()));
+        matching
+            .into_iter()
+            .map(|stored| stored.cookie.clone())
+            .collect()
+    }
+
+    /// Removes all cookies from the store.
+    pub fn clear(&self) {
+        self.cookies.borrow_mut().clear();
+    }
+
+    /// Returns the number of stored cookies, including expired cookies that have not been
+    /// removed yet.
+    pub fn len(&self) -> usize {
+        self.cookies.borrow().len()
+    }
+
+    /// Returns true if no cookies are stored.
+    pub fn is_empty(&self) -> bool {
+        self.cookies.borrow().is_empty()
+    }
+
+    /// Stores the cookies of the `Set-Cookie` headers of a response to a request for `uri`.
+    fn store(&self, uri: &Uri, headers: &HeaderMap) {
+        let Some(host) = host(uri) else {
+            return;
+        };
+
+        let now = OffsetDateTime::now_utc();
+        let mut cookies = self.cookies.borrow_mut();
+
+        for value in headers.get_all(SET_COOKIE) {
+            let Some(cookie) = value
+                .to_str()
+                .ok()
+                .and_then(|value| Cookie::parse(value).ok())
+            else {
+                continue;
+            };
+
+            let cookie = cookie.into_owned();
+
+            let (domain, host_only) = match cookie.domain() {
+                Some(domain) if !domain.is_empty() => {
+                    let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+
+                    // responses can only set cookies for their own domain and its parents
+                    if !domain_matches(&host, &domain, false) {
+                        continue;
+                    }
+
+                    (domain, false)
+                }
+                _ => (host.clone(), true),
+            };
+
+            let path = match cookie.path() {
+                Some(path) if path.starts_with('/') => path.to_owned(),
+                _ => default_path(uri.path()).to_owned(),
+            };
+
+            // a max age that overflows the date never expires
+            let expires = match cookie.max_age() {
+                Some(max_age) => now.checked_add(max_age),
+                None => cookie.expires_datetime(),
+            };
+
+            cookies.retain(|stored| {
+                stored.cookie.name() != cookie.name()
+                    || stored.domain != domain
+                    || stored.path != path
+            });
+
+            if !matches!(expires, Some(expires) if expires <= now) {
+                cookies.push(StoredCookie {
+                    cookie,
+                    domain,
+                    host_only,
+                    path,
+                    expires,
+                });
+            }
+        }
+    }
+
+    /// Returns the `Cookie` header for a request to `uri`, with the stored cookies appended to the
+    /// request's own cookies, if there are any stored cookies to send.
+    fn header(&self, uri: &Uri, own: Option<&HeaderValue>) -> Option<HeaderValue> {
+        let cookies = self.cookies(uri);
+
+        if cookies.is_empty() {
+            return None;
+        }
+
+        let mut value = cookies
+            .iter()
+            // ensure only name=value is written to cookie header
+            .map(|cookie| cookie.stripped().encoded().to_string())
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        if let Some(own) = own.and_then(|own| own.to_str().ok()) {
+            value = format!("{own}; {value}");
+        }
+
+        match HeaderValue::try_from(value) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                log::error!("Stored cookies are not a valid header value: {err}");
+                None
+            }
+        }
+    }
+
+    /// Adds the cookies for its URI to the `Cookie` header of a request.
+    fn add_to(&self, head: &mut RequestHeadType) {
+        match head {
+            RequestHeadType::Owned(head) => {
+                if let Some(value) = self.header(&head.uri, head.headers.get(COOKIE)) {
+                    head.headers.insert(COOKIE, value);
+                }
+            }
+
+            RequestHeadType::Rc(head, extra_headers) => {
+                // extra headers replace the headers of the shared head
+                let own = extra_headers
+                    .as_ref()
+                    .and_then(|headers| headers.get(COOKIE))
+                    .or_else(|| head.headers.get(COOKIE));
+
+                if let Some(value) = self.header(&head.uri, own) {
+                    extra_headers
+                        .get_or_insert_with(HeaderMap::new)
+                        .insert(COOKIE, value);
+                }
+            }
+        }
+    }
+}
+
+impl Default for CookieStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CookieStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CookieStore")
+            .field("cookies", &self.len())
+            .finish()
+    }
+}
+
+impl<S> Transform<S, ConnectRequest> for CookieStore
+where
+    S: Service<ConnectRequest, Response = ConnectResponse, Error = SendRequestError> + 'static,
+{
+    type Transform = CookieStoreService<S>;
+
+    fn new_transform(self, service: S) -> Self::Transform {
+        CookieStoreService {
+            service,
+            store: self,
+        }
+    }
+}
+
+/// Service of a [`CookieStore`] middleware.
+pub struct CookieStoreService<S> {
+    service: S,
+    store: CookieStore,
+}
+
+impl<S> Service<ConnectRequest> for CookieStoreService<S>
+where
+    S: Service<ConnectRequest, Response = ConnectResponse, Error = SendRequestError> + 'static,
+{
+    type Response = ConnectResponse;
+    type Error = SendRequestError;
+    type Future = LocalBoxFuture<'static, Result<ConnectResponse, SendRequestError>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, mut req: ConnectRequest) -> Self::Future {
+        let uri = match req {
+            ConnectRequest::Client(ref mut head, ..) => {
+                self.store.add_to(head);
+                head.as_ref().uri.clone()
+            }
+
+            ConnectRequest::Tunnel(ref mut head, _) => {
+                if let Some(value) = self.store.header(&head.uri, head.headers.get(COOKIE)) {
+                    head.headers.insert(COOKIE, value);
+                }
+
+                head.uri.clone()
+            }
+        };
+
+        let fut = self.service.call(req);
+        let store = self.store.clone();
+
+        Box::pin(async move {
+            let res = fut.await?;
+
+            match res {
+                ConnectResponse::Client(ref res) => store.store(&uri, res.headers()),
+                ConnectResponse::Tunnel(ref head, _) => store.store(&uri, &head.headers),
+            }
+
+            Ok(res)
+        })
+    }
+}
+
+/// Returns the lowercase host of `uri`, without brackets.
+fn host(uri: &Uri) -> Option<String> {
+    let host = uri.host()?.trim_start_matches('[').trim_end_matches(']');
+    Some(host.trim_end_matches('.').to_ascii_lowercase())
+}
+
+/// Returns true if `host` domain-matches `domain`, as described by RFC 6265 §5.1.3.
+fn domain_matches(host: &str, domain: &str, host_only: bool) -> bool {
+    if host == domain {
+        return true;
+    }
+
+    !host_only
+        && host.parse::<std::net::IpAddr>().is_err()
+        && host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// Returns true if `path` path-matches `cookie_path`, as described by RFC 6265 §5.1.4.
+fn path_matches(path: &str, cookie_path: &str) -> bool {
+    let path = if path.is_empty() { "/" } else { path };
+
+    path.strip_prefix(cookie_path)
+        .is_some_and(|rest| rest.is_empty() || cookie_path.ends_with('/') || rest.starts_with('/'))
+}
+
+/// Returns the default cookie path for a request path, as described by RFC 6265 §5.1.4.
+fn default_path(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(idx) => &path[..idx],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::RequestHead;
+
+    use super::*;
+
+    fn uri(uri: &str) -> Uri {
+        uri.parse().unwrap()
+    }
+
+    fn store(store: &CookieStore, req: &str, set_cookies: &[&'static str]) {
+        let mut headers = HeaderMap::new();
+
+        for set_cookie in set_cookies {
+            headers.append(SET_COOKIE, HeaderValue::from_static(set_cookie));
+        }
+
+        store.store(&uri(req), &headers);
+    }
+
+    fn names(store: &CookieStore, req: &str) -> Vec<String> {
+        store
+            .cookies(&uri(req))
+            .iter()
+            .map(|cookie| cookie.name().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn domain_and_path() {
+        let cookies = CookieStore::new();
+
+        store(
+            &cookies,
+            "https://api.example.com/v1/login",
+            &[
+                "host=1",
+                "shared=1; Domain=.Example.com; Path=/",
+                "scoped=1; Path=/v1/patients",
+                "other=1; Domain=example.org",
+                "secure=1; Secure; Path=/",
+            ],
+        );
+
+        assert_eq!(cookies.len(), 4);
+        assert_eq!(
+            names(&cookies, "https://api.example.com/v1/patients/7"),
+            ["scoped", "host", "shared", "secure"]
+        );
+        assert_eq!(names(&cookies, "https://example.com/"), ["shared"]);
+        assert_eq!(names(&cookies, "http://www.example.com/v1"), ["shared"]);
+        assert_eq!(
+            names(&cookies, "http://api.example.com/v1"),
+            ["host", "shared"]
+        );
+        assert!(names(&cookies, "https://example.org/").is_empty());
+        assert!(names(&cookies, "https://notexample.com/").is_empty());
+    }
+
+    #[test]
+    fn replace_and_expire() {
+        let cookies = CookieStore::new();
+
+        store(
+            &cookies,
+            "http://example.com/",
+            &["session=a", "theme=dark"],
+        );
+        store(&cookies, "http://example.com/", &["session=b"]);
+
+        let sent = cookies.cookies(&uri("http://example.com/"));
+        assert_eq!(sent.len(), 2);
+        assert!(sent
+            .iter()
+            .any(|cookie| cookie.name() == "session" && cookie.value() == "b"));
+
+        store(&cookies, "http://example.com/", &["session=; Max-Age=0"]);
+        assert_eq!(names(&cookies, "http://example.com/"), ["theme"]);
+
+        store(
+            &cookies,
+            "http://example.com/",
+            &["theme=; Expires=Thu, 01 Jan 1970 00:00:00 GMT"],
+        );
+        assert!(cookies.is_empty());
+    }
+
+    #[test]
+    fn add_to_request() {
+        let cookies = CookieStore::new();
+        store(&cookies, "http://example.com/", &["session=abc"]);
+
+        let mut head = RequestHead::default();
+        head.uri = uri("http://example.com/");
+        head.headers
+            .insert(COOKIE, HeaderValue::from_static("explicit=1"));
+
+        let mut head = RequestHeadType::Owned(head);
+        cookies.add_to(&mut head);
+        assert_eq!(
+            head.as_ref().headers.get(COOKIE).unwrap(),
+            "explicit=1; session=abc"
+        );
+    }
+}
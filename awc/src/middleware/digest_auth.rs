@@ -0,0 +1,758 @@
+use std::{
+    cell::RefCell,
+    fmt::{self, Write as _},
+    rc::Rc,
+};
+
+use actix_http::{
+    header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE},
+    Method, RequestHeadType, StatusCode, Uri,
+};
+use actix_service::Service;
+use futures_core::future::LocalBoxFuture;
+use md5::Md5;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rand::{distributions::Alphanumeric, Rng as _};
+use sha2::{Digest as _, Sha256, Sha512_256};
+
+use super::Transform;
+use crate::{
+    any_body::AnyBody,
+    client::SendRequestError,
+    connect::{ConnectRequest, ConnectResponse},
+};
+
+/// Header in which servers send the next nonce to use, as described by RFC 7616 §3.5.
+const AUTHENTICATION_INFO: HeaderName = HeaderName::from_static("authentication-info");
+
+/// Middleware that answers `Digest` authentication challenges, as described by [RFC 7616].
+///
+/// Requests that receive a `401 Unauthorized` response with a `Digest` challenge are sent once
+/// more with an `Authorization` header answering it. Challenges using the `MD5`, `SHA-256`, and
+/// `SHA-512-256` algorithms, and their session variants, are supported, with the `auth` quality
+/// of protection. The strongest of the supported challenges of a response is answered.
+///
+/// The last challenge of each origin is remembered and answered up front by later requests to the
+/// origin, with an increasing nonce count, which saves them a round trip. Next nonces sent in
+/// `Authentication-Info` headers are used for the following requests.
+///
+/// Requests that already have an `Authorization` header, such as requests with
+/// [bearer authentication](crate::ClientRequest::bearer_auth), are passed on unchanged. Requests
+/// with a streaming body are not sent again, and receive the `401 Unauthorized` response
+/// instead.
+///
+/// Use the `digest_auth()` methods of [`ClientRequest`], [`ClientBuilder`], and
+/// [`ChildClientBuilder`] rather than registering this middleware directly: they answer only
+/// challenges of the requested origins, while middleware registered with
+/// [`ClientBuilder::wrap()`] also answers challenges of the hosts that requests are redirected to.
+///
+/// # Examples
+/// ```
+/// use awc::Client;
+///
+/// # async fn run() -> Result<(), awc::error::SendRequestError> {
+/// let client = Client::builder()
+///     .digest_auth("Mufasa", Some("Circle of Life"))
+///     .finish();
+///
+/// // the challenge of the first response is answered with the credentials
+/// let res = client.get("https://pacs.example.com/studies").send().await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [RFC 7616]: https://www.rfc-editor.org/rfc/rfc7616
+/// [`ClientRequest`]: crate::ClientRequest::digest_auth
+/// [`ClientBuilder`]: crate::ClientBuilder::digest_auth
+/// [`ChildClientBuilder`]: crate::ChildClientBuilder::digest_auth
+/// [`ClientBuilder::wrap()`]: crate::ClientBuilder::wrap
+#[derive(Clone)]
+pub struct DigestAuth {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    username: String,
+    password: String,
+
+    /// Last challenge of each origin.
+    challenges: RefCell<Vec<(String, Challenge)>>,
+}
+
+impl DigestAuth {
+    /// Constructs a middleware answering challenges with the given credentials.
+    pub fn new(username: impl fmt::Display, password: impl fmt::Display) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                username: username.to_string(),
+                password: password.to_string(),
+                challenges: RefCell::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Stores `challenge` as the last challenge of the origin of `uri`.
+    fn store(&self, uri: &Uri, challenge: Challenge) {
+        let Some(origin) = origin(uri) else {
+            return;
+        };
+
+        let mut challenges = self.inner.challenges.borrow_mut();
+        challenges.retain(|(existing, _)| *existing != origin);
+        challenges.push((origin, challenge));
+    }
+
+    /// Returns the `Authorization` header of a request answering the last challenge of its origin,
+    /// if there is one.
+    fn authorization(&self, method: &Method, uri: &Uri) -> Option<HeaderValue> {
+        let origin = origin(uri)?;
+
+        let mut challenges = self.inner.challenges.borrow_mut();
+        let (_, challenge) = challenges
+            .iter_mut()
+            .find(|(existing, _)| *existing == origin)?;
+
+        challenge.nc += 1;
+
+        let cnonce = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect::<String>();
+
+        let target = uri.path_and_query().map_or("/", |target| target.as_str());
+        let value = challenge.answer(
+            &self.inner.username,
+            &self.inner.password,
+            method,
+            target,
+            &cnonce,
+        );
+
+        HeaderValue::try_from(value).ok()
+    }
+
+    /// Uses the next nonce of the `Authentication-Info` header of a response, if it has one, for
+    /// the following requests to the origin of `uri`.
+    fn next_nonce(&self, uri: &Uri, headers: &HeaderMap) {
+        let Some(next_nonce) = headers
+            .get(AUTHENTICATION_INFO)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| {
+                let info = parse_challenges(value);
+                param(&info.first()?.1, "nextnonce").map(ToOwned::to_owned)
+            })
+        else {
+            return;
+        };
+
+        let Some(origin) = origin(uri) else {
+            return;
+        };
+
+        let mut challenges = self.inner.challenges.borrow_mut();
+
+        if let Some((_, challenge)) = challenges
+            .iter_mut()
+            .find(|(existing, _)| *existing == origin)
+        {
+            challenge.nonce = next_nonce;
+            challenge.nc = 0;
+        }
+    }
+}
+
+impl fmt::Debug for DigestAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DigestAuth")
+            .field("username", &self.inner.username)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> Transform<S, ConnectRequest> for DigestAuth
+where
+    S: Service<ConnectRequest, Response = ConnectResponse, Error = SendRequestError> + 'static,
+{
+    type Transform = DigestAuthService<S>;
+
+    fn new_transform(self, service: S) -> Self::Transform {
+        DigestAuthService {
+            service: Rc::new(service),
+            auth: self,
+        }
+    }
+}
+
+/// Service of a [`DigestAuth`] middleware.
+pub struct DigestAuthService<S> {
+    service: Rc<S>,
+    auth: DigestAuth,
+}
+
+impl<S> Service<ConnectRequest> for DigestAuthService<S>
+where
+    S: Service<ConnectRequest, Response = ConnectResponse, Error = SendRequestError> + 'static,
+{
+    type Response = ConnectResponse;
+    type Error = SendRequestError;
+    type Future = LocalBoxFuture<'static, Result<ConnectResponse, SendRequestError>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ConnectRequest) -> Self::Future {
+        let (mut head, body, addr) = match req {
+            ConnectRequest::Client(head, body, addr) if !has_authorization(&head) => {
+                (head, body, addr)
+            }
+            _ => return Box::pin(self.service.call(req)),
+        };
+
+        let method = head.as_ref().method.clone();
+        let uri = head.as_ref().uri.clone();
+
+        if let Some(value) = self.auth.authorization(&method, &uri) {
+            set_authorization(&mut head, value);
+        }
+
+        // keep the request for answering a challenge, unless its body can not be sent again
+        let retry = match body {
+            AnyBody::None => Some((clone_head(&head), AnyBody::None)),
+            AnyBody::Bytes { ref body } => {
+                Some((clone_head(&head), AnyBody::Bytes { body: body.clone() }))
+            }
+            AnyBody::Body { .. } => None,
+        };
+
+        let fut = self.service.call(ConnectRequest::Client(head, body, addr));
+        let service = Rc::clone(&self.service);
+        let auth = self.auth.clone();
+
+        Box::pin(async move {
+            let res = fut.await?;
+
+            let challenge = match res {
+                ConnectResponse::Client(ref res) if res.status() == StatusCode::UNAUTHORIZED => {
+                    challenge(res.headers())
+                }
+                ConnectResponse::Client(ref res) => {
+                    auth.next_nonce(&uri, res.headers());
+                    None
+                }
+                ConnectResponse::Tunnel(..) => None,
+            };
+
+            let (Some(challenge), Some((mut head, body))) = (challenge, retry) else {
+                return Ok(res);
+            };
+
+            auth.store(&uri, challenge);
+
+            let Some(value) = auth.authorization(&method, &uri) else {
+                return Ok(res);
+            };
+
+            set_authorization(&mut head, value);
+
+            let res = service
+                .call(ConnectRequest::Client(head, body, addr))
+                .await?;
+
+            if let ConnectResponse::Client(ref res) = res {
+                auth.next_nonce(&uri, res.headers());
+            }
+
+            Ok(res)
+        })
+    }
+}
+
+/// Hash algorithm of a challenge, in order of strength.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Algorithm {
+    Md5,
+    Sha256,
+    Sha512Trunc256,
+}
+
+impl Algorithm {
+    /// Returns the lowercase hex encoded hash of `data`.
+    fn hash(self, data: &str) -> String {
+        match self {
+            Algorithm::Md5 => hex(&Md5::digest(data)),
+            Algorithm::Sha256 => hex(&Sha256::digest(data)),
+            Algorithm::Sha512Trunc256 => hex(&Sha512_256::digest(data)),
+        }
+    }
+
+    fn name(self, session: bool) -> &'static str {
+        match (self, session) {
+            (Algorithm::Md5, false) => "MD5",
+            (Algorithm::Md5, true) => "MD5-sess",
+            (Algorithm::Sha256, false) => "SHA-256",
+            (Algorithm::Sha256, true) => "SHA-256-sess",
+            (Algorithm::Sha512Trunc256, false) => "SHA-512-256",
+            (Algorithm::Sha512Trunc256, true) => "SHA-512-256-sess",
+        }
+    }
+}
+
+/// `Digest` challenge of a server.
+#[derive(Debug, Clone)]
+struct Challenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: Algorithm,
+    session: bool,
+
+    /// Whether the `auth` quality of protection is used, rather than the RFC 2069 scheme.
+    qop: bool,
+    userhash: bool,
+
+    /// Number of requests that answered the nonce.
+    nc: u32,
+}
+
+impl Challenge {
+    /// Parses the parameters of a `Digest` challenge, if it is supported.
+    fn from_params(params: &[(&str, String)]) -> Option<Self> {
+        let (algorithm, session) = match param(params, "algorithm")
+            .unwrap_or("MD5")
+            .to_ascii_uppercase()
+            .as_str()
+        {
+            "MD5" => (Algorithm::Md5, false),
+            "MD5-SESS" => (Algorithm::Md5, true),
+            "SHA-256" => (Algorithm::Sha256, false),
+            "SHA-256-SESS" => (Algorithm::Sha256, true),
+            "SHA-512-256" => (Algorithm::Sha512Trunc256, false),
+            "SHA-512-256-SESS" => (Algorithm::Sha512Trunc256, true),
+            _ => return None,
+        };
+
+        let qop = match param(params, "qop") {
+            Some(qop)
+                if qop
+                    .split(',')
+                    .any(|qop| qop.trim().eq_ignore_ascii_case("auth")) =>
+            {
+                true
+            }
+            Some(_) => return None,
+            None => false,
+        };
+
+        // session algorithms need the client nonce, which is only sent with a quality of protection
+        if session && !qop {
+            return None;
+        }
+
+        Some(Self {
+            realm: param(params, "realm")?.to_owned(),
+            nonce: param(params, "nonce")?.to_owned(),
+            opaque: param(params, "opaque").map(ToOwned::to_owned),
+            algorithm,
+            session,
+            qop,
+            userhash: param(params, "userhash")
+                .is_some_and(|userhash| userhash.eq_ignore_ascii_case("true")),
+            nc: 0,
+        })
+    }
+
+    /// Returns the `Authorization` header value answering the challenge for a request.
+    fn answer(
+        &self,
+        username: &str,
+        password: &str,
+        method: &Method,
+        target: &str,
+        cnonce: &str,
+    ) -> String {
+        let hash = |data: String| self.algorithm.hash(&data);
+        let nc = format!("{:08x}", self.nc);
+
+        let mut ha1 = hash(format!("{username}:{}:{password}", self.realm));
+        if self.session {
+            ha1 = hash(format!("{ha1}:{}:{cnonce}", self.nonce));
+        }
+
+        let ha2 = hash(format!("{method}:{target}"));
+
+        let response = if self.qop {
+            hash(format!("{ha1}:{}:{nc}:{cnonce}:auth:{ha2}", self.nonce))
+        } else {
+            hash(format!("{ha1}:{}:{ha2}", self.nonce))
+        };
+
+        let mut value = if self.userhash {
+            let username = hash(format!("{username}:{}", self.realm));
+            format!("Digest username={}", quote(&username))
+        } else if username.is_ascii() && !username.chars().any(|c| c.is_ascii_control()) {
+            format!("Digest username={}", quote(username))
+        } else {
+            let username = utf8_percent_encode(username, NON_ALPHANUMERIC);
+            format!("Digest username*=UTF-8''{username}")
+        };
+
+        let _ = write!(
+            value,
+            ", realm={}, uri={}, algorithm={}, nonce={}",
+            quote(&self.realm),
+            quote(target),
+            self.algorithm.name(self.session),
+            quote(&self.nonce)
+        );
+
+        if self.qop {
+            let _ = write!(value, ", nc={nc}, cnonce={}, qop=auth", quote(cnonce));
+        }
+
+        let _ = write!(value, ", response=\"{response}\"");
+
+        if let Some(opaque) = &self.opaque {
+            let _ = write!(value, ", opaque={}", quote(opaque));
+        }
+
+        if self.userhash {
+            value.push_str(", userhash=true");
+        }
+
+        value
+    }
+}
+
+/// Returns the strongest supported `Digest` challenge of the `WWW-Authenticate` headers.
+fn challenge(headers: &HeaderMap) -> Option<Challenge> {
+    headers
+        .get_all(WWW_AUTHENTICATE)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(parse_challenges)
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("Digest"))
+        .filter_map(|(_, params)| Challenge::from_params(&params))
+        .max_by_key(|challenge| challenge.algorithm)
+}
+
+/// Parses the challenges of a `WWW-Authenticate` header value into their schemes and parameters.
+///
+/// Parameters before the first scheme, as in `Authentication-Info` headers, are listed under an
+/// empty scheme.
+fn parse_challenges(value: &str) -> Vec<(&str, Vec<(&str, String)>)> {
+    let mut challenges = Vec::<(&str, Vec<(&str, String)>)>::new();
+    let mut rest = value;
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c == ',' || c.is_ascii_whitespace());
+
+        let (token, after) = split_token(rest);
+
+        if token.is_empty() {
+            // skip characters that do not start a token, such as those of token68 credentials
+            let mut chars = after.chars();
+            if chars.next().is_none() {
+                break;
+            }
+
+            rest = chars.as_str();
+            continue;
+        }
+
+        let after = after.trim_start_matches([' ', '\t']);
+
+        let Some(value) = after.strip_prefix('=') else {
+            challenges.push((token, Vec::new()));
+            rest = after;
+            continue;
+        };
+
+        let value = value.trim_start_matches([' ', '\t']);
+
+        let (value, after) = match value.strip_prefix('"') {
+            Some(quoted) => unquote(quoted),
+            None => {
+                let (value, after) = split_token(value);
+                (value.to_owned(), after)
+            }
+        };
+
+        if challenges.is_empty() {
+            challenges.push(("", Vec::new()));
+        }
+
+        challenges.last_mut().unwrap().1.push((token, value));
+        rest = after;
+    }
+
+    challenges
+}
+
+/// Returns the value of the parameter `name` of a challenge.
+fn param<'a>(params: &'a [(&str, String)], name: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Splits the token at the start of `value` from the rest.
+fn split_token(value: &str) -> (&str, &str) {
+    let is_tchar = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+    let end = value.find(|c| !is_tchar(c)).unwrap_or(value.len());
+    value.split_at(end)
+}
+
+/// Unescapes the quoted string at the start of `value`, whose opening quote is already removed,
+/// and returns it with the rest of `value`.
+fn unquote(value: &str) -> (String, &str) {
+    let mut unquoted = String::new();
+    let mut chars = value.char_indices();
+
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '"' => return (unquoted, &value[idx + 1..]),
+            '\\' => unquoted.extend(chars.next().map(|(_, c)| c)),
+            c => unquoted.push(c),
+        }
+    }
+
+    (unquoted, "")
+}
+
+/// Returns `value` as a quoted string.
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+
+        quoted.push(c);
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/// Returns the scheme and lowercase authority of `uri`.
+fn origin(uri: &Uri) -> Option<String> {
+    let authority = uri.authority()?.as_str().to_ascii_lowercase();
+    Some(format!("{}://{authority}", uri.scheme_str()?))
+}
+
+fn has_authorization(head: &RequestHeadType) -> bool {
+    head.as_ref().headers.contains_key(AUTHORIZATION)
+        || head
+            .extra_headers()
+            .is_some_and(|headers| headers.contains_key(AUTHORIZATION))
+}
+
+/// Sets the `Authorization` header of a request.
+fn set_authorization(head: &mut RequestHeadType, value: HeaderValue) {
+    match head {
+        RequestHeadType::Owned(head) => {
+            head.headers.insert(AUTHORIZATION, value);
+        }
+
+        RequestHeadType::Rc(_, extra_headers) => {
+            // extra headers replace the headers of the shared head
+            extra_headers
+                .get_or_insert_with(HeaderMap::new)
+                .insert(AUTHORIZATION, value);
+        }
+    }
+}
+
+fn clone_head(head: &RequestHeadType) -> RequestHeadType {
+    match head {
+        RequestHeadType::Owned(head) => RequestHeadType::Owned(head.clone()),
+        RequestHeadType::Rc(head, extra_headers) => {
+            RequestHeadType::Rc(Rc::clone(head), extra_headers.clone())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn challenge_of(values: &[&'static str]) -> Option<Challenge> {
+        let mut headers = HeaderMap::new();
+
+        for value in values {
+            headers.append(WWW_AUTHENTICATE, HeaderValue::from_static(value));
+        }
+
+        challenge(&headers)
+    }
+
+    #[test]
+    fn parse() {
+        let challenges = parse_challenges(
+            r#"Negotiate YII=, Basic realm="a, b", Digest realm="x\"y", nonce=abc, qop="auth""#,
+        );
+
+        let schemes = challenges
+            .iter()
+            .map(|(scheme, _)| *scheme)
+            .collect::<Vec<_>>();
+        assert_eq!(schemes, ["Negotiate", "Basic", "Digest"]);
+        assert_eq!(param(&challenges[1].1, "realm"), Some("a, b"));
+        assert_eq!(param(&challenges[2].1, "REALM"), Some("x\"y"));
+        assert_eq!(param(&challenges[2].1, "nonce"), Some("abc"));
+
+        let info = parse_challenges(r#"nextnonce="n2", qop=auth"#);
+        assert_eq!(info[0].0, "");
+        assert_eq!(param(&info[0].1, "nextnonce"), Some("n2"));
+    }
+
+    #[test]
+    fn strongest_challenge() {
+        let challenge = challenge_of(&[
+            r#"Digest realm="r", nonce="n1", qop="auth", algorithm=MD5"#,
+            concat!(
+                r#"Basic realm="r", "#,
+                r#"Digest realm="r", nonce="n2", qop="auth-int, auth", algorithm=SHA-256"#,
+            ),
+            r#"Digest realm="r", nonce="n3", qop="auth", algorithm=SHA-1"#,
+        ])
+        .unwrap();
+
+        assert_eq!(challenge.algorithm, Algorithm::Sha256);
+        assert_eq!(challenge.nonce, "n2");
+
+        assert!(challenge_of(&[r#"Digest realm="r", nonce="n", qop="auth-int""#]).is_none());
+        assert!(challenge_of(&[r#"Digest realm="r", algorithm=MD5-sess, nonce="n""#]).is_none());
+        assert!(challenge_of(&[r#"Basic realm="r""#]).is_none());
+    }
+
+    #[test]
+    fn rfc_7616_examples() {
+        let answer = |value: &'static str| {
+            let mut challenge = challenge_of(&[value]).unwrap();
+            challenge.nc = 1;
+
+            challenge.answer(
+                "Mufasa",
+                "Circle of Life",
+                &Method::GET,
+                "/dir/index.html",
+                "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ",
+            )
+        };
+
+        let md5 = answer(concat!(
+            r#"Digest realm="http-auth@example.org", qop="auth, auth-int", algorithm=MD5, "#,
+            r#"nonce="7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", "#,
+            r#"opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS""#,
+        ));
+        assert_eq!(
+            md5,
+            "Digest username=\"Mufasa\", realm=\"http-auth@example.org\", \
+             uri=\"/dir/index.html\", algorithm=MD5, \
+             nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", nc=00000001, \
+             cnonce=\"f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ\", qop=auth, \
+             response=\"8ca523f5e9506fed4657c9700eebdbec\", \
+             opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\""
+        );
+
+        let sha256 = answer(concat!(
+            r#"Digest realm="http-auth@example.org", qop="auth, auth-int", algorithm=SHA-256, "#,
+            r#"nonce="7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", "#,
+            r#"opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS""#,
+        ));
+        assert!(sha256.contains(
+            "response=\"753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1\""
+        ));
+
+        let userhash = answer(concat!(
+            r#"Digest realm="http-auth@example.org", qop="auth", algorithm=SHA-256, "#,
+            r#"nonce="n", userhash=true"#,
+        ));
+        assert!(userhash.starts_with(
+            "Digest username=\"a947aad205e80e429958a387394944c6b496301e79f89d35a4cc23b6ee12b5b6\""
+        ));
+        assert!(userhash.ends_with(", userhash=true"));
+    }
+
+    #[test]
+    fn rfc_2617_example() {
+        let mut challenge = challenge_of(&[concat!(
+            r#"Digest realm="testrealm@host.com", qop="auth,auth-int", "#,
+            r#"nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", "#,
+            r#"opaque="5ccc069c403ebaf9f0171e9517f40e41""#,
+        )])
+        .unwrap();
+        challenge.nc = 1;
+
+        let answer = challenge.answer(
+            "Mufasa",
+            "Circle Of Life",
+            &Method::GET,
+            "/dir/index.html",
+            "0a4f113b",
+        );
+        assert!(answer.contains("response=\"6629fae49393a05397450978507c4ef1\""));
+    }
+
+    #[test]
+    fn non_ascii_username() {
+        let challenge = challenge_of(&[r#"Digest realm="r", nonce="n", qop="auth""#]).unwrap();
+        let answer = challenge.answer("J\u{e4}son", "pw", &Method::GET, "/", "c");
+        assert!(answer.starts_with("Digest username*=UTF-8''J%C3%A4son, "));
+    }
+
+    #[test]
+    fn remembers_challenges_per_origin() {
+        let auth = DigestAuth::new("user", "pw");
+        let uri = |uri: &str| uri.parse::<Uri>().unwrap();
+
+        assert!(auth
+            .authorization(&Method::GET, &uri("https://a.example/x"))
+            .is_none());
+
+        let challenge = challenge_of(&[r#"Digest realm="r", nonce="n1", qop="auth""#]).unwrap();
+        auth.store(&uri("https://a.example/login"), challenge);
+
+        let value = auth
+            .authorization(&Method::GET, &uri("https://A.example/x?y=1"))
+            .unwrap();
+        let value = value.to_str().unwrap();
+        assert!(value.contains("uri=\"/x?y=1\""));
+        assert!(value.contains("nc=00000001"));
+
+        let value = auth
+            .authorization(&Method::GET, &uri("https://a.example/x"))
+            .unwrap();
+        assert!(value.to_str().unwrap().contains("nc=00000002"));
+
+        assert!(auth
+            .authorization(&Method::GET, &uri("http://a.example/x"))
+            .is_none());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHENTICATION_INFO,
+            HeaderValue::from_static(r#"nextnonce="n2""#),
+        );
+        auth.next_nonce(&uri("https://a.example/x"), &headers);
+
+        let value = auth
+            .authorization(&Method::GET, &uri("https://a.example/x"))
+            .unwrap();
+        let value = value.to_str().unwrap();
+        assert!(value.contains("nonce=\"n2\""));
+        assert!(value.contains("nc=00000001"));
+    }
+}
@@ -1,10 +1,30 @@
+mod cache;
+mod circuit_breaker;
+#[cfg(feature = "cookies")]
+mod cookie_store;
+mod digest_auth;
+mod rate_limit;
 mod redirect;
+mod sign_request;
+mod wrap_fn;
 
 use std::marker::PhantomData;
 
+use actix_http::Uri;
 use actix_service::Service;
 
-pub use self::redirect::Redirect;
+pub use self::{
+    cache::{Cache, CacheService, CacheStorage, CachedResponse, MemoryCacheStorage},
+    circuit_breaker::{CircuitBreaker, CircuitBreakerService, CircuitState},
+    digest_auth::{DigestAuth, DigestAuthService},
+    rate_limit::{RateLimit, RateLimitService},
+    redirect::{Redirect, RedirectAttempt},
+    sign_request::{RequestToSign, SignRequest, SignRequestService},
+    wrap_fn::{WrapFn, WrapFnService},
+};
+
+#[cfg(feature = "cookies")]
+pub use self::cookie_store::{CookieStore, CookieStoreService};
 
 /// Trait for transform a type to another one.
 /// Both the input and output type should impl [actix_service::Service] trait.
@@ -69,3 +89,38 @@ where
         service
     }
 }
+
+/// Returns the lowercase host and port of `uri`, which identify the host in per-host middleware.
+fn host_key(uri: &Uri) -> Option<String> {
+    let host = uri.host()?.to_ascii_lowercase();
+
+    let port = uri.port_u16().or(match uri.scheme_str() {
+        Some("https" | "wss") => Some(443),
+        Some("http" | "ws") => Some(80),
+        _ => None,
+    });
+
+    Some(match port {
+        Some(port) => format!("{host}:{port}"),
+        None => host,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys() {
+        let key = |uri: &'static str| host_key(&Uri::from_static(uri));
+
+        assert_eq!(
+            key("https://API.example.com/v1").unwrap(),
+            "api.example.com:443"
+        );
+        assert_eq!(key("http://example.com:8080/").unwrap(), "example.com:8080");
+        assert_eq!(key("ws://example.com/").unwrap(), "example.com:80");
+        assert_eq!(key("http://[::1]/").unwrap(), "[::1]:80");
+        assert!(key("/relative").is_none());
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use actix_rt::time::sleep;
+use actix_service::Service;
+use futures_core::future::LocalBoxFuture;
+
+use super::{host_key, Transform};
+use crate::{
+    client::SendRequestError,
+    connect::{ConnectRequest, ConnectResponse},
+};
+
+/// Middleware for limiting the rate of requests sent to each host.
+///
+/// Every host and port that requests are sent to has its own token bucket, which holds up to
+/// [`burst`](Self::burst) tokens and is refilled at the configured rate. Sending a request takes
+/// a token. When the bucket of a host is empty, requests wait for a token in the order they were
+/// sent, or fail with [`SendRequestError::RateLimited`] if they would wait longer than the
+/// [maximum delay](Self::max_delay).
+///
+/// The limit applies to every request the connector is called with, including each redirect and
+/// WebSocket handshake. The time requests spend waiting counts towards their timeout. Clones of a
+/// `RateLimit` share their buckets, so a single limit can be applied to several clients.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use awc::{middleware::RateLimit, ClientBuilder};
+///
+/// // 10 requests per second to each host, with bursts of up to 5 requests
+/// let client = ClientBuilder::new()
+///     .wrap(RateLimit::new(10, Duration::from_secs(1)).burst(5))
+///     .finish();
+/// ```
+#[derive(Clone)]
+pub struct RateLimit {
+    interval: Duration,
+    burst: u32,
+    max_delay: Option<Duration>,
+
+    /// Time at which each bucket is full again, by host and port.
+    buckets: Rc<RefCell<HashMap<String, Instant>>>,
+}
+
+impl RateLimit {
+    /// Constructs a middleware that sends up to `requests` requests to each host `per` period.
+    ///
+    /// # Panics
+    /// Panics if `requests` is zero.
+    pub fn new(requests: u32, per: Duration) -> Self {
+        assert!(requests > 0, "rate limit must allow at least one request");
+
+        Self {
+            interval: per / requests,
+            burst: 1,
+            max_delay: None,
+            buckets: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+
+    /// Sets the number of requests that can be sent to a host at once after not sending any for
+    /// a while.
+    ///
+    /// Requests beyond the burst are spread evenly over the period. Upstream quotas that count
+    /// requests in fixed windows can be exceeded by a burst at the end of one window followed by
+    /// one at the start of the next, so a burst larger than one is only safe with some headroom.
+    /// Defaults to 1.
+    ///
+    /// # Panics
+    /// Panics if `burst` is zero.
+    pub fn burst(mut self, burst: u32) -> Self {
+        assert!(burst > 0, "rate limit burst must be at least one request");
+        self.burst = burst;
+        self
+    }
+
+    /// Sets the longest time that requests wait for their host's rate limit.
+    ///
+    /// Requests that would have to wait longer fail immediately with
+    /// [`SendRequestError::RateLimited`]. To fail requests instead of delaying them at all, set
+    /// the maximum delay to zero. By default, requests wait as long as needed.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = Some(max_delay);
+        self
+    }
+
+    /// Takes a token from the bucket of `key`, returning how long the request has to wait for it.
+    fn acquire(&self, key: String, now: Instant) -> Result<Duration, SendRequestError> {
+        let mut buckets = self.buckets.borrow_mut();
+
+        // full buckets behave like missing ones, so drop them before adding a host
+        if !buckets.contains_key(&key) {
+            buckets.retain(|_, full_at| *full_at > now);
+        }
+
+        let full_at = buckets.get(&key).map_or(now, |full_at| (*full_at).max(now)) + self.interval;
+        let delay = (full_at - now).saturating_sub(self.interval * self.burst);
+
+        if self.max_delay.is_some_and(|max_delay| delay > max_delay) {
+            return Err(SendRequestError::RateLimited(key));
+        }
+
+        buckets.insert(key, full_at);
+        Ok(delay)
+    }
+}
+
+impl fmt::Debug for RateLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimit")
+            .field("interval", &self.interval)
+            .field("burst", &self.burst)
+            .field("max_delay", &self.max_delay)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> Transform<S, ConnectRequest> for RateLimit
+where
+    S: Service<ConnectRequest, Response = ConnectResponse, Error = SendRequestError> + 'static,
+{
+    type Transform = RateLimitService<S>;
+
+    fn new_transform(self, service: S) -> Self::Transform {
+        RateLimitService {
+            limit: self,
+            connector: Rc::new(service),
+        }
+    }
+}
+
+/// Service of a [`RateLimit`] middleware.
+pub struct RateLimitService<S> {
+    limit: RateLimit,
+    connector: Rc<S>,
+}
+
+impl<S> Service<ConnectRequest> for RateLimitService<S>
+where
+    S: Service<ConnectRequest, Response = ConnectResponse, Error = SendRequestError> + 'static,
+{
+    type Response = ConnectResponse;
+    type Error = SendRequestError;
+    type Future = LocalBoxFuture<'static, Result<ConnectResponse, SendRequestError>>;
+
+    actix_service::forward_ready!(connector);
+
+    fn call(&self, req: ConnectRequest) -> Self::Future {
+        let uri = match req {
+            ConnectRequest::Client(ref head, ..) => &head.as_ref().uri,
+            ConnectRequest::Tunnel(ref head, ..) => &head.uri,
+        };
+
+        // requests without a host fail in the connector
+        let delay = match host_key(uri) {
+            Some(key) => self.limit.acquire(key, Instant::now()),
+            None => Ok(Duration::ZERO),
+        };
+
+        let connector = Rc::clone(&self.connector);
+
+        Box::pin(async move {
+            let delay = delay?;
+
+            if !delay.is_zero() {
+                sleep(delay).await;
+            }
+
+            connector.call(req).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{web, App, HttpResponse};
+
+    use super::*;
+    use crate::ClientBuilder;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn token_bucket() {
+        let limit = RateLimit::new(4, SECOND).burst(2);
+        let interval = SECOND / 4;
+        let now = Instant::now();
+        let acquire = |key: &str, now| limit.acquire(key.to_owned(), now).unwrap();
+
+        // burst is sent at once, later requests are spread over the period
+        assert_eq!(acquire("a:80", now), Duration::ZERO);
+        assert_eq!(acquire("a:80", now), Duration::ZERO);
+        assert_eq!(acquire("a:80", now), interval);
+        assert_eq!(acquire("a:80", now), interval * 2);
+
+        // hosts have their own buckets
+        assert_eq!(acquire("b:80", now), Duration::ZERO);
+
+        // bucket refills over time
+        let later = now + interval * 2;
+        assert_eq!(acquire("a:80", later), interval);
+
+        // full buckets are dropped when another host is added
+        let later = now + SECOND * 2;
+        assert_eq!(acquire("c:80", later), Duration::ZERO);
+        assert_eq!(limit.buckets.borrow().len(), 1);
+    }
+
+    #[test]
+    fn max_delay() {
+        let limit = RateLimit::new(1, SECOND).max_delay(SECOND / 2);
+        let now = Instant::now();
+
+        assert!(limit.acquire("a:80".to_owned(), now).is_ok());
+
+        let err = limit.acquire("a:80".to_owned(), now).unwrap_err();
+        assert!(matches!(err, SendRequestError::RateLimited(ref key) if key == "a:80"));
+
+        // rejected requests do not take a token
+        assert!(limit.acquire("a:80".to_owned(), now + SECOND / 2).is_ok());
+    }
+
+    #[actix_rt::test]
+    async fn limits_requests() {
+        let srv = actix_test::start(|| {
+            App::new().default_service(web::to(|| async { HttpResponse::Ok().finish() }))
+        });
+
+        let limit = RateLimit::new(5, SECOND);
+        let client = ClientBuilder::new().wrap(limit.clone()).finish();
+
+        let start = Instant::now();
+        for _ in 0..3 {
+            let res = client.get(srv.url("/")).send().await.unwrap();
+            assert!(res.status().is_success());
+        }
+        assert!(start.elapsed() >= SECOND / 5 * 2);
+
+        // clones share buckets, so a client failing instead of waiting is rejected right away
+        let client = ClientBuilder::new()
+            .wrap(limit.max_delay(Duration::ZERO))
+            .finish();
+
+        let err = client.get(srv.url("/")).send().await.unwrap_err();
+        assert!(matches!(err, SendRequestError::RateLimited(_)));
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    fmt,
     future::Future,
     net::SocketAddr,
     pin::Pin,
@@ -6,7 +7,10 @@ use std::{
     task::{Context, Poll},
 };
 
-use actix_http::{header, Method, RequestHead, RequestHeadType, StatusCode, Uri};
+use actix_http::{
+    body::{BodySize, MessageBody as _},
+    header, Method, RequestHead, RequestHeadType, StatusCode, Uri,
+};
 use actix_service::Service;
 use bytes::Bytes;
 use futures_core::ready;
@@ -19,8 +23,38 @@ use crate::{
     ClientResponse,
 };
 
+/// Middleware for following redirect responses.
+///
+/// Responses with a `301`, `302`, `303`, `307`, or `308` status code and a `Location` header are
+/// followed. `307` and `308` redirects repeat the request with the same method and body, while the
+/// others repeat it as a `GET` request without a body, unless it was a `HEAD` request.
+///
+/// A redirect response is returned to the caller, instead of being followed, once the maximum
+/// number of redirects is reached or if the [filter](Self::filter) vetoes it.
+///
+/// # Examples
+/// ```
+/// use awc::{middleware::Redirect, ClientBuilder};
+///
+/// let client = ClientBuilder::new()
+///     .redirect_policy(
+///         Redirect::new()
+///             .max_redirect_times(3)
+///             .replay_body(false)
+///             .filter(|attempt| attempt.next_uri().scheme_str() == Some("https")),
+///     )
+///     .finish();
+/// ```
 pub struct Redirect {
     max_redirect_times: u8,
+    policy: Policy,
+}
+
+#[derive(Clone)]
+struct Policy {
+    replay_body: bool,
+    strip_sensitive_headers: bool,
+    filter: Option<Rc<dyn Fn(&RedirectAttempt<'_>) -> bool>>,
 }
 
 impl Default for Redirect {
@@ -30,16 +64,108 @@ impl Default for Redirect {
 }
 
 impl Redirect {
+    /// Constructs a redirect middleware that follows up to 10 redirects.
     pub fn new() -> Self {
         Self {
             max_redirect_times: 10,
+            policy: Policy {
+                replay_body: true,
+                strip_sensitive_headers: true,
+                filter: None,
+            },
         }
     }
 
+    /// Sets the maximum number of redirects that are followed for a request.
     pub fn max_redirect_times(mut self, times: u8) -> Self {
         self.max_redirect_times = times;
         self
     }
+
+    /// Sets whether `307` and `308` redirects of requests with a body are followed.
+    ///
+    /// These redirects are followed by sending the same body again. Bodies that can not be sent
+    /// again, such as streams, are replaced by an empty body. If disabled, the redirect response
+    /// of a request with a body is returned instead. Enabled by default.
+    pub fn replay_body(mut self, replay: bool) -> Self {
+        self.policy.replay_body = replay;
+        self
+    }
+
+    /// Sets whether `Authorization`, `Proxy-Authorization`, and `Cookie` headers are removed from
+    /// requests that are redirected to a different origin.
+    ///
+    /// The origin is different if the scheme, host, or port of the redirect target is. Enabled by
+    /// default.
+    pub fn strip_sensitive_headers(mut self, strip: bool) -> Self {
+        self.policy.strip_sensitive_headers = strip;
+        self
+    }
+
+    /// Sets a function that decides whether a redirect is followed.
+    ///
+    /// The function is called before each redirect that would otherwise be followed. If it returns
+    /// false, the redirect response is returned instead.
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&RedirectAttempt<'_>) -> bool + 'static,
+    {
+        self.policy.filter = Some(Rc::new(filter));
+        self
+    }
+
+    pub(crate) fn max_times(&self) -> u8 {
+        self.max_redirect_times
+    }
+}
+
+impl fmt::Debug for Redirect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Redirect")
+            .field("max_redirect_times", &self.max_redirect_times)
+            .field("replay_body", &self.policy.replay_body)
+            .field(
+                "strip_sensitive_headers",
+                &self.policy.strip_sensitive_headers,
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+/// Redirect that is about to be followed, as passed to [`Redirect::filter()`].
+#[derive(Debug)]
+pub struct RedirectAttempt<'a> {
+    status: StatusCode,
+    method: &'a Method,
+    prev_uri: &'a Uri,
+    next_uri: &'a Uri,
+}
+
+impl RedirectAttempt<'_> {
+    /// Returns the status code of the redirect response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Returns the method of the request that was redirected.
+    pub fn method(&self) -> &Method {
+        self.method
+    }
+
+    /// Returns the URI of the request that was redirected.
+    pub fn previous_uri(&self) -> &Uri {
+        self.prev_uri
+    }
+
+    /// Returns the URI that the request is redirected to.
+    pub fn next_uri(&self) -> &Uri {
+        self.next_uri
+    }
+
+    /// Returns true if the redirect target has a different origin than the redirected request.
+    pub fn is_cross_origin(&self) -> bool {
+        !is_same_origin(self.prev_uri, self.next_uri)
+    }
 }
 
 impl<S> Transform<S, ConnectRequest> for Redirect
@@ -51,6 +177,7 @@ where
     fn new_transform(self, service: S) -> Self::Transform {
         RedirectService {
             max_redirect_times: self.max_redirect_times,
+            policy: Rc::new(self.policy),
             connector: Rc::new(service),
         }
     }
@@ -58,6 +185,7 @@ where
 
 pub struct RedirectService<S> {
     max_redirect_times: u8,
+    policy: Rc<Policy>,
     connector: Rc<S>,
 }
 
@@ -91,6 +219,7 @@ where
                     }
                 };
 
+                let has_body = !matches!(body.size(), BodySize::None | BodySize::Sized(0));
                 let body_opt = match body {
                     AnyBody::Bytes { ref body } => Some(body.clone()),
                     _ => None,
@@ -101,10 +230,12 @@ where
                 RedirectServiceFuture::Client {
                     fut,
                     max_redirect_times,
+                    policy: Rc::clone(&self.policy),
                     uri: Some(uri),
                     method: Some(method),
                     headers: Some(headers),
                     body: body_opt,
+                    has_body,
                     addr,
                     connector: Some(connector),
                 }
@@ -125,10 +256,12 @@ pin_project_lite::pin_project! {
             #[pin]
             fut: S::Future,
             max_redirect_times: u8,
+            policy: Rc<Policy>,
             uri: Option<Uri>,
             method: Option<Method>,
             headers: Option<header::HeaderMap>,
             body: Option<Bytes>,
+            has_body: bool,
             addr: Option<SocketAddr>,
             connector: Option<Rc<S>>,
         }
@@ -147,10 +280,12 @@ where
             RedirectServiceProj::Client {
                 fut,
                 max_redirect_times,
+                policy,
                 uri,
                 method,
                 headers,
                 body,
+                has_body,
                 addr,
                 connector,
             } => match ready!(fut.poll(cx))? {
@@ -166,14 +301,32 @@ where
                         let reuse_body = res.head().status == StatusCode::TEMPORARY_REDIRECT
                             || res.head().status == StatusCode::PERMANENT_REDIRECT;
 
+                        if reuse_body && *has_body && !policy.replay_body {
+                            return Poll::Ready(Ok(ConnectResponse::Client(res)));
+                        }
+
                         let prev_uri = uri.take().unwrap();
 
                         // rebuild uri from the location header value.
                         let next_uri = build_next_uri(&res, &prev_uri)?;
 
+                        if let Some(filter) = &policy.filter {
+                            let attempt = RedirectAttempt {
+                                status: res.head().status,
+                                method: method.as_ref().unwrap(),
+                                prev_uri: &prev_uri,
+                                next_uri: &next_uri,
+                            };
+
+                            if !filter(&attempt) {
+                                return Poll::Ready(Ok(ConnectResponse::Client(res)));
+                            }
+                        }
+
                         // take ownership of states that could be reused
                         let addr = addr.take();
                         let connector = connector.take();
+                        let policy = Rc::clone(policy);
 
                         // reset method
                         let method = if reuse_body {
@@ -187,6 +340,7 @@ where
                         };
 
                         let mut body = body.take();
+                        let mut has_body = *has_body;
                         let body_new = if reuse_body {
                             // try to reuse saved body
                             match body {
@@ -199,13 +353,16 @@ where
                             }
                         } else {
                             body = None;
+                            has_body = false;
                             // remove body since we're downgrading to a GET
                             AnyBody::None
                         };
 
                         let mut headers = headers.take().unwrap();
 
-                        remove_sensitive_headers(&mut headers, &prev_uri, &next_uri);
+                        if policy.strip_sensitive_headers {
+                            remove_sensitive_headers(&mut headers, &prev_uri, &next_uri);
+                        }
 
                         // use a new request head.
                         let mut head = RequestHead::default();
@@ -226,10 +383,12 @@ where
                         self.set(RedirectServiceFuture::Client {
                             fut,
                             max_redirect_times,
+                            policy,
                             uri: Some(next_uri),
                             method: Some(method),
                             headers: Some(headers),
                             body,
+                            has_body,
                             addr,
                             connector,
                         });
@@ -238,7 +397,7 @@ where
                     }
                     _ => Poll::Ready(Ok(ConnectResponse::Client(res))),
                 },
-                _ => unreachable!("ConnectRequest::Tunnel is not handled by Redirect"),
+                ConnectResponse::Tunnel(..) => Poll::Ready(Err(SendRequestError::UnexpectedTunnel)),
             },
         }
     }
@@ -285,11 +444,14 @@ fn build_next_uri(res: &ClientResponse, prev_uri: &Uri) -> Result<Uri, SendReque
     Ok(uri)
 }
 
+fn is_same_origin(prev_uri: &Uri, next_uri: &Uri) -> bool {
+    next_uri.host() == prev_uri.host()
+        && next_uri.port() == prev_uri.port()
+        && next_uri.scheme() == prev_uri.scheme()
+}
+
 fn remove_sensitive_headers(headers: &mut header::HeaderMap, prev_uri: &Uri, next_uri: &Uri) {
-    if next_uri.host() != prev_uri.host()
-        || next_uri.port() != prev_uri.port()
-        || next_uri.scheme() != prev_uri.scheme()
-    {
+    if !is_same_origin(prev_uri, next_uri) {
         headers.remove(header::COOKIE);
         headers.remove(header::AUTHORIZATION);
         headers.remove(header::PROXY_AUTHORIZATION);
@@ -436,6 +598,46 @@ mod tests {
         assert_eq!(res.status().as_u16(), 200);
     }
 
+    #[actix_rt::test]
+    async fn redirect_policy_replay_body_and_filter() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .service(web::resource("/").route(web::to(|| async {
+                    HttpResponse::TemporaryRedirect()
+                        .append_header(("location", "/test"))
+                        .finish()
+                })))
+                .service(
+                    web::resource("/test").route(web::to(|| async { HttpResponse::Ok().finish() })),
+                )
+        });
+
+        let client = ClientBuilder::new()
+            .redirect_policy(Redirect::new().replay_body(false))
+            .finish();
+
+        // requests with a body are not redirected
+        let res = client.post(srv.url("/")).send_body("Hello").await.unwrap();
+        assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+
+        let res = client.get(srv.url("/")).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let client = ClientBuilder::new()
+            .redirect_policy(Redirect::new().filter(|attempt| {
+                assert_eq!(attempt.status(), StatusCode::TEMPORARY_REDIRECT);
+                assert!(!attempt.is_cross_origin());
+                attempt.method() != Method::DELETE
+            }))
+            .finish();
+
+        let res = client.delete(srv.url("/")).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+
+        let res = client.put(srv.url("/")).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
     #[actix_rt::test]
     async fn test_redirect_status_kind_301_302_303() {
         let srv = actix_test::start(|| {
@@ -588,6 +790,14 @@ mod tests {
         // send a request to same origin, http://srv1/test1 then http://srv1/test2. So it should NOT remove any header
         let res = client.get(srv1.url("/test1")).send().await.unwrap();
         assert_eq!(res.status().as_u16(), 200);
+
+        // header stripping can be disabled, in which case srv2 receives the header
+        let client = ClientBuilder::new()
+            .add_default_header((header::AUTHORIZATION, "auth_key_value"))
+            .redirect_policy(Redirect::new().strip_sensitive_headers(false))
+            .finish();
+        let res = client.get(srv1.url("/")).send().await.unwrap();
+        assert_eq!(res.status().as_u16(), 500);
     }
 
     #[actix_rt::test]
@@ -0,0 +1,415 @@
+use std::{cell::OnceCell, fmt, rc::Rc};
+
+use actix_http::{
+    header::{HeaderMap, HeaderValue, HOST},
+    Method, RequestHead, RequestHeadType, Uri,
+};
+use actix_service::Service;
+use bytes::Bytes;
+use futures_util::future::{self, Either, Ready};
+use sha2::{Digest as _, Sha256};
+
+use super::Transform;
+use crate::{
+    any_body::AnyBody,
+    client::SendRequestError,
+    connect::{ConnectRequest, ConnectResponse},
+};
+
+/// Middleware that signs requests right before they are sent, such as with [AWS Signature
+/// Version 4] or the HMAC schemes of internal APIs.
+///
+/// The signing function receives each request as it is about to be sent, with its final method,
+/// URI, and headers, including the `Host` header and the headers set by other middleware, and the
+/// SHA-256 hash of its body. It adds its signature by changing the headers of the request.
+/// Errors returned by the function fail the request with [`SendRequestError::Custom`].
+///
+/// Registered with [`ClientBuilder::wrap()`], the middleware runs for every request sent,
+/// including requests following redirects and requests answering authentication challenges,
+/// which are signed anew.
+///
+/// # Examples
+/// ```
+/// use awc::{http::header::HeaderValue, middleware::SignRequest, Client};
+///
+/// # fn hmac(_: &[u8]) -> String { String::new() }
+/// let client = Client::builder()
+///     .wrap(SignRequest::new(|req| {
+///         let body_hash = req.body_sha256().ok_or("streaming bodies are not signed")?;
+///
+///         let mut message = format!("{}\n{}\n", req.method(), req.uri()).into_bytes();
+///         message.extend_from_slice(&body_hash);
+///
+///         let signature = HeaderValue::try_from(hmac(&message))?;
+///         req.headers_mut().insert("x-signature".parse()?, signature);
+///
+///         Ok::<_, Box<dyn std::error::Error>>(())
+///     }))
+///     .finish();
+/// ```
+///
+/// [AWS Signature Version 4]: https://docs.aws.amazon.com/IAM/latest/UserGuide/reference_sigv.html
+/// [`ClientBuilder::wrap()`]: crate::ClientBuilder::wrap
+pub struct SignRequest<F> {
+    sign: Rc<F>,
+}
+
+impl<F, E> SignRequest<F>
+where
+    F: Fn(&mut RequestToSign<'_>) -> Result<(), E>,
+    E: Into<Box<dyn std::error::Error>>,
+{
+    /// Constructs a middleware signing requests with the given function.
+    pub fn new(sign: F) -> Self {
+        Self {
+            sign: Rc::new(sign),
+        }
+    }
+}
+
+impl<F> Clone for SignRequest<F> {
+    fn clone(&self) -> Self {
+        Self {
+            sign: Rc::clone(&self.sign),
+        }
+    }
+}
+
+impl<F> fmt::Debug for SignRequest<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignRequest").finish_non_exhaustive()
+    }
+}
+
+impl<S, F, E> Transform<S, ConnectRequest> for SignRequest<F>
+where
+    S: Service<ConnectRequest, Response = ConnectResponse, Error = SendRequestError>,
+    F: Fn(&mut RequestToSign<'_>) -> Result<(), E>,
+    E: Into<Box<dyn std::error::Error>>,
+{
+    type Transform = SignRequestService<S, F>;
+
+    fn new_transform(self, service: S) -> Self::Transform {
+        SignRequestService {
+            service,
+            sign: self.sign,
+        }
+    }
+}
+
+/// Service of a [`SignRequest`] middleware.
+pub struct SignRequestService<S, F> {
+    service: S,
+    sign: Rc<F>,
+}
+
+impl<S, F, E> Service<ConnectRequest> for SignRequestService<S, F>
+where
+    S: Service<ConnectRequest, Response = ConnectResponse, Error = SendRequestError>,
+    F: Fn(&mut RequestToSign<'_>) -> Result<(), E>,
+    E: Into<Box<dyn std::error::Error>>,
+{
+    type Response = ConnectResponse;
+    type Error = SendRequestError;
+    type Future = Either<S::Future, Ready<Result<ConnectResponse, SendRequestError>>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ConnectRequest) -> Self::Future {
+        let ConnectRequest::Client(head, body, addr) = req else {
+            return Either::Left(self.service.call(req));
+        };
+
+        let mut head = final_head(head);
+
+        let body_bytes = match body {
+            AnyBody::None => Some(Bytes::new()),
+            AnyBody::Bytes { ref body } => Some(body.clone()),
+            AnyBody::Body { .. } => None,
+        };
+
+        let mut req = RequestToSign {
+            head: &mut head,
+            body: body_bytes.as_ref(),
+            body_sha256: OnceCell::new(),
+        };
+
+        if let Err(err) = (self.sign)(&mut req) {
+            return Either::Right(future::err(SendRequestError::Custom(
+                err.into(),
+                Box::new("request signing error"),
+            )));
+        }
+
+        Either::Left(self.service.call(ConnectRequest::Client(
+            RequestHeadType::Owned(head),
+            body,
+            addr,
+        )))
+    }
+}
+
+/// Request about to be sent, given to the signing function of a [`SignRequest`] middleware.
+pub struct RequestToSign<'a> {
+    head: &'a mut RequestHead,
+    body: Option<&'a Bytes>,
+    body_sha256: OnceCell<[u8; 32]>,
+}
+
+impl RequestToSign<'_> {
+    /// Returns the method of the request.
+    pub fn method(&self) -> &Method {
+        &self.head.method
+    }
+
+    /// Returns the URI of the request.
+    pub fn uri(&self) -> &Uri {
+        &self.head.uri
+    }
+
+    /// Returns the headers of the request.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.head.headers
+    }
+
+    /// Returns a mutable reference to the headers of the request.
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.head.headers
+    }
+
+    /// Returns the SHA-256 hash of the body of the request.
+    ///
+    /// Requests without a body have the hash of an empty body. Returns `None` for streaming
+    /// bodies, which are not read before they are sent; signing schemes commonly mark their
+    /// payload as unsigned instead, such as with `UNSIGNED-PAYLOAD` in AWS Signature Version 4.
+    pub fn body_sha256(&self) -> Option<[u8; 32]> {
+        let body = self.body?;
+        Some(*self.body_sha256.get_or_init(|| Sha256::digest(body).into()))
+    }
+}
+
+impl fmt::Debug for RequestToSign<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestToSign")
+            .field("method", self.method())
+            .field("uri", self.uri())
+            .field("headers", self.headers())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns the head of a request with the headers that it is sent with.
+///
+/// The extra headers of shared heads are merged into their headers, and the `Host` header is set
+/// if it is missing, as it is when the request is sent over HTTP/1.
+fn final_head(head: RequestHeadType) -> RequestHead {
+    let mut head = match head {
+        RequestHeadType::Owned(head) => head,
+
+        RequestHeadType::Rc(head, extra_headers) => {
+            let mut head = RequestHead::clone(&head);
+
+            if let Some(extra_headers) = extra_headers {
+                // extra headers replace the headers of the shared head
+                for name in extra_headers.keys() {
+                    head.headers.remove(name);
+                }
+
+                for (name, value) in extra_headers.iter() {
+                    head.headers.append(name.clone(), value.clone());
+                }
+            }
+
+            head
+        }
+    };
+
+    if !head.headers.contains_key(HOST) {
+        if let Some(host) = head.uri.host() {
+            let host = match head.uri.port_u16() {
+                None | Some(80) | Some(443) => host.to_owned(),
+                Some(port) => format!("{host}:{port}"),
+            };
+
+            if let Ok(value) = HeaderValue::try_from(host) {
+                head.headers.insert(HOST, value);
+            }
+        }
+    }
+
+    head
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use actix_web::{web, App, HttpRequest, HttpResponse};
+    use futures_util::stream;
+
+    use super::*;
+    use crate::{BoxError, ClientBuilder};
+
+    /// Signs requests with the SHA-256 hash of their method, path, `Host` header, and body hash.
+    fn sign(req: &mut RequestToSign<'_>) -> Result<(), BoxError> {
+        let body_hash = req.body_sha256().ok_or("streaming body")?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(req.method().as_str());
+        hasher.update(req.uri().path());
+        hasher.update(req.headers().get(HOST).ok_or("missing host")?.as_bytes());
+        hasher.update(body_hash);
+
+        let signature = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<Vec<_>>()
+            .concat();
+
+        req.headers_mut()
+            .insert("x-signature".parse()?, HeaderValue::try_from(signature)?);
+        Ok(())
+    }
+
+    fn verify(req: &HttpRequest, body: &[u8]) -> bool {
+        let mut hasher = Sha256::new();
+        hasher.update(req.method().as_str());
+        hasher.update(req.path());
+        hasher.update(req.headers().get(HOST).unwrap().as_bytes());
+        hasher.update(Sha256::digest(body));
+
+        let signature = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<Vec<_>>()
+            .concat();
+
+        req.headers()
+            .get("x-signature")
+            .is_some_and(|value| value.as_bytes() == signature.as_bytes())
+    }
+
+    #[actix_rt::test]
+    async fn sign_requests() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route(
+                    "/old",
+                    web::to(|req: HttpRequest, body: Bytes| async move {
+                        assert!(verify(&req, &body));
+                        HttpResponse::TemporaryRedirect()
+                            .insert_header(("location", "/records"))
+                            .finish()
+                    }),
+                )
+                .route(
+                    "/records",
+                    web::to(|req: HttpRequest, body: Bytes| async move {
+                        if verify(&req, &body) {
+                            let request_ids = req
+                                .headers()
+                                .get_all("x-request-id")
+                                .map(|value| value.to_str().unwrap())
+                                .collect::<Vec<_>>();
+
+                            HttpResponse::Ok().body(request_ids.join(","))
+                        } else {
+                            HttpResponse::Forbidden().finish()
+                        }
+                    }),
+                )
+        });
+
+        let client = ClientBuilder::new().wrap(SignRequest::new(sign)).finish();
+
+        let res = client.get(srv.url("/records")).send().await.unwrap();
+        assert!(res.status().is_success());
+
+        // extra headers of frozen requests replace their headers before signing
+        let req = client
+            .put(srv.url("/records"))
+            .insert_header(("x-request-id", "1"))
+            .freeze()
+            .unwrap();
+
+        let mut res = req
+            .extra_header(("x-request-id", "2"))
+            .send_body("record")
+            .await
+            .unwrap();
+        assert!(res.status().is_success());
+        assert_eq!(res.body().await.unwrap(), "2");
+
+        // requests following redirects are signed anew
+        let res = client
+            .post(srv.url("/old"))
+            .send_body("record")
+            .await
+            .unwrap();
+        assert!(res.status().is_success());
+
+        // requests sent without the middleware are rejected
+        let res = ClientBuilder::new()
+            .finish()
+            .get(srv.url("/records"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status().as_u16(), 403);
+    }
+
+    #[actix_rt::test]
+    async fn body_hash() {
+        let srv = actix_test::start(|| App::new().default_service(web::to(HttpResponse::Ok)));
+
+        let hashes = Rc::new(RefCell::new(Vec::new()));
+
+        let client = ClientBuilder::new()
+            .wrap(SignRequest::new({
+                let hashes = Rc::clone(&hashes);
+
+                move |req: &mut RequestToSign<'_>| {
+                    hashes.borrow_mut().push(req.body_sha256());
+                    Ok::<_, BoxError>(())
+                }
+            }))
+            .finish();
+
+        client.get(srv.url("/")).send().await.unwrap();
+        client.post(srv.url("/")).send_body("record").await.unwrap();
+        client
+            .post(srv.url("/"))
+            .send_stream(stream::once(async {
+                Ok::<_, BoxError>(Bytes::from_static(b"record"))
+            }))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *hashes.borrow(),
+            [
+                Some(<[u8; 32]>::from(Sha256::digest(b""))),
+                Some(<[u8; 32]>::from(Sha256::digest(b"record"))),
+                None,
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn signing_error() {
+        let srv = actix_test::start(|| App::new().default_service(web::to(HttpResponse::Ok)));
+
+        let client = ClientBuilder::new().wrap(SignRequest::new(sign)).finish();
+
+        let res = client
+            .post(srv.url("/"))
+            .send_stream(stream::once(async {
+                Ok::<_, BoxError>(Bytes::from_static(b"record"))
+            }))
+            .await;
+
+        assert!(matches!(res, Err(SendRequestError::Custom(..))));
+    }
+}
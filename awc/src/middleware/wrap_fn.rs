@@ -0,0 +1,192 @@
+use std::future::Future;
+
+use actix_service::{boxed, Service};
+
+use super::Transform;
+use crate::{
+    client::SendRequestError,
+    connect::{BoxConnectorService, ConnectRequest, ConnectResponse},
+};
+
+/// Middleware created from an async function.
+///
+/// See [`ClientBuilder::wrap_fn()`](crate::ClientBuilder::wrap_fn).
+pub struct WrapFn<F> {
+    f: F,
+}
+
+impl<F> WrapFn<F> {
+    pub(crate) fn new(f: F) -> Self {
+        Self { f }
+    }
+}
+
+impl<S, F, Fut> Transform<S, ConnectRequest> for WrapFn<F>
+where
+    S: Service<ConnectRequest, Response = ConnectResponse, Error = SendRequestError> + 'static,
+    S::Future: 'static,
+    F: Fn(ConnectRequest, &BoxConnectorService) -> Fut,
+    Fut: Future<Output = Result<ConnectResponse, SendRequestError>>,
+{
+    type Transform = WrapFnService<F>;
+
+    fn new_transform(self, service: S) -> Self::Transform {
+        WrapFnService {
+            service: boxed::rc_service(service),
+            f: self.f,
+        }
+    }
+}
+
+/// Service of a [`WrapFn`] middleware.
+pub struct WrapFnService<F> {
+    service: BoxConnectorService,
+    f: F,
+}
+
+impl<F, Fut> Service<ConnectRequest> for WrapFnService<F>
+where
+    F: Fn(ConnectRequest, &BoxConnectorService) -> Fut,
+    Fut: Future<Output = Result<ConnectResponse, SendRequestError>>,
+{
+    type Response = ConnectResponse;
+    type Error = SendRequestError;
+    type Future = Fut;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ConnectRequest) -> Self::Future {
+        (self.f)(req, &self.service)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        rc::Rc,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use actix_http::RequestHeadType;
+    use actix_web::{web, App, HttpRequest, HttpResponse};
+
+    use super::*;
+    use crate::{
+        any_body::AnyBody,
+        error::WsClientError,
+        http::header::{self, HeaderValue},
+        ClientBuilder,
+    };
+
+    #[actix_rt::test]
+    async fn middleware_order_and_retry() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+
+        let srv = actix_test::start({
+            let attempts = Arc::clone(&attempts);
+
+            move || {
+                let attempts = Arc::clone(&attempts);
+
+                App::new().default_service(web::to(move |req: HttpRequest| {
+                    let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+
+                    async move {
+                        if attempt == 0 {
+                            return HttpResponse::ServiceUnavailable().finish();
+                        }
+
+                        let auth = req.headers().get("authorization").unwrap();
+                        HttpResponse::Ok().body(auth.to_str().unwrap().to_owned())
+                    }
+                }))
+            }
+        });
+
+        let calls = Rc::new(Cell::new(0));
+
+        let client = ClientBuilder::new()
+            // inner middleware injects the credentials
+            .wrap_fn(|mut req, srv| {
+                if let ConnectRequest::Client(RequestHeadType::Owned(head), ..) = &mut req {
+                    head.headers
+                        .insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer t"));
+                }
+
+                srv.call(req)
+            })
+            // outer middleware retries requests with in-memory bodies once
+            .wrap_fn({
+                let calls = Rc::clone(&calls);
+
+                move |req, srv| {
+                    calls.set(calls.get() + 1);
+
+                    let retry = match &req {
+                        ConnectRequest::Client(
+                            RequestHeadType::Owned(head),
+                            AnyBody::Bytes { body },
+                            addr,
+                        ) => Some(ConnectRequest::Client(
+                            RequestHeadType::Owned(head.clone()),
+                            AnyBody::Bytes { body: body.clone() },
+                            *addr,
+                        )),
+                        _ => None,
+                    };
+
+                    let srv = Rc::clone(srv);
+
+                    async move {
+                        let res = srv.call(req).await?;
+
+                        match (res, retry) {
+                            (ConnectResponse::Client(res), Some(retry))
+                                if res.status().is_server_error() =>
+                            {
+                                srv.call(retry).await
+                            }
+                            (res, _) => Ok(res),
+                        }
+                    }
+                }
+            })
+            .finish();
+
+        let mut res = client.get(srv.url("/")).send().await.unwrap();
+        assert!(res.status().is_success());
+        assert_eq!(res.body().await.unwrap(), "Bearer t");
+
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn misrouted_tunnel() {
+        let srv = actix_test::start(|| App::new().default_service(web::to(HttpResponse::Ok)));
+
+        // sends tunnel requests as standard HTTP requests
+        let client = ClientBuilder::new()
+            .wrap_fn(|req, srv| match req {
+                ConnectRequest::Tunnel(head, addr) => srv.call(ConnectRequest::Client(
+                    RequestHeadType::Owned(head),
+                    AnyBody::None,
+                    addr,
+                )),
+                req => srv.call(req),
+            })
+            .finish();
+
+        let res = client.ws(srv.url("/")).connect().await;
+        assert!(matches!(
+            res,
+            Err(WsClientError::SendRequest(
+                SendRequestError::UnexpectedClientResponse
+            ))
+        ));
+    }
+}
@@ -1,11 +1,13 @@
-use std::{fmt, net, rc::Rc, time::Duration};
+use std::{fmt, net, ops::RangeBounds, path::Path, rc::Rc, time::Duration};
 
 use actix_http::{
     body::MessageBody,
     error::HttpError,
     header::{self, HeaderMap, HeaderValue, TryIntoHeaderPair},
-    ConnectionType, Method, RequestHead, Uri, Version,
+    uri::Authority,
+    ClientDisconnect, ConnectionType, Method, RequestHead, Uri, Version,
 };
+use actix_service::boxed;
 use base64::prelude::*;
 use bytes::Bytes;
 use futures_core::Stream;
@@ -14,11 +16,12 @@ use serde::Serialize;
 #[cfg(feature = "cookies")]
 use crate::cookie::{Cookie, CookieJar};
 use crate::{
-    client::ClientConfig,
+    client::{ClientConfig, ClientIdentity},
     error::{FreezeRequestError, InvalidUrl},
     frozen::FrozenClientRequest,
+    middleware::{DigestAuth, Transform as _},
     sender::{PrepForSendingError, RequestSender, SendClientRequest},
-    BoxError,
+    BoxError, FileBody,
 };
 
 /// An HTTP Client request builder
@@ -45,6 +48,7 @@ pub struct ClientRequest {
     pub(crate) head: RequestHead,
     err: Option<HttpError>,
     addr: Option<net::SocketAddr>,
+    version_set: bool,
     response_decompress: bool,
     timeout: Option<Duration>,
     config: ClientConfig,
@@ -65,6 +69,7 @@ impl ClientRequest {
             head: RequestHead::default(),
             err: None,
             addr: None,
+            version_set: false,
             #[cfg(feature = "cookies")]
             cookies: None,
             timeout: None,
@@ -116,11 +121,16 @@ impl ClientRequest {
 
     /// Set HTTP version of this request.
     ///
-    /// By default requests's HTTP version depends on network stream
-    #[doc(hidden)]
+    /// For `http` URLs, this selects the protocol of the connection: [`Version::HTTP_2`] sends the
+    /// request over HTTP/2 with prior knowledge and other versions send it over HTTP/1.1, even if
+    /// the client was built with [`http2_prior_knowledge()`]. For `https` URLs, the protocol is
+    /// negotiated with ALPN.
+    ///
+    /// [`http2_prior_knowledge()`]: crate::ClientBuilder::http2_prior_knowledge
     #[inline]
     pub fn version(mut self, version: Version) -> Self {
         self.head.version = version;
+        self.version_set = true;
         self
     }
 
@@ -246,6 +256,17 @@ impl ClientRequest {
         self.insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
     }
 
+    /// Set HTTP digest authentication credentials.
+    ///
+    /// The request is sent again with an `Authorization` header when the server challenges it,
+    /// unless its body is a stream, which can not be sent again. Challenges of hosts that the
+    /// request is redirected to are not answered. See [`DigestAuth`] for details.
+    pub fn digest_auth(mut self, username: impl fmt::Display, password: impl fmt::Display) -> Self {
+        let auth = DigestAuth::new(username, password);
+        self.config.connector = boxed::rc_service(auth.new_transform(self.config.connector));
+        self
+    }
+
     /// Set a cookie
     ///
     /// ```no_run
@@ -288,6 +309,93 @@ impl ClientRequest {
         self
     }
 
+    /// Presents a client identity on the TLS connection of this request, and of the requests
+    /// following its redirects.
+    ///
+    /// Overrides the identities of the client and its connector. See [`ClientIdentity`] for
+    /// details.
+    pub fn client_identity(mut self, identity: ClientIdentity) -> Self {
+        self.config.identity = Some(identity);
+        self
+    }
+
+    /// Sends `name` as the TLS server name (SNI) of this request, and verifies the server's
+    /// certificate against it, instead of the host of the URL.
+    ///
+    /// The `Host` header is still taken from the URL. Combined with [`address()`](Self::address),
+    /// this connects to a specific server while presenting other names to it, such as when
+    /// testing an origin server behind a CDN. The name also applies to the requests following
+    /// redirects to the same host, but not to redirects to other hosts. Connections are pooled
+    /// separately per server name.
+    ///
+    /// An invalid name is reported as an error when the request is sent.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # async fn run() {
+    /// use std::net::SocketAddr;
+    ///
+    /// let client = awc::Client::default();
+    ///
+    /// // the origin server answers for `portal.example.com` with a certificate for its own name
+    /// let res = client
+    ///     .get("https://portal.example.com/health")
+    ///     .address(SocketAddr::from(([203, 0, 113, 7], 443)))
+    ///     .sni("origin-1.internal.example.com")
+    ///     .send()
+    ///     .await;
+    /// # }
+    /// ```
+    pub fn sni(mut self, name: &str) -> Self {
+        match Authority::try_from(name) {
+            Ok(name) => self.config.server_name = Some(name),
+            Err(err) => self.err = Some(err.into()),
+        }
+        self
+    }
+
+    /// Cancels this request, and the requests following its redirects, once `disconnect` is
+    /// notified.
+    ///
+    /// Ties an outbound request made while handling an inbound request to the lifetime of its
+    /// connection, so that an abandoned inbound request stops consuming upstream resources. A
+    /// cancelled request fails with [`SendRequestError::Cancelled`] and its connection is closed.
+    /// Cancellation covers connecting, sending the request, and waiting for the response head.
+    /// Reading the response body is not covered; it stops when the response is dropped.
+    ///
+    /// The disconnect handle of an inbound request is available with the `ClientDisconnect`
+    /// extractor of Actix Web. See also [`ChildClientBuilder::cancel_on()`] for cancelling all
+    /// requests of a client.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::{http::ClientDisconnect, web, HttpResponse, Responder};
+    ///
+    /// async fn search(
+    ///     client: web::Data<awc::Client>,
+    ///     disconnect: ClientDisconnect,
+    ///     query: String,
+    /// ) -> impl Responder {
+    ///     let res = client
+    ///         .post("http://index.internal/search")
+    ///         .cancel_on(disconnect)
+    ///         .send_body(query)
+    ///         .await;
+    ///
+    ///     match res {
+    ///         Ok(res) => HttpResponse::build(res.status()).streaming(res),
+    ///         Err(_) => HttpResponse::BadGateway().finish(),
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// [`SendRequestError::Cancelled`]: crate::error::SendRequestError::Cancelled
+    /// [`ChildClientBuilder::cancel_on()`]: crate::ChildClientBuilder::cancel_on
+    pub fn cancel_on(mut self, disconnect: ClientDisconnect) -> Self {
+        self.config.cancel_on = Some(disconnect);
+        self
+    }
+
     /// Sets the query part of the request
     pub fn query<T: Serialize>(mut self, query: &T) -> Result<Self, serde_urlencoded::ser::Error> {
         let mut parts = self.head.uri.clone().into_parts();
@@ -398,6 +506,49 @@ impl ClientRequest {
         )
     }
 
+    /// Set a body streamed from a region of the file at `path` and generate `ClientRequest`.
+    ///
+    /// Pass `..` as the `range` to send the whole file. The file is read in chunks on the blocking
+    /// thread pool as the connection accepts them. The `Content-Length` header is set to the length
+    /// of the range and, unless already set, the `Content-Type` header is guessed from the file
+    /// extension. To limit the upload rate, send a [`FileBody`] with [`send_body()`](Self::send_body)
+    /// instead.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # #[actix_rt::main]
+    /// # async fn main() -> Result<(), awc::error::SendRequestError> {
+    /// let res = awc::Client::new()
+    ///     .put("http://www.rust-lang.org/upload")
+    ///     .send_file("archive.tar.gz", 1024..)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn send_file(
+        self,
+        path: impl AsRef<Path>,
+        range: impl RangeBounds<u64>,
+    ) -> SendClientRequest {
+        let body = match FileBody::open(path, range) {
+            Ok(body) => body,
+            Err(err) => return PrepForSendingError::File(err).into(),
+        };
+
+        let slf = match self.prep_for_sending() {
+            Ok(slf) => slf,
+            Err(err) => return err.into(),
+        };
+
+        RequestSender::Owned(slf.head).send_file(
+            slf.addr,
+            slf.response_decompress,
+            slf.timeout,
+            &slf.config,
+            body,
+        )
+    }
+
     /// Set an empty body and generate `ClientRequest`.
     pub fn send(self) -> SendClientRequest {
         let slf = match self.prep_for_sending() {
@@ -453,6 +604,13 @@ impl ClientRequest {
 
         let mut slf = self;
 
+        if slf.config.http2_prior_knowledge
+            && !slf.version_set
+            && slf.head.uri.scheme_str() == Some("http")
+        {
+            slf.head.version = Version::HTTP_2;
+        }
+
         // Set Accept-Encoding HTTP header depending on enabled feature.
         // If decompress is not ask, then we are not able to find which encoding is
         // supported, so we cannot guess Accept-Encoding HTTP header.
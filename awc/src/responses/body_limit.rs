@@ -0,0 +1,82 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_http::error::PayloadError;
+use bytes::Bytes;
+use futures_core::{ready, Stream};
+use pin_project_lite::pin_project;
+
+pin_project! {
+    /// Payload stream that fails with [`PayloadError::Overflow`] once more than `limit` bytes have
+    /// been read from it.
+    pub(crate) struct LimitedPayload<S> {
+        #[pin]
+        stream: S,
+        remaining: usize,
+        overflowed: bool,
+    }
+}
+
+impl<S> LimitedPayload<S> {
+    pub(crate) fn new(stream: S, limit: usize) -> Self {
+        Self {
+            stream,
+            remaining: limit,
+            overflowed: false,
+        }
+    }
+}
+
+impl<S> Stream for LimitedPayload<S>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>>,
+{
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+
+        if *this.overflowed {
+            return Poll::Ready(None);
+        }
+
+        match ready!(this.stream.poll_next(cx)) {
+            Some(Ok(chunk)) if chunk.len() > *this.remaining => {
+                *this.overflowed = true;
+                Poll::Ready(Some(Err(PayloadError::Overflow)))
+            }
+            Some(Ok(chunk)) => {
+                *this.remaining -= chunk.len();
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            item => Poll::Ready(item),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{stream, StreamExt as _};
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn overflow() {
+        let chunks = stream::iter(["abc", "def", "gh"].map(|chunk| Ok(Bytes::from(chunk))));
+        let mut payload = LimitedPayload::new(chunks, 7);
+
+        assert_eq!(payload.next().await.unwrap().unwrap(), "abc");
+        assert_eq!(payload.next().await.unwrap().unwrap(), "def");
+        assert!(matches!(
+            payload.next().await.unwrap(),
+            Err(PayloadError::Overflow)
+        ));
+        assert!(payload.next().await.is_none());
+
+        let chunks = stream::iter(["abc", "def"].map(|chunk| Ok(Bytes::from(chunk))));
+        let payload = LimitedPayload::new(chunks, 6);
+        assert_eq!(payload.collect::<Vec<_>>().await.len(), 2);
+    }
+}
@@ -3,14 +3,20 @@ use std::{future::Future, io, pin::Pin, task::Context};
 use actix_http::error::PayloadError;
 use actix_rt::time::Sleep;
 
+mod body_limit;
 mod json_body;
 mod read_body;
 mod response;
 mod response_body;
+mod save_body;
 
 #[allow(deprecated)]
 pub use self::response_body::{MessageBody, ResponseBody};
-pub use self::{json_body::JsonBody, response::ClientResponse};
+pub(crate) use self::{
+    body_limit::LimitedPayload,
+    save_body::{content_range, Destination},
+};
+pub use self::{json_body::JsonBody, response::ClientResponse, save_body::SaveBody};
 
 /// Default body size limit: 2 MiB
 const DEFAULT_BODY_LIMIT: usize = 2 * 1024 * 1024;
@@ -1,11 +1,13 @@
 use std::{
     cell::{Ref, RefCell, RefMut},
     fmt, mem,
+    path::Path,
     pin::Pin,
     task::{Context, Poll},
     time::{Duration, Instant},
 };
 
+use actix_codec::AsyncWrite;
 use actix_http::{
     error::PayloadError, header::HeaderMap, BoxedPayloadStream, Extensions, HttpMessage, Payload,
     ResponseHead, StatusCode, Version,
@@ -16,9 +18,10 @@ use futures_core::Stream;
 use pin_project_lite::pin_project;
 use serde::de::DeserializeOwned;
 
-use super::{JsonBody, ResponseBody, ResponseTimeout};
+use super::{Destination, JsonBody, ResponseBody, ResponseTimeout, SaveBody};
 #[cfg(feature = "cookies")]
 use crate::cookie::{Cookie, ParseError as CookieParseError};
+use crate::Timings;
 
 pin_project! {
     /// Client Response
@@ -28,7 +31,8 @@ pin_project! {
         pub(crate) payload: Payload<S>,
         pub(crate) timeout: ResponseTimeout,
         pub(crate) extensions: RefCell<Extensions>,
-
+        pub(crate) timings: Option<Timings>,
+        pub(crate) body_limit: Option<usize>,
     }
 }
 
@@ -40,6 +44,8 @@ impl<S> ClientResponse<S> {
             payload,
             timeout: ResponseTimeout::default(),
             extensions: RefCell::new(Extensions::new()),
+            timings: None,
+            body_limit: None,
         }
     }
 
@@ -80,9 +86,20 @@ impl<S> ClientResponse<S> {
             head: self.head,
             timeout: self.timeout,
             extensions: self.extensions,
+            timings: self.timings,
+            body_limit: self.body_limit,
         }
     }
 
+    /// Returns the time spent in each phase of the request, up to this response's head being
+    /// received.
+    ///
+    /// Returns `None` for responses that were not received through a connector, such as those
+    /// built with [`TestResponse`](crate::test::TestResponse).
+    pub fn timings(&self) -> Option<Timings> {
+        self.timings
+    }
+
     /// Set a timeout duration for [`ClientResponse`](self::ClientResponse).
     ///
     /// This duration covers the duration of processing the response body stream
@@ -107,6 +124,8 @@ impl<S> ClientResponse<S> {
             head: self.head,
             timeout,
             extensions: self.extensions,
+            timings: self.timings,
+            body_limit: self.body_limit,
         }
     }
 
@@ -206,6 +225,59 @@ where
     }
 }
 
+impl<S> ClientResponse<S>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + 'static,
+{
+    /// Returns a [`Future`] that streams the body into `writer` and resolves to the number of
+    /// bytes written.
+    ///
+    /// Unlike [`body()`](Self::body), the body is never buffered in full, so there is no size
+    /// limit. The returned [`SaveBody`] can also verify the body's digest and resume the download
+    /// with range requests if the connection fails.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use awc::Client;
+    /// # #[actix_rt::main]
+    /// # async fn async_ctx() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::default();
+    /// let mut res = client.get("https://httpbin.org/robots.txt").send().await?;
+    /// let mut buf = Vec::new();
+    /// let len = res.save_to(&mut buf).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Future`]: std::future::Future
+    pub fn save_to<'a>(&mut self, writer: impl AsyncWrite + Unpin + 'a) -> SaveBody<'a> {
+        SaveBody::new(self, Destination::writer(writer))
+    }
+
+    /// Returns a [`Future`] that streams the body into the file at `path` and resolves to the
+    /// number of bytes written.
+    ///
+    /// The file is created, or truncated if it exists, when the future is first polled. See
+    /// [`save_to()`](Self::save_to) for details.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use awc::Client;
+    /// # #[actix_rt::main]
+    /// # async fn async_ctx() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::default();
+    /// let mut res = client.get("https://httpbin.org/robots.txt").send().await?;
+    /// res.save_to_file("robots.txt").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Future`]: std::future::Future
+    pub fn save_to_file(&mut self, path: impl AsRef<Path>) -> SaveBody<'static> {
+        SaveBody::new(self, Destination::file(path.as_ref().to_owned()))
+    }
+}
+
 impl<S> fmt::Debug for ClientResponse<S> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "\nClientResponse {:?} {}", self.version(), self.status(),)?;
@@ -0,0 +1,551 @@
+use std::{
+    fmt,
+    future::{poll_fn, Future},
+    io, mem,
+    path::PathBuf,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_codec::AsyncWrite;
+use actix_http::{
+    error::PayloadError,
+    header::{self, HeaderMap, HeaderValue},
+    HttpMessage as _, StatusCode,
+};
+use bytes::Bytes;
+use digest::Digest;
+use futures_core::{future::LocalBoxFuture, ready, stream::LocalBoxStream, Stream};
+use tokio::io::AsyncWriteExt as _;
+
+use super::ResponseTimeout;
+use crate::{error::SaveError, ClientResponse, FrozenClientRequest};
+
+/// Default number of times a download is resumed after a payload error.
+const DEFAULT_MAX_RESUMES: usize = 3;
+
+type Payload = LocalBoxStream<'static, Result<Bytes, PayloadError>>;
+
+/// A `Future` that streams a response body into a writer, resolving to the number of bytes
+/// written.
+///
+/// Each chunk is written out in full before the next one is read from the connection, so a slow
+/// writer slows down the download instead of buffering the body in memory.
+///
+/// # Errors
+/// `Future` implementation returns error if:
+/// - reading the payload fails, or it ends before the length given by `Content-Length`, and the
+///   download could not be [resumed](Self::resume);
+/// - writing to or flushing the destination fails;
+/// - the body does not match the digest given to [`verify`](Self::verify).
+#[must_use = "futures do nothing unless polled"]
+pub struct SaveBody<'a> {
+    state: SaveState<'a>,
+}
+
+enum SaveState<'a> {
+    Init(Box<SaveOptions<'a>>),
+    Saving(LocalBoxFuture<'a, Result<u64, SaveError>>),
+    Done,
+}
+
+struct SaveOptions<'a> {
+    dest: Destination<'a>,
+    /// Body to save, or `None` if it is requested with a range request once the download starts.
+    payload: Option<Payload>,
+    /// Position in the complete body of the first byte of `payload`.
+    offset: u64,
+    timeout: ResponseTimeout,
+    headers: HeaderMap,
+    verifier: Option<Box<dyn Verifier>>,
+    resume: Option<FrozenClientRequest>,
+    max_resumes: usize,
+}
+
+pub(crate) enum Destination<'a> {
+    Writer(Box<dyn AsyncWrite + Unpin + 'a>),
+    File(PathBuf),
+    Append(PathBuf),
+}
+
+impl<'a> SaveBody<'a> {
+    pub(crate) fn new<S>(res: &mut ClientResponse<S>, dest: Destination<'a>) -> Self
+    where
+        S: Stream<Item = Result<Bytes, PayloadError>> + 'static,
+    {
+        // a partial response continues from the start of its range
+        let offset = match res.status() {
+            StatusCode::PARTIAL_CONTENT => res
+                .headers()
+                .get(header::CONTENT_RANGE)
+                .and_then(content_range)
+                .map_or(0, |(start, _)| start),
+            _ => 0,
+        };
+
+        Self {
+            state: SaveState::Init(Box::new(SaveOptions {
+                dest,
+                payload: Some(Box::pin(res.take_payload())),
+                offset,
+                timeout: mem::take(&mut res.timeout),
+                headers: res.headers().clone(),
+                verifier: None,
+                resume: None,
+                max_resumes: DEFAULT_MAX_RESUMES,
+            })),
+        }
+    }
+
+    /// Constructs a download that starts by requesting the body from `offset` with `req`.
+    ///
+    /// `headers` hold the validators of the original response.
+    pub(crate) fn ranged(
+        req: &FrozenClientRequest,
+        headers: HeaderMap,
+        offset: u64,
+        dest: Destination<'a>,
+    ) -> Self {
+        Self {
+            state: SaveState::Init(Box::new(SaveOptions {
+                dest,
+                payload: None,
+                offset,
+                timeout: ResponseTimeout::default(),
+                headers,
+                verifier: None,
+                resume: Some(req.clone()),
+                max_resumes: DEFAULT_MAX_RESUMES,
+            })),
+        }
+    }
+
+    /// Checks that the digest of the saved body, computed with `D`, equals `expected`.
+    ///
+    /// The digest is computed over the bytes written, after any content decoding. A mismatch is
+    /// only detected once the whole body has been written, so the destination should be treated
+    /// as untrusted until the future resolves successfully.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use awc::Client;
+    /// # use sha2::Sha256;
+    /// # #[actix_rt::main]
+    /// # async fn async_ctx() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let expected = [0; 32];
+    /// let client = Client::default();
+    /// let mut res = client.get("https://example.com/study.dcm").send().await?;
+    /// res.save_to_file("study.dcm").verify::<Sha256>(expected).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn verify<D>(mut self, expected: impl AsRef<[u8]>) -> Self
+    where
+        D: Digest + 'static,
+    {
+        if let SaveState::Init(ref mut opts) = self.state {
+            opts.verifier = Some(Box::new(DigestVerifier {
+                digest: D::new(),
+                expected: expected.as_ref().to_vec(),
+            }));
+        }
+
+        self
+    }
+
+    /// Resumes the download with a range request built from `req` if reading the payload fails.
+    ///
+    /// `req` should be the request that produced this response. When the connection fails part
+    /// way through the body, `req` is sent again with a `Range` header asking for the rest of the
+    /// body, and an `If-Range` header with the original response's `ETag` or `Last-Modified`
+    /// value, if it had one. The download only continues if the server responds with
+    /// `206 Partial Content` starting at the first missing byte.
+    ///
+    /// Responses with a `Content-Encoding` are never resumed, since ranges would refer to the
+    /// encoded representation.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use awc::Client;
+    /// # #[actix_rt::main]
+    /// # async fn async_ctx() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::default();
+    /// let req = client.get("https://example.com/study.dcm").freeze()?;
+    /// let mut res = req.send().await?;
+    /// res.save_to_file("study.dcm").resume(&req).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resume(mut self, req: &FrozenClientRequest) -> Self {
+        if let SaveState::Init(ref mut opts) = self.state {
+            opts.resume = Some(req.clone());
+        }
+
+        self
+    }
+
+    /// Sets the maximum number of times the download is [resumed](Self::resume).
+    ///
+    /// By default, a download is resumed up to 3 times.
+    pub fn max_resumes(mut self, max: usize) -> Self {
+        if let SaveState::Init(ref mut opts) = self.state {
+            opts.max_resumes = max;
+        }
+
+        self
+    }
+}
+
+impl<'a> Future for SaveBody<'a> {
+    type Output = Result<u64, SaveError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        loop {
+            match mem::replace(&mut this.state, SaveState::Done) {
+                SaveState::Init(opts) => {
+                    this.state = SaveState::Saving(Box::pin(save(*opts)));
+                }
+
+                SaveState::Saving(mut fut) => {
+                    let res = fut.as_mut().poll(cx);
+
+                    if res.is_pending() {
+                        this.state = SaveState::Saving(fut);
+                    }
+
+                    return res;
+                }
+
+                SaveState::Done => panic!("SaveBody polled after completion"),
+            }
+        }
+    }
+}
+
+impl fmt::Debug for SaveBody<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SaveBody").finish_non_exhaustive()
+    }
+}
+
+impl<'a> Destination<'a> {
+    pub(crate) fn writer(writer: impl AsyncWrite + Unpin + 'a) -> Self {
+        Self::Writer(Box::new(writer))
+    }
+
+    pub(crate) fn file(path: PathBuf) -> Self {
+        Self::File(path)
+    }
+
+    pub(crate) fn append(path: PathBuf) -> Self {
+        Self::Append(path)
+    }
+
+    /// Opens the destination, returning the writer and the number of bytes already in it.
+    async fn open(self) -> io::Result<(Box<dyn AsyncWrite + Unpin + 'a>, u64)> {
+        match self {
+            Self::Writer(writer) => Ok((writer, 0)),
+
+            Self::File(path) => Ok((Box::new(tokio::fs::File::create(path).await?), 0)),
+
+            Self::Append(path) => {
+                let file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?;
+                let len = file.metadata().await?.len();
+                Ok((Box::new(file), len))
+            }
+        }
+    }
+}
+
+async fn save(opts: SaveOptions<'_>) -> Result<u64, SaveError> {
+    let SaveOptions {
+        dest,
+        payload,
+        offset,
+        mut timeout,
+        headers,
+        mut verifier,
+        resume,
+        max_resumes,
+    } = opts;
+
+    let (mut writer, existing) = dest.open().await?;
+    let offset = offset + existing;
+    let mut written = 0;
+    let mut resumes = 0;
+
+    // the length of a decoded body is only known up front for identity-encoded responses
+    let identity = !headers.contains_key(header::CONTENT_ENCODING);
+    let resume = resume.filter(|_| identity);
+
+    let mut len = headers
+        .get(header::CONTENT_LENGTH)
+        .filter(|_| identity)
+        .and_then(|len| len.to_str().ok())
+        .and_then(|len| len.parse::<u64>().ok());
+
+    let mut payload = match payload {
+        Some(payload) => payload,
+
+        None => {
+            let req = resume
+                .as_ref()
+                .expect("ranged downloads have a request to resume with");
+            let (payload, range_timeout, total) = resume_at(req, &headers, offset).await?;
+            timeout = range_timeout;
+            len = total.map(|total| total.saturating_sub(offset));
+            payload
+        }
+    };
+
+    loop {
+        let res = copy(
+            &mut payload,
+            &mut timeout,
+            &mut writer,
+            &mut written,
+            &mut verifier,
+        )
+        .await;
+
+        // an HTTP/1 payload that is cut short by the connection closing just ends early
+        let res = match res {
+            Ok(()) if len.is_some_and(|len| written < len) => {
+                Err(SaveError::Payload(PayloadError::Incomplete(None)))
+            }
+            res => res,
+        };
+
+        let err = match res {
+            Ok(()) => break,
+            Err(SaveError::Payload(err)) if is_transient(&err) => SaveError::Payload(err),
+            Err(err) => return Err(err),
+        };
+
+        let Some(req) = &resume else {
+            return Err(err);
+        };
+
+        let mut last_err = err;
+
+        // keep sending range requests until one succeeds or we run out of attempts
+        (payload, timeout) = loop {
+            if resumes == max_resumes {
+                return Err(last_err);
+            }
+
+            resumes += 1;
+            log::debug!(
+                "resuming download at byte {} (attempt {resumes})",
+                offset + written
+            );
+
+            match resume_at(req, &headers, offset + written).await {
+                Ok((payload, timeout, total)) => {
+                    len = len.or(total.map(|total| total.saturating_sub(offset)));
+                    break (payload, timeout);
+                }
+                Err(err @ SaveError::ResumeRejected(_)) => return Err(err),
+                Err(err) => last_err = err,
+            }
+        };
+    }
+
+    writer.flush().await?;
+
+    if let Some(verifier) = verifier {
+        if !verifier.verify() {
+            return Err(SaveError::Integrity);
+        }
+    }
+
+    Ok(written)
+}
+
+/// Writes the payload to `writer` until it ends, one chunk at a time.
+async fn copy(
+    payload: &mut Payload,
+    timeout: &mut ResponseTimeout,
+    writer: &mut (dyn AsyncWrite + Unpin + '_),
+    written: &mut u64,
+    verifier: &mut Option<Box<dyn Verifier>>,
+) -> Result<(), SaveError> {
+    loop {
+        let chunk = poll_fn(|cx| {
+            timeout.poll_timeout(cx)?;
+            Poll::Ready(ready!(payload.as_mut().poll_next(cx)).transpose())
+        })
+        .await?;
+
+        let Some(chunk) = chunk else {
+            return Ok(());
+        };
+
+        writer.write_all(&chunk).await?;
+        *written += chunk.len() as u64;
+
+        if let Some(verifier) = verifier {
+            verifier.update(&chunk);
+        }
+    }
+}
+
+/// Requests the body again, starting at `offset`.
+async fn resume_at(
+    req: &FrozenClientRequest,
+    headers: &HeaderMap,
+    offset: u64,
+) -> Result<(Payload, ResponseTimeout, Option<u64>), SaveError> {
+    let mut builder = req
+        .extra_header((header::RANGE, format!("bytes={offset}-")))
+        .extra_header((header::ACCEPT_ENCODING, "identity"));
+
+    let validator = headers
+        .get(header::ETAG)
+        .filter(|etag| !etag.as_bytes().starts_with(b"W/"))
+        .or_else(|| headers.get(header::LAST_MODIFIED));
+
+    if let Some(validator) = validator {
+        builder = builder.extra_header((header::IF_RANGE, validator.clone()));
+    }
+
+    let mut res = builder.send().await.map_err(SaveError::Resume)?;
+
+    let status = res.status();
+    let range = res
+        .headers()
+        .get(header::CONTENT_RANGE)
+        .and_then(content_range);
+
+    let total = match range {
+        Some((start, total))
+            if status == StatusCode::PARTIAL_CONTENT
+                && start == offset
+                && !res.headers().contains_key(header::CONTENT_ENCODING) =>
+        {
+            total
+        }
+        _ => return Err(SaveError::ResumeRejected(status)),
+    };
+
+    Ok((
+        Box::pin(res.take_payload()),
+        mem::take(&mut res.timeout),
+        total,
+    ))
+}
+
+/// Parses the first byte position and, if known, the complete length from a
+/// `Content-Range: bytes <start>-<end>/<len>` value.
+pub(crate) fn content_range(val: &HeaderValue) -> Option<(u64, Option<u64>)> {
+    let range = val.to_str().ok()?.strip_prefix("bytes ")?;
+    let (range, total) = range.split_once('/')?;
+    let (start, _) = range.split_once('-')?;
+    Some((start.trim().parse().ok()?, total.trim().parse().ok()))
+}
+
+/// Returns true if the payload error may go away by requesting the rest of the body again.
+fn is_transient(err: &PayloadError) -> bool {
+    matches!(
+        err,
+        PayloadError::Incomplete(_) | PayloadError::Io(_) | PayloadError::Http2Payload(_)
+    )
+}
+
+trait Verifier {
+    fn update(&mut self, chunk: &[u8]);
+
+    fn verify(self: Box<Self>) -> bool;
+}
+
+struct DigestVerifier<D> {
+    digest: D,
+    expected: Vec<u8>,
+}
+
+impl<D: Digest> Verifier for DigestVerifier<D> {
+    fn update(&mut self, chunk: &[u8]) {
+        self.digest.update(chunk);
+    }
+
+    fn verify(self: Box<Self>) -> bool {
+        self.digest.finalize().as_slice() == self.expected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use static_assertions::assert_impl_all;
+
+    use super::*;
+    use crate::test::TestResponse;
+
+    assert_impl_all!(SaveBody<'_>: Unpin);
+
+    #[actix_rt::test]
+    async fn save_to_writer() {
+        let mut res = TestResponse::default()
+            .set_payload(Bytes::from_static(b"hello world"))
+            .finish();
+
+        let mut buf = Vec::new();
+        let written = res.save_to(&mut buf).await.unwrap();
+
+        assert_eq!(written, 11);
+        assert_eq!(buf, b"hello world");
+    }
+
+    #[actix_rt::test]
+    async fn verify_digest() {
+        let digest = sha2::Sha256::digest(b"hello world");
+
+        let mut res = TestResponse::default()
+            .set_payload(Bytes::from_static(b"hello world"))
+            .finish();
+
+        let mut buf = Vec::new();
+        res.save_to(&mut buf)
+            .verify::<sha2::Sha256>(digest)
+            .await
+            .unwrap();
+
+        let mut res = TestResponse::default()
+            .set_payload(Bytes::from_static(b"hello wurld"))
+            .finish();
+
+        let mut buf = Vec::new();
+        let err = res
+            .save_to(&mut buf)
+            .verify::<sha2::Sha256>(digest)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, SaveError::Integrity));
+    }
+
+    #[test]
+    fn parse_content_range() {
+        assert_eq!(
+            content_range(&HeaderValue::from_static("bytes 100-199/200")),
+            Some((100, Some(200)))
+        );
+        assert_eq!(
+            content_range(&HeaderValue::from_static("bytes 100-199/*")),
+            Some((100, None))
+        );
+        assert_eq!(
+            content_range(&HeaderValue::from_static("bytes */200")),
+            None
+        );
+        assert_eq!(
+            content_range(&HeaderValue::from_static("items 1-2/3")),
+            None
+        );
+    }
+}
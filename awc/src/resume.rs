@@ -0,0 +1,325 @@
+//! Serializable state for continuing transfers after a restart.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use actix_codec::AsyncWrite;
+use actix_http::{
+    header::{self, HeaderMap, HeaderValue},
+    BoxedPayloadStream, Payload, StatusCode, Uri,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::UploadError,
+    responses::{content_range, Destination, SaveBody},
+    ClientResponse, FileBody, FrozenClientRequest,
+};
+
+/// State needed to continue a download in another process.
+///
+/// [`SaveBody::resume()`] resumes a download when the connection fails, but its state is lost if
+/// the process exits. A `DownloadToken` records what a later process needs to request the rest of
+/// the body: the URI and the validators of the original response. It can be serialized with any
+/// `serde` format and stored next to the partially written file.
+///
+/// The number of bytes already downloaded is not part of the token; it is taken from the
+/// destination when resuming, since that is what actually reached the disk.
+///
+/// # Examples
+/// ```no_run
+/// # use awc::{Client, DownloadToken};
+/// # #[actix_rt::main]
+/// # async fn async_ctx() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = Client::default();
+/// let req = client.get("https://example.com/study.dcm").freeze()?;
+/// let mut res = req.send().await?;
+///
+/// if let Some(token) = DownloadToken::new(req.get_uri(), &res) {
+///     std::fs::write("study.dcm.token", serde_json::to_vec(&token)?)?;
+/// }
+///
+/// res.save_to_file("study.dcm").resume(&req).await?;
+///
+/// // after a restart
+/// let token: DownloadToken = serde_json::from_slice(&std::fs::read("study.dcm.token")?)?;
+/// let req = client.get(token.uri()).freeze()?;
+/// token.resume_to_file(&req, "study.dcm").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadToken {
+    uri: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    len: Option<u64>,
+}
+
+impl DownloadToken {
+    /// Constructs a token for continuing the download of `res`, which was received from `uri`.
+    ///
+    /// Returns `None` if the download can not be safely resumed: when the response has a
+    /// `Content-Encoding`, or has neither a strong `ETag` nor a `Last-Modified` header that would
+    /// let the server detect that the resource has changed in the meantime.
+    pub fn new<S>(uri: &Uri, res: &ClientResponse<S>) -> Option<Self> {
+        let headers = res.headers();
+
+        if headers.contains_key(header::CONTENT_ENCODING) {
+            return None;
+        }
+
+        let etag = headers
+            .get(header::ETAG)
+            .filter(|etag| !etag.as_bytes().starts_with(b"W/"))
+            .and_then(|etag| etag.to_str().ok())
+            .map(str::to_owned);
+
+        let last_modified = headers
+            .get(header::LAST_MODIFIED)
+            .and_then(|date| date.to_str().ok())
+            .map(str::to_owned);
+
+        if etag.is_none() && last_modified.is_none() {
+            return None;
+        }
+
+        let len = match res.status() {
+            StatusCode::PARTIAL_CONTENT => headers
+                .get(header::CONTENT_RANGE)
+                .and_then(content_range)
+                .and_then(|(_, total)| total),
+            _ => headers
+                .get(header::CONTENT_LENGTH)
+                .and_then(|len| len.to_str().ok())
+                .and_then(|len| len.parse().ok()),
+        };
+
+        Some(Self {
+            uri: uri.to_string(),
+            etag,
+            last_modified,
+            len,
+        })
+    }
+
+    /// Returns the URI the body is downloaded from.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Returns the length of the complete body, if it was known.
+    pub fn total_len(&self) -> Option<u64> {
+        self.len
+    }
+
+    /// Returns a [`SaveBody`] that requests the body from byte `offset` with `req` and streams it
+    /// into `writer`.
+    ///
+    /// `req` should be a `GET` request for [`uri()`](Self::uri), with any credentials it needs;
+    /// they are not stored in the token. It is sent with a `Range` header and an `If-Range` header
+    /// holding the original response's validator, and is also used to [resume](SaveBody::resume)
+    /// the download if the connection fails again. The download fails with
+    /// [`SaveError::ResumeRejected`](crate::error::SaveError::ResumeRejected) if the resource has
+    /// changed since the token was created.
+    ///
+    /// The returned future resolves to the number of bytes written by it; digests given to
+    /// [`SaveBody::verify()`] only cover those bytes.
+    pub fn resume_to<'a>(
+        &self,
+        req: &FrozenClientRequest,
+        offset: u64,
+        writer: impl AsyncWrite + Unpin + 'a,
+    ) -> SaveBody<'a> {
+        SaveBody::ranged(req, self.validators(), offset, Destination::writer(writer))
+    }
+
+    /// Returns a [`SaveBody`] that appends the rest of the body to the file at `path`.
+    ///
+    /// The body is requested from the current length of the file, which is created if it does not
+    /// exist. See [`resume_to()`](Self::resume_to) for details.
+    pub fn resume_to_file(
+        &self,
+        req: &FrozenClientRequest,
+        path: impl AsRef<Path>,
+    ) -> SaveBody<'static> {
+        SaveBody::ranged(
+            req,
+            self.validators(),
+            0,
+            Destination::append(path.as_ref().to_owned()),
+        )
+    }
+
+    fn validators(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        let validators = [
+            (header::ETAG, &self.etag),
+            (header::LAST_MODIFIED, &self.last_modified),
+        ];
+
+        for (name, val) in validators {
+            if let Some(val) = val
+                .as_deref()
+                .and_then(|val| HeaderValue::from_str(val).ok())
+            {
+                headers.insert(name, val);
+            }
+        }
+
+        headers
+    }
+}
+
+/// State of a file upload that is sent in chunks, which can be continued in another process.
+///
+/// Each chunk is sent as a separate request with the file region as its body and a
+/// `Content-Range: bytes <start>-<end>/<len>` header. The token records how much of the file the
+/// server has acknowledged, and can be serialized with any `serde` format after each chunk so that
+/// a later process continues from the first unacknowledged byte instead of sending the whole file
+/// again.
+///
+/// If the process exits after a chunk was received but before the token was stored, that chunk is
+/// sent again, so the server should accept repeated ranges.
+///
+/// # Examples
+/// ```no_run
+/// # use awc::{Client, UploadToken};
+/// # #[actix_rt::main]
+/// # async fn async_ctx() -> Result<(), Box<dyn std::error::Error>> {
+/// # let stored: Option<Vec<u8>> = None;
+/// let client = Client::default();
+///
+/// let mut token = match stored {
+///     Some(token) => serde_json::from_slice(&token)?,
+///     None => UploadToken::new(
+///         &"https://example.com/studies/1".parse()?,
+///         "study.dcm",
+///         8 * 1024 * 1024,
+///     )?,
+/// };
+///
+/// let req = client.put(token.uri()).freeze()?;
+///
+/// while !token.is_complete() {
+///     token.send_chunk(&req).await?;
+///     std::fs::write("study.dcm.upload", serde_json::to_vec(&token)?)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadToken {
+    uri: String,
+    path: PathBuf,
+    len: u64,
+    offset: u64,
+    chunk_size: u64,
+}
+
+impl UploadToken {
+    /// Constructs a token for uploading the file at `path` to `uri` in chunks of `chunk_size`
+    /// bytes.
+    ///
+    /// # Errors
+    /// Returns an error if the file's metadata could not be read, or if it is empty.
+    ///
+    /// # Panics
+    /// Panics if `chunk_size` is zero.
+    pub fn new(uri: &Uri, path: impl Into<PathBuf>, chunk_size: u64) -> io::Result<Self> {
+        assert!(chunk_size > 0, "upload chunk size must be non-zero");
+
+        let path = path.into();
+        let len = std::fs::metadata(&path)?.len();
+
+        if len == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "empty files can not be uploaded in chunks",
+            ));
+        }
+
+        Ok(Self {
+            uri: uri.to_string(),
+            path,
+            len,
+            offset: 0,
+            chunk_size,
+        })
+    }
+
+    /// Returns the URI the file is uploaded to.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Returns the path of the file being uploaded.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the length of the file.
+    pub fn file_len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns the number of bytes acknowledged by the server.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns true once every chunk has been acknowledged.
+    pub fn is_complete(&self) -> bool {
+        self.offset == self.len
+    }
+
+    /// Sends the next chunk of the file with `req` and resolves to the server's response.
+    ///
+    /// `req` should be a request for [`uri()`](Self::uri), usually `PUT`, with any credentials it
+    /// needs; they are not stored in the token. The chunk is acknowledged, and the
+    /// [`offset()`](Self::offset) advanced past it, once the server responds with a success status.
+    ///
+    /// # Errors
+    /// Returns an error, leaving the offset unchanged, if the file could not be read or has changed
+    /// length since the upload started, if the request fails, or if the server responds with a
+    /// non-success status.
+    ///
+    /// # Panics
+    /// Panics if the upload [is complete](Self::is_complete).
+    pub async fn send_chunk(
+        &mut self,
+        req: &FrozenClientRequest,
+    ) -> Result<ClientResponse, UploadError> {
+        assert!(!self.is_complete(), "upload is already complete");
+
+        if std::fs::metadata(&self.path)?.len() != self.len {
+            return Err(UploadError::FileChanged);
+        }
+
+        let start = self.offset;
+        let end = self.len.min(start.saturating_add(self.chunk_size));
+        let body = FileBody::open(&self.path, start..end)?;
+
+        let res = req
+            .extra_header((
+                header::CONTENT_RANGE,
+                format!("bytes {start}-{}/{}", end - 1, self.len),
+            ))
+            .send_body(body)
+            .await?;
+
+        if !res.status().is_success() {
+            return Err(UploadError::Rejected(res.status()));
+        }
+
+        self.offset = end;
+
+        Ok(res.map_body(|_, payload| {
+            let payload: BoxedPayloadStream = Box::pin(payload);
+            Payload::from(payload)
+        }))
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     future::Future,
-    net,
+    io, net,
     pin::Pin,
     rc::Rc,
     task::{Context, Poll},
@@ -11,10 +11,10 @@ use actix_http::{
     body::{BodyStream, MessageBody},
     error::HttpError,
     header::{self, HeaderMap, HeaderName, TryIntoHeaderValue},
-    RequestHead, RequestHeadType,
+    Payload, RequestHead, RequestHeadType,
 };
 #[cfg(feature = "__compress")]
-use actix_http::{encoding::Decoder, header::ContentEncoding, Payload};
+use actix_http::{encoding::Decoder, header::ContentEncoding};
 use actix_rt::time::{sleep, Sleep};
 use bytes::Bytes;
 use derive_more::derive::From;
@@ -25,7 +25,8 @@ use crate::{
     any_body::AnyBody,
     client::ClientConfig,
     error::{FreezeRequestError, InvalidUrl, SendRequestError},
-    BoxError, ClientResponse, ConnectRequest, ConnectResponse,
+    responses::LimitedPayload,
+    BoxError, ClientResponse, ConnectRequest, ConnectResponse, FileBody,
 };
 
 #[derive(Debug, From)]
@@ -34,6 +35,7 @@ pub(crate) enum PrepForSendingError {
     Http(HttpError),
     Json(serde_json::Error),
     Form(serde_urlencoded::ser::Error),
+    File(io::Error),
 }
 
 impl From<PrepForSendingError> for FreezeRequestError {
@@ -47,6 +49,9 @@ impl From<PrepForSendingError> for FreezeRequestError {
             PrepForSendingError::Form(err) => {
                 FreezeRequestError::Custom(Box::new(err), Box::new("form serialization error"))
             }
+            PrepForSendingError::File(err) => {
+                FreezeRequestError::Custom(Box::new(err), Box::new("file body error"))
+            }
         }
     }
 }
@@ -62,6 +67,9 @@ impl From<PrepForSendingError> for SendRequestError {
             PrepForSendingError::Form(err) => {
                 SendRequestError::Custom(Box::new(err), Box::new("form serialization error"))
             }
+            PrepForSendingError::File(err) => {
+                SendRequestError::Custom(Box::new(err), Box::new("file body error"))
+            }
         }
     }
 }
@@ -104,21 +112,32 @@ impl Future for SendClientRequest {
                     }
                 }
 
-                let res = futures_core::ready!(send.as_mut().poll(cx)).map(|res| {
-                    res.into_client_response()
-                        ._timeout(delay.take())
-                        .map_body(|head, payload| {
-                            if *response_decompress {
-                                Payload::Stream {
-                                    payload: Decoder::from_headers(payload, &head.headers),
-                                }
+                let res = futures_core::ready!(send.as_mut().poll(cx))
+                    .and_then(ConnectResponse::try_into_client_response)
+                    .map(|res| {
+                        let body_limit = res.body_limit;
+
+                        res._timeout(delay.take()).map_body(|head, payload| {
+                            let payload = if *response_decompress {
+                                Decoder::from_headers(payload, &head.headers)
                             } else {
-                                Payload::Stream {
-                                    payload: Decoder::new(payload, ContentEncoding::Identity),
-                                }
-                            }
+                                Decoder::new(payload, ContentEncoding::Identity)
+                            };
+
+                            // limit the size of the decompressed body
+                            let payload: Decoder<Payload> = match body_limit {
+                                Some(limit) => Decoder::new(
+                                    Payload::Stream {
+                                        payload: Box::pin(LimitedPayload::new(payload, limit)),
+                                    },
+                                    ContentEncoding::Identity,
+                                ),
+                                None => payload,
+                            };
+
+                            Payload::Stream { payload }
                         })
-                });
+                    });
 
                 Poll::Ready(res)
             }
@@ -143,9 +162,22 @@ impl Future for SendClientRequest {
                         return Poll::Ready(Err(SendRequestError::Timeout));
                     }
                 }
-                send.as_mut()
-                    .poll(cx)
-                    .map_ok(|res| res.into_client_response()._timeout(delay.take()))
+                send.as_mut().poll(cx).map(|res| {
+                    res.and_then(ConnectResponse::try_into_client_response)
+                        .map(|res| {
+                            let body_limit = res.body_limit;
+
+                            res._timeout(delay.take())
+                                .map_body(|_, payload| -> Payload {
+                                    match body_limit {
+                                        Some(limit) => Payload::Stream {
+                                            payload: Box::pin(LimitedPayload::new(payload, limit)),
+                                        },
+                                        None => payload,
+                                    }
+                                })
+                        })
+                })
             }
             SendClientRequest::Err(ref mut err) => match err.take() {
                 Some(err) => Poll::Ready(Err(err)),
@@ -201,7 +233,19 @@ impl RequestSender {
             ),
         };
 
-        let fut = config.connector.call(req);
+        let mut fut = config.connect(req);
+
+        if let Some(limit) = config.max_response_body_size {
+            fut = Box::pin(async move {
+                let mut res = fut.await?;
+
+                if let ConnectResponse::Client(ref mut res) = res {
+                    res.body_limit = Some(limit);
+                }
+
+                Ok(res)
+            });
+        }
 
         SendClientRequest::new(fut, response_decompress, timeout.or(config.timeout))
     }
@@ -249,6 +293,23 @@ impl RequestSender {
         self.send_body(addr, response_decompress, timeout, config, body)
     }
 
+    pub(crate) fn send_file(
+        mut self,
+        addr: Option<net::SocketAddr>,
+        response_decompress: bool,
+        timeout: Option<Duration>,
+        config: &ClientConfig,
+        body: FileBody,
+    ) -> SendClientRequest {
+        if let Err(err) =
+            self.set_header_if_none(header::CONTENT_TYPE, body.content_type().as_ref())
+        {
+            return err.into();
+        }
+
+        self.send_body(addr, response_decompress, timeout, config, body)
+    }
+
     pub(crate) fn send_stream<S, E>(
         self,
         addr: Option<net::SocketAddr>,
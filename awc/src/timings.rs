@@ -0,0 +1,426 @@
+//! Per-request timing breakdown.
+
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+#[cfg(feature = "tracing")]
+use std::{
+    collections::HashSet,
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+#[cfg(feature = "tracing")]
+use actix_http::{
+    error::PayloadError,
+    header::{HeaderMap, HeaderName, AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION, SET_COOKIE},
+};
+use actix_http::{Payload, RequestHead, ResponseHead};
+#[cfg(feature = "tracing")]
+use bytes::Bytes;
+#[cfg(feature = "tracing")]
+use futures_core::Stream;
+
+/// Time spent in each phase of a request, up to the response head being received.
+///
+/// Returned by [`ClientResponse::timings()`](crate::ClientResponse::timings). The connection phases
+/// ([`dns`](Self::dns), [`connect`](Self::connect), and [`tls`](Self::tls)) are only recorded when
+/// a new connection was opened for the request, and only by the built-in [`Connector`]
+/// services; DNS resolution done by a custom [`Connector::connector()`] is counted as part of
+/// [`connect`](Self::connect).
+///
+/// With the `tracing` crate feature enabled, each request is also traced with an `awc.request`
+/// span that has a child span for each phase, a `send` span covering this request's
+/// [`ttfb`](Self::ttfb), and a `body` span lasting until the response body has been read. The
+/// request span records the request and response headers, with the values of the headers set
+/// with [`ClientBuilder::trace_redacted_headers()`] redacted.
+///
+/// [`Connector`]: crate::Connector
+/// [`Connector::connector()`]: crate::Connector::connector
+/// [`ClientBuilder::trace_redacted_headers()`]: crate::ClientBuilder::trace_redacted_headers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timings {
+    dns: Option<Duration>,
+    connect: Option<Duration>,
+    tls: Option<Duration>,
+    ttfb: Duration,
+    total: Duration,
+    reused: bool,
+}
+
+impl Timings {
+    /// Returns the time spent resolving the host name, if it was resolved for this request.
+    pub fn dns(&self) -> Option<Duration> {
+        self.dns
+    }
+
+    /// Returns the time spent opening the TCP connection, after DNS resolution, if a connection
+    /// was opened for this request.
+    ///
+    /// When connecting through a [proxy](crate::Connector::proxy), this includes opening the
+    /// tunnel.
+    pub fn connect(&self) -> Option<Duration> {
+        self.connect
+    }
+
+    /// Returns the time spent on the TLS handshake, if a TLS connection was opened for this
+    /// request.
+    pub fn tls(&self) -> Option<Duration> {
+        self.tls
+    }
+
+    /// Returns the time from starting to write the request until the response head was received.
+    ///
+    /// This includes sending the request body and the time the server took to respond.
+    pub fn ttfb(&self) -> Duration {
+        self.ttfb
+    }
+
+    /// Returns the time from the request being handed to the connector until the response head
+    /// was received.
+    ///
+    /// Reading the response body is not included.
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    /// Returns true if the request was sent on an idle connection taken from the pool.
+    pub fn connection_reused(&self) -> bool {
+        self.reused
+    }
+}
+
+/// A phase of a request that is timed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Phase {
+    Dns,
+    Connect,
+    Tls,
+}
+
+impl Phase {
+    #[cfg(feature = "tracing")]
+    fn span(self, parent: &tracing::Span) -> tracing::Span {
+        match self {
+            Phase::Dns => tracing::debug_span!(parent: parent, "dns"),
+            Phase::Connect => tracing::debug_span!(parent: parent, "connect"),
+            Phase::Tls => tracing::debug_span!(parent: parent, "tls"),
+        }
+    }
+}
+
+/// Settings for tracing requests.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "tracing"), derive(Default))]
+pub(crate) struct TraceConfig {
+    #[cfg(feature = "tracing")]
+    redacted_headers: Rc<HashSet<HeaderName>>,
+}
+
+#[cfg(feature = "tracing")]
+impl TraceConfig {
+    pub(crate) fn redacted_headers(&mut self, names: impl IntoIterator<Item = HeaderName>) {
+        self.redacted_headers = Rc::new(names.into_iter().collect());
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Default for TraceConfig {
+    fn default() -> Self {
+        Self {
+            redacted_headers: Rc::new(HashSet::from([
+                AUTHORIZATION,
+                PROXY_AUTHORIZATION,
+                COOKIE,
+                SET_COOKIE,
+            ])),
+        }
+    }
+}
+
+thread_local! {
+    /// Recorder of the request whose connection is currently being polled.
+    static CURRENT: RefCell<Option<Rc<Recorder>>> = const { RefCell::new(None) };
+}
+
+/// Collects the timings of one request.
+#[derive(Debug)]
+pub(crate) struct Recorder {
+    started: Instant,
+    timings: Cell<Timings>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    config: TraceConfig,
+}
+
+impl Recorder {
+    pub(crate) fn new(head: &RequestHead, config: &TraceConfig) -> Rc<Self> {
+        #[cfg(not(feature = "tracing"))]
+        let _ = (head, config);
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "awc.request",
+            method = %head.method,
+            uri = %head.uri,
+            headers = tracing::field::Empty,
+            status = tracing::field::Empty,
+            response_headers = tracing::field::Empty,
+        );
+
+        #[cfg(feature = "tracing")]
+        if !span.is_disabled() {
+            let headers = Redacted(&head.headers, &config.redacted_headers);
+            span.record("headers", tracing::field::debug(headers));
+        }
+
+        Rc::new(Self {
+            started: Instant::now(),
+            timings: Cell::new(Timings::default()),
+            #[cfg(feature = "tracing")]
+            span,
+            #[cfg(feature = "tracing")]
+            config: config.clone(),
+        })
+    }
+
+    /// Runs `f` with this recorder as the one that connection phases are recorded to.
+    pub(crate) fn scope<R>(self: &Rc<Self>, f: impl FnOnce() -> R) -> R {
+        struct Restore(Option<Rc<Recorder>>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT.with(|current| *current.borrow_mut() = self.0.take());
+            }
+        }
+
+        let _restore = Restore(CURRENT.with(|current| current.replace(Some(Rc::clone(self)))));
+        f()
+    }
+
+    /// Begins the wait for the response, once a connection has been acquired.
+    pub(crate) fn start_request(self: &Rc<Self>) -> RequestTimer {
+        RequestTimer {
+            recorder: Rc::clone(self),
+            started: Instant::now(),
+            #[cfg(feature = "tracing")]
+            _span: tracing::debug_span!(parent: &self.span, "send"),
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Timings)) {
+        let mut timings = self.timings.get();
+        f(&mut timings);
+        self.timings.set(timings);
+    }
+}
+
+/// Marks the current request as sent on a pooled connection.
+pub(crate) fn record_reused() {
+    if let Some(recorder) = CURRENT.with(|current| current.borrow().clone()) {
+        recorder.update(|timings| timings.reused = true);
+    }
+}
+
+/// Timer for a connection phase of the current request.
+#[derive(Debug)]
+pub(crate) struct PhaseTimer {
+    inner: Option<(Rc<Recorder>, Instant)>,
+    phase: Phase,
+    #[cfg(feature = "tracing")]
+    _span: Option<tracing::Span>,
+}
+
+impl PhaseTimer {
+    /// Starts timing `phase` for the request whose connection is being polled, if any.
+    pub(crate) fn start(phase: Phase) -> Self {
+        let recorder = CURRENT.with(|current| current.borrow().clone());
+
+        Self {
+            #[cfg(feature = "tracing")]
+            _span: recorder.as_ref().map(|recorder| phase.span(&recorder.span)),
+            inner: recorder.map(|recorder| (recorder, Instant::now())),
+            phase,
+        }
+    }
+
+    /// Records the time elapsed since the phase started.
+    pub(crate) fn finish(self) {
+        let Some((recorder, started)) = self.inner else {
+            return;
+        };
+
+        let elapsed = started.elapsed();
+
+        recorder.update(|timings| match self.phase {
+            Phase::Dns => timings.dns = Some(elapsed),
+            // DNS resolution happens inside the connect phase
+            Phase::Connect => {
+                timings.connect = Some(elapsed.saturating_sub(timings.dns.unwrap_or_default()))
+            }
+            Phase::Tls => timings.tls = Some(elapsed),
+        });
+    }
+}
+
+/// Timer for sending a request and waiting for its response head.
+#[derive(Debug)]
+pub(crate) struct RequestTimer {
+    recorder: Rc<Recorder>,
+    started: Instant,
+    #[cfg(feature = "tracing")]
+    _span: tracing::Span,
+}
+
+impl RequestTimer {
+    /// Records the response head in the request's span and traces the reading of its body.
+    pub(crate) fn trace_response(&self, head: &ResponseHead, payload: Payload) -> Payload {
+        #[cfg(not(feature = "tracing"))]
+        let _ = head;
+
+        #[cfg(feature = "tracing")]
+        let payload = {
+            let recorder = &self.recorder;
+
+            if recorder.span.is_disabled() {
+                return payload;
+            }
+
+            recorder.span.record("status", head.status.as_u16());
+            recorder.span.record(
+                "response_headers",
+                tracing::field::debug(Redacted(&head.headers, &recorder.config.redacted_headers)),
+            );
+
+            let payload: Payload = Payload::Stream {
+                payload: Box::pin(TracedBody {
+                    payload,
+                    span: Some(tracing::debug_span!(
+                        parent: &recorder.span,
+                        "body",
+                        bytes = tracing::field::Empty,
+                    )),
+                    bytes: 0,
+                }),
+            };
+            payload
+        };
+
+        payload
+    }
+
+    /// Returns the request's timings, once its response head has been received.
+    pub(crate) fn finish(self) -> Timings {
+        let mut timings = self.recorder.timings.get();
+        timings.ttfb = self.started.elapsed();
+        timings.total = self.recorder.started.elapsed();
+        timings
+    }
+}
+
+/// Debug formatter of headers that hides the values of redacted headers.
+#[cfg(feature = "tracing")]
+struct Redacted<'a>(&'a HeaderMap, &'a HashSet<HeaderName>);
+
+#[cfg(feature = "tracing")]
+impl fmt::Debug for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut map = f.debug_map();
+
+        for (name, value) in self.0 {
+            if self.1.contains(name) {
+                map.entry(name, &"[redacted]");
+            } else {
+                map.entry(name, value);
+            }
+        }
+
+        map.finish()
+    }
+}
+
+/// Response payload that keeps the `body` span open until the body has been read.
+#[cfg(feature = "tracing")]
+struct TracedBody {
+    payload: Payload,
+    span: Option<tracing::Span>,
+    bytes: u64,
+}
+
+#[cfg(feature = "tracing")]
+impl Stream for TracedBody {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let res = Pin::new(&mut this.payload).poll_next(cx);
+
+        match res {
+            Poll::Ready(Some(Ok(ref chunk))) => this.bytes += chunk.len() as u64,
+
+            Poll::Ready(None | Some(Err(_))) => {
+                if let Some(span) = this.span.take() {
+                    span.record("bytes", this.bytes);
+                }
+            }
+
+            Poll::Pending => {}
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases_are_recorded_in_scope() {
+        let recorder = Recorder::new(&RequestHead::default(), &TraceConfig::default());
+
+        // outside of a scope, nothing is recorded
+        PhaseTimer::start(Phase::Tls).finish();
+
+        recorder.scope(|| {
+            let connect = PhaseTimer::start(Phase::Connect);
+            PhaseTimer::start(Phase::Dns).finish();
+            connect.finish();
+        });
+
+        let timings = recorder.start_request().finish();
+        assert!(timings.dns().is_some());
+        assert!(timings.connect().is_some());
+        assert!(timings.tls().is_none());
+        assert!(!timings.connection_reused());
+        assert!(timings.total() >= timings.ttfb());
+
+        assert!(CURRENT.with(|current| current.borrow().is_none()));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn redacted_headers() {
+        use actix_http::header::{HeaderValue, ACCEPT};
+
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        headers.insert(ACCEPT, HeaderValue::from_static("text/csv"));
+
+        let config = TraceConfig::default();
+        let formatted = format!("{:?}", Redacted(&headers, &config.redacted_headers));
+        assert!(formatted.contains("\"accept\": \"text/csv\""));
+        assert!(formatted.contains("\"authorization\": \"[redacted]\""));
+        assert!(!formatted.contains("secret"));
+
+        let mut config = TraceConfig::default();
+        config.redacted_headers([ACCEPT]);
+        let formatted = format!("{:?}", Redacted(&headers, &config.redacted_headers));
+        assert!(formatted.contains("Bearer secret"));
+        assert!(!formatted.contains("text/csv"));
+    }
+}
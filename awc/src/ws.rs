@@ -32,7 +32,6 @@ use actix_codec::Framed;
 pub use actix_http::ws::{CloseCode, CloseReason, Codec, Frame, Message};
 use actix_http::{ws, Payload, RequestHead};
 use actix_rt::time::timeout;
-use actix_service::Service as _;
 use base64::prelude::*;
 
 #[cfg(feature = "cookies")]
@@ -340,7 +339,7 @@ impl WebsocketsRequest {
 
         let req = ConnectRequest::Tunnel(head, self.addr);
 
-        let fut = self.config.connector.call(req);
+        let fut = self.config.connect(req);
 
         // set request timeout
         let res = if let Some(to) = self.config.timeout {
@@ -351,7 +350,7 @@ impl WebsocketsRequest {
             fut.await?
         };
 
-        let (head, framed) = res.into_tunnel_response();
+        let (head, framed) = res.try_into_tunnel_response()?;
 
         // verify response
         if head.status != StatusCode::SWITCHING_PROTOCOLS {
@@ -14,13 +14,23 @@ use actix_http::{HttpService, StatusCode};
 use actix_http_test::test_server;
 use actix_service::{fn_service, map_config, ServiceFactoryExt as _};
 use actix_utils::future::ok;
-use actix_web::{dev::AppConfig, http::header, web, App, Error, HttpRequest, HttpResponse};
-use awc::error::{JsonPayloadError, PayloadError, SendRequestError};
+use actix_web::{
+    dev::AppConfig,
+    http::{header, ClientDisconnect},
+    web, App, Error, HttpRequest, HttpResponse,
+};
+use awc::{
+    error::{
+        ConnectError, JsonPayloadError, PayloadError, SaveError, SendRequestError, UploadError,
+    },
+    DownloadToken, UploadToken,
+};
 use base64::prelude::*;
 use bytes::Bytes;
 use cookie::Cookie;
-use futures_util::stream;
+use futures_util::{stream, StreamExt as _};
 use rand::Rng;
+use sha2::{Digest as _, Sha256};
 
 mod utils;
 
@@ -585,6 +595,130 @@ async fn client_brotli_encoding_large_random() {
     assert_eq!(bytes, data);
 }
 
+#[cfg(feature = "compress-zstd")]
+#[actix_rt::test]
+async fn client_zstd_encoding() {
+    let srv = actix_test::start(|| {
+        App::new().service(web::resource("/").route(web::to(
+            |req: HttpRequest, data: Bytes| async move {
+                // zstd is advertised by default
+                let accept_encoding = req
+                    .headers()
+                    .get(header::ACCEPT_ENCODING)
+                    .unwrap()
+                    .to_str()
+                    .unwrap();
+                assert!(accept_encoding
+                    .split(", ")
+                    .any(|encoding| encoding == "zstd"));
+
+                HttpResponse::Ok()
+                    .insert_header(("content-encoding", "zstd"))
+                    .body(utils::zstd::encode(data))
+            },
+        )))
+    });
+
+    // client request
+    let mut response = srv.post("/").send_body(STR).await.unwrap();
+    assert!(response.status().is_success());
+
+    // read response
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+}
+
+#[cfg(feature = "compress-zstd")]
+#[actix_rt::test]
+async fn client_zstd_encoding_large_random() {
+    let data = rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(70_000)
+        .map(char::from)
+        .collect::<String>();
+
+    let srv = actix_test::start(|| {
+        App::new().service(web::resource("/").route(web::to(|data: Bytes| async {
+            HttpResponse::Ok()
+                .insert_header(header::ContentEncoding::Zstd)
+                .body(utils::zstd::encode(data))
+        })))
+    });
+
+    // client request
+    let mut response = srv.post("/").send_body(data.clone()).await.unwrap();
+    assert!(response.status().is_success());
+
+    // read response
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, data);
+}
+
+#[actix_rt::test]
+async fn max_response_body_size() {
+    let srv = actix_test::start(|| {
+        App::new()
+            .route("/", web::to(|| async { HttpResponse::Ok().body(STR) }))
+            .route(
+                "/json",
+                web::to(|| async { HttpResponse::Ok().json([STR, STR]) }),
+            )
+    });
+
+    let client = awc::Client::builder()
+        .max_response_body_size(STR.len() - 1)
+        .finish();
+
+    // per-call limits can not raise the client's limit
+    let mut res = client.get(srv.url("/")).send().await.unwrap();
+    assert!(matches!(
+        res.body().limit(STR.len() * 2).await,
+        Err(PayloadError::Overflow)
+    ));
+
+    let mut res = client.get(srv.url("/json")).send().await.unwrap();
+    assert!(matches!(
+        res.json::<Vec<String>>().await,
+        Err(JsonPayloadError::Payload(PayloadError::Overflow))
+    ));
+
+    // streamed bodies fail once the limit is exceeded
+    let res = client.get(srv.url("/")).send().await.unwrap();
+    let chunks = res.collect::<Vec<_>>().await;
+    assert!(matches!(chunks.last(), Some(Err(PayloadError::Overflow))));
+
+    // child clients can change the limit
+    let child = client.child().max_response_body_size(STR.len()).finish();
+    let mut res = child.get(srv.url("/")).send().await.unwrap();
+    assert_eq!(res.body().await.unwrap(), STR);
+}
+
+#[cfg(feature = "compress-gzip")]
+#[actix_rt::test]
+async fn max_response_body_size_decompressed() {
+    let srv = actix_test::start(|| {
+        App::new().default_service(web::to(|| async {
+            HttpResponse::Ok()
+                .insert_header(header::ContentEncoding::Gzip)
+                .body(utils::gzip::encode(STR.repeat(100)))
+        }))
+    });
+
+    let limit = 10 * STR.len();
+    assert!(utils::gzip::encode(STR.repeat(100)).len() < limit);
+
+    let client = awc::Client::builder()
+        .max_response_body_size(limit)
+        .finish();
+
+    // the limit applies to the decompressed body
+    let mut res = client.get(srv.url("/")).send().await.unwrap();
+    assert!(matches!(
+        res.body().limit(STR.len() * 1000).await,
+        Err(PayloadError::Overflow)
+    ));
+}
+
 #[actix_rt::test]
 async fn client_deflate_encoding() {
     let srv = actix_test::start(|| {
@@ -850,3 +984,723 @@ async fn local_address() {
 
     assert_eq!(res.status(), 200);
 }
+
+#[actix_rt::test]
+async fn save_to_resumes_download() {
+    async fn flaky(req: HttpRequest, honor_range: web::Data<bool>) -> HttpResponse {
+        let body = STR.as_bytes();
+
+        match req.headers().get(header::RANGE) {
+            Some(range) if **honor_range => {
+                assert_eq!(req.headers().get(header::IF_RANGE).unwrap(), "\"v1\"");
+
+                let range = range.to_str().unwrap();
+                let start = range["bytes=".len()..range.len() - 1].parse().unwrap();
+
+                HttpResponse::PartialContent()
+                    .insert_header((
+                        header::CONTENT_RANGE,
+                        format!("bytes {start}-{}/{}", body.len() - 1, body.len()),
+                    ))
+                    .body(&body[start..])
+            }
+
+            _ => {
+                // give the first half time to be flushed before the connection is dropped
+                let chunks =
+                    stream::once(async { Ok(Bytes::from_static(&body[..body.len() / 2])) }).chain(
+                        stream::once(async {
+                            actix_rt::time::sleep(Duration::from_millis(50)).await;
+                            Err(std::io::Error::other("connection dropped"))
+                        }),
+                    );
+
+                HttpResponse::Ok()
+                    .insert_header((header::ETAG, "\"v1\""))
+                    .no_chunking(body.len() as u64)
+                    .streaming(chunks)
+            }
+        }
+    }
+
+    let srv = actix_test::start(|| {
+        App::new()
+            .service(
+                web::resource("/range")
+                    .app_data(web::Data::new(true))
+                    .to(flaky),
+            )
+            .service(
+                web::resource("/no-range")
+                    .app_data(web::Data::new(false))
+                    .to(flaky),
+            )
+    });
+
+    let req = srv.get("/range").freeze().unwrap();
+    let mut res = req.send().await.unwrap();
+    let mut buf = Vec::new();
+    let written = res.save_to(&mut buf).resume(&req).await.unwrap();
+    assert_eq!(written, STR.len() as u64);
+    assert_eq!(buf, STR.as_bytes());
+
+    // not resumed unless asked to
+    let mut res = req.send().await.unwrap();
+    let mut buf = Vec::new();
+    let err = res.save_to(&mut buf).await.unwrap_err();
+    assert!(matches!(
+        err,
+        SaveError::Payload(PayloadError::Incomplete(_))
+    ));
+    assert_eq!(buf.len(), STR.len() / 2);
+
+    let req = srv.get("/no-range").freeze().unwrap();
+    let mut res = req.send().await.unwrap();
+    let mut buf = Vec::new();
+    let err = res.save_to(&mut buf).resume(&req).await.unwrap_err();
+    assert!(matches!(err, SaveError::ResumeRejected(StatusCode::OK)));
+}
+
+#[actix_rt::test]
+async fn resume_tokens() {
+    async fn download(req: HttpRequest) -> HttpResponse {
+        let body = STR.as_bytes();
+
+        let if_range = req.headers().get(header::IF_RANGE);
+        match req.headers().get(header::RANGE) {
+            Some(range) if if_range.is_some_and(|etag| etag == "\"v1\"") => {
+                let range = range.to_str().unwrap();
+                let start = range["bytes=".len()..range.len() - 1].parse().unwrap();
+
+                HttpResponse::PartialContent()
+                    .insert_header((
+                        header::CONTENT_RANGE,
+                        format!("bytes {start}-{}/{}", body.len() - 1, body.len()),
+                    ))
+                    .body(&body[start..])
+            }
+
+            _ => HttpResponse::Ok()
+                .insert_header((header::ETAG, "\"v1\""))
+                .body(body),
+        }
+    }
+
+    let chunks = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+    let srv = actix_test::start({
+        let chunks = Arc::clone(&chunks);
+
+        move || {
+            let chunks = Arc::clone(&chunks);
+
+            App::new()
+                .route("/download", web::get().to(download))
+                .route(
+                    "/upload",
+                    web::put().to(move |req: HttpRequest, body: Bytes| {
+                        let range = req.headers().get(header::CONTENT_RANGE).unwrap();
+                        let range = range.to_str().unwrap().to_owned();
+                        chunks.lock().unwrap().push((range, body));
+                        async { HttpResponse::Ok().finish() }
+                    }),
+                )
+        }
+    });
+
+    let dir = std::env::temp_dir();
+    let download_path = dir.join(format!("awc-resume-download-{}", std::process::id()));
+    let upload_path = dir.join(format!("awc-resume-upload-{}", std::process::id()));
+
+    // a previous process wrote part of the body before exiting
+    let req = srv.get("/download").freeze().unwrap();
+    let res = req.send().await.unwrap();
+    let token = DownloadToken::new(req.get_uri(), &res).unwrap();
+    assert_eq!(token.total_len(), Some(STR.len() as u64));
+    std::fs::write(&download_path, &STR[..500]).unwrap();
+
+    let token: DownloadToken =
+        serde_json::from_slice(&serde_json::to_vec(&token).unwrap()).unwrap();
+    let client = awc::Client::default();
+    let req = client.get(token.uri()).freeze().unwrap();
+    let written = token.resume_to_file(&req, &download_path).await.unwrap();
+    assert_eq!(written, STR.len() as u64 - 500);
+    assert_eq!(std::fs::read(&download_path).unwrap(), STR.as_bytes());
+
+    let mut buf = Vec::new();
+    token.resume_to(&req, 1000, &mut buf).await.unwrap();
+    assert_eq!(buf, &STR.as_bytes()[1000..]);
+
+    // the resource has changed since the token was created
+    let stale = serde_json::to_string(&token).unwrap().replace("v1", "v0");
+    let stale: DownloadToken = serde_json::from_str(&stale).unwrap();
+    let err = stale.resume_to(&req, 1000, Vec::new()).await.unwrap_err();
+    assert!(matches!(err, SaveError::ResumeRejected(StatusCode::OK)));
+
+    // a previous process sent the first chunk of the upload before exiting
+    std::fs::write(&upload_path, STR).unwrap();
+    let uri = srv.url("/upload").parse().unwrap();
+    let mut token = UploadToken::new(&uri, &upload_path, 500).unwrap();
+    let req = client.put(token.uri()).freeze().unwrap();
+    token.send_chunk(&req).await.unwrap();
+    assert_eq!(token.offset(), 500);
+
+    let mut token: UploadToken =
+        serde_json::from_slice(&serde_json::to_vec(&token).unwrap()).unwrap();
+
+    let err = token
+        .send_chunk(&srv.put("/missing").freeze().unwrap())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, UploadError::Rejected(StatusCode::NOT_FOUND)));
+    assert_eq!(token.offset(), 500);
+
+    while !token.is_complete() {
+        token.send_chunk(&req).await.unwrap();
+    }
+
+    let chunks = chunks.lock().unwrap();
+    let ranges = chunks
+        .iter()
+        .map(|(range, _)| range.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ranges,
+        [
+            "bytes 0-499/1200",
+            "bytes 500-999/1200",
+            "bytes 1000-1199/1200"
+        ]
+    );
+    assert_eq!(
+        chunks.iter().map(|(_, body)| body.len()).sum::<usize>(),
+        STR.len()
+    );
+
+    std::fs::remove_file(&download_path).unwrap();
+    std::fs::remove_file(&upload_path).unwrap();
+}
+
+#[actix_rt::test]
+async fn send_file() {
+    let srv = actix_test::start(|| {
+        App::new().default_service(web::to(|req: HttpRequest, body: Bytes| async move {
+            let content_type = req.headers().get(header::CONTENT_TYPE).unwrap().clone();
+            let len = req.headers().get(header::CONTENT_LENGTH).unwrap().clone();
+
+            HttpResponse::Ok()
+                .insert_header(("x-content-type", content_type))
+                .insert_header(("x-content-length", len))
+                .body(body)
+        }))
+    });
+
+    let path = std::env::temp_dir().join(format!("awc-send-file-{}.txt", std::process::id()));
+    std::fs::write(&path, STR).unwrap();
+
+    let mut res = srv.put("/").send_file(&path, 12..24).await.unwrap();
+    assert_eq!(res.headers().get("x-content-type").unwrap(), "text/plain");
+    assert_eq!(res.headers().get("x-content-length").unwrap(), "12");
+    assert_eq!(res.body().await.unwrap(), S);
+
+    let mut res = srv
+        .put("/")
+        .content_type("application/x-custom")
+        .send_file(&path, ..)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.headers().get("x-content-type").unwrap(),
+        "application/x-custom"
+    );
+    assert_eq!(res.body().await.unwrap(), STR);
+
+    let err = srv
+        .put("/")
+        .send_file(&path, ..=STR.len() as u64)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, SendRequestError::Custom(..)));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[actix_rt::test]
+async fn timings() {
+    let srv = actix_test::start(|| {
+        App::new()
+            .service(web::resource("/").route(web::to(|| async { HttpResponse::Ok().body(STR) })))
+    });
+
+    // the test server's own client does not keep connections alive
+    let client = awc::Client::default();
+
+    let mut res = client.get(srv.url("/")).send().await.unwrap();
+    let timings = res.timings().unwrap();
+    assert!(!timings.connection_reused());
+    assert!(timings.connect().is_some());
+    assert!(timings.tls().is_none());
+    assert!(timings.total() >= timings.ttfb());
+    assert_eq!(res.body().await.unwrap(), STR);
+
+    // the second request is sent on the now idle connection
+    let res = client.get(srv.url("/")).send().await.unwrap();
+    let timings = res.timings().unwrap();
+    assert!(timings.connection_reused());
+    assert!(timings.dns().is_none());
+    assert!(timings.connect().is_none());
+}
+
+#[actix_rt::test]
+async fn doh_resolver() {
+    let srv = actix_test::start(|| {
+        App::new()
+            .route(
+                "/dns-query",
+                web::post().to(|req: HttpRequest, query: Bytes| async move {
+                    assert_eq!(
+                        req.headers().get(header::CONTENT_TYPE).unwrap(),
+                        "application/dns-message"
+                    );
+
+                    // echo the question; answer A queries with the loopback address
+                    let is_a = query[query.len() - 3] == 1;
+                    let mut msg = vec![0, 0, 0x81, 0x80, 0, 1, 0, is_a as u8, 0, 0, 0, 0];
+                    msg.extend_from_slice(&query[12..]);
+                    if is_a {
+                        msg.extend_from_slice(b"\xc0\x0c\x00\x01\x00\x01\x00\x00\x00\x3c\x00\x04");
+                        msg.extend_from_slice(&[127, 0, 0, 1]);
+                    }
+
+                    HttpResponse::Ok()
+                        .content_type("application/dns-message")
+                        .body(msg)
+                }),
+            )
+            .route("/", web::to(|| async { HttpResponse::Ok().body(STR) }))
+    });
+
+    let resolver = awc::DohResolver::new(srv.url("/dns-query").parse().unwrap());
+    let client = awc::Client::builder()
+        .connector(awc::Connector::new().resolver(resolver))
+        .finish();
+
+    let url = format!("http://doh-test.invalid:{}/", srv.addr().port());
+    let mut res = client.get(url).send().await.unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.body().await.unwrap(), STR);
+}
+
+#[actix_rt::test]
+async fn http2_prior_knowledge() {
+    let srv = test_server(|| {
+        HttpService::new(map_config(
+            App::new().default_service(web::to(|req: HttpRequest| async move {
+                HttpResponse::Ok().body(format!("{:?}", req.version()))
+            })),
+            |_| AppConfig::default(),
+        ))
+        .tcp_auto_h2c()
+    })
+    .await;
+
+    let client = awc::Client::builder().http2_prior_knowledge().finish();
+
+    let mut res = client.get(srv.url("/")).send().await.unwrap();
+    assert_eq!(res.body().await.unwrap(), "HTTP/2.0");
+
+    let req = client
+        .get(srv.url("/"))
+        .version(awc::http::Version::HTTP_11);
+    let mut res = req.send().await.unwrap();
+    assert_eq!(res.body().await.unwrap(), "HTTP/1.1");
+
+    let req = awc::Client::new()
+        .get(srv.url("/"))
+        .version(awc::http::Version::HTTP_2);
+    let mut res = req.send().await.unwrap();
+    assert_eq!(res.body().await.unwrap(), "HTTP/2.0");
+}
+
+#[actix_rt::test]
+async fn happy_eyeballs() {
+    /// Resolves every host to an unreachable IPv6 address and the loopback address.
+    struct Resolver(u16);
+
+    impl awc::Resolve for Resolver {
+        fn lookup<'a>(
+            &'a self,
+            _host: &'a str,
+            _port: u16,
+        ) -> futures_util::future::LocalBoxFuture<
+            'a,
+            Result<Vec<std::net::SocketAddr>, Box<dyn std::error::Error>>,
+        > {
+            let addrs = vec![
+                (IpAddr::from([0x100, 0, 0, 0, 0, 0, 0, 1]), self.0).into(),
+                (IpAddr::V4(Ipv4Addr::LOCALHOST), self.0).into(),
+            ];
+
+            Box::pin(async move { Ok(addrs) })
+        }
+    }
+
+    let srv = actix_test::start(|| {
+        App::new().service(web::resource("/").route(web::to(HttpResponse::Ok)))
+    });
+
+    let connector = awc::Connector::new()
+        .resolver(Resolver(srv.addr().port()))
+        .connection_attempt_delay(Duration::from_millis(10))
+        .timeout(Duration::from_secs(1));
+    let client = awc::Client::builder().connector(connector).finish();
+
+    let url = format!("http://dual-stack.invalid:{}/", srv.addr().port());
+    let res = client.get(url).send().await.unwrap();
+    assert!(res.status().is_success());
+}
+
+#[cfg(unix)]
+#[actix_rt::test]
+async fn unix_socket() {
+    let path = std::env::temp_dir().join(format!("awc-test-{}.sock", rand::random::<u64>()));
+
+    let srv = actix_web::HttpServer::new(|| {
+        App::new().default_service(web::to(|req: HttpRequest| async move {
+            let host = req.headers().get(header::HOST).unwrap();
+            HttpResponse::Ok().body(format!("{} {}", host.to_str().unwrap(), req.path()))
+        }))
+    })
+    .workers(1)
+    .disable_signals()
+    .bind_uds(&path)
+    .unwrap()
+    .run();
+    let handle = srv.handle();
+    actix_rt::spawn(srv);
+
+    let client = awc::Client::unix(&path);
+    let mut res = client.get("http://docker/version").send().await.unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.body().await.unwrap(), "docker /version");
+
+    handle.stop(false).await;
+    let _ = std::fs::remove_file(path);
+}
+
+#[actix_rt::test]
+async fn proxy_forward() {
+    let proxy = actix_test::start(|| {
+        App::new().default_service(web::to(|req: HttpRequest| async move {
+            let auth = req.headers().get(header::PROXY_AUTHORIZATION).unwrap();
+            HttpResponse::Ok().body(format!("{} {}", req.uri(), auth.to_str().unwrap()))
+        }))
+    });
+
+    let direct = actix_test::start(|| {
+        App::new().route("/", web::to(|| async { HttpResponse::Ok().body("direct") }))
+    });
+
+    let proxy_uri = format!("http://user:pass@{}", proxy.addr());
+    let client = awc::Client::builder()
+        .proxy(awc::Proxy::new(proxy_uri.parse().unwrap()).no_proxy("localhost,127.0.0.1"))
+        .finish();
+
+    // proxied targets are not resolved by the client
+    let mut res = client
+        .get("http://proxied.invalid/path?q=1")
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(
+        res.body().await.unwrap(),
+        format!(
+            "http://proxied.invalid/path?q=1 Basic {}",
+            BASE64_STANDARD.encode("user:pass")
+        )
+    );
+
+    let mut res = client.get(direct.url("/")).send().await.unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.body().await.unwrap(), "direct");
+}
+
+#[actix_rt::test]
+async fn egress_policy() {
+    let srv = actix_test::start(|| {
+        App::new().route("/", web::to(HttpResponse::Ok)).route(
+            "/redirect",
+            web::to(|| async {
+                HttpResponse::Found()
+                    .insert_header((header::LOCATION, "http://10.0.0.1/"))
+                    .finish()
+            }),
+        )
+    });
+    let port = srv.addr().port();
+
+    let denied = |res: Result<_, SendRequestError>| {
+        matches!(res, Err(SendRequestError::Connect(ConnectError::Denied(_))))
+    };
+
+    // loopback addresses are not allowed by default
+    let client = awc::Client::builder()
+        .egress_policy(awc::EgressPolicy::new())
+        .finish();
+    assert!(denied(client.get(srv.url("/")).send().await));
+
+    // nor are the addresses that host names resolve to
+    let url = format!("http://localhost:{port}/");
+    assert!(denied(client.get(&url).send().await));
+
+    let client = awc::Client::builder()
+        .egress_policy(awc::EgressPolicy::new().allow_networks("127.0.0.0/8, ::1"))
+        .finish();
+    let res = client.get(&url).send().await.unwrap();
+    assert!(res.status().is_success());
+
+    // redirects are checked too
+    assert!(denied(client.get(srv.url("/redirect")).send().await));
+
+    let client = awc::Client::builder()
+        .egress_policy(
+            awc::EgressPolicy::new()
+                .allow_networks("127.0.0.0/8, ::1")
+                .ports("443"),
+        )
+        .finish();
+    assert!(denied(client.get(&url).send().await));
+}
+
+#[actix_rt::test]
+async fn child_clients() {
+    let srv = actix_test::start(|| {
+        App::new()
+            .route(
+                "/login/{tenant}",
+                web::to(|tenant: web::Path<String>| async move {
+                    HttpResponse::Ok()
+                        .cookie(
+                            Cookie::build("session", tenant.into_inner())
+                                .path("/")
+                                .finish(),
+                        )
+                        .finish()
+                }),
+            )
+            .route(
+                "/whoami",
+                web::to(|req: HttpRequest| async move {
+                    let header = |name: header::HeaderName| {
+                        req.headers()
+                            .get(name)
+                            .map_or("", |value| value.to_str().unwrap())
+                            .to_owned()
+                    };
+
+                    HttpResponse::Ok().body(format!(
+                        "{} | {}",
+                        header(header::AUTHORIZATION),
+                        header(header::COOKIE)
+                    ))
+                }),
+            )
+    });
+
+    let client = awc::Client::new();
+    let store_a = awc::middleware::CookieStore::new();
+
+    let tenant_a = client
+        .child()
+        .bearer_auth("a")
+        .cookie_store(store_a.clone())
+        .finish();
+    let tenant_b = client
+        .child()
+        .bearer_auth("b")
+        .cookie_store(awc::middleware::CookieStore::new())
+        .finish();
+
+    tenant_a.get(srv.url("/login/a")).send().await.unwrap();
+    tenant_b.get(srv.url("/login/b")).send().await.unwrap();
+    assert_eq!(store_a.len(), 1);
+
+    let mut res = tenant_a
+        .get(srv.url("/whoami"))
+        .cookie(Cookie::new("theme", "dark"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        res.body().await.unwrap(),
+        "Bearer a | theme=dark; session=a"
+    );
+
+    let mut res = tenant_b.get(srv.url("/whoami")).send().await.unwrap();
+    assert_eq!(res.body().await.unwrap(), "Bearer b | session=b");
+
+    // the parent client has neither credentials nor cookies
+    let mut res = client.get(srv.url("/whoami")).send().await.unwrap();
+    assert_eq!(res.body().await.unwrap(), " | ");
+}
+
+#[actix_rt::test]
+async fn cancel_on_client_disconnect() {
+    let upstream = actix_test::start(|| {
+        App::new().default_service(web::to(|| async {
+            actix_rt::time::sleep(Duration::from_secs(60)).await;
+            HttpResponse::Ok().finish()
+        }))
+    });
+
+    let upstream_url = upstream.url("/search");
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    let srv = actix_test::start(move || {
+        let upstream_url = upstream_url.clone();
+        let tx = tx.clone();
+
+        App::new().default_service(web::to(move |disconnect: ClientDisconnect| {
+            let upstream_url = upstream_url.clone();
+            let tx = tx.clone();
+
+            async move {
+                let res = awc::Client::new()
+                    .get(upstream_url)
+                    .cancel_on(disconnect)
+                    .send()
+                    .await;
+
+                tx.send(matches!(res, Err(SendRequestError::Cancelled)))
+                    .unwrap();
+                HttpResponse::Ok().finish()
+            }
+        }))
+    });
+
+    let mut stream = std::net::TcpStream::connect(srv.addr()).unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+
+    // let the upstream request start before the inbound client goes away
+    actix_rt::time::sleep(Duration::from_millis(200)).await;
+    drop(stream);
+
+    let cancelled = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(cancelled);
+
+    // requests made for clients that are still connected are not cancelled
+    let res = awc::Client::new()
+        .get(upstream.url("/"))
+        .cancel_on(ClientDisconnect::never())
+        .timeout(Duration::from_millis(100))
+        .send()
+        .await;
+    assert!(matches!(res, Err(SendRequestError::Timeout)));
+}
+
+#[actix_rt::test]
+async fn digest_auth() {
+    fn verify(req: &HttpRequest) -> bool {
+        let Some(auth) = req.headers().get(header::AUTHORIZATION) else {
+            return false;
+        };
+
+        let params = auth
+            .to_str()
+            .unwrap()
+            .strip_prefix("Digest ")
+            .unwrap()
+            .split(", ")
+            .filter_map(|param| param.split_once('='))
+            .map(|(name, value)| (name, value.trim_matches('"')))
+            .collect::<HashMap<_, _>>();
+
+        let hash = |data: String| {
+            Sha256::digest(data)
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<Vec<_>>()
+                .concat()
+        };
+
+        let ha1 = hash(format!("{}:records:Circle of Life", params["username"]));
+        let ha2 = hash(format!("{}:{}", req.method(), params["uri"]));
+        let response = hash(format!(
+            "{ha1}:{}:{}:{}:auth:{ha2}",
+            params["nonce"], params["nc"], params["cnonce"]
+        ));
+
+        params["username"] == "Mufasa" && params["opaque"] == "o" && params["response"] == response
+    }
+
+    let hits = Arc::new(AtomicUsize::new(0));
+
+    let srv = actix_test::start({
+        let hits = Arc::clone(&hits);
+
+        move || {
+            let hits = Arc::clone(&hits);
+
+            App::new().default_service(web::to(move |req: HttpRequest, body: Bytes| {
+                hits.fetch_add(1, Ordering::SeqCst);
+                let authorized = verify(&req);
+
+                async move {
+                    if authorized {
+                        return HttpResponse::Ok().body(body);
+                    }
+
+                    HttpResponse::Unauthorized()
+                        .insert_header((
+                            header::WWW_AUTHENTICATE,
+                            concat!(
+                                r#"Digest realm="records", qop="auth", algorithm=SHA-256, "#,
+                                r#"nonce="n1", opaque="o""#,
+                            ),
+                        ))
+                        .finish()
+                }
+            }))
+        }
+    });
+
+    let client = awc::Client::builder()
+        .digest_auth("Mufasa", Some("Circle of Life"))
+        .finish();
+
+    // the challenge is answered by sending the request again, with its body
+    let mut res = client
+        .post(srv.url("/records"))
+        .send_body("chart")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.body().await.unwrap(), "chart");
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+    // later requests answer the challenge up front
+    let res = client.get(srv.url("/records")).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+
+    let res = awc::Client::new()
+        .get(srv.url("/records"))
+        .digest_auth("Mufasa", "Circle of Life")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(hits.load(Ordering::SeqCst), 5);
+
+    let res = awc::Client::new()
+        .get(srv.url("/records"))
+        .digest_auth("Mufasa", "wrong")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(hits.load(Ordering::SeqCst), 7);
+}
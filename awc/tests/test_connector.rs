@@ -2,15 +2,29 @@
 
 extern crate tls_openssl as openssl;
 
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
 use actix_http::HttpService;
 use actix_http_test::test_server;
 use actix_service::{map_config, ServiceFactoryExt};
-use actix_web::{dev::AppConfig, http::Version, web, App, HttpResponse};
+use actix_web::{
+    dev::AppConfig,
+    http::{StatusCode, Version},
+    web, App, HttpResponse,
+};
+use awc::error::{ConnectError, SendRequestError};
 use openssl::{
     pkey::PKey,
     ssl::{SslAcceptor, SslConnector, SslMethod, SslVerifyMode},
     x509::X509,
 };
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
 
 fn tls_config() -> SslAcceptor {
     let rcgen::CertifiedKey { cert, key_pair } =
@@ -69,3 +83,128 @@ async fn test_connection_window_size() {
     assert!(response.status().is_success());
     assert_eq!(response.version(), Version::HTTP_2);
 }
+
+/// Starts an HTTP proxy that answers `CONNECT` requests with `status` and counts them.
+async fn start_proxy(status: &'static str) -> (SocketAddr, Arc<AtomicUsize>) {
+    let listener = actix_rt::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap();
+    let addr = listener.local_addr().unwrap();
+    let tunnels = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&tunnels);
+
+    actix_rt::spawn(async move {
+        while let Ok((mut client, _)) = listener.accept().await {
+            counter.fetch_add(1, Ordering::SeqCst);
+
+            actix_rt::spawn(async move {
+                let mut buf = Vec::new();
+                while !buf.ends_with(b"\r\n\r\n") {
+                    let mut byte = [0];
+                    client.read_exact(&mut byte).await.unwrap();
+                    buf.push(byte[0]);
+                }
+
+                let head = String::from_utf8(buf).unwrap();
+                let target = head.strip_prefix("CONNECT ").unwrap();
+                let target = &target[..target.find(' ').unwrap()];
+
+                let res = format!("HTTP/1.1 {status}\r\ncontent-length: 0\r\n\r\n");
+                client.write_all(res.as_bytes()).await.unwrap();
+
+                if status.starts_with("200") {
+                    let mut server = actix_rt::net::TcpStream::connect(target).await.unwrap();
+                    let _ = tokio::io::copy_bidirectional(&mut client, &mut server).await;
+                }
+            });
+        }
+    });
+
+    (addr, tunnels)
+}
+
+#[actix_rt::test]
+async fn test_proxy_tunnel_reuse() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1(map_config(
+                App::new().service(web::resource("/").route(web::to(HttpResponse::Ok))),
+                |_| AppConfig::default(),
+            ))
+            .tcp()
+    })
+    .await;
+
+    let (proxy, tunnels) = start_proxy("200 Connection Established").await;
+
+    let client = awc::Client::builder()
+        .connector(awc::Connector::new().proxy(format!("http://{proxy}").parse().unwrap()))
+        .finish();
+
+    for _ in 0..3 {
+        let response = client.get(srv.url("/")).send().await.unwrap();
+        assert!(response.status().is_success());
+    }
+
+    assert_eq!(tunnels.load(Ordering::SeqCst), 1);
+}
+
+#[actix_rt::test]
+async fn test_proxy_tunnel_h2() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h2(map_config(
+                App::new().service(web::resource("/").route(web::to(HttpResponse::Ok))),
+                |_| AppConfig::default(),
+            ))
+            .openssl(tls_config())
+            .map_err(|_| ())
+    })
+    .await;
+
+    let (proxy, tunnels) = start_proxy("200 Connection Established").await;
+
+    let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.set_verify(SslVerifyMode::NONE);
+    builder.set_alpn_protos(b"\x02h2\x08http/1.1").unwrap();
+
+    let client = awc::Client::builder()
+        .connector(
+            awc::Connector::new()
+                .openssl(builder.build())
+                .proxy(format!("http://{proxy}").parse().unwrap()),
+        )
+        .finish();
+
+    let response = client.get(srv.surl("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.version(), Version::HTTP_2);
+
+    // concurrent requests are multiplexed over the pooled tunnel
+    let (res1, res2) = futures_util::future::join(
+        client.get(srv.surl("/")).send(),
+        client.get(srv.surl("/")).send(),
+    )
+    .await;
+    assert!(res1.unwrap().status().is_success());
+    assert!(res2.unwrap().status().is_success());
+
+    assert_eq!(tunnels.load(Ordering::SeqCst), 1);
+}
+
+#[actix_rt::test]
+async fn test_proxy_tunnel_rejected() {
+    let (proxy, _) = start_proxy("407 Proxy Authentication Required").await;
+
+    let client = awc::Client::builder()
+        .connector(awc::Connector::new().proxy(format!("http://{proxy}").parse().unwrap()))
+        .finish();
+
+    let err = client.get("http://localhost/").send().await.unwrap_err();
+    assert!(matches!(
+        err,
+        SendRequestError::Connect(ConnectError::Tunnel(
+            StatusCode::PROXY_AUTHENTICATION_REQUIRED
+        ))
+    ));
+}
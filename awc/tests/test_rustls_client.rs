@@ -16,6 +16,11 @@ use actix_service::{fn_service, map_config, ServiceFactoryExt};
 use actix_tls::connect::rustls_0_23::webpki_roots_cert_store;
 use actix_utils::future::ok;
 use actix_web::{dev::AppConfig, http::Version, web, App, HttpResponse};
+use awc::{
+    error::{ConnectError, SendRequestError},
+    CertificatePins,
+};
+use base64::prelude::*;
 use rustls::{
     pki_types::{CertificateDer, PrivateKeyDer, ServerName},
     ClientConfig, ServerConfig,
@@ -25,9 +30,11 @@ use rustls_pemfile::{certs, pkcs8_private_keys};
 fn tls_config() -> ServerConfig {
     let rcgen::CertifiedKey { cert, key_pair } =
         rcgen::generate_simple_self_signed(["localhost".to_owned()]).unwrap();
-    let cert_file = cert.pem();
-    let key_file = key_pair.serialize_pem();
 
+    tls_config_from_pem(&cert.pem(), &key_pair.serialize_pem())
+}
+
+fn tls_config_from_pem(cert_file: &str, key_file: &str) -> ServerConfig {
     let cert_file = &mut BufReader::new(cert_file.as_bytes());
     let key_file = &mut BufReader::new(key_file.as_bytes());
 
@@ -144,3 +151,285 @@ async fn test_connection_reuse_h2() {
     // one connection
     assert_eq!(num.load(Ordering::Relaxed), 1);
 }
+
+#[cfg(feature = "tls-key-log")]
+#[actix_rt::test]
+async fn test_tls_key_log() {
+    let key_log = std::env::temp_dir().join(format!("awc-key-log-{}.txt", std::process::id()));
+    std::env::set_var("SSLKEYLOGFILE", &key_log);
+
+    let srv = test_server(move || {
+        HttpService::build()
+            .h1(map_config(
+                App::new().service(web::resource("/").route(web::to(HttpResponse::Ok))),
+                |_| AppConfig::default(),
+            ))
+            .rustls_0_23(tls_config())
+            .map_err(|_| ())
+    })
+    .await;
+
+    let mut config = ClientConfig::builder()
+        .with_root_certificates(webpki_roots_cert_store())
+        .with_no_client_auth();
+
+    // disable TLS verification
+    config
+        .dangerous()
+        .set_certificate_verifier(Arc::new(danger::NoCertificateVerification));
+
+    let client = awc::Client::builder()
+        .connector(
+            awc::Connector::new()
+                .rustls_0_23(Arc::new(config))
+                .tls_key_log(true),
+        )
+        .finish();
+
+    let response = client.get(srv.surl("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+
+    let secrets = std::fs::read_to_string(&key_log).unwrap();
+    assert!(secrets.contains("CLIENT_TRAFFIC_SECRET_0"));
+    std::fs::remove_file(&key_log).unwrap();
+}
+
+/// Accepts any client certificate, but requires clients to present one.
+#[derive(Debug)]
+struct RequireClientCert;
+
+impl rustls::server::danger::ClientCertVerifier for RequireClientCert {
+    fn root_hint_subjects(&self) -> &[rustls::DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _now: rustls::pki_types::UnixTime,
+    ) -> Result<rustls::server::danger::ClientCertVerified, rustls::Error> {
+        Ok(rustls::server::danger::ClientCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        Ok(rustls::client::danger::HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        Ok(rustls::client::danger::HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        rustls::crypto::aws_lc_rs::default_provider()
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+#[actix_rt::test]
+async fn test_client_identity() {
+    let srv = test_server(move || {
+        let config = ServerConfig::builder()
+            .with_client_cert_verifier(Arc::new(RequireClientCert))
+            .with_cert_resolver(tls_config().cert_resolver);
+
+        HttpService::build()
+            .h1(map_config(
+                App::new().service(web::resource("/").route(web::to(HttpResponse::Ok))),
+                |_| AppConfig::default(),
+            ))
+            .rustls_0_23(config)
+            .map_err(|_| ())
+    })
+    .await;
+
+    let client_config = |with_cert: bool| {
+        let builder = ClientConfig::builder().with_root_certificates(webpki_roots_cert_store());
+
+        let mut config = if with_cert {
+            let rcgen::CertifiedKey { cert, key_pair } =
+                rcgen::generate_simple_self_signed(["client".to_owned()]).unwrap();
+            let key = PrivateKeyDer::Pkcs8(key_pair.serialize_der().into());
+
+            builder
+                .with_client_auth_cert(vec![cert.der().clone()], key)
+                .unwrap()
+        } else {
+            builder.with_no_client_auth()
+        };
+
+        // disable TLS verification
+        config
+            .dangerous()
+            .set_certificate_verifier(Arc::new(danger::NoCertificateVerification));
+
+        Arc::new(config)
+    };
+
+    let identity = awc::ClientIdentity::rustls_0_23(client_config(true));
+
+    // connector's own configuration has no client certificate
+    let client = awc::Client::builder()
+        .connector(awc::Connector::new().rustls_0_23(client_config(false)))
+        .finish();
+    assert!(client.get(srv.surl("/")).send().await.is_err());
+
+    // identity of a single request
+    let res = client
+        .get(srv.surl("/"))
+        .client_identity(identity.clone())
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+
+    // identity of a child client
+    let child = client.child().client_identity(identity.clone()).finish();
+    let res = child.get(srv.surl("/")).send().await.unwrap();
+    assert!(res.status().is_success());
+
+    // identity of a host pattern
+    let client = awc::Client::builder()
+        .connector(
+            awc::Connector::new()
+                .rustls_0_23(client_config(false))
+                .client_identity("other.example.com", identity.clone())
+                .client_identity("localhost", identity),
+        )
+        .finish();
+    let res = client.get(srv.surl("/")).send().await.unwrap();
+    assert!(res.status().is_success());
+}
+
+#[actix_rt::test]
+async fn test_certificate_pinning() {
+    use sha2::{Digest as _, Sha256};
+
+    let rcgen::CertifiedKey { cert, key_pair } =
+        rcgen::generate_simple_self_signed(["localhost".to_owned()]).unwrap();
+    let pin = BASE64_STANDARD.encode(Sha256::digest(key_pair.public_key_der()));
+    let other_pin = BASE64_STANDARD.encode(Sha256::digest(b"other key"));
+
+    let (cert_file, key_file) = (cert.pem(), key_pair.serialize_pem());
+
+    let srv = test_server(move || {
+        HttpService::build()
+            .h1(map_config(
+                App::new().service(web::resource("/").route(web::to(HttpResponse::Ok))),
+                |_| AppConfig::default(),
+            ))
+            .rustls_0_23(tls_config_from_pem(&cert_file, &key_file))
+            .map_err(|_| ())
+    })
+    .await;
+
+    let mut config = ClientConfig::builder()
+        .with_root_certificates(webpki_roots_cert_store())
+        .with_no_client_auth();
+
+    // disable TLS verification
+    config
+        .dangerous()
+        .set_certificate_verifier(Arc::new(danger::NoCertificateVerification));
+
+    let config = Arc::new(config);
+
+    let client = |pins: CertificatePins| {
+        awc::Client::builder()
+            .connector(
+                awc::Connector::new()
+                    .rustls_0_23(Arc::clone(&config))
+                    .pin_certificates("localhost", pins),
+            )
+            .finish()
+    };
+
+    // key matches one of the pins
+    let pins = CertificatePins::sha256([&other_pin, &pin]);
+    let res = client(pins).get(srv.surl("/")).send().await.unwrap();
+    assert!(res.status().is_success());
+
+    // key matches none of the pins
+    let pins = CertificatePins::sha256([&other_pin]);
+    let err = client(pins.clone())
+        .get(srv.surl("/"))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        SendRequestError::Connect(ConnectError::CertificatePinMismatch)
+    ));
+
+    // mismatches of report-only pins are logged
+    let res = client(pins.report_only(true))
+        .get(srv.surl("/"))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+}
+
+#[actix_rt::test]
+async fn test_sni_override() {
+    let rcgen::CertifiedKey { cert, key_pair } =
+        rcgen::generate_simple_self_signed(["origin.test".to_owned()]).unwrap();
+
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(cert.der().clone()).unwrap();
+
+    let (cert_file, key_file) = (cert.pem(), key_pair.serialize_pem());
+
+    let srv = test_server(move || {
+        HttpService::build()
+            .h1(map_config(
+                App::new().service(web::resource("/").route(web::to(
+                    |req: actix_web::HttpRequest| async move {
+                        HttpResponse::Ok().body(req.connection_info().host().to_owned())
+                    },
+                ))),
+                |_| AppConfig::default(),
+            ))
+            .rustls_0_23(tls_config_from_pem(&cert_file, &key_file))
+            .map_err(|_| ())
+    })
+    .await;
+
+    let config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let client = awc::Client::builder()
+        .connector(awc::Connector::new().rustls_0_23(Arc::new(config)))
+        .finish();
+
+    let url = format!("https://portal.test:{}/", srv.addr().port());
+
+    // certificate is not valid for the host of the URL
+    let res = client.get(&url).address(srv.addr()).send().await;
+    assert!(res.is_err());
+
+    let mut res = client
+        .get(&url)
+        .address(srv.addr())
+        .sni("origin.test")
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+
+    // host header is still taken from the URL
+    let host = res.body().await.unwrap();
+    assert_eq!(host, format!("portal.test:{}", srv.addr().port()));
+}